@@ -22,16 +22,19 @@ fn main() {
                           "WGL_ARB_create_context",
                           "WGL_ARB_create_context_profile",
                           "WGL_ARB_create_context_robustness",
+                          "WGL_ARB_create_context_no_error",
                           "WGL_ARB_context_flush_control",
                           "WGL_ARB_extensions_string",
                           "WGL_ARB_framebuffer_sRGB",
                           "WGL_ARB_multisample",
+                          "WGL_ARB_pbuffer",
                           "WGL_ARB_pixel_format",
                           "WGL_ARB_pixel_format_float",
                           "WGL_EXT_create_context_es2_profile",
                           "WGL_EXT_extensions_string",
                           "WGL_EXT_framebuffer_sRGB",
                           "WGL_EXT_swap_control",
+                          "WGL_NV_swap_group",
                       ])
             .write_bindings(gl_generator::StructGenerator, &mut file).unwrap();
 
@@ -40,6 +43,9 @@ fn main() {
                           "EGL_KHR_create_context",
                           "EGL_EXT_create_context_robustness",
                           "EGL_KHR_create_context_no_error",
+                          "EGL_KHR_context_flush_control",
+                          "EGL_IMG_context_priority",
+                          "EGL_NV_context_priority_realtime",
                           "EGL_KHR_platform_x11",
                           "EGL_KHR_platform_android",
                           "EGL_KHR_platform_wayland",
@@ -47,8 +53,29 @@ fn main() {
                           "EGL_EXT_platform_base",
                           "EGL_EXT_platform_x11",
                           "EGL_MESA_platform_gbm",
+                          "EGL_MESA_query_driver",
                           "EGL_EXT_platform_wayland",
                           "EGL_EXT_platform_device",
+                          "EGL_EXT_device_base",
+                          "EGL_EXT_device_enumeration",
+                          "EGL_EXT_device_query",
+                          "EGL_EXT_device_drm",
+                          "EGL_EXT_buffer_age",
+                          "EGL_KHR_mutable_render_buffer",
+                          "EGL_NV_coverage_sample",
+                          "EGL_KHR_partial_update",
+                          "EGL_ANDROID_get_frame_timestamps",
+                          "EGL_ANDROID_presentation_time",
+                          "EGL_ANDROID_recordable",
+                          "EGL_EXT_protected_content",
+                          "EGL_EXT_pixel_format_float",
+                          "EGL_KHR_fence_sync",
+                          "EGL_ANDROID_native_fence_sync",
+                          "EGL_KHR_gl_colorspace",
+                          "EGL_EXT_gl_colorspace_display_p3",
+                          "EGL_EXT_gl_colorspace_display_p3_linear",
+                          "EGL_EXT_gl_colorspace_scrgb",
+                          "EGL_EXT_gl_colorspace_scrgb_linear",
                       ])
             .write_bindings(gl_generator::StructGenerator, &mut file).unwrap();
     }
@@ -63,12 +90,19 @@ fn main() {
                           "GLX_ARB_create_context",
                           "GLX_ARB_create_context_profile",
                           "GLX_ARB_create_context_robustness",
+                          "GLX_ARB_create_context_no_error",
                           "GLX_ARB_context_flush_control",
                           "GLX_ARB_fbconfig_float",
                           "GLX_ARB_framebuffer_sRGB",
                           "GLX_EXT_framebuffer_sRGB",
                           "GLX_ARB_multisample",
+                          "GLX_EXT_buffer_age",
                           "GLX_EXT_swap_control",
+                          "GLX_EXT_swap_control_tear",
+                          "GLX_MESA_swap_control",
+                          "GLX_MESA_query_renderer",
+                          "GLX_NV_swap_group",
+                          "GLX_OML_sync_control",
                           "GLX_SGI_swap_control"
                       ])
             .write_bindings(gl_generator::StructGenerator, &mut file).unwrap();
@@ -78,6 +112,9 @@ fn main() {
                           "EGL_KHR_create_context",
                           "EGL_EXT_create_context_robustness",
                           "EGL_KHR_create_context_no_error",
+                          "EGL_KHR_context_flush_control",
+                          "EGL_IMG_context_priority",
+                          "EGL_NV_context_priority_realtime",
                           "EGL_KHR_platform_x11",
                           "EGL_KHR_platform_android",
                           "EGL_KHR_platform_wayland",
@@ -85,8 +122,29 @@ fn main() {
                           "EGL_EXT_platform_base",
                           "EGL_EXT_platform_x11",
                           "EGL_MESA_platform_gbm",
+                          "EGL_MESA_query_driver",
                           "EGL_EXT_platform_wayland",
                           "EGL_EXT_platform_device",
+                          "EGL_EXT_device_base",
+                          "EGL_EXT_device_enumeration",
+                          "EGL_EXT_device_query",
+                          "EGL_EXT_device_drm",
+                          "EGL_EXT_buffer_age",
+                          "EGL_KHR_mutable_render_buffer",
+                          "EGL_NV_coverage_sample",
+                          "EGL_KHR_partial_update",
+                          "EGL_ANDROID_get_frame_timestamps",
+                          "EGL_ANDROID_presentation_time",
+                          "EGL_ANDROID_recordable",
+                          "EGL_EXT_protected_content",
+                          "EGL_EXT_pixel_format_float",
+                          "EGL_KHR_fence_sync",
+                          "EGL_ANDROID_native_fence_sync",
+                          "EGL_KHR_gl_colorspace",
+                          "EGL_EXT_gl_colorspace_display_p3",
+                          "EGL_EXT_gl_colorspace_display_p3_linear",
+                          "EGL_EXT_gl_colorspace_scrgb",
+                          "EGL_EXT_gl_colorspace_scrgb_linear",
                       ])
             .write_bindings(gl_generator::StructGenerator, &mut file).unwrap();
     }
@@ -97,6 +155,9 @@ fn main() {
                           "EGL_KHR_create_context",
                           "EGL_EXT_create_context_robustness",
                           "EGL_KHR_create_context_no_error",
+                          "EGL_KHR_context_flush_control",
+                          "EGL_IMG_context_priority",
+                          "EGL_NV_context_priority_realtime",
                           "EGL_KHR_platform_x11",
                           "EGL_KHR_platform_android",
                           "EGL_KHR_platform_wayland",
@@ -104,8 +165,29 @@ fn main() {
                           "EGL_EXT_platform_base",
                           "EGL_EXT_platform_x11",
                           "EGL_MESA_platform_gbm",
+                          "EGL_MESA_query_driver",
                           "EGL_EXT_platform_wayland",
                           "EGL_EXT_platform_device",
+                          "EGL_EXT_device_base",
+                          "EGL_EXT_device_enumeration",
+                          "EGL_EXT_device_query",
+                          "EGL_EXT_device_drm",
+                          "EGL_EXT_buffer_age",
+                          "EGL_KHR_mutable_render_buffer",
+                          "EGL_NV_coverage_sample",
+                          "EGL_KHR_partial_update",
+                          "EGL_ANDROID_get_frame_timestamps",
+                          "EGL_ANDROID_presentation_time",
+                          "EGL_ANDROID_recordable",
+                          "EGL_EXT_protected_content",
+                          "EGL_EXT_pixel_format_float",
+                          "EGL_KHR_fence_sync",
+                          "EGL_ANDROID_native_fence_sync",
+                          "EGL_KHR_gl_colorspace",
+                          "EGL_EXT_gl_colorspace_display_p3",
+                          "EGL_EXT_gl_colorspace_display_p3_linear",
+                          "EGL_EXT_gl_colorspace_scrgb",
+                          "EGL_EXT_gl_colorspace_scrgb_linear",
                       ])
             .write_bindings(gl_generator::StaticStructGenerator, &mut file).unwrap();
     }
@@ -116,6 +198,9 @@ fn main() {
                           "EGL_KHR_create_context",
                           "EGL_EXT_create_context_robustness",
                           "EGL_KHR_create_context_no_error",
+                          "EGL_KHR_context_flush_control",
+                          "EGL_IMG_context_priority",
+                          "EGL_NV_context_priority_realtime",
                           "EGL_KHR_platform_x11",
                           "EGL_KHR_platform_android",
                           "EGL_KHR_platform_wayland",
@@ -123,8 +208,29 @@ fn main() {
                           "EGL_EXT_platform_base",
                           "EGL_EXT_platform_x11",
                           "EGL_MESA_platform_gbm",
+                          "EGL_MESA_query_driver",
                           "EGL_EXT_platform_wayland",
                           "EGL_EXT_platform_device",
+                          "EGL_EXT_device_base",
+                          "EGL_EXT_device_enumeration",
+                          "EGL_EXT_device_query",
+                          "EGL_EXT_device_drm",
+                          "EGL_EXT_buffer_age",
+                          "EGL_KHR_mutable_render_buffer",
+                          "EGL_NV_coverage_sample",
+                          "EGL_KHR_partial_update",
+                          "EGL_ANDROID_get_frame_timestamps",
+                          "EGL_ANDROID_presentation_time",
+                          "EGL_ANDROID_recordable",
+                          "EGL_EXT_protected_content",
+                          "EGL_EXT_pixel_format_float",
+                          "EGL_KHR_fence_sync",
+                          "EGL_ANDROID_native_fence_sync",
+                          "EGL_KHR_gl_colorspace",
+                          "EGL_EXT_gl_colorspace_display_p3",
+                          "EGL_EXT_gl_colorspace_display_p3_linear",
+                          "EGL_EXT_gl_colorspace_scrgb",
+                          "EGL_EXT_gl_colorspace_scrgb_linear",
                       ])
             .write_bindings(gl_generator::StaticStructGenerator, &mut file).unwrap();
 
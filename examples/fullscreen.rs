@@ -31,9 +31,9 @@ fn main() {
         .with_title("Hello world!")
         .with_fullscreen(Some(monitor));
     let context = glutin::ContextBuilder::new();
-    let gl_window = glutin::GlWindow::new(window, context, &events_loop).unwrap();
+    let gl_window = glutin::WindowedContext::new(window, context, &events_loop).unwrap();
 
-    let _ = unsafe { gl_window.make_current() };
+    let gl_window = unsafe { gl_window.make_current().unwrap() };
 
     let gl = support::load(&gl_window);
 
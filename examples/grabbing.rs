@@ -8,9 +8,9 @@ fn main() {
     let mut events_loop = glutin::EventsLoop::new();
     let window = glutin::WindowBuilder::new().with_title("glutin - Cursor grabbing test");
     let context = glutin::ContextBuilder::new();
-    let gl_window = glutin::GlWindow::new(window, context, &events_loop).unwrap();
+    let gl_window = glutin::WindowedContext::new(window, context, &events_loop).unwrap();
 
-    let _ = unsafe { gl_window.make_current() };
+    let gl_window = unsafe { gl_window.make_current().unwrap() };
 
     let gl = support::load(&gl_window);
     let mut grabbed = false;
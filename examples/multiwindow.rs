@@ -11,8 +11,8 @@ fn main() {
     for _ in 0..3 {
         let window = glutin::WindowBuilder::new();
         let context = glutin::ContextBuilder::new();
-        let gl_window = glutin::GlWindow::new(window, context, &events_loop).unwrap();
-        let _ = unsafe { gl_window.make_current() };
+        let gl_window = glutin::WindowedContext::new(window, context, &events_loop).unwrap();
+        let gl_window = unsafe { gl_window.make_current().unwrap() };
         let gl = support::load(&gl_window);
         let window_id = gl_window.id();
         windows.insert(window_id, (gl_window, gl));
@@ -38,12 +38,14 @@ fn main() {
             _ => (),
         }
 
-        for (i, window) in windows.values().enumerate() {
+        let previous_windows = std::mem::replace(&mut windows, std::collections::HashMap::new());
+        for (i, (window_id, (gl_window, gl))) in previous_windows.into_iter().enumerate() {
             let mut color = [0.0, 0.0, 0.0, 1.0];
             color[i] = 1.0; // Color each of the three windows a different color.
-            let _ = unsafe { window.0.make_current() };
-            window.1.draw_frame(color);
-            let _ = window.0.swap_buffers();
+            let gl_window = unsafe { gl_window.make_current().unwrap() };
+            gl.draw_frame(color);
+            let _ = gl_window.swap_buffers();
+            windows.insert(window_id, (gl_window, gl));
         }
 
         glutin::ControlFlow::Continue
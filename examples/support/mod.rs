@@ -12,7 +12,7 @@ pub struct Gl {
     gl: gl::Gl
 }
 
-pub fn load(gl_window: &glutin::GlWindow) -> Gl {
+pub fn load(gl_window: &glutin::WindowedContext<glutin::PossiblyCurrent>) -> Gl {
     let gl = gl::Gl::load_with(|ptr| gl_window.get_proc_address(ptr) as *const _);
 
     let version = unsafe {
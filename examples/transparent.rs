@@ -11,9 +11,9 @@ fn main() {
         .with_decorations(false)
         .with_transparency(true);
     let context = glutin::ContextBuilder::new();
-    let gl_window = glutin::GlWindow::new(window, context, &events_loop).unwrap();
+    let gl_window = glutin::WindowedContext::new(window, context, &events_loop).unwrap();
 
-    let _ = unsafe { gl_window.make_current() };
+    let gl_window = unsafe { gl_window.make_current().unwrap() };
 
     println!("Pixel format of the window's GL context: {:?}", gl_window.get_pixel_format());
 
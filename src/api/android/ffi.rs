@@ -21,9 +21,17 @@ pub type AAssetManager = raw::c_void;
  */
 pub type ANativeWindow = raw::c_void;
 
+pub const WINDOW_FORMAT_RGBA_8888: libc::int32_t = 1;
+pub const WINDOW_FORMAT_RGBX_8888: libc::int32_t = 2;
+pub const WINDOW_FORMAT_RGB_565: libc::int32_t = 4;
+
 extern {
     pub fn ANativeWindow_getHeight(window: *const ANativeWindow) -> libc::int32_t;
     pub fn ANativeWindow_getWidth(window: *const ANativeWindow) -> libc::int32_t;
+    pub fn ANativeWindow_getFormat(window: *const ANativeWindow) -> libc::int32_t;
+    pub fn ANativeWindow_setBuffersGeometry(window: *const ANativeWindow, width: libc::int32_t,
+                                            height: libc::int32_t, format: libc::int32_t)
+                                            -> libc::int32_t;
 }
 
 /**
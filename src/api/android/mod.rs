@@ -6,31 +6,243 @@ use libc;
 
 use CreationError::{self, OsError};
 
+#[cfg(feature = "winit")]
 use winit;
 
 use Api;
 use ContextError;
+use DeviceInfo;
+use SupportedVersions;
 use GlAttributes;
+use PbufferAttributes;
+use PbufferLimits;
 use PixelFormat;
 use PixelFormatRequirements;
+use RawDisplayHandle;
+use RawPixmapHandle;
+use RawWindowHandle;
+use Rect;
+use PresentMode;
+use MultisampleResolve;
+use NativeConfigId;
+use PresentationTime;
+use RenderBuffer;
+use SwapBehavior;
+use Colorspace;
+use SwapInterval;
 
 use api::egl;
 use api::egl::Context as EglContext;
+pub use api::egl::CurrentState;
 use std::cell::Cell;
 use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "winit")]
 use winit::os::android::EventsLoopExt;
 
 mod ffi;
 
+/// The format of the buffers of an `ANativeWindow`, which must match the native visual of the
+/// EGL config for the window to be shown correctly and without a conversion on some devices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NativeWindowFormat {
+    /// 8 bits per channel, with alpha.
+    Rgba8888,
+    /// 8 bits per channel, without alpha.
+    Rgbx8888,
+    /// 5 bits of red and blue and 6 bits of green, which halves the memory and bandwidth.
+    Rgb565,
+}
+
+impl NativeWindowFormat {
+    /// Returns the `WINDOW_FORMAT_*` of the format, which is also the `EGL_NATIVE_VISUAL_ID` of
+    /// the matching configs.
+    pub(crate) fn native_visual_id(self) -> i32 {
+        match self {
+            NativeWindowFormat::Rgba8888 => ffi::WINDOW_FORMAT_RGBA_8888,
+            NativeWindowFormat::Rgbx8888 => ffi::WINDOW_FORMAT_RGBX_8888,
+            NativeWindowFormat::Rgb565 => ffi::WINDOW_FORMAT_RGB_565,
+        }
+    }
+}
+
+/// Builds an EGL context rendering to `native_window`.
+///
+/// Unless a format was forced with `ContextBuilderExt::with_native_window_format`, a config whose
+/// native visual is the current format of the window is preferred. The buffers of the window are
+/// then given the format of the chosen config.
+unsafe fn new_window_context(native_window: *const ffi::ANativeWindow,
+                             pf_reqs: &PixelFormatRequirements,
+                             gl_attr: GlAttributes<&EglContext>)
+                             -> Result<EglContext, CreationError>
+{
+    let window_format = ffi::ANativeWindow_getFormat(native_window);
+    let prototype = if pf_reqs.native_visual_id.is_none() && window_format > 0 {
+        let matching = PixelFormatRequirements {
+            native_visual_id: Some(window_format),
+            .. pf_reqs.clone()
+        };
+        match EglContext::new(egl::ffi::egl::Egl, &matching, gl_attr.clone(),
+                              egl::NativeDisplay::Android)
+        {
+            Err(ref err) if err.is_no_pixel_format() => {
+                EglContext::new(egl::ffi::egl::Egl, pf_reqs, gl_attr, egl::NativeDisplay::Android)?
+            },
+            result => result?,
+        }
+    } else {
+        EglContext::new(egl::ffi::egl::Egl, pf_reqs, gl_attr, egl::NativeDisplay::Android)?
+    };
+
+    set_buffers_format(native_window, prototype.get_native_visual_id())?;
+    prototype.finish(native_window as *const _)
+}
+
+/// Sets the format of the buffers of `native_window` to `native_visual_id`, keeping the size of
+/// the window.
+unsafe fn set_buffers_format(native_window: *const ffi::ANativeWindow, native_visual_id: i32)
+                             -> Result<(), CreationError>
+{
+    if native_visual_id <= 0 {
+        return Ok(());
+    }
+    if ffi::ANativeWindow_setBuffersGeometry(native_window, 0, 0, native_visual_id) != 0 {
+        return Err(OsError(format!("ANativeWindow_setBuffersGeometry failed")));
+    }
+    Ok(())
+}
+
 struct AndroidContext {
     egl_context: EglContext,
     stopped: Cell<bool>,
 }
 
+/// A display connection provided by the user. Android doesn't need one, so it holds no state.
+pub struct Display;
+
+impl Display {
+    pub unsafe fn from_raw(display_handle: RawDisplayHandle) -> Result<Display, CreationError> {
+        match display_handle {
+            RawDisplayHandle::Android => Ok(Display),
+            _ => Err(CreationError::NotSupported("the display handle is not an Android handle")),
+        }
+    }
+
+    pub fn find_configs(&self, _: &PixelFormatRequirements)
+                        -> Result<Vec<Config>, CreationError>
+    {
+        Err(CreationError::NotSupported("config enumeration is not supported on this platform"))
+    }
+
+    pub fn device_info(&self) -> Result<DeviceInfo, CreationError> {
+        Err(CreationError::NotSupported("device information is not available on this platform"))
+    }
+
+    pub fn probe_versions(&self, _: Api) -> Result<SupportedVersions, CreationError> {
+        Err(CreationError::NotSupported("version probing is not supported on this platform"))
+    }
+}
+
+/// Config enumeration is not supported on this platform, so this type can't be constructed.
+#[derive(Clone)]
+pub enum Config {}
+
+impl Config {
+    #[inline]
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        match *self {}
+    }
+
+    pub unsafe fn create_window_surface(&self, _: RawWindowHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        match *self {}
+    }
+
+    pub fn create_pbuffer_surface(&self, _: &PbufferAttributes) -> Result<Surface, CreationError> {
+        match *self {}
+    }
+
+    pub unsafe fn create_pixmap_surface(&self, _: RawPixmapHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        match *self {}
+    }
+}
+
+/// Surfaces are not supported on this platform, so this type can't be constructed.
+pub enum Surface {}
+
+impl Surface {
+    #[inline]
+    pub fn dimensions(&self) -> Result<(u32, u32), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn buffer_age(&self) -> Result<u32, ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn set_damage_region(&self, _rects: &[Rect]) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn set_swap_behavior(&self, _behavior: SwapBehavior) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn set_render_buffer(&self, _render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn set_multisample_resolve(&self, _resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn resize(&self, _: u32, _: u32) {
+        match *self {}
+    }
+}
+
 pub struct Context(Arc<AndroidContext>);
 
+#[cfg(feature = "winit")]
 struct AndroidSyncEventHandler(Arc<AndroidContext>);
 
+#[cfg(feature = "winit")]
 impl android_glue::SyncEventHandler for AndroidSyncEventHandler {
     fn handle(&mut self, event: &android_glue::Event) {
         match *event {
@@ -47,6 +259,7 @@ impl android_glue::SyncEventHandler for AndroidSyncEventHandler {
 }
 
 impl Context {
+    #[cfg(feature = "winit")]
     pub fn new(
         window_builder: winit::WindowBuilder,
         events_loop: &winit::EventsLoop,
@@ -60,10 +273,9 @@ impl Context {
         if native_window.is_null() {
             return Err(OsError(format!("Android's native window is null")));
         }
-        let egl = egl::ffi::egl::Egl;
-        let native_display = egl::NativeDisplay::Android;
-        let context = try!(EglContext::new(egl, pf_reqs, &gl_attr, native_display)
-            .and_then(|p| p.finish(native_window as *const _)));
+        let context = unsafe {
+            new_window_context(native_window as *const _, pf_reqs, gl_attr)?
+        };
         let ctx = Arc::new(AndroidContext {
             egl_context: context,
             stopped: Cell::new(false),
@@ -86,6 +298,11 @@ impl Context {
                 // Restore the EGL surface and animation loop.
                 unsafe {
                     let native_window = android_glue::get_native_window();
+                    if let Some(NativeConfigId::Egl { native_visual_id: Some(id), .. }) =
+                        ctx.egl_context.get_pixel_format().native_id
+                    {
+                        let _ = set_buffers_format(native_window as *const _, id);
+                    }
                     ctx.egl_context.on_surface_created(native_window as *const _);
                 }
             }
@@ -94,6 +311,87 @@ impl Context {
         Ok((window, context))
     }
 
+    /// Builds a context on a native window that was created by another library.
+    ///
+    /// Unlike contexts built through `new`, the surface is not recreated when the activity is
+    /// suspended and resumed.
+    pub unsafe fn new_from_raw_handles(
+        display_handle: RawDisplayHandle,
+        window_handle: RawWindowHandle,
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Self>,
+    ) -> Result<Self, CreationError>
+    {
+        let native_window = match (display_handle, window_handle) {
+            (RawDisplayHandle::Android, RawWindowHandle::AndroidNdk { a_native_window }) => {
+                a_native_window
+            },
+            _ => return Err(CreationError::NotSupported("raw handles are not Android handles")),
+        };
+        if native_window.is_null() {
+            return Err(OsError(format!("Android's native window is null")));
+        }
+        let gl_attr = gl_attr.clone().map_sharing(|c| &c.0.egl_context);
+        let context = new_window_context(native_window as *const _, pf_reqs, gl_attr)?;
+        Ok(Context(Arc::new(AndroidContext {
+            egl_context: context,
+            stopped: Cell::new(false),
+        })))
+    }
+
+    /// Builds a context on a window that was created by another library, using a display
+    /// provided by the user.
+    #[inline]
+    pub unsafe fn new_with_display(
+        _: &Display,
+        window_handle: RawWindowHandle,
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Self>,
+    ) -> Result<Self, CreationError>
+    {
+        Context::new_from_raw_handles(RawDisplayHandle::Android, window_handle, pf_reqs, gl_attr)
+    }
+
+    #[inline]
+    pub unsafe fn new_with_config(
+        config: &Config,
+        _: RawWindowHandle,
+        _: &GlAttributes<&Self>,
+    ) -> Result<Self, CreationError>
+    {
+        match *config {}
+    }
+
+    /// Destroys this context and builds a new one drawing to the same window, which isn't
+    /// supported on this platform.
+    #[inline]
+    pub unsafe fn recreate(self, _: &GlAttributes<&Self>) -> Result<Self, CreationError> {
+        Err(CreationError::NotSupported("contexts can't be recreated on this platform"))
+    }
+
+    pub unsafe fn create_window_surface(&self, _: RawWindowHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        Err(CreationError::NotSupported("surfaces are not supported on this platform"))
+    }
+
+    pub fn create_pbuffer_surface(&self, _: &PbufferAttributes) -> Result<Surface, CreationError> {
+        Err(CreationError::NotSupported("surfaces are not supported on this platform"))
+    }
+
+    pub unsafe fn create_pixmap_surface(&self, _: RawPixmapHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        Err(CreationError::NotSupported("surfaces are not supported on this platform"))
+    }
+
+    #[inline]
+    pub unsafe fn make_current_surfaces(&self, draw: &Surface, _: &Surface)
+                                        -> Result<(), ContextError>
+    {
+        match *draw {}
+    }
+
     #[inline]
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         if !self.0.stopped.get() {
@@ -102,6 +400,11 @@ impl Context {
         Err(ContextError::ContextLost)
     }
 
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        self.0.egl_context.make_not_current()
+    }
+
     #[inline]
     pub fn resize(&self, _: u32, _: u32) {
     }
@@ -111,6 +414,114 @@ impl Context {
         self.0.egl_context.is_current()
     }
 
+    #[inline]
+    pub fn validate(&self) -> Result<(), ContextError> {
+        if self.0.stopped.get() {
+            return Err(ContextError::ContextLost);
+        }
+        self.0.egl_context.validate()
+    }
+
+    #[inline]
+    pub fn set_swap_interval(&self, interval: SwapInterval) -> Result<(), ContextError> {
+        self.0.egl_context.set_swap_interval(interval)
+    }
+
+    #[inline]
+    pub fn get_swap_interval(&self) -> Result<SwapInterval, ContextError> {
+        self.0.egl_context.get_swap_interval()
+    }
+
+    #[inline]
+    pub fn get_swap_interval_range(&self) -> Result<(u32, u32), ContextError> {
+        self.0.egl_context.get_swap_interval_range()
+    }
+
+    #[inline]
+    pub fn supported_present_modes(&self) -> Result<Vec<PresentMode>, ContextError> {
+        self.0.egl_context.supported_present_modes()
+    }
+
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        self.0.egl_context.swap_behavior()
+    }
+
+    #[inline]
+    pub fn colorspace(&self) -> Result<Colorspace, ContextError> {
+        self.0.egl_context.colorspace()
+    }
+
+    #[inline]
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        self.0.egl_context.render_buffer()
+    }
+
+    #[inline]
+    pub fn set_render_buffer(&self, render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        self.0.egl_context.set_render_buffer(render_buffer)
+    }
+
+    #[inline]
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        self.0.egl_context.multisample_resolve()
+    }
+
+    #[inline]
+    pub fn set_multisample_resolve(&self, resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        self.0.egl_context.set_multisample_resolve(resolve)
+    }
+
+    #[inline]
+    pub fn join_swap_group(&self, _: u32) -> Result<(), ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+
+    #[inline]
+    pub fn bind_swap_barrier(&self, _: u32, _: u32) -> Result<(), ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+
+    #[inline]
+    pub fn swap_buffers_with_frame_id(&self) -> Result<u64, ContextError> {
+        if !self.0.stopped.get() {
+            return self.0.egl_context.swap_buffers_with_frame_id();
+        }
+        Err(ContextError::ContextLost)
+    }
+
+    #[inline]
+    pub fn try_swap_buffers(&self) -> Result<u64, ContextError> {
+        if !self.0.stopped.get() {
+            return self.0.egl_context.try_swap_buffers();
+        }
+        Err(ContextError::ContextLost)
+    }
+
+    #[inline]
+    pub fn set_presentation_time(&self, time: Duration) -> Result<(), ContextError> {
+        self.0.egl_context.set_presentation_time(time)
+    }
+
+    #[inline]
+    pub fn presentation_time(&self, frame_id: u64) -> Result<PresentationTime, ContextError> {
+        self.0.egl_context.presentation_time(frame_id)
+    }
+
+    #[inline]
+    pub fn current_state(&self) -> CurrentState {
+        self.0.egl_context.current_state()
+    }
+
+    #[inline]
+    pub unsafe fn restore_current_state(&self, state: &CurrentState)
+                                        -> Result<(), ContextError>
+    {
+        self.0.egl_context.restore_current_state(state)
+    }
+
     #[inline]
     pub fn get_proc_address(&self, addr: &str) -> *const () {
         self.0.egl_context.get_proc_address(addr)
@@ -134,6 +545,11 @@ impl Context {
         self.0.egl_context.get_pixel_format()
     }
 
+    #[inline]
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        self.0.egl_context.max_pbuffer_size()
+    }
+
     #[inline]
     pub unsafe fn raw_handle(&self) -> egl::ffi::EGLContext {
         self.0.egl_context.raw_handle()
@@ -160,17 +576,47 @@ impl HeadlessContext {
         let gl_attr = gl_attr.clone().map_sharing(|c| &c.0);
         let context = EglContext::new(egl::ffi::egl::Egl,
                                            pf_reqs,
-                                           &gl_attr,
+                                           gl_attr,
                                            egl::NativeDisplay::Android)?;
         let context = context.finish_pbuffer(dimensions)?;     // TODO:
         Ok(HeadlessContext(context))
     }
 
+    /// See the docs in the crate root file.
+    pub fn new_surfaceless(
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&HeadlessContext>,
+        _: &PlatformSpecificHeadlessBuilderAttributes,
+    ) -> Result<Self, CreationError>
+    {
+        let gl_attr = gl_attr.clone().map_sharing(|c| &c.0);
+        let context = EglContext::new(egl::ffi::egl::Egl,
+                                      pf_reqs,
+                                      gl_attr,
+                                      egl::NativeDisplay::Android)?;
+        let context = context.finish_surfaceless()?;
+        Ok(HeadlessContext(context))
+    }
+
+    pub fn new_shared(
+        _: (u32, u32),
+        _: bool,
+        _: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        Err(CreationError::NotSupported("sharing lists between a headless and a windowed context"))
+    }
+
     #[inline]
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         self.0.make_current()
     }
 
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        self.0.make_not_current()
+    }
+
     #[inline]
     pub fn is_current(&self) -> bool {
         self.0.is_current()
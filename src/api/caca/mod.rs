@@ -27,7 +27,7 @@ impl Context {
     ) -> Result<Self, CreationError>
     {
         let gl_attr = gl_attr.clone().map_sharing(|w| &w.opengl);
-        let opengl = OsMesaContext::new(window_outer_size, pf_reqs, &gl_attr)?;
+        let opengl = OsMesaContext::new(window_outer_size, pf_reqs, &gl_attr, None)?;
 
         let opengl_dimensions = opengl.get_dimensions();
 
@@ -0,0 +1,138 @@
+#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+
+use CreationError;
+
+use super::egl_error;
+use super::ffi;
+
+use std::ffi::CStr;
+use std::path::PathBuf;
+use std::ptr;
+
+// From `EGL_EXT_device_query_name`, which is too recent for the registry of `gl_generator`.
+const RENDERER_EXT: ffi::egl::types::EGLint = 0x335F;
+// From `EGL_EXT_device_drm_render_node`, which is too recent for the registry of `gl_generator`.
+const DRM_RENDER_NODE_FILE_EXT: ffi::egl::types::EGLint = 0x3377;
+
+/// An `EGLDeviceEXT`, which represents a GPU or a software renderer on which an EGL display can
+/// be created with `EGL_EXT_platform_device`.
+#[derive(Clone)]
+pub struct Device {
+    egl: ffi::egl::Egl,
+    device: ffi::egl::types::EGLDeviceEXT,
+}
+
+unsafe impl Send for Device {}
+unsafe impl Sync for Device {}
+
+impl Device {
+    /// Returns the devices of the system with `eglQueryDevicesEXT`.
+    pub fn enumerate(egl: &ffi::egl::Egl) -> Result<Vec<Device>, CreationError> {
+        let extensions = unsafe { client_extensions(egl) };
+        let has_extension = |e: &str| extensions.iter().any(|s| s == e);
+        let has_enumeration = has_extension("EGL_EXT_device_enumeration") ||
+                              has_extension("EGL_EXT_device_base");
+        if !has_enumeration || !has_extension("EGL_EXT_platform_device") ||
+           !egl.QueryDevicesEXT.is_loaded() || !egl.QueryDeviceStringEXT.is_loaded()
+        {
+            return Err(CreationError::NotSupported("EGL_EXT_device_enumeration not supported"));
+        }
+
+        unsafe {
+            let mut count = 0;
+            if egl.QueryDevicesEXT(0, ptr::null_mut(), &mut count) == 0 {
+                return Err(egl_error(egl, "eglQueryDevicesEXT"));
+            }
+
+            let mut devices = Vec::with_capacity(count as usize);
+            if egl.QueryDevicesEXT(count, devices.as_mut_ptr(), &mut count) == 0 {
+                return Err(egl_error(egl, "eglQueryDevicesEXT"));
+            }
+            devices.set_len(count as usize);
+
+            Ok(devices.into_iter().map(|device| Device {
+                egl: egl.clone(),
+                device: device,
+            }).collect())
+        }
+    }
+
+    /// Returns the library through which the device was enumerated.
+    #[inline]
+    pub fn egl(&self) -> &ffi::egl::Egl {
+        &self.egl
+    }
+
+    #[inline]
+    pub fn as_ptr(&self) -> ffi::egl::types::EGLDeviceEXT {
+        self.device
+    }
+
+    /// Returns the device extensions, which are distinct from the extensions of the displays.
+    pub fn extensions(&self) -> Vec<String> {
+        self.query_string(ffi::egl::EXTENSIONS as ffi::egl::types::EGLint)
+            .map(|list| list.split(' ').filter(|e| !e.is_empty()).map(|e| e.to_owned()).collect())
+            .unwrap_or_else(Vec::new)
+    }
+
+    /// Returns the name of the renderer of the device if `EGL_EXT_device_query_name` is
+    /// supported.
+    pub fn renderer(&self) -> Option<String> {
+        self.query_extension_string("EGL_EXT_device_query_name", RENDERER_EXT)
+    }
+
+    /// Returns the vendor of the device if `EGL_EXT_device_query_name` is supported.
+    pub fn vendor(&self) -> Option<String> {
+        self.query_extension_string("EGL_EXT_device_query_name",
+                                    ffi::egl::VENDOR as ffi::egl::types::EGLint)
+    }
+
+    /// Returns the primary node of the device if `EGL_EXT_device_drm` is supported.
+    pub fn drm_device_file(&self) -> Option<PathBuf> {
+        self.query_extension_string("EGL_EXT_device_drm",
+                                    ffi::egl::DRM_DEVICE_FILE_EXT as ffi::egl::types::EGLint)
+            .map(PathBuf::from)
+    }
+
+    /// Returns the render node of the device if `EGL_EXT_device_drm_render_node` is supported.
+    pub fn drm_render_node_file(&self) -> Option<PathBuf> {
+        self.query_extension_string("EGL_EXT_device_drm_render_node", DRM_RENDER_NODE_FILE_EXT)
+            .map(PathBuf::from)
+    }
+
+    /// Returns true if the device is a software renderer, which Mesa reports with
+    /// `EGL_MESA_device_software`.
+    pub fn is_software(&self) -> bool {
+        self.extensions().iter().any(|e| e == "EGL_MESA_device_software")
+    }
+
+    fn query_extension_string(&self, extension: &str, name: ffi::egl::types::EGLint)
+                              -> Option<String>
+    {
+        if !self.extensions().iter().any(|e| e == extension) {
+            return None;
+        }
+        self.query_string(name)
+    }
+
+    fn query_string(&self, name: ffi::egl::types::EGLint) -> Option<String> {
+        unsafe {
+            let p = self.egl.QueryDeviceStringEXT(self.device, name);
+            if p.is_null() {
+                None
+            } else {
+                Some(CStr::from_ptr(p).to_string_lossy().into_owned())
+            }
+        }
+    }
+}
+
+/// Returns the client extensions, which can be queried without any display with EGL 1.5 or
+/// `EGL_EXT_client_extensions`.
+unsafe fn client_extensions(egl: &ffi::egl::Egl) -> Vec<String> {
+    let p = egl.QueryString(ffi::egl::NO_DISPLAY, ffi::egl::EXTENSIONS as i32);
+    if p.is_null() {
+        return vec![];
+    }
+    CStr::from_ptr(p).to_string_lossy().split(' ').map(|e| e.to_string()).collect()
+}
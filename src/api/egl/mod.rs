@@ -6,18 +6,47 @@ use ContextError;
 use CreationError;
 use GlAttributes;
 use GlRequest;
+use PbufferAttributes;
+use PbufferLimits;
+use PbufferTextureFormat;
 use PixelFormat;
 use PixelFormatRequirements;
+use PresentMode;
+use PresentationTime;
+use RawContextHandle;
+use MultisampleResolve;
+use Rect;
+use RenderBuffer;
 use ReleaseBehavior;
 use Robustness;
+use SwapBehavior;
+use SwapInterval;
 use Api;
+use Colorspace;
+use ConfigCaveat;
+use Conformance;
+use ContextPriority;
+use DeviceInfo;
+use GlProfile;
+use SupportedVersions;
+use find_max_versions;
+use probe_requirements;
+use NativeConfigId;
+use config_cache;
 
 use std::ffi::{CStr, CString};
-use std::os::raw::{c_void, c_int};
+use std::os::raw::{c_char, c_void, c_int};
 use std::{mem, ptr};
 use std::cell::Cell;
+use std::sync::Arc;
+use std::time::Duration;
 
 pub mod ffi;
+mod device;
+
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+          target_os = "openbsd"))]
+pub use self::device::Device;
 
 /// Specifies the type of display passed as `native_display`.
 pub enum NativeDisplay {
@@ -29,8 +58,8 @@ pub enum NativeDisplay {
     Wayland(Option<ffi::EGLNativeDisplayType>),
     /// `EGL_DEFAULT_DISPLAY` is mandatory for Android.
     Android,
-    // TODO: should be `EGLDeviceEXT`
-    Device(ffi::EGLNativeDisplayType),
+    /// An `EGLDeviceEXT` returned by `eglQueryDevicesEXT`.
+    Device(ffi::egl::types::EGLDeviceEXT),
     /// Don't specify any display type. Useful on windows. `None` means `EGL_DEFAULT_DISPLAY`.
     Other(Option<ffi::EGLNativeDisplayType>),
 }
@@ -43,6 +72,347 @@ pub struct Context {
     api: Api,
     pixel_format: PixelFormat,
     config_id: ffi::egl::types::EGLConfig,
+    // The identifier of the last frame swapped with `swap_buffers_with_frame_id`.
+    last_frame_id: Cell<Option<u64>>,
+    // The last interval passed to `eglSwapInterval`, since EGL can't query it. EGL defaults to 1.
+    swap_interval: Cell<u32>,
+    // Terminates the display once the context and all the surfaces created from it are gone.
+    terminate: Arc<TerminateGuard>,
+    // True if the context was created by another library, in which case it isn't destroyed.
+    foreign: bool,
+}
+
+/// The EGL context and surfaces that were current on the calling thread at some point, so that
+/// they can be made current again later.
+pub struct CurrentState {
+    display: ffi::egl::types::EGLDisplay,
+    draw: ffi::egl::types::EGLSurface,
+    read: ffi::egl::types::EGLSurface,
+    context: ffi::egl::types::EGLContext,
+}
+
+/// Calls `eglTerminate` when dropped, unless the display was initialized by another library.
+struct TerminateGuard {
+    egl: ffi::egl::Egl,
+    display: ffi::egl::types::EGLDisplay,
+    foreign: bool,
+}
+
+unsafe impl Send for TerminateGuard {}
+unsafe impl Sync for TerminateGuard {}
+
+impl Drop for TerminateGuard {
+    fn drop(&mut self) {
+        if !self.foreign {
+            // the configs are invalidated by `eglTerminate`
+            config_cache::invalidate(self.display as usize);
+            unsafe { self.egl.Terminate(self.display); }
+        }
+    }
+}
+
+/// An EGL config of a display, from which contexts and surfaces can be created.
+///
+/// The display stays initialized for as long as a config, context or surface created from it is
+/// alive.
+#[derive(Clone)]
+pub struct Config {
+    egl: ffi::egl::Egl,
+    display: ffi::egl::types::EGLDisplay,
+    config_id: ffi::egl::types::EGLConfig,
+    pixel_format: PixelFormat,
+    terminate: Arc<TerminateGuard>,
+}
+
+unsafe impl Send for Config {}
+unsafe impl Sync for Config {}
+
+impl Config {
+    #[inline]
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        self.pixel_format.clone()
+    }
+
+    #[inline]
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        unsafe { query_pbuffer_limits(&self.egl, self.display, self.config_id) }
+    }
+
+    /// Creates a surface on `native_window` using this config.
+    pub unsafe fn create_window_surface(&self, native_window: ffi::EGLNativeWindowType)
+                                        -> Result<Surface, CreationError>
+    {
+        self.check_surface_type(ffi::egl::WINDOW_BIT)?;
+        let surface = self.egl.CreateWindowSurface(self.display, self.config_id, native_window,
+                                                   ptr::null());
+        if surface.is_null() {
+            return Err(egl_error(&self.egl, "eglCreateWindowSurface"));
+        }
+        Ok(self.wrap_surface(surface))
+    }
+
+    /// Creates an offscreen surface using this config.
+    pub fn create_pbuffer_surface(&self, attributes: &PbufferAttributes)
+                                  -> Result<Surface, CreationError>
+    {
+        self.check_surface_type(ffi::egl::PBUFFER_BIT)?;
+        let mut attrs = vec![
+            ffi::egl::WIDTH as c_int, attributes.dimensions.0 as c_int,
+            ffi::egl::HEIGHT as c_int, attributes.dimensions.1 as c_int,
+        ];
+        if attributes.largest {
+            attrs.push(ffi::egl::LARGEST_PBUFFER as c_int);
+            attrs.push(ffi::egl::TRUE as c_int);
+        }
+        if let Some(format) = attributes.texture_format {
+            attrs.push(ffi::egl::TEXTURE_FORMAT as c_int);
+            attrs.push(match format {
+                PbufferTextureFormat::Rgb => ffi::egl::TEXTURE_RGB as c_int,
+                PbufferTextureFormat::Rgba => ffi::egl::TEXTURE_RGBA as c_int,
+            });
+            attrs.push(ffi::egl::TEXTURE_TARGET as c_int);
+            attrs.push(ffi::egl::TEXTURE_2D as c_int);
+        }
+        if attributes.mipmap {
+            if attributes.texture_format.is_none() {
+                return Err(CreationError::NotSupported("mipmaps require a texture format"));
+            }
+            attrs.push(ffi::egl::MIPMAP_TEXTURE as c_int);
+            attrs.push(ffi::egl::TRUE as c_int);
+        }
+        attrs.push(ffi::egl::NONE as c_int);
+
+        let surface = unsafe {
+            self.egl.CreatePbufferSurface(self.display, self.config_id, attrs.as_ptr())
+        };
+        if surface.is_null() {
+            return Err(egl_error(&self.egl, "eglCreatePbufferSurface"));
+        }
+        Ok(self.wrap_surface(surface))
+    }
+
+    /// Creates a surface on `native_pixmap` using this config.
+    pub unsafe fn create_pixmap_surface(&self, native_pixmap: ffi::EGLNativePixmapType)
+                                        -> Result<Surface, CreationError>
+    {
+        self.check_surface_type(ffi::egl::PIXMAP_BIT)?;
+        let surface = self.egl.CreatePixmapSurface(self.display, self.config_id,
+                                                   native_pixmap as *mut _, ptr::null());
+        if surface.is_null() {
+            return Err(egl_error(&self.egl, "eglCreatePixmapSurface"));
+        }
+        Ok(self.wrap_surface(surface))
+    }
+
+    fn check_surface_type(&self, bit: ffi::egl::types::EGLenum) -> Result<(), CreationError> {
+        let mut value = 0;
+        let ret = unsafe {
+            self.egl.GetConfigAttrib(self.display, self.config_id,
+                                     ffi::egl::SURFACE_TYPE as ffi::egl::types::EGLint,
+                                     &mut value)
+        };
+        if ret == 0 {
+            return Err(egl_error(&self.egl, "eglGetConfigAttrib"));
+        }
+        if value & bit as ffi::egl::types::EGLint == 0 {
+            return Err(CreationError::NotSupported("the config doesn't support this kind of \
+                                                    surface"));
+        }
+        Ok(())
+    }
+
+    fn wrap_surface(&self, surface: ffi::egl::types::EGLSurface) -> Surface {
+        Surface {
+            egl: self.egl.clone(),
+            display: self.display,
+            surface: surface,
+            _terminate: self.terminate.clone(),
+        }
+    }
+}
+
+/// An EGL surface created from the config of a `Context`.
+///
+/// The surface can be used with any context that was created with a compatible config, and may
+/// outlive the context it was created from.
+pub struct Surface {
+    egl: ffi::egl::Egl,
+    display: ffi::egl::types::EGLDisplay,
+    surface: ffi::egl::types::EGLSurface,
+    _terminate: Arc<TerminateGuard>,
+}
+
+unsafe impl Send for Surface {}
+unsafe impl Sync for Surface {}
+
+impl Surface {
+    /// Returns the width and height of the surface.
+    pub fn dimensions(&self) -> Result<(u32, u32), ContextError> {
+        unsafe {
+            let mut width = 0;
+            let mut height = 0;
+            if self.egl.QuerySurface(self.display, self.surface, ffi::egl::WIDTH as i32,
+                                     &mut width) == 0 ||
+               self.egl.QuerySurface(self.display, self.surface, ffi::egl::HEIGHT as i32,
+                                     &mut height) == 0
+            {
+                return Err(ContextError::OsError(
+                    format!("eglQuerySurface failed (eglGetError returned 0x{:x})",
+                            self.egl.GetError())
+                ));
+            }
+            Ok((width as u32, height as u32))
+        }
+    }
+
+    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        let ret = unsafe { self.egl.SwapBuffers(self.display, self.surface) };
+
+        if ret == 0 {
+            match unsafe { self.egl.GetError() } as u32 {
+                ffi::egl::CONTEXT_LOST => Err(ContextError::ContextLost),
+                err => Err(ContextError::OsError(
+                    format!("eglSwapBuffers failed (eglGetError returned 0x{:x})", err)
+                )),
+            }
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the age of the back buffer, or 0 if its contents are undefined. The surface must
+    /// be current.
+    pub fn buffer_age(&self) -> Result<u32, ContextError> {
+        unsafe {
+            let extensions = self.egl.QueryString(self.display, ffi::egl::EXTENSIONS as i32);
+            if extensions.is_null() ||
+               !CStr::from_ptr(extensions).to_string_lossy().split(' ')
+                                          .any(|e| e == "EGL_EXT_buffer_age")
+            {
+                return Err(ContextError::FunctionUnavailable);
+            }
+
+            let mut age = 0;
+            if self.egl.QuerySurface(self.display, self.surface,
+                                     ffi::egl::BUFFER_AGE_EXT as i32, &mut age) == 0
+            {
+                return Err(ContextError::OsError(
+                    format!("eglQuerySurface failed (eglGetError returned 0x{:x})",
+                            self.egl.GetError())
+                ));
+            }
+
+            Ok(age as u32)
+        }
+    }
+
+    /// Tells the implementation that only `rects` will be modified in the back buffer before the
+    /// next swap. The surface must be current.
+    pub fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        unsafe {
+            let extensions = self.egl.QueryString(self.display, ffi::egl::EXTENSIONS as i32);
+            if extensions.is_null() ||
+               !CStr::from_ptr(extensions).to_string_lossy().split(' ')
+                                          .any(|e| e == "EGL_KHR_partial_update") ||
+               !has_partial_update_functions(&self.egl)
+            {
+                return Err(ContextError::FunctionUnavailable);
+            }
+
+            let mut flat_rects = Vec::with_capacity(rects.len() * 4);
+            for rect in rects {
+                flat_rects.push(rect.x as ffi::egl::types::EGLint);
+                flat_rects.push(rect.y as ffi::egl::types::EGLint);
+                flat_rects.push(rect.width as ffi::egl::types::EGLint);
+                flat_rects.push(rect.height as ffi::egl::types::EGLint);
+            }
+
+            if self.egl.SetDamageRegionKHR(self.display, self.surface, flat_rects.as_mut_ptr(),
+                                           rects.len() as ffi::egl::types::EGLint) == 0
+            {
+                return Err(ContextError::OsError(
+                    format!("eglSetDamageRegionKHR failed (eglGetError returned 0x{:x})",
+                            self.egl.GetError())
+                ));
+            }
+
+            Ok(())
+        }
+    }
+
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        unsafe { query_swap_behavior(&self.egl, self.display, self.surface) }
+    }
+
+    #[inline]
+    pub fn set_swap_behavior(&self, behavior: SwapBehavior) -> Result<(), ContextError> {
+        unsafe { set_swap_behavior(&self.egl, self.display, self.surface, behavior) }
+    }
+
+    #[inline]
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        unsafe { query_render_buffer(&self.egl, self.display, self.surface) }
+    }
+
+    #[inline]
+    pub fn set_render_buffer(&self, render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        unsafe { set_render_buffer(&self.egl, self.display, self.surface, render_buffer) }
+    }
+
+    #[inline]
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        unsafe { query_multisample_resolve(&self.egl, self.display, self.surface) }
+    }
+
+    #[inline]
+    pub fn set_multisample_resolve(&self, resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        unsafe { set_multisample_resolve(&self.egl, self.display, self.surface, resolve) }
+    }
+
+    #[inline]
+    pub unsafe fn raw_handle(&self) -> ffi::egl::types::EGLSurface {
+        self.surface
+    }
+}
+
+impl Drop for Surface {
+    fn drop(&mut self) {
+        // if the surface is current, its destruction is deferred by EGL until it no longer is
+        unsafe { self.egl.DestroySurface(self.display, self.surface); }
+    }
+}
+
+/// Returns the largest pbuffer that can be created with `config_id`.
+unsafe fn query_pbuffer_limits(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                               config_id: ffi::egl::types::EGLConfig)
+                               -> Result<PbufferLimits, ContextError>
+{
+    let get_attrib = |attrib: ffi::egl::types::EGLenum| {
+        let mut value = 0;
+        if egl.GetConfigAttrib(display, config_id, attrib as ffi::egl::types::EGLint,
+                               &mut value) == 0
+        {
+            return Err(ContextError::OsError(
+                format!("eglGetConfigAttrib failed (eglGetError returned 0x{:x})",
+                        egl.GetError())
+            ));
+        }
+        Ok(value)
+    };
+
+    let surface_type = get_attrib(ffi::egl::SURFACE_TYPE)?;
+    if surface_type & ffi::egl::PBUFFER_BIT as ffi::egl::types::EGLint == 0 {
+        return Err(ContextError::FunctionUnavailable);
+    }
+
+    Ok(PbufferLimits {
+        max_width: get_attrib(ffi::egl::MAX_PBUFFER_WIDTH)? as u32,
+        max_height: get_attrib(ffi::egl::MAX_PBUFFER_HEIGHT)? as u32,
+        max_pixels: get_attrib(ffi::egl::MAX_PBUFFER_PIXELS)? as u32,
+    })
 }
 
 #[cfg(target_os = "android")]
@@ -165,7 +535,7 @@ impl Context {
     pub fn new<'a>(
         egl: ffi::egl::Egl,
         pf_reqs: &PixelFormatRequirements,
-        opengl: &'a GlAttributes<&'a Context>,
+        opengl: GlAttributes<&'a Context>,
         native_display: NativeDisplay,
     ) -> Result<ContextPrototype<'a>, CreationError>
     {
@@ -180,80 +550,13 @@ impl Context {
             return Err(CreationError::OsError("Could not create EGL display object".to_string()));
         }
 
-        let egl_version = unsafe {
-            let mut major: ffi::egl::types::EGLint = mem::uninitialized();
-            let mut minor: ffi::egl::types::EGLint = mem::uninitialized();
-
-            if egl.Initialize(display, &mut major, &mut minor) == 0 {
-                return Err(CreationError::OsError(format!("eglInitialize failed")))
-            }
-
-            (major, minor)
-        };
-
-        // the list of extensions supported by the client once initialized is different from the
-        // list of extensions obtained earlier
-        let extensions = if egl_version >= (1, 2) {
-            let p = unsafe { CStr::from_ptr(egl.QueryString(display, ffi::egl::EXTENSIONS as i32)) };
-            let list = String::from_utf8(p.to_bytes().to_vec()).unwrap_or_else(|_| format!(""));
-            list.split(' ').map(|e| e.to_string()).collect::<Vec<_>>()
-
-        } else {
-            vec![]
-        };
+        let (egl_version, extensions) = unsafe { initialize_display(&egl, display)? };
 
         // binding the right API and choosing the version
-        let (version, api) = unsafe {
-            match opengl.version {
-                GlRequest::Latest => {
-                    if egl_version >= (1, 4) {
-                        if egl.BindAPI(ffi::egl::OPENGL_API) != 0 {
-                            (None, Api::OpenGl)
-                        } else if egl.BindAPI(ffi::egl::OPENGL_ES_API) != 0 {
-                            (None, Api::OpenGlEs)
-                        } else {
-                            return Err(CreationError::OpenGlVersionNotSupported);
-                        }
-                    } else {
-                        (None, Api::OpenGlEs)
-                    }
-                },
-                GlRequest::Specific(Api::OpenGlEs, version) => {
-                    if egl_version >= (1, 2) {
-                        if egl.BindAPI(ffi::egl::OPENGL_ES_API) == 0 {
-                            return Err(CreationError::OpenGlVersionNotSupported);
-                        }
-                    }
-                    (Some(version), Api::OpenGlEs)
-                },
-                GlRequest::Specific(Api::OpenGl, version) => {
-                    if egl_version < (1, 4) {
-                        return Err(CreationError::OpenGlVersionNotSupported);
-                    }
-                    if egl.BindAPI(ffi::egl::OPENGL_API) == 0 {
-                        return Err(CreationError::OpenGlVersionNotSupported);
-                    }
-                    (Some(version), Api::OpenGl)
-                },
-                GlRequest::Specific(_, _) => return Err(CreationError::OpenGlVersionNotSupported),
-                GlRequest::GlThenGles { opengles_version, opengl_version } => {
-                    if egl_version >= (1, 4) {
-                        if egl.BindAPI(ffi::egl::OPENGL_API) != 0 {
-                            (Some(opengl_version), Api::OpenGl)
-                        } else if egl.BindAPI(ffi::egl::OPENGL_ES_API) != 0 {
-                            (Some(opengles_version), Api::OpenGlEs)
-                        } else {
-                            return Err(CreationError::OpenGlVersionNotSupported);
-                        }
-                    } else {
-                        (Some(opengles_version), Api::OpenGlEs)
-                    }
-                },
-            }
-        };
+        let (version, api) = unsafe { bind_api(&egl, egl_version, opengl.version)? };
 
         let (config_id, pixel_format) = unsafe {
-            choose_fbconfig(&egl, display, &egl_version, api, version, pf_reqs)?
+            choose_fbconfig(&egl, display, &egl_version, &extensions, api, version, pf_reqs)?
         };
 
         Ok(ContextPrototype {
@@ -266,9 +569,152 @@ impl Context {
             version: version,
             config_id: config_id,
             pixel_format: pixel_format,
+            swap_behavior: pf_reqs.swap_behavior,
+            single_buffer: pf_reqs.double_buffer == Some(false),
+            terminate: None,
+        })
+    }
+
+    /// Builds a prototype from a config returned by `find_configs`.
+    pub fn new_with_config<'a>(
+        config: &Config,
+        opengl: GlAttributes<&'a Context>,
+    ) -> Result<ContextPrototype<'a>, CreationError>
+    {
+        if opengl.sharing.is_some() {
+            return Err(CreationError::NotSupported(
+                "sharing lists with a context built from a Config"));
+        }
+
+        // the display is already initialized, this only queries its version and extensions
+        let (egl_version, extensions) = unsafe {
+            initialize_display(&config.egl, config.display)?
+        };
+        let (version, api) = unsafe { bind_api(&config.egl, egl_version, opengl.version)? };
+
+        Ok(ContextPrototype {
+            opengl: opengl,
+            egl: config.egl.clone(),
+            display: config.display,
+            egl_version: egl_version,
+            extensions: extensions,
+            api: api,
+            version: version,
+            config_id: config.config_id,
+            pixel_format: config.pixel_format.clone(),
+            swap_behavior: SwapBehavior::Destroyed,
+            single_buffer: false,
+            terminate: Some(config.terminate.clone()),
+        })
+    }
+
+    /// Wraps a context created by another library on the display of `native_display`, along
+    /// with a new surface on `native_window` for it to draw to.
+    ///
+    /// The display must already be initialized. Neither the context nor the display are
+    /// destroyed when the returned context is dropped.
+    pub unsafe fn adopt(
+        egl: ffi::egl::Egl,
+        native_display: NativeDisplay,
+        context: ffi::egl::types::EGLContext,
+        native_window: ffi::EGLNativeWindowType,
+    ) -> Result<Context, CreationError>
+    {
+        let display = get_native_display(&egl, native_display);
+        if display.is_null() {
+            return Err(CreationError::OsError("Could not create EGL display object".to_string()));
+        }
+
+        let query_context = |attrib: ffi::egl::types::EGLenum| {
+            let mut value = 0;
+            if egl.QueryContext(display, context, attrib as ffi::egl::types::EGLint,
+                                &mut value) == 0
+            {
+                return Err(egl_error(&egl, "eglQueryContext"));
+            }
+            Ok(value)
+        };
+
+        let api = match query_context(ffi::egl::CONTEXT_CLIENT_TYPE)? as ffi::egl::types::EGLenum {
+            ffi::egl::OPENGL_API => Api::OpenGl,
+            ffi::egl::OPENGL_ES_API => Api::OpenGlEs,
+            _ => return Err(CreationError::NotSupported("the context uses an unsupported API")),
+        };
+        if egl.BindAPI(if api == Api::OpenGl { ffi::egl::OPENGL_API }
+                       else { ffi::egl::OPENGL_ES_API }) == 0
+        {
+            return Err(egl_error(&egl, "eglBindAPI"));
+        }
+
+        // looking up the config the context was created with from its id
+        let descriptor = [
+            ffi::egl::CONFIG_ID as c_int, query_context(ffi::egl::CONFIG_ID)?,
+            ffi::egl::NONE as c_int,
+        ];
+        let config_id = match choose_all_configs(&egl, display, &descriptor)?.first() {
+            Some(&config_id) => config_id,
+            None => return Err(CreationError::OsError(format!("the config of the context \
+                                                               could not be found"))),
+        };
+        let pixel_format = config_pixel_format(&egl, display, config_id)?;
+
+        let surface = egl.CreateWindowSurface(display, config_id, native_window, ptr::null());
+        if surface.is_null() {
+            return Err(egl_error(&egl, "eglCreateWindowSurface"));
+        }
+
+        let terminate = Arc::new(TerminateGuard {
+            egl: egl.clone(),
+            display: display,
+            foreign: true,
+        });
+
+        Ok(Context {
+            egl: egl,
+            display: display,
+            context: context,
+            surface: Cell::new(surface),
+            api: api,
+            pixel_format: pixel_format,
+            config_id: config_id,
+            last_frame_id: Cell::new(None),
+            swap_interval: Cell::new(1),
+            terminate: terminate,
+            foreign: true,
         })
     }
 
+    /// Returns the config this context was created with.
+    #[inline]
+    pub fn config(&self) -> Config {
+        Config {
+            egl: self.egl.clone(),
+            display: self.display,
+            config_id: self.config_id,
+            pixel_format: self.pixel_format.clone(),
+            terminate: self.terminate.clone(),
+        }
+    }
+
+    /// Makes the context current, drawing to `draw` and reading from `read` instead of the
+    /// surface the context was created with.
+    pub unsafe fn make_current_surfaces(&self, draw: &Surface, read: &Surface)
+                                        -> Result<(), ContextError>
+    {
+        let ret = self.egl.MakeCurrent(self.display, draw.surface, read.surface, self.context);
+
+        if ret == 0 {
+            match self.egl.GetError() as u32 {
+                ffi::egl::CONTEXT_LOST => Err(ContextError::ContextLost),
+                err => Err(ContextError::OsError(
+                    format!("eglMakeCurrent failed (eglGetError returned 0x{:x})", err)
+                )),
+            }
+        } else {
+            Ok(())
+        }
+    }
+
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         let ret = self.egl.MakeCurrent(self.display, self.surface.get(), self.surface.get(), self.context);
 
@@ -283,40 +729,446 @@ impl Context {
         }
     }
 
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        if !self.is_current() {
+            return Ok(());
+        }
+
+        let ret = self.egl.MakeCurrent(self.display, ffi::egl::NO_SURFACE, ffi::egl::NO_SURFACE,
+                                       ffi::egl::NO_CONTEXT);
+
+        if ret == 0 {
+            match self.egl.GetError() as u32 {
+                ffi::egl::CONTEXT_LOST => Err(ContextError::ContextLost),
+                err => Err(ContextError::OsError(
+                    format!("eglMakeCurrent failed (eglGetError returned 0x{:x})", err)
+                )),
+            }
+        } else {
+            Ok(())
+        }
+    }
+
     #[inline]
     pub fn is_current(&self) -> bool {
         unsafe { self.egl.GetCurrentContext() == self.context }
     }
 
-    pub fn get_proc_address(&self, addr: &str) -> *const () {
-        let addr = CString::new(addr.as_bytes()).unwrap();
-        let addr = addr.as_ptr();
+    /// Checks that the context and the surface it draws to are still valid, without making them
+    /// current.
+    pub fn validate(&self) -> Result<(), ContextError> {
         unsafe {
-            self.egl.GetProcAddress(addr) as *const _
+            let mut value = 0;
+            if self.egl.QueryContext(self.display, self.context,
+                                     ffi::egl::CONFIG_ID as ffi::egl::types::EGLint,
+                                     &mut value) == 0
+            {
+                return Err(validation_error(&self.egl, "eglQueryContext"));
+            }
+            let surface = self.surface.get();
+            if surface != ffi::egl::NO_SURFACE &&
+               self.egl.QuerySurface(self.display, surface,
+                                     ffi::egl::WIDTH as ffi::egl::types::EGLint,
+                                     &mut value) == 0
+            {
+                return Err(validation_error(&self.egl, "eglQuerySurface"));
+            }
         }
+        Ok(())
     }
 
-    #[inline]
-    pub fn swap_buffers(&self) -> Result<(), ContextError> {
-        if self.surface.get() == ffi::egl::NO_SURFACE {
-            return Err(ContextError::ContextLost);
-        }
+    /// Sets the number of screen refreshes to wait for before swapping the buffers of the current
+    /// surface. The context must be current.
+    pub fn set_swap_interval(&self, interval: SwapInterval) -> Result<(), ContextError> {
+        let interval = match interval {
+            SwapInterval::Wait(interval) => interval,
+            SwapInterval::Adaptive(_) => return Err(ContextError::FunctionUnavailable),
+        };
 
         let ret = unsafe {
-            self.egl.SwapBuffers(self.display, self.surface.get())
+            self.egl.SwapInterval(self.display, interval as ffi::egl::types::EGLint)
         };
 
         if ret == 0 {
             match unsafe { self.egl.GetError() } as u32 {
-                ffi::egl::CONTEXT_LOST => return Err(ContextError::ContextLost),
-                err => panic!("eglSwapBuffers failed (eglGetError returned 0x{:x})", err)
+                ffi::egl::CONTEXT_LOST => Err(ContextError::ContextLost),
+                err => Err(ContextError::OsError(
+                    format!("eglSwapInterval failed (eglGetError returned 0x{:x})", err)
+                )),
             }
-
         } else {
+            // EGL silently clamps the interval to the bounds of the config
+            let (min, max) = self.get_swap_interval_range()?;
+            self.swap_interval.set(interval.max(min).min(max));
             Ok(())
         }
     }
 
+    /// Returns the last swap interval set on this context. EGL can't query the interval of a
+    /// surface, so the value is only accurate if the interval was set through this context.
+    #[inline]
+    pub fn get_swap_interval(&self) -> Result<SwapInterval, ContextError> {
+        Ok(SwapInterval::Wait(self.swap_interval.get()))
+    }
+
+    /// Returns the smallest and largest swap intervals supported by the config of the context.
+    pub fn get_swap_interval_range(&self) -> Result<(u32, u32), ContextError> {
+        let attrib = |attrib| -> Result<u32, ContextError> {
+            let mut value = 0;
+            let ret = unsafe {
+                self.egl.GetConfigAttrib(self.display, self.config_id,
+                                         attrib as ffi::egl::types::EGLint, &mut value)
+            };
+            if ret == 0 {
+                return Err(ContextError::OsError(format!(
+                    "eglGetConfigAttrib failed (eglGetError returned 0x{:x})",
+                    unsafe { self.egl.GetError() }
+                )));
+            }
+            Ok(value.max(0) as u32)
+        };
+        Ok((attrib(ffi::egl::MIN_SWAP_INTERVAL)?, attrib(ffi::egl::MAX_SWAP_INTERVAL)?))
+    }
+
+    /// Returns the present modes allowed by the swap intervals of the config of the context.
+    pub fn supported_present_modes(&self) -> Result<Vec<PresentMode>, ContextError> {
+        let (min, max) = self.get_swap_interval_range()?;
+        let mut modes = Vec::new();
+        if min == 0 {
+            modes.push(PresentMode::Immediate);
+        }
+        if max >= 1 {
+            modes.push(PresentMode::Fifo);
+        }
+        Ok(modes)
+    }
+
+    /// Returns the swap behavior of the surface the context currently draws to. The context must
+    /// be current.
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        unsafe {
+            let surface = self.egl.GetCurrentSurface(ffi::egl::DRAW as ffi::egl::types::EGLint);
+            if surface == ffi::egl::NO_SURFACE {
+                return Err(ContextError::ContextLost);
+            }
+            query_swap_behavior(&self.egl, self.display, surface)
+        }
+    }
+
+    /// Returns the colorspace of the surface the context currently draws to. The context must be
+    /// current.
+    pub fn colorspace(&self) -> Result<Colorspace, ContextError> {
+        unsafe {
+            let surface = self.egl.GetCurrentSurface(ffi::egl::DRAW as ffi::egl::types::EGLint);
+            if surface == ffi::egl::NO_SURFACE {
+                return Err(ContextError::ContextLost);
+            }
+            query_colorspace(&self.egl, self.display, surface)
+        }
+    }
+
+    /// Returns the buffer the context actually renders to, which only reflects a change of the
+    /// render buffer of the surface after the next swap.
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        let mut value = 0;
+        unsafe {
+            if self.egl.QueryContext(self.display, self.context,
+                                     ffi::egl::RENDER_BUFFER as ffi::egl::types::EGLint,
+                                     &mut value) == 0
+            {
+                return Err(ContextError::OsError(format!(
+                    "eglQueryContext failed (eglGetError returned 0x{:x})", self.egl.GetError()
+                )));
+            }
+        }
+
+        if value == ffi::egl::SINGLE_BUFFER as ffi::egl::types::EGLint {
+            Ok(RenderBuffer::Single)
+        } else {
+            Ok(RenderBuffer::Back)
+        }
+    }
+
+    /// Sets the render buffer of the surface the context currently draws to. The context must be
+    /// current.
+    pub fn set_render_buffer(&self, render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        unsafe {
+            let surface = self.egl.GetCurrentSurface(ffi::egl::DRAW as ffi::egl::types::EGLint);
+            if surface == ffi::egl::NO_SURFACE {
+                return Err(ContextError::ContextLost);
+            }
+            set_render_buffer(&self.egl, self.display, surface, render_buffer)
+        }
+    }
+
+    /// Returns the multisample resolve of the surface the context currently draws to. The context
+    /// must be current.
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        unsafe {
+            let surface = self.egl.GetCurrentSurface(ffi::egl::DRAW as ffi::egl::types::EGLint);
+            if surface == ffi::egl::NO_SURFACE {
+                return Err(ContextError::ContextLost);
+            }
+            query_multisample_resolve(&self.egl, self.display, surface)
+        }
+    }
+
+    /// Sets the multisample resolve of the surface the context currently draws to. The context
+    /// must be current.
+    pub fn set_multisample_resolve(&self, resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        unsafe {
+            let surface = self.egl.GetCurrentSurface(ffi::egl::DRAW as ffi::egl::types::EGLint);
+            if surface == ffi::egl::NO_SURFACE {
+                return Err(ContextError::ContextLost);
+            }
+            set_multisample_resolve(&self.egl, self.display, surface, resolve)
+        }
+    }
+
+    /// Returns the context and surfaces that are current on the calling thread, whether or not
+    /// they belong to this context.
+    pub fn current_state(&self) -> CurrentState {
+        unsafe {
+            CurrentState {
+                display: self.egl.GetCurrentDisplay(),
+                draw: self.egl.GetCurrentSurface(ffi::egl::DRAW as ffi::egl::types::EGLint),
+                read: self.egl.GetCurrentSurface(ffi::egl::READ as ffi::egl::types::EGLint),
+                context: self.egl.GetCurrentContext(),
+            }
+        }
+    }
+
+    /// Makes the context and surfaces of `state` current again. If no context was current when
+    /// the state was saved, this context is released instead.
+    pub unsafe fn restore_current_state(&self, state: &CurrentState) -> Result<(), ContextError> {
+        if state.context == ffi::egl::NO_CONTEXT {
+            return self.make_not_current();
+        }
+
+        let ret = self.egl.MakeCurrent(state.display, state.draw, state.read, state.context);
+
+        if ret == 0 {
+            match self.egl.GetError() as u32 {
+                ffi::egl::CONTEXT_LOST => Err(ContextError::ContextLost),
+                err => Err(ContextError::OsError(
+                    format!("eglMakeCurrent failed (eglGetError returned 0x{:x})", err)
+                )),
+            }
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn get_proc_address(&self, addr: &str) -> *const () {
+        let addr = CString::new(addr.as_bytes()).unwrap();
+        let addr = addr.as_ptr();
+        unsafe {
+            self.egl.GetProcAddress(addr) as *const _
+        }
+    }
+
+    #[inline]
+    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        if self.surface.get() == ffi::egl::NO_SURFACE {
+            return Err(ContextError::ContextLost);
+        }
+
+        let ret = unsafe {
+            self.egl.SwapBuffers(self.display, self.surface.get())
+        };
+
+        if ret == 0 {
+            match unsafe { self.egl.GetError() } as u32 {
+                ffi::egl::CONTEXT_LOST => return Err(ContextError::ContextLost),
+                err => panic!("eglSwapBuffers failed (eglGetError returned 0x{:x})", err)
+            }
+
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Swaps the buffers, and returns the identifier that `EGL_ANDROID_get_frame_timestamps`
+    /// gave to the frame.
+    pub fn swap_buffers_with_frame_id(&self) -> Result<u64, ContextError> {
+        if !self.has_frame_timestamps() {
+            return Err(ContextError::FunctionUnavailable);
+        }
+        if self.surface.get() == ffi::egl::NO_SURFACE {
+            return Err(ContextError::ContextLost);
+        }
+
+        let mut frame_id = 0;
+        unsafe {
+            // timestamps are only recorded for frames queued after they are enabled
+            if self.egl.SurfaceAttrib(self.display, self.surface.get(),
+                                      ffi::egl::TIMESTAMPS_ANDROID as ffi::egl::types::EGLint,
+                                      ffi::egl::TRUE as ffi::egl::types::EGLint) == 0 ||
+               self.egl.GetNextFrameIdANDROID(self.display, self.surface.get(),
+                                              &mut frame_id) == 0
+            {
+                return Err(ContextError::OsError(
+                    format!("eglGetNextFrameIdANDROID failed (eglGetError returned 0x{:x})",
+                            self.egl.GetError())
+                ));
+            }
+        }
+
+        self.swap_buffers()?;
+        self.last_frame_id.set(Some(frame_id as u64));
+        Ok(frame_id as u64)
+    }
+
+    /// Swaps the buffers like `swap_buffers_with_frame_id`, unless the last frame swapped with it
+    /// hasn't been presented yet.
+    pub fn try_swap_buffers(&self) -> Result<u64, ContextError> {
+        if let Some(frame_id) = self.last_frame_id.get() {
+            if self.presentation_time(frame_id)? == PresentationTime::Pending {
+                return Err(ContextError::WouldBlock);
+            }
+        }
+        self.swap_buffers_with_frame_id()
+    }
+
+    /// Returns when the frame identified by `frame_id` was presented on the display.
+    pub fn presentation_time(&self, frame_id: u64) -> Result<PresentationTime, ContextError> {
+        if !self.has_frame_timestamps() {
+            return Err(ContextError::FunctionUnavailable);
+        }
+        if self.surface.get() == ffi::egl::NO_SURFACE {
+            return Err(ContextError::ContextLost);
+        }
+
+        let timestamps = [ffi::egl::DISPLAY_PRESENT_TIME_ANDROID as ffi::egl::types::EGLint];
+        let mut values = [0];
+        let ret = unsafe {
+            self.egl.GetFrameTimestampsANDROID(self.display, self.surface.get(),
+                                               frame_id as ffi::egl::types::EGLuint64KHR, 1,
+                                               timestamps.as_ptr(), values.as_mut_ptr())
+        };
+
+        if ret == 0 {
+            // the frame is too old or too recent for its timestamps to be known
+            return match unsafe { self.egl.GetError() } as u32 {
+                ffi::egl::BAD_ACCESS => Ok(PresentationTime::Unavailable),
+                err => Err(ContextError::OsError(
+                    format!("eglGetFrameTimestampsANDROID failed (eglGetError returned 0x{:x})",
+                            err)
+                )),
+            };
+        }
+
+        match values[0] {
+            v if v == ffi::egl::TIMESTAMP_PENDING_ANDROID as ffi::egl::types::EGLnsecsANDROID => {
+                Ok(PresentationTime::Pending)
+            },
+            v if v < 0 => Ok(PresentationTime::Unavailable),
+            v => {
+                let v = v as u64;
+                Ok(PresentationTime::Presented(Duration::new(v / 1_000_000_000,
+                                                             (v % 1_000_000_000) as u32)))
+            },
+        }
+    }
+
+    /// Sets the time at which the next frame queued on the surface should be presented, with
+    /// `EGL_ANDROID_presentation_time`.
+    pub fn set_presentation_time(&self, time: Duration) -> Result<(), ContextError> {
+        let extensions = unsafe {
+            self.egl.QueryString(self.display, ffi::egl::EXTENSIONS as i32)
+        };
+        if extensions.is_null() ||
+           !unsafe { CStr::from_ptr(extensions) }.to_string_lossy().split(' ')
+                .any(|e| e == "EGL_ANDROID_presentation_time") ||
+           !has_presentation_time_functions(&self.egl)
+        {
+            return Err(ContextError::FunctionUnavailable);
+        }
+        if self.surface.get() == ffi::egl::NO_SURFACE {
+            return Err(ContextError::ContextLost);
+        }
+
+        let nanos = time.as_secs() * 1_000_000_000 + time.subsec_nanos() as u64;
+        let ret = unsafe {
+            self.egl.PresentationTimeANDROID(self.display, self.surface.get(),
+                                             nanos as ffi::egl::types::EGLnsecsANDROID)
+        };
+
+        if ret == 0 {
+            Err(ContextError::OsError(
+                format!("eglPresentationTimeANDROID failed (eglGetError returned 0x{:x})",
+                        unsafe { self.egl.GetError() })
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns true if `create_native_fence_fd` is supported.
+    pub fn has_native_fence_sync(&self) -> bool {
+        let extensions = unsafe {
+            self.egl.QueryString(self.display, ffi::egl::EXTENSIONS as i32)
+        };
+        !extensions.is_null() &&
+            unsafe { CStr::from_ptr(extensions) }.to_string_lossy().split(' ')
+                .any(|e| e == "EGL_ANDROID_native_fence_sync") &&
+            has_native_fence_sync_functions(&self.egl)
+    }
+
+    /// Creates a fence that signals once the commands submitted so far complete, and returns
+    /// the file descriptor of its native fence with `EGL_ANDROID_native_fence_sync`. The caller
+    /// owns the file descriptor. The context must be current.
+    pub fn create_native_fence_fd(&self) -> Result<c_int, ContextError> {
+        if !self.has_native_fence_sync() {
+            return Err(ContextError::FunctionUnavailable);
+        }
+
+        let attribs = [ffi::egl::NONE as ffi::egl::types::EGLint];
+        let sync = unsafe {
+            self.egl.CreateSyncKHR(self.display, ffi::egl::SYNC_NATIVE_FENCE_ANDROID,
+                                   attribs.as_ptr())
+        };
+        if sync == ffi::egl::NO_SYNC_KHR {
+            return Err(ContextError::OsError(
+                format!("eglCreateSyncKHR failed (eglGetError returned 0x{:x})",
+                        unsafe { self.egl.GetError() })
+            ));
+        }
+
+        // the file descriptor only exists once the fence command has been flushed
+        let flush = self.get_proc_address("glFlush");
+        if !flush.is_null() {
+            unsafe {
+                let flush: extern "system" fn() = mem::transmute(flush);
+                flush();
+            }
+        }
+        let fd = unsafe {
+            let fd = self.egl.DupNativeFenceFDANDROID(self.display, sync);
+            self.egl.DestroySyncKHR(self.display, sync);
+            fd
+        };
+        if fd == ffi::egl::NO_NATIVE_FENCE_FD_ANDROID {
+            return Err(ContextError::OsError(
+                format!("eglDupNativeFenceFDANDROID failed (eglGetError returned 0x{:x})",
+                        unsafe { self.egl.GetError() })
+            ));
+        }
+        Ok(fd)
+    }
+
+    fn has_frame_timestamps(&self) -> bool {
+        let extensions = unsafe {
+            self.egl.QueryString(self.display, ffi::egl::EXTENSIONS as i32)
+        };
+        !extensions.is_null() &&
+            unsafe { CStr::from_ptr(extensions) }.to_string_lossy().split(' ')
+                .any(|e| e == "EGL_ANDROID_get_frame_timestamps") &&
+            has_frame_timestamps_functions(&self.egl)
+    }
+
     #[inline]
     pub fn get_api(&self) -> Api {
         self.api
@@ -327,11 +1179,22 @@ impl Context {
         self.pixel_format.clone()
     }
 
+    #[inline]
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        unsafe { query_pbuffer_limits(&self.egl, self.display, self.config_id) }
+    }
+
     #[inline]
     pub unsafe fn raw_handle(&self) -> ffi::egl::types::EGLContext {
         self.context
     }
 
+    /// Returns true if the context was built with `finish_surfaceless`.
+    #[inline]
+    pub fn is_surfaceless(&self) -> bool {
+        self.surface.get() == ffi::egl::NO_SURFACE
+    }
+
     // Handle Android Life Cycle.
     // Android has started the activity or sent it to foreground.
     // Create a new surface and attach it to the recreated ANativeWindow.
@@ -378,15 +1241,17 @@ impl Drop for Context {
         unsafe {
             // we don't call MakeCurrent(0, 0) because we are not sure that the context
             // is still the current one
-            self.egl.DestroyContext(self.display, self.context);
+            if !self.foreign {
+                self.egl.DestroyContext(self.display, self.context);
+            }
             self.egl.DestroySurface(self.display, self.surface.get());
-            self.egl.Terminate(self.display);
+            // the display is terminated by `self.terminate`
         }
     }
 }
 
 pub struct ContextPrototype<'a> {
-    opengl: &'a GlAttributes<&'a Context>,
+    opengl: GlAttributes<&'a Context>,
     egl: ffi::egl::Egl,
     display: ffi::egl::types::EGLDisplay,
     egl_version: (ffi::egl::types::EGLint, ffi::egl::types::EGLint),
@@ -395,6 +1260,11 @@ pub struct ContextPrototype<'a> {
     version: Option<(u8, u8)>,
     config_id: ffi::egl::types::EGLConfig,
     pixel_format: PixelFormat,
+    swap_behavior: SwapBehavior,
+    // True if the window surface should be created with `EGL_SINGLE_BUFFER`.
+    single_buffer: bool,
+    // Set when the display was initialized by a `Config`, which the context must share.
+    terminate: Option<Arc<TerminateGuard>>,
 }
 
 impl<'a> ContextPrototype<'a> {
@@ -410,30 +1280,59 @@ impl<'a> ContextPrototype<'a> {
     pub fn finish(self, native_window: ffi::EGLNativeWindowType)
                   -> Result<Context, CreationError>
     {
+        let mut base_attrs = if self.single_buffer {
+            vec![ffi::egl::RENDER_BUFFER as c_int, ffi::egl::SINGLE_BUFFER as c_int]
+        } else {
+            vec![]
+        };
+        base_attrs.extend(colorspace_attributes(&self.egl_version, &self.extensions,
+                                                self.opengl.colorspace)?);
+        base_attrs.extend(protected_content_attributes(&self.extensions,
+                                                       self.opengl.protected_content)?);
+        let attrs = surface_attributes(&self.opengl.raw_surface_attributes, &base_attrs);
         let surface = unsafe {
             let surface = self.egl.CreateWindowSurface(self.display, self.config_id, native_window,
-                                                       ptr::null());
+                                                       attrs.as_ptr());
             if surface.is_null() {
-                return Err(CreationError::OsError(format!("eglCreateWindowSurface failed")))
+                return Err(creation_error(&self.egl, "eglCreateWindowSurface",
+                                          self.opengl.protected_content))
             }
             surface
         };
 
+        if self.swap_behavior == SwapBehavior::Preserved {
+            let ret = unsafe {
+                self.egl.SurfaceAttrib(self.display, surface,
+                                       ffi::egl::SWAP_BEHAVIOR as ffi::egl::types::EGLint,
+                                       ffi::egl::BUFFER_PRESERVED as ffi::egl::types::EGLint)
+            };
+            if ret == 0 {
+                let err = egl_error(&self.egl, "eglSurfaceAttrib");
+                unsafe { self.egl.DestroySurface(self.display, surface); }
+                return Err(err);
+            }
+        }
+
         self.finish_impl(surface)
     }
 
     pub fn finish_pbuffer(self, dimensions: (u32, u32)) -> Result<Context, CreationError> {
-        let attrs = &[
+        let mut base_attrs = vec![
             ffi::egl::WIDTH as c_int, dimensions.0 as c_int,
             ffi::egl::HEIGHT as c_int, dimensions.1 as c_int,
-            ffi::egl::NONE as c_int,
         ];
+        base_attrs.extend(colorspace_attributes(&self.egl_version, &self.extensions,
+                                                self.opengl.colorspace)?);
+        base_attrs.extend(protected_content_attributes(&self.extensions,
+                                                       self.opengl.protected_content)?);
+        let attrs = surface_attributes(&self.opengl.raw_surface_attributes, &base_attrs);
 
         let surface = unsafe {
             let surface = self.egl.CreatePbufferSurface(self.display, self.config_id,
                                                         attrs.as_ptr());
             if surface.is_null() {
-                return Err(CreationError::OsError(format!("eglCreatePbufferSurface failed")))
+                return Err(creation_error(&self.egl, "eglCreatePbufferSurface",
+                                          self.opengl.protected_content))
             }
             surface
         };
@@ -441,25 +1340,60 @@ impl<'a> ContextPrototype<'a> {
         self.finish_impl(surface)
     }
 
+    /// Creates the context without any surface, with `EGL_KHR_surfaceless_context`. The context
+    /// can only render to framebuffer objects.
+    pub fn finish_surfaceless(self) -> Result<Context, CreationError> {
+        if self.extensions.iter().find(|s| s == &"EGL_KHR_surfaceless_context").is_none() {
+            return Err(CreationError::NotSupported("EGL_KHR_surfaceless_context not supported"));
+        }
+        self.finish_impl(ffi::egl::NO_SURFACE)
+    }
+
     fn finish_impl(self, surface: ffi::egl::types::EGLSurface)
                    -> Result<Context, CreationError>
     {
+        let share = match self.opengl.raw_sharing {
+            Some(RawContextHandle::Egl { context }) => context,
+            Some(_) => {
+                return Err(CreationError::NotSupported("the shared context is not an EGL context"));
+            },
+            None => ffi::egl::NO_CONTEXT,
+        };
+
+        // the context must be protected as well to render to a protected surface
+        let protected = protected_content_attributes(&self.extensions,
+                                                     self.opengl.protected_content)?;
+        let no_error = no_error_attribute(&self.extensions, &self.opengl)?;
+        let release_behavior = release_behavior_attribute(&self.extensions,
+                                                          self.opengl.release_behavior);
+        let priority = priority_attribute(&self.extensions, self.opengl.priority);
+        let raw_context_attributes: Vec<(i32, i32)> = protected.chunks(2)
+            .map(|pair| (pair[0], pair[1]))
+            .chain(no_error)
+            .chain(release_behavior)
+            .chain(priority)
+            .chain(self.opengl.raw_context_attributes.iter().cloned())
+            .collect();
+
         let context = unsafe {
             if let Some(version) = self.version {
                 create_context(&self.egl, self.display, &self.egl_version,
                                     &self.extensions, self.api, version, self.config_id,
-                                    self.opengl.debug, self.opengl.robustness)?
+                                    self.opengl.debug, self.opengl.robustness,
+                                    &raw_context_attributes, share)?
 
             } else if self.api == Api::OpenGlEs {
                 if let Ok(ctxt) = create_context(&self.egl, self.display, &self.egl_version,
                                                  &self.extensions, self.api, (2, 0), self.config_id,
-                                                 self.opengl.debug, self.opengl.robustness)
+                                                 self.opengl.debug, self.opengl.robustness,
+                                                 &raw_context_attributes, share)
                 {
                     ctxt
                 } else if let Ok(ctxt) = create_context(&self.egl, self.display, &self.egl_version,
                                                         &self.extensions, self.api, (1, 0),
                                                         self.config_id, self.opengl.debug,
-                                                        self.opengl.robustness)
+                                                        self.opengl.robustness,
+                                                        &raw_context_attributes, share)
                 {
                     ctxt
                 } else {
@@ -469,19 +1403,22 @@ impl<'a> ContextPrototype<'a> {
             } else {
                 if let Ok(ctxt) = create_context(&self.egl, self.display, &self.egl_version,
                                                  &self.extensions, self.api, (3, 2), self.config_id,
-                                                 self.opengl.debug, self.opengl.robustness)
+                                                 self.opengl.debug, self.opengl.robustness,
+                                                 &raw_context_attributes, share)
                 {
                     ctxt
                 } else if let Ok(ctxt) = create_context(&self.egl, self.display, &self.egl_version,
                                                         &self.extensions, self.api, (3, 1),
                                                         self.config_id, self.opengl.debug,
-                                                        self.opengl.robustness)
+                                                        self.opengl.robustness,
+                                                        &raw_context_attributes, share)
                 {
                     ctxt
                 } else if let Ok(ctxt) = create_context(&self.egl, self.display, &self.egl_version,
                                                         &self.extensions, self.api, (1, 0),
                                                         self.config_id, self.opengl.debug,
-                                                        self.opengl.robustness)
+                                                        self.opengl.robustness,
+                                                        &raw_context_attributes, share)
                 {
                     ctxt
                 } else {
@@ -490,6 +1427,15 @@ impl<'a> ContextPrototype<'a> {
             }
         };
 
+        let terminate = match self.terminate {
+            Some(terminate) => terminate,
+            None => Arc::new(TerminateGuard {
+                egl: self.egl.clone(),
+                display: self.display,
+                foreign: false,
+            }),
+        };
+
         Ok(Context {
             egl: self.egl,
             display: self.display,
@@ -497,134 +1443,852 @@ impl<'a> ContextPrototype<'a> {
             surface: Cell::new(surface),
             api: self.api,
             pixel_format: self.pixel_format,
-            config_id: self.config_id
+            config_id: self.config_id,
+            last_frame_id: Cell::new(None),
+            swap_interval: Cell::new(1),
+            terminate: terminate,
+            foreign: false,
         })
     }
 }
 
-unsafe fn choose_fbconfig(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
-                          egl_version: &(ffi::egl::types::EGLint, ffi::egl::types::EGLint),
-                          api: Api, version: Option<(u8, u8)>, reqs: &PixelFormatRequirements)
-                          -> Result<(ffi::egl::types::EGLConfig, PixelFormat), CreationError>
+/// Returns all the configs of `native_display` that match `reqs`, in the order given by
+/// `eglChooseConfig`.
+///
+/// Configs are looked up for desktop OpenGL if the implementation supports it, and for OpenGL ES
+/// 2 otherwise.
+pub fn find_configs(egl: ffi::egl::Egl, native_display: NativeDisplay,
+                    reqs: &PixelFormatRequirements) -> Result<Vec<Config>, CreationError>
 {
-    let descriptor = {
-        let mut out: Vec<c_int> = Vec::with_capacity(37);
-
-        if egl_version >= &(1, 2) {
-            out.push(ffi::egl::COLOR_BUFFER_TYPE as c_int);
-            out.push(ffi::egl::RGB_BUFFER as c_int);
-        }
-
-        out.push(ffi::egl::SURFACE_TYPE as c_int);
-        // TODO: Some versions of Mesa report a BAD_ATTRIBUTE error
-        // if we ask for PBUFFER_BIT as well as WINDOW_BIT
-        out.push((ffi::egl::WINDOW_BIT) as c_int);
-
-        match (api, version) {
-            (Api::OpenGlEs, Some((3, _))) => {
-                if egl_version < &(1, 3) { return Err(CreationError::NoAvailablePixelFormat); }
-                out.push(ffi::egl::RENDERABLE_TYPE as c_int);
-                out.push(ffi::egl::OPENGL_ES3_BIT as c_int);
-                out.push(ffi::egl::CONFORMANT as c_int);
-                out.push(ffi::egl::OPENGL_ES3_BIT as c_int);
-            },
-            (Api::OpenGlEs, Some((2, _))) => {
-                if egl_version < &(1, 3) { return Err(CreationError::NoAvailablePixelFormat); }
-                out.push(ffi::egl::RENDERABLE_TYPE as c_int);
-                out.push(ffi::egl::OPENGL_ES2_BIT as c_int);
-                out.push(ffi::egl::CONFORMANT as c_int);
-                out.push(ffi::egl::OPENGL_ES2_BIT as c_int);
-            },
-            (Api::OpenGlEs, Some((1, _))) => {
-                if egl_version >= &(1, 3) {
-                    out.push(ffi::egl::RENDERABLE_TYPE as c_int);
-                    out.push(ffi::egl::OPENGL_ES_BIT as c_int);
-                    out.push(ffi::egl::CONFORMANT as c_int);
-                    out.push(ffi::egl::OPENGL_ES_BIT as c_int);
-                }
-            },
-            (Api::OpenGlEs, _) => unimplemented!(),
-            (Api::OpenGl, _) => {
-                if egl_version < &(1, 3) { return Err(CreationError::NoAvailablePixelFormat); }
-                out.push(ffi::egl::RENDERABLE_TYPE as c_int);
-                out.push(ffi::egl::OPENGL_BIT as c_int);
-                out.push(ffi::egl::CONFORMANT as c_int);
-                out.push(ffi::egl::OPENGL_BIT as c_int);
-            },
-            (_, _) => unimplemented!(),
+    let display = get_native_display(&egl, native_display);
+
+    if display.is_null() {
+        return Err(CreationError::OsError("Could not create EGL display object".to_string()));
+    }
+
+    let (egl_version, extensions) = unsafe { initialize_display(&egl, display)? };
+    let terminate = Arc::new(TerminateGuard { egl: egl.clone(), display: display, foreign: false });
+
+    let (version, api) = unsafe { bind_api(&egl, egl_version, GlRequest::Latest)? };
+    let version = match api {
+        Api::OpenGlEs => version.or(Some((2, 0))),
+        _ => version,
+    };
+
+    let descriptor = match config_descriptor(&egl_version, &extensions, api, version, reqs) {
+        Ok(descriptor) => descriptor,
+        Err(CreationError::NoAvailablePixelFormat) => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    unsafe {
+        let config_ids = choose_all_configs(&egl, display, &descriptor)?;
+
+        config_ids.into_iter().map(|config_id| {
+            Ok(Config {
+                egl: egl.clone(),
+                display: display,
+                config_id: config_id,
+                pixel_format: config_pixel_format(&egl, display, config_id)?,
+                terminate: terminate.clone(),
+            })
+        }).collect()
+    }
+}
+
+/// Returns the vendor of `native_display`, and the name of its driver with
+/// `EGL_MESA_query_driver`.
+pub fn device_info(egl: ffi::egl::Egl, native_display: NativeDisplay)
+                   -> Result<DeviceInfo, CreationError>
+{
+    let display = get_native_display(&egl, native_display);
+
+    if display.is_null() {
+        return Err(CreationError::OsError("Could not create EGL display object".to_string()));
+    }
+
+    // `eglQueryString` fails on displays that aren't initialized, and the displays that already
+    // are may be used by contexts, so they are left initialized
+    let initialized = unsafe { !egl.QueryString(display, ffi::egl::VENDOR as i32).is_null() };
+    let (_, extensions) = unsafe { initialize_display(&egl, display)? };
+    let _terminate = TerminateGuard { egl: egl.clone(), display: display, foreign: initialized };
+
+    let to_string = |string: *const c_char| if string.is_null() {
+        None
+    } else {
+        Some(unsafe { CStr::from_ptr(string) }.to_string_lossy().into_owned())
+    };
+
+    let vendor = to_string(unsafe { egl.QueryString(display, ffi::egl::VENDOR as i32) });
+    let driver_name = if extensions.iter().any(|s| s == "EGL_MESA_query_driver") {
+        to_string(unsafe { egl.GetDisplayDriverName(display) })
+    } else {
+        None
+    };
+
+    if driver_name.is_none() {
+        return Err(CreationError::NotSupported("EGL_MESA_query_driver is not supported"));
+    }
+
+    Ok(DeviceInfo {
+        vendor: vendor,
+        driver_name: driver_name,
+        .. DeviceInfo::default()
+    })
+}
+
+/// Finds the highest versions of `api` for which a context can be created on `native_display`,
+/// by creating and destroying contexts.
+pub fn probe_versions(egl: ffi::egl::Egl, native_display: NativeDisplay, api: Api)
+                      -> Result<SupportedVersions, CreationError>
+{
+    let display = get_native_display(&egl, native_display);
+
+    if display.is_null() {
+        return Err(CreationError::OsError("Could not create EGL display object".to_string()));
+    }
+
+    // like in `device_info`, the displays that are already initialized are left initialized
+    let initialized = unsafe { !egl.QueryString(display, ffi::egl::VENDOR as i32).is_null() };
+    let (egl_version, extensions) = unsafe { initialize_display(&egl, display)? };
+    let _terminate = TerminateGuard { egl: egl.clone(), display: display, foreign: initialized };
+
+    // without it, only the major version of OpenGL ES contexts can be requested
+    if egl_version < (1, 5) && !extensions.iter().any(|s| s == "EGL_KHR_create_context") {
+        return Err(CreationError::NotSupported("EGL_KHR_create_context is not supported"));
+    }
+
+    let egl_api = match api {
+        Api::OpenGl => ffi::egl::OPENGL_API,
+        Api::OpenGlEs => ffi::egl::OPENGL_ES_API,
+        Api::WebGl => return Err(CreationError::NotSupported("WebGL contexts can't be probed")),
+    };
+    if unsafe { egl.BindAPI(egl_api) } == 0 {
+        return Err(egl_error(&egl, "eglBindAPI"));
+    }
+
+    let reqs = probe_requirements();
+    find_max_versions(api, |version, profile| unsafe {
+        // the renderable type of the config depends on the major version with OpenGL ES
+        let descriptor = match config_descriptor(&egl_version, &extensions, api, Some(version),
+                                                 &reqs)
+        {
+            Ok(descriptor) => descriptor,
+            Err(_) => return false,
+        };
+        let config_id = match choose_all_configs(&egl, display, &descriptor) {
+            Ok(ref config_ids) if !config_ids.is_empty() => config_ids[0],
+            _ => return false,
         };
 
-        if let Some(hardware_accelerated) = reqs.hardware_accelerated {
-            out.push(ffi::egl::CONFIG_CAVEAT as c_int);
-            out.push(if hardware_accelerated {
-                ffi::egl::NONE as c_int
-            } else {
-                ffi::egl::SLOW_CONFIG as c_int
-            });
-        }
+        let profile_attributes = match profile {
+            Some(GlProfile::Core) => vec![
+                (ffi::egl::CONTEXT_OPENGL_PROFILE_MASK as i32,
+                 ffi::egl::CONTEXT_OPENGL_CORE_PROFILE_BIT as i32),
+            ],
+            Some(GlProfile::Compatibility) => vec![
+                (ffi::egl::CONTEXT_OPENGL_PROFILE_MASK as i32,
+                 ffi::egl::CONTEXT_OPENGL_COMPATIBILITY_PROFILE_BIT as i32),
+            ],
+            None => Vec::new(),
+        };
 
-        if let Some(color) = reqs.color_bits {
-            out.push(ffi::egl::RED_SIZE as c_int);
-            out.push((color / 3) as c_int);
-            out.push(ffi::egl::GREEN_SIZE as c_int);
-            out.push((color / 3 + if color % 3 != 0 { 1 } else { 0 }) as c_int);
-            out.push(ffi::egl::BLUE_SIZE as c_int);
-            out.push((color / 3 + if color % 3 == 2 { 1 } else { 0 }) as c_int);
+        match create_context(&egl, display, &egl_version, &extensions, api, version, config_id,
+                             false, Robustness::NotRobust, &profile_attributes,
+                             ffi::egl::NO_CONTEXT)
+        {
+            Ok(context) => {
+                egl.DestroyContext(display, context);
+                true
+            },
+            Err(_) => false,
         }
+    })
+}
 
-        if let Some(alpha) = reqs.alpha_bits {
-            out.push(ffi::egl::ALPHA_SIZE as c_int);
-            out.push(alpha as c_int);
-        }
+/// Initializes `display`, and returns its version and its list of extensions.
+unsafe fn initialize_display(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay)
+                             -> Result<((ffi::egl::types::EGLint, ffi::egl::types::EGLint),
+                                        Vec<String>), CreationError>
+{
+    let egl_version = {
+        let mut major: ffi::egl::types::EGLint = mem::uninitialized();
+        let mut minor: ffi::egl::types::EGLint = mem::uninitialized();
 
-        if let Some(depth) = reqs.depth_bits {
-            out.push(ffi::egl::DEPTH_SIZE as c_int);
-            out.push(depth as c_int);
+        if egl.Initialize(display, &mut major, &mut minor) == 0 {
+            return Err(egl_error(egl, "eglInitialize"))
         }
 
-        if let Some(stencil) = reqs.stencil_bits {
-            out.push(ffi::egl::STENCIL_SIZE as c_int);
-            out.push(stencil as c_int);
-        }
+        (major, minor)
+    };
 
-        if let Some(true) = reqs.double_buffer {
-            return Err(CreationError::NoAvailablePixelFormat);
-        }
+    // the list of extensions supported by the client once initialized is different from the
+    // list of extensions obtained earlier
+    let extensions = if egl_version >= (1, 2) {
+        let p = CStr::from_ptr(egl.QueryString(display, ffi::egl::EXTENSIONS as i32));
+        let list = String::from_utf8(p.to_bytes().to_vec()).unwrap_or_else(|_| format!(""));
+        list.split(' ').map(|e| e.to_string()).collect::<Vec<_>>()
+
+    } else {
+        vec![]
+    };
+
+    Ok((egl_version, extensions))
+}
+
+/// Binds the API matching `request`, and returns the version to create the context with.
+unsafe fn bind_api(egl: &ffi::egl::Egl,
+                   egl_version: (ffi::egl::types::EGLint, ffi::egl::types::EGLint),
+                   request: GlRequest) -> Result<(Option<(u8, u8)>, Api), CreationError>
+{
+    Ok(match request {
+        GlRequest::Latest => {
+            if egl_version >= (1, 4) {
+                if egl.BindAPI(ffi::egl::OPENGL_API) != 0 {
+                    (None, Api::OpenGl)
+                } else if egl.BindAPI(ffi::egl::OPENGL_ES_API) != 0 {
+                    (None, Api::OpenGlEs)
+                } else {
+                    return Err(CreationError::OpenGlVersionNotSupported);
+                }
+            } else {
+                (None, Api::OpenGlEs)
+            }
+        },
+        GlRequest::Specific(Api::OpenGlEs, version) => {
+            if egl_version >= (1, 2) {
+                if egl.BindAPI(ffi::egl::OPENGL_ES_API) == 0 {
+                    return Err(CreationError::OpenGlVersionNotSupported);
+                }
+            }
+            (Some(version), Api::OpenGlEs)
+        },
+        GlRequest::Specific(Api::OpenGl, version) => {
+            if egl_version < (1, 4) {
+                return Err(CreationError::OpenGlVersionNotSupported);
+            }
+            if egl.BindAPI(ffi::egl::OPENGL_API) == 0 {
+                return Err(CreationError::OpenGlVersionNotSupported);
+            }
+            (Some(version), Api::OpenGl)
+        },
+        GlRequest::Specific(_, _) => return Err(CreationError::OpenGlVersionNotSupported),
+        GlRequest::GlThenGles { opengles_version, opengl_version } => {
+            if egl_version >= (1, 4) {
+                if egl.BindAPI(ffi::egl::OPENGL_API) != 0 {
+                    (Some(opengl_version), Api::OpenGl)
+                } else if egl.BindAPI(ffi::egl::OPENGL_ES_API) != 0 {
+                    (Some(opengles_version), Api::OpenGlEs)
+                } else {
+                    return Err(CreationError::OpenGlVersionNotSupported);
+                }
+            } else {
+                (Some(opengles_version), Api::OpenGlEs)
+            }
+        },
+        // the list is expanded into `GlRequest::Specific` requests by `create_with_versions`
+        GlRequest::PreferredList(_) => return Err(CreationError::OpenGlVersionNotSupported),
+    })
+}
 
-        if let Some(multisampling) = reqs.multisampling {
-            out.push(ffi::egl::SAMPLES as c_int);
-            out.push(multisampling as c_int);
+unsafe fn choose_fbconfig(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                          egl_version: &(ffi::egl::types::EGLint, ffi::egl::types::EGLint),
+                          extensions: &[String], api: Api, version: Option<(u8, u8)>,
+                          reqs: &PixelFormatRequirements)
+                          -> Result<(ffi::egl::types::EGLConfig, PixelFormat), CreationError>
+{
+    let descriptor = config_descriptor(egl_version, extensions, api, version, reqs)?;
+
+    let configs = config_cache::get_or_enumerate(display as usize, 0, &descriptor, || {
+        choose_all_configs(egl, display, &descriptor)?.into_iter().map(|config_id| {
+            Ok((config_id as usize, config_pixel_format(egl, display, config_id)?))
+        }).collect()
+    })?;
+    let (mut config_ids, mut formats): (Vec<_>, Vec<_>) = configs.into_iter()
+        .map(|(config_id, format)| (config_id as ffi::egl::types::EGLConfig, format))
+        .unzip();
+
+    if reqs.needs_all_formats() {
+        // `eglChooseConfig` ignores `EGL_NATIVE_VISUAL_ID`, so the configs are filtered here
+        if let Some(native_visual_id) = reqs.native_visual_id {
+            let matching: Vec<bool> = formats.iter().map(|format| match format.native_id {
+                Some(NativeConfigId::Egl { native_visual_id: id, .. }) => {
+                    id == Some(native_visual_id)
+                },
+                _ => false,
+            }).collect();
+            let mut matching_iter = matching.iter();
+            config_ids.retain(|_| *matching_iter.next().unwrap());
+            let mut matching_iter = matching.iter();
+            formats.retain(|_| *matching_iter.next().unwrap());
         }
 
-        if reqs.stereoscopy {
-            return Err(CreationError::NoAvailablePixelFormat);
+        return match reqs.select_format(&formats) {
+            Some(index) => Ok((config_ids[index], formats.swap_remove(index))),
+            None if formats.is_empty() => Err(no_matching_config(egl, display, reqs)),
+            None => Err(CreationError::NoAvailablePixelFormat),
+        };
+    }
+
+    // the implementation returns the configs sorted by preference
+    if config_ids.is_empty() {
+        return Err(no_matching_config(egl, display, reqs));
+    }
+
+    Ok((config_ids[0], formats.swap_remove(0)))
+}
+
+/// Returns an `OsError` saying that `function` failed, along with the code returned by
+/// `eglGetError`.
+fn egl_error(egl: &ffi::egl::Egl, function: &str) -> CreationError {
+    let code = unsafe { egl.GetError() };
+    CreationError::OsError(format!("{} failed (eglGetError returned 0x{:x})", function, code))
+}
+
+/// Returns the error of a function that failed while validating a context, which is
+/// `ContextError::ContextLost` if the context was lost.
+fn validation_error(egl: &ffi::egl::Egl, function: &str) -> ContextError {
+    match unsafe { egl.GetError() } as u32 {
+        ffi::egl::CONTEXT_LOST => ContextError::ContextLost,
+        ffi::egl::BAD_NATIVE_WINDOW | ffi::egl::BAD_SURFACE => {
+            ContextError::OsError(format!("{} failed: the window of the context is no longer \
+                                           valid", function))
+        },
+        code => ContextError::OsError(format!("{} failed (eglGetError returned 0x{:x})",
+                                              function, code)),
+    }
+}
+
+/// Like `egl_error`, but reports the errors returned when the implementation can't allocate the
+/// secure memory of a protected surface as `ProtectedContentNotSupported`.
+fn creation_error(egl: &ffi::egl::Egl, function: &str, protected_content: bool)
+                  -> CreationError
+{
+    let code = unsafe { egl.GetError() };
+    match code as u32 {
+        ffi::egl::BAD_ACCESS | ffi::egl::BAD_ALLOC if protected_content => {
+            CreationError::ProtectedContentNotSupported
+        },
+        _ => CreationError::OsError(format!("{} failed (eglGetError returned 0x{:x})",
+                                            function, code)),
+    }
+}
+
+/// Builds the attributes passed to `eglCreate*Surface`, made of `attrs` followed by the raw
+/// attributes requested by the user.
+// The bindings are linked statically on Android, so the functions are always available there.
+#[cfg(not(target_os = "android"))]
+fn has_partial_update_functions(egl: &ffi::egl::Egl) -> bool {
+    egl.SetDamageRegionKHR.is_loaded()
+}
+
+#[cfg(target_os = "android")]
+fn has_partial_update_functions(_: &ffi::egl::Egl) -> bool {
+    true
+}
+
+#[cfg(not(target_os = "android"))]
+fn has_frame_timestamps_functions(egl: &ffi::egl::Egl) -> bool {
+    egl.GetNextFrameIdANDROID.is_loaded() && egl.GetFrameTimestampsANDROID.is_loaded()
+}
+
+#[cfg(target_os = "android")]
+fn has_frame_timestamps_functions(_: &ffi::egl::Egl) -> bool {
+    true
+}
+
+#[cfg(not(target_os = "android"))]
+fn has_presentation_time_functions(egl: &ffi::egl::Egl) -> bool {
+    egl.PresentationTimeANDROID.is_loaded()
+}
+
+#[cfg(target_os = "android")]
+fn has_presentation_time_functions(_: &ffi::egl::Egl) -> bool {
+    true
+}
+
+#[cfg(not(target_os = "android"))]
+fn has_native_fence_sync_functions(egl: &ffi::egl::Egl) -> bool {
+    egl.CreateSyncKHR.is_loaded() && egl.DestroySyncKHR.is_loaded() &&
+        egl.DupNativeFenceFDANDROID.is_loaded()
+}
+
+#[cfg(target_os = "android")]
+fn has_native_fence_sync_functions(_: &ffi::egl::Egl) -> bool {
+    true
+}
+
+/// Returns the `EGL_SWAP_BEHAVIOR` of `surface`.
+unsafe fn query_swap_behavior(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                               surface: ffi::egl::types::EGLSurface)
+                               -> Result<SwapBehavior, ContextError>
+{
+    let mut value = 0;
+    if egl.QuerySurface(display, surface, ffi::egl::SWAP_BEHAVIOR as ffi::egl::types::EGLint,
+                        &mut value) == 0
+    {
+        return Err(ContextError::OsError(
+            format!("eglQuerySurface failed (eglGetError returned 0x{:x})", egl.GetError())
+        ));
+    }
+
+    if value == ffi::egl::BUFFER_PRESERVED as ffi::egl::types::EGLint {
+        Ok(SwapBehavior::Preserved)
+    } else {
+        Ok(SwapBehavior::Destroyed)
+    }
+}
+
+/// Returns the `EGL_GL_COLORSPACE` of `surface`. Surfaces of implementations that don't support
+/// `EGL_KHR_gl_colorspace` are linear.
+unsafe fn query_colorspace(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                           surface: ffi::egl::types::EGLSurface)
+                           -> Result<Colorspace, ContextError>
+{
+    let mut value = 0;
+    if egl.QuerySurface(display, surface, ffi::egl::GL_COLORSPACE as ffi::egl::types::EGLint,
+                        &mut value) == 0
+    {
+        let error = egl.GetError();
+        if error == ffi::egl::BAD_ATTRIBUTE as ffi::egl::types::EGLint {
+            return Ok(Colorspace::Linear);
         }
+        return Err(ContextError::OsError(
+            format!("eglQuerySurface failed (eglGetError returned 0x{:x})", error)
+        ));
+    }
 
-        // FIXME: srgb is not taken into account
+    match value as ffi::egl::types::EGLenum {
+        ffi::egl::GL_COLORSPACE_LINEAR => Ok(Colorspace::Linear),
+        ffi::egl::GL_COLORSPACE_SRGB => Ok(Colorspace::Srgb),
+        ffi::egl::GL_COLORSPACE_DISPLAY_P3_EXT => Ok(Colorspace::DisplayP3),
+        ffi::egl::GL_COLORSPACE_DISPLAY_P3_LINEAR_EXT => Ok(Colorspace::DisplayP3Linear),
+        ffi::egl::GL_COLORSPACE_SCRGB_EXT => Ok(Colorspace::ScRgb),
+        ffi::egl::GL_COLORSPACE_SCRGB_LINEAR_EXT => Ok(Colorspace::ScRgbLinear),
+        value => Err(ContextError::OsError(format!("unknown EGL colorspace 0x{:x}", value))),
+    }
+}
 
-        match reqs.release_behavior {
-            ReleaseBehavior::Flush => (),
-            ReleaseBehavior::None => {
-                // TODO: with EGL you need to manually set the behavior
-                unimplemented!()
-            },
+/// Returns the `EGL_GL_COLORSPACE` attribute requesting `colorspace` for a new surface, or
+/// nothing if `colorspace` is `None`.
+fn colorspace_attributes(egl_version: &(ffi::egl::types::EGLint, ffi::egl::types::EGLint),
+                         extensions: &[String], colorspace: Option<Colorspace>)
+                         -> Result<Vec<c_int>, CreationError>
+{
+    let colorspace = match colorspace {
+        Some(colorspace) => colorspace,
+        None => return Ok(vec![]),
+    };
+
+    let has_extension = |e: &str| extensions.iter().find(|s| s == &e).is_some();
+    if egl_version < &(1, 5) && !has_extension("EGL_KHR_gl_colorspace") {
+        return Err(CreationError::NotSupported("EGL_KHR_gl_colorspace not supported"));
+    }
+
+    let (value, extension) = match colorspace {
+        Colorspace::Linear => (ffi::egl::GL_COLORSPACE_LINEAR, None),
+        Colorspace::Srgb => (ffi::egl::GL_COLORSPACE_SRGB, None),
+        Colorspace::DisplayP3 => (ffi::egl::GL_COLORSPACE_DISPLAY_P3_EXT,
+                                  Some("EGL_EXT_gl_colorspace_display_p3")),
+        Colorspace::DisplayP3Linear => (ffi::egl::GL_COLORSPACE_DISPLAY_P3_LINEAR_EXT,
+                                        Some("EGL_EXT_gl_colorspace_display_p3_linear")),
+        Colorspace::ScRgb => (ffi::egl::GL_COLORSPACE_SCRGB_EXT,
+                              Some("EGL_EXT_gl_colorspace_scrgb")),
+        Colorspace::ScRgbLinear => (ffi::egl::GL_COLORSPACE_SCRGB_LINEAR_EXT,
+                                    Some("EGL_EXT_gl_colorspace_scrgb_linear")),
+    };
+    if let Some(extension) = extension {
+        if !has_extension(extension) {
+            return Err(CreationError::NotSupported("the requested colorspace is not supported"));
         }
+    }
+
+    Ok(vec![ffi::egl::GL_COLORSPACE as c_int, value as c_int])
+}
 
-        out.push(ffi::egl::NONE as c_int);
-        out
+/// Returns the attribute that disables the error checking of a context if `no_error` is set in
+/// `opengl`, or an error if `EGL_KHR_create_context_no_error` isn't supported.
+fn no_error_attribute<S>(extensions: &[String], opengl: &GlAttributes<S>)
+                          -> Result<Option<(i32, i32)>, CreationError>
+{
+    if !opengl.no_error {
+        return Ok(None);
+    }
+    opengl.check_no_error()?;
+    if !extensions.iter().any(|s| s == "EGL_KHR_create_context_no_error") {
+        return Err(CreationError::NoErrorNotSupported);
+    }
+    Ok(Some((ffi::egl::CONTEXT_OPENGL_NO_ERROR_KHR as i32, ffi::egl::TRUE as i32)))
+}
+
+/// Returns the attribute that stops the context from being flushed when it is released, if
+/// `behavior` is `None` and `EGL_KHR_context_flush_control` is supported.
+fn release_behavior_attribute(extensions: &[String], behavior: ReleaseBehavior)
+                              -> Option<(i32, i32)>
+{
+    let supported = extensions.iter().any(|s| s == "EGL_KHR_context_flush_control");
+    match behavior {
+        ReleaseBehavior::None if supported => {
+            Some((ffi::egl::CONTEXT_RELEASE_BEHAVIOR_KHR as i32,
+                  ffi::egl::CONTEXT_RELEASE_BEHAVIOR_NONE_KHR as i32))
+        },
+        _ => None,
+    }
+}
+
+/// Returns the attribute requesting `priority` with `EGL_IMG_context_priority`, if it is
+/// supported.
+fn priority_attribute(extensions: &[String], priority: Option<ContextPriority>)
+                      -> Option<(i32, i32)>
+{
+    let has_extension = |name: &str| extensions.iter().any(|s| s == name);
+    if !has_extension("EGL_IMG_context_priority") {
+        return None;
+    }
+
+    let level = match priority? {
+        ContextPriority::Low => ffi::egl::CONTEXT_PRIORITY_LOW_IMG,
+        ContextPriority::Medium => ffi::egl::CONTEXT_PRIORITY_MEDIUM_IMG,
+        ContextPriority::High => ffi::egl::CONTEXT_PRIORITY_HIGH_IMG,
+        ContextPriority::Realtime if has_extension("EGL_NV_context_priority_realtime") => {
+            ffi::egl::CONTEXT_PRIORITY_REALTIME_NV
+        },
+        ContextPriority::Realtime => ffi::egl::CONTEXT_PRIORITY_HIGH_IMG,
     };
+    Some((ffi::egl::CONTEXT_PRIORITY_LEVEL_IMG as i32, level as i32))
+}
 
-    // calling `eglChooseConfig`
-    let mut config_id = mem::uninitialized();
-    let mut num_configs = mem::uninitialized();
-    if egl.ChooseConfig(display, descriptor.as_ptr(), &mut config_id, 1, &mut num_configs) == 0 {
-        return Err(CreationError::OsError(format!("eglChooseConfig failed")));
+/// Returns the attributes that make a context or a surface protected if `protected_content` is
+/// true, or an error if `EGL_EXT_protected_content` isn't supported.
+fn protected_content_attributes(extensions: &[String], protected_content: bool)
+                                -> Result<Vec<c_int>, CreationError>
+{
+    if !protected_content {
+        return Ok(Vec::new());
+    }
+    if extensions.iter().find(|s| s == &"EGL_EXT_protected_content").is_none() {
+        return Err(CreationError::ProtectedContentNotSupported);
     }
-    if num_configs == 0 {
+    Ok(vec![ffi::egl::PROTECTED_CONTENT_EXT as c_int, ffi::egl::TRUE as c_int])
+}
+
+/// Sets the `EGL_SWAP_BEHAVIOR` of `surface`, which must have been created from a config with
+/// `EGL_SWAP_BEHAVIOR_PRESERVED_BIT` to be preserved.
+unsafe fn set_swap_behavior(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                            surface: ffi::egl::types::EGLSurface, behavior: SwapBehavior)
+                            -> Result<(), ContextError>
+{
+    let value = match behavior {
+        SwapBehavior::Destroyed => ffi::egl::BUFFER_DESTROYED,
+        SwapBehavior::Preserved => ffi::egl::BUFFER_PRESERVED,
+    };
+
+    if egl.SurfaceAttrib(display, surface, ffi::egl::SWAP_BEHAVIOR as ffi::egl::types::EGLint,
+                         value as ffi::egl::types::EGLint) == 0
+    {
+        return Err(ContextError::OsError(
+            format!("eglSurfaceAttrib failed (eglGetError returned 0x{:x})", egl.GetError())
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns the `EGL_RENDER_BUFFER` requested for `surface`.
+unsafe fn query_render_buffer(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                              surface: ffi::egl::types::EGLSurface)
+                              -> Result<RenderBuffer, ContextError>
+{
+    let mut value = 0;
+    if egl.QuerySurface(display, surface, ffi::egl::RENDER_BUFFER as ffi::egl::types::EGLint,
+                        &mut value) == 0
+    {
+        return Err(ContextError::OsError(
+            format!("eglQuerySurface failed (eglGetError returned 0x{:x})", egl.GetError())
+        ));
+    }
+
+    if value == ffi::egl::SINGLE_BUFFER as ffi::egl::types::EGLint {
+        Ok(RenderBuffer::Single)
+    } else {
+        Ok(RenderBuffer::Back)
+    }
+}
+
+/// Sets the `EGL_RENDER_BUFFER` of `surface`, which must have been created from a config with
+/// `EGL_MUTABLE_RENDER_BUFFER_BIT_KHR` to change.
+unsafe fn set_render_buffer(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                            surface: ffi::egl::types::EGLSurface, render_buffer: RenderBuffer)
+                            -> Result<(), ContextError>
+{
+    if query_render_buffer(egl, display, surface)? == render_buffer {
+        return Ok(());
+    }
+
+    let value = match render_buffer {
+        RenderBuffer::Back => ffi::egl::BACK_BUFFER,
+        RenderBuffer::Single => ffi::egl::SINGLE_BUFFER,
+    };
+
+    if egl.SurfaceAttrib(display, surface, ffi::egl::RENDER_BUFFER as ffi::egl::types::EGLint,
+                         value as ffi::egl::types::EGLint) == 0
+    {
+        return match egl.GetError() as u32 {
+            // the config doesn't have `EGL_MUTABLE_RENDER_BUFFER_BIT_KHR`
+            ffi::egl::BAD_MATCH | ffi::egl::BAD_ATTRIBUTE => Err(ContextError::FunctionUnavailable),
+            err => Err(ContextError::OsError(
+                format!("eglSurfaceAttrib failed (eglGetError returned 0x{:x})", err)
+            )),
+        };
+    }
+
+    Ok(())
+}
+
+/// Returns the `EGL_MULTISAMPLE_RESOLVE` of `surface`.
+unsafe fn query_multisample_resolve(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                                    surface: ffi::egl::types::EGLSurface)
+                                    -> Result<MultisampleResolve, ContextError>
+{
+    let mut value = 0;
+    if egl.QuerySurface(display, surface,
+                        ffi::egl::MULTISAMPLE_RESOLVE as ffi::egl::types::EGLint,
+                        &mut value) == 0
+    {
+        return Err(ContextError::OsError(
+            format!("eglQuerySurface failed (eglGetError returned 0x{:x})", egl.GetError())
+        ));
+    }
+
+    if value == ffi::egl::MULTISAMPLE_RESOLVE_BOX as ffi::egl::types::EGLint {
+        Ok(MultisampleResolve::Box)
+    } else {
+        Ok(MultisampleResolve::Default)
+    }
+}
+
+/// Sets the `EGL_MULTISAMPLE_RESOLVE` of `surface`, which must have been created from a config
+/// with `EGL_MULTISAMPLE_RESOLVE_BOX_BIT` to use the box filter.
+unsafe fn set_multisample_resolve(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                                  surface: ffi::egl::types::EGLSurface,
+                                  resolve: MultisampleResolve)
+                                  -> Result<(), ContextError>
+{
+    let value = match resolve {
+        MultisampleResolve::Default => ffi::egl::MULTISAMPLE_RESOLVE_DEFAULT,
+        MultisampleResolve::Box => ffi::egl::MULTISAMPLE_RESOLVE_BOX,
+    };
+
+    if egl.SurfaceAttrib(display, surface,
+                         ffi::egl::MULTISAMPLE_RESOLVE as ffi::egl::types::EGLint,
+                         value as ffi::egl::types::EGLint) == 0
+    {
+        return match egl.GetError() as u32 {
+            // the config doesn't have `EGL_MULTISAMPLE_RESOLVE_BOX_BIT`
+            ffi::egl::BAD_MATCH => Err(ContextError::FunctionUnavailable),
+            err => Err(ContextError::OsError(
+                format!("eglSurfaceAttrib failed (eglGetError returned 0x{:x})", err)
+            )),
+        };
+    }
+
+    Ok(())
+}
+
+fn surface_attributes(raw_attributes: &[(i32, i32)], attrs: &[c_int]) -> Vec<c_int> {
+    let mut out = attrs.to_vec();
+    for &(attrib, value) in raw_attributes {
+        out.push(attrib as c_int);
+        out.push(value as c_int);
+    }
+    out.push(ffi::egl::NONE as c_int);
+    out
+}
+
+/// Returns the error to report when no config of `display` matches `reqs`, along with the
+/// nearest ones among all the configs of the display.
+unsafe fn no_matching_config(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                             reqs: &PixelFormatRequirements) -> CreationError
+{
+    let mut num_configs = 0;
+    if egl.GetConfigs(display, ptr::null_mut(), 0, &mut num_configs) == 0 {
+        return CreationError::NoAvailablePixelFormat;
+    }
+    let mut config_ids = Vec::with_capacity(num_configs as usize);
+    if egl.GetConfigs(display, config_ids.as_mut_ptr(), num_configs, &mut num_configs) == 0 {
+        return CreationError::NoAvailablePixelFormat;
+    }
+    config_ids.set_len(num_configs as usize);
+
+    let formats = config_ids.iter()
+        .filter_map(|&config_id| config_pixel_format(egl, display, config_id).ok())
+        .collect();
+    reqs.no_matching_format(formats)
+}
+
+/// Returns every config matching `descriptor`, in the order of preference of the driver.
+unsafe fn choose_all_configs(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                             descriptor: &[c_int])
+                             -> Result<Vec<ffi::egl::types::EGLConfig>, CreationError>
+{
+    let mut num_configs = 0;
+    if egl.ChooseConfig(display, descriptor.as_ptr(), ptr::null_mut(), 0, &mut num_configs) == 0 {
+        return Err(egl_error(egl, "eglChooseConfig"));
+    }
+
+    let mut config_ids = Vec::with_capacity(num_configs as usize);
+    if egl.ChooseConfig(display, descriptor.as_ptr(), config_ids.as_mut_ptr(), num_configs,
+                        &mut num_configs) == 0
+    {
+        return Err(egl_error(egl, "eglChooseConfig"));
+    }
+    config_ids.set_len(num_configs as usize);
+
+    Ok(config_ids)
+}
+
+/// Builds the attributes passed to `eglChooseConfig` for `reqs`.
+fn config_descriptor(egl_version: &(ffi::egl::types::EGLint, ffi::egl::types::EGLint),
+                     extensions: &[String], api: Api, version: Option<(u8, u8)>,
+                     reqs: &PixelFormatRequirements)
+                     -> Result<Vec<c_int>, CreationError>
+{
+    let mut out: Vec<c_int> = Vec::with_capacity(37);
+
+    if egl_version >= &(1, 2) {
+        out.push(ffi::egl::COLOR_BUFFER_TYPE as c_int);
+        out.push(ffi::egl::RGB_BUFFER as c_int);
+    }
+
+    out.push(ffi::egl::SURFACE_TYPE as c_int);
+    // TODO: Some versions of Mesa report a BAD_ATTRIBUTE error
+    // if we ask for PBUFFER_BIT as well as WINDOW_BIT
+    let mut surface_type = ffi::egl::WINDOW_BIT;
+    if reqs.swap_behavior == SwapBehavior::Preserved {
+        surface_type |= ffi::egl::SWAP_BEHAVIOR_PRESERVED_BIT;
+    }
+    if reqs.mutable_render_buffer {
+        surface_type |= ffi::egl::MUTABLE_RENDER_BUFFER_BIT_KHR;
+    }
+    if reqs.multisample_resolve_box {
+        if egl_version < &(1, 4) { return Err(CreationError::NoAvailablePixelFormat); }
+        surface_type |= ffi::egl::MULTISAMPLE_RESOLVE_BOX_BIT;
+    }
+    out.push(surface_type as c_int);
+
+    let api_bit = match (api, version) {
+        (Api::OpenGlEs, Some((3, _))) => {
+            if egl_version < &(1, 3) { return Err(CreationError::NoAvailablePixelFormat); }
+            Some(ffi::egl::OPENGL_ES3_BIT)
+        },
+        (Api::OpenGlEs, Some((2, _))) => {
+            if egl_version < &(1, 3) { return Err(CreationError::NoAvailablePixelFormat); }
+            Some(ffi::egl::OPENGL_ES2_BIT)
+        },
+        (Api::OpenGlEs, Some((1, _))) => {
+            if egl_version >= &(1, 3) { Some(ffi::egl::OPENGL_ES_BIT) } else { None }
+        },
+        (Api::OpenGlEs, _) => unimplemented!(),
+        (Api::OpenGl, _) => {
+            if egl_version < &(1, 3) { return Err(CreationError::NoAvailablePixelFormat); }
+            Some(ffi::egl::OPENGL_BIT)
+        },
+        (_, _) => unimplemented!(),
+    };
+
+    if let Some(api_bit) = api_bit {
+        out.push(ffi::egl::RENDERABLE_TYPE as c_int);
+        out.push(api_bit as c_int);
+        // `Prefer` first tries the conformant configs, and `DontCare` after falling back
+        if reqs.conformance != Conformance::DontCare {
+            out.push(ffi::egl::CONFORMANT as c_int);
+            out.push(api_bit as c_int);
+        }
+    }
+
+    match (reqs.reject_caveats, reqs.hardware_accelerated.required()) {
+        (true, Some(false)) => return Err(CreationError::NoAvailablePixelFormat),
+        (true, _) | (false, Some(true)) => {
+            out.push(ffi::egl::CONFIG_CAVEAT as c_int);
+            out.push(ffi::egl::NONE as c_int);
+        },
+        (false, Some(false)) => {
+            out.push(ffi::egl::CONFIG_CAVEAT as c_int);
+            out.push(ffi::egl::SLOW_CONFIG as c_int);
+        },
+        (false, None) => (),
+    }
+
+    if let Some((red, green, blue)) = reqs.min_rgb_bits() {
+        out.push(ffi::egl::RED_SIZE as c_int);
+        out.push(red as c_int);
+        out.push(ffi::egl::GREEN_SIZE as c_int);
+        out.push(green as c_int);
+        out.push(ffi::egl::BLUE_SIZE as c_int);
+        out.push(blue as c_int);
+    }
+
+    if let Some(alpha) = reqs.alpha_bits {
+        out.push(ffi::egl::ALPHA_SIZE as c_int);
+        out.push(alpha as c_int);
+    }
+
+    if let Some(depth) = reqs.depth_bits {
+        out.push(ffi::egl::DEPTH_SIZE as c_int);
+        out.push(depth as c_int);
+    }
+
+    if let Some(stencil) = reqs.stencil_bits {
+        out.push(ffi::egl::STENCIL_SIZE as c_int);
+        out.push(stencil as c_int);
+    }
+
+    // window surfaces render to the back buffer unless they are created with
+    // `EGL_SINGLE_BUFFER`, which doesn't depend on the config
+
+    if let Some(multisampling) = reqs.multisampling {
+        out.push(ffi::egl::SAMPLES as c_int);
+        out.push(multisampling as c_int);
+    }
+
+    if let Some(coverage_samples) = reqs.coverage_samples {
+        if !extensions.iter().any(|s| s == "EGL_NV_coverage_sample") {
+            return Err(CreationError::NoAvailablePixelFormat);
+        }
+        out.push(ffi::egl::COVERAGE_BUFFERS_NV as c_int);
+        out.push(1);
+        out.push(ffi::egl::COVERAGE_SAMPLES_NV as c_int);
+        out.push(coverage_samples as c_int);
+    }
+
+    if reqs.recordable {
+        if !extensions.iter().any(|s| s == "EGL_ANDROID_recordable") {
+            return Err(CreationError::NoAvailablePixelFormat);
+        }
+        out.push(ffi::egl::RECORDABLE_ANDROID as c_int);
+        out.push(ffi::egl::TRUE as c_int);
+    }
+
+    if reqs.stereoscopy {
         return Err(CreationError::NoAvailablePixelFormat);
     }
 
-    // analyzing each config
+    // FIXME: srgb is not taken into account
+
+    for &(attrib, value) in &reqs.raw_attributes {
+        out.push(attrib as c_int);
+        out.push(value as c_int);
+    }
+
+    out.push(ffi::egl::NONE as c_int);
+    Ok(out)
+}
+
+/// Reads the pixel format described by `config_id`.
+unsafe fn config_pixel_format(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
+                              config_id: ffi::egl::types::EGLConfig)
+                              -> Result<PixelFormat, CreationError>
+{
     macro_rules! attrib {
         ($egl:expr, $display:expr, $config:expr, $attr:expr) => (
             {
@@ -632,20 +2296,38 @@ unsafe fn choose_fbconfig(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisp
                 let res = $egl.GetConfigAttrib($display, $config,
                                                $attr as ffi::egl::types::EGLint, &mut value);
                 if res == 0 {
-                    return Err(CreationError::OsError(format!("eglGetConfigAttrib failed")));
+                    return Err(egl_error($egl, "eglGetConfigAttrib"));
                 }
                 value
             }
         )
     };
 
-    let desc = PixelFormat {
-        hardware_accelerated: attrib!(egl, display, config_id, ffi::egl::CONFIG_CAVEAT)
-                                      != ffi::egl::SLOW_CONFIG as i32,
-        color_bits: attrib!(egl, display, config_id, ffi::egl::RED_SIZE) as u8 +
-                    attrib!(egl, display, config_id, ffi::egl::BLUE_SIZE) as u8 +
-                    attrib!(egl, display, config_id, ffi::egl::GREEN_SIZE) as u8,
-        alpha_bits: attrib!(egl, display, config_id, ffi::egl::ALPHA_SIZE) as u8,
+    let red_bits = attrib!(egl, display, config_id, ffi::egl::RED_SIZE) as u8;
+    let green_bits = attrib!(egl, display, config_id, ffi::egl::GREEN_SIZE) as u8;
+    let blue_bits = attrib!(egl, display, config_id, ffi::egl::BLUE_SIZE) as u8;
+    let alpha_bits = attrib!(egl, display, config_id, ffi::egl::ALPHA_SIZE) as u8;
+    let caveat = match attrib!(egl, display, config_id, ffi::egl::CONFIG_CAVEAT) as u32 {
+        ffi::egl::SLOW_CONFIG => ConfigCaveat::Slow,
+        ffi::egl::NON_CONFORMANT_CONFIG => ConfigCaveat::NonConformant,
+        _ => ConfigCaveat::None,
+    };
+
+    // `EGL_COLOR_COMPONENT_TYPE_EXT` is an error without `EGL_EXT_pixel_format_float`
+    let mut component_type = 0;
+    let float_color_buffer = egl.GetConfigAttrib(display, config_id,
+                                                 ffi::egl::COLOR_COMPONENT_TYPE_EXT
+                                                 as ffi::egl::types::EGLint,
+                                                 &mut component_type) != 0 &&
+        component_type == ffi::egl::COLOR_COMPONENT_TYPE_FLOAT_EXT as ffi::egl::types::EGLint;
+
+    Ok(PixelFormat {
+        hardware_accelerated: caveat != ConfigCaveat::Slow,
+        color_bits: red_bits + green_bits + blue_bits,
+        red_bits: red_bits,
+        green_bits: green_bits,
+        blue_bits: blue_bits,
+        alpha_bits: alpha_bits,
         depth_bits: attrib!(egl, display, config_id, ffi::egl::DEPTH_SIZE) as u8,
         stencil_bits: attrib!(egl, display, config_id, ffi::egl::STENCIL_SIZE) as u8,
         stereoscopy: false,
@@ -655,16 +2337,26 @@ unsafe fn choose_fbconfig(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisp
             a => Some(a as u16),
         },
         srgb: false,        // TODO: use EGL_KHR_gl_colorspace to know that
-    };
-
-    Ok((config_id, desc))
+        native_id: Some(NativeConfigId::Egl {
+            config_id: attrib!(egl, display, config_id, ffi::egl::CONFIG_ID),
+            native_visual_id: match attrib!(egl, display, config_id, ffi::egl::NATIVE_VISUAL_ID) {
+                0 => None,
+                id => Some(id),
+            },
+        }),
+        caveat: caveat,
+        colorspace: Colorspace::Linear,
+        float_color_buffer: float_color_buffer,
+        transparent: alpha_bits > 0,
+    })
 }
 
 unsafe fn create_context(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDisplay,
                          egl_version: &(ffi::egl::types::EGLint, ffi::egl::types::EGLint),
                          extensions: &[String], api: Api, version: (u8, u8),
                          config_id: ffi::egl::types::EGLConfig, gl_debug: bool,
-                         gl_robustness: Robustness)
+                         gl_robustness: Robustness, raw_attributes: &[(i32, i32)],
+                         share: ffi::egl::types::EGLContext)
                          -> Result<ffi::egl::types::EGLContext, CreationError>
 {
     let mut context_attributes = Vec::with_capacity(10);
@@ -764,16 +2456,22 @@ unsafe fn create_context(egl: &ffi::egl::Egl, display: ffi::egl::types::EGLDispl
         context_attributes.push(version.0 as i32);
     }
 
+    for &(attrib, value) in raw_attributes {
+        context_attributes.push(attrib);
+        context_attributes.push(value);
+    }
+
     context_attributes.push(ffi::egl::NONE as i32);
 
-    let context = egl.CreateContext(display, config_id, ptr::null(),
-                                    context_attributes.as_ptr());
+    let context = egl.CreateContext(display, config_id, share, context_attributes.as_ptr());
 
     if context.is_null() {
         match egl.GetError() as u32 {
             ffi::egl::BAD_MATCH |
             ffi::egl::BAD_ATTRIBUTE => return Err(CreationError::OpenGlVersionNotSupported),
-            e => panic!("eglCreateContext failed: 0x{:x}", e),
+            e => return Err(CreationError::OsError(
+                format!("eglCreateContext failed (eglGetError returned 0x{:x})", e)
+            )),
         }
     }
 
@@ -1,20 +1,42 @@
 #![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
 
+use Colorspace;
+use ConfigCaveat;
+use DeviceInfo;
+use SupportedVersions;
+use find_max_versions;
+use probe_requirements;
 use ContextError;
 use CreationError;
 use GlAttributes;
 use GlProfile;
 use GlRequest;
+use GlxSwapControl;
+use NativeConfigId;
 use Api;
+use PbufferAttributes;
+use PbufferLimits;
+use PresentMode;
+use PresentationTime;
 use PixelFormat;
 use PixelFormatRequirements;
+use RawContextHandle;
+use MultisampleResolve;
+use Rect;
+use RenderBuffer;
 use ReleaseBehavior;
 use Robustness;
+use SrgbRequirement;
+use SwapBehavior;
+use SwapInterval;
+use config_cache;
 
 use libc;
 use libc::c_int;
+use std::cell::Cell;
 use std::ffi::{CStr, CString};
 use std::{mem, ptr, slice};
+use std::time::Duration;
 
 pub mod ffi {
     pub use x11_dl::xlib::*;
@@ -36,9 +58,256 @@ pub struct Context {
     display: *mut ffi::Display,
     window: ffi::Window,
     context: ffi::GLXContext,
+    fb_config: ffi::glx::types::GLXFBConfig,
+    pixel_format: PixelFormat,
+    // The swap buffer count targeted by the last swap made with `glXSwapBuffersMscOML`.
+    last_sbc: Cell<i64>,
+    // The extension used to set the swap interval.
+    swap_control: Option<GlxSwapControl>,
+    // True if the context was created by another library, in which case it isn't destroyed.
+    foreign: bool,
+}
+
+/// The GLX context and drawables that were current on the calling thread at some point, so that
+/// they can be made current again later.
+pub struct CurrentState {
+    display: *mut ffi::Display,
+    draw: ffi::glx::types::GLXDrawable,
+    read: ffi::glx::types::GLXDrawable,
+    context: ffi::GLXContext,
+}
+
+/// The kind of a GLX drawable, as given by the `DRAWABLE_TYPE` bit needed to create it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SurfaceKind {
+    Window,
+    Pbuffer,
+    Pixmap,
+}
+
+/// An FBConfig of a display, from which contexts and drawables can be created.
+///
+/// The X display must outlive the config.
+#[derive(Clone)]
+pub struct Config {
+    glx: ffi::glx::Glx,
+    display: *mut ffi::Display,
+    fb_config: ffi::glx::types::GLXFBConfig,
     pixel_format: PixelFormat,
 }
 
+unsafe impl Send for Config {}
+unsafe impl Sync for Config {}
+
+impl Config {
+    #[inline]
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        self.pixel_format.clone()
+    }
+
+    #[inline]
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        unsafe { fbconfig_pbuffer_limits(&self.glx, self.display, self.fb_config) }
+    }
+
+    /// Creates a drawable on `window` using this FBConfig.
+    pub unsafe fn create_window_surface(&self, window: ffi::Window)
+                                        -> Result<Surface, CreationError>
+    {
+        self.check_drawable_type(ffi::glx::WINDOW_BIT)?;
+        let drawable = self.glx.CreateWindow(self.display as *mut _, self.fb_config, window,
+                                             ptr::null());
+        self.wrap_surface(drawable, SurfaceKind::Window)
+    }
+
+    /// Creates an offscreen drawable using this FBConfig.
+    pub fn create_pbuffer_surface(&self, attributes: &PbufferAttributes)
+                                  -> Result<Surface, CreationError>
+    {
+        self.check_drawable_type(ffi::glx::PBUFFER_BIT)?;
+        // GLX pbuffers can't be bound to textures, only pixmaps with
+        // `GLX_EXT_texture_from_pixmap` can
+        if attributes.texture_format.is_some() || attributes.mipmap {
+            return Err(CreationError::NotSupported("binding pbuffers to textures requires EGL"));
+        }
+        let attrs = [
+            ffi::glx::PBUFFER_WIDTH as c_int, attributes.dimensions.0 as c_int,
+            ffi::glx::PBUFFER_HEIGHT as c_int, attributes.dimensions.1 as c_int,
+            ffi::glx::LARGEST_PBUFFER as c_int, attributes.largest as c_int,
+            0,
+        ];
+        let drawable = unsafe {
+            self.glx.CreatePbuffer(self.display as *mut _, self.fb_config, attrs.as_ptr())
+        };
+        self.wrap_surface(drawable, SurfaceKind::Pbuffer)
+    }
+
+    /// Creates a drawable on `pixmap` using this FBConfig.
+    pub unsafe fn create_pixmap_surface(&self, pixmap: ffi::Pixmap)
+                                        -> Result<Surface, CreationError>
+    {
+        self.check_drawable_type(ffi::glx::PIXMAP_BIT)?;
+        let drawable = self.glx.CreatePixmap(self.display as *mut _, self.fb_config, pixmap,
+                                             ptr::null());
+        self.wrap_surface(drawable, SurfaceKind::Pixmap)
+    }
+
+    fn check_drawable_type(&self, bit: u32) -> Result<(), CreationError> {
+        let mut value = 0;
+        unsafe {
+            self.glx.GetFBConfigAttrib(self.display as *mut _, self.fb_config,
+                                       ffi::glx::DRAWABLE_TYPE as c_int, &mut value);
+        }
+        if value & bit as c_int == 0 {
+            return Err(CreationError::NotSupported("the config doesn't support this kind of \
+                                                    surface"));
+        }
+        Ok(())
+    }
+
+    fn wrap_surface(&self, drawable: ffi::glx::types::GLXDrawable, kind: SurfaceKind)
+                    -> Result<Surface, CreationError>
+    {
+        if drawable == 0 {
+            return Err(CreationError::OsError(format!("GLX drawable creation failed")));
+        }
+        Ok(Surface {
+            glx: self.glx.clone(),
+            display: self.display,
+            drawable: drawable,
+            kind: kind,
+            double_buffer: self.pixel_format.double_buffer,
+        })
+    }
+}
+
+/// A GLX drawable created from an FBConfig.
+///
+/// The X display must outlive the surface.
+pub struct Surface {
+    glx: ffi::glx::Glx,
+    display: *mut ffi::Display,
+    drawable: ffi::glx::types::GLXDrawable,
+    kind: SurfaceKind,
+    double_buffer: bool,
+}
+
+unsafe impl Send for Surface {}
+unsafe impl Sync for Surface {}
+
+impl Surface {
+    #[inline]
+    pub fn raw_handle(&self) -> ffi::glx::types::GLXDrawable {
+        self.drawable
+    }
+
+    /// Returns the width and height of the drawable.
+    pub fn dimensions(&self) -> Result<(u32, u32), ContextError> {
+        let mut width = 0;
+        let mut height = 0;
+        unsafe {
+            self.glx.QueryDrawable(self.display as *mut _, self.drawable,
+                                   ffi::glx::WIDTH as c_int, &mut width);
+            self.glx.QueryDrawable(self.display as *mut _, self.drawable,
+                                   ffi::glx::HEIGHT as c_int, &mut height);
+        }
+        Ok((width, height))
+    }
+
+    #[inline]
+    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        unsafe { self.glx.SwapBuffers(self.display as *mut _, self.drawable); }
+        Ok(())
+    }
+
+    /// Returns the age of the back buffer, or 0 if its contents are undefined. The surface must
+    /// be current.
+    pub fn buffer_age(&self) -> Result<u32, ContextError> {
+        unsafe {
+            let extensions = self.glx.GetClientString(self.display as *mut _,
+                                                      ffi::glx::EXTENSIONS as c_int);
+            if extensions.is_null() {
+                return Err(ContextError::FunctionUnavailable);
+            }
+            let extensions = CStr::from_ptr(extensions).to_string_lossy();
+            if !check_ext(&extensions, "GLX_EXT_buffer_age") {
+                return Err(ContextError::FunctionUnavailable);
+            }
+
+            let mut age = 0;
+            self.glx.QueryDrawable(self.display as *mut _, self.drawable,
+                                   ffi::glx_extra::BACK_BUFFER_AGE_EXT as c_int, &mut age);
+            Ok(age)
+        }
+    }
+
+    /// GLX doesn't guarantee that the back buffer is preserved.
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        Ok(SwapBehavior::Destroyed)
+    }
+
+    #[inline]
+    pub fn set_swap_behavior(&self, behavior: SwapBehavior) -> Result<(), ContextError> {
+        match behavior {
+            SwapBehavior::Destroyed => Ok(()),
+            SwapBehavior::Preserved => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    /// GLX drawables render to the buffer of their FBConfig, which can't be changed.
+    #[inline]
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        if self.double_buffer { Ok(RenderBuffer::Back) } else { Ok(RenderBuffer::Single) }
+    }
+
+    #[inline]
+    pub fn set_render_buffer(&self, render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        if self.render_buffer()? == render_buffer {
+            Ok(())
+        } else {
+            Err(ContextError::FunctionUnavailable)
+        }
+    }
+
+    /// GLX drawables can't choose how their samples are resolved.
+    #[inline]
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        Ok(MultisampleResolve::Default)
+    }
+
+    #[inline]
+    pub fn set_multisample_resolve(&self, resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        match resolve {
+            MultisampleResolve::Default => Ok(()),
+            MultisampleResolve::Box => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    /// GLX has no equivalent of `EGL_KHR_partial_update`.
+    #[inline]
+    pub fn set_damage_region(&self, _rects: &[Rect]) -> Result<(), ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+}
+
+impl Drop for Surface {
+    fn drop(&mut self) {
+        unsafe {
+            match self.kind {
+                SurfaceKind::Window => self.glx.DestroyWindow(self.display as *mut _,
+                                                              self.drawable),
+                SurfaceKind::Pbuffer => self.glx.DestroyPbuffer(self.display as *mut _,
+                                                                self.drawable),
+                SurfaceKind::Pixmap => self.glx.DestroyPixmap(self.display as *mut _,
+                                                              self.drawable),
+            }
+        }
+    }
+}
+
 // TODO: remove me
 fn with_c_str<F, T>(s: &str, f: F) -> T where F: FnOnce(*const libc::c_char) -> T {
     use std::ffi::CString;
@@ -51,37 +320,50 @@ impl Context {
         glx: ffi::glx::Glx,
         xlib: &'a ffi::Xlib,
         pf_reqs: &PixelFormatRequirements,
-        opengl: &'a GlAttributes<&'a Context>,
+        opengl: GlAttributes<&'a Context>,
         display: *mut ffi::Display,
         screen_id: libc::c_int,
         transparent: bool,
     ) -> Result<ContextPrototype<'a>, CreationError>
     {
-        // This is completely ridiculous, but VirtualBox's OpenGL driver needs some call handled by
-        // *it* (i.e. not Mesa) to occur before anything else can happen. That is because
-        // VirtualBox's OpenGL driver is going to apply binary patches to Mesa in the DLL
-        // constructor and until it's loaded it won't have a chance to do that.
-        //
-        // The easiest way to do this is to just call `glXQueryVersion()` before doing anything
-        // else. See: https://www.virtualbox.org/ticket/8293
-        let (mut major, mut minor) = (0, 0);
-        unsafe {
-            glx.QueryVersion(display as *mut _, &mut major, &mut minor);
-        }
-
-        // loading the list of extensions
-        let extensions = unsafe {
-            let extensions = glx.QueryExtensionsString(display as *mut _, screen_id);
-            let extensions = CStr::from_ptr(extensions).to_bytes().to_vec();
-            String::from_utf8(extensions).unwrap()
-        };
+        let extensions = unsafe { query_extensions(&glx, display, screen_id) };
 
         // finding the pixel format we want
         let (fb_config, pixel_format) = unsafe {
             choose_fbconfig(&glx, &extensions, xlib, display, screen_id, pf_reqs, transparent)
-                                          .map_err(|_| CreationError::NoAvailablePixelFormat)?
+                .map_err(|_| no_matching_fbconfig(&glx, xlib, display, screen_id, pf_reqs))?
         };
 
+        Context::new_prototype(glx, extensions, xlib, opengl, display, fb_config, pixel_format)
+    }
+
+    /// Builds a prototype from an FBConfig returned by `find_configs`.
+    pub fn new_with_config<'a>(
+        xlib: &'a ffi::Xlib,
+        config: &Config,
+        opengl: GlAttributes<&'a Context>,
+        screen_id: libc::c_int,
+    ) -> Result<ContextPrototype<'a>, CreationError>
+    {
+        let extensions = unsafe { query_extensions(&config.glx, config.display, screen_id) };
+        Context::new_prototype(config.glx.clone(), extensions, xlib, opengl, config.display,
+                               config.fb_config, config.pixel_format.clone())
+    }
+
+    fn new_prototype<'a>(
+        glx: ffi::glx::Glx,
+        extensions: String,
+        xlib: &'a ffi::Xlib,
+        opengl: GlAttributes<&'a Context>,
+        display: *mut ffi::Display,
+        fb_config: ffi::glx::types::GLXFBConfig,
+        pixel_format: PixelFormat,
+    ) -> Result<ContextPrototype<'a>, CreationError>
+    {
+        if opengl.protected_content {
+            return Err(CreationError::ProtectedContentNotSupported);
+        }
+
         // getting the visual infos
         let visual_infos: ffi::glx::types::XVisualInfo = unsafe {
             let vi = glx.GetVisualFromFBConfig(display as *mut _, fb_config);
@@ -105,6 +387,80 @@ impl Context {
         })
     }
 
+    /// Wraps a context created by another library, drawing to `window`.
+    ///
+    /// The context is neither released nor destroyed when the returned context is dropped.
+    pub unsafe fn adopt(
+        glx: ffi::glx::Glx,
+        xlib: &ffi::Xlib,
+        display: *mut ffi::Display,
+        screen_id: libc::c_int,
+        context: ffi::GLXContext,
+        window: ffi::Window,
+    ) -> Result<Context, CreationError>
+    {
+        // looking up the FBConfig the context was created with from its id
+        let mut fb_config_id = 0;
+        if glx.QueryContext(display as *mut _, context, ffi::glx::FBCONFIG_ID as c_int,
+                            &mut fb_config_id) != 0
+        {
+            return Err(CreationError::OsError(format!("glXQueryContext failed")));
+        }
+
+        let descriptor = [ffi::glx::FBCONFIG_ID as c_int, fb_config_id, 0];
+        let mut num_configs = 0;
+        let configs = glx.ChooseFBConfig(display as *mut _, screen_id, descriptor.as_ptr(),
+                                         &mut num_configs);
+        if configs.is_null() || num_configs == 0 {
+            return Err(CreationError::OsError(format!("the FBConfig of the context could not \
+                                                       be found")));
+        }
+        let fb_config = *configs;
+        (xlib.XFree)(configs as *mut _);
+
+        let extensions = query_extensions(&glx, display, screen_id);
+        let extra_functions = ffi::glx_extra::Glx::load_with(|addr| {
+            with_c_str(addr, |s| glx.GetProcAddress(s as *const u8) as *const _)
+        });
+        let swap_control = select_swap_control(&extensions, &extra_functions, None)?;
+
+        Ok(Context {
+            pixel_format: fbconfig_pixel_format(&glx, xlib, display, fb_config),
+            glx: glx,
+            display: display,
+            window: window,
+            context: context,
+            fb_config: fb_config,
+            last_sbc: Cell::new(0),
+            swap_control: swap_control,
+            foreign: true,
+        })
+    }
+
+    /// Returns the FBConfig this context was created with.
+    #[inline]
+    pub fn config(&self) -> Config {
+        Config {
+            glx: self.glx.clone(),
+            display: self.display,
+            fb_config: self.fb_config,
+            pixel_format: self.pixel_format.clone(),
+        }
+    }
+
+    /// Makes the context current, drawing to `draw` and reading from `read` instead of the
+    /// window the context was created with.
+    pub unsafe fn make_current_surfaces(&self, draw: &Surface, read: &Surface)
+                                        -> Result<(), ContextError>
+    {
+        let res = self.glx.MakeContextCurrent(self.display as *mut _, draw.drawable,
+                                              read.drawable, self.context);
+        if res == 0 {
+            return Err(ContextError::OsError(format!("glXMakeContextCurrent failed")));
+        }
+        Ok(())
+    }
+
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         // TODO: glutin needs some internal changes for proper error recovery
         let res = self.glx.MakeCurrent(self.display as *mut _, self.window, self.context);
@@ -114,11 +470,196 @@ impl Context {
         Ok(())
     }
 
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        if !self.is_current() {
+            return Ok(());
+        }
+
+        let res = self.glx.MakeCurrent(self.display as *mut _, 0, ptr::null());
+        if res == 0 {
+            return Err(ContextError::OsError(format!("glXMakeCurrent failed")));
+        }
+        Ok(())
+    }
+
     #[inline]
     pub fn is_current(&self) -> bool {
         unsafe { self.glx.GetCurrentContext() == self.context }
     }
 
+    /// Checks that the context is still valid, without making it current. The window can't be
+    /// checked without waiting for the X server to report an error.
+    pub fn validate(&self) -> Result<(), ContextError> {
+        let mut value = 0;
+        let ret = unsafe {
+            self.glx.QueryContext(self.display as *mut _, self.context,
+                                  ffi::glx::FBCONFIG_ID as c_int, &mut value)
+        };
+        if ret != 0 {
+            return Err(ContextError::OsError(format!("glXQueryContext failed (error {})", ret)));
+        }
+        Ok(())
+    }
+
+    /// Sets the number of screen refreshes to wait for before swapping the buffers of the current
+    /// drawable, through the extension selected when the context was created. The context must be
+    /// current.
+    pub fn set_swap_interval(&self, interval: SwapInterval) -> Result<(), ContextError> {
+        let (extra_functions, extensions) = self.load_swap_control()?;
+
+        let res = unsafe {
+            match (self.swap_control, interval) {
+                (Some(GlxSwapControl::Ext), SwapInterval::Wait(interval)) => {
+                    let drawable = self.glx.GetCurrentDrawable();
+                    extra_functions.SwapIntervalEXT(self.display as *mut _, drawable,
+                                                    interval as c_int);
+                    0
+                },
+                // late swaps tear instead of waiting for the next refresh when the interval is
+                // negative
+                (Some(GlxSwapControl::Ext), SwapInterval::Adaptive(interval)) => {
+                    if !check_ext(&extensions, "GLX_EXT_swap_control_tear") {
+                        return Err(ContextError::FunctionUnavailable);
+                    }
+                    let drawable = self.glx.GetCurrentDrawable();
+                    extra_functions.SwapIntervalEXT(self.display as *mut _, drawable,
+                                                    -(interval as c_int));
+                    0
+                },
+                (Some(GlxSwapControl::Mesa), SwapInterval::Wait(interval)) => {
+                    extra_functions.SwapIntervalMESA(interval as _)
+                },
+                (Some(GlxSwapControl::Sgi), SwapInterval::Wait(interval)) => {
+                    extra_functions.SwapIntervalSGI(interval as c_int)
+                },
+                _ => return Err(ContextError::FunctionUnavailable),
+            }
+        };
+
+        if res != 0 {
+            return Err(ContextError::OsError(format!("setting the swap interval failed \
+                                                      (returned {})", res)));
+        }
+        Ok(())
+    }
+
+    /// Returns the swap interval of the current drawable. The context must be current.
+    pub fn get_swap_interval(&self) -> Result<SwapInterval, ContextError> {
+        let (extra_functions, extensions) = self.load_swap_control()?;
+
+        unsafe {
+            match self.swap_control {
+                Some(GlxSwapControl::Ext) => {
+                    let drawable = self.glx.GetCurrentDrawable();
+                    let mut interval = 0;
+                    self.glx.QueryDrawable(self.display as *mut _, drawable,
+                                           ffi::glx_extra::SWAP_INTERVAL_EXT as c_int,
+                                           &mut interval);
+                    let mut late_swaps_tear = 0;
+                    if check_ext(&extensions, "GLX_EXT_swap_control_tear") {
+                        self.glx.QueryDrawable(self.display as *mut _, drawable,
+                                               ffi::glx_extra::LATE_SWAPS_TEAR_EXT as c_int,
+                                               &mut late_swaps_tear);
+                    }
+                    Ok(if late_swaps_tear != 0 {
+                        SwapInterval::Adaptive(interval)
+                    } else {
+                        SwapInterval::Wait(interval)
+                    })
+                },
+                Some(GlxSwapControl::Mesa) if extra_functions.GetSwapIntervalMESA.is_loaded() => {
+                    Ok(SwapInterval::Wait(extra_functions.GetSwapIntervalMESA().max(0) as u32))
+                },
+                _ => Err(ContextError::FunctionUnavailable),
+            }
+        }
+    }
+
+    /// Returns the smallest and largest swap intervals supported by the current drawable. The
+    /// context must be current.
+    pub fn get_swap_interval_range(&self) -> Result<(u32, u32), ContextError> {
+        if self.swap_control != Some(GlxSwapControl::Ext) {
+            return Err(ContextError::FunctionUnavailable);
+        }
+
+        let mut max = 0;
+        unsafe {
+            let drawable = self.glx.GetCurrentDrawable();
+            self.glx.QueryDrawable(self.display as *mut _, drawable,
+                                   ffi::glx_extra::MAX_SWAP_INTERVAL_EXT as c_int, &mut max);
+        }
+        Ok((0, max))
+    }
+
+    /// Returns the present modes allowed by the extension used to set the swap interval.
+    pub fn supported_present_modes(&self) -> Result<Vec<PresentMode>, ContextError> {
+        let (_, extensions) = self.load_swap_control()?;
+        match self.swap_control {
+            Some(GlxSwapControl::Ext) => {
+                let mut modes = vec![PresentMode::Immediate, PresentMode::Fifo];
+                if check_ext(&extensions, "GLX_EXT_swap_control_tear") {
+                    modes.push(PresentMode::FifoRelaxed);
+                }
+                Ok(modes)
+            },
+            Some(GlxSwapControl::Mesa) => Ok(vec![PresentMode::Immediate, PresentMode::Fifo]),
+            // `GLX_SGI_swap_control` rejects an interval of 0
+            Some(GlxSwapControl::Sgi) => Ok(vec![PresentMode::Fifo]),
+            None => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    /// Returns the extension used to set the swap interval, or `None` if none is available.
+    #[inline]
+    pub fn swap_control(&self) -> Option<GlxSwapControl> {
+        self.swap_control
+    }
+
+    fn load_swap_control(&self) -> Result<(ffi::glx_extra::Glx, String), ContextError> {
+        let extra_functions = ffi::glx_extra::Glx::load_with(|addr| {
+            with_c_str(addr, |s| {
+                unsafe { self.glx.GetProcAddress(s as *const u8) as *const _ }
+            })
+        });
+        let extensions = unsafe {
+            let extensions = self.glx.GetClientString(self.display as *mut _,
+                                                      ffi::glx::EXTENSIONS as c_int);
+            if extensions.is_null() {
+                return Err(ContextError::FunctionUnavailable);
+            }
+            String::from_utf8_lossy(CStr::from_ptr(extensions).to_bytes()).into_owned()
+        };
+        Ok((extra_functions, extensions))
+    }
+
+    /// Returns the context and drawables that are current on the calling thread, whether or not
+    /// they belong to this context.
+    pub fn current_state(&self) -> CurrentState {
+        unsafe {
+            CurrentState {
+                display: self.glx.GetCurrentDisplay() as *mut _,
+                draw: self.glx.GetCurrentDrawable(),
+                read: self.glx.GetCurrentReadDrawable(),
+                context: self.glx.GetCurrentContext(),
+            }
+        }
+    }
+
+    /// Makes the context and drawables of `state` current again. If no context was current when
+    /// the state was saved, this context is released instead.
+    pub unsafe fn restore_current_state(&self, state: &CurrentState) -> Result<(), ContextError> {
+        if state.context.is_null() {
+            return self.make_not_current();
+        }
+
+        let res = self.glx.MakeContextCurrent(state.display as *mut _, state.draw, state.read,
+                                              state.context);
+        if res == 0 {
+            return Err(ContextError::OsError(format!("glXMakeContextCurrent failed")));
+        }
+        Ok(())
+    }
+
     pub fn get_proc_address(&self, addr: &str) -> *const () {
         let addr = CString::new(addr.as_bytes()).unwrap();
         let addr = addr.as_ptr();
@@ -134,6 +675,135 @@ impl Context {
         Ok(())
     }
 
+    /// Swaps the buffers with `glXSwapBuffersMscOML`, and returns the swap buffer count that the
+    /// window will have once the swap completes.
+    pub fn swap_buffers_with_frame_id(&self) -> Result<u64, ContextError> {
+        let extra_functions = self.load_sync_control()?;
+        let sbc = unsafe {
+            extra_functions.SwapBuffersMscOML(self.display as *mut _, self.window, 0, 0, 0)
+        };
+        if sbc < 0 {
+            return Err(ContextError::OsError(format!("glXSwapBuffersMscOML failed")));
+        }
+        self.last_sbc.set(sbc);
+        Ok(sbc as u64)
+    }
+
+    /// Swaps the buffers like `swap_buffers_with_frame_id`, unless the last swap made with it
+    /// hasn't completed yet.
+    pub fn try_swap_buffers(&self) -> Result<u64, ContextError> {
+        let last_sbc = self.last_sbc.get();
+        if last_sbc > 0 &&
+           self.presentation_time(last_sbc as u64)? == PresentationTime::Pending
+        {
+            return Err(ContextError::WouldBlock);
+        }
+        self.swap_buffers_with_frame_id()
+    }
+
+    /// Returns when the swap that brought the swap buffer count of the window to `sbc` was
+    /// presented. Only the time of the last completed swap is known.
+    pub fn presentation_time(&self, sbc: u64) -> Result<PresentationTime, ContextError> {
+        let extra_functions = self.load_sync_control()?;
+        let (mut ust, mut msc, mut current_sbc) = (0, 0, 0);
+        let ret = unsafe {
+            extra_functions.GetSyncValuesOML(self.display as *mut _, self.window, &mut ust,
+                                             &mut msc, &mut current_sbc)
+        };
+        if ret == 0 {
+            return Err(ContextError::OsError(format!("glXGetSyncValuesOML failed")));
+        }
+
+        let current_sbc = current_sbc as u64;
+        if current_sbc < sbc {
+            Ok(PresentationTime::Pending)
+        } else if current_sbc == sbc {
+            // the UST is in microseconds
+            let ust = ust as u64;
+            Ok(PresentationTime::Presented(Duration::new(ust / 1_000_000,
+                                                         (ust % 1_000_000) as u32 * 1000)))
+        } else {
+            Ok(PresentationTime::Unavailable)
+        }
+    }
+
+    /// Makes the window join the swap group `group`, or leaves the current one if `group` is 0.
+    pub fn join_swap_group(&self, group: u32) -> Result<(), ContextError> {
+        let extra_functions = self.load_swap_group()?;
+        let ret = unsafe {
+            extra_functions.JoinSwapGroupNV(self.display as *mut _, self.window, group)
+        };
+        if ret == 0 {
+            return Err(ContextError::OsError(format!("glXJoinSwapGroupNV failed")));
+        }
+        Ok(())
+    }
+
+    /// Binds the swap group `group` to the swap barrier `barrier`, or unbinds it if `barrier` is
+    /// 0.
+    pub fn bind_swap_barrier(&self, group: u32, barrier: u32) -> Result<(), ContextError> {
+        let extra_functions = self.load_swap_group()?;
+        let ret = unsafe {
+            extra_functions.BindSwapBarrierNV(self.display as *mut _, group, barrier)
+        };
+        if ret == 0 {
+            return Err(ContextError::OsError(format!("glXBindSwapBarrierNV failed")));
+        }
+        Ok(())
+    }
+
+    fn load_swap_group(&self) -> Result<ffi::glx_extra::Glx, ContextError> {
+        let extensions = unsafe {
+            let extensions = self.glx.GetClientString(self.display as *mut _,
+                                                      ffi::glx::EXTENSIONS as c_int);
+            if extensions.is_null() {
+                return Err(ContextError::FunctionUnavailable);
+            }
+            String::from_utf8_lossy(CStr::from_ptr(extensions).to_bytes()).into_owned()
+        };
+        if !check_ext(&extensions, "GLX_NV_swap_group") {
+            return Err(ContextError::FunctionUnavailable);
+        }
+
+        let extra_functions = ffi::glx_extra::Glx::load_with(|addr| {
+            with_c_str(addr, |s| {
+                unsafe { self.glx.GetProcAddress(s as *const u8) as *const _ }
+            })
+        });
+        if !extra_functions.JoinSwapGroupNV.is_loaded() ||
+           !extra_functions.BindSwapBarrierNV.is_loaded()
+        {
+            return Err(ContextError::FunctionUnavailable);
+        }
+        Ok(extra_functions)
+    }
+
+    fn load_sync_control(&self) -> Result<ffi::glx_extra::Glx, ContextError> {
+        let extensions = unsafe {
+            let extensions = self.glx.GetClientString(self.display as *mut _,
+                                                      ffi::glx::EXTENSIONS as c_int);
+            if extensions.is_null() {
+                return Err(ContextError::FunctionUnavailable);
+            }
+            String::from_utf8_lossy(CStr::from_ptr(extensions).to_bytes()).into_owned()
+        };
+        if !check_ext(&extensions, "GLX_OML_sync_control") {
+            return Err(ContextError::FunctionUnavailable);
+        }
+
+        let extra_functions = ffi::glx_extra::Glx::load_with(|addr| {
+            with_c_str(addr, |s| {
+                unsafe { self.glx.GetProcAddress(s as *const u8) as *const _ }
+            })
+        });
+        if !extra_functions.SwapBuffersMscOML.is_loaded() ||
+           !extra_functions.GetSyncValuesOML.is_loaded()
+        {
+            return Err(ContextError::FunctionUnavailable);
+        }
+        Ok(extra_functions)
+    }
+
     #[inline]
     pub fn get_api(&self) -> ::Api {
         ::Api::OpenGl
@@ -155,6 +825,10 @@ unsafe impl Sync for Context {}
 
 impl Drop for Context {
     fn drop(&mut self) {
+        if self.foreign {
+            return;
+        }
+
         unsafe {
             if self.is_current() {
                 self.glx.MakeCurrent(self.display as *mut _, 0, ptr::null_mut());
@@ -169,7 +843,7 @@ pub struct ContextPrototype<'a> {
     glx: ffi::glx::Glx,
     extensions: String,
     xlib: &'a ffi::Xlib,
-    opengl: &'a GlAttributes<&'a Context>,
+    opengl: GlAttributes<&'a Context>,
     display: *mut ffi::Display,
     fb_config: ffi::glx::types::GLXFBConfig,
     visual_infos: ffi::XVisualInfo,
@@ -183,9 +857,13 @@ impl<'a> ContextPrototype<'a> {
     }
 
     pub fn finish(self, window: ffi::Window) -> Result<Context, CreationError> {
-        let share = match self.opengl.sharing {
-            Some(ctxt) => ctxt.context,
-            None => ptr::null()
+        let share = match (self.opengl.raw_sharing, self.opengl.sharing) {
+            (Some(RawContextHandle::Glx { context }), _) => context as ffi::GLXContext,
+            (Some(_), _) => {
+                return Err(CreationError::NotSupported("the shared context is not a GLX context"));
+            },
+            (None, Some(ctxt)) => ctxt.context,
+            (None, None) => ptr::null()
         };
 
         // loading the extra GLX functions
@@ -195,6 +873,24 @@ impl<'a> ContextPrototype<'a> {
             })
         });
 
+        let swap_control = select_swap_control(&self.extensions, &extra_functions,
+                                               self.opengl.glx_swap_control)?;
+
+        let mut raw_context_attributes = self.opengl.raw_context_attributes.clone();
+        if self.opengl.no_error {
+            self.opengl.check_no_error()?;
+            if !check_ext(&self.extensions, "GLX_ARB_create_context_no_error") {
+                return Err(CreationError::NoErrorNotSupported);
+            }
+            raw_context_attributes.push((ffi::glx_extra::CONTEXT_OPENGL_NO_ERROR_ARB as i32, 1));
+        }
+        if self.opengl.release_behavior == ReleaseBehavior::None &&
+           check_ext(&self.extensions, "GLX_ARB_context_flush_control")
+        {
+            raw_context_attributes.push((ffi::glx_extra::CONTEXT_RELEASE_BEHAVIOR_ARB as i32,
+                                         ffi::glx_extra::CONTEXT_RELEASE_BEHAVIOR_NONE_ARB as i32));
+        }
+
         // creating GL context
         let context = match self.opengl.version {
             GlRequest::Latest => {
@@ -210,7 +906,8 @@ impl<'a> ContextPrototype<'a> {
                         match create_context(&self.glx, &extra_functions, &self.extensions, &self.xlib,
                                              *opengl_version, self.opengl.profile,
                                              self.opengl.debug, self.opengl.robustness, share,
-                                             self.display, self.fb_config, &self.visual_infos)
+                                             self.display, self.fb_config, &self.visual_infos,
+                                             &raw_context_attributes)
                         {
                             Ok(x) => {
                                 ctxt = x;
@@ -222,7 +919,8 @@ impl<'a> ContextPrototype<'a> {
                     ctxt = create_context(&self.glx, &extra_functions, &self.extensions, &self.xlib, (1, 0),
                                                self.opengl.profile, self.opengl.debug,
                                                self.opengl.robustness, share,
-                                               self.display, self.fb_config, &self.visual_infos)?;
+                                               self.display, self.fb_config, &self.visual_infos,
+                                               &raw_context_attributes)?;
                     break;
                 }
                 ctxt
@@ -231,58 +929,37 @@ impl<'a> ContextPrototype<'a> {
                 create_context(&self.glx, &extra_functions, &self.extensions, &self.xlib, (major, minor),
                                     self.opengl.profile, self.opengl.debug,
                                     self.opengl.robustness, share, self.display, self.fb_config,
-                                    &self.visual_infos)?
+                                    &self.visual_infos, &raw_context_attributes)?
             },
             GlRequest::Specific(_, _) => panic!("Only OpenGL is supported"),
             GlRequest::GlThenGles { opengl_version: (major, minor), .. } => {
                 create_context(&self.glx, &extra_functions, &self.extensions, &self.xlib, (major, minor),
                                     self.opengl.profile, self.opengl.debug,
                                     self.opengl.robustness, share, self.display, self.fb_config,
-                                    &self.visual_infos)?
+                                    &self.visual_infos, &raw_context_attributes)?
             },
+            // the list is expanded into `GlRequest::Specific` requests by `create_with_versions`
+            GlRequest::PreferredList(_) => return Err(CreationError::OpenGlVersionNotSupported),
         };
 
         // vsync
         if self.opengl.vsync {
             unsafe { self.glx.MakeCurrent(self.display as *mut _, window, context) };
 
-            if check_ext(&self.extensions, "GLX_EXT_swap_control") && extra_functions.SwapIntervalEXT.is_loaded() {
-                // this should be the most common extension
-                unsafe {
-                    extra_functions.SwapIntervalEXT(self.display as *mut _, window, 1);
+            unsafe {
+                match swap_control {
+                    Some(GlxSwapControl::Ext) => {
+                        extra_functions.SwapIntervalEXT(self.display as *mut _, window, 1);
+                    },
+                    Some(GlxSwapControl::Mesa) => {
+                        extra_functions.SwapIntervalMESA(1);
+                    },
+                    Some(GlxSwapControl::Sgi) => {
+                        extra_functions.SwapIntervalSGI(1);
+                    },
+                    None => (),
                 }
-
-                // checking that it worked
-                // TODO: handle this
-                /*if self.builder.strict {
-                    let mut swap = unsafe { mem::uninitialized() };
-                    unsafe {
-                        self.glx.QueryDrawable(self.display as *mut _, window,
-                                               ffi::glx_extra::SWAP_INTERVAL_EXT as i32,
-                                               &mut swap);
-                    }
-
-                    if swap != 1 {
-                        return Err(CreationError::OsError(format!("Couldn't setup vsync: expected \
-                                                    interval `1` but got `{}`", swap)));
-                    }
-                }*/
-
-            // GLX_MESA_swap_control is not official
-            /*} else if extra_functions.SwapIntervalMESA.is_loaded() {
-                unsafe {
-                    extra_functions.SwapIntervalMESA(1);
-                }*/
-
-            } else if check_ext(&self.extensions, "GLX_SGI_swap_control") && extra_functions.SwapIntervalSGI.is_loaded() {
-                unsafe {
-                    extra_functions.SwapIntervalSGI(1);
-                }
-
-            }/* else if self.builder.strict {
-                // TODO: handle this
-                return Err(CreationError::OsError(format!("Couldn't find any available vsync extension")));
-            }*/
+            }
 
             unsafe { self.glx.MakeCurrent(self.display as *mut _, 0, ptr::null()) };
         }
@@ -292,11 +969,193 @@ impl<'a> ContextPrototype<'a> {
             display: self.display,
             window: window,
             context: context,
+            fb_config: self.fb_config,
             pixel_format: self.pixel_format,
+            last_sbc: Cell::new(0),
+            swap_control: swap_control,
+            foreign: false,
         })
     }
 }
 
+/// Picks the extension used to set the swap interval: `forced` if it's available, or the first
+/// available one otherwise.
+fn select_swap_control(extensions: &str, extra_functions: &ffi::glx_extra::Glx,
+                       forced: Option<GlxSwapControl>)
+                       -> Result<Option<GlxSwapControl>, CreationError>
+{
+    let available = |swap_control| match swap_control {
+        GlxSwapControl::Ext => check_ext(extensions, "GLX_EXT_swap_control") &&
+                               extra_functions.SwapIntervalEXT.is_loaded(),
+        GlxSwapControl::Mesa => check_ext(extensions, "GLX_MESA_swap_control") &&
+                                extra_functions.SwapIntervalMESA.is_loaded(),
+        GlxSwapControl::Sgi => check_ext(extensions, "GLX_SGI_swap_control") &&
+                               extra_functions.SwapIntervalSGI.is_loaded(),
+    };
+
+    match forced {
+        Some(swap_control) if available(swap_control) => Ok(Some(swap_control)),
+        Some(_) => Err(CreationError::NotSupported("the requested swap control extension is not \
+                                                    available")),
+        None => {
+            Ok([GlxSwapControl::Ext, GlxSwapControl::Mesa, GlxSwapControl::Sgi].iter().cloned()
+                .find(|&swap_control| available(swap_control)))
+        },
+    }
+}
+
+/// Queries the version and the list of extensions of the GLX implementation.
+unsafe fn query_extensions(glx: &ffi::glx::Glx, display: *mut ffi::Display,
+                           screen_id: libc::c_int) -> String
+{
+    // This is completely ridiculous, but VirtualBox's OpenGL driver needs some call handled by
+    // *it* (i.e. not Mesa) to occur before anything else can happen. That is because
+    // VirtualBox's OpenGL driver is going to apply binary patches to Mesa in the DLL
+    // constructor and until it's loaded it won't have a chance to do that.
+    //
+    // The easiest way to do this is to just call `glXQueryVersion()` before doing anything
+    // else. See: https://www.virtualbox.org/ticket/8293
+    let (mut major, mut minor) = (0, 0);
+    glx.QueryVersion(display as *mut _, &mut major, &mut minor);
+
+    let extensions = glx.QueryExtensionsString(display as *mut _, screen_id);
+    let extensions = CStr::from_ptr(extensions).to_bytes().to_vec();
+    String::from_utf8(extensions).unwrap()
+}
+
+/// Returns all the FBConfigs of `screen_id` that match `reqs`, in the order given by
+/// `glXChooseFBConfig`.
+pub unsafe fn find_configs(glx: &ffi::glx::Glx, xlib: &ffi::Xlib, display: *mut ffi::Display,
+                           screen_id: libc::c_int, reqs: &PixelFormatRequirements)
+                           -> Result<Vec<Config>, CreationError>
+{
+    let extensions = query_extensions(glx, display, screen_id);
+    let mut descriptor = match fbconfig_descriptor(&extensions, reqs) {
+        Ok(descriptor) => descriptor,
+        Err(()) => return Ok(Vec::new()),
+    };
+    // contexts are double buffered unless requested otherwise, but the configs of both kinds are
+    // listed unless the template cares
+    if reqs.double_buffer.is_none() {
+        let double_buffer = ffi::glx::DOUBLEBUFFER as c_int;
+        if let Some(pair) = descriptor.chunks_mut(2).find(|pair| pair[0] == double_buffer) {
+            pair[1] = ffi::glx::DONT_CARE as c_int;
+        }
+    }
+
+    let mut num_configs = 0;
+    let configs = glx.ChooseFBConfig(display as *mut _, screen_id, descriptor.as_ptr(),
+                                     &mut num_configs);
+    if configs.is_null() {
+        return Ok(Vec::new());
+    }
+
+    let result = slice::from_raw_parts(configs, num_configs as usize).iter().map(|&fb_config| {
+        Config {
+            glx: glx.clone(),
+            display: display,
+            fb_config: fb_config,
+            pixel_format: fbconfig_pixel_format(glx, xlib, display, fb_config),
+        }
+    }).collect();
+
+    (xlib.XFree)(configs as *mut _);
+    Ok(result)
+}
+
+/// Returns the renderer of `screen_id` as reported by `GLX_MESA_query_renderer`.
+pub unsafe fn device_info(glx: &ffi::glx::Glx, display: *mut ffi::Display,
+                          screen_id: libc::c_int) -> Result<DeviceInfo, CreationError>
+{
+    let extensions = query_extensions(glx, display, screen_id);
+    if !check_ext(&extensions, "GLX_MESA_query_renderer") {
+        return Err(CreationError::NotSupported("GLX_MESA_query_renderer is not supported"));
+    }
+
+    let extra_functions = ffi::glx_extra::Glx::load_with(|addr| {
+        with_c_str(addr, |s| glx.GetProcAddress(s as *const u8) as *const _)
+    });
+
+    // the renderer 0 is the one used by the contexts created on the screen
+    let query_integers = |attribute: u32, count: usize| {
+        let mut values = vec![0; count];
+        if extra_functions.QueryRendererIntegerMESA(display as *mut _, screen_id, 0,
+                                                    attribute as c_int,
+                                                    values.as_mut_ptr()) == 0
+        {
+            return None;
+        }
+        Some(values)
+    };
+    let query_integer = |attribute| query_integers(attribute, 1).map(|values| values[0]);
+    let query_string = |attribute: u32| {
+        let string = extra_functions.QueryRendererStringMESA(display as *mut _, screen_id, 0,
+                                                             attribute as c_int);
+        if string.is_null() {
+            return None;
+        }
+        Some(CStr::from_ptr(string).to_string_lossy().into_owned())
+    };
+
+    Ok(DeviceInfo {
+        vendor: query_string(ffi::glx_extra::RENDERER_VENDOR_ID_MESA),
+        vendor_id: query_integer(ffi::glx_extra::RENDERER_VENDOR_ID_MESA),
+        device_name: query_string(ffi::glx_extra::RENDERER_DEVICE_ID_MESA),
+        device_id: query_integer(ffi::glx_extra::RENDERER_DEVICE_ID_MESA),
+        video_memory: query_integer(ffi::glx_extra::RENDERER_VIDEO_MEMORY_MESA),
+        // GLX doesn't report the name of the driver
+        driver_name: None,
+        driver_version: query_integers(ffi::glx_extra::RENDERER_VERSION_MESA, 3)
+            .map(|version| (version[0], version[1], version[2])),
+        hardware_accelerated: query_integer(ffi::glx_extra::RENDERER_ACCELERATED_MESA)
+            .map(|accelerated| accelerated != 0),
+    })
+}
+
+/// Finds the highest OpenGL versions for which a context can be created on `screen_id`, by
+/// creating and destroying contexts.
+pub unsafe fn probe_versions(glx: &ffi::glx::Glx, xlib: &ffi::Xlib, display: *mut ffi::Display,
+                             screen_id: libc::c_int) -> Result<SupportedVersions, CreationError>
+{
+    let extensions = query_extensions(glx, display, screen_id);
+    // without it, the version of the context can't be requested
+    if !check_ext(&extensions, "GLX_ARB_create_context") {
+        return Err(CreationError::NotSupported("GLX_ARB_create_context is not supported"));
+    }
+
+    let config = find_configs(glx, xlib, display, screen_id, &probe_requirements())?
+        .into_iter()
+        .next()
+        .ok_or(CreationError::NoAvailablePixelFormat)?;
+
+    let visual_infos: ffi::glx::types::XVisualInfo = {
+        let vi = glx.GetVisualFromFBConfig(display as *mut _, config.fb_config);
+        if vi.is_null() {
+            return Err(CreationError::OsError(format!("glxGetVisualFromFBConfig failed")));
+        }
+        let vi_copy = ptr::read(vi as *const _);
+        (xlib.XFree)(vi as *mut _);
+        vi_copy
+    };
+
+    let extra_functions = ffi::glx_extra::Glx::load_with(|addr| {
+        with_c_str(addr, |s| glx.GetProcAddress(s as *const u8) as *const _)
+    });
+
+    find_max_versions(Api::OpenGl, |version, profile| {
+        match create_context(glx, &extra_functions, &extensions, xlib, version, profile, false,
+                             Robustness::NotRobust, ptr::null(), display, config.fb_config,
+                             &visual_infos, &[])
+        {
+            Ok(context) => {
+                glx.DestroyContext(display as *mut _, context);
+                true
+            },
+            Err(_) => false,
+        }
+    })
+}
+
 extern fn x_error_callback(_dpy: *mut ffi::Display, _err: *mut ffi::XErrorEvent) -> i32
 {
     0
@@ -307,7 +1166,7 @@ fn create_context(glx: &ffi::glx::Glx, extra_functions: &ffi::glx_extra::Glx, ex
                   version: (u8, u8), profile: Option<GlProfile>, debug: bool,
                   robustness: Robustness, share: ffi::GLXContext, display: *mut ffi::Display,
                   fb_config: ffi::glx::types::GLXFBConfig,
-                  visual_infos: &ffi::XVisualInfo)
+                  visual_infos: &ffi::XVisualInfo, raw_attributes: &[(i32, i32)])
                   -> Result<ffi::GLXContext, CreationError>
 {
     unsafe {
@@ -370,6 +1229,11 @@ fn create_context(glx: &ffi::glx::Glx, extra_functions: &ffi::glx_extra::Glx, ex
             attributes.push(ffi::glx_extra::CONTEXT_FLAGS_ARB as c_int);
             attributes.push(flags);
 
+            for &(attrib, value) in raw_attributes {
+                attributes.push(attrib as c_int);
+                attributes.push(value as c_int);
+            }
+
             attributes.push(0);
 
             extra_functions.CreateContextAttribsARB(display as *mut _, fb_config, share, 1,
@@ -397,132 +1261,209 @@ unsafe fn choose_fbconfig(glx: &ffi::glx::Glx, extensions: &str, xlib: &ffi::Xli
                           reqs: &PixelFormatRequirements, transparent: bool)
                           -> Result<(ffi::glx::types::GLXFBConfig, PixelFormat), ()>
 {
-    let descriptor = {
-        let mut out: Vec<c_int> = Vec::with_capacity(37);
+    let descriptor = fbconfig_descriptor(extensions, reqs)?;
 
-        out.push(ffi::glx::X_RENDERABLE as c_int);
-        out.push(1);
+    if !config_cache::contains_display(display as usize) {
+        invalidate_cache_on_close(xlib, display);
+    }
 
-        out.push(ffi::glx::X_VISUAL_TYPE as c_int);
-        out.push(ffi::glx::TRUE_COLOR as c_int);
+    // calling glXChooseFBConfig
+    let configs = config_cache::get_or_enumerate(display as usize, screen_id, &descriptor, || {
+        let mut num_configs = 1;
+        let configs = glx.ChooseFBConfig(display as *mut _, screen_id, descriptor.as_ptr(),
+                                        &mut num_configs);
+        if configs.is_null() { return Err(()); }
 
-        out.push(ffi::glx::DRAWABLE_TYPE as c_int);
-        out.push(ffi::glx::WINDOW_BIT as c_int);
+        let formats = slice::from_raw_parts(configs, num_configs as usize).iter()
+            .map(|&config| (config as usize, fbconfig_pixel_format(glx, xlib, display, config)))
+            .collect();
+        (xlib.XFree)(configs as *mut _);
+        Ok(formats)
+    })?;
+
+    // Transparency was requested, so only choose configs with 32 bits for RGBA.
+    let mut candidates: Vec<_> = configs.into_iter()
+        .filter(|&(_, ref format)| !transparent || format.transparent)
+        .collect();
+
+    let index = if reqs.needs_all_formats() {
+        let formats: Vec<_> = candidates.iter().map(|&(_, ref format)| format.clone()).collect();
+        reqs.select_format(&formats)
+    } else if candidates.is_empty() {
+        None
+    } else {
+        Some(0)
+    };
 
-        out.push(ffi::glx::RENDER_TYPE as c_int);
-        if reqs.float_color_buffer {
-            if check_ext(extensions, "GLX_ARB_fbconfig_float") {
-                out.push(ffi::glx_extra::RGBA_FLOAT_BIT_ARB as c_int);
-            } else {
-                return Err(());
-            }
-        } else {
-            out.push(ffi::glx::RGBA_BIT as c_int);
-        }
+    let (fb_config, pf_desc) = candidates.swap_remove(index.ok_or(())?);
+    Ok((fb_config as ffi::glx::types::GLXFBConfig, pf_desc))
+}
 
-        if let Some(color) = reqs.color_bits {
-            out.push(ffi::glx::RED_SIZE as c_int);
-            out.push((color / 3) as c_int);
-            out.push(ffi::glx::GREEN_SIZE as c_int);
-            out.push((color / 3 + if color % 3 != 0 { 1 } else { 0 }) as c_int);
-            out.push(ffi::glx::BLUE_SIZE as c_int);
-            out.push((color / 3 + if color % 3 == 2 { 1 } else { 0 }) as c_int);
-        }
+/// Registers a callback forgetting the cached FBConfigs of `display` when it is closed, since a
+/// display opened later may get the same address.
+unsafe fn invalidate_cache_on_close(xlib: &ffi::Xlib, display: *mut ffi::Display) {
+    unsafe extern "C" fn on_close(display: *mut ffi::Display, _: *mut ffi::XExtCodes) -> c_int {
+        config_cache::invalidate(display as usize);
+        0
+    }
 
-        if let Some(alpha) = reqs.alpha_bits {
-            out.push(ffi::glx::ALPHA_SIZE as c_int);
-            out.push(alpha as c_int);
-        }
+    let codes = (xlib.XAddExtension)(display);
+    if !codes.is_null() {
+        (xlib.XESetCloseDisplay)(display, (*codes).extension, Some(on_close));
+    }
+}
 
-        if let Some(depth) = reqs.depth_bits {
-            out.push(ffi::glx::DEPTH_SIZE as c_int);
-            out.push(depth as c_int);
-        }
+/// Returns the error to report when no FBConfig of the screen matches `reqs`, along with the
+/// nearest ones among all the FBConfigs of the screen.
+unsafe fn no_matching_fbconfig(glx: &ffi::glx::Glx, xlib: &ffi::Xlib,
+                               display: *mut ffi::Display, screen_id: libc::c_int,
+                               reqs: &PixelFormatRequirements) -> CreationError
+{
+    let mut num_configs = 0;
+    let configs = glx.GetFBConfigs(display as *mut _, screen_id, &mut num_configs);
+    if configs.is_null() {
+        return CreationError::NoAvailablePixelFormat;
+    }
 
-        if let Some(stencil) = reqs.stencil_bits {
-            out.push(ffi::glx::STENCIL_SIZE as c_int);
-            out.push(stencil as c_int);
-        }
+    let formats = slice::from_raw_parts(configs, num_configs as usize).iter()
+        .map(|&config| fbconfig_pixel_format(glx, xlib, display, config))
+        .collect();
+    (xlib.XFree)(configs as *mut _);
+    reqs.no_matching_format(formats)
+}
+
+/// Builds the attributes passed to `glXChooseFBConfig` for `reqs`.
+///
+/// Returns an error if the requirements can't be expressed with the available extensions.
+fn fbconfig_descriptor(extensions: &str, reqs: &PixelFormatRequirements)
+                       -> Result<Vec<c_int>, ()>
+{
+    let mut out: Vec<c_int> = Vec::with_capacity(37);
 
-        let double_buffer = reqs.double_buffer.unwrap_or(true);
-        out.push(ffi::glx::DOUBLEBUFFER as c_int);
-        out.push(if double_buffer { 1 } else { 0 });
+    out.push(ffi::glx::X_RENDERABLE as c_int);
+    out.push(1);
 
-        if let Some(multisampling) = reqs.multisampling {
-            if check_ext(extensions, "GLX_ARB_multisample") {
-                out.push(ffi::glx_extra::SAMPLE_BUFFERS_ARB as c_int);
-                out.push(if multisampling == 0 { 0 } else { 1 });
-                out.push(ffi::glx_extra::SAMPLES_ARB as c_int);
-                out.push(multisampling as c_int);
-            } else {
-                return Err(());
-            }
-        }
+    out.push(ffi::glx::X_VISUAL_TYPE as c_int);
+    out.push(ffi::glx::TRUE_COLOR as c_int);
 
-        out.push(ffi::glx::STEREO as c_int);
-        out.push(if reqs.stereoscopy { 1 } else { 0 });
+    out.push(ffi::glx::DRAWABLE_TYPE as c_int);
+    out.push(ffi::glx::WINDOW_BIT as c_int);
 
-        if reqs.srgb {
-            if check_ext(extensions, "GLX_ARB_framebuffer_sRGB") {
-                out.push(ffi::glx_extra::FRAMEBUFFER_SRGB_CAPABLE_ARB as c_int);
-                out.push(1);
-            } else if check_ext(extensions, "GLX_EXT_framebuffer_sRGB") {
-                out.push(ffi::glx_extra::FRAMEBUFFER_SRGB_CAPABLE_EXT as c_int);
-                out.push(1);
-            } else {
-                return Err(());
-            }
+    out.push(ffi::glx::RENDER_TYPE as c_int);
+    if reqs.float_color_buffer {
+        if check_ext(extensions, "GLX_ARB_fbconfig_float") {
+            out.push(ffi::glx_extra::RGBA_FLOAT_BIT_ARB as c_int);
+        } else {
+            return Err(());
         }
+    } else {
+        out.push(ffi::glx::RGBA_BIT as c_int);
+    }
 
-        match reqs.release_behavior {
-            ReleaseBehavior::Flush => (),
-            ReleaseBehavior::None => {
-                if check_ext(extensions, "GLX_ARB_context_flush_control") {
-                    out.push(ffi::glx_extra::CONTEXT_RELEASE_BEHAVIOR_ARB as c_int);
-                    out.push(ffi::glx_extra::CONTEXT_RELEASE_BEHAVIOR_NONE_ARB as c_int);
-                }
-            },
-        }
+    if let Some((red, green, blue)) = reqs.min_rgb_bits() {
+        out.push(ffi::glx::RED_SIZE as c_int);
+        out.push(red as c_int);
+        out.push(ffi::glx::GREEN_SIZE as c_int);
+        out.push(green as c_int);
+        out.push(ffi::glx::BLUE_SIZE as c_int);
+        out.push(blue as c_int);
+    }
 
-        out.push(ffi::glx::CONFIG_CAVEAT as c_int);
-        out.push(ffi::glx::DONT_CARE as c_int);
+    if let Some(alpha) = reqs.alpha_bits {
+        out.push(ffi::glx::ALPHA_SIZE as c_int);
+        out.push(alpha as c_int);
+    }
 
-        out.push(0);
-        out
-    };
+    if let Some(depth) = reqs.depth_bits {
+        out.push(ffi::glx::DEPTH_SIZE as c_int);
+        out.push(depth as c_int);
+    }
 
-    // calling glXChooseFBConfig
-    let fb_config = {
-        let mut num_configs = 1;
-        let configs = glx.ChooseFBConfig(display as *mut _, screen_id, descriptor.as_ptr(),
-                                        &mut num_configs);
-        if configs.is_null() { return Err(()); }
-        if num_configs == 0 { return Err(()); }
+    if let Some(stencil) = reqs.stencil_bits {
+        out.push(ffi::glx::STENCIL_SIZE as c_int);
+        out.push(stencil as c_int);
+    }
 
-        let config = if transparent {
-            let configs = slice::from_raw_parts(configs, num_configs as usize);
-            configs.iter().find(|&config| {
-                let vi = glx.GetVisualFromFBConfig(display as *mut _, *config);
-                // Transparency was requested, so only choose configs with 32 bits for RGBA.
-                let found = !vi.is_null() && (*vi).depth == 32;
-                (xlib.XFree)(vi as *mut _);
+    let double_buffer = reqs.double_buffer.unwrap_or(true);
+    out.push(ffi::glx::DOUBLEBUFFER as c_int);
+    out.push(if double_buffer { 1 } else { 0 });
 
-                found
-            })
+    if let Some(multisampling) = reqs.multisampling {
+        if check_ext(extensions, "GLX_ARB_multisample") {
+            out.push(ffi::glx_extra::SAMPLE_BUFFERS_ARB as c_int);
+            out.push(if multisampling == 0 { 0 } else { 1 });
+            out.push(ffi::glx_extra::SAMPLES_ARB as c_int);
+            out.push(multisampling as c_int);
         } else {
-            Some(&*configs)
-        };
+            return Err(());
+        }
+    }
 
-        let res = if let Some(&conf) = config {
-            Ok(conf)
-        } else {
-            Err(())
-        };
+    out.push(ffi::glx::STEREO as c_int);
+    out.push(if reqs.stereoscopy { 1 } else { 0 });
 
-        (xlib.XFree)(configs as *mut _);
-        res?
+    // without the extensions, no format is sRGB-capable
+    let srgb_capable = match reqs.srgb {
+        SrgbRequirement::Require | SrgbRequirement::Prefer => Some(1),
+        SrgbRequirement::Disallow => Some(0),
+        SrgbRequirement::DontCare => None,
     };
+    if let Some(srgb_capable) = srgb_capable {
+        if check_ext(extensions, "GLX_ARB_framebuffer_sRGB") {
+            out.push(ffi::glx_extra::FRAMEBUFFER_SRGB_CAPABLE_ARB as c_int);
+            out.push(srgb_capable);
+        } else if check_ext(extensions, "GLX_EXT_framebuffer_sRGB") {
+            out.push(ffi::glx_extra::FRAMEBUFFER_SRGB_CAPABLE_EXT as c_int);
+            out.push(srgb_capable);
+        } else if srgb_capable == 1 {
+            return Err(());
+        }
+    }
+
+    out.push(ffi::glx::CONFIG_CAVEAT as c_int);
+    out.push(if reqs.reject_caveats {
+        ffi::glx::NONE as c_int
+    } else {
+        ffi::glx::DONT_CARE as c_int
+    });
+
+    for &(attrib, value) in &reqs.raw_attributes {
+        out.push(attrib as c_int);
+        out.push(value as c_int);
+    }
+
+    out.push(0);
+    Ok(out)
+}
 
+/// Returns the largest pbuffer that can be created with `fb_config`.
+unsafe fn fbconfig_pbuffer_limits(glx: &ffi::glx::Glx, display: *mut ffi::Display,
+                                  fb_config: ffi::glx::types::GLXFBConfig)
+                                  -> Result<PbufferLimits, ContextError>
+{
+    let get_attrib = |attrib: u32| {
+        let mut value = 0;
+        if glx.GetFBConfigAttrib(display as *mut _, fb_config, attrib as c_int, &mut value) != 0 {
+            return Err(ContextError::OsError(format!("glXGetFBConfigAttrib failed")));
+        }
+        Ok(value)
+    };
+
+    if get_attrib(ffi::glx::DRAWABLE_TYPE)? & ffi::glx::PBUFFER_BIT as c_int == 0 {
+        return Err(ContextError::FunctionUnavailable);
+    }
+
+    Ok(PbufferLimits {
+        max_width: get_attrib(ffi::glx::MAX_PBUFFER_WIDTH)? as u32,
+        max_height: get_attrib(ffi::glx::MAX_PBUFFER_HEIGHT)? as u32,
+        max_pixels: get_attrib(ffi::glx::MAX_PBUFFER_PIXELS)? as u32,
+    })
+}
+
+/// Reads the pixel format described by `fb_config`.
+unsafe fn fbconfig_pixel_format(glx: &ffi::glx::Glx, xlib: &ffi::Xlib, display: *mut ffi::Display,
+                                fb_config: ffi::glx::types::GLXFBConfig) -> PixelFormat
+{
     let get_attrib = |attrib: c_int| -> i32 {
         let mut value = 0;
         glx.GetFBConfigAttrib(display as *mut _, fb_config, attrib, &mut value);
@@ -530,12 +1471,30 @@ unsafe fn choose_fbconfig(glx: &ffi::glx::Glx, extensions: &str, xlib: &ffi::Xli
         value
     };
 
-    let pf_desc = PixelFormat {
-        hardware_accelerated: get_attrib(ffi::glx::CONFIG_CAVEAT as c_int) !=
-                                                            ffi::glx::SLOW_CONFIG as c_int,
-        color_bits: get_attrib(ffi::glx::RED_SIZE as c_int) as u8 +
-                    get_attrib(ffi::glx::GREEN_SIZE as c_int) as u8 +
-                    get_attrib(ffi::glx::BLUE_SIZE as c_int) as u8,
+    let red_bits = get_attrib(ffi::glx::RED_SIZE as c_int) as u8;
+    let green_bits = get_attrib(ffi::glx::GREEN_SIZE as c_int) as u8;
+    let blue_bits = get_attrib(ffi::glx::BLUE_SIZE as c_int) as u8;
+    let srgb = get_attrib(ffi::glx_extra::FRAMEBUFFER_SRGB_CAPABLE_ARB as c_int) != 0 ||
+               get_attrib(ffi::glx_extra::FRAMEBUFFER_SRGB_CAPABLE_EXT as c_int) != 0;
+    let caveat = match get_attrib(ffi::glx::CONFIG_CAVEAT as c_int) as u32 {
+        ffi::glx::SLOW_CONFIG => ConfigCaveat::Slow,
+        ffi::glx::NON_CONFORMANT_CONFIG => ConfigCaveat::NonConformant,
+        _ => ConfigCaveat::None,
+    };
+
+    // only the visuals with 32 bits can be composited with their alpha
+    let vi = glx.GetVisualFromFBConfig(display as *mut _, fb_config);
+    let transparent = !vi.is_null() && (*vi).depth == 32;
+    if !vi.is_null() {
+        (xlib.XFree)(vi as *mut _);
+    }
+
+    PixelFormat {
+        hardware_accelerated: caveat != ConfigCaveat::Slow,
+        color_bits: red_bits + green_bits + blue_bits,
+        red_bits: red_bits,
+        green_bits: green_bits,
+        blue_bits: blue_bits,
         alpha_bits: get_attrib(ffi::glx::ALPHA_SIZE as c_int) as u8,
         depth_bits: get_attrib(ffi::glx::DEPTH_SIZE as c_int) as u8,
         stencil_bits: get_attrib(ffi::glx::STENCIL_SIZE as c_int) as u8,
@@ -546,11 +1505,20 @@ unsafe fn choose_fbconfig(glx: &ffi::glx::Glx, extensions: &str, xlib: &ffi::Xli
         } else {
             None
         },
-        srgb: get_attrib(ffi::glx_extra::FRAMEBUFFER_SRGB_CAPABLE_ARB as c_int) != 0 ||
-              get_attrib(ffi::glx_extra::FRAMEBUFFER_SRGB_CAPABLE_EXT as c_int) != 0,
-    };
-
-    Ok((fb_config, pf_desc))
+        srgb: srgb,
+        native_id: Some(NativeConfigId::Glx {
+            fbconfig_id: get_attrib(ffi::glx::FBCONFIG_ID as c_int),
+            visual_id: match get_attrib(ffi::glx::VISUAL_ID as c_int) {
+                0 => None,
+                id => Some(id),
+            },
+        }),
+        caveat: caveat,
+        colorspace: if srgb { Colorspace::Srgb } else { Colorspace::Linear },
+        float_color_buffer: get_attrib(ffi::glx::RENDER_TYPE as c_int) &
+                            ffi::glx_extra::RGBA_FLOAT_BIT_ARB as c_int != 0,
+        transparent: transparent,
+    }
 }
 
 /// Checks if `ext` is available.
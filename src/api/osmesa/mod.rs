@@ -3,12 +3,15 @@
 extern crate osmesa_sys;
 
 use Api;
+use Colorspace;
+use ConfigCaveat;
 use ContextError;
 use CreationError;
 use GlAttributes;
 use GlProfile;
 use GlRequest;
 use PixelFormat;
+use channel_bits;
 use PixelFormatRequirements;
 use Robustness;
 use libc;
@@ -16,18 +19,127 @@ use libc;
 use std::error::Error;
 use std::ffi::CString;
 use std::fmt::{Debug, Display, Error as FormatError, Formatter};
-use std::{mem, ptr};
-use std::os::raw::c_void;
+use std::{mem, ptr, slice};
+use std::os::raw::{c_int, c_uint, c_void};
 
 pub mod ffi {
     pub use super::osmesa_sys::OSMesaContext;
 }
 
+// From `osmesa.h`, which `osmesa_sys` doesn't export.
+const OSMESA_FORMAT: c_int = 0x22;
+const OSMESA_DEPTH_BITS: c_int = 0x30;
+const OSMESA_STENCIL_BITS: c_int = 0x31;
+const OSMESA_RGBA: c_int = 0x1908;
+const OSMESA_BGRA: c_int = 0x1;
+const OSMESA_ARGB: c_int = 0x2;
+const OSMESA_RGB: c_int = 0x1907;
+const OSMESA_BGR: c_int = 0x4;
+const OSMESA_RGB_565: c_int = 0x5;
+
+const GL_UNSIGNED_BYTE: c_uint = 0x1401;
+const GL_FLOAT: c_uint = 0x1406;
+const GL_UNSIGNED_SHORT_5_6_5: c_uint = 0x8363;
+
+/// The layout of the pixels of the buffer that an OSMesa context renders to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OsMesaFormat {
+    /// `OSMESA_RGBA`, with 8 bits per component.
+    Rgba,
+    /// `OSMESA_BGRA`, with 8 bits per component.
+    Bgra,
+    /// `OSMESA_ARGB`, with 8 bits per component.
+    Argb,
+    /// `OSMESA_RGB`, with 8 bits per component and no padding between the pixels.
+    Rgb,
+    /// `OSMESA_BGR`, with 8 bits per component and no padding between the pixels.
+    Bgr,
+    /// `OSMESA_RGB_565`, with 16 bits per pixel.
+    Rgb565,
+    /// `OSMESA_RGBA`, with a 32 bits float per component. Requires the gallium implementation
+    /// of OSMesa.
+    RgbaF32,
+}
+
+impl OsMesaFormat {
+    fn osmesa_format(&self) -> c_int {
+        match *self {
+            OsMesaFormat::Rgba | OsMesaFormat::RgbaF32 => OSMESA_RGBA,
+            OsMesaFormat::Bgra => OSMESA_BGRA,
+            OsMesaFormat::Argb => OSMESA_ARGB,
+            OsMesaFormat::Rgb => OSMESA_RGB,
+            OsMesaFormat::Bgr => OSMESA_BGR,
+            OsMesaFormat::Rgb565 => OSMESA_RGB_565,
+        }
+    }
+
+    /// The type passed to `OSMesaMakeCurrent`.
+    fn data_type(&self) -> c_uint {
+        match *self {
+            OsMesaFormat::RgbaF32 => GL_FLOAT,
+            OsMesaFormat::Rgb565 => GL_UNSIGNED_SHORT_5_6_5,
+            _ => GL_UNSIGNED_BYTE,
+        }
+    }
+
+    fn bytes_per_pixel(&self) -> u32 {
+        match *self {
+            OsMesaFormat::Rgba | OsMesaFormat::Bgra | OsMesaFormat::Argb => 4,
+            OsMesaFormat::Rgb | OsMesaFormat::Bgr => 3,
+            OsMesaFormat::Rgb565 => 2,
+            OsMesaFormat::RgbaF32 => 16,
+        }
+    }
+
+    /// The alignment that `OSMesaMakeCurrent` requires for the buffer.
+    fn alignment(&self) -> usize {
+        match *self {
+            OsMesaFormat::RgbaF32 => 4,
+            OsMesaFormat::Rgb565 => 2,
+            _ => 1,
+        }
+    }
+
+    /// Allocates a buffer of `dimensions` in this format. The buffer is made of words rather
+    /// than bytes so that it is aligned for all the formats.
+    fn alloc_buffer(&self, dimensions: (u32, u32)) -> Vec<u32> {
+        let buffer_size = dimensions.0 as usize * dimensions.1 as usize *
+                          self.bytes_per_pixel() as usize;
+        ::std::iter::repeat(unsafe { mem::uninitialized() }).take((buffer_size + 3) / 4).collect()
+    }
+
+    /// Returns the number of bits of the red, green and blue components, and of the alpha one.
+    fn color_alpha_bits(&self) -> (u8, u8) {
+        match *self {
+            OsMesaFormat::Rgba | OsMesaFormat::Bgra | OsMesaFormat::Argb => (24, 8),
+            OsMesaFormat::Rgb | OsMesaFormat::Bgr => (24, 0),
+            OsMesaFormat::Rgb565 => (16, 0),
+            OsMesaFormat::RgbaF32 => (96, 32),
+        }
+    }
+}
+
+/// The depth buffer of an OSMesa context, as returned by `OSMesaGetDepthBuffer`.
+#[derive(Debug, Clone, Copy)]
+pub struct OsMesaDepthBuffer<'a> {
+    /// The depth values, row by row from the bottom of the buffer.
+    pub data: &'a [u8],
+    /// The width of the buffer, in pixels.
+    pub width: u32,
+    /// The height of the buffer, in pixels.
+    pub height: u32,
+    /// The size of each depth value, in native endianness.
+    pub bytes_per_value: u32,
+}
+
 pub struct OsMesaContext {
     context: osmesa_sys::OSMesaContext,
     buffer: Vec<u32>,
     width: u32,
     height: u32,
+    format: OsMesaFormat,
+    depth_bits: u8,
+    stencil_bits: u8,
 }
 
 #[derive(Debug)]
@@ -67,17 +179,23 @@ impl Error for LoadingError {
 }
 
 impl OsMesaContext {
+    /// Builds a context rendering to a buffer of `dimensions` in `format`, which takes precedence
+    /// over the color and alpha bits of `pf_reqs`. If `format` is `None`, `OsMesaFormat::RgbaF32`
+    /// is used if a float color buffer is requested and `OsMesaFormat::Rgba` otherwise.
     pub fn new(
         dimensions: (u32, u32),
-        _pf_reqs: &PixelFormatRequirements,
+        pf_reqs: &PixelFormatRequirements,
         opengl: &GlAttributes<&OsMesaContext>,
+        format: Option<OsMesaFormat>,
     ) -> Result<OsMesaContext, CreationError>
     {
         osmesa_sys::OsMesa::try_loading()
             .map_err(LoadingError::new)
             .map_err(|e| CreationError::NoBackendAvailable(Box::new(e)))?;
 
-        if opengl.sharing.is_some() { panic!("Context sharing not possible with OsMesa") }
+        if opengl.raw_sharing.is_some() {
+            return Err(CreationError::NotSupported("context sharing not possible with OsMesa"));
+        }
 
         match opengl.robustness {
             Robustness::RobustNoResetNotification | Robustness::RobustLoseContextOnReset => {
@@ -86,9 +204,26 @@ impl OsMesaContext {
             _ => ()
         }
 
-        // TODO: use `pf_reqs` for the format
+        if opengl.protected_content {
+            return Err(CreationError::ProtectedContentNotSupported);
+        }
+        if opengl.no_error {
+            return Err(CreationError::NoErrorNotSupported);
+        }
+
+        let format = format.unwrap_or(if pf_reqs.float_color_buffer {
+            OsMesaFormat::RgbaF32
+        } else {
+            OsMesaFormat::Rgba
+        });
+        let depth_bits = pf_reqs.depth_bits.unwrap_or(0);
+        let stencil_bits = pf_reqs.stencil_bits.unwrap_or(0);
 
-        let mut attribs = Vec::new();
+        let mut attribs = vec![
+            OSMESA_FORMAT, format.osmesa_format(),
+            OSMESA_DEPTH_BITS, depth_bits as c_int,
+            OSMESA_STENCIL_BITS, stencil_bits as c_int,
+        ];
 
         if let Some(profile) = opengl.profile {
             attribs.push(osmesa_sys::OSMESA_PROFILE);
@@ -120,6 +255,8 @@ impl OsMesaContext {
                 attribs.push(osmesa_sys::OSMESA_CONTEXT_MINOR_VERSION);
                 attribs.push(minor as libc::c_int);
             },
+            // the list is expanded into `GlRequest::Specific` requests by `create_with_versions`
+            GlRequest::PreferredList(_) => return Err(CreationError::OpenGlVersionNotSupported),
         }
 
         // attribs array must be NULL terminated.
@@ -128,10 +265,13 @@ impl OsMesaContext {
         Ok(OsMesaContext {
             width: dimensions.0,
             height: dimensions.1,
-            buffer: ::std::iter::repeat(unsafe { mem::uninitialized() })
-                .take((dimensions.0 * dimensions.1) as usize).collect(),
+            format: format,
+            depth_bits: depth_bits,
+            stencil_bits: stencil_bits,
+            buffer: format.alloc_buffer(dimensions),
             context: unsafe {
-                let ctxt = osmesa_sys::OSMesaCreateContextAttribs(attribs.as_ptr(), ptr::null_mut());
+                let share = opengl.sharing.map_or(ptr::null_mut(), |ctxt| ctxt.context);
+                let ctxt = osmesa_sys::OSMesaCreateContextAttribs(attribs.as_ptr(), share);
                 if ctxt.is_null() {
                     return Err(CreationError::OsError("OSMesaCreateContextAttribs failed".to_string()));
                 }
@@ -145,15 +285,113 @@ impl OsMesaContext {
         &self.buffer
     }
 
+    /// Returns the buffer the context renders to, in its format and row by row from the bottom
+    /// of the buffer, once the rendering commands have finished if the context is current.
+    pub fn color_buffer(&self) -> &[u8] {
+        self.finish();
+        let len = self.width as usize * self.height as usize *
+                  self.format.bytes_per_pixel() as usize;
+        unsafe { slice::from_raw_parts(self.buffer.as_ptr() as *const u8, len) }
+    }
+
+    /// Returns the depth buffer of the context, once the rendering commands have finished if the
+    /// context is current. Returns `None` if the context has no depth buffer or was never made
+    /// current.
+    pub fn depth_buffer(&self) -> Option<OsMesaDepthBuffer> {
+        self.finish();
+        unsafe {
+            let mut width = 0;
+            let mut height = 0;
+            let mut bytes_per_value = 0;
+            let mut buffer = ptr::null_mut();
+            if osmesa_sys::OSMesaGetDepthBuffer(self.context, &mut width, &mut height,
+                                                &mut bytes_per_value, &mut buffer) == 0 ||
+               buffer.is_null()
+            {
+                return None;
+            }
+
+            let len = width as usize * height as usize * bytes_per_value as usize;
+            Some(OsMesaDepthBuffer {
+                data: slice::from_raw_parts(buffer as *const u8, len),
+                width: width as u32,
+                height: height as u32,
+                bytes_per_value: bytes_per_value as u32,
+            })
+        }
+    }
+
+    /// Waits for the rendering commands of the context to finish, so that its buffers can be
+    /// read. Does nothing if the context isn't current.
+    fn finish(&self) {
+        if !self.is_current() {
+            return;
+        }
+        let finish = self.get_proc_address("glFinish");
+        if !finish.is_null() {
+            unsafe {
+                let finish: extern "system" fn() = mem::transmute(finish);
+                finish();
+            }
+        }
+    }
+
+    /// Reallocates the buffer of the context for `dimensions`, keeping the context and its
+    /// objects. If the context is current, it is made current again with the new buffer.
+    pub fn resize_buffer(&mut self, dimensions: (u32, u32)) -> Result<(), ContextError> {
+        let was_current = self.is_current();
+        self.buffer = self.format.alloc_buffer(dimensions);
+        self.width = dimensions.0;
+        self.height = dimensions.1;
+
+        if was_current {
+            unsafe { self.make_current()?; }
+        }
+        Ok(())
+    }
+
+    /// Makes the context current, rendering to `buffer` of `width` by `height` pixels instead of
+    /// its own buffer until it is made current again. `buffer` must hold the pixels in the format
+    /// of the context and be aligned for its components.
+    ///
+    /// The buffer must stay alive and must not be accessed while the context renders to it.
+    pub unsafe fn bind_to_buffer(&self, buffer: &mut [u8], width: u32, height: u32)
+        -> Result<(), ContextError>
+    {
+        let size = width as usize * height as usize * self.format.bytes_per_pixel() as usize;
+        if buffer.len() < size {
+            return Err(ContextError::OsError(format!("the buffer holds {} bytes but {} are \
+                                                      needed", buffer.len(), size)));
+        }
+        if buffer.as_ptr() as usize % self.format.alignment() != 0 {
+            return Err(ContextError::OsError(format!("the buffer isn't aligned to {} bytes",
+                                                     self.format.alignment())));
+        }
+
+        let ret = osmesa_sys::OSMesaMakeCurrent(self.context, buffer.as_mut_ptr() as *mut _,
+                                                self.format.data_type(), width as libc::c_int,
+                                                height as libc::c_int);
+        if ret == 0 {
+            return Err(ContextError::OsError(format!("OSMesaMakeCurrent failed")));
+        }
+
+        Ok(())
+    }
+
     #[inline]
     pub fn get_dimensions(&self) -> (u32, u32) {
         (self.width, self.height)
     }
 
+    #[inline]
+    pub fn get_format(&self) -> OsMesaFormat {
+        self.format
+    }
+
     #[inline]
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         let ret = osmesa_sys::OSMesaMakeCurrent(self.context, self.buffer.as_ptr()
-                                                as *mut _, 0x1401, self.width
+                                                as *mut _, self.format.data_type(), self.width
                                                 as libc::c_int, self.height as libc::c_int);
 
         // an error can only happen in case of invalid parameter, which would indicate a bug
@@ -165,6 +403,21 @@ impl OsMesaContext {
         Ok(())
     }
 
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        if !self.is_current() {
+            return Ok(());
+        }
+
+        // passing a null context and a null buffer releases the current context
+        let ret = osmesa_sys::OSMesaMakeCurrent(ptr::null_mut(), ptr::null_mut(), 0, 0, 0);
+        if ret == 0 {
+            return Err(ContextError::OsError(format!("OSMesaMakeCurrent failed")));
+        }
+
+        Ok(())
+    }
+
     #[inline]
     pub fn is_current(&self) -> bool {
         unsafe { osmesa_sys::OSMesaGetCurrentContext() == self.context }
@@ -189,7 +442,27 @@ impl OsMesaContext {
 
     #[inline]
     pub fn get_pixel_format(&self) -> PixelFormat {
-        unimplemented!();
+        let (color_bits, alpha_bits) = self.format.color_alpha_bits();
+        let (red_bits, green_bits, blue_bits) = channel_bits(color_bits);
+        PixelFormat {
+            hardware_accelerated: false,
+            color_bits: color_bits,
+            red_bits: red_bits,
+            green_bits: green_bits,
+            blue_bits: blue_bits,
+            alpha_bits: alpha_bits,
+            depth_bits: self.depth_bits,
+            stencil_bits: self.stencil_bits,
+            stereoscopy: false,
+            double_buffer: false,
+            multisampling: None,
+            srgb: false,
+            native_id: None,
+            caveat: ConfigCaveat::Slow,
+            colorspace: Colorspace::Linear,
+            float_color_buffer: self.format == OsMesaFormat::RgbaF32,
+            transparent: false,
+        }
     }
 
     #[inline]
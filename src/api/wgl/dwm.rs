@@ -0,0 +1,26 @@
+#![cfg(any(target_os = "windows"))]
+
+use std::{mem, ptr};
+
+use winapi::shared::winerror::SUCCEEDED;
+use winapi::um::dwmapi::{DwmFlush, DwmGetCompositionTiming, DWM_TIMING_INFO};
+
+/// Returns the number of compositions done by the desktop window manager, or `None` if the
+/// desktop isn't composited.
+pub fn composition_count() -> Option<u64> {
+    unsafe {
+        let mut timing: DWM_TIMING_INFO = mem::zeroed();
+        timing.cbSize = mem::size_of::<DWM_TIMING_INFO>() as _;
+        // the window must be null since Windows 8.1
+        if SUCCEEDED(DwmGetCompositionTiming(ptr::null_mut(), &mut timing)) {
+            Some(timing.cFrame)
+        } else {
+            None
+        }
+    }
+}
+
+/// Blocks until the next composition of the desktop window manager.
+pub fn flush() {
+    unsafe { DwmFlush(); }
+}
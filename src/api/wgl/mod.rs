@@ -1,24 +1,35 @@
 #![cfg(any(target_os = "windows"))]
 
+use Colorspace;
+use ConfigCaveat;
 use ContextError;
 use CreationError;
 use GlAttributes;
 use GlRequest;
 use GlProfile;
+use NativeConfigId;
+use PbufferLimits;
 use PixelFormat;
 use PixelFormatRequirements;
+use PresentMode;
+use RawContextHandle;
 use ReleaseBehavior;
 use Robustness;
+use SrgbRequirement;
+use SwapInterval;
 use Api;
 
 use self::make_current_guard::CurrentContextGuard;
+use self::vblank::MonitorVblank;
 
 use std::ffi::{CStr, CString, OsStr};
 use std::os::raw::{c_void, c_int};
 use std::os::windows::ffi::OsStrExt;
 use std::{io, mem, ptr};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use winapi::shared::windef::{HDC, HGLRC, HWND};
+use winapi::shared::windef::{HBITMAP, HDC, HGDIOBJ, HGLRC, HWND};
 use winapi::shared::minwindef::HMODULE;
 use winapi::um::wingdi::*;
 use winapi::shared::minwindef::*;
@@ -26,8 +37,10 @@ use winapi::um::winuser::*;
 use winapi::um::libloaderapi::*;
 use winapi::shared::ntdef::LPCWSTR;
 
+mod dwm;
 mod make_current_guard;
 mod gl;
+mod vblank;
 
 /// A WGL context.
 ///
@@ -35,8 +48,21 @@ mod gl;
 pub struct Context {
     context: ContextWrapper,
 
+    /// The bitmap the context draws to if it was built with `new_dib_section`, destroyed after
+    /// the context.
+    dib_section: Option<DibSectionWrapper>,
+
+    /// The pbuffer the context draws to if it was built with `new_pbuffer`, destroyed after the
+    /// context.
+    pbuffer: Option<PbufferWrapper>,
+
+    /// The invisible window that the pbuffer was created from, destroyed after the pbuffer.
+    hidden_window: Option<WindowWrapper>,
+
     hdc: HDC,
 
+    window: HWND,
+
     /// Bound to `opengl32.dll`.
     ///
     /// `wglGetProcAddress` returns null for GL 1.1 functions because they are
@@ -45,6 +71,21 @@ pub struct Context {
 
     /// The pixel format that has been used to create this context.
     pixel_format: PixelFormat,
+
+    /// Waits for the vertical blanks of the window's monitor before swapping, if enabled with
+    /// `set_monitor_vsync`.
+    monitor_vblank: Mutex<Option<MonitorVblank>>,
+
+    /// Waits for the composition of the desktop after swapping, if enabled with
+    /// `set_dwm_low_latency`.
+    dwm_low_latency: AtomicBool,
+}
+
+/// The WGL context and device context that were current on the calling thread at some point, so
+/// that they can be made current again later.
+pub struct CurrentState {
+    hdc: HDC,
+    hglrc: HGLRC,
 }
 
 /// A simple wrapper that destroys the window when it is destroyed.
@@ -59,6 +100,53 @@ impl Drop for WindowWrapper {
     }
 }
 
+/// A device-independent bitmap that a context draws to, selected into a memory device context.
+///
+/// The pixels are 32 bits BGRA values, stored from the bottom row to the top row like the
+/// framebuffers of OpenGL.
+#[derive(Debug, Clone, Copy)]
+pub struct DibSection {
+    /// The memory device context in which the bitmap is selected, which can be passed to
+    /// `BitBlt` to copy the bitmap to a window.
+    pub hdc: HDC,
+    /// The bitmap returned by `CreateDIBSection`.
+    pub hbitmap: HBITMAP,
+    /// The pixels of the bitmap.
+    pub bits: *mut c_void,
+    /// The width and height of the bitmap.
+    pub dimensions: (u32, u32),
+}
+
+/// Deletes the memory device context and the bitmap when it is destroyed.
+struct DibSectionWrapper(DibSection);
+
+impl Drop for DibSectionWrapper {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            DeleteDC(self.0.hdc);
+            DeleteObject(self.0.hbitmap as HGDIOBJ);
+        }
+    }
+}
+
+/// A pbuffer created with `WGL_ARB_pbuffer`, released and destroyed when it is destroyed.
+struct PbufferWrapper {
+    extra_functions: gl::wgl_extra::Wgl,
+    pbuffer: gl::wgl_extra::types::HPBUFFERARB,
+    hdc: HDC,
+}
+
+impl Drop for PbufferWrapper {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            self.extra_functions.ReleasePbufferDCARB(self.pbuffer, self.hdc as *const _);
+            self.extra_functions.DestroyPbufferARB(self.pbuffer);
+        }
+    }
+}
+
 /// Wraps around a context so that it is destroyed when necessary.
 struct ContextWrapper(HGLRC);
 
@@ -82,6 +170,10 @@ impl Context {
     pub unsafe fn new(pf_reqs: &PixelFormatRequirements, opengl: &GlAttributes<HGLRC>,
                       window: HWND) -> Result<Context, CreationError>
     {
+        if opengl.protected_content {
+            return Err(CreationError::ProtectedContentNotSupported);
+        }
+
         let hdc = GetDC(window);
         if hdc.is_null() {
             let err = Err(CreationError::OsError(format!("GetDC function failed: {}",
@@ -93,29 +185,19 @@ impl Context {
         let extra_functions = load_extra_functions(window)?;
 
         // getting the list of the supported extensions
-        let extensions = if extra_functions.GetExtensionsStringARB.is_loaded() {
-            let data = extra_functions.GetExtensionsStringARB(hdc as *const _);
-            let data = CStr::from_ptr(data).to_bytes().to_vec();
-            String::from_utf8(data).unwrap()
-
-        } else if extra_functions.GetExtensionsStringEXT.is_loaded() {
-            let data = extra_functions.GetExtensionsStringEXT();
-            let data = CStr::from_ptr(data).to_bytes().to_vec();
-            String::from_utf8(data).unwrap()
-
-        } else {
-            format!("")
-        };
+        let extensions = extensions_string(&extra_functions, hdc);
 
         // calling SetPixelFormat
         let pixel_format = {
             let (id, f) = if extensions.split(' ').find(|&i| i == "WGL_ARB_pixel_format")
                                                   .is_some()
             {
-                choose_arb_pixel_format(&extra_functions, &extensions, hdc, pf_reqs)
-                                            .map_err(|_| CreationError::NoAvailablePixelFormat)?
+                choose_arb_pixel_format(&extra_functions, &extensions, hdc, pf_reqs, false)
+                    .map_err(|_| {
+                        no_matching_arb_pixel_format(&extra_functions, &extensions, hdc, pf_reqs)
+                    })?
             } else {
-                choose_native_pixel_format(hdc, pf_reqs)
+                choose_native_pixel_format(hdc, pf_reqs, false)
                                             .map_err(|_| CreationError::NoAvailablePixelFormat)?
             };
 
@@ -141,9 +223,215 @@ impl Context {
 
         Ok(Context {
             context: context,
+            dib_section: None,
+            pbuffer: None,
+            hidden_window: None,
+            hdc: hdc,
+            window: window,
+            gl_library: gl_library,
+            pixel_format: pixel_format,
+            monitor_vblank: Mutex::new(None),
+            dwm_low_latency: AtomicBool::new(false),
+        })
+    }
+
+    /// Attempt to build a new WGL context that draws to a new device-independent bitmap of
+    /// `dimensions`.
+    ///
+    /// Only the generic implementation of Microsoft can draw to bitmaps, so the context is
+    /// software rendered and supports OpenGL 1.1 at most.
+    pub unsafe fn new_dib_section(pf_reqs: &PixelFormatRequirements,
+                                  opengl: &GlAttributes<HGLRC>, dimensions: (u32, u32))
+                                  -> Result<Context, CreationError>
+    {
+        match opengl.version {
+            GlRequest::Latest => (),
+            GlRequest::Specific(Api::OpenGl, version) |
+            GlRequest::GlThenGles { opengl_version: version, .. } if version <= (1, 1) => (),
+            _ => return Err(CreationError::OpenGlVersionNotSupported),
+        }
+        if opengl.sharing.is_some() || opengl.raw_sharing.is_some() {
+            return Err(CreationError::NotSupported("sharing lists with a DIB section context"));
+        }
+        if opengl.protected_content {
+            return Err(CreationError::ProtectedContentNotSupported);
+        }
+
+        let hdc = CreateCompatibleDC(ptr::null_mut());
+        if hdc.is_null() {
+            return Err(CreationError::OsError(format!("CreateCompatibleDC function failed: {}",
+                                                      io::Error::last_os_error())));
+        }
+
+        let mut info: BITMAPINFO = mem::zeroed();
+        info.bmiHeader.biSize = mem::size_of::<BITMAPINFOHEADER>() as DWORD;
+        info.bmiHeader.biWidth = dimensions.0 as i32;
+        // a positive height makes the bitmap bottom-up, like the framebuffers of OpenGL
+        info.bmiHeader.biHeight = dimensions.1 as i32;
+        info.bmiHeader.biPlanes = 1;
+        info.bmiHeader.biBitCount = 32;
+        info.bmiHeader.biCompression = BI_RGB;
+
+        let mut bits = ptr::null_mut();
+        let hbitmap = CreateDIBSection(hdc, &info, DIB_RGB_COLORS, &mut bits, ptr::null_mut(), 0);
+        if hbitmap.is_null() {
+            let err = io::Error::last_os_error();
+            DeleteDC(hdc);
+            return Err(CreationError::OsError(format!("CreateDIBSection function failed: {}",
+                                                      err)));
+        }
+        let dib_section = DibSectionWrapper(DibSection {
+            hdc: hdc,
+            hbitmap: hbitmap,
+            bits: bits,
+            dimensions: dimensions,
+        });
+        SelectObject(hdc, hbitmap as HGDIOBJ);
+
+        let (id, pixel_format) = choose_native_pixel_format(hdc, pf_reqs, true)
+            .map_err(|_| CreationError::NoAvailablePixelFormat)?;
+        set_pixel_format(hdc, id)?;
+
+        // the WGL extensions can't be loaded without a window, and the generic implementation
+        // doesn't support them anyway
+        let context = create_context(None, ptr::null_mut(), hdc)?;
+        let gl_library = load_opengl32_dll()?;
+
+        Ok(Context {
+            context: context,
+            dib_section: Some(dib_section),
+            pbuffer: None,
+            hidden_window: None,
+            hdc: hdc,
+            window: ptr::null_mut(),
+            gl_library: gl_library,
+            pixel_format: pixel_format,
+            monitor_vblank: Mutex::new(None),
+            dwm_low_latency: AtomicBool::new(false),
+        })
+    }
+
+    /// Attempt to build a new WGL context that draws to a pbuffer of `dimensions` with
+    /// `WGL_ARB_pbuffer`.
+    ///
+    /// The WGL extensions can only be loaded with a window, so an invisible window is created
+    /// for the pbuffer and kept alive as long as the context.
+    pub unsafe fn new_pbuffer(pf_reqs: &PixelFormatRequirements, opengl: &GlAttributes<HGLRC>,
+                              dimensions: (u32, u32)) -> Result<Context, CreationError>
+    {
+        if opengl.protected_content {
+            return Err(CreationError::ProtectedContentNotSupported);
+        }
+
+        let window = create_hidden_window()?;
+        let extra_functions = load_extra_functions(window.0)?;
+        let extensions = extensions_string(&extra_functions, window.1);
+
+        if extensions.split(' ').find(|&i| i == "WGL_ARB_pbuffer").is_none() ||
+           extensions.split(' ').find(|&i| i == "WGL_ARB_pixel_format").is_none()
+        {
+            return Err(CreationError::NotSupported("WGL_ARB_pbuffer not available"));
+        }
+
+        let (id, pixel_format) = choose_arb_pixel_format(&extra_functions, &extensions, window.1,
+                                                         pf_reqs, true)
+            .map_err(|_| {
+                no_matching_arb_pixel_format(&extra_functions, &extensions, window.1, pf_reqs)
+            })?;
+
+        let attributes = [0];
+        let pbuffer = extra_functions.CreatePbufferARB(window.1 as *const _, id,
+                                                       dimensions.0 as c_int,
+                                                       dimensions.1 as c_int,
+                                                       attributes.as_ptr());
+        if pbuffer.is_null() {
+            return Err(CreationError::OsError(format!("wglCreatePbufferARB failed: {}",
+                                                      io::Error::last_os_error())));
+        }
+
+        let hdc = extra_functions.GetPbufferDCARB(pbuffer) as HDC;
+        if hdc.is_null() {
+            let err = io::Error::last_os_error();
+            extra_functions.DestroyPbufferARB(pbuffer);
+            return Err(CreationError::OsError(format!("wglGetPbufferDCARB failed: {}", err)));
+        }
+        let pbuffer = PbufferWrapper {
+            extra_functions: extra_functions.clone(),
+            pbuffer: pbuffer,
+            hdc: hdc,
+        };
+
+        let context = create_context(Some((&extra_functions, pf_reqs, opengl, &extensions)),
+                                     window.0, hdc)?;
+        let gl_library = load_opengl32_dll()?;
+
+        Ok(Context {
+            context: context,
+            dib_section: None,
+            pbuffer: Some(pbuffer),
+            hidden_window: Some(window),
+            hdc: hdc,
+            // the pbuffer isn't presented, so it has no window to synchronize with
+            window: ptr::null_mut(),
+            gl_library: gl_library,
+            pixel_format: pixel_format,
+            monitor_vblank: Mutex::new(None),
+            dwm_low_latency: AtomicBool::new(false),
+        })
+    }
+
+    /// Returns the bitmap the context draws to if it was built with `new_dib_section`.
+    #[inline]
+    pub fn dib_section(&self) -> Option<DibSection> {
+        self.dib_section.as_ref().map(|dib_section| dib_section.0)
+    }
+
+    /// Destroys this context and builds a new one drawing to the same window, for example after
+    /// the driver was reset. The pixel format of a window can't be changed once set, so the new
+    /// context keeps the one of this context.
+    ///
+    /// Only the contexts of windows can be recreated.
+    pub unsafe fn recreate(self, opengl: &GlAttributes<HGLRC>) -> Result<Context, CreationError> {
+        if self.window.is_null() || self.dib_section.is_some() || self.pbuffer.is_some() {
+            let err = "only the contexts of windows can be recreated";
+            return Err(CreationError::NotSupported(err));
+        }
+        if opengl.protected_content {
+            return Err(CreationError::ProtectedContentNotSupported);
+        }
+
+        let Context {
+            context, hdc, window, gl_library, pixel_format, monitor_vblank, dwm_low_latency, ..
+        } = self;
+        // the resources of the lost context are released before allocating new ones
+        drop(context);
+
+        let extra_functions = load_extra_functions(window)?;
+        let extensions = extensions_string(&extra_functions, hdc);
+        // the pixel format requirements are only used to choose the pixel format
+        let context = create_context(Some((&extra_functions, &PixelFormatRequirements::default(),
+                                           opengl, &extensions)),
+                                     window, hdc)?;
+
+        if extensions.split(' ').find(|&i| i == "WGL_EXT_swap_control").is_some() {
+            let _guard = CurrentContextGuard::make_current(hdc, context.0)?;
+
+            if extra_functions.SwapIntervalEXT(if opengl.vsync { 1 } else { 0 }) == 0 {
+                return Err(CreationError::OsError(format!("wglSwapIntervalEXT failed")));
+            }
+        }
+
+        Ok(Context {
+            context: context,
+            dib_section: None,
+            pbuffer: None,
+            hidden_window: None,
             hdc: hdc,
+            window: window,
             gl_library: gl_library,
             pixel_format: pixel_format,
+            monitor_vblank: monitor_vblank,
+            dwm_low_latency: dwm_low_latency,
         })
     }
 
@@ -162,11 +450,274 @@ impl Context {
         }
     }
 
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        if !self.is_current() {
+            return Ok(());
+        }
+
+        if gl::wgl::MakeCurrent(self.hdc as *const _, ptr::null()) != 0 {
+            Ok(())
+        } else {
+            Err(ContextError::IoError(io::Error::last_os_error()))
+        }
+    }
+
     #[inline]
     pub fn is_current(&self) -> bool {
         unsafe { gl::wgl::GetCurrentContext() == self.context.0 as *const c_void }
     }
 
+    /// Checks that the window of the context still exists. WGL can't check the context itself,
+    /// whose loss is only reported by `glGetGraphicsResetStatus`.
+    pub fn validate(&self) -> Result<(), ContextError> {
+        if !self.window.is_null() && unsafe { IsWindow(self.window) } == 0 {
+            return Err(ContextError::OsError("the window of the context was destroyed".into()));
+        }
+        Ok(())
+    }
+
+    /// Sets the number of screen refreshes to wait for before swapping the buffers. The context
+    /// must be current.
+    pub fn set_swap_interval(&self, interval: SwapInterval) -> Result<(), ContextError> {
+        let extra_functions = gl::wgl_extra::Wgl::load_with(|addr| {
+            let addr = CString::new(addr.as_bytes()).unwrap();
+            unsafe { gl::wgl::GetProcAddress(addr.as_ptr()) as *const c_void }
+        });
+        if !extra_functions.SwapIntervalEXT.is_loaded() {
+            return Err(ContextError::FunctionUnavailable);
+        }
+
+        let interval = match interval {
+            SwapInterval::Wait(interval) => interval as c_int,
+            // late swaps tear instead of waiting for the next refresh when the interval is
+            // negative
+            SwapInterval::Adaptive(interval) => {
+                if !self.has_swap_control_tear(&extra_functions) {
+                    return Err(ContextError::FunctionUnavailable);
+                }
+                -(interval as c_int)
+            },
+        };
+
+        if unsafe { extra_functions.SwapIntervalEXT(interval) } != 0 {
+            Ok(())
+        } else {
+            Err(ContextError::IoError(io::Error::last_os_error()))
+        }
+    }
+
+    /// Returns the present modes allowed by `WGL_EXT_swap_control`.
+    pub fn supported_present_modes(&self) -> Result<Vec<PresentMode>, ContextError> {
+        let extra_functions = gl::wgl_extra::Wgl::load_with(|addr| {
+            let addr = CString::new(addr.as_bytes()).unwrap();
+            unsafe { gl::wgl::GetProcAddress(addr.as_ptr()) as *const c_void }
+        });
+        if !extra_functions.SwapIntervalEXT.is_loaded() {
+            return Err(ContextError::FunctionUnavailable);
+        }
+
+        let mut modes = vec![PresentMode::Immediate, PresentMode::Fifo];
+        if self.has_swap_control_tear(&extra_functions) {
+            modes.push(PresentMode::FifoRelaxed);
+        }
+        Ok(modes)
+    }
+
+    /// Returns the largest pbuffer that `WGL_ARB_pbuffer` can create with the pixel format of
+    /// this context.
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        let extra_functions = gl::wgl_extra::Wgl::load_with(|addr| {
+            let addr = CString::new(addr.as_bytes()).unwrap();
+            unsafe { gl::wgl::GetProcAddress(addr.as_ptr()) as *const c_void }
+        });
+        if !extra_functions.GetPixelFormatAttribivARB.is_loaded() ||
+           !extra_functions.CreatePbufferARB.is_loaded()
+        {
+            return Err(ContextError::FunctionUnavailable);
+        }
+
+        let attribs = [
+            gl::wgl_extra::DRAW_TO_PBUFFER_ARB as c_int,
+            gl::wgl_extra::MAX_PBUFFER_WIDTH_ARB as c_int,
+            gl::wgl_extra::MAX_PBUFFER_HEIGHT_ARB as c_int,
+            gl::wgl_extra::MAX_PBUFFER_PIXELS_ARB as c_int,
+        ];
+        let mut values = [0; 4];
+        unsafe {
+            let format_id = GetPixelFormat(self.hdc);
+            if extra_functions.GetPixelFormatAttribivARB(self.hdc as *const _, format_id, 0,
+                                                         attribs.len() as UINT, attribs.as_ptr(),
+                                                         values.as_mut_ptr()) == 0
+            {
+                return Err(ContextError::IoError(io::Error::last_os_error()));
+            }
+        }
+
+        if values[0] == 0 {
+            return Err(ContextError::FunctionUnavailable);
+        }
+        Ok(PbufferLimits {
+            max_width: values[1] as u32,
+            max_height: values[2] as u32,
+            max_pixels: values[3] as u32,
+        })
+    }
+
+    fn has_swap_control_tear(&self, extra_functions: &gl::wgl_extra::Wgl) -> bool {
+        let extensions = unsafe {
+            if extra_functions.GetExtensionsStringARB.is_loaded() {
+                let data = extra_functions.GetExtensionsStringARB(self.hdc as *const _);
+                CStr::from_ptr(data).to_string_lossy().into_owned()
+            } else if extra_functions.GetExtensionsStringEXT.is_loaded() {
+                let data = extra_functions.GetExtensionsStringEXT();
+                CStr::from_ptr(data).to_string_lossy().into_owned()
+            } else {
+                String::new()
+            }
+        };
+        extensions.split(' ').find(|&i| i == "WGL_EXT_swap_control_tear").is_some()
+    }
+
+    /// Returns the swap interval of the window. The context must be current.
+    pub fn get_swap_interval(&self) -> Result<SwapInterval, ContextError> {
+        let extra_functions = gl::wgl_extra::Wgl::load_with(|addr| {
+            let addr = CString::new(addr.as_bytes()).unwrap();
+            unsafe { gl::wgl::GetProcAddress(addr.as_ptr()) as *const c_void }
+        });
+        if !extra_functions.GetSwapIntervalEXT.is_loaded() {
+            return Err(ContextError::FunctionUnavailable);
+        }
+
+        let interval = unsafe { extra_functions.GetSwapIntervalEXT() };
+        if interval < 0 {
+            Ok(SwapInterval::Adaptive(-interval as u32))
+        } else {
+            Ok(SwapInterval::Wait(interval as u32))
+        }
+    }
+
+    /// Returns the smallest and largest swap intervals supported by the window.
+    ///
+    /// `WGL_EXT_swap_control` doesn't report a maximum, so any positive interval is accepted.
+    pub fn get_swap_interval_range(&self) -> Result<(u32, u32), ContextError> {
+        let extra_functions = gl::wgl_extra::Wgl::load_with(|addr| {
+            let addr = CString::new(addr.as_bytes()).unwrap();
+            unsafe { gl::wgl::GetProcAddress(addr.as_ptr()) as *const c_void }
+        });
+        if !extra_functions.SwapIntervalEXT.is_loaded() {
+            return Err(ContextError::FunctionUnavailable);
+        }
+        Ok((0, c_int::max_value() as u32))
+    }
+
+    /// Makes the window join the swap group `group`, or leaves the current one if `group` is 0.
+    pub fn join_swap_group(&self, group: u32) -> Result<(), ContextError> {
+        let extra_functions = self.load_swap_group()?;
+        if unsafe { extra_functions.JoinSwapGroupNV(self.hdc as *const _, group) } != 0 {
+            Ok(())
+        } else {
+            Err(ContextError::IoError(io::Error::last_os_error()))
+        }
+    }
+
+    /// Binds the swap group `group` to the swap barrier `barrier`, or unbinds it if `barrier` is
+    /// 0.
+    pub fn bind_swap_barrier(&self, group: u32, barrier: u32) -> Result<(), ContextError> {
+        let extra_functions = self.load_swap_group()?;
+        if unsafe { extra_functions.BindSwapBarrierNV(group, barrier) } != 0 {
+            Ok(())
+        } else {
+            Err(ContextError::IoError(io::Error::last_os_error()))
+        }
+    }
+
+    /// Synchronizes the swaps with the vertical blanks of the monitor the window is on, instead
+    /// of the primary monitor. The monitor is looked up again before every swap, so that vsync
+    /// follows the window when it is moved to another monitor.
+    ///
+    /// Enabling it sets the swap interval to 0, and disabling it sets the swap interval back to
+    /// 1. The context must be current.
+    pub fn set_monitor_vsync(&self, enabled: bool) -> Result<(), ContextError> {
+        if self.window.is_null() {
+            return Err(ContextError::FunctionUnavailable);
+        }
+
+        let mut monitor_vblank = self.monitor_vblank.lock().unwrap();
+        if enabled {
+            if monitor_vblank.is_none() {
+                *monitor_vblank = Some(unsafe { MonitorVblank::new(self.window)? });
+            }
+        } else {
+            *monitor_vblank = None;
+        }
+
+        // the driver must not wait for the primary monitor in addition to the wait before the
+        // swap
+        match self.set_swap_interval(SwapInterval::Wait(if enabled { 0 } else { 1 })) {
+            Ok(()) | Err(ContextError::FunctionUnavailable) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Enables or disables waiting for the desktop window manager to compose the frame after each
+    /// swap, instead of letting the driver queue frames. When the desktop is composited, this
+    /// removes up to a few frames of latency at the cost of throughput, since the application
+    /// can't render the next frame while the current one waits to be composed. Has no effect
+    /// when the desktop isn't composited.
+    ///
+    /// Enabling it sets the swap interval to 0, as the composition already synchronizes with the
+    /// vertical blanks, and disabling it sets the swap interval back to 1. The context must be
+    /// current.
+    pub fn set_dwm_low_latency(&self, enabled: bool) -> Result<(), ContextError> {
+        self.dwm_low_latency.store(enabled, Ordering::Relaxed);
+
+        match self.set_swap_interval(SwapInterval::Wait(if enabled { 0 } else { 1 })) {
+            Ok(()) | Err(ContextError::FunctionUnavailable) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn load_swap_group(&self) -> Result<gl::wgl_extra::Wgl, ContextError> {
+        let extra_functions = gl::wgl_extra::Wgl::load_with(|addr| {
+            let addr = CString::new(addr.as_bytes()).unwrap();
+            unsafe { gl::wgl::GetProcAddress(addr.as_ptr()) as *const c_void }
+        });
+        if !extra_functions.JoinSwapGroupNV.is_loaded() ||
+           !extra_functions.BindSwapBarrierNV.is_loaded()
+        {
+            return Err(ContextError::FunctionUnavailable);
+        }
+        Ok(extra_functions)
+    }
+
+    /// Returns the context and device context that are current on the calling thread, whether or
+    /// not they belong to this context.
+    #[inline]
+    pub fn current_state(&self) -> CurrentState {
+        unsafe {
+            CurrentState {
+                hdc: gl::wgl::GetCurrentDC() as HDC,
+                hglrc: gl::wgl::GetCurrentContext() as HGLRC,
+            }
+        }
+    }
+
+    /// Makes the context and device context of `state` current again. If no context was current
+    /// when the state was saved, this context is released instead.
+    #[inline]
+    pub unsafe fn restore_current_state(&self, state: &CurrentState) -> Result<(), ContextError> {
+        if state.hglrc.is_null() {
+            return self.make_not_current();
+        }
+
+        if gl::wgl::MakeCurrent(state.hdc as *const _, state.hglrc as *const _) != 0 {
+            Ok(())
+        } else {
+            Err(ContextError::IoError(io::Error::last_os_error()))
+        }
+    }
+
     pub fn get_proc_address(&self, addr: &str) -> *const () {
         let addr = CString::new(addr.as_bytes()).unwrap();
         let addr = addr.as_ptr();
@@ -180,13 +731,45 @@ impl Context {
 
     #[inline]
     pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        // bitmaps are single buffered, so the rendering only needs to be finished for the bits
+        // to be up to date
+        if self.dib_section.is_some() {
+            unsafe {
+                let finish = GetProcAddress(self.gl_library, b"glFinish\0".as_ptr() as *const _);
+                if !finish.is_null() {
+                    let finish: unsafe extern "system" fn() = mem::transmute(finish);
+                    finish();
+                }
+                GdiFlush();
+            }
+            return Ok(());
+        }
+
+        if let Some(ref mut monitor_vblank) = *self.monitor_vblank.lock().unwrap() {
+            unsafe { monitor_vblank.update(self.window)?; }
+            monitor_vblank.wait()?;
+        }
+
         // TODO: decide how to handle the error
         /*if unsafe { SwapBuffers(self.hdc) } != 0 {
             Ok(())
         } else {
             Err(ContextError::IoError(io::Error::last_os_error()))
         }*/
+        let composition = if self.dwm_low_latency.load(Ordering::Relaxed) {
+            dwm::composition_count()
+        } else {
+            None
+        };
+
         unsafe { SwapBuffers(self.hdc) };
+
+        // there's no need to wait if the swap already blocked until a composition
+        if let Some(composition) = composition {
+            if dwm::composition_count() == Some(composition) {
+                dwm::flush();
+            }
+        }
         Ok(())
     }
 
@@ -220,7 +803,13 @@ unsafe fn create_context(extra: Option<(&gl::wgl_extra::Wgl, &PixelFormatRequire
     let share;
 
     if let Some((extra_functions, _pf_reqs, opengl, extensions)) = extra {
-        share = opengl.sharing.unwrap_or(ptr::null_mut());
+        share = match opengl.raw_sharing {
+            Some(RawContextHandle::Wgl { hglrc }) => hglrc as HGLRC,
+            Some(_) => {
+                return Err(CreationError::NotSupported("the shared context is not a WGL context"));
+            },
+            None => opengl.sharing.unwrap_or(ptr::null_mut()),
+        };
 
         if extensions.split(' ').find(|&i| i == "WGL_ARB_create_context").is_some() {
             let mut attributes = Vec::new();
@@ -255,6 +844,11 @@ unsafe fn create_context(extra: Option<(&gl::wgl_extra::Wgl, &PixelFormatRequire
                     attributes.push(gl::wgl_extra::CONTEXT_MINOR_VERSION_ARB as c_int);
                     attributes.push(minor as c_int);
                 },
+                // the list is expanded into `GlRequest::Specific` requests by
+                // `create_with_versions`
+                GlRequest::PreferredList(_) => {
+                    return Err(CreationError::OpenGlVersionNotSupported);
+                },
             }
 
             if let Some(profile) = opengl.profile {
@@ -312,6 +906,34 @@ unsafe fn create_context(extra: Option<(&gl::wgl_extra::Wgl, &PixelFormatRequire
             attributes.push(gl::wgl_extra::CONTEXT_FLAGS_ARB as c_int);
             attributes.push(flags);
 
+            match opengl.release_behavior {
+                ReleaseBehavior::Flush => (),
+                ReleaseBehavior::None => {
+                    if extensions.split(' ').find(|&i| i == "WGL_ARB_context_flush_control")
+                                            .is_some()
+                    {
+                        attributes.push(gl::wgl_extra::CONTEXT_RELEASE_BEHAVIOR_ARB as c_int);
+                        attributes.push(gl::wgl_extra::CONTEXT_RELEASE_BEHAVIOR_NONE_ARB as c_int);
+                    }
+                },
+            }
+
+            if opengl.no_error {
+                opengl.check_no_error()?;
+                if extensions.split(' ').find(|&i| i == "WGL_ARB_create_context_no_error")
+                                        .is_none()
+                {
+                    return Err(CreationError::NoErrorNotSupported);
+                }
+                attributes.push(gl::wgl_extra::CONTEXT_OPENGL_NO_ERROR_ARB as c_int);
+                attributes.push(1);
+            }
+
+            for &(attrib, value) in &opengl.raw_context_attributes {
+                attributes.push(attrib as c_int);
+                attributes.push(value as c_int);
+            }
+
             attributes.push(0);
 
             let ctxt = extra_functions.CreateContextAttribsARB(hdc as *const c_void,
@@ -326,6 +948,10 @@ unsafe fn create_context(extra: Option<(&gl::wgl_extra::Wgl, &PixelFormatRequire
             }
         }
 
+        // the error checking can only be disabled with `wglCreateContextAttribsARB`
+        if opengl.no_error {
+            return Err(CreationError::NoErrorNotSupported);
+        }
     } else {
         share = ptr::null_mut();
     }
@@ -349,7 +975,10 @@ unsafe fn create_context(extra: Option<(&gl::wgl_extra::Wgl, &PixelFormatRequire
 /// Chooses a pixel formats without using WGL.
 ///
 /// Gives less precise results than `enumerate_arb_pixel_formats`.
-unsafe fn choose_native_pixel_format(hdc: HDC, reqs: &PixelFormatRequirements)
+///
+/// If `bitmap` is true, the pixel format draws to the bitmap selected in `hdc` instead of a
+/// window.
+unsafe fn choose_native_pixel_format(hdc: HDC, reqs: &PixelFormatRequirements, bitmap: bool)
                                      -> Result<(c_int, PixelFormat), ()>
 {
     // TODO: hardware acceleration is not handled
@@ -369,20 +998,29 @@ unsafe fn choose_native_pixel_format(hdc: HDC, reqs: &PixelFormatRequirements)
         return Err(());
     }
 
-    if reqs.srgb {
-        return Err(());
+    match reqs.srgb {
+        SrgbRequirement::Require | SrgbRequirement::Prefer => return Err(()),
+        SrgbRequirement::DontCare | SrgbRequirement::Disallow => (),
     }
 
-    if reqs.release_behavior != ReleaseBehavior::Flush {
+    // bitmaps can't be double buffered
+    if bitmap && reqs.double_buffer == Some(true) {
         return Err(());
     }
 
+    let target = if bitmap {
+        PFD_DRAW_TO_BITMAP | PFD_SUPPORT_GDI
+    } else {
+        PFD_DRAW_TO_WINDOW
+    };
+
     // building the descriptor to pass to ChoosePixelFormat
     let descriptor = PIXELFORMATDESCRIPTOR {
         nSize: mem::size_of::<PIXELFORMATDESCRIPTOR>() as u16,
         nVersion: 1,
         dwFlags: {
             let f1 = match reqs.double_buffer {
+                _ if bitmap => 0,
                 None => PFD_DOUBLEBUFFER, // Should be PFD_DOUBLEBUFFER_DONTCARE after you can choose
                 Some(true) => PFD_DOUBLEBUFFER,
                 Some(false) => 0,
@@ -394,10 +1032,11 @@ unsafe fn choose_native_pixel_format(hdc: HDC, reqs: &PixelFormatRequirements)
                 0
             };
 
-            PFD_DRAW_TO_WINDOW | PFD_SUPPORT_OPENGL | f1 | f2
+            target | PFD_SUPPORT_OPENGL | f1 | f2
         },
         iPixelType: PFD_TYPE_RGBA,
-        cColorBits: reqs.color_bits.unwrap_or(0),
+        // the pixel format of a bitmap must match the depth of the bitmap
+        cColorBits: if bitmap { 32 } else { reqs.min_color_bits().unwrap_or(0) },
         cRedBits: 0,
         cRedShift: 0,
         cGreenBits: 0,
@@ -437,7 +1076,7 @@ unsafe fn choose_native_pixel_format(hdc: HDC, reqs: &PixelFormatRequirements)
 
     // windows may return us a non-conforming pixel format if none are supported, so we have to
     // check this
-    if (output.dwFlags & PFD_DRAW_TO_WINDOW) == 0 {
+    if (output.dwFlags & target) != target {
         return Err(());
     }
     if (output.dwFlags & PFD_SUPPORT_OPENGL) == 0 {
@@ -450,6 +1089,9 @@ unsafe fn choose_native_pixel_format(hdc: HDC, reqs: &PixelFormatRequirements)
     let pf_desc = PixelFormat {
         hardware_accelerated: (output.dwFlags & PFD_GENERIC_FORMAT) == 0,
         color_bits: output.cRedBits + output.cGreenBits + output.cBlueBits,
+        red_bits: output.cRedBits,
+        green_bits: output.cGreenBits,
+        blue_bits: output.cBlueBits,
         alpha_bits: output.cAlphaBits,
         depth_bits: output.cDepthBits,
         stencil_bits: output.cStencilBits,
@@ -457,6 +1099,15 @@ unsafe fn choose_native_pixel_format(hdc: HDC, reqs: &PixelFormatRequirements)
         double_buffer: (output.dwFlags & PFD_DOUBLEBUFFER) != 0,
         multisampling: None,
         srgb: false,
+        native_id: Some(NativeConfigId::Wgl(pf_id)),
+        caveat: if (output.dwFlags & PFD_GENERIC_FORMAT) == 0 {
+            ConfigCaveat::None
+        } else {
+            ConfigCaveat::Slow
+        },
+        colorspace: Colorspace::Linear,
+        float_color_buffer: false,
+        transparent: output.cAlphaBits > 0,
     };
 
     if pf_desc.alpha_bits < reqs.alpha_bits.unwrap_or(0) {
@@ -468,10 +1119,24 @@ unsafe fn choose_native_pixel_format(hdc: HDC, reqs: &PixelFormatRequirements)
     if pf_desc.stencil_bits < reqs.stencil_bits.unwrap_or(0) {
         return Err(());
     }
-    if pf_desc.color_bits < reqs.color_bits.unwrap_or(0) {
+    if pf_desc.color_bits < reqs.min_color_bits().unwrap_or(0) {
         return Err(());
     }
-    if let Some(req) = reqs.hardware_accelerated {
+    if let Some((red, green, blue)) = reqs.min_rgb_bits() {
+        if pf_desc.red_bits < red || pf_desc.green_bits < green || pf_desc.blue_bits < blue {
+            return Err(());
+        }
+    }
+    if !reqs.matches_rgb_bits(&pf_desc) {
+        return Err(());
+    }
+    // only the generic implementation of Windows renders in software
+    let hardware_accelerated = if reqs.software_rendering {
+        Some(false)
+    } else {
+        reqs.hardware_accelerated.required()
+    };
+    if let Some(req) = hardware_accelerated {
         if pf_desc.hardware_accelerated != req {
             return Err(());
         }
@@ -488,14 +1153,20 @@ unsafe fn choose_native_pixel_format(hdc: HDC, reqs: &PixelFormatRequirements)
 /// Enumerates the list of pixel formats by using extra WGL functions.
 ///
 /// Gives more precise results than `enumerate_native_pixel_formats`.
+///
+/// If `pbuffer` is true, the pixel format draws to pbuffers instead of windows.
 unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
-                                  hdc: HDC, reqs: &PixelFormatRequirements)
+                                  hdc: HDC, reqs: &PixelFormatRequirements, pbuffer: bool)
                                   -> Result<(c_int, PixelFormat), ()>
 {
     let descriptor = {
         let mut out: Vec<c_int> = Vec::with_capacity(37);
 
-        out.push(gl::wgl_extra::DRAW_TO_WINDOW_ARB as c_int);
+        out.push(if pbuffer {
+            gl::wgl_extra::DRAW_TO_PBUFFER_ARB as c_int
+        } else {
+            gl::wgl_extra::DRAW_TO_WINDOW_ARB as c_int
+        });
         out.push(1);
 
         out.push(gl::wgl_extra::SUPPORT_OPENGL_ARB as c_int);
@@ -512,7 +1183,12 @@ unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
             out.push(gl::wgl_extra::TYPE_RGBA_ARB as c_int);
         }
 
-        if let Some(hardware_accelerated) = reqs.hardware_accelerated {
+        let hardware_accelerated = if reqs.software_rendering {
+            Some(false)
+        } else {
+            reqs.hardware_accelerated.required()
+        };
+        if let Some(hardware_accelerated) = hardware_accelerated {
             out.push(gl::wgl_extra::ACCELERATION_ARB as c_int);
             out.push(if hardware_accelerated {
                 gl::wgl_extra::FULL_ACCELERATION_ARB as c_int
@@ -521,9 +1197,15 @@ unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
             });
         }
 
-        if let Some(color) = reqs.color_bits {
+        if let Some((red, green, blue)) = reqs.min_rgb_bits() {
             out.push(gl::wgl_extra::COLOR_BITS_ARB as c_int);
-            out.push(color as c_int);
+            out.push((red + green + blue) as c_int);
+            out.push(gl::wgl_extra::RED_BITS_ARB as c_int);
+            out.push(red as c_int);
+            out.push(gl::wgl_extra::GREEN_BITS_ARB as c_int);
+            out.push(green as c_int);
+            out.push(gl::wgl_extra::BLUE_BITS_ARB as c_int);
+            out.push(blue as c_int);
         }
 
         if let Some(alpha) = reqs.alpha_bits {
@@ -541,8 +1223,9 @@ unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
             out.push(stencil as c_int);
         }
 
-        // Prefer double buffering if unspecified (probably shouldn't once you can choose)
-        let double_buffer = reqs.double_buffer.unwrap_or(true);
+        // Prefer double buffering if unspecified (probably shouldn't once you can choose), except
+        // for pbuffers which are never presented
+        let double_buffer = reqs.double_buffer.unwrap_or(!pbuffer);
         out.push(gl::wgl_extra::DOUBLE_BUFFER_ARB as c_int);
         out.push(if double_buffer { 1 } else { 0 });
 
@@ -560,32 +1243,53 @@ unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
         out.push(gl::wgl_extra::STEREO_ARB as c_int);
         out.push(if reqs.stereoscopy { 1 } else { 0 });
 
-        if reqs.srgb {
+        let srgb_capable = match reqs.srgb {
+            SrgbRequirement::Require | SrgbRequirement::Prefer => Some(1),
+            SrgbRequirement::Disallow => Some(0),
+            SrgbRequirement::DontCare => None,
+        };
+        if let Some(srgb_capable) = srgb_capable {
             if extensions.split(' ').find(|&i| i == "WGL_ARB_framebuffer_sRGB").is_some() {
                 out.push(gl::wgl_extra::FRAMEBUFFER_SRGB_CAPABLE_ARB as c_int);
-                out.push(1);
+                out.push(srgb_capable);
             } else if extensions.split(' ').find(|&i| i == "WGL_EXT_framebuffer_sRGB").is_some() {
                 out.push(gl::wgl_extra::FRAMEBUFFER_SRGB_CAPABLE_EXT as c_int);
-                out.push(1);
-            } else {
+                out.push(srgb_capable);
+            } else if srgb_capable == 1 {
                 return Err(());
             }
         }
 
-        match reqs.release_behavior {
-            ReleaseBehavior::Flush => (),
-            ReleaseBehavior::None => {
-                if extensions.split(' ').find(|&i| i == "WGL_ARB_context_flush_control").is_some() {
-                    out.push(gl::wgl_extra::CONTEXT_RELEASE_BEHAVIOR_ARB as c_int);
-                    out.push(gl::wgl_extra::CONTEXT_RELEASE_BEHAVIOR_NONE_ARB as c_int);
-                }
-            },
+        for &(attrib, value) in &reqs.raw_attributes {
+            out.push(attrib as c_int);
+            out.push(value as c_int);
         }
 
         out.push(0);
         out
     };
 
+    if reqs.needs_all_formats() {
+        let mut format_ids: Vec<c_int> = vec![0; 256];
+        let mut num_formats = 0;
+        if extra.ChoosePixelFormatARB(hdc as *const _, descriptor.as_ptr(), ptr::null(),
+                                      format_ids.len() as u32, format_ids.as_mut_ptr(),
+                                      &mut num_formats) == 0
+        {
+            return Err(());
+        }
+        format_ids.truncate(num_formats as usize);
+
+        let mut formats: Vec<_> = format_ids.iter()
+            .map(|&format_id| arb_pixel_format(extra, extensions, hdc, format_id))
+            .collect();
+
+        return match reqs.select_format(&formats) {
+            Some(index) => Ok((format_ids[index], formats.swap_remove(index))),
+            None => Err(()),
+        };
+    }
+
     let mut format_id = mem::uninitialized();
     let mut num_formats = mem::uninitialized();
     if extra.ChoosePixelFormatARB(hdc as *const _, descriptor.as_ptr(), ptr::null(), 1,
@@ -598,6 +1302,29 @@ unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
         return Err(());
     }
 
+    let pf_desc = arb_pixel_format(extra, extensions, hdc, format_id);
+
+    Ok((format_id, pf_desc))
+}
+
+/// Returns the error to report when no pixel format of `hdc` matches `reqs`, along with the
+/// nearest ones among all the pixel formats of `hdc`.
+unsafe fn no_matching_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str, hdc: HDC,
+                                       reqs: &PixelFormatRequirements) -> CreationError
+{
+    // with a null descriptor, `DescribePixelFormat` returns the largest pixel format id
+    let count = DescribePixelFormat(hdc, 1, mem::size_of::<PIXELFORMATDESCRIPTOR>() as UINT,
+                                    ptr::null_mut());
+    let formats = (1 .. count + 1)
+        .map(|format_id| arb_pixel_format(extra, extensions, hdc, format_id))
+        .collect();
+    reqs.no_matching_format(formats)
+}
+
+/// Reads the pixel format with the given id using `WGL_ARB_pixel_format`.
+unsafe fn arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str, hdc: HDC,
+                           format_id: c_int) -> PixelFormat
+{
     let get_info = |attrib: u32| {
         let mut value = mem::uninitialized();
         extra.GetPixelFormatAttribivARB(hdc as *const _, format_id as c_int,
@@ -606,13 +1333,27 @@ unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
         value as u32
     };
 
-    let pf_desc = PixelFormat {
-        hardware_accelerated: get_info(gl::wgl_extra::ACCELERATION_ARB) !=
-                                                                gl::wgl_extra::NO_ACCELERATION_ARB,
-        color_bits: get_info(gl::wgl_extra::RED_BITS_ARB) as u8 +
-                    get_info(gl::wgl_extra::GREEN_BITS_ARB) as u8 +
-                    get_info(gl::wgl_extra::BLUE_BITS_ARB) as u8,
-        alpha_bits: get_info(gl::wgl_extra::ALPHA_BITS_ARB) as u8,
+    let red_bits = get_info(gl::wgl_extra::RED_BITS_ARB) as u8;
+    let green_bits = get_info(gl::wgl_extra::GREEN_BITS_ARB) as u8;
+    let blue_bits = get_info(gl::wgl_extra::BLUE_BITS_ARB) as u8;
+    let alpha_bits = get_info(gl::wgl_extra::ALPHA_BITS_ARB) as u8;
+    let hardware_accelerated = get_info(gl::wgl_extra::ACCELERATION_ARB) !=
+                               gl::wgl_extra::NO_ACCELERATION_ARB;
+    let srgb = if extensions.split(' ').find(|&i| i == "WGL_ARB_framebuffer_sRGB").is_some() {
+        get_info(gl::wgl_extra::FRAMEBUFFER_SRGB_CAPABLE_ARB) != 0
+    } else if extensions.split(' ').find(|&i| i == "WGL_EXT_framebuffer_sRGB").is_some() {
+        get_info(gl::wgl_extra::FRAMEBUFFER_SRGB_CAPABLE_EXT) != 0
+    } else {
+        false
+    };
+
+    PixelFormat {
+        hardware_accelerated: hardware_accelerated,
+        color_bits: red_bits + green_bits + blue_bits,
+        red_bits: red_bits,
+        green_bits: green_bits,
+        blue_bits: blue_bits,
+        alpha_bits: alpha_bits,
         depth_bits: get_info(gl::wgl_extra::DEPTH_BITS_ARB) as u8,
         stencil_bits: get_info(gl::wgl_extra::STENCIL_BITS_ARB) as u8,
         stereoscopy: get_info(gl::wgl_extra::STEREO_ARB) != 0,
@@ -627,16 +1368,14 @@ unsafe fn choose_arb_pixel_format(extra: &gl::wgl_extra::Wgl, extensions: &str,
                 None
             }
         },
-        srgb: if extensions.split(' ').find(|&i| i == "WGL_ARB_framebuffer_sRGB").is_some() {
-            get_info(gl::wgl_extra::FRAMEBUFFER_SRGB_CAPABLE_ARB) != 0
-        } else if extensions.split(' ').find(|&i| i == "WGL_EXT_framebuffer_sRGB").is_some() {
-            get_info(gl::wgl_extra::FRAMEBUFFER_SRGB_CAPABLE_EXT) != 0
-        } else {
-            false
-        },
-    };
-
-    Ok((format_id, pf_desc))
+        srgb: srgb,
+        native_id: Some(NativeConfigId::Wgl(format_id)),
+        caveat: if hardware_accelerated { ConfigCaveat::None } else { ConfigCaveat::Slow },
+        colorspace: if srgb { Colorspace::Srgb } else { Colorspace::Linear },
+        float_color_buffer: get_info(gl::wgl_extra::PIXEL_TYPE_ARB) ==
+                            gl::wgl_extra::TYPE_RGBA_FLOAT_ARB,
+        transparent: alpha_bits > 0,
+    }
 }
 
 /// Calls `SetPixelFormat` on a window.
@@ -658,6 +1397,60 @@ unsafe fn set_pixel_format(hdc: HDC, id: c_int) -> Result<(), CreationError> {
     Ok(())
 }
 
+/// Returns the list of the WGL extensions supported for `hdc`, separated by spaces.
+unsafe fn extensions_string(extra_functions: &gl::wgl_extra::Wgl, hdc: HDC) -> String {
+    if extra_functions.GetExtensionsStringARB.is_loaded() {
+        let data = extra_functions.GetExtensionsStringARB(hdc as *const _);
+        let data = CStr::from_ptr(data).to_bytes().to_vec();
+        String::from_utf8(data).unwrap()
+
+    } else if extra_functions.GetExtensionsStringEXT.is_loaded() {
+        let data = extra_functions.GetExtensionsStringEXT();
+        let data = CStr::from_ptr(data).to_bytes().to_vec();
+        String::from_utf8(data).unwrap()
+
+    } else {
+        format!("")
+    }
+}
+
+/// Creates an invisible window with a class of its own, for contexts that don't draw to a
+/// window of the user.
+unsafe fn create_hidden_window() -> Result<WindowWrapper, CreationError> {
+    let class_name = OsStr::new("GlutinHidden Class").encode_wide().chain(Some(0).into_iter())
+                                                     .collect::<Vec<_>>();
+
+    let mut class: WNDCLASSEXW = mem::zeroed();
+    class.cbSize = mem::size_of::<WNDCLASSEXW>() as UINT;
+    class.style = CS_OWNDC;
+    class.lpfnWndProc = Some(DefWindowProcW);
+    class.hInstance = GetModuleHandleW(ptr::null());
+    class.lpszClassName = class_name.as_ptr();
+
+    // registering the class again fails, which can be ignored
+    RegisterClassExW(&class);
+
+    let title = OsStr::new("hidden window").encode_wide().chain(Some(0).into_iter())
+                                           .collect::<Vec<_>>();
+    let win = CreateWindowExW(0, class_name.as_ptr(), title.as_ptr() as LPCWSTR,
+                              WS_POPUP | WS_CLIPSIBLINGS | WS_CLIPCHILDREN, 0, 0, 1, 1,
+                              ptr::null_mut(), ptr::null_mut(), GetModuleHandleW(ptr::null()),
+                              ptr::null_mut());
+    if win.is_null() {
+        return Err(CreationError::OsError(format!("CreateWindowEx function failed: {}",
+                                                  io::Error::last_os_error())));
+    }
+
+    let hdc = GetDC(win);
+    if hdc.is_null() {
+        let err = io::Error::last_os_error();
+        DestroyWindow(win);
+        return Err(CreationError::OsError(format!("GetDC function failed: {}", err)));
+    }
+
+    Ok(WindowWrapper(win, hdc))
+}
+
 /// Loads the `opengl32.dll` library.
 unsafe fn load_opengl32_dll() -> Result<HMODULE, CreationError> {
     let name = OsStr::new("opengl32.dll").encode_wide().chain(Some(0).into_iter())
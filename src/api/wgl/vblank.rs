@@ -0,0 +1,148 @@
+#![cfg(any(target_os = "windows"))]
+
+use ContextError;
+
+use std::{io, mem, ptr};
+
+use winapi::shared::minwindef::{FARPROC, UINT};
+use winapi::shared::ntdef::{LONG, LUID};
+use winapi::shared::windef::{HDC, HMONITOR, HWND};
+use winapi::um::libloaderapi::{GetProcAddress, LoadLibraryA};
+use winapi::um::wingdi::{CreateDCW, DeleteDC};
+use winapi::um::winuser::{GetMonitorInfoW, MonitorFromWindow, MONITORINFO, MONITORINFOEXW};
+use winapi::um::winuser::MONITOR_DEFAULTTONEAREST;
+
+#[repr(C)]
+struct OpenAdapterFromHdc {
+    hdc: HDC,
+    adapter: UINT,
+    adapter_luid: LUID,
+    vid_pn_source_id: UINT,
+}
+
+#[repr(C)]
+struct CloseAdapter {
+    adapter: UINT,
+}
+
+#[repr(C)]
+struct WaitForVerticalBlankEvent {
+    adapter: UINT,
+    device: UINT,
+    vid_pn_source_id: UINT,
+}
+
+type OpenAdapterFromHdcFn = unsafe extern "system" fn(*mut OpenAdapterFromHdc) -> LONG;
+type CloseAdapterFn = unsafe extern "system" fn(*const CloseAdapter) -> LONG;
+type WaitForVerticalBlankEventFn =
+    unsafe extern "system" fn(*const WaitForVerticalBlankEvent) -> LONG;
+
+/// Waits for the vertical blanks of the monitor a window is on, through the D3DKMT functions of
+/// `gdi32.dll`.
+///
+/// `wglSwapIntervalEXT` synchronizes with the primary monitor when the desktop is composited,
+/// which makes windows on other monitors stutter if their refresh rate is different.
+pub struct MonitorVblank {
+    open_adapter: OpenAdapterFromHdcFn,
+    close_adapter: CloseAdapterFn,
+    wait_for_vblank: WaitForVerticalBlankEventFn,
+    monitor: HMONITOR,
+    adapter: UINT,
+    vid_pn_source_id: UINT,
+}
+
+impl MonitorVblank {
+    /// Opens the adapter of the monitor `window` is on.
+    pub unsafe fn new(window: HWND) -> Result<MonitorVblank, ContextError> {
+        let gdi32 = LoadLibraryA(b"gdi32.dll\0".as_ptr() as *const _);
+        if gdi32.is_null() {
+            return Err(ContextError::FunctionUnavailable);
+        }
+        let sym = |name: &[u8]| -> Result<FARPROC, ContextError> {
+            let sym = GetProcAddress(gdi32, name.as_ptr() as *const _);
+            if sym.is_null() { Err(ContextError::FunctionUnavailable) } else { Ok(sym) }
+        };
+
+        let mut vblank = MonitorVblank {
+            open_adapter: mem::transmute(sym(b"D3DKMTOpenAdapterFromHdc\0")?),
+            close_adapter: mem::transmute(sym(b"D3DKMTCloseAdapter\0")?),
+            wait_for_vblank: mem::transmute(sym(b"D3DKMTWaitForVerticalBlankEvent\0")?),
+            monitor: ptr::null_mut(),
+            adapter: 0,
+            vid_pn_source_id: 0,
+        };
+        vblank.update(window)?;
+        Ok(vblank)
+    }
+
+    /// Opens the adapter of the monitor `window` is on, if the window moved to another monitor
+    /// since the last call.
+    pub unsafe fn update(&mut self, window: HWND) -> Result<(), ContextError> {
+        let monitor = MonitorFromWindow(window, MONITOR_DEFAULTTONEAREST);
+        if monitor == self.monitor {
+            return Ok(());
+        }
+
+        let mut info: MONITORINFOEXW = mem::zeroed();
+        info.cbSize = mem::size_of::<MONITORINFOEXW>() as _;
+        if GetMonitorInfoW(monitor, &mut info as *mut MONITORINFOEXW as *mut MONITORINFO) == 0 {
+            return Err(ContextError::IoError(io::Error::last_os_error()));
+        }
+
+        let hdc = CreateDCW(ptr::null(), info.szDevice.as_ptr(), ptr::null(), ptr::null());
+        if hdc.is_null() {
+            return Err(ContextError::IoError(io::Error::last_os_error()));
+        }
+        let mut open = OpenAdapterFromHdc {
+            hdc: hdc,
+            adapter: 0,
+            adapter_luid: mem::zeroed(),
+            vid_pn_source_id: 0,
+        };
+        let status = (self.open_adapter)(&mut open);
+        DeleteDC(hdc);
+        if status != 0 {
+            return Err(ContextError::OsError(
+                format!("D3DKMTOpenAdapterFromHdc failed (NTSTATUS 0x{:x})", status)
+            ));
+        }
+
+        self.close();
+        self.monitor = monitor;
+        self.adapter = open.adapter;
+        self.vid_pn_source_id = open.vid_pn_source_id;
+        Ok(())
+    }
+
+    /// Blocks until the next vertical blank of the monitor.
+    pub fn wait(&self) -> Result<(), ContextError> {
+        let event = WaitForVerticalBlankEvent {
+            adapter: self.adapter,
+            device: 0,
+            vid_pn_source_id: self.vid_pn_source_id,
+        };
+        let status = unsafe { (self.wait_for_vblank)(&event) };
+        if status != 0 {
+            return Err(ContextError::OsError(
+                format!("D3DKMTWaitForVerticalBlankEvent failed (NTSTATUS 0x{:x})", status)
+            ));
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        if self.adapter != 0 {
+            unsafe { (self.close_adapter)(&CloseAdapter { adapter: self.adapter }); }
+            self.adapter = 0;
+        }
+    }
+}
+
+impl Drop for MonitorVblank {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+// The handles can be used from any thread.
+unsafe impl Send for MonitorVblank {}
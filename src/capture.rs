@@ -0,0 +1,256 @@
+use Api;
+use ContextError;
+use GlContext;
+
+use std::os::raw::{c_int, c_uint, c_void};
+use std::mem;
+
+// From `gl.h`, since glutin doesn't generate OpenGL bindings.
+const GL_FRONT: c_uint = 0x0404;
+const GL_READ_BUFFER: c_uint = 0x0C02;
+const GL_PACK_ALIGNMENT: c_uint = 0x0D05;
+const GL_RGBA: c_uint = 0x1908;
+const GL_RGBA8: c_uint = 0x8058;
+const GL_UNSIGNED_BYTE: c_uint = 0x1401;
+const GL_NEAREST: c_uint = 0x2600;
+const GL_COLOR_BUFFER_BIT: c_uint = 0x4000;
+const GL_FRAMEBUFFER_SRGB: c_uint = 0x8DB9;
+const GL_READ_FRAMEBUFFER: c_uint = 0x8CA8;
+const GL_DRAW_FRAMEBUFFER: c_uint = 0x8CA9;
+const GL_READ_FRAMEBUFFER_BINDING: c_uint = 0x8CAA;
+const GL_DRAW_FRAMEBUFFER_BINDING: c_uint = 0x8CA6;
+const GL_RENDERBUFFER: c_uint = 0x8D41;
+const GL_RENDERBUFFER_BINDING: c_uint = 0x8CA7;
+const GL_COLOR_ATTACHMENT0: c_uint = 0x8CE0;
+
+/// A frame read by `WindowedContext::capture_frame`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedFrame {
+    /// The width and height of the frame, in pixels.
+    pub dimensions: (u32, u32),
+    /// Tightly packed 8 bits RGBA values, from the top row to the bottom row like most image
+    /// formats.
+    pub pixels: Vec<u8>,
+}
+
+/// Reads the default framebuffer of `context`, which must be current, into a `CapturedFrame`.
+///
+/// The front buffer is read with desktop OpenGL, and the back buffer with OpenGL ES. A
+/// multisampled framebuffer is first resolved into a temporary framebuffer object, without
+/// converting sRGB values. The bindings and the pack alignment are restored afterwards.
+pub(crate) fn capture_frame<C: GlContext>(context: &C, dimensions: (u32, u32))
+                                          -> Result<CapturedFrame, ContextError>
+{
+    let load = |name: &str| -> Result<*const (), ContextError> {
+        let ptr = context.get_proc_address(name);
+        if ptr.is_null() { Err(ContextError::FunctionUnavailable) } else { Ok(ptr) }
+    };
+    let (width, height) = (dimensions.0 as c_int, dimensions.1 as c_int);
+    let pixel_format = context.get_pixel_format();
+    let desktop = context.get_api() == Api::OpenGl;
+
+    unsafe {
+        let get_integerv: extern "system" fn(c_uint, *mut c_int) =
+            mem::transmute(load("glGetIntegerv")?);
+        let pixel_storei: extern "system" fn(c_uint, c_int) =
+            mem::transmute(load("glPixelStorei")?);
+        let read_pixels: extern "system" fn(c_int, c_int, c_int, c_int, c_uint, c_uint,
+                                            *mut c_void) =
+            mem::transmute(load("glReadPixels")?);
+        // framebuffer objects are only needed to resolve multisampling, and may be missing
+        // from old contexts otherwise
+        let bind_framebuffer: Option<extern "system" fn(c_uint, c_uint)> =
+            load("glBindFramebuffer").ok().map(|f| mem::transmute(f));
+
+        let mut saved = SavedState {
+            pixel_storei: pixel_storei,
+            alignment: 0,
+            bind_framebuffer: bind_framebuffer,
+            read_framebuffer: 0,
+            draw_framebuffer: 0,
+            read_buffer: None,
+        };
+        if bind_framebuffer.is_some() {
+            get_integerv(GL_READ_FRAMEBUFFER_BINDING, &mut saved.read_framebuffer);
+            get_integerv(GL_DRAW_FRAMEBUFFER_BINDING, &mut saved.draw_framebuffer);
+        }
+        get_integerv(GL_PACK_ALIGNMENT, &mut saved.alignment);
+        if let Some(bind_framebuffer) = bind_framebuffer {
+            bind_framebuffer(GL_READ_FRAMEBUFFER, 0);
+        }
+
+        // the last presented frame is in the front buffer, which OpenGL ES can't read
+        if desktop && pixel_format.double_buffer {
+            let read_buffer: extern "system" fn(c_uint) = mem::transmute(load("glReadBuffer")?);
+            let mut previous = 0;
+            get_integerv(GL_READ_BUFFER, &mut previous);
+            read_buffer(GL_FRONT);
+            saved.read_buffer = Some((read_buffer, previous));
+        }
+
+        let resolved = if pixel_format.multisampling.unwrap_or(0) > 0 {
+            let bind_framebuffer = bind_framebuffer.ok_or(ContextError::FunctionUnavailable)?;
+            Some(resolve(&load, get_integerv, bind_framebuffer, desktop, width, height)?)
+        } else {
+            None
+        };
+
+        pixel_storei(GL_PACK_ALIGNMENT, 1);
+        let mut pixels = vec![0u8; dimensions.0 as usize * dimensions.1 as usize * 4];
+        read_pixels(0, 0, width, height, GL_RGBA, GL_UNSIGNED_BYTE,
+                    pixels.as_mut_ptr() as *mut c_void);
+
+        drop(saved);
+        if let Some(resolved) = resolved {
+            resolved.delete();
+        }
+
+        Ok(CapturedFrame {
+            dimensions: dimensions,
+            pixels: flip_rows(pixels, dimensions.0 as usize * 4),
+        })
+    }
+}
+
+/// The state changed by `capture_frame`, restored when dropped so that it is also restored when
+/// the capture fails.
+struct SavedState {
+    pixel_storei: extern "system" fn(c_uint, c_int),
+    alignment: c_int,
+    bind_framebuffer: Option<extern "system" fn(c_uint, c_uint)>,
+    read_framebuffer: c_int,
+    draw_framebuffer: c_int,
+    // Set once the read buffer of the default framebuffer was changed.
+    read_buffer: Option<(extern "system" fn(c_uint), c_int)>,
+}
+
+impl Drop for SavedState {
+    fn drop(&mut self) {
+        (self.pixel_storei)(GL_PACK_ALIGNMENT, self.alignment);
+        // the read buffer belongs to the default framebuffer, which must be bound to restore it
+        if let Some((read_buffer, previous)) = self.read_buffer {
+            if let Some(bind_framebuffer) = self.bind_framebuffer {
+                bind_framebuffer(GL_READ_FRAMEBUFFER, 0);
+            }
+            read_buffer(previous as c_uint);
+        }
+        if let Some(bind_framebuffer) = self.bind_framebuffer {
+            bind_framebuffer(GL_READ_FRAMEBUFFER, self.read_framebuffer as c_uint);
+            bind_framebuffer(GL_DRAW_FRAMEBUFFER, self.draw_framebuffer as c_uint);
+        }
+    }
+}
+
+/// A framebuffer object that a multisampled framebuffer was resolved into, along with the
+/// functions deleting it, loaded before it was created.
+struct Resolved {
+    framebuffer: c_uint,
+    renderbuffer: c_uint,
+    delete_framebuffers: extern "system" fn(c_int, *const c_uint),
+    delete_renderbuffers: extern "system" fn(c_int, *const c_uint),
+}
+
+impl Resolved {
+    fn delete(self) {
+        (self.delete_framebuffers)(1, &self.framebuffer);
+        (self.delete_renderbuffers)(1, &self.renderbuffer);
+    }
+}
+
+/// Blits the read framebuffer into a new single-sampled framebuffer object, and binds it as the
+/// read framebuffer. The renderbuffer binding is restored.
+///
+/// Every function is loaded before the objects are created, so that nothing is leaked or left
+/// bound if one of them is missing.
+unsafe fn resolve<F>(load: &F, get_integerv: extern "system" fn(c_uint, *mut c_int),
+                     bind_framebuffer: extern "system" fn(c_uint, c_uint), desktop: bool,
+                     width: c_int, height: c_int)
+                     -> Result<Resolved, ContextError>
+    where F: Fn(&str) -> Result<*const (), ContextError>
+{
+    let gen_framebuffers: extern "system" fn(c_int, *mut c_uint) =
+        mem::transmute(load("glGenFramebuffers")?);
+    let gen_renderbuffers: extern "system" fn(c_int, *mut c_uint) =
+        mem::transmute(load("glGenRenderbuffers")?);
+    let bind_renderbuffer: extern "system" fn(c_uint, c_uint) =
+        mem::transmute(load("glBindRenderbuffer")?);
+    let renderbuffer_storage: extern "system" fn(c_uint, c_uint, c_int, c_int) =
+        mem::transmute(load("glRenderbufferStorage")?);
+    let framebuffer_renderbuffer: extern "system" fn(c_uint, c_uint, c_uint, c_uint) =
+        mem::transmute(load("glFramebufferRenderbuffer")?);
+    let blit_framebuffer: extern "system" fn(c_int, c_int, c_int, c_int, c_int, c_int, c_int,
+                                             c_int, c_uint, c_uint) =
+        mem::transmute(load("glBlitFramebuffer")?);
+    let delete_framebuffers: extern "system" fn(c_int, *const c_uint) =
+        mem::transmute(load("glDeleteFramebuffers")?);
+    let delete_renderbuffers: extern "system" fn(c_int, *const c_uint) =
+        mem::transmute(load("glDeleteRenderbuffers")?);
+    // `GL_FRAMEBUFFER_SRGB` only exists with desktop OpenGL
+    let srgb_functions: Option<(extern "system" fn(c_uint) -> u8, extern "system" fn(c_uint),
+                                extern "system" fn(c_uint))> = if desktop {
+        Some((mem::transmute(load("glIsEnabled")?), mem::transmute(load("glDisable")?),
+              mem::transmute(load("glEnable")?)))
+    } else {
+        None
+    };
+
+    let mut previous_renderbuffer = 0;
+    get_integerv(GL_RENDERBUFFER_BINDING, &mut previous_renderbuffer);
+    let mut resolved = Resolved {
+        framebuffer: 0,
+        renderbuffer: 0,
+        delete_framebuffers: delete_framebuffers,
+        delete_renderbuffers: delete_renderbuffers,
+    };
+    gen_framebuffers(1, &mut resolved.framebuffer);
+    gen_renderbuffers(1, &mut resolved.renderbuffer);
+    bind_renderbuffer(GL_RENDERBUFFER, resolved.renderbuffer);
+    renderbuffer_storage(GL_RENDERBUFFER, GL_RGBA8, width, height);
+    bind_framebuffer(GL_DRAW_FRAMEBUFFER, resolved.framebuffer);
+    framebuffer_renderbuffer(GL_DRAW_FRAMEBUFFER, GL_COLOR_ATTACHMENT0, GL_RENDERBUFFER,
+                             resolved.renderbuffer);
+
+    // with `GL_FRAMEBUFFER_SRGB` enabled, the blit would convert sRGB values to linear ones
+    let srgb = srgb_functions.map(|(is_enabled, disable, enable)| {
+        let enabled = is_enabled(GL_FRAMEBUFFER_SRGB) != 0;
+        if enabled {
+            disable(GL_FRAMEBUFFER_SRGB);
+        }
+        (enabled, enable)
+    });
+
+    blit_framebuffer(0, 0, width, height, 0, 0, width, height, GL_COLOR_BUFFER_BIT, GL_NEAREST);
+
+    if let Some((true, enable)) = srgb {
+        enable(GL_FRAMEBUFFER_SRGB);
+    }
+    bind_framebuffer(GL_READ_FRAMEBUFFER, resolved.framebuffer);
+    bind_renderbuffer(GL_RENDERBUFFER, previous_renderbuffer as c_uint);
+    Ok(resolved)
+}
+
+/// Reverses the order of the rows of `pixels`, which are `stride` bytes long.
+fn flip_rows(pixels: Vec<u8>, stride: usize) -> Vec<u8> {
+    if stride == 0 {
+        return pixels;
+    }
+    let mut flipped = Vec::with_capacity(pixels.len());
+    for row in pixels.chunks(stride).rev() {
+        flipped.extend_from_slice(row);
+    }
+    flipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::flip_rows;
+
+    #[test]
+    fn rows_are_reversed() {
+        let pixels = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
+        assert_eq!(flip_rows(pixels.clone(), 3), [7, 8, 9, 4, 5, 6, 1, 2, 3]);
+        assert_eq!(flip_rows(pixels.clone(), 9), pixels);
+        assert_eq!(flip_rows(pixels.clone(), 0), pixels);
+        assert!(flip_rows(Vec::new(), 4).is_empty());
+    }
+}
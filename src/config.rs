@@ -0,0 +1,39 @@
+use ContextError;
+use PbufferLimits;
+use PixelFormat;
+
+use platform;
+
+/// A framebuffer configuration offered by the driver for a `Display`, such as a `GLXFBConfig` or
+/// an `EGLConfig`.
+///
+/// Configs are obtained through `Display::find_configs`, which lets you inspect every available
+/// configuration before committing to one. Contexts are then created from a config with
+/// `RawContext::new_with_config`, and surfaces with the `new_with_config` constructors of
+/// `Surface`.
+#[derive(Clone)]
+pub struct Config {
+    pub(crate) config: platform::Config,
+}
+
+impl Config {
+    /// Returns the pixel format described by this config.
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        self.config.get_pixel_format()
+    }
+
+    /// Returns the largest pbuffer that can be created with this config.
+    ///
+    /// Returns `ContextError::FunctionUnavailable` if the config doesn't support pbuffers.
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        self.config.max_pbuffer_size()
+    }
+}
+
+impl std::fmt::Debug for Config {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        formatter.debug_struct("Config")
+            .field("pixel_format", &self.get_pixel_format())
+            .finish()
+    }
+}
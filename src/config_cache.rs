@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+use std::os::raw::c_int;
+use std::sync::Mutex;
+
+use PixelFormat;
+
+/// The configs matching a descriptor, as native handles cast to `usize` along with their pixel
+/// formats, in the order in which the implementation returned them.
+pub(crate) type CachedConfigs = Vec<(usize, PixelFormat)>;
+
+lazy_static! {
+    // Keyed by the address of the display, the screen, and the attributes the configs were
+    // chosen with.
+    static ref CACHE: Mutex<HashMap<(usize, c_int, Vec<c_int>), CachedConfigs>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Returns the configs of the screen `screen` of `display` matching `descriptor`, enumerating
+/// them with `enumerate` if they aren't cached yet.
+///
+/// Creating many contexts on the same display this way only asks the implementation for its
+/// configs, and describes each of them, once.
+pub(crate) fn get_or_enumerate<F, E>(display: usize, screen: c_int, descriptor: &[c_int],
+                                     enumerate: F)
+                                     -> Result<CachedConfigs, E>
+    where F: FnOnce() -> Result<CachedConfigs, E>
+{
+    let key = (display, screen, descriptor.to_vec());
+    if let Some(configs) = CACHE.lock().unwrap().get(&key) {
+        return Ok(configs.clone());
+    }
+
+    // the lock isn't held while enumerating, since this may take a while
+    let configs = enumerate()?;
+    CACHE.lock().unwrap().insert(key, configs.clone());
+    Ok(configs)
+}
+
+/// Returns true if some configs of `display` are cached.
+pub(crate) fn contains_display(display: usize) -> bool {
+    CACHE.lock().unwrap().keys().any(|&(cached, _, _)| cached == display)
+}
+
+/// Forgets the configs of `display`. Must be called when the display is closed or terminated,
+/// since its handles become invalid and a display opened later may get the same address.
+pub(crate) fn invalidate(display: usize) {
+    CACHE.lock().unwrap().retain(|&(cached, _, _), _| cached != display);
+}
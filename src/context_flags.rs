@@ -0,0 +1,93 @@
+use std::ffi::CStr;
+use std::mem;
+use std::os::raw::{c_char, c_int, c_uint};
+
+use ContextError;
+use gl_version::parse_version;
+
+/// The flags of a context as reported by the driver, which may differ from the requested ones
+/// since some drivers silently ignore them. See `Context::flags`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContextFlags {
+    /// The context is a debug context, requested with `ContextBuilder::with_gl_debug_flag`.
+    pub debug: bool,
+    /// The deprecated functions of OpenGL are removed, which is always the case for the core
+    /// profile on macOS.
+    pub forward_compatible: bool,
+    /// Out-of-bounds accesses are guaranteed not to crash, requested with a robust
+    /// `Robustness`.
+    pub robust_access: bool,
+    /// Errors aren't checked, requested with `ContextBuilder::with_gl_no_error`.
+    pub no_error: bool,
+}
+
+// From `glext.h`.
+const GL_VERSION: c_uint = 0x1F02;
+const GL_EXTENSIONS: c_uint = 0x1F03;
+const GL_CONTEXT_FLAGS: c_uint = 0x821E;
+const GL_CONTEXT_ROBUST_ACCESS: c_uint = 0x90F3;
+const GL_CONTEXT_FLAG_FORWARD_COMPATIBLE_BIT: c_int = 0x1;
+const GL_CONTEXT_FLAG_DEBUG_BIT: c_int = 0x2;
+const GL_CONTEXT_FLAG_ROBUST_ACCESS_BIT: c_int = 0x4;
+const GL_CONTEXT_FLAG_NO_ERROR_BIT: c_int = 0x8;
+
+type GetStringFn = extern "system" fn(c_uint) -> *const c_char;
+type GetIntegervFn = extern "system" fn(c_uint, *mut c_int);
+
+/// Returns the flags of the current context, whose functions are loaded with
+/// `get_proc_address`.
+///
+/// Contexts older than OpenGL 3.0 and OpenGL ES 3.2 can't report their flags, and can't have
+/// them either, except for robust access which is then queried with `GL_EXT_robustness` or
+/// `GL_KHR_robustness` on OpenGL ES. Whether a query is supported is decided from the version
+/// and the extensions, and `glGetError` is never called, so that the errors pending for the
+/// application are left alone.
+pub(crate) unsafe fn current_flags<F>(get_proc_address: F) -> Result<ContextFlags, ContextError>
+    where F: Fn(&str) -> *const ()
+{
+    let get_string = get_proc_address("glGetString");
+    let get_integerv = get_proc_address("glGetIntegerv");
+    if get_string.is_null() || get_integerv.is_null() {
+        return Err(ContextError::FunctionUnavailable);
+    }
+    let get_string: GetStringFn = mem::transmute(get_string);
+    let get_integerv: GetIntegervFn = mem::transmute(get_integerv);
+
+    let version = get_string(GL_VERSION);
+    if version.is_null() {
+        return Err(ContextError::FunctionUnavailable);
+    }
+    let version = CStr::from_ptr(version).to_string_lossy();
+    let es = version.starts_with("OpenGL ES");
+    let version = parse_version(&version).unwrap_or((0, 0));
+
+    // the support of `name` is checked first, and the value is left to 0 if the query fails
+    let get_integer = |name| {
+        let mut value = 0;
+        get_integerv(name, &mut value);
+        value
+    };
+
+    let flags = if (es && version >= (3, 2)) || (!es && version >= (3, 0)) {
+        get_integer(GL_CONTEXT_FLAGS)
+    } else {
+        0
+    };
+
+    // `GL_CONTEXT_ROBUST_ACCESS` is part of OpenGL ES 3.2 and of the robustness extensions of
+    // OpenGL ES, the extensions of OpenGL report robust access in the flags instead
+    let robust_access_query = es && (version >= (3, 2) || {
+        let extensions = get_string(GL_EXTENSIONS);
+        !extensions.is_null() && CStr::from_ptr(extensions).to_string_lossy().split(' ')
+            .any(|ext| ext == "GL_EXT_robustness" || ext == "GL_KHR_robustness")
+    });
+    let robust_access = flags & GL_CONTEXT_FLAG_ROBUST_ACCESS_BIT != 0 ||
+                        (robust_access_query &&
+                         get_integer(GL_CONTEXT_ROBUST_ACCESS) != 0);
+    Ok(ContextFlags {
+        debug: flags & GL_CONTEXT_FLAG_DEBUG_BIT != 0,
+        forward_compatible: flags & GL_CONTEXT_FLAG_FORWARD_COMPATIBLE_BIT != 0,
+        robust_access: robust_access,
+        no_error: flags & GL_CONTEXT_FLAG_NO_ERROR_BIT != 0,
+    })
+}
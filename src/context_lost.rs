@@ -0,0 +1,101 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use ContextError;
+
+/// Remembers whether a context was lost, and calls the function registered with
+/// `Context::set_context_lost_callback` when it is. Used by `Context`.
+pub(crate) struct ContextLostNotifier {
+    lost: AtomicBool,
+    callback: Mutex<Option<Box<Fn() + Send + Sync>>>,
+}
+
+impl ContextLostNotifier {
+    pub(crate) fn new() -> ContextLostNotifier {
+        ContextLostNotifier {
+            lost: AtomicBool::new(false),
+            callback: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::SeqCst)
+    }
+
+    /// Forgets that the context was lost, once it has been recreated.
+    pub(crate) fn reset(&self) {
+        self.lost.store(false, Ordering::SeqCst);
+    }
+
+    pub(crate) fn set_callback(&self, callback: Option<Box<Fn() + Send + Sync>>) {
+        *self.callback.lock().unwrap() = callback;
+    }
+
+    /// Calls the callback if `error` is the first `ContextError::ContextLost` seen.
+    pub(crate) fn notify(&self, error: &ContextError) {
+        if let ContextError::ContextLost = *error {
+            if !self.lost.swap(true, Ordering::SeqCst) {
+                if let Some(ref callback) = *self.callback.lock().unwrap() {
+                    callback();
+                }
+            }
+        }
+    }
+
+    /// Passes `result` through, after calling `notify` with its error.
+    pub(crate) fn watch<R>(&self, result: Result<R, ContextError>) -> Result<R, ContextError> {
+        if let Err(ref error) = result {
+            self.notify(error);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ContextLostNotifier;
+    use ContextError;
+
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // a notifier counting the calls to its callback in the returned counter
+    fn counting() -> (ContextLostNotifier, Arc<AtomicUsize>) {
+        let notifier = ContextLostNotifier::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        notifier.set_callback(Some(Box::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })));
+        (notifier, calls)
+    }
+
+    #[test]
+    fn callback_fires_once_until_reset() {
+        let (notifier, calls) = counting();
+        assert!(!notifier.is_lost());
+        notifier.notify(&ContextError::ContextLost);
+        notifier.notify(&ContextError::ContextLost);
+        assert!(notifier.is_lost());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        notifier.reset();
+        assert!(!notifier.is_lost());
+        notifier.notify(&ContextError::ContextLost);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn other_errors_are_ignored() {
+        let (notifier, calls) = counting();
+        notifier.notify(&ContextError::FunctionUnavailable);
+        assert!(notifier.watch(Err::<(), _>(ContextError::WouldBlock)).is_err());
+        assert!(notifier.watch(Ok(())).is_ok());
+        assert!(!notifier.is_lost());
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        assert!(notifier.watch(Err::<(), _>(ContextError::ContextLost)).is_err());
+        assert!(notifier.is_lost());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,94 @@
+use Api;
+use Context;
+use ContextCurrentState;
+use ContextError;
+use GlContext;
+use PixelFormat;
+
+use platform;
+
+use std::marker::PhantomData;
+
+/// A guard returned by `Context::make_current_scoped`. The context is current on the calling
+/// thread for as long as the guard is alive.
+///
+/// When the guard is dropped, the context and surfaces that were current on the thread before
+/// `make_current_scoped` was called are made current again. If no context was current, the
+/// context is released instead. Errors that happen at this point are ignored; use `restore` to
+/// handle them.
+///
+/// The previous context is only restored if it was created with the same API as this one, for
+/// example GLX or EGL on X11.
+pub struct CurrentContextGuard<'a> {
+    context: &'a platform::Context,
+    // `None` once the previous state has been restored by `restore`.
+    previous: Option<platform::CurrentState>,
+    // The guard must be dropped on the thread the context was made current on.
+    phantom: PhantomData<*mut ()>,
+}
+
+impl<'a> CurrentContextGuard<'a> {
+    pub(crate) unsafe fn make_current<T: ContextCurrentState>(context: &'a Context<T>)
+        -> Result<CurrentContextGuard<'a>, ContextError>
+    {
+        let previous = context.context.current_state();
+        context.context.make_current()?;
+
+        Ok(CurrentContextGuard {
+            context: &context.context,
+            previous: Some(previous),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Makes the previously current context current again, or releases the context if there was
+    /// none, and returns the error instead of ignoring it like dropping the guard does.
+    pub fn restore(mut self) -> Result<(), ContextError> {
+        let previous = self.previous.take().unwrap();
+        unsafe { self.context.restore_current_state(&previous) }
+    }
+}
+
+impl<'a> GlContext for CurrentContextGuard<'a> {
+    fn is_current(&self) -> bool {
+        self.context.is_current()
+    }
+
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        self.context.get_proc_address(addr)
+    }
+
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.context.swap_buffers()
+    }
+
+    fn get_api(&self) -> Api {
+        self.context.get_api()
+    }
+
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.context.get_pixel_format()
+    }
+
+    fn resize(&self, width: u32, height: u32) {
+        self.context.resize(width, height);
+    }
+}
+
+impl<'a> Drop for CurrentContextGuard<'a> {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            unsafe {
+                let _ = self.context.restore_current_state(&previous);
+            }
+        }
+    }
+}
+
+impl<'a> std::fmt::Debug for CurrentContextGuard<'a> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        formatter.debug_struct("CurrentContextGuard")
+            .field("api", &self.get_api())
+            .finish()
+    }
+}
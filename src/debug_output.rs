@@ -0,0 +1,326 @@
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_uint, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The part of the system that generated a debug message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DebugSource {
+    /// The GL API, `GL_DEBUG_SOURCE_API`.
+    Api,
+    /// The window system, `GL_DEBUG_SOURCE_WINDOW_SYSTEM`.
+    WindowSystem,
+    /// The shader compiler, `GL_DEBUG_SOURCE_SHADER_COMPILER`.
+    ShaderCompiler,
+    /// A tool or library used along with GL, `GL_DEBUG_SOURCE_THIRD_PARTY`.
+    ThirdParty,
+    /// The application itself, with `glDebugMessageInsert`.
+    Application,
+    /// Any other source, `GL_DEBUG_SOURCE_OTHER`.
+    Other,
+}
+
+/// The kind of event described by a debug message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DebugType {
+    /// An error, usually also reported by `glGetError`.
+    Error,
+    /// The use of a deprecated feature.
+    DeprecatedBehavior,
+    /// The use of a feature whose behavior is undefined.
+    UndefinedBehavior,
+    /// The use of a feature that isn't portable between implementations.
+    Portability,
+    /// A possible performance issue.
+    Performance,
+    /// Any other kind of event.
+    Other,
+    /// A marker inserted in the command stream.
+    Marker,
+    /// The start of a debug group, with `glPushDebugGroup`.
+    PushGroup,
+    /// The end of a debug group, with `glPopDebugGroup`.
+    PopGroup,
+}
+
+/// The severity of a debug message, ordered from the least to the most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DebugSeverity {
+    /// Anything that isn't an error or a performance issue.
+    Notification,
+    /// A redundant state change or an unimportant undefined behavior.
+    Low,
+    /// A major performance warning, or the use of a deprecated feature.
+    Medium,
+    /// An error or an undefined behavior.
+    High,
+}
+
+/// A message generated by the implementation for a debug context. See
+/// `ContextBuilder::with_debug_callback`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugMessage {
+    /// The part of the system that generated the message.
+    pub source: DebugSource,
+    /// The kind of event, `type` in the GL API.
+    pub kind: DebugType,
+    /// The identifier of the message, which depends on the implementation.
+    pub id: u32,
+    /// How severe the event is, used by `DebugFilter::min_severity`.
+    pub severity: DebugSeverity,
+    /// The human-readable text of the message.
+    pub message: String,
+}
+
+/// Which debug messages are passed to the callback. See `ContextBuilder::with_debug_callback`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugFilter {
+    /// Messages less severe than this one are ignored.
+    ///
+    /// The default is `DebugSeverity::Low`, which ignores the notifications that most drivers
+    /// send for every buffer allocation.
+    pub min_severity: DebugSeverity,
+
+    /// The sources whose messages are kept, or `None` to keep the messages of all sources.
+    ///
+    /// The default is `None`.
+    pub sources: Option<Vec<DebugSource>>,
+}
+
+impl Default for DebugFilter {
+    #[inline]
+    fn default() -> DebugFilter {
+        DebugFilter {
+            min_severity: DebugSeverity::Low,
+            sources: None,
+        }
+    }
+}
+
+impl DebugFilter {
+    fn accepts(&self, source: DebugSource, severity: DebugSeverity) -> bool {
+        severity >= self.min_severity &&
+            self.sources.as_ref().map_or(true, |sources| sources.contains(&source))
+    }
+}
+
+/// The callback receiving the debug messages of a context, along with its filter.
+#[derive(Clone)]
+pub(crate) struct DebugCallback(pub(crate) Arc<Fn(&DebugMessage) + Send + Sync>,
+                                pub(crate) DebugFilter);
+
+impl ::std::fmt::Debug for DebugCallback {
+    fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        formatter.debug_tuple("DebugCallback").field(&self.1).finish()
+    }
+}
+
+const GL_DEBUG_OUTPUT: c_uint = 0x92E0;
+const GL_DEBUG_OUTPUT_SYNCHRONOUS: c_uint = 0x8242;
+
+type DebugProc = extern "system" fn(c_uint, c_uint, c_uint, c_uint, c_int, *const c_char,
+                                    *mut c_void);
+type DebugMessageCallbackFn = extern "system" fn(DebugProc, *const c_void);
+type EnableFn = extern "system" fn(c_uint);
+
+/// Installs the debug callback of a context the first time it is made current. Used by
+/// `Context` and `HeadlessContext`.
+pub(crate) struct DebugOutput {
+    // Boxed so that the pointer given to the implementation stays valid when the context is
+    // moved between its states.
+    callback: Option<Box<DebugCallback>>,
+    installed: AtomicBool,
+}
+
+impl DebugOutput {
+    pub(crate) fn new(callback: Option<DebugCallback>) -> DebugOutput {
+        DebugOutput {
+            callback: callback.map(Box::new),
+            installed: AtomicBool::new(false),
+        }
+    }
+
+    /// Installs the callback if there is one and it isn't installed yet. The context must be
+    /// current, and `get_proc_address` must load its functions.
+    ///
+    /// Nothing happens if the context doesn't support `GL_KHR_debug`, `GL_ARB_debug_output` or
+    /// OpenGL 4.3, in which case it wasn't created as a debug context either.
+    pub(crate) unsafe fn install<F>(&self, get_proc_address: F)
+        where F: Fn(&str) -> *const ()
+    {
+        let callback = match self.callback {
+            Some(ref callback) => callback,
+            None => return,
+        };
+        if self.installed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let message_callback = ["glDebugMessageCallback", "glDebugMessageCallbackKHR",
+                                "glDebugMessageCallbackARB"]
+            .iter()
+            .map(|name| get_proc_address(name))
+            .find(|address| !address.is_null());
+        let enable = get_proc_address("glEnable");
+        let message_callback = match message_callback {
+            Some(address) if !enable.is_null() => address,
+            _ => return,
+        };
+
+        let message_callback: DebugMessageCallbackFn = ::std::mem::transmute(message_callback);
+        let enable: EnableFn = ::std::mem::transmute(enable);
+        message_callback(debug_proc, &**callback as *const DebugCallback as *const c_void);
+        enable(GL_DEBUG_OUTPUT);
+        // so that the messages are received on the thread that made the faulty call
+        enable(GL_DEBUG_OUTPUT_SYNCHRONOUS);
+    }
+}
+
+extern "system" fn debug_proc(source: c_uint, kind: c_uint, id: c_uint, severity: c_uint,
+                              length: c_int, message: *const c_char, user_param: *mut c_void)
+{
+    let DebugCallback(ref callback, ref filter) = unsafe {
+        &*(user_param as *const DebugCallback)
+    };
+    let source = debug_source(source);
+    let severity = debug_severity(severity);
+    if !filter.accepts(source, severity) || message.is_null() {
+        return;
+    }
+
+    let message = unsafe {
+        if length < 0 {
+            CStr::from_ptr(message).to_string_lossy().into_owned()
+        } else {
+            let bytes = slice::from_raw_parts(message as *const u8, length as usize);
+            String::from_utf8_lossy(bytes).into_owned()
+        }
+    };
+    let message = DebugMessage {
+        source: source,
+        kind: debug_type(kind),
+        id: id,
+        severity: severity,
+        message: message,
+    };
+    // unwinding into the implementation is undefined behavior, so a panic of the callback is
+    // stopped here and the message is dropped
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| callback(&message)));
+}
+
+fn debug_source(source: c_uint) -> DebugSource {
+    match source {
+        0x8246 => DebugSource::Api,
+        0x8247 => DebugSource::WindowSystem,
+        0x8248 => DebugSource::ShaderCompiler,
+        0x8249 => DebugSource::ThirdParty,
+        0x824A => DebugSource::Application,
+        _ => DebugSource::Other,
+    }
+}
+
+fn debug_type(kind: c_uint) -> DebugType {
+    match kind {
+        0x824C => DebugType::Error,
+        0x824D => DebugType::DeprecatedBehavior,
+        0x824E => DebugType::UndefinedBehavior,
+        0x824F => DebugType::Portability,
+        0x8250 => DebugType::Performance,
+        0x8268 => DebugType::Marker,
+        0x8269 => DebugType::PushGroup,
+        0x826A => DebugType::PopGroup,
+        _ => DebugType::Other,
+    }
+}
+
+fn debug_severity(severity: c_uint) -> DebugSeverity {
+    match severity {
+        0x9146 => DebugSeverity::High,
+        0x9147 => DebugSeverity::Medium,
+        0x9148 => DebugSeverity::Low,
+        _ => DebugSeverity::Notification,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{debug_proc, debug_severity, debug_source, debug_type};
+    use super::{DebugCallback, DebugFilter, DebugMessage, DebugSeverity, DebugSource, DebugType};
+
+    use std::os::raw::c_void;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn panics_of_the_callback_are_stopped() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let callback = DebugCallback(Arc::new(move |message: &DebugMessage| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            panic!("{}", message.message);
+        }), DebugFilter::default());
+        let text = b"buffer overflow";
+        debug_proc(0x8246, 0x824C, 1, 0x9146, text.len() as i32, text.as_ptr() as *const _,
+                   &callback as *const DebugCallback as *mut c_void);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn default_filter_ignores_notifications() {
+        let filter = DebugFilter::default();
+        assert!(!filter.accepts(DebugSource::Api, DebugSeverity::Notification));
+        assert!(filter.accepts(DebugSource::Api, DebugSeverity::Low));
+        assert!(filter.accepts(DebugSource::Other, DebugSeverity::High));
+    }
+
+    #[test]
+    fn filter_keeps_listed_sources() {
+        let filter = DebugFilter {
+            min_severity: DebugSeverity::Medium,
+            sources: Some(vec![DebugSource::ShaderCompiler, DebugSource::Application]),
+        };
+        assert!(filter.accepts(DebugSource::ShaderCompiler, DebugSeverity::Medium));
+        assert!(filter.accepts(DebugSource::Application, DebugSeverity::High));
+        assert!(!filter.accepts(DebugSource::Api, DebugSeverity::High));
+        assert!(!filter.accepts(DebugSource::ShaderCompiler, DebugSeverity::Low));
+
+        let filter = DebugFilter { sources: Some(Vec::new()), .. DebugFilter::default() };
+        assert!(!filter.accepts(DebugSource::Api, DebugSeverity::High));
+    }
+
+    #[test]
+    fn sources() {
+        assert_eq!(debug_source(0x8246), DebugSource::Api);
+        assert_eq!(debug_source(0x8247), DebugSource::WindowSystem);
+        assert_eq!(debug_source(0x8248), DebugSource::ShaderCompiler);
+        assert_eq!(debug_source(0x8249), DebugSource::ThirdParty);
+        assert_eq!(debug_source(0x824A), DebugSource::Application);
+        assert_eq!(debug_source(0x824B), DebugSource::Other);
+        assert_eq!(debug_source(0), DebugSource::Other);
+    }
+
+    #[test]
+    fn types() {
+        assert_eq!(debug_type(0x824C), DebugType::Error);
+        assert_eq!(debug_type(0x824D), DebugType::DeprecatedBehavior);
+        assert_eq!(debug_type(0x824E), DebugType::UndefinedBehavior);
+        assert_eq!(debug_type(0x824F), DebugType::Portability);
+        assert_eq!(debug_type(0x8250), DebugType::Performance);
+        assert_eq!(debug_type(0x8251), DebugType::Other);
+        assert_eq!(debug_type(0x8268), DebugType::Marker);
+        assert_eq!(debug_type(0x8269), DebugType::PushGroup);
+        assert_eq!(debug_type(0x826A), DebugType::PopGroup);
+    }
+
+    #[test]
+    fn severities() {
+        assert_eq!(debug_severity(0x9146), DebugSeverity::High);
+        assert_eq!(debug_severity(0x9147), DebugSeverity::Medium);
+        assert_eq!(debug_severity(0x9148), DebugSeverity::Low);
+        assert_eq!(debug_severity(0x826B), DebugSeverity::Notification);
+        assert!(DebugSeverity::Notification < DebugSeverity::Low);
+        assert!(DebugSeverity::Medium < DebugSeverity::High);
+    }
+}
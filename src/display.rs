@@ -0,0 +1,97 @@
+use Api;
+use Config;
+use CreationError;
+use DeviceInfo;
+use PixelFormatRequirements;
+use RawDisplayHandle;
+use SupportedVersions;
+
+use std::sync::Mutex;
+
+use platform;
+
+/// A native display connection obtained from the user, from which contexts are created.
+///
+/// This lets embedders such as plugins or compositors reuse a connection they already have
+/// instead of glutin opening its own. The connection is not closed when the `Display` is
+/// dropped.
+///
+/// See `RawContext::new_with_display`.
+pub struct Display {
+    pub(crate) display: platform::Display,
+    // The results of `max_supported_version`, which creates contexts and is slow.
+    supported_versions: Mutex<Vec<(Api, SupportedVersions)>>,
+}
+
+impl Display {
+    /// Wraps a native display connection.
+    ///
+    /// The handle must belong to the current platform, otherwise `CreationError::NotSupported`
+    /// is returned.
+    ///
+    /// # Unsafety
+    ///
+    /// The handle must be valid, and the connection must remain open for as long as the
+    /// `Display` and any context created from it.
+    pub unsafe fn from_raw(display_handle: RawDisplayHandle) -> Result<Self, CreationError> {
+        platform::Display::from_raw(display_handle).map(|display| Display {
+            display: display,
+            supported_versions: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Returns every config offered by the driver for this display that matches `template`.
+    ///
+    /// The configs are returned in the order of preference of the driver. Use `PixelFormat`'s
+    /// fields to inspect them, and `PixelFormatRequirements::default()` with the fields you
+    /// don't care about set to `None` to list as many of them as possible.
+    ///
+    /// Config enumeration is only supported on X11 and Wayland for now. On other platforms,
+    /// `CreationError::NotSupported` is returned.
+    pub fn find_configs(&self, template: &PixelFormatRequirements)
+        -> Result<impl Iterator<Item = Config>, CreationError>
+    {
+        let configs = self.display.find_configs(template)?;
+        Ok(configs.into_iter().map(|config| Config { config: config }))
+    }
+
+    /// Returns the vendor, model, video memory and driver of the device of this display, without
+    /// creating a context, so that an application can pick its settings or refuse an unsupported
+    /// GPU up front.
+    ///
+    /// This uses `GLX_MESA_query_renderer` with GLX, and `EGL_MESA_query_driver` along with the
+    /// vendor string of the display with EGL, so it is only supported on X11 and Wayland with
+    /// drivers implementing these extensions. Otherwise, `CreationError::NotSupported` is
+    /// returned.
+    pub fn device_info(&self) -> Result<DeviceInfo, CreationError> {
+        self.display.device_info()
+    }
+
+    /// Returns the highest core and compatibility versions of `api` for which a context can be
+    /// created on this display, so that an application can pick its renderer before creating
+    /// its window.
+    ///
+    /// The versions are found by creating and destroying throwaway contexts the first time, and
+    /// are then cached for this `Display`.
+    ///
+    /// Requires `GLX_ARB_create_context` with GLX, and EGL 1.5 or `EGL_KHR_create_context` with
+    /// EGL, since the version of a context can't be requested otherwise. Like config enumeration,
+    /// this is only supported on X11 and Wayland for now. On other platforms,
+    /// `CreationError::NotSupported` is returned.
+    pub fn max_supported_version(&self, api: Api) -> Result<SupportedVersions, CreationError> {
+        let mut cache = self.supported_versions.lock().unwrap();
+        if let Some(&(_, versions)) = cache.iter().find(|&&(cached, _)| cached == api) {
+            return Ok(versions);
+        }
+
+        let versions = self.display.probe_versions(api)?;
+        cache.push((api, versions));
+        Ok(versions)
+    }
+}
+
+impl std::fmt::Debug for Display {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        formatter.debug_struct("Display").finish()
+    }
+}
@@ -0,0 +1,146 @@
+use ContextError;
+
+use std::collections::VecDeque;
+use std::mem;
+use std::os::raw::{c_uint, c_void};
+use std::sync::Mutex;
+
+type GLsync = *const c_void;
+
+// From `glcorearb.h`.
+const GL_SYNC_GPU_COMMANDS_COMPLETE: c_uint = 0x9117;
+const GL_SYNC_FLUSH_COMMANDS_BIT: c_uint = 0x00000001;
+const GL_WAIT_FAILED: c_uint = 0x911D;
+
+/// How long to wait for a frame to complete before giving up, in nanoseconds. Long enough for
+/// any frame, but keeps a lost GPU from hanging the application.
+const WAIT_TIMEOUT: u64 = 1_000_000_000;
+
+/// The sync object functions of GL 3.2, `GL_ARB_sync` and GLES 3.0.
+struct SyncFunctions {
+    fence_sync: extern "system" fn(c_uint, c_uint) -> GLsync,
+    client_wait_sync: extern "system" fn(GLsync, c_uint, u64) -> c_uint,
+    delete_sync: extern "system" fn(GLsync),
+}
+
+impl SyncFunctions {
+    fn load<F>(get_proc_address: F) -> Option<SyncFunctions>
+        where F: Fn(&str) -> *const ()
+    {
+        let fence_sync = get_proc_address("glFenceSync");
+        let client_wait_sync = get_proc_address("glClientWaitSync");
+        let delete_sync = get_proc_address("glDeleteSync");
+        if fence_sync.is_null() || client_wait_sync.is_null() || delete_sync.is_null() {
+            return None;
+        }
+        unsafe {
+            Some(SyncFunctions {
+                fence_sync: mem::transmute(fence_sync),
+                client_wait_sync: mem::transmute(client_wait_sync),
+                delete_sync: mem::transmute(delete_sync),
+            })
+        }
+    }
+}
+
+/// Limits how many frames the driver may queue ahead of the GPU, by inserting a fence after
+/// every swap and waiting for the oldest ones. Used by `Context::set_max_frame_latency`.
+///
+/// The fences are deleted by `Context` when the limit changes, when the context is released and
+/// before it is destroyed, since sync objects are shared with the other contexts of its group.
+pub(crate) struct FrameLatencyLimiter {
+    state: Mutex<LatencyState>,
+}
+
+struct LatencyState {
+    max_frames: Option<usize>,
+    // Loaded the first time a limit is set.
+    functions: Option<SyncFunctions>,
+    // The fences of the frames in flight, oldest first.
+    fences: VecDeque<GLsync>,
+}
+
+// The fences are only used while the context is current, which the `PossiblyCurrent` state
+// guarantees for every method that uses them.
+unsafe impl Send for FrameLatencyLimiter {}
+unsafe impl Sync for FrameLatencyLimiter {}
+
+impl FrameLatencyLimiter {
+    pub(crate) fn new() -> FrameLatencyLimiter {
+        FrameLatencyLimiter {
+            state: Mutex::new(LatencyState {
+                max_frames: None,
+                functions: None,
+                fences: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Sets the maximum number of frames in flight, which must be at least 1. `None` removes the
+    /// limit. The context must be current.
+    pub(crate) fn set_max_frame_latency<F>(&self, frames: Option<u32>, get_proc_address: F)
+                                           -> Result<(), ContextError>
+        where F: Fn(&str) -> *const ()
+    {
+        let mut state = self.state.lock().unwrap();
+        if frames.is_some() && state.functions.is_none() {
+            state.functions = Some(SyncFunctions::load(get_proc_address)
+                .ok_or(ContextError::FunctionUnavailable)?);
+        }
+
+        let max_frames = frames.map(|frames| frames.max(1) as usize);
+        if max_frames != state.max_frames {
+            state.max_frames = max_frames;
+            state.delete_fences();
+        }
+        Ok(())
+    }
+
+    /// Deletes the fences of the frames in flight without waiting for them. The context must be
+    /// current.
+    pub(crate) unsafe fn delete_fences(&self) {
+        self.state.lock().unwrap().delete_fences();
+    }
+
+    /// Inserts a fence for the frame that was just swapped, and waits until no more than the
+    /// maximum number of frames are in flight. Must be called right after swapping.
+    pub(crate) fn after_swap(&self) -> Result<(), ContextError> {
+        let mut state = self.state.lock().unwrap();
+        let max_frames = match state.max_frames {
+            Some(max_frames) => max_frames,
+            None => return Ok(()),
+        };
+
+        let fence_sync = state.functions.as_ref().unwrap().fence_sync;
+        let fence = fence_sync(GL_SYNC_GPU_COMMANDS_COMPLETE, 0);
+        if !fence.is_null() {
+            state.fences.push_back(fence);
+        }
+        state.wait_for_fences(max_frames)
+    }
+}
+
+impl LatencyState {
+    fn delete_fences(&mut self) {
+        if let Some(ref functions) = self.functions {
+            for fence in self.fences.drain(..) {
+                (functions.delete_sync)(fence);
+            }
+        }
+    }
+
+    /// Waits for the oldest fences until at most `max_frames` are left.
+    fn wait_for_fences(&mut self, max_frames: usize) -> Result<(), ContextError> {
+        while self.fences.len() > max_frames {
+            let fence = self.fences.pop_front().unwrap();
+            let functions = self.functions.as_ref().unwrap();
+            let ret = (functions.client_wait_sync)(fence, GL_SYNC_FLUSH_COMMANDS_BIT, WAIT_TIMEOUT);
+            (functions.delete_sync)(fence);
+            // on timeout the frame is most likely stuck, and waiting longer wouldn't help
+            if ret == GL_WAIT_FAILED {
+                return Err(ContextError::OsError(format!("glClientWaitSync failed")));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(target_os = "windows")]
+use winapi::um::timeapi::{timeBeginPeriod, timeEndPeriod};
+
+/// Limits how often the buffers of a context are swapped, by sleeping before the swap. Used by
+/// `Context::set_frame_rate_limit`.
+pub(crate) struct FrameLimiter {
+    state: Mutex<LimiterState>,
+}
+
+struct LimiterState {
+    frame_time: Option<Duration>,
+    // The earliest time at which the next swap may happen.
+    next_swap: Option<Instant>,
+}
+
+impl FrameLimiter {
+    pub(crate) fn new() -> FrameLimiter {
+        FrameLimiter {
+            state: Mutex::new(LimiterState { frame_time: None, next_swap: None }),
+        }
+    }
+
+    /// Sets the maximum number of swaps per second. `None`, or a value that isn't strictly
+    /// positive, removes the limit.
+    pub(crate) fn set_frame_rate_limit(&self, limit: Option<f32>) {
+        let frame_time = frame_time(limit);
+        let mut state = self.state.lock().unwrap();
+        match (state.frame_time.is_some(), frame_time.is_some()) {
+            (false, true) => begin_high_resolution_timer(),
+            (true, false) => end_high_resolution_timer(),
+            _ => (),
+        }
+        state.frame_time = frame_time;
+        state.next_swap = None;
+    }
+
+    /// Sleeps until the next swap may happen. Must be called right before swapping.
+    pub(crate) fn wait(&self) {
+        let mut state = self.state.lock().unwrap();
+        let frame_time = match state.frame_time {
+            Some(frame_time) => frame_time,
+            None => return,
+        };
+
+        if let Some(next_swap) = state.next_swap {
+            sleep_until(next_swap);
+        }
+        state.next_swap = Some(next_swap(state.next_swap, Instant::now(), frame_time));
+    }
+}
+
+/// Returns the time between two swaps for a limit of swaps per second, or `None` if the limit
+/// isn't strictly positive.
+fn frame_time(limit: Option<f32>) -> Option<Duration> {
+    match limit {
+        Some(fps) if fps > 0.0 && fps.is_finite() => {
+            let nanos = (1_000_000_000.0 / fps as f64) as u64;
+            Some(Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32))
+        },
+        _ => None,
+    }
+}
+
+/// Returns the earliest time of the swap after the one happening at `now`, which was scheduled
+/// for `scheduled`.
+fn next_swap(scheduled: Option<Instant>, now: Instant, frame_time: Duration) -> Instant {
+    // if the swap is late by more than a frame, the next ones are scheduled from now instead of
+    // swapping several times in a row to catch up
+    match scheduled {
+        Some(scheduled) if now < scheduled + frame_time => scheduled + frame_time,
+        _ => now + frame_time,
+    }
+}
+
+impl Drop for FrameLimiter {
+    fn drop(&mut self) {
+        if self.state.get_mut().unwrap().frame_time.is_some() {
+            end_high_resolution_timer();
+        }
+    }
+}
+
+/// Sleeps until `deadline`. The thread sleeps until shortly before the deadline and yields for
+/// the remaining time, since the OS may wake it up late.
+fn sleep_until(deadline: Instant) {
+    let spin_time = Duration::new(0, 1_000_000);
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return;
+        }
+        let remaining = deadline - now;
+        if remaining > spin_time {
+            thread::sleep(remaining - spin_time);
+        } else {
+            thread::yield_now();
+        }
+    }
+}
+
+// The default timer resolution on Windows is around 15ms, which is too coarse for sleeping
+// between frames.
+#[cfg(target_os = "windows")]
+fn begin_high_resolution_timer() {
+    unsafe { timeBeginPeriod(1); }
+}
+
+#[cfg(target_os = "windows")]
+fn end_high_resolution_timer() {
+    unsafe { timeEndPeriod(1); }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn begin_high_resolution_timer() {
+}
+
+#[cfg(not(target_os = "windows"))]
+fn end_high_resolution_timer() {
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{frame_time, next_swap};
+
+    use std::f32;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn frame_time_of_limits() {
+        assert_eq!(frame_time(Some(60.0)), Some(Duration::new(0, 16_666_666)));
+        assert_eq!(frame_time(Some(0.5)), Some(Duration::new(2, 0)));
+        assert_eq!(frame_time(None), None);
+        assert_eq!(frame_time(Some(0.0)), None);
+        assert_eq!(frame_time(Some(-30.0)), None);
+        assert_eq!(frame_time(Some(f32::INFINITY)), None);
+        assert_eq!(frame_time(Some(f32::NAN)), None);
+    }
+
+    #[test]
+    fn swaps_are_scheduled_one_frame_apart() {
+        let start = Instant::now();
+        let frame = Duration::from_millis(10);
+        // the first swap happens right away
+        assert_eq!(next_swap(None, start, frame), start + frame);
+        // on time or slightly late, the schedule is kept
+        assert_eq!(next_swap(Some(start), start, frame), start + frame);
+        assert_eq!(next_swap(Some(start), start + Duration::from_millis(4), frame),
+                   start + frame);
+        // late by more than a frame, the schedule restarts from now
+        let late = start + Duration::from_millis(15);
+        assert_eq!(next_swap(Some(start), late, frame), late + frame);
+    }
+}
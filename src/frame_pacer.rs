@@ -0,0 +1,221 @@
+use ContextError;
+use GlContext;
+
+use std::collections::VecDeque;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Number of frames whose duration is remembered to estimate the refresh period.
+const SAMPLES: usize = 32;
+
+/// Keeps the time between frames stable by measuring when swaps complete, estimating the refresh
+/// period of the screen and sleeping before swapping so that every frame is shown for the same
+/// number of vertical blanks.
+///
+/// The pacer works with any context through `swap_buffers`, and only relies on the time at which
+/// the swaps return. Vsync must be enabled for the pacer to line frames up with vertical blanks;
+/// without it, the pacer only limits the frame rate to the target frame time.
+///
+/// ```no_run
+/// # fn example<C: glutin::GlContext>(context: &C) -> Result<(), glutin::ContextError> {
+/// use std::time::Duration;
+///
+/// // render at 30 frames per second on a 60Hz screen
+/// let mut pacer = glutin::FramePacer::new()
+///     .with_target_frame_time(Duration::from_millis(33));
+/// loop {
+///     // draw the frame
+///     pacer.swap_buffers(context)?;
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FramePacer {
+    target_frame_time: Option<Duration>,
+    last_swap: Option<Instant>,
+    // The time between the last swaps, most recent last.
+    samples: VecDeque<Duration>,
+}
+
+impl FramePacer {
+    /// Builds a pacer that presents a frame on every vertical blank.
+    pub fn new() -> FramePacer {
+        FramePacer {
+            target_frame_time: None,
+            last_swap: None,
+            samples: VecDeque::with_capacity(SAMPLES),
+        }
+    }
+
+    /// Sets the time every frame should stay on screen. It is rounded to a whole number of
+    /// refresh periods once the refresh period is known.
+    pub fn with_target_frame_time(mut self, frame_time: Duration) -> FramePacer {
+        self.target_frame_time = Some(frame_time);
+        self
+    }
+
+    /// Returns the refresh period estimated from the previous swaps, or `None` if not enough
+    /// frames were swapped yet.
+    ///
+    /// The estimate is the shortest time between two recent swaps, so it is only accurate if
+    /// vsync is enabled and some frames were rendered within one refresh period.
+    pub fn refresh_period(&self) -> Option<Duration> {
+        if self.samples.len() < 2 {
+            return None;
+        }
+        self.samples.iter().cloned().min()
+    }
+
+    /// Returns the predicted time of the vertical blank the next frame will be presented on.
+    pub fn predicted_present_time(&self) -> Option<Instant> {
+        let last_swap = self.last_swap?;
+        Some(last_swap + self.frame_time()?)
+    }
+
+    /// Sleeps until the next frame should be submitted, then swaps the buffers of `context`.
+    ///
+    /// If the frame is late, the buffers are swapped immediately and the next frames are paced
+    /// from this one, instead of trying to catch up.
+    pub fn swap_buffers<C: GlContext>(&mut self, context: &C) -> Result<(), ContextError> {
+        if let Some(deadline) = self.deadline() {
+            let now = Instant::now();
+            if deadline > now {
+                thread::sleep(deadline - now);
+            }
+        }
+
+        context.swap_buffers()?;
+        self.record_swap(Instant::now());
+        Ok(())
+    }
+
+    /// Forgets the previous swaps, for example after the window was moved to another screen.
+    pub fn reset(&mut self) {
+        self.last_swap = None;
+        self.samples.clear();
+    }
+
+    // The time at which the next frame should be submitted, or `None` if it can be submitted
+    // right away.
+    fn deadline(&self) -> Option<Instant> {
+        let last_swap = self.last_swap?;
+        let frame_time = self.frame_time()?;
+        // with vsync, the swap completes on the vertical blank following its submission, so it
+        // is submitted half a refresh period before the expected vertical blank
+        let margin = self.refresh_period().map(|p| p / 2).unwrap_or(Duration::new(0, 0));
+        Some(last_swap + frame_time - margin.min(frame_time))
+    }
+
+    // Records that a swap completed at `now`.
+    fn record_swap(&mut self, now: Instant) {
+        if let Some(last_swap) = self.last_swap {
+            if self.samples.len() == SAMPLES {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(now - last_swap);
+        }
+        self.last_swap = Some(now);
+    }
+
+    // The time between two frames: the target frame time rounded to a whole number of refresh
+    // periods, or a single refresh period if there is no target.
+    fn frame_time(&self) -> Option<Duration> {
+        let target = self.target_frame_time;
+        match (self.refresh_period(), target) {
+            (Some(period), Some(target)) => {
+                let period_nanos = duration_nanos(period);
+                if period_nanos == 0 {
+                    return Some(target);
+                }
+                let periods = (duration_nanos(target) + period_nanos / 2) / period_nanos;
+                Some(period * periods.max(1) as u32)
+            },
+            (Some(period), None) => Some(period),
+            (None, target) => target,
+        }
+    }
+}
+
+impl Default for FramePacer {
+    fn default() -> FramePacer {
+        FramePacer::new()
+    }
+}
+
+fn duration_nanos(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000_000 + duration.subsec_nanos() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FramePacer, SAMPLES};
+
+    use std::time::{Duration, Instant};
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    // a pacer that swapped at `start` and then after each of `intervals`
+    fn swapped(mut pacer: FramePacer, start: Instant, intervals: &[u64]) -> FramePacer {
+        pacer.record_swap(start);
+        let mut time = start;
+        for &interval in intervals {
+            time += millis(interval);
+            pacer.record_swap(time);
+        }
+        pacer
+    }
+
+    #[test]
+    fn refresh_period_is_the_shortest_interval() {
+        let start = Instant::now();
+        let pacer = swapped(FramePacer::new(), start, &[20]);
+        assert_eq!(pacer.refresh_period(), None);
+        let pacer = swapped(FramePacer::new(), start, &[20, 16, 33]);
+        assert_eq!(pacer.refresh_period(), Some(millis(16)));
+        assert_eq!(pacer.predicted_present_time(), Some(start + millis(69 + 16)));
+    }
+
+    #[test]
+    fn samples_are_bounded() {
+        let mut intervals = vec![10];
+        intervals.extend(vec![16; SAMPLES]);
+        let pacer = swapped(FramePacer::new(), Instant::now(), &intervals);
+        assert_eq!(pacer.samples.len(), SAMPLES);
+        assert_eq!(pacer.refresh_period(), Some(millis(16)));
+    }
+
+    #[test]
+    fn frame_time_is_rounded_to_refresh_periods() {
+        let start = Instant::now();
+        let pacer = FramePacer::new().with_target_frame_time(millis(33));
+        assert_eq!(pacer.frame_time(), Some(millis(33)));
+        let pacer = swapped(pacer, start, &[16, 16]);
+        assert_eq!(pacer.frame_time(), Some(millis(32)));
+        let pacer = swapped(FramePacer::new().with_target_frame_time(millis(40)), start, &[16, 16]);
+        assert_eq!(pacer.frame_time(), Some(millis(48)));
+        // a target shorter than a refresh period still waits for one
+        let pacer = swapped(FramePacer::new().with_target_frame_time(millis(5)), start, &[16, 16]);
+        assert_eq!(pacer.frame_time(), Some(millis(16)));
+        let pacer = swapped(FramePacer::new(), start, &[16, 16]);
+        assert_eq!(pacer.frame_time(), Some(millis(16)));
+    }
+
+    #[test]
+    fn deadline_is_half_a_period_before_the_blank() {
+        let start = Instant::now();
+        assert_eq!(FramePacer::new().deadline(), None);
+        let pacer = swapped(FramePacer::new(), start, &[16, 16]);
+        assert_eq!(pacer.deadline(), Some(start + millis(32 + 16 - 8)));
+        let pacer = swapped(FramePacer::new().with_target_frame_time(millis(33)), start, &[16, 16]);
+        assert_eq!(pacer.deadline(), Some(start + millis(32 + 32 - 8)));
+
+        // without a refresh period, only the target frame time is waited for
+        let mut pacer = FramePacer::new().with_target_frame_time(millis(33));
+        pacer.record_swap(start);
+        assert_eq!(pacer.deadline(), Some(start + millis(33)));
+        pacer.reset();
+        assert_eq!(pacer.deadline(), None);
+    }
+}
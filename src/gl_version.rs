@@ -0,0 +1,132 @@
+use std::ffi::CStr;
+use std::mem;
+use std::os::raw::{c_char, c_int, c_uint};
+use std::sync::Mutex;
+
+use GlProfile;
+
+// From `glext.h`.
+const GL_VERSION: c_uint = 0x1F02;
+const GL_MAJOR_VERSION: c_uint = 0x821B;
+const GL_MINOR_VERSION: c_uint = 0x821C;
+const GL_CONTEXT_PROFILE_MASK: c_uint = 0x9126;
+const GL_CONTEXT_CORE_PROFILE_BIT: c_int = 0x1;
+const GL_CONTEXT_COMPATIBILITY_PROFILE_BIT: c_int = 0x2;
+
+type GetStringFn = extern "system" fn(c_uint) -> *const c_char;
+type GetIntegervFn = extern "system" fn(c_uint, *mut c_int);
+
+/// The version and profile of a context, read the first time it is made current. Used by
+/// `Context::get_version` and `Context::get_profile`.
+pub(crate) struct VersionQuery {
+    read: Mutex<Option<((u8, u8), Option<GlProfile>)>>,
+}
+
+impl VersionQuery {
+    pub(crate) fn new() -> VersionQuery {
+        VersionQuery { read: Mutex::new(None) }
+    }
+
+    /// Returns the version and the profile of the context, or `((0, 0), None)` if it was never
+    /// made current.
+    pub(crate) fn get(&self) -> ((u8, u8), Option<GlProfile>) {
+        self.read.lock().unwrap().unwrap_or(((0, 0), None))
+    }
+
+    /// Reads the version and the profile if they weren't read yet. The context must be current,
+    /// and `get_proc_address` must load its functions.
+    pub(crate) unsafe fn read<F>(&self, get_proc_address: F)
+        where F: Fn(&str) -> *const ()
+    {
+        let mut read = self.read.lock().unwrap();
+        if read.is_some() {
+            return;
+        }
+
+        let get_string = get_proc_address("glGetString");
+        if get_string.is_null() {
+            return;
+        }
+        let get_string: GetStringFn = mem::transmute(get_string);
+        let version = get_string(GL_VERSION);
+        if version.is_null() {
+            return;
+        }
+        let version = CStr::from_ptr(version).to_string_lossy();
+        let es = version.starts_with("OpenGL ES");
+        let (major, minor) = parse_version(&version).unwrap_or((0, 0));
+
+        // `GL_CONTEXT_PROFILE_MASK` only exists since OpenGL 3.2, earlier versions only have
+        // what became the compatibility profile
+        let profile = if es {
+            None
+        } else if (major, minor) >= (3, 2) {
+            let get_integerv = get_proc_address("glGetIntegerv");
+            let mut mask = 0;
+            if !get_integerv.is_null() {
+                let get_integerv: GetIntegervFn = mem::transmute(get_integerv);
+                get_integerv(GL_CONTEXT_PROFILE_MASK, &mut mask);
+            }
+            if mask & GL_CONTEXT_CORE_PROFILE_BIT != 0 {
+                Some(GlProfile::Core)
+            } else if mask & GL_CONTEXT_COMPATIBILITY_PROFILE_BIT != 0 {
+                Some(GlProfile::Compatibility)
+            } else {
+                None
+            }
+        } else {
+            Some(GlProfile::Compatibility)
+        };
+
+        // the integer queries don't depend on the format of the string, but need OpenGL 3.0 or
+        // OpenGL ES 3.0
+        let (major, minor) = if major >= 3 {
+            read_version_integers(&get_proc_address).unwrap_or((major, minor))
+        } else {
+            (major, minor)
+        };
+
+        *read = Some(((major, minor), profile));
+    }
+}
+
+unsafe fn read_version_integers<F>(get_proc_address: &F) -> Option<(u8, u8)>
+    where F: Fn(&str) -> *const ()
+{
+    let get_integerv = get_proc_address("glGetIntegerv");
+    if get_integerv.is_null() {
+        return None;
+    }
+    let get_integerv: GetIntegervFn = mem::transmute(get_integerv);
+    let mut major = -1;
+    let mut minor = -1;
+    get_integerv(GL_MAJOR_VERSION, &mut major);
+    get_integerv(GL_MINOR_VERSION, &mut minor);
+    if major < 0 || minor < 0 {
+        return None;
+    }
+    Some((major as u8, minor as u8))
+}
+
+/// Parses the version at the start of a `GL_VERSION` string, such as `4.6.0 NVIDIA 440.82`,
+/// `OpenGL ES 3.2 Mesa 20.0.8` or `OpenGL ES-CM 1.1`.
+pub(crate) fn parse_version(version: &str) -> Option<(u8, u8)> {
+    let version = version.trim_left_matches(|c: char| !c.is_digit(10));
+    let mut numbers = version.split(|c: char| !c.is_digit(10));
+    let major = numbers.next()?.parse().ok()?;
+    let minor = numbers.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_version;
+
+    #[test]
+    fn parse_version_formats() {
+        assert_eq!(parse_version("4.6.0 NVIDIA 440.82"), Some((4, 6)));
+        assert_eq!(parse_version("OpenGL ES 3.2 Mesa 20.0.8"), Some((3, 2)));
+        assert_eq!(parse_version("OpenGL ES-CM 1.1"), Some((1, 1)));
+        assert_eq!(parse_version("OpenGL ES"), None);
+    }
+}
@@ -1,16 +1,43 @@
 use Api;
+use ContextCurrentState;
 use ContextError;
+use ContextPriority;
+use ContextTrait;
 use CreationError;
 use GlAttributes;
 use GlContext;
 use GlProfile;
 use GlRequest;
+use HardwareAcceleration;
+use NotCurrent;
 use PixelFormat;
+use PossiblyCurrent;
 use PixelFormatRequirements;
+use RelaxedRequirement;
+use ReleaseBehavior;
 use Robustness;
 
+use create_with_fallbacks;
+use create_with_versions;
+use current_renderer;
+use debug_output::{DebugCallback, DebugFilter, DebugMessage, DebugOutput};
+use is_software_renderer;
+
 use platform;
 
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::{c_int, c_uint, c_void};
+use std::sync::Arc;
+
+// From `gl.h`.
+const GL_FRONT: c_uint = 0x0404;
+const GL_READ_BUFFER: c_uint = 0x0C02;
+const GL_PACK_ALIGNMENT: c_uint = 0x0D05;
+const GL_RGBA: c_uint = 0x1908;
+const GL_UNSIGNED_BYTE: c_uint = 0x1401;
+const GL_READ_FRAMEBUFFER_BINDING: c_uint = 0x8CAA;
+
 /// Object that allows you to build headless contexts.
 #[derive(Clone)]
 pub struct HeadlessRendererBuilder<'a> {
@@ -23,8 +50,14 @@ pub struct HeadlessRendererBuilder<'a> {
     // Should be made public once it's stabilized.
     pf_reqs: PixelFormatRequirements,
 
+    // True if the context should be built without any surface.
+    surfaceless: bool,
+
+    // The share group of the context passed to `with_shared_lists`.
+    share_group: Option<Arc<()>>,
+
     /// Platform-specific configuration.
-    platform_specific: platform::PlatformSpecificHeadlessBuilderAttributes,
+    pub(crate) platform_specific: platform::PlatformSpecificHeadlessBuilderAttributes,
 }
 
 impl<'a> HeadlessRendererBuilder<'a> {
@@ -34,10 +67,12 @@ impl<'a> HeadlessRendererBuilder<'a> {
         HeadlessRendererBuilder {
             dimensions: (width, height),
             pf_reqs: PixelFormatRequirements {
-                hardware_accelerated: None,
+                hardware_accelerated: HardwareAcceleration::DontCare,
                 .. Default::default()
             },
             opengl: Default::default(),
+            surfaceless: false,
+            share_group: None,
             platform_specific: Default::default(),
         }
     }
@@ -73,15 +108,141 @@ impl<'a> HeadlessRendererBuilder<'a> {
         self
     }
 
+    /// Sets whether the context is flushed when it stops being current. See
+    /// `ContextBuilder::with_release_behavior`.
+    #[inline]
+    pub fn with_release_behavior(mut self, behavior: ReleaseBehavior)
+                                 -> HeadlessRendererBuilder<'a>
+    {
+        self.opengl.release_behavior = behavior;
+        self
+    }
+
+    /// Requests a scheduling priority for the context. See
+    /// `ContextBuilder::with_context_priority`.
+    #[inline]
+    pub fn with_context_priority(mut self, priority: ContextPriority)
+                                 -> HeadlessRendererBuilder<'a>
+    {
+        self.opengl.priority = Some(priority);
+        self
+    }
+
+    /// Sets whether the context is created without error checking. See
+    /// `ContextBuilder::with_gl_no_error`.
+    #[inline]
+    pub fn with_gl_no_error(mut self, no_error: bool) -> HeadlessRendererBuilder<'a> {
+        self.opengl.no_error = no_error;
+        self
+    }
+
+    /// Sets a function receiving the debug messages of the context that pass `filter`, and
+    /// requests a debug context. See `ContextBuilder::with_debug_callback`.
+    #[inline]
+    pub fn with_debug_callback<F>(mut self, filter: DebugFilter, callback: F)
+                                  -> HeadlessRendererBuilder<'a>
+        where F: Fn(&DebugMessage) + Send + Sync + 'static
+    {
+        self.opengl.debug = true;
+        self.opengl.debug_callback = Some(DebugCallback(Arc::new(callback), filter));
+        self
+    }
+
+    /// Sets whether to build the context without any surface, instead of creating a pbuffer of
+    /// `dimensions`. Such a context can only render to framebuffer objects, which is enough for
+    /// compute or offscreen workloads and also works on drivers whose pbuffers are broken.
+    ///
+    /// This requires EGL with `EGL_KHR_surfaceless_context`, otherwise `build` returns
+    /// `CreationError::NotSupported`. OpenGL ES contexts also need `GL_OES_surfaceless_context`,
+    /// which can only be checked once the context is current. On macOS, headless contexts never
+    /// have a surface and this is ignored. On Unix, a context with a 1x1 pbuffer or an OSMesa
+    /// context is built instead if no EGL display supports surfaceless contexts.
+    ///
+    /// The default value is `false`.
+    #[inline]
+    pub fn with_surfaceless(mut self, surfaceless: bool) -> HeadlessRendererBuilder<'a> {
+        self.surfaceless = surfaceless;
+        self
+    }
+
+    /// Requires the context to be rendered in software, for example by llvmpipe, softpipe or
+    /// OSMesa, so that the images it renders don't depend on the GPU of the machine. `build`
+    /// fails with `CreationError::NotSupported` if only hardware renderers are available.
+    ///
+    /// The renderer is checked with `GL_RENDERER`, so the context is made current during `build`
+    /// and no context is current on the calling thread afterwards. On Unix, OSMesa is tried
+    /// first unless a backend priority, an EGL device or a DRM device was chosen.
+    #[inline]
+    pub fn require_software_rendering(mut self) -> HeadlessRendererBuilder<'a> {
+        self.pf_reqs.software_rendering = true;
+        self
+    }
+
+    /// Shares the display lists with another headless context. The new context is built with
+    /// the same backend, display and config as `other`, and the other attributes of the builder
+    /// are ignored.
+    ///
+    /// This is only supported on Unix-like platforms, elsewhere `build` returns
+    /// `CreationError::NotSupported`.
+    #[inline]
+    pub fn with_shared_lists<T: ContextCurrentState>(mut self, other: &'a HeadlessContext<T>)
+                                                     -> HeadlessRendererBuilder<'a>
+    {
+        self.opengl.sharing = Some(&other.context);
+        self.share_group = Some(other.share_group.clone());
+        self
+    }
+
     /// Builds the headless context.
     ///
     /// Error should be very rare and only occur in case of permission denied, incompatible system,
     ///  out of memory, etc.
     #[inline]
-    pub fn build(self) -> Result<HeadlessContext, CreationError> {
-        platform::HeadlessContext::new(self.dimensions, &self.pf_reqs, &self.opengl,
-                                       &self.platform_specific)
-                .map(|w| HeadlessContext { context: w })
+    pub fn build(self) -> Result<HeadlessContext<NotCurrent>, CreationError> {
+        self.build_with(|dimensions, surfaceless, pf_reqs, opengl, platform_specific| {
+            if surfaceless {
+                platform::HeadlessContext::new_surfaceless(pf_reqs, opengl, platform_specific)
+            } else {
+                platform::HeadlessContext::new(dimensions, pf_reqs, opengl, platform_specific)
+            }
+        })
+    }
+
+    /// Builds the headless context with `create`, which is called with the dimensions, whether
+    /// the context is surfaceless, and the requirements of each step of the fallback chain.
+    fn build_with<F>(self, mut create: F) -> Result<HeadlessContext<NotCurrent>, CreationError>
+        where F: FnMut((u32, u32), bool, &PixelFormatRequirements,
+                       &GlAttributes<&platform::HeadlessContext>,
+                       &platform::PlatformSpecificHeadlessBuilderAttributes)
+                       -> Result<platform::HeadlessContext, CreationError>
+    {
+        let HeadlessRendererBuilder {
+            dimensions, opengl, pf_reqs, surfaceless, share_group, platform_specific
+        } = self;
+        // only the Unix backends can share lists between headless contexts
+        let unix = cfg!(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+                            target_os = "openbsd"));
+        if !unix && opengl.sharing.is_some() {
+            return Err(CreationError::NotSupported("sharing lists between headless contexts"));
+        }
+        let (context, relaxed) = create_with_versions(&opengl, |opengl| {
+            create_with_fallbacks(&pf_reqs, |pf_reqs| {
+                create(dimensions, surfaceless, pf_reqs, opengl, &platform_specific)
+            })
+        })?;
+
+        if pf_reqs.software_rendering && !is_software_renderer(&unsafe { renderer(&context)? }) {
+            return Err(CreationError::NotSupported("no software renderer is available"));
+        }
+
+        Ok(HeadlessContext {
+            context: context,
+            dimensions: if surfaceless { None } else { Some(dimensions) },
+            relaxed_requirements: relaxed,
+            share_group: share_group.unwrap_or_else(|| Arc::new(())),
+            debug_output: DebugOutput::new(opengl.debug_callback.clone()),
+            phantom: PhantomData,
+        })
     }
 
     /// Builds the headless context.
@@ -89,24 +250,250 @@ impl<'a> HeadlessRendererBuilder<'a> {
     /// The context is build in a *strict* way. That means that if the backend couldn't give
     /// you what you requested, an `Err` will be returned.
     #[inline]
-    pub fn build_strict(self) -> Result<HeadlessContext, CreationError> {
+    pub fn build_strict(self) -> Result<HeadlessContext<NotCurrent>, CreationError> {
         self.build()
     }
 }
 
+/// Returns the `GL_RENDERER` string of `context`, which is made current for the query and then
+/// released.
+unsafe fn renderer(context: &platform::HeadlessContext) -> Result<String, CreationError> {
+    context.make_current().map_err(|err| CreationError::OsError(format!("{}", err)))?;
+    let renderer = current_renderer(|addr| context.get_proc_address(addr)).unwrap_or_default();
+
+    context.make_not_current().map_err(|err| CreationError::OsError(format!("{}", err)))?;
+    Ok(renderer)
+}
+
 /// Represents a headless OpenGL context.
-pub struct HeadlessContext {
+pub struct HeadlessContext<T: ContextCurrentState> {
     pub(crate) context: platform::HeadlessContext,
+    // The dimensions of the framebuffer, or `None` if the context was built without a surface.
+    pub(crate) dimensions: Option<(u32, u32)>,
+    pub(crate) relaxed_requirements: Vec<RelaxedRequirement>,
+    // The contexts whose lists are shared with this one, if any, are in the same group.
+    pub(crate) share_group: Arc<()>,
+    pub(crate) debug_output: DebugOutput,
+    pub(crate) phantom: PhantomData<T>,
 }
 
-impl GlContext for HeadlessContext {
-    /// Creates a new OpenGL context
-    /// Sets the context as the current context.
+impl<T: ContextCurrentState> HeadlessContext<T> {
+    /// Sets the context as the current context on the calling thread.
+    ///
+    /// See `Context::make_current` for more infos.
     #[inline]
-    unsafe fn make_current(&self) -> Result<(), ContextError> {
-        self.context.make_current()
+    pub unsafe fn make_current(self)
+        -> Result<HeadlessContext<PossiblyCurrent>, (Self, ContextError)>
+    {
+        match self.context.make_current() {
+            Ok(()) => {
+                self.debug_output.install(|addr| self.context.get_proc_address(addr));
+                Ok(HeadlessContext {
+                    context: self.context,
+                    dimensions: self.dimensions,
+                    relaxed_requirements: self.relaxed_requirements,
+                    share_group: self.share_group,
+                    debug_output: self.debug_output,
+                    phantom: PhantomData,
+                })
+            },
+            Err(err) => Err((self, err)),
+        }
+    }
+
+    /// If this context is current on the calling thread, releases it.
+    ///
+    /// See `Context::make_not_current` for more infos.
+    #[inline]
+    pub unsafe fn make_not_current(self)
+        -> Result<HeadlessContext<NotCurrent>, (Self, ContextError)>
+    {
+        match self.context.make_not_current() {
+            Ok(()) => Ok(HeadlessContext {
+                context: self.context,
+                dimensions: self.dimensions,
+                relaxed_requirements: self.relaxed_requirements,
+                share_group: self.share_group,
+                debug_output: self.debug_output,
+                phantom: PhantomData,
+            }),
+            Err(err) => Err((self, err)),
+        }
+    }
+
+    /// Changes the state of the context to `PossiblyCurrent` without making it current.
+    ///
+    /// See `Context::treat_as_current` for more infos.
+    #[inline]
+    pub unsafe fn treat_as_current(self) -> HeadlessContext<PossiblyCurrent> {
+        HeadlessContext {
+            context: self.context,
+            dimensions: self.dimensions,
+            relaxed_requirements: self.relaxed_requirements,
+            share_group: self.share_group,
+            debug_output: self.debug_output,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Changes the state of the context to `NotCurrent` without releasing it.
+    ///
+    /// See `Context::treat_as_not_current` for more infos.
+    #[inline]
+    pub unsafe fn treat_as_not_current(self) -> HeadlessContext<NotCurrent> {
+        HeadlessContext {
+            context: self.context,
+            dimensions: self.dimensions,
+            relaxed_requirements: self.relaxed_requirements,
+            share_group: self.share_group,
+            debug_output: self.debug_output,
+            phantom: PhantomData,
+        }
     }
 
+    /// Returns true if this context is the current one in this thread.
+    #[inline]
+    pub fn is_current(&self) -> bool {
+        self.context.is_current()
+    }
+
+    /// Returns the API that is currently provided by this context.
+    #[inline]
+    pub fn get_api(&self) -> Api {
+        self.context.get_api()
+    }
+
+    /// Returns the pixel format of the main framebuffer of the context.
+    ///
+    /// See `Context::get_pixel_format` for more infos.
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        let mut pixel_format = self.context.get_pixel_format();
+        if pixel_format.hardware_accelerated && self.context.is_current() {
+            let context = &self.context;
+            let renderer = unsafe { current_renderer(|addr| context.get_proc_address(addr)) };
+            if let Some(renderer) = renderer {
+                pixel_format.hardware_accelerated = !is_software_renderer(&renderer);
+            }
+        }
+        pixel_format
+    }
+
+    /// Returns the requirements that were relaxed to create the context.
+    ///
+    /// See `Context::relaxed_requirements` for more infos.
+    #[inline]
+    pub fn relaxed_requirements(&self) -> &[RelaxedRequirement] {
+        &self.relaxed_requirements
+    }
+
+    /// Returns true if this context and `other` share objects, because one was built with
+    /// `with_shared_lists` on the other or on a context of the same group.
+    ///
+    /// See `Context::is_shared_with` for more infos.
+    #[inline]
+    pub fn is_shared_with<T2: ContextCurrentState>(&self, other: &HeadlessContext<T2>) -> bool {
+        Arc::ptr_eq(&self.share_group, &other.share_group)
+    }
+}
+
+impl HeadlessContext<PossiblyCurrent> {
+    /// Reads the pixels of the framebuffer the context renders to with `glReadPixels`, as
+    /// tightly packed 8 bits RGBA values from the bottom row to the top row like OpenGL.
+    ///
+    /// The context is made current if it isn't. If the default framebuffer is bound and double
+    /// buffered, the front buffer is read, so `swap_buffers` should be called first. If a
+    /// framebuffer object is bound, its read buffer is read instead. The pack alignment and the
+    /// read buffer are restored afterwards.
+    ///
+    /// Returns `ContextError::FunctionUnavailable` if the context was built without a surface.
+    pub fn read_front_buffer(&self) -> Result<Vec<u8>, ContextError> {
+        let (width, height) = self.dimensions.ok_or(ContextError::FunctionUnavailable)?;
+
+        unsafe {
+            if !self.context.is_current() {
+                self.context.make_current()?;
+            }
+
+            let load = |name: &str| -> Result<*const (), ContextError> {
+                let ptr = self.context.get_proc_address(name);
+                if ptr.is_null() { Err(ContextError::FunctionUnavailable) } else { Ok(ptr) }
+            };
+            let get_integerv: extern "system" fn(c_uint, *mut c_int) =
+                mem::transmute(load("glGetIntegerv")?);
+            let pixel_storei: extern "system" fn(c_uint, c_int) =
+                mem::transmute(load("glPixelStorei")?);
+            let read_pixels: extern "system" fn(c_int, c_int, c_int, c_int, c_uint, c_uint,
+                                                *mut c_void) =
+                mem::transmute(load("glReadPixels")?);
+
+            // only desktop OpenGL can read the front buffer of the default framebuffer
+            let mut read_framebuffer = 0;
+            get_integerv(GL_READ_FRAMEBUFFER_BINDING, &mut read_framebuffer);
+            let read_buffer = if self.get_api() == Api::OpenGl && read_framebuffer == 0 &&
+                                 self.get_pixel_format().double_buffer
+            {
+                let read_buffer: extern "system" fn(c_uint) =
+                    mem::transmute(load("glReadBuffer")?);
+                let mut previous = 0;
+                get_integerv(GL_READ_BUFFER, &mut previous);
+                read_buffer(GL_FRONT);
+                Some((read_buffer, previous))
+            } else {
+                None
+            };
+
+            let mut alignment = 0;
+            get_integerv(GL_PACK_ALIGNMENT, &mut alignment);
+            pixel_storei(GL_PACK_ALIGNMENT, 1);
+
+            let mut pixels = vec![0u8; width as usize * height as usize * 4];
+            read_pixels(0, 0, width as c_int, height as c_int, GL_RGBA, GL_UNSIGNED_BYTE,
+                        pixels.as_mut_ptr() as *mut c_void);
+
+            pixel_storei(GL_PACK_ALIGNMENT, alignment);
+            if let Some((read_buffer, previous)) = read_buffer {
+                read_buffer(previous as c_uint);
+            }
+
+            Ok(pixels)
+        }
+    }
+}
+
+#[cfg(feature = "winit")]
+impl HeadlessContext<NotCurrent> {
+    /// Builds a headless context with `builder` on the display connection of `events_loop`,
+    /// instead of opening another one. On X11, the context is built on the X11 connection of
+    /// the events loop with a config of its default screen, so that it can cheaply share
+    /// resources with the windows of the loop.
+    ///
+    /// The wayland display of an events loop can't be reached without a window, so this returns
+    /// `CreationError::NotSupported` on wayland, as it does if `with_shared_lists` was called
+    /// since the context must then be built on the display of the other context. The
+    /// platform-specific attributes of the builder are ignored on Unix-like platforms. Other
+    /// platforms have no display connection, and build the context as `builder.build()` does.
+    pub fn new_shared_display(events_loop: &::winit::EventsLoop,
+                              builder: HeadlessRendererBuilder)
+                              -> Result<HeadlessContext<NotCurrent>, CreationError>
+    {
+        #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+                  target_os = "openbsd"))]
+        {
+            builder.build_with(|dimensions, surfaceless, pf_reqs, opengl, _| {
+                platform::HeadlessContext::new_on_events_loop(events_loop, dimensions,
+                                                              surfaceless, pf_reqs, opengl)
+            })
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd",
+                      target_os = "openbsd")))]
+        {
+            let _ = events_loop;
+            builder.build()
+        }
+    }
+}
+
+impl GlContext for HeadlessContext<PossiblyCurrent> {
     /// Returns true if this context is the current one in this thread.
     #[inline]
     fn is_current(&self) -> bool {
@@ -136,7 +523,7 @@ impl GlContext for HeadlessContext {
 
     #[inline]
     fn get_pixel_format(&self) -> PixelFormat {
-        self.context.get_pixel_format()
+        HeadlessContext::get_pixel_format(self)
     }
 
     #[inline]
@@ -145,3 +532,36 @@ impl GlContext for HeadlessContext {
         unimplemented!()
     }
 }
+
+impl<T: ContextCurrentState> ContextTrait for HeadlessContext<T> {
+    type PossiblyCurrent = HeadlessContext<PossiblyCurrent>;
+    type NotCurrent = HeadlessContext<NotCurrent>;
+
+    unsafe fn make_current(self) -> Result<Self::PossiblyCurrent, (Self, ContextError)> {
+        HeadlessContext::make_current(self)
+    }
+
+    unsafe fn make_not_current(self) -> Result<Self::NotCurrent, (Self, ContextError)> {
+        HeadlessContext::make_not_current(self)
+    }
+
+    fn is_current(&self) -> bool {
+        HeadlessContext::is_current(self)
+    }
+
+    fn get_api(&self) -> Api {
+        HeadlessContext::get_api(self)
+    }
+
+    fn get_pixel_format(&self) -> PixelFormat {
+        HeadlessContext::get_pixel_format(self)
+    }
+}
+
+impl<T: ContextCurrentState> ::std::fmt::Debug for HeadlessContext<T> {
+    fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        formatter.debug_struct("HeadlessContext")
+            .field("api", &self.get_api())
+            .finish()
+    }
+}
@@ -0,0 +1,190 @@
+use ContextError;
+use CreationError;
+use HeadlessContext;
+use HeadlessRendererBuilder;
+use NotCurrent;
+use PossiblyCurrent;
+
+use std::ops::Deref;
+use std::sync::{Condvar, Mutex};
+
+/// A set of headless contexts sharing their lists, to render offline from several threads.
+///
+/// The contexts are all built from the same builder, on the same display and with the same
+/// config as the first one, whose lists they share. A worker thread calls `acquire` to get one of
+/// them, current on that thread, until the returned `PooledContext` is dropped. Textures, buffers
+/// and programs created by one worker can then be used by the others.
+///
+/// Sharing lists between headless contexts is only supported on Unix-like platforms. Elsewhere,
+/// only a pool of one context can be built.
+///
+/// ```no_run
+/// # use std::sync::Arc;
+/// # use std::thread;
+/// let builder = glutin::HeadlessRendererBuilder::new(256, 256);
+/// let pool = Arc::new(glutin::HeadlessContextPool::new(builder, 4).unwrap());
+///
+/// let workers: Vec<_> = (0..4).map(|_| {
+///     let pool = pool.clone();
+///     thread::spawn(move || {
+///         let context = pool.acquire().unwrap();
+///         // render with `context`, which is current on this thread
+///     })
+/// }).collect();
+/// # for worker in workers { worker.join().unwrap(); }
+/// ```
+pub struct HeadlessContextPool {
+    // The contexts that aren't acquired by any thread.
+    available: Mutex<Vec<HeadlessContext<NotCurrent>>>,
+    // Notified when a context is given back to the pool.
+    released: Condvar,
+    size: usize,
+}
+
+impl HeadlessContextPool {
+    /// Builds `size` contexts with `builder`, the first one as it is and the others sharing its
+    /// lists.
+    ///
+    /// Returns `CreationError::NotSupported` if `size` is zero, or if it is larger than one on a
+    /// platform that can't share lists between headless contexts.
+    pub fn new(builder: HeadlessRendererBuilder, size: usize)
+               -> Result<HeadlessContextPool, CreationError>
+    {
+        if size == 0 {
+            return Err(CreationError::NotSupported("a headless context pool can't be empty"));
+        }
+
+        let first = builder.clone().build()?;
+        let mut contexts = Vec::with_capacity(size);
+        for _ in 1..size {
+            contexts.push(builder.clone().with_shared_lists(&first).build()?);
+        }
+        contexts.insert(0, first);
+
+        Ok(HeadlessContextPool {
+            available: Mutex::new(contexts),
+            released: Condvar::new(),
+            size: size,
+        })
+    }
+
+    /// Returns the number of contexts of the pool.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the number of contexts that aren't acquired by any thread.
+    #[inline]
+    pub fn available(&self) -> usize {
+        self.available.lock().unwrap().len()
+    }
+
+    /// Waits until a context is available and makes it current on the calling thread.
+    ///
+    /// If making the context current fails, the context is given back to the pool and the error
+    /// is returned.
+    pub fn acquire(&self) -> Result<PooledContext, ContextError> {
+        let mut available = self.available.lock().unwrap();
+        loop {
+            if let Some(context) = available.pop() {
+                drop(available);
+                return self.make_current(context);
+            }
+            available = self.released.wait(available).unwrap();
+        }
+    }
+
+    /// Makes an available context current on the calling thread, or returns
+    /// `ContextError::WouldBlock` if all of them are acquired.
+    pub fn try_acquire(&self) -> Result<PooledContext, ContextError> {
+        let context = self.available.lock().unwrap().pop();
+        match context {
+            Some(context) => self.make_current(context),
+            None => Err(ContextError::WouldBlock),
+        }
+    }
+
+    /// Returns the contexts, for example to destroy them on specific threads. Blocks until all
+    /// the contexts acquired by other threads are given back.
+    pub fn into_contexts(self) -> Vec<HeadlessContext<NotCurrent>> {
+        let mut available = self.available.lock().unwrap();
+        while available.len() < self.size {
+            available = self.released.wait(available).unwrap();
+        }
+        drop(available);
+        self.available.into_inner().unwrap()
+    }
+
+    fn make_current(&self, context: HeadlessContext<NotCurrent>)
+                    -> Result<PooledContext, ContextError>
+    {
+        match unsafe { context.make_current() } {
+            Ok(context) => Ok(PooledContext { pool: self, context: Some(context) }),
+            Err((context, err)) => {
+                self.give_back(context);
+                Err(err)
+            },
+        }
+    }
+
+    fn give_back(&self, context: HeadlessContext<NotCurrent>) {
+        self.available.lock().unwrap().push(context);
+        self.released.notify_one();
+    }
+}
+
+impl ::std::fmt::Debug for HeadlessContextPool {
+    fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        formatter.debug_struct("HeadlessContextPool")
+            .field("size", &self.size)
+            .field("available", &self.available())
+            .finish()
+    }
+}
+
+/// A context acquired from a `HeadlessContextPool`, current on the thread that acquired it.
+///
+/// When it is dropped, the context is released and given back to the pool. Errors that happen
+/// at this point are ignored; use `release` to handle them.
+pub struct PooledContext<'a> {
+    pool: &'a HeadlessContextPool,
+    // `None` once the context has been given back by `release`.
+    context: Option<HeadlessContext<PossiblyCurrent>>,
+}
+
+impl<'a> PooledContext<'a> {
+    /// Releases the context and gives it back to the pool, returning the error instead of
+    /// ignoring it like dropping the `PooledContext` does. The context is given back even if it
+    /// couldn't be released.
+    pub fn release(mut self) -> Result<(), ContextError> {
+        let context = self.context.take().unwrap();
+        self.give_back(context)
+    }
+
+    fn give_back(&self, context: HeadlessContext<PossiblyCurrent>) -> Result<(), ContextError> {
+        let (context, result) = match unsafe { context.make_not_current() } {
+            Ok(context) => (context, Ok(())),
+            Err((context, err)) => (unsafe { context.treat_as_not_current() }, Err(err)),
+        };
+        self.pool.give_back(context);
+        result
+    }
+}
+
+impl<'a> Deref for PooledContext<'a> {
+    type Target = HeadlessContext<PossiblyCurrent>;
+
+    #[inline]
+    fn deref(&self) -> &HeadlessContext<PossiblyCurrent> {
+        self.context.as_ref().unwrap()
+    }
+}
+
+impl<'a> Drop for PooledContext<'a> {
+    fn drop(&mut self) {
+        if let Some(context) = self.context.take() {
+            let _ = self.give_back(context);
+        }
+    }
+}
@@ -1,10 +1,10 @@
 //! The purpose of this library is to provide an OpenGL context on as many platforms as possible.
 //!
-//! # Building a GlWindow
+//! # Building a WindowedContext
 //!
-//! A `GlWindow` is composed of a `Window` and an OpenGL `Context`. Due to some
+//! A `WindowedContext` is composed of a `Window` and an OpenGL `Context`. Due to some
 //! operating-system-specific quirks, glutin requires control over the order of creation of the
-//! `Context` and `Window`. Here is an example of building a GlWindow:
+//! `Context` and `Window`. Here is an example of building a WindowedContext:
 //!
 //! ```no_run
 //! # extern crate glutin;
@@ -14,24 +14,25 @@
 //!     .with_title("Hello world!")
 //!     .with_dimensions(1024, 768);
 //! let context = glutin::ContextBuilder::new();
-//! let gl_window = glutin::GlWindow::new(window, context, &events_loop).unwrap();
+//! let gl_window = glutin::WindowedContext::new(window, context, &events_loop).unwrap();
 //! # }
 //! ```
 //!
+//! If you create your windows yourself, a `RawContext` can be built on top of the native
+//! window handle instead. See the `RawContextExt` traits in the `os` module.
+//!
 //! For contexts that are *not* associated with any particular window, see the HeadlessContext
-//! type.
+//! type. Both kinds of contexts can be built from the same `ContextBuilder`, through
+//! `build_windowed` and `build_headless`.
 //!
 //! # Features
 //!
-//! This crate has two Cargo features: `window` and `headless`.
-//!
-//!  - `window` allows you to create regular windows and enables the `WindowBuilder` object.
-//!  - `headless` allows you to do headless rendering, and enables
-//!     the `HeadlessRendererBuilder` object.
+//! This crate has one Cargo feature: `winit`, enabled by default.
 //!
-//! By default only `window` is enabled.
+//! It pulls in winit and enables everything that creates windows: `WindowedContext`,
+//! `ContextBuilder::build_windowed` and the re-exports of winit's types. Without it, contexts
+//! can still be built headless or on top of native window handles through `RawContext`.
 
-#[cfg(target_os = "windows")]
 #[macro_use]
 extern crate lazy_static;
 
@@ -41,6 +42,7 @@ extern crate shared_library;
 
 extern crate libc;
 
+#[cfg(feature = "winit")]
 extern crate winit;
 
 #[cfg(target_os = "windows")]
@@ -61,7 +63,26 @@ extern crate x11_dl;
 #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly", target_os = "openbsd"))]
 extern crate wayland_client;
 
+#[cfg(feature = "winit")]
+pub use capture::CapturedFrame;
+pub use config::Config;
+pub use context_flags::ContextFlags;
+pub use current_guard::CurrentContextGuard;
+pub use debug_output::{DebugFilter, DebugMessage, DebugSeverity, DebugSource, DebugType};
+pub use display::Display;
+pub use frame_pacer::FramePacer;
 pub use headless::{HeadlessRendererBuilder, HeadlessContext};
+pub use headless_pool::{HeadlessContextPool, PooledContext};
+pub use metrics::ContextMetrics;
+pub use raw_handle::{RawContextHandle, RawDisplayHandle, RawPixmapHandle, RawWindowHandle};
+pub use surface::{PbufferAttributes, PbufferLimits, PbufferSurface, PbufferTextureFormat};
+pub use surface::{PixmapSurface, Surface, SurfaceTypeTrait, WindowSurface};
+pub use raw_context::RawContext;
+pub use readback::{AsyncReadback, ReadbackFrame};
+pub use reset_status::ResetStatus;
+#[cfg(feature = "winit")]
+pub use windowed::{GlWindow, WindowedContext};
+#[cfg(feature = "winit")]
 pub use winit::{AvailableMonitorsIter, AxisId, ButtonId, ControlFlow,
                 CreationError as WindowCreationError, CursorState, DeviceEvent, DeviceId,
                 ElementState, Event, EventsLoop, EventsLoopClosed, EventsLoopProxy,
@@ -70,19 +91,59 @@ pub use winit::{AvailableMonitorsIter, AxisId, ButtonId, ControlFlow,
                 Touch, TouchPhase, VirtualKeyCode, Window, WindowAttributes, WindowBuilder,
                 WindowEvent, WindowId};
 
+use std::ffi::CStr;
 use std::io;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::{c_char, c_uint};
+use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use context_lost::ContextLostNotifier;
+use debug_output::{DebugCallback, DebugOutput};
+use frame_latency::FrameLatencyLimiter;
+use frame_limiter::FrameLimiter;
+use gl_version::VersionQuery;
+use metrics::MetricsRecorder;
+use reset_status::ResetStatusQuery;
 
 mod api;
 mod platform;
+#[cfg(feature = "winit")]
+mod capture;
+mod config;
+mod config_cache;
+mod context_flags;
+mod context_lost;
+mod current_guard;
+mod debug_output;
+mod display;
+mod frame_latency;
+mod frame_limiter;
+mod frame_pacer;
+mod gl_version;
 mod headless;
+mod headless_pool;
+mod metrics;
+mod raw_context;
+mod raw_handle;
+mod readback;
+mod reset_status;
+mod surface;
+#[cfg(feature = "winit")]
+mod windowed;
 
 pub mod os;
+#[cfg(feature = "mock")]
+pub mod mock;
 
-/// A trait for types associated with a GL context.
+/// A trait for types associated with a GL context that may be current on the calling thread.
+///
+/// This trait is only implemented for the `PossiblyCurrent` state of each context type. Use the
+/// inherent `make_current` method to obtain one.
 pub trait GlContext {
-    /// Sets the context as the current context.
-    unsafe fn make_current(&self) -> Result<(), ContextError>;
-
     /// Returns true if this context is the current one in this thread.
     fn is_current(&self) -> bool;
 
@@ -97,6 +158,10 @@ pub trait GlContext {
     /// **Warning**: if you enabled vsync, this function will block until the next time the screen
     /// is refreshed. However drivers can choose to override your vsync settings, which means that
     /// you can't know in advance whether `swap_buffers` will block or not.
+    ///
+    /// On Wayland, the swap waits until the compositor asks for a new frame. If it doesn't within
+    /// 100 milliseconds, for example because the window is hidden, the frame is dropped instead of
+    /// blocking until the window is shown again.
     fn swap_buffers(&self) -> Result<(), ContextError>;
 
     /// Returns the OpenGL API being used.
@@ -115,11 +180,60 @@ pub trait GlContext {
     fn resize(&self, width: u32, height: u32);
 }
 
+/// A trait implemented by every context type, whatever its current state.
+///
+/// Together with `GlContext`, which is implemented by the `PossiblyCurrent` state of each
+/// context type, this lets generic code handle windowed, raw and headless contexts the same way.
+///
+/// # Example
+///
+/// ```no_run
+/// # extern crate glutin;
+/// use glutin::{ContextTrait, GlContext};
+///
+/// unsafe fn load<C: ContextTrait>(context: C) -> C::PossiblyCurrent {
+///     let context = context.make_current().map_err(|(_, err)| err).unwrap();
+///     let _clear = context.get_proc_address("glClear");
+///     context
+/// }
+/// # fn main() {}
+/// ```
+pub trait ContextTrait: Sized {
+    /// This context type in the `PossiblyCurrent` state.
+    type PossiblyCurrent: GlContext;
+
+    /// This context type in the `NotCurrent` state.
+    type NotCurrent;
+
+    /// Sets the context as the current context on the calling thread.
+    ///
+    /// See `Context::make_current` for more infos.
+    unsafe fn make_current(self) -> Result<Self::PossiblyCurrent, (Self, ContextError)>;
+
+    /// If this context is current on the calling thread, releases it.
+    ///
+    /// See `Context::make_not_current` for more infos.
+    unsafe fn make_not_current(self) -> Result<Self::NotCurrent, (Self, ContextError)>;
+
+    /// Returns true if this context is the current one in this thread.
+    fn is_current(&self) -> bool;
+
+    /// Returns the OpenGL API being used.
+    fn get_api(&self) -> Api;
+
+    /// Returns the pixel format of the main framebuffer of the context.
+    fn get_pixel_format(&self) -> PixelFormat;
+}
+
 /// Represents an OpenGL context.
 ///
 /// A `Context` is normally associated with a single Window, however `Context`s can be *shared*
 /// between multiple windows.
 ///
+/// A `Context` does not own the window it draws to. See `WindowedContext` for a context that is
+/// bundled with its window, and `RawContext` for a context built on a window that glutin did not
+/// create.
+///
 /// # Example
 ///
 /// ```no_run
@@ -129,61 +243,63 @@ pub trait GlContext {
 /// # let events_loop = glutin::EventsLoop::new();
 /// # let window = glutin::WindowBuilder::new();
 /// # let context = glutin::ContextBuilder::new();
-/// # let some_gl_window = glutin::GlWindow::new(window, context, &events_loop).unwrap();
+/// # let some_gl_window = glutin::WindowedContext::new(window, context, &events_loop).unwrap();
 /// let context = glutin::ContextBuilder::new()
 ///     .with_vsync(true)
 ///     .with_multisampling(8)
 ///     .with_shared_lists(some_gl_window.context());
 /// # }
 /// ```
-pub struct Context {
+pub struct Context<T: ContextCurrentState> {
     context: platform::Context,
+    relaxed_requirements: Vec<RelaxedRequirement>,
+    // Shared by every context that shares objects with this one.
+    share_group: Arc<()>,
+    frame_limiter: FrameLimiter,
+    frame_latency: FrameLatencyLimiter,
+    metrics: MetricsRecorder,
+    debug_output: DebugOutput,
+    reset_status: ResetStatusQuery,
+    context_lost: ContextLostNotifier,
+    // The attributes the context was built with, used to recreate it. `None` for the contexts
+    // adopted from another library, whose attributes are unknown.
+    attributes: Option<GlAttributes<()>>,
+    version: VersionQuery,
+    phantom: PhantomData<T>,
 }
 
-/// Object that allows you to build `Context`s.
-pub struct ContextBuilder<'a> {
-    /// The attributes to use to create the context.
-    pub gl_attr: GlAttributes<&'a Context>,
-    // Should be made public once it's stabilized.
-    pf_reqs: PixelFormatRequirements,
-}
+/// A type that contexts use to keep track, at compile time, of whether they may be current on
+/// the calling thread.
+pub trait ContextCurrentState: std::fmt::Debug + Clone {}
 
-/// Represents an OpenGL context and a Window with which it is associated.
-///
-/// # Example
-///
-/// ```no_run
-/// # extern crate glutin;
-/// # use glutin::GlContext;
-/// # fn main() {
-/// let mut events_loop = glutin::EventsLoop::new();
-/// let window = glutin::WindowBuilder::new();
-/// let context = glutin::ContextBuilder::new();
-/// let gl_window = glutin::GlWindow::new(window, context, &events_loop).unwrap();
-///
-/// unsafe { gl_window.make_current().unwrap() };
+/// A context in this state may be current on the calling thread.
 ///
-/// loop {
-///     events_loop.poll_events(|event| {
-///         match event {
-///             // process events here
-///             _ => ()
-///         }
-///     });
-///
-///     // draw everything here
+/// Contexts in this state can't be sent to another thread. Functions that need the context to be
+/// current, such as `get_proc_address` or `swap_buffers`, are only available in this state.
+#[derive(Debug, Clone)]
+pub struct PossiblyCurrent {
+    phantom: PhantomData<*mut ()>,
+}
+
+impl ContextCurrentState for PossiblyCurrent {}
+
+/// A context in this state is not current on any thread.
 ///
-///     gl_window.swap_buffers();
-///     std::thread::sleep(std::time::Duration::from_millis(17));
-/// }
-/// # }
-/// ```
-pub struct GlWindow {
-    context: Context,
-    window: Window,
+/// Newly created contexts start in this state.
+#[derive(Debug, Clone)]
+pub enum NotCurrent {}
+
+impl ContextCurrentState for NotCurrent {}
+
+/// Object that allows you to build `Context`s.
+pub struct ContextBuilder<'a, T: ContextCurrentState = NotCurrent> {
+    /// The attributes to use to create the context.
+    pub gl_attr: GlAttributes<&'a Context<T>>,
+    // Should be made public once it's stabilized.
+    pub(crate) pf_reqs: PixelFormatRequirements,
 }
 
-impl<'a> ContextBuilder<'a> {
+impl<'a> ContextBuilder<'a, NotCurrent> {
     /// Initializes a new `ContextBuilder` with default values.
     pub fn new() -> Self {
         ContextBuilder {
@@ -191,6 +307,9 @@ impl<'a> ContextBuilder<'a> {
             gl_attr: std::default::Default::default(),
         }
     }
+}
+
+impl<'a, T: ContextCurrentState> ContextBuilder<'a, T> {
 
     /// Sets how the backend should choose the OpenGL API and version.
     #[inline]
@@ -223,6 +342,51 @@ impl<'a> ContextBuilder<'a> {
         self
     }
 
+    /// Sets whether the context is flushed when another context is made current or when it is
+    /// made not current.
+    ///
+    /// Renderers switching between several contexts can use `ReleaseBehavior::None` to avoid a
+    /// flush at every switch, in which case they must flush themselves when another context
+    /// depends on the commands of this one. This falls back to `ReleaseBehavior::Flush` without
+    /// `EGL_KHR_context_flush_control`, `GLX_ARB_context_flush_control` or
+    /// `WGL_ARB_context_flush_control`, and on the other backends.
+    ///
+    /// The default value is `ReleaseBehavior::Flush`.
+    #[inline]
+    pub fn with_release_behavior(mut self, behavior: ReleaseBehavior) -> Self {
+        self.gl_attr.release_behavior = behavior;
+        self
+    }
+
+    /// Requests a scheduling priority for the context, so that compositors and latency-critical
+    /// render threads can preempt the other work of the GPU.
+    ///
+    /// This is a hint. It is only supported by EGL with `EGL_IMG_context_priority`, and the
+    /// implementation may give the context another priority, for example when the process isn't
+    /// allowed to use high priorities.
+    #[inline]
+    pub fn with_context_priority(mut self, priority: ContextPriority) -> Self {
+        self.gl_attr.priority = Some(priority);
+        self
+    }
+
+    /// Sets a function receiving the messages of `glDebugMessageCallback` that pass `filter`,
+    /// and requests a debug context.
+    ///
+    /// The callback is installed the first time the context is made current, with
+    /// `GL_DEBUG_OUTPUT_SYNCHRONOUS` so that it's called on the thread that issued the faulty
+    /// command. Nothing is installed if the context supports neither OpenGL 4.3, `GL_KHR_debug`
+    /// nor `GL_ARB_debug_output`. The messages can be forwarded to a logging library from the
+    /// callback.
+    #[inline]
+    pub fn with_debug_callback<F>(mut self, filter: DebugFilter, callback: F) -> Self
+        where F: Fn(&DebugMessage) + Send + Sync + 'static
+    {
+        self.gl_attr.debug = true;
+        self.gl_attr.debug_callback = Some(DebugCallback(Arc::new(callback), filter));
+        self
+    }
+
     /// Requests that the window has vsync enabled.
     ///
     /// By default, vsync is not enabled.
@@ -232,10 +396,41 @@ impl<'a> ContextBuilder<'a> {
         self
     }
 
+    /// Forces the GLX extension used to set the swap interval, for drivers that misbehave with
+    /// some of them. Building the context fails if the extension isn't available. Ignored by the
+    /// other backends.
+    ///
+    /// By default, the first available extension is used, in the order of `GlxSwapControl`.
+    #[inline]
+    pub fn with_glx_swap_control(mut self, swap_control: GlxSwapControl) -> Self {
+        self.gl_attr.glx_swap_control = Some(swap_control);
+        self
+    }
+
     /// Share the display lists with the given `Context`.
     #[inline]
-    pub fn with_shared_lists(mut self, other: &'a Context) -> Self {
-        self.gl_attr.sharing = Some(other);
+    pub fn with_shared_lists<T2: ContextCurrentState>(self, other: &'a Context<T2>)
+        -> ContextBuilder<'a, T2>
+    {
+        ContextBuilder {
+            gl_attr: self.gl_attr.set_sharing(Some(other)),
+            pf_reqs: self.pf_reqs,
+        }
+    }
+
+    /// Share the display lists with a context created by another library, such as a GUI toolkit
+    /// or a media framework. Takes precedence over `with_shared_lists`.
+    ///
+    /// The handle must match the backend glutin uses for the new context, otherwise
+    /// `CreationError::NotSupported` is returned when building it. Sharing with a raw context is
+    /// not supported on macOS, nor by headless contexts that use OSMesa.
+    ///
+    /// # Unsafety
+    ///
+    /// The handle must be valid and remain alive until the new context is built.
+    #[inline]
+    pub unsafe fn with_shared_raw_context(mut self, context: RawContextHandle) -> Self {
+        self.gl_attr.raw_sharing = Some(context);
         self
     }
 
@@ -257,6 +452,21 @@ impl<'a> ContextBuilder<'a> {
         self
     }
 
+    /// Sets the number of coverage samples to request, for coverage-sample antialiasing (CSAA)
+    /// with `EGL_NV_coverage_sample`. A value of `0` means "don't care".
+    ///
+    /// CSAA configs usually have fewer color samples than coverage samples, so they are never
+    /// selected by `with_multisampling` alone. Only supported by EGL, where no config is found
+    /// without the extension.
+    #[inline]
+    pub fn with_coverage_samples(mut self, samples: u16) -> Self {
+        self.pf_reqs.coverage_samples = match samples {
+            0 => None,
+            _ => Some(samples),
+        };
+        self
+    }
+
     /// Sets the number of bits in the depth buffer.
     #[inline]
     pub fn with_depth_buffer(mut self, bits: u8) -> Self {
@@ -271,7 +481,40 @@ impl<'a> ContextBuilder<'a> {
         self
     }
 
+    /// Sets the number of bits that the depth buffer should preferably have, among the formats
+    /// with at least the number set with `with_depth_buffer`. For example
+    /// `with_depth_buffer(16).with_preferred_depth_buffer(24)` accepts 16 bits but picks 24
+    /// bits when available.
+    ///
+    /// Preferences are only applied by EGL, GLX and WGL with `WGL_ARB_pixel_format`, and are
+    /// ignored when a function is given to `with_config_selector`.
+    #[inline]
+    pub fn with_preferred_depth_buffer(mut self, bits: u8) -> Self {
+        self.pf_reqs.preferred_depth_bits = Some(bits);
+        self
+    }
+
+    /// Sets the number of bits that the stencil buffer should preferably have. See
+    /// `with_preferred_depth_buffer`.
+    #[inline]
+    pub fn with_preferred_stencil_buffer(mut self, bits: u8) -> Self {
+        self.pf_reqs.preferred_stencil_bits = Some(bits);
+        self
+    }
+
+    /// Sets the number of bits of alpha that the color buffer should preferably have. See
+    /// `with_preferred_depth_buffer`.
+    #[inline]
+    pub fn with_preferred_alpha_bits(mut self, bits: u8) -> Self {
+        self.pf_reqs.preferred_alpha_bits = Some(bits);
+        self
+    }
+
     /// Sets the number of bits in the color buffer.
+    ///
+    /// For 10 bits per channel on deep color displays, use `with_pixel_format(30, 2)`, since
+    /// these formats usually only have 2 bits of alpha. The bits actually obtained for each
+    /// channel are in the `red_bits`, `green_bits` and `blue_bits` of `get_pixel_format`.
     #[inline]
     pub fn with_pixel_format(mut self, color_bits: u8, alpha_bits: u8) -> Self {
         self.pf_reqs.color_bits = Some(color_bits);
@@ -279,6 +522,15 @@ impl<'a> ContextBuilder<'a> {
         self
     }
 
+    /// Sets the exact number of bits of the red, green and blue channels of the color buffer,
+    /// replacing the total given to `with_pixel_format`. For example `with_rgb_bits(5, 6, 5)`
+    /// requests an RGB565 surface, and `with_rgb_bits(8, 8, 8)` rejects 10-10-10 formats.
+    #[inline]
+    pub fn with_rgb_bits(mut self, red: u8, green: u8, blue: u8) -> Self {
+        self.pf_reqs.rgb_bits = Some((red, green, blue));
+        self
+    }
+
     /// Request the backend to be stereoscopic.
     #[inline]
     pub fn with_stereoscopy(mut self) -> Self {
@@ -286,154 +538,1176 @@ impl<'a> ContextBuilder<'a> {
         self
     }
 
-    /// Sets whether sRGB should be enabled on the window.
+    /// Sets whether sRGB should be enabled on the window. `true` requires an sRGB-capable
+    /// format and `false` doesn't care, like `with_srgb_requirement` with
+    /// `SrgbRequirement::Require` and `SrgbRequirement::DontCare`.
     ///
     /// The default value is `false`.
     #[inline]
-    pub fn with_srgb(mut self, srgb_enabled: bool) -> Self {
-        self.pf_reqs.srgb = srgb_enabled;
-        self
+    pub fn with_srgb(self, srgb_enabled: bool) -> Self {
+        self.with_srgb_requirement(if srgb_enabled { SrgbRequirement::Require }
+                                   else { SrgbRequirement::DontCare })
     }
-}
 
-impl GlWindow {
-    /// Builds the given window along with the associated GL context, returning the pair as a
-    /// `GlWindow`.
+    /// Sets whether the format must, should or must not be sRGB-capable. See `SrgbRequirement`.
     ///
-    /// Error should be very rare and only occur in case of permission denied, incompatible system,
-    /// out of memory, etc.
-    pub fn new(
-        window_builder: WindowBuilder,
-        context_builder: ContextBuilder,
-        events_loop: &EventsLoop,
-    ) -> Result<Self, CreationError>
-    {
-        let ContextBuilder { pf_reqs, gl_attr } = context_builder;
-        let gl_attr = gl_attr.map_sharing(|ctxt| &ctxt.context);
-        platform::Context::new(window_builder, events_loop, &pf_reqs, &gl_attr)
-            .map(|(window, context)| GlWindow {
-                window: window,
-                context: Context { context: context },
-            })
+    /// The default value is `SrgbRequirement::DontCare`.
+    #[inline]
+    pub fn with_srgb_requirement(mut self, srgb: SrgbRequirement) -> Self {
+        self.pf_reqs.srgb = srgb;
+        self
     }
 
-    /// Borrow the inner `Window`.
-    pub fn window(&self) -> &Window {
-        &self.window
+    /// Sets whether the format must, should or must not be hardware accelerated. With `Prefer`,
+    /// a software renderer is used when no hardware accelerated format is available, which can
+    /// be detected with `PixelFormat::is_hardware_accelerated`.
+    ///
+    /// The default value is `HardwareAcceleration::Require`.
+    #[inline]
+    pub fn with_hardware_acceleration(mut self, acceleration: HardwareAcceleration) -> Self {
+        self.pf_reqs.hardware_accelerated = acceleration;
+        self
     }
 
-    /// Borrow the inner GL `Context`.
-    pub fn context(&self) -> &Context {
-        &self.context
+    /// Sets whether the EGL config must be conformant to the requested API. See `Conformance`.
+    ///
+    /// Ignored by the backends other than EGL. The default value is `Conformance::Require`.
+    #[inline]
+    pub fn with_conformance(mut self, conformance: Conformance) -> Self {
+        self.pf_reqs.conformance = conformance;
+        self
     }
-}
 
-impl GlContext for Context {
-    unsafe fn make_current(&self) -> Result<(), ContextError> {
-        self.context.make_current()
+    /// Sets whether the EGL and GLX configs marked with `SLOW_CONFIG` or `NON_CONFORMANT_CONFIG`
+    /// as their `CONFIG_CAVEAT` are excluded, so that software-emulated formats are never
+    /// selected.
+    ///
+    /// Ignored by the other backends. The default value is `false`.
+    #[inline]
+    pub fn with_reject_caveats(mut self, reject: bool) -> Self {
+        self.pf_reqs.reject_caveats = reject;
+        self
     }
 
-    fn is_current(&self) -> bool {
-        self.context.is_current()
+    /// Sets the colorspace of the window surface, for example `Colorspace::DisplayP3` on wide
+    /// gamut displays. The colorspace that was obtained is returned by `Context::colorspace`.
+    ///
+    /// Only supported by EGL, where `Srgb` and `Linear` require EGL 1.5 or
+    /// `EGL_KHR_gl_colorspace`, and the others their `EGL_EXT_gl_colorspace_*` extension.
+    /// Building the context fails with `CreationError::NotSupported` otherwise. Use `with_srgb`
+    /// with the other backends.
+    ///
+    /// The default value is `None`, which leaves the choice to the implementation.
+    #[inline]
+    pub fn with_colorspace(mut self, colorspace: Option<Colorspace>) -> Self {
+        self.gl_attr.colorspace = colorspace;
+        self
     }
 
-    fn get_proc_address(&self, addr: &str) -> *const () {
-        self.context.get_proc_address(addr)
+    /// Sets whether the context is created without error checking, with `GL_KHR_no_error`, so
+    /// that the driver skips its validation. Errors then cause undefined behavior instead of
+    /// being reported, so this is meant for shipping builds that are known to be correct.
+    ///
+    /// Unlike `Robustness::NoError`, building the context fails with
+    /// `CreationError::NoErrorNotSupported` if the backend doesn't support it, which requires
+    /// `EGL_KHR_create_context_no_error`, `GLX_ARB_create_context_no_error` or
+    /// `WGL_ARB_create_context_no_error`. Since the extension forbids no-error contexts that are
+    /// debug or robust contexts, it also fails with `CreationError::NotSupported` unless the
+    /// debug flag is disabled with `with_gl_debug_flag(false)` and the robustness is `NotRobust`.
+    ///
+    /// The default value is `false`.
+    #[inline]
+    pub fn with_gl_no_error(mut self, no_error: bool) -> Self {
+        self.gl_attr.no_error = no_error;
+        self
     }
 
-    fn swap_buffers(&self) -> Result<(), ContextError> {
-        self.context.swap_buffers()
+    /// Sets whether the context and its surface are protected, for the playback of
+    /// DRM-protected video decoded into secure memory. The content of protected surfaces can't
+    /// be read back or copied to unprotected ones.
+    ///
+    /// Only supported by EGL with `EGL_EXT_protected_content`. Building the context fails with
+    /// `CreationError::ProtectedContentNotSupported` with the other backends, without the
+    /// extension, or if the implementation can't allocate secure memory.
+    ///
+    /// The default value is `false`.
+    #[inline]
+    pub fn with_protected_content(mut self, protected_content: bool) -> Self {
+        self.gl_attr.protected_content = protected_content;
+        self
     }
 
-    fn get_api(&self) -> Api {
-        self.context.get_api()
+    /// Sets whether the content of the back buffer should be preserved when the buffers are
+    /// swapped, so that only the regions that changed need to be redrawn.
+    ///
+    /// Only supported by EGL, where `SwapBehavior::Preserved` requires a config with
+    /// `EGL_SWAP_BEHAVIOR_PRESERVED_BIT`. Use `Context::swap_behavior` to check whether the
+    /// request was honored.
+    ///
+    /// The default value is `SwapBehavior::Destroyed`.
+    #[inline]
+    pub fn with_swap_behavior(mut self, swap_behavior: SwapBehavior) -> Self {
+        self.pf_reqs.swap_behavior = swap_behavior;
+        self
     }
 
-    fn get_pixel_format(&self) -> PixelFormat {
-        self.context.get_pixel_format()
+    /// Sets whether the pixel format must be double-buffered. `Some(false)` requests a
+    /// single-buffered format, where rendering goes directly to the front buffer. `None` means
+    /// "don't care".
+    ///
+    /// With EGL, `Some(false)` creates the window surface with `EGL_SINGLE_BUFFER`, which the
+    /// implementation may ignore. Use `Context::render_buffer` to check whether the request was
+    /// honored.
+    ///
+    /// The default value is `None`.
+    #[inline]
+    pub fn with_double_buffer(mut self, double_buffer: Option<bool>) -> Self {
+        self.pf_reqs.double_buffer = double_buffer;
+        self
     }
 
-    fn resize(&self, width: u32, height: u32) {
-        self.context.resize(width, height);
+    /// Sets whether the window surface must be able to switch between rendering to the back
+    /// buffer and to the front buffer at runtime through `Context::set_render_buffer`.
+    ///
+    /// Only supported by EGL, where it requires a config with
+    /// `EGL_MUTABLE_RENDER_BUFFER_BIT_KHR`.
+    ///
+    /// The default value is `false`.
+    #[inline]
+    pub fn with_mutable_render_buffer(mut self, mutable_render_buffer: bool) -> Self {
+        self.pf_reqs.mutable_render_buffer = mutable_render_buffer;
+        self
     }
-}
 
-impl GlContext for GlWindow {
-    unsafe fn make_current(&self) -> Result<(), ContextError> {
-        self.context.make_current()
+    /// Sets whether the window surface must be able to resolve its samples with a box filter,
+    /// selected at runtime through `Context::set_multisample_resolve`.
+    ///
+    /// Only supported by EGL, where it requires a config with `EGL_MULTISAMPLE_RESOLVE_BOX_BIT`.
+    ///
+    /// The default value is `false`.
+    #[inline]
+    pub fn with_multisample_resolve_box(mut self, multisample_resolve_box: bool) -> Self {
+        self.pf_reqs.multisample_resolve_box = multisample_resolve_box;
+        self
     }
 
-    fn is_current(&self) -> bool {
-        self.context.is_current()
+    /// Sets whether the config must have `EGL_RECORDABLE_ANDROID`, so that the frames rendered to
+    /// the surface can be encoded by `MediaCodec` or shown on a virtual display.
+    ///
+    /// Only supported by EGL with `EGL_ANDROID_recordable`, otherwise no pixel format is found.
+    ///
+    /// The default value is `false`.
+    #[inline]
+    pub fn with_recordable(mut self, recordable: bool) -> Self {
+        self.pf_reqs.recordable = recordable;
+        self
     }
 
-    fn get_proc_address(&self, addr: &str) -> *const () {
-        self.context.get_proc_address(addr)
+    /// Sets whether the window should be transparent where the alpha of the rendered pixels is
+    /// lower than 1, for example for translucent overlays. The pixels must be premultiplied by
+    /// their alpha.
+    ///
+    /// This makes the window transparent like `WindowBuilder::with_transparency`, and requires at
+    /// least 8 bits of alpha. On X11, GLX then chooses a 32 bits ARGB visual. On Wayland, the
+    /// compositor uses the alpha of the EGL surface. On Windows, the window is composited by DWM
+    /// with blur-behind, and on macOS the surface opacity of the context is set to 0. It is
+    /// ignored by contexts built on existing windows.
+    ///
+    /// The default value is `false`.
+    #[inline]
+    pub fn with_transparency(mut self, transparent: bool) -> Self {
+        self.pf_reqs.transparent = transparent;
+        self
     }
 
-    fn swap_buffers(&self) -> Result<(), ContextError> {
-        self.context.swap_buffers()
+    /// Appends a native attribute to the list used to choose the pixel format, for vendor
+    /// extensions that glutin doesn't wrap.
+    ///
+    /// The attribute is passed as-is to `eglChooseConfig`, `glXChooseFBConfig` or
+    /// `wglChoosePixelFormatARB`. On macOS, `attrib` and `value` are both appended to the
+    /// `NSOpenGLPixelFormat` attributes, so only attributes that take a value can be used.
+    /// Attributes that glutin sets itself may be overridden or rejected by the driver.
+    #[inline]
+    pub fn with_raw_pixel_format_attribute(mut self, attrib: i32, value: i32) -> Self {
+        self.pf_reqs.raw_attributes.push((attrib, value));
+        self
     }
 
-    fn get_api(&self) -> Api {
-        self.context.get_api()
+    /// Appends a native attribute to the list used to create the context, for vendor extensions
+    /// that glutin doesn't wrap.
+    ///
+    /// The attribute is passed as-is to `eglCreateContext`, `glXCreateContextAttribsARB` or
+    /// `wglCreateContextAttribsARB`. It is ignored on macOS, and when the driver doesn't support
+    /// creating contexts with attributes.
+    #[inline]
+    pub fn with_raw_context_attribute(mut self, attrib: i32, value: i32) -> Self {
+        self.gl_attr.raw_context_attributes.push((attrib, value));
+        self
     }
 
-    fn get_pixel_format(&self) -> PixelFormat {
-        self.context.get_pixel_format()
+    /// Appends a native attribute to the list used to create the surface of the context, for
+    /// vendor extensions that glutin doesn't wrap.
+    ///
+    /// The attribute is passed as-is to `eglCreateWindowSurface` or `eglCreatePbufferSurface`.
+    /// It is ignored by the other backends, whose surfaces are created without attributes.
+    #[inline]
+    pub fn with_raw_surface_attribute(mut self, attrib: i32, value: i32) -> Self {
+        self.gl_attr.raw_surface_attributes.push((attrib, value));
+        self
     }
 
-    fn resize(&self, width: u32, height: u32) {
-        self.context.resize(width, height);
+    /// Sets whether the pixel format requirements may be relaxed when no format satisfies them,
+    /// instead of failing with `CreationError::NoMatchingPixelFormat`.
+    ///
+    /// When enabled, the multisampling is lowered step by step down to none, then sRGB is no
+    /// longer required, until a context can be created. The requirements that had to be relaxed
+    /// are reported by `Context::relaxed_requirements`.
+    ///
+    /// The default value is `false`.
+    #[inline]
+    pub fn with_fallback(mut self, fallback: bool) -> Self {
+        self.pf_reqs.fallback = fallback;
+        self
     }
-}
 
-impl std::ops::Deref for GlWindow {
-    type Target = Window;
-    fn deref(&self) -> &Self::Target {
-        &self.window
+    /// Sets how to choose between several pixel formats that match the other requirements.
+    ///
+    /// The policy is ignored on the same platforms as `with_config_selector`, and when a config
+    /// selector is set.
+    ///
+    /// The default value is `RankingPolicy::Strict`.
+    #[inline]
+    pub fn with_ranking_policy(mut self, ranking_policy: RankingPolicy) -> Self {
+        self.pf_reqs.ranking_policy = ranking_policy;
+        self
     }
-}
-
-/// Error that can happen while creating a window or a headless renderer.
-#[derive(Debug)]
-pub enum CreationError {
-    OsError(String),
-    /// TODO: remove this error
-    NotSupported(&'static str),
-    NoBackendAvailable(Box<std::error::Error + Send>),
-    RobustnessNotSupported,
-    OpenGlVersionNotSupported,
-    NoAvailablePixelFormat,
-    PlatformSpecific(String),
-    Window(WindowCreationError),
-}
 
-impl CreationError {
-    fn to_string(&self) -> &str {
-        match *self {
-            CreationError::OsError(ref text) => &text,
-            CreationError::NotSupported(text) => &text,
-            CreationError::NotSupported(_) => "Some of the requested attributes are not supported",
-            CreationError::NoBackendAvailable(_) => "No backend is available",
-            CreationError::RobustnessNotSupported => "You requested robustness, but it is \
-                                                      not supported.",
-            CreationError::OpenGlVersionNotSupported => "The requested OpenGL version is not \
-                                                         supported.",
-            CreationError::NoAvailablePixelFormat => "Couldn't find any pixel format that matches \
-                                                      the criterias.",
-            CreationError::PlatformSpecific(ref text) => &text,
-            CreationError::Window(ref err) => std::error::Error::description(err),
-        }
+    /// Sets a function that picks the pixel format to use among all the ones that match the
+    /// other requirements, overriding the built-in heuristic.
+    ///
+    /// The function receives the matching formats in the order of preference of the driver, and
+    /// returns the index of the one to use. If it returns an index that is out of bounds,
+    /// `CreationError::NoAvailablePixelFormat` is returned.
+    ///
+    /// The function is ignored on macOS, iOS and emscripten, by OSMesa, and by WGL drivers without
+    /// `WGL_ARB_pixel_format`, where the matching formats aren't enumerated.
+    #[inline]
+    pub fn with_config_selector<F>(mut self, selector: F) -> Self
+        where F: Fn(&[PixelFormat]) -> usize + Send + Sync + 'static
+    {
+        self.pf_reqs.config_selector = Some(ConfigSelector(Arc::new(selector)));
+        self
     }
-}
 
-impl std::fmt::Display for CreationError {
+    /// Sets a function receiving a description of every pixel format considered while creating
+    /// the context, of why each of them was rejected or chosen, and of the fallbacks tried.
+    ///
+    /// The same diagnostics are printed to the standard error if the `GLUTIN_DEBUG_CONFIGS`
+    /// environment variable is set to `1`, which is useful to investigate which format glutin
+    /// picks on a machine without rebuilding the application.
+    ///
+    /// Enabling the diagnostics makes all the matching formats be enumerated, which is slower.
+    /// They aren't available on the same platforms as `with_config_selector`.
+    #[inline]
+    pub fn with_config_diagnostics<F>(mut self, diagnostics: F) -> Self
+        where F: Fn(&str) + Send + Sync + 'static
+    {
+        self.pf_reqs.config_diagnostics = Some(ConfigDiagnostics(Arc::new(diagnostics)));
+        self
+    }
+
+    /// Builds the given window along with the associated GL context, returning the pair as a
+    /// `WindowedContext`.
+    ///
+    /// This is equivalent to `WindowedContext::new`.
+    #[cfg(feature = "winit")]
+    #[inline]
+    pub fn build_windowed(
+        self,
+        window_builder: WindowBuilder,
+        events_loop: &EventsLoop,
+    ) -> Result<WindowedContext<NotCurrent>, CreationError>
+    {
+        WindowedContext::new(window_builder, self, events_loop)
+    }
+
+    /// Builds a headless context whose default framebuffer has the given dimensions.
+    ///
+    /// If `with_shared_lists` was called, the headless context is built on the display and with
+    /// the config of the shared context, so that it is in the same share group and can for
+    /// example upload textures from a background thread. The other attributes of the builder
+    /// are ignored in that case. This is only supported on Unix-like platforms, elsewhere
+    /// `CreationError::NotSupported` is returned.
+    ///
+    /// Error should be very rare and only occur in case of permission denied, incompatible system,
+    /// out of memory, etc.
+    pub fn build_headless(
+        self,
+        dimensions: (u32, u32),
+    ) -> Result<HeadlessContext<NotCurrent>, CreationError>
+    {
+        let ContextBuilder { pf_reqs, gl_attr } = self;
+        if gl_attr.sharing.is_some() {
+            return Self::build_shared_headless(gl_attr, dimensions, false);
+        }
+        let gl_attr = gl_attr.set_sharing(None);
+        create_with_versions(&gl_attr, |gl_attr| {
+            create_with_fallbacks(&pf_reqs, |pf_reqs| {
+                platform::HeadlessContext::new(dimensions, pf_reqs, gl_attr, &Default::default())
+            }).map(|(context, relaxed)| HeadlessContext {
+                context: context,
+                dimensions: Some(dimensions),
+                relaxed_requirements: relaxed,
+                share_group: Arc::new(()),
+                debug_output: DebugOutput::new(gl_attr.debug_callback.clone()),
+                phantom: PhantomData,
+            })
+        })
+    }
+
+    /// Builds a headless context without any surface, which can only render to framebuffer
+    /// objects.
+    ///
+    /// See `HeadlessRendererBuilder::with_surfaceless` for the requirements. Lists can be shared
+    /// with a windowed context as with `build_headless`. In that case, the context has a 1x1
+    /// pbuffer if the display doesn't support surfaceless contexts or uses GLX.
+    pub fn build_surfaceless(self) -> Result<HeadlessContext<NotCurrent>, CreationError> {
+        let ContextBuilder { pf_reqs, gl_attr } = self;
+        if gl_attr.sharing.is_some() {
+            return Self::build_shared_headless(gl_attr, (1, 1), true);
+        }
+        let gl_attr = gl_attr.set_sharing(None);
+        create_with_versions(&gl_attr, |gl_attr| {
+            create_with_fallbacks(&pf_reqs, |pf_reqs| {
+                platform::HeadlessContext::new_surfaceless(pf_reqs, gl_attr, &Default::default())
+            }).map(|(context, relaxed)| HeadlessContext {
+                context: context,
+                dimensions: None,
+                relaxed_requirements: relaxed,
+                share_group: Arc::new(()),
+                debug_output: DebugOutput::new(gl_attr.debug_callback.clone()),
+                phantom: PhantomData,
+            })
+        })
+    }
+
+    /// Builds a headless context in the share group of the context passed to
+    /// `with_shared_lists`, using its display and its config.
+    fn build_shared_headless(gl_attr: GlAttributes<&'a Context<T>>, dimensions: (u32, u32),
+                             surfaceless: bool)
+                             -> Result<HeadlessContext<NotCurrent>, CreationError>
+    {
+        let share_group = gl_attr.share_group();
+        let gl_attr = gl_attr.map_sharing(|ctxt| &ctxt.context);
+        create_with_versions(&gl_attr, |gl_attr| {
+            platform::HeadlessContext::new_shared(dimensions, surfaceless, gl_attr)
+                .map(|context| HeadlessContext {
+                    context: context,
+                    dimensions: if surfaceless { None } else { Some(dimensions) },
+                    relaxed_requirements: Vec::new(),
+                    share_group: share_group.clone(),
+                    debug_output: DebugOutput::new(gl_attr.debug_callback.clone()),
+                    phantom: PhantomData,
+                })
+        })
+    }
+}
+
+impl Context<NotCurrent> {
+    /// Wraps a context of the platform, built with `gl_attr`.
+    pub(crate) fn from_platform<S: Clone>(
+        context: platform::Context,
+        relaxed_requirements: Vec<RelaxedRequirement>,
+        share_group: Arc<()>,
+        gl_attr: &GlAttributes<S>,
+    ) -> Self
+    {
+        Context {
+            context: context,
+            relaxed_requirements: relaxed_requirements,
+            share_group: share_group,
+            frame_limiter: FrameLimiter::new(),
+            frame_latency: FrameLatencyLimiter::new(),
+            metrics: MetricsRecorder::new(),
+            debug_output: DebugOutput::new(gl_attr.debug_callback.clone()),
+            reset_status: ResetStatusQuery::new(gl_attr.robustness),
+            context_lost: ContextLostNotifier::new(),
+            attributes: Some(gl_attr.clone().set_sharing(None)),
+            version: VersionQuery::new(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: ContextCurrentState> Context<T> {
+    /// Sets the context as the current context on the calling thread.
+    ///
+    /// On success, returns the context in the `PossiblyCurrent` state. On failure, the context is
+    /// handed back unchanged along with the error. If the context reports resets and the GPU was
+    /// reset, the context is released again and `ContextError::ContextLost` is returned; see
+    /// `reset_status`.
+    ///
+    /// # Unsafety
+    ///
+    /// Making a context current on one thread while it is already current on another one is
+    /// undefined behavior.
+    pub unsafe fn make_current(self) -> Result<Context<PossiblyCurrent>, (Self, ContextError)> {
+        match self.context.make_current() {
+            Ok(()) => {
+                self.metrics.on_make_current();
+                self.debug_output.install(|addr| self.context.get_proc_address(addr));
+                self.version.read(|addr| self.context.get_proc_address(addr));
+                if let Err(err) = self.check_reset() {
+                    // the context is handed back in its previous state, which may be `NotCurrent`
+                    let _ = self.context.make_not_current();
+                    return Err((self, err));
+                }
+                Ok(self.into_state())
+            },
+            Err(err) => {
+                self.context_lost.notify(&err);
+                Err((self, err))
+            },
+        }
+    }
+
+    /// If this context is current on the calling thread, releases it so that the thread no
+    /// longer has a current context.
+    ///
+    /// On success, returns the context in the `NotCurrent` state, after which it may be sent to
+    /// and made current on another thread. On failure, the context is handed back unchanged
+    /// along with the error.
+    pub unsafe fn make_not_current(self) -> Result<Context<NotCurrent>, (Self, ContextError)> {
+        // the fences of `set_max_frame_latency` can only be deleted while the context is current
+        if self.context.is_current() {
+            self.frame_latency.delete_fences();
+        }
+        match self.context.make_not_current() {
+            Ok(()) => Ok(self.into_state()),
+            Err(err) => {
+                self.context_lost.notify(&err);
+                Err((self, err))
+            },
+        }
+    }
+
+    /// Changes the state of the context to `PossiblyCurrent` without making it current.
+    ///
+    /// This is for when the context was made current on the calling thread behind glutin's back,
+    /// for example by foreign code that glutin calls into, so that the functions that need it to
+    /// be current can be used.
+    ///
+    /// # Unsafety
+    ///
+    /// The context must actually be current on the calling thread, and must not be current on
+    /// any other thread.
+    pub unsafe fn treat_as_current(self) -> Context<PossiblyCurrent> {
+        self.into_state()
+    }
+
+    /// Changes the state of the context to `NotCurrent` without releasing it.
+    ///
+    /// This is for when the context was released behind glutin's back, so that it can be sent
+    /// to another thread.
+    ///
+    /// # Unsafety
+    ///
+    /// The context must not be current on any thread.
+    pub unsafe fn treat_as_not_current(self) -> Context<NotCurrent> {
+        self.into_state()
+    }
+
+    /// Sets the context as the current context on the calling thread until the returned guard is
+    /// dropped, at which point the context that was current before is made current again.
+    ///
+    /// This lets library code briefly use its own context inside an application that manages
+    /// which of its contexts is current. The context itself stays in the same state; functions
+    /// that need it to be current, such as `get_proc_address`, are available on the guard.
+    ///
+    /// # Unsafety
+    ///
+    /// Making a context current on one thread while it is already current on another one is
+    /// undefined behavior.
+    pub unsafe fn make_current_scoped(&self) -> Result<CurrentContextGuard, ContextError> {
+        let guard = self.context_lost.watch(CurrentContextGuard::make_current(self))?;
+        self.metrics.on_make_current();
+        self.debug_output.install(|addr| self.context.get_proc_address(addr));
+        self.version.read(|addr| self.context.get_proc_address(addr));
+        self.check_reset()?;
+        Ok(guard)
+    }
+
+    /// Returns true if an operation on the context failed with `ContextError::ContextLost`, in
+    /// which case the context and all its objects must be recreated.
+    ///
+    /// The loss is detected when making the context current, swapping its buffers or calling
+    /// `validate`: with `EGL_CONTEXT_LOST`, with the reset status of robust contexts (see
+    /// `reset_status`), which also reports the GPU timeouts of Windows, or when the WebGL canvas
+    /// was lost.
+    pub fn is_context_lost(&self) -> bool {
+        self.context_lost.is_lost()
+    }
+
+    /// Checks that the native context and the window or buffer it draws to are still valid,
+    /// without making the context current, so that render loops can detect problems before
+    /// `swap_buffers` fails or crashes.
+    ///
+    /// This is cheap enough to be called every frame. EGL checks both the context and its
+    /// surface, GLX only the context, and WGL only the window. `ContextError::ContextLost` is
+    /// returned if the context was lost, which `is_context_lost` then reports.
+    pub fn validate(&self) -> Result<(), ContextError> {
+        self.context_lost.watch(self.context.validate())
+    }
+
+    /// Sets a function called once, on the thread performing the operation, the first time the
+    /// context is found to be lost. See `is_context_lost`.
+    ///
+    /// This gives a single place to start recovering, whichever backend and operation detected
+    /// the loss. The function replaces the one set previously, if any.
+    ///
+    /// The loss is only detected by the operations of glutin, never asynchronously. With
+    /// emscripten, the `webglcontextlost` event isn't listened to: whether the canvas was lost
+    /// is polled by `make_current`, `swap_buffers` and `validate` instead.
+    pub fn set_context_lost_callback<F>(&self, callback: F)
+        where F: Fn() + Send + Sync + 'static
+    {
+        self.context_lost.set_callback(Some(Box::new(callback)));
+    }
+
+    /// Destroys the context and builds a new one with the same attributes and config, drawing
+    /// to the same window and sharing lists with `shared` if given, along with its share group.
+    /// The frame rate limit, the metrics and the context-lost callback are kept.
+    pub(crate) unsafe fn recreate(self, shared: Option<(&platform::Context, &Arc<()>)>)
+                                  -> Result<Context<NotCurrent>, CreationError>
+    {
+        let attributes = match self.attributes {
+            Some(ref attributes) => attributes.clone(),
+            None => {
+                return Err(CreationError::NotSupported(
+                    "contexts adopted from another library can't be recreated"));
+            },
+        };
+        if self.context.is_current() {
+            self.frame_latency.delete_fences();
+        }
+        // `Context` implements `Drop`, so its fields can only be moved out by reading them
+        let this = mem::ManuallyDrop::new(self);
+        let context = ptr::read(&this.context);
+        let relaxed_requirements = ptr::read(&this.relaxed_requirements);
+        let frame_limiter = ptr::read(&this.frame_limiter);
+        let metrics = ptr::read(&this.metrics);
+        let context_lost = ptr::read(&this.context_lost);
+        // the old context may call the debug callback until it is destroyed
+        let debug_output = ptr::read(&this.debug_output);
+        // the other fields are replaced
+        drop((ptr::read(&this.share_group), ptr::read(&this.frame_latency),
+              ptr::read(&this.reset_status), ptr::read(&this.attributes),
+              ptr::read(&this.version)));
+
+        // the raw context that lists were shared with may be gone, like the other contexts of
+        // the group unless `shared` is still alive
+        let mut gl_attr = attributes.clone().set_sharing(shared.map(|(context, _)| context));
+        gl_attr.raw_sharing = None;
+        let context = context.recreate(&gl_attr);
+        drop(debug_output);
+        let context = context?;
+        context_lost.reset();
+        Ok(Context {
+            context: context,
+            relaxed_requirements: relaxed_requirements,
+            share_group: shared.map_or_else(|| Arc::new(()), |(_, group)| group.clone()),
+            frame_limiter: frame_limiter,
+            frame_latency: FrameLatencyLimiter::new(),
+            metrics: metrics,
+            debug_output: DebugOutput::new(attributes.debug_callback.clone()),
+            reset_status: ResetStatusQuery::new(attributes.robustness),
+            context_lost: context_lost,
+            attributes: Some(attributes),
+            version: VersionQuery::new(),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Moves the context to the state `U`, which must be the one it is actually in.
+    unsafe fn into_state<U: ContextCurrentState>(self) -> Context<U> {
+        // `Context` implements `Drop`, so its fields can only be moved out by reading them
+        let this = mem::ManuallyDrop::new(self);
+        Context {
+            context: ptr::read(&this.context),
+            relaxed_requirements: ptr::read(&this.relaxed_requirements),
+            share_group: ptr::read(&this.share_group),
+            frame_limiter: ptr::read(&this.frame_limiter),
+            frame_latency: ptr::read(&this.frame_latency),
+            metrics: ptr::read(&this.metrics),
+            debug_output: ptr::read(&this.debug_output),
+            reset_status: ptr::read(&this.reset_status),
+            context_lost: ptr::read(&this.context_lost),
+            attributes: ptr::read(&this.attributes),
+            version: ptr::read(&this.version),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns `ContextError::ContextLost` if the context was created with a robustness that
+    /// reports resets and the GPU was reset. The context must be current.
+    fn check_reset(&self) -> Result<(), ContextError> {
+        let result = unsafe { self.reset_status.check(|addr| self.context.get_proc_address(addr)) };
+        self.context_lost.watch(result)
+    }
+
+    /// Sets the context as the current context on the calling thread, drawing to `draw` and
+    /// reading from `read` instead of the window or buffer it was created for.
+    ///
+    /// Both surfaces must have been created from a context with a compatible config. On failure,
+    /// the context is handed back unchanged along with the error.
+    ///
+    /// # Unsafety
+    ///
+    /// Making a context current on one thread while it is already current on another one is
+    /// undefined behavior. The surfaces must outlive the time during which the context is current
+    /// on them.
+    pub unsafe fn make_current_surfaces<D: SurfaceTypeTrait, R: SurfaceTypeTrait>(
+        self,
+        draw: &Surface<D>,
+        read: &Surface<R>,
+    ) -> Result<Context<PossiblyCurrent>, (Self, ContextError)>
+    {
+        match self.context.make_current_surfaces(&draw.surface, &read.surface) {
+            Ok(()) => {
+                self.metrics.on_make_current();
+                self.debug_output.install(|addr| self.context.get_proc_address(addr));
+                self.version.read(|addr| self.context.get_proc_address(addr));
+                if let Err(err) = self.check_reset() {
+                    // the context is handed back in its previous state, which may be `NotCurrent`
+                    let _ = self.context.make_not_current();
+                    return Err((self, err));
+                }
+                Ok(self.into_state())
+            },
+            Err(err) => {
+                self.context_lost.notify(&err);
+                Err((self, err))
+            },
+        }
+    }
+
+    /// Same as `make_current_surfaces`, drawing to and reading from the same surface.
+    #[inline]
+    pub unsafe fn make_current_surface<S: SurfaceTypeTrait>(self, surface: &Surface<S>)
+        -> Result<Context<PossiblyCurrent>, (Self, ContextError)>
+    {
+        self.make_current_surfaces(surface, surface)
+    }
+
+    /// Returns true if this context is the current one in this thread.
+    pub fn is_current(&self) -> bool {
+        self.context.is_current()
+    }
+
+    /// Returns the OpenGL API being used.
+    pub fn get_api(&self) -> Api {
+        self.context.get_api()
+    }
+
+    /// Returns the pixel format of the main framebuffer of the context.
+    ///
+    /// If the context is current, `hardware_accelerated` is also false when the `GL_RENDERER`
+    /// string names a software renderer.
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        let mut pixel_format = self.context.get_pixel_format();
+        if pixel_format.hardware_accelerated && self.context.is_current() {
+            let context = &self.context;
+            let renderer = unsafe { current_renderer(|addr| context.get_proc_address(addr)) };
+            if let Some(renderer) = renderer {
+                pixel_format.hardware_accelerated = !is_software_renderer(&renderer);
+            }
+        }
+        pixel_format
+    }
+
+    /// Returns the requirements that were relaxed to create the context, in the order they were
+    /// relaxed. Always empty unless `ContextBuilder::with_fallback` was enabled.
+    pub fn relaxed_requirements(&self) -> &[RelaxedRequirement] {
+        &self.relaxed_requirements
+    }
+
+    /// Returns true if this context and `other` share objects, either because one was built with
+    /// `with_shared_lists` on the other or because both share objects with the same context.
+    ///
+    /// Sharing set up with `with_shared_raw_context` is not tracked.
+    pub fn is_shared_with<T2: ContextCurrentState>(&self, other: &Context<T2>) -> bool {
+        Arc::ptr_eq(&self.share_group, &other.share_group)
+    }
+
+    /// Returns the largest pbuffer that can be created with the config of this context, for
+    /// example with `Surface::<PbufferSurface>::new`, so that large offscreen renders can be split
+    /// into tiles instead of failing.
+    ///
+    /// Returns `ContextError::FunctionUnavailable` if the config doesn't support pbuffers, with
+    /// WGL if `WGL_ARB_pbuffer` isn't supported, and on macOS and emscripten.
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        self.context.max_pbuffer_size()
+    }
+
+    /// Limits how many times per second `swap_buffers` swaps the buffers, by sleeping before the
+    /// swap when the previous one was too recent. `None` removes the limit, which is the
+    /// default.
+    ///
+    /// This is meant for when vsync is disabled, to avoid rendering thousands of frames per
+    /// second. The sleeps use the high-resolution timers of the platform, and the last
+    /// millisecond is spent yielding the thread to stay precise.
+    pub fn set_frame_rate_limit(&self, limit: Option<f32>) {
+        self.frame_limiter.set_frame_rate_limit(limit);
+    }
+
+    /// Starts or stops recording the metrics returned by `metrics`. Recording is disabled by
+    /// default, and stopping it discards the metrics recorded so far.
+    ///
+    /// When enabled, the clock is read around every swap, which has a small cost.
+    pub fn set_metrics_enabled(&self, enabled: bool) {
+        self.metrics.set_enabled(enabled);
+    }
+
+    /// Returns statistics about the swaps and `make_current` calls made since the metrics were
+    /// enabled or last reset, or `None` if they are disabled.
+    ///
+    /// The swap durations don't include the sleeps of the frame rate limit.
+    pub fn metrics(&self) -> Option<ContextMetrics> {
+        self.metrics.metrics()
+    }
+
+    /// Clears the metrics recorded so far, if they are enabled.
+    pub fn reset_metrics(&self) {
+        self.metrics.reset();
+    }
+}
+
+impl Context<PossiblyCurrent> {
+    /// Limits how many frames the driver may queue ahead of the GPU to `frames`, by inserting a
+    /// fence after every swap and waiting for the fence of the oldest frame once the limit is
+    /// reached. `None` removes the limit, which is the default.
+    ///
+    /// Drivers usually let the application submit a few frames ahead, which keeps the GPU busy
+    /// but adds input latency. A limit of 1 waits for each frame to be rendered before
+    /// returning from the next swap. Values below 1 are treated as 1.
+    ///
+    /// The fences are GL sync objects, so this requires OpenGL 3.2, `GL_ARB_sync` or OpenGL ES
+    /// 3.0, otherwise `ContextError::FunctionUnavailable` is returned.
+    pub fn set_max_frame_latency(&self, frames: Option<u32>) -> Result<(), ContextError> {
+        let context = &self.context;
+        self.frame_latency.set_max_frame_latency(frames, |addr| context.get_proc_address(addr))
+    }
+
+    /// Sets how many screen refreshes to wait for before swapping the buffers, overriding the
+    /// value chosen by `ContextBuilder::with_vsync`. `SwapInterval::Wait(0)` disables vsync.
+    ///
+    /// Applies to the window or surface the context currently draws to. Returns
+    /// `ContextError::FunctionUnavailable` if the platform doesn't support changing the swap
+    /// interval, or doesn't support `SwapInterval::Adaptive`. As with `with_vsync`, drivers may
+    /// override the value.
+    pub fn set_swap_interval(&self, interval: SwapInterval) -> Result<(), ContextError> {
+        self.context.set_swap_interval(interval)
+    }
+
+    /// Returns the swap interval of the window or surface the context currently draws to.
+    ///
+    /// On EGL, which can't query the interval, this is the last value passed to
+    /// `set_swap_interval`, or `SwapInterval::Wait(1)` by default. Returns
+    /// `ContextError::FunctionUnavailable` if the platform can't report the interval.
+    pub fn get_swap_interval(&self) -> Result<SwapInterval, ContextError> {
+        self.context.get_swap_interval()
+    }
+
+    /// Returns the smallest and largest number of vertical blanks that `set_swap_interval`
+    /// accepts for the window or surface the context currently draws to, so that applications can
+    /// offer the supported vsync options. Larger values are clamped by the driver.
+    ///
+    /// The bounds come from `EGL_MIN_SWAP_INTERVAL` and `EGL_MAX_SWAP_INTERVAL` on EGL and from
+    /// `GLX_MAX_SWAP_INTERVAL_EXT` on GLX. WGL doesn't report a maximum.
+    pub fn get_swap_interval_range(&self) -> Result<(u32, u32), ContextError> {
+        self.context.get_swap_interval_range()
+    }
+
+    /// Returns the present modes supported by the window or surface the context currently draws
+    /// to, so that applications can offer them to users. The order has no meaning.
+    ///
+    /// `PresentMode::FifoRelaxed` requires `GLX_EXT_swap_control_tear` or
+    /// `WGL_EXT_swap_control_tear`. `PresentMode::Mailbox` is only reported on Wayland, where
+    /// the compositor shows the latest frame without tearing, and replaces `Immediate` there.
+    pub fn supported_present_modes(&self) -> Result<Vec<PresentMode>, ContextError> {
+        self.context.supported_present_modes()
+    }
+
+    /// Sets how the frames are presented, through the swap interval. Returns
+    /// `ContextError::FunctionUnavailable` if the mode isn't in `supported_present_modes`.
+    pub fn set_present_mode(&self, mode: PresentMode) -> Result<(), ContextError> {
+        if !self.supported_present_modes()?.contains(&mode) {
+            return Err(ContextError::FunctionUnavailable);
+        }
+
+        let interval = match mode {
+            PresentMode::Immediate | PresentMode::Mailbox => SwapInterval::Wait(0),
+            PresentMode::Fifo => SwapInterval::Wait(1),
+            PresentMode::FifoRelaxed => SwapInterval::Adaptive(1),
+        };
+        self.set_swap_interval(interval)
+    }
+
+    /// Returns the present mode matching the current swap interval. Intervals above 1 are
+    /// reported as `Fifo`.
+    pub fn present_mode(&self) -> Result<PresentMode, ContextError> {
+        Ok(match self.get_swap_interval()? {
+            SwapInterval::Wait(0) | SwapInterval::Adaptive(0) => {
+                if self.supported_present_modes()?.contains(&PresentMode::Mailbox) {
+                    PresentMode::Mailbox
+                } else {
+                    PresentMode::Immediate
+                }
+            },
+            SwapInterval::Wait(_) => PresentMode::Fifo,
+            SwapInterval::Adaptive(_) => PresentMode::FifoRelaxed,
+        })
+    }
+
+    /// Returns what happens to the content of the back buffer of the window or surface the
+    /// context currently draws to when the buffers are swapped.
+    ///
+    /// Backends other than EGL always return `SwapBehavior::Destroyed`, since they don't
+    /// guarantee that the content is preserved.
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        self.context.swap_behavior()
+    }
+
+    /// Returns the colorspace of the window or surface the context currently draws to, which may
+    /// differ from the one requested with `ContextBuilder::with_colorspace` when it was `None`.
+    ///
+    /// Backends other than EGL return `Colorspace::Srgb` for sRGB-capable pixel formats and
+    /// `Colorspace::Linear` otherwise.
+    pub fn colorspace(&self) -> Result<Colorspace, ContextError> {
+        self.context.colorspace()
+    }
+
+    /// Returns the buffer the context currently renders to.
+    ///
+    /// With `EGL_KHR_mutable_render_buffer`, a change made with `set_render_buffer` only takes
+    /// effect after the next call to `swap_buffers`. Other backends return the buffer of the
+    /// pixel format, which never changes.
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        self.context.render_buffer()
+    }
+
+    /// Switches the window or surface the context currently draws to between rendering to the
+    /// back buffer and rendering directly to the front buffer, for low-latency drawing. The change
+    /// takes effect after the next call to `swap_buffers`, and `swap_buffers` must still be
+    /// called to flush the rendering to the front buffer.
+    ///
+    /// Requires a context built with `ContextBuilder::with_mutable_render_buffer`, otherwise
+    /// `ContextError::FunctionUnavailable` is returned unless the buffer doesn't change.
+    pub fn set_render_buffer(&self, render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        self.context.set_render_buffer(render_buffer)
+    }
+
+    /// Returns how the samples of the window or surface the context currently draws to are
+    /// resolved when the buffers are swapped.
+    ///
+    /// Backends other than EGL always return `MultisampleResolve::Default`.
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        self.context.multisample_resolve()
+    }
+
+    /// Sets how the samples of the window or surface the context currently draws to are
+    /// resolved when the buffers are swapped, trading quality for bandwidth.
+    ///
+    /// `MultisampleResolve::Box` requires a context built with
+    /// `ContextBuilder::with_multisample_resolve_box`, otherwise
+    /// `ContextError::FunctionUnavailable` is returned. Backends other than EGL only accept
+    /// `MultisampleResolve::Default`.
+    pub fn set_multisample_resolve(&self, resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        self.context.set_multisample_resolve(resolve)
+    }
+
+    /// Makes the window of the context join the swap group `group`, so that its buffers are
+    /// swapped at the same time as the other windows of the group. `None` leaves the current
+    /// swap group.
+    ///
+    /// Requires `GLX_NV_swap_group` or `WGL_NV_swap_group`, otherwise
+    /// `ContextError::FunctionUnavailable` is returned.
+    pub fn join_swap_group(&self, group: Option<u32>) -> Result<(), ContextError> {
+        self.context.join_swap_group(group.unwrap_or(0))
+    }
+
+    /// Binds the swap group `group` to the swap barrier `barrier`, so that the groups bound to
+    /// the same barrier swap in lockstep across GPUs and machines, for example in video walls.
+    /// `None` unbinds the group from its barrier.
+    ///
+    /// Has the same requirements as `join_swap_group`.
+    pub fn bind_swap_barrier(&self, group: u32, barrier: Option<u32>)
+                             -> Result<(), ContextError>
+    {
+        self.context.bind_swap_barrier(group, barrier.unwrap_or(0))
+    }
+
+    /// Returns the OpenGL or OpenGL ES version of the context, which may be higher than the
+    /// requested one.
+    ///
+    /// The version is read from `GL_MAJOR_VERSION` and `GL_MINOR_VERSION`, or by parsing
+    /// `GL_VERSION` before OpenGL 3.0, the first time the context is made current. Returns
+    /// `(0, 0)` if the context was never made current or the version couldn't be read.
+    pub fn get_version(&self) -> (u8, u8) {
+        self.version.get().0
+    }
+
+    /// Returns the profile of the context, or `None` for OpenGL ES. Contexts older than OpenGL
+    /// 3.2 are reported as `GlProfile::Compatibility`. Read like `get_version`.
+    pub fn get_profile(&self) -> Option<GlProfile> {
+        self.version.get().1
+    }
+
+    /// Returns the flags the driver actually gave the context, read from `GL_CONTEXT_FLAGS`.
+    ///
+    /// Some drivers silently ignore the requested debug flag or robustness, which this reveals.
+    /// A context built with `ContextBuilder::with_gl_no_error` is always reported as such, since
+    /// drivers older than OpenGL 4.6 don't report it. Returns `ContextError::FunctionUnavailable`
+    /// if `glGetString` or `glGetIntegerv` can't be loaded, or if the context isn't current.
+    pub fn flags(&self) -> Result<ContextFlags, ContextError> {
+        // the flags of whichever context is current would be returned otherwise
+        if !self.context.is_current() {
+            return Err(ContextError::FunctionUnavailable);
+        }
+        let flags = unsafe {
+            context_flags::current_flags(|addr| self.context.get_proc_address(addr))?
+        };
+        let no_error = self.attributes.as_ref().map_or(false, |attributes| attributes.no_error);
+        Ok(ContextFlags { no_error: flags.no_error || no_error, .. flags })
+    }
+
+    /// Returns whether the GPU was reset since the last call, with `glGetGraphicsResetStatus`.
+    ///
+    /// After a reset, the context is lost and must be recreated along with all its objects. If
+    /// the context was built with `Robustness::RobustLoseContextOnReset` or
+    /// `Robustness::TryRobustLoseContextOnReset`, the status is also checked by `make_current` and
+    /// `swap_buffers`, which return `ContextError::ContextLost` after a reset; in that case, the
+    /// reset may already have been reported there.
+    ///
+    /// Requires OpenGL 4.5, OpenGL ES 3.2, `GL_KHR_robustness`, `GL_ARB_robustness` or
+    /// `GL_EXT_robustness`, otherwise `ContextError::FunctionUnavailable` is returned. It is
+    /// also returned if the context isn't current.
+    pub fn reset_status(&self) -> Result<ResetStatus, ContextError> {
+        if !self.context.is_current() {
+            return Err(ContextError::FunctionUnavailable);
+        }
+        unsafe { self.reset_status.query(|addr| self.context.get_proc_address(addr)) }
+    }
+
+    /// Swaps the buffers like `swap_buffers`, and returns an identifier of the frame that can be
+    /// passed to `presentation_time` later.
+    ///
+    /// Requires `GLX_OML_sync_control` or `EGL_ANDROID_get_frame_timestamps`, otherwise
+    /// `ContextError::FunctionUnavailable` is returned and the buffers are not swapped. With GLX,
+    /// the swap always happens on a vertical blank and ignores the swap interval. On X11, the
+    /// Present extension is used instead when it is available.
+    pub fn swap_buffers_with_frame_id(&self) -> Result<FrameId, ContextError> {
+        let frame = self.metrics.time_swap(|| self.context.swap_buffers_with_frame_id());
+        let frame = self.context_lost.watch(frame)?;
+        self.check_reset()?;
+        self.frame_latency.after_swap()?;
+        Ok(FrameId(frame))
+    }
+
+    /// Swaps the buffers like `swap_buffers_with_frame_id`, unless the frame submitted by the
+    /// previous call to this method or to `swap_buffers_with_frame_id` has not been presented
+    /// yet, in which case the swap could block and `ContextError::WouldBlock` is returned instead.
+    ///
+    /// Swaps made with `swap_buffers` are not taken into account. Has the same requirements as
+    /// `swap_buffers_with_frame_id`, otherwise `ContextError::FunctionUnavailable` is returned.
+    pub fn try_swap_buffers(&self) -> Result<FrameId, ContextError> {
+        let frame = self.metrics.time_swap(|| self.context.try_swap_buffers());
+        let frame = self.context_lost.watch(frame)?;
+        self.check_reset()?;
+        self.frame_latency.after_swap()?;
+        Ok(FrameId(frame))
+    }
+
+    /// Returns when the frame identified by `frame` was presented on the screen.
+    ///
+    /// This doesn't block, and returns `PresentationTime::Pending` until the frame is presented.
+    /// Only the time of the most recent frames is known, so this should be called shortly after
+    /// the frame was presented.
+    pub fn presentation_time(&self, frame: FrameId) -> Result<PresentationTime, ContextError> {
+        self.context.presentation_time(frame.0)
+    }
+}
+
+impl GlContext for Context<PossiblyCurrent> {
+    fn is_current(&self) -> bool {
+        self.context.is_current()
+    }
+
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        self.context.get_proc_address(addr)
+    }
+
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.frame_limiter.wait();
+        self.context_lost.watch(self.metrics.time_swap(|| self.context.swap_buffers()))?;
+        self.check_reset()?;
+        self.frame_latency.after_swap()
+    }
+
+    fn get_api(&self) -> Api {
+        self.context.get_api()
+    }
+
+    fn get_pixel_format(&self) -> PixelFormat {
+        Context::get_pixel_format(self)
+    }
+
+    fn resize(&self, width: u32, height: u32) {
+        self.context.resize(width, height);
+    }
+}
+
+impl<T: ContextCurrentState> ContextTrait for Context<T> {
+    type PossiblyCurrent = Context<PossiblyCurrent>;
+    type NotCurrent = Context<NotCurrent>;
+
+    unsafe fn make_current(self) -> Result<Self::PossiblyCurrent, (Self, ContextError)> {
+        Context::make_current(self)
+    }
+
+    unsafe fn make_not_current(self) -> Result<Self::NotCurrent, (Self, ContextError)> {
+        Context::make_not_current(self)
+    }
+
+    fn is_current(&self) -> bool {
+        Context::is_current(self)
+    }
+
+    fn get_api(&self) -> Api {
+        Context::get_api(self)
+    }
+
+    fn get_pixel_format(&self) -> PixelFormat {
+        Context::get_pixel_format(self)
+    }
+}
+
+impl<T: ContextCurrentState> std::fmt::Debug for Context<T> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        formatter.debug_struct("Context")
+            .field("api", &self.get_api())
+            .finish()
+    }
+}
+
+impl<T: ContextCurrentState> Drop for Context<T> {
+    fn drop(&mut self) {
+        // sync objects belong to the share group, so they would outlive the context
+        if self.context.is_current() {
+            unsafe { self.frame_latency.delete_fences(); }
+        }
+    }
+}
+
+/// Error that can happen while creating a window or a headless renderer.
+#[derive(Debug)]
+pub enum CreationError {
+    OsError(String),
+    /// TODO: remove this error
+    NotSupported(&'static str),
+    NoBackendAvailable(Box<std::error::Error + Send>),
+    RobustnessNotSupported,
+    /// Protected content was requested with `ContextBuilder::with_protected_content`, but the
+    /// backend doesn't support `EGL_EXT_protected_content` or couldn't allocate secure memory.
+    ProtectedContentNotSupported,
+    /// A context without error checking was requested with `ContextBuilder::with_gl_no_error`,
+    /// but the backend doesn't support `GL_KHR_no_error`.
+    NoErrorNotSupported,
+    OpenGlVersionNotSupported,
+    NoAvailablePixelFormat,
+    /// No pixel format matches the requirements. Contains up to 3 of the available formats that
+    /// fail the fewest requirements, closest first. Returned by EGL, GLX and WGL with
+    /// `WGL_ARB_pixel_format`.
+    NoMatchingPixelFormat(Vec<NearestPixelFormat>),
+    PlatformSpecific(String),
+    #[cfg(feature = "winit")]
+    Window(WindowCreationError),
+    /// None of the backends that glutin tried could create the context. Contains the error of
+    /// each backend, in the order they were tried.
+    BackendsFailed(Vec<(Backend, CreationError)>),
+}
+
+impl CreationError {
+    fn to_string(&self) -> &str {
+        match *self {
+            CreationError::OsError(ref text) => &text,
+            CreationError::NotSupported(text) => &text,
+            CreationError::NotSupported(_) => "Some of the requested attributes are not supported",
+            CreationError::NoBackendAvailable(_) => "No backend is available",
+            CreationError::RobustnessNotSupported => "You requested robustness, but it is \
+                                                      not supported.",
+            CreationError::ProtectedContentNotSupported => "You requested protected content, \
+                                                            but it is not supported.",
+            CreationError::NoErrorNotSupported => "You requested a context without error \
+                                                   checking, but it is not supported.",
+            CreationError::OpenGlVersionNotSupported => "The requested OpenGL version is not \
+                                                         supported.",
+            CreationError::NoAvailablePixelFormat |
+            CreationError::NoMatchingPixelFormat(_) => "Couldn't find any pixel format that \
+                                                       matches the criterias.",
+            CreationError::PlatformSpecific(ref text) => &text,
+            #[cfg(feature = "winit")]
+            CreationError::Window(ref err) => std::error::Error::description(err),
+            CreationError::BackendsFailed(_) => "None of the backends could create the context",
+        }
+    }
+
+    /// Returns true if the error is `NoAvailablePixelFormat` or `NoMatchingPixelFormat`.
+    pub(crate) fn is_no_pixel_format(&self) -> bool {
+        match *self {
+            CreationError::NoAvailablePixelFormat | CreationError::NoMatchingPixelFormat(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the error to report when every backend in `errors` failed. If they all failed
+    /// because no pixel format matched, the first of their errors that has the nearest formats,
+    /// or else `NoAvailablePixelFormat`, is returned so that `ContextBuilder::with_fallback` can
+    /// relax the requirements.
+    pub(crate) fn from_backend_errors(errors: Vec<(Backend, CreationError)>) -> CreationError {
+        if errors.iter().all(|&(_, ref err)| err.is_no_pixel_format()) {
+            return errors.into_iter()
+                .map(|(_, err)| err)
+                .find(|err| match *err {
+                    CreationError::NoMatchingPixelFormat(_) => true,
+                    _ => false,
+                })
+                .unwrap_or(CreationError::NoAvailablePixelFormat);
+        }
+        CreationError::BackendsFailed(errors)
+    }
+}
+
+impl std::fmt::Display for CreationError {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
         formatter.write_str(self.to_string())?;
         if let &CreationError::NotSupported(msg) = self {
             write!(formatter, ": {}", msg)?;
         }
+        if let &CreationError::NoMatchingPixelFormat(ref nearest) = self {
+            for (i, format) in nearest.iter().enumerate() {
+                let separator = if i == 0 { " The closest ones are: " } else { "; " };
+                write!(formatter, "{}{}", separator, format)?;
+            }
+        }
+        if let &CreationError::BackendsFailed(ref errors) = self {
+            for (i, &(backend, ref err)) in errors.iter().enumerate() {
+                let separator = if i == 0 { ": " } else { "; " };
+                write!(formatter, "{}{:?} failed with \"{}\"", separator, backend, err)?;
+            }
+        }
         if let Some(err) = std::error::Error::cause(self) {
             write!(formatter, ": {}", err)?;
         }
@@ -449,12 +1723,14 @@ impl std::error::Error for CreationError {
     fn cause(&self) -> Option<&std::error::Error> {
         match *self {
             CreationError::NoBackendAvailable(ref err) => Some(&**err),
+            #[cfg(feature = "winit")]
             CreationError::Window(ref err) => Some(err),
             _ => None
         }
     }
 }
 
+#[cfg(feature = "winit")]
 impl From<WindowCreationError> for CreationError {
     fn from(err: WindowCreationError) -> Self {
         CreationError::Window(err)
@@ -464,16 +1740,25 @@ impl From<WindowCreationError> for CreationError {
 /// Error that can happen when manipulating an OpenGL context.
 #[derive(Debug)]
 pub enum ContextError {
+    /// General platform error.
+    OsError(String),
     IoError(io::Error),
     ContextLost,
+    /// The requested functionality is not available on this context.
+    FunctionUnavailable,
+    /// The operation would have blocked the calling thread.
+    WouldBlock,
 }
 
 impl ContextError {
     fn to_string(&self) -> &str {
         use std::error::Error;
         match *self {
+            ContextError::OsError(ref string) => string,
             ContextError::IoError(ref err) => err.description(),
-            ContextError::ContextLost => "Context lost"
+            ContextError::ContextLost => "Context lost",
+            ContextError::FunctionUnavailable => "This functionality is not available",
+            ContextError::WouldBlock => "The operation would block",
         }
     }
 }
@@ -490,6 +1775,19 @@ impl std::error::Error for ContextError {
     }
 }
 
+/// A native API that glutin can create contexts with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// GLX, on X11.
+    Glx,
+    /// EGL, on X11, Wayland, Android and Windows.
+    Egl,
+    /// OSMesa, for headless contexts rendered in software.
+    OsMesa,
+    /// WGL, on Windows.
+    Wgl,
+}
+
 /// All APIs related to OpenGL that you can possibly get while using glutin.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Api {
@@ -501,6 +1799,155 @@ pub enum Api {
     WebGl,
 }
 
+/// How many screen refreshes to wait for before swapping the buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapInterval {
+    /// Waits for the given number of vertical blanks. `Wait(0)` swaps immediately, which may
+    /// cause tearing.
+    Wait(u32),
+    /// Waits for the given number of vertical blanks, but swaps immediately if the frame missed
+    /// the last one, so that late frames tear instead of stalling for a full refresh.
+    ///
+    /// Requires `GLX_EXT_swap_control_tear` or `WGL_EXT_swap_control_tear`.
+    Adaptive(u32),
+}
+
+/// How frames are queued for presentation, modeled after the present modes of Vulkan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PresentMode {
+    /// Frames are shown as soon as they are swapped, which may cause tearing.
+    Immediate,
+    /// Frames are shown on the next vertical blank, and a frame that is swapped while another is
+    /// waiting replaces it. Swapping never blocks and doesn't tear.
+    Mailbox,
+    /// Frames are shown on the next vertical blank, in order. Swapping blocks when the queue is
+    /// full. This is vsync.
+    Fifo,
+    /// Like `Fifo`, but a frame that missed its vertical blank is shown immediately, which may
+    /// cause tearing.
+    FifoRelaxed,
+}
+
+/// A GLX extension that sets the swap interval, in the order in which they are preferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlxSwapControl {
+    /// `GLX_EXT_swap_control`, which sets the interval of a drawable and is the only one that
+    /// supports `SwapInterval::Adaptive` and querying the interval.
+    Ext,
+    /// `GLX_MESA_swap_control`, which sets the interval of the current drawable.
+    Mesa,
+    /// `GLX_SGI_swap_control`, which can't disable vsync.
+    Sgi,
+}
+
+/// Identifies a frame submitted with `Context::swap_buffers_with_frame_id`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FrameId(u64);
+
+/// When a frame was presented on the screen, as returned by `Context::presentation_time`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresentationTime {
+    /// The frame has not been presented yet.
+    Pending,
+    /// The frame was presented at this time of the system's monotonic clock.
+    Presented(Duration),
+    /// The time of the frame is not known, for example because it was never presented or because
+    /// the frame is too old for the implementation to remember it.
+    Unavailable,
+}
+
+/// How a frame was shown on the screen, as reported by `PresentationFeedback`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresentationMethod {
+    /// The content of the frame was copied to the screen.
+    Copy,
+    /// The frame was shown by flipping the buffer scanned out by the display, without a copy.
+    Flip,
+    /// The frame was replaced by a later one before it could be shown.
+    Skipped,
+}
+
+/// Information about how a frame was presented on the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresentationFeedback {
+    /// When the frame was presented, as a time of the system's monotonic clock.
+    pub time: Duration,
+    /// The media stream counter of the display when the frame was presented, which counts its
+    /// vertical blanks.
+    pub msc: u64,
+    /// How the frame was presented.
+    pub method: PresentationMethod,
+    /// The refresh period of the display the frame was presented on, if known.
+    pub refresh: Option<Duration>,
+    /// Details about the presentation, if the platform reports them.
+    pub flags: Option<PresentationFlags>,
+}
+
+/// Details about how a frame was presented, as reported by the `presentation-time` protocol of
+/// Wayland.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PresentationFlags {
+    /// The presentation was synchronized with the vertical blank of the display, without
+    /// tearing.
+    pub vsync: bool,
+    /// The time was given by the clock of the display hardware rather than sampled in software.
+    pub hw_clock: bool,
+    /// The display hardware signaled that the presentation started.
+    pub hw_completion: bool,
+    /// The buffer was scanned out directly, without a copy by the compositor.
+    pub zero_copy: bool,
+}
+
+/// What happens to the content of the back buffer when the buffers are swapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapBehavior {
+    /// The content of the back buffer is undefined after a swap, and the whole frame must be
+    /// redrawn.
+    Destroyed,
+    /// The content of the back buffer is preserved across swaps, so that only the regions that
+    /// changed need to be redrawn.
+    Preserved,
+}
+
+/// How the values written to the color buffer of a surface are interpreted by the display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colorspace {
+    /// Linear values in the sRGB primaries. This is the default of EGL surfaces.
+    Linear,
+    /// sRGB encoded values. Writes are encoded when `GL_FRAMEBUFFER_SRGB` is enabled.
+    Srgb,
+    /// Display-P3 encoded values, for wide gamut displays.
+    DisplayP3,
+    /// Linear values in the Display-P3 primaries.
+    DisplayP3Linear,
+    /// scRGB encoded values, which extend sRGB with values outside of `[0.0, 1.0]` for HDR and
+    /// wide gamut. Requires a floating point color buffer.
+    ScRgb,
+    /// Linear scRGB values. Requires a floating point color buffer.
+    ScRgbLinear,
+}
+
+/// The buffer the client API renders to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderBuffer {
+    /// Rendering goes to the back buffer, which is shown by swapping the buffers.
+    Back,
+    /// Rendering goes directly to the buffer that is shown on the screen, which avoids the
+    /// latency of swapping but may show incomplete frames.
+    Single,
+}
+
+/// How the samples of a multisampled window or surface are resolved into single pixels when the
+/// buffers are swapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultisampleResolve {
+    /// The filter chosen by the implementation, which is often the cheapest one.
+    Default,
+    /// A box filter, which averages the samples of each pixel with equal weights. It usually
+    /// looks better, but can use more bandwidth and power on tiled mobile GPUs.
+    Box,
+}
+
 /// Describes the requested OpenGL context profiles.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GlProfile {
@@ -532,6 +1979,18 @@ pub enum GlRequest {
         /// The version to use for OpenGL ES.
         opengles_version: (u8, u8),
     },
+
+    /// Tries each API and version of the list in order, and creates a context with the first
+    /// one that is supported.
+    ///
+    /// Example: `GlRequest::PreferredList(&[(Api::OpenGl, (4, 6)), (Api::OpenGl, (3, 3)),
+    /// (Api::OpenGlEs, (3, 0))])`. The profile and the other attributes are the same for every
+    /// attempt.
+    ///
+    /// With `WindowedContext::new`, the window is built along with each attempt and destroyed
+    /// when the attempt fails, so a window may briefly flash on screen for each unsupported
+    /// version. Build the window hidden and show it once the context is created to avoid this.
+    PreferredList(&'static [(Api, (u8, u8))]),
 }
 
 impl GlRequest {
@@ -540,6 +1999,9 @@ impl GlRequest {
         match self {
             &GlRequest::Specific(Api::OpenGl, version) => Some(version),
             &GlRequest::GlThenGles { opengl_version: version, .. } => Some(version),
+            &GlRequest::PreferredList(versions) => {
+                versions.iter().find(|&&(api, _)| api == Api::OpenGl).map(|&(_, version)| version)
+            },
             _ => None,
         }
     }
@@ -562,7 +2024,8 @@ pub enum Robustness {
     /// doing before using it. See the `GL_KHR_no_error` extension.
     ///
     /// Since this option is purely an optimisation, no error will be returned if the backend
-    /// doesn't support it. Instead it will automatically fall back to `NotRobust`.
+    /// doesn't support it. Instead it will automatically fall back to `NotRobust`. Use
+    /// `ContextBuilder::with_gl_no_error` to fail instead.
     NoError,
 
     /// Everything is checked to avoid any crash. The driver will attempt to avoid any problem,
@@ -574,24 +2037,353 @@ pub enum Robustness {
     /// supported.
     TryRobustNoResetNotification,
 
-    /// Everything is checked to avoid any crash. If a problem occurs, the context will enter a
-    /// "context lost" state. It must then be recreated. For the moment, glutin doesn't provide a
-    /// way to recreate a context with the same window :-/
-    RobustLoseContextOnReset,
+    /// Everything is checked to avoid any crash. If a problem occurs, the context will enter a
+    /// "context lost" state. It must then be recreated, which `WindowedContext::recreate` and
+    /// `RawContext::recreate` do with the same window.
+    RobustLoseContextOnReset,
+
+    /// Same as `RobustLoseContextOnReset` but the context creation doesn't fail if it's not
+    /// supported.
+    TryRobustLoseContextOnReset,
+}
+
+/// The priority with which the GPU schedules the commands of a context, relative to the other
+/// contexts of the system. See `ContextBuilder::with_context_priority`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ContextPriority {
+    /// Lower than the default priority, for background work.
+    Low,
+    /// The default priority of contexts.
+    Medium,
+    /// Higher than the default priority, for compositors and latency-critical rendering.
+    High,
+    /// Higher than all the other priorities, with `EGL_NV_context_priority_realtime`. Falls back
+    /// to `High` without it.
+    Realtime,
+}
+
+/// The behavior of the driver when you change the current context.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReleaseBehavior {
+    /// Doesn't do anything. Most notably doesn't flush.
+    None,
+
+    /// Flushes the context that was previously current as if `glFlush` was called.
+    Flush,
+}
+
+/// How to choose between several pixel formats that all match the requirements.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RankingPolicy {
+    /// Takes the format preferred by the driver, which follows the sorting rules of the
+    /// underlying API (`glXChooseFBConfig`, `eglChooseConfig`, ...).
+    Strict,
+
+    /// Prefers hardware-accelerated formats, then the fewest samples and the smallest
+    /// depth/stencil and color buffers that satisfy the request.
+    PreferPerformance,
+
+    /// Prefers the format that uses the least memory per pixel, counting every sample and
+    /// every buffer.
+    MinimizeMemory,
+
+    /// Prefers hardware-accelerated formats, then the highest sample count and the deepest
+    /// color and depth buffers.
+    MaximizeQuality,
+}
+
+/// Whether the pixel format must be rendered by the GPU.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HardwareAcceleration {
+    /// Only hardware accelerated formats are considered.
+    Require,
+    /// Hardware accelerated formats are tried first, then software renderers if none of them is
+    /// available. The fallback is reported as `RelaxedRequirement::HardwareAcceleration`.
+    Prefer,
+    /// The choice is left to the implementation.
+    DontCare,
+    /// Only software renderers are considered.
+    Disallow,
+}
+
+impl HardwareAcceleration {
+    /// Returns the value of `PixelFormat::hardware_accelerated` that backends must match, or
+    /// `None` if they don't need to filter formats. The fallback of `Prefer` is handled by
+    /// `PixelFormatRequirements::fallback_chain`.
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd",
+              target_os = "macos"))]
+    pub(crate) fn required(&self) -> Option<bool> {
+        match *self {
+            HardwareAcceleration::Require | HardwareAcceleration::Prefer => Some(true),
+            HardwareAcceleration::DontCare => None,
+            HardwareAcceleration::Disallow => Some(false),
+        }
+    }
+}
+
+/// Whether the EGL config must be conformant to the requested API, that is whether it has the
+/// API in its `EGL_CONFORMANT`.
+///
+/// Some mobile drivers list non-conformant configs first, with which rendering can subtly break.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Conformance {
+    /// Only conformant configs are considered.
+    Require,
+    /// Conformant configs are tried first, then non-conformant ones if none of them is
+    /// available. The fallback is reported as `RelaxedRequirement::Conformance`.
+    Prefer,
+    /// Configs are considered whether they are conformant or not.
+    DontCare,
+}
+
+/// Whether the pixel format must be sRGB-capable, that is whether writes to the color buffer
+/// can be encoded to sRGB with `GL_FRAMEBUFFER_SRGB`.
+///
+/// Only GLX and WGL can choose formats by their sRGB capability. The other backends ignore this
+/// requirement.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SrgbRequirement {
+    /// Only sRGB-capable formats are considered.
+    Require,
+    /// sRGB-capable formats are tried first, then any format if none of them is available. The
+    /// fallback is reported as `RelaxedRequirement::Srgb`.
+    Prefer,
+    /// Formats are considered whether they are sRGB-capable or not.
+    DontCare,
+    /// Only formats that aren't sRGB-capable are considered, for shaders that already encode
+    /// their output.
+    Disallow,
+}
+
+/// A pixel format requirement that was relaxed because no format satisfied it. See
+/// `ContextBuilder::with_fallback`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RelaxedRequirement {
+    /// The number of samples was lowered to the given value. `0` means that multisampling was
+    /// disabled.
+    Multisampling(u16),
+
+    /// sRGB-capable formats were no longer required.
+    Srgb,
+
+    /// Hardware accelerated formats were preferred with `HardwareAcceleration::Prefer`, but only
+    /// software renderers were available.
+    HardwareAcceleration,
+
+    /// Conformant configs were preferred with `Conformance::Prefer`, but none was available.
+    Conformance,
+}
+
+/// Calls `create` with `pf_reqs`, then with each of its fallbacks as long as it fails because no
+/// pixel format matched. The error of the first attempt is returned if they all fail, since its
+/// nearest formats are compared to the requirements as they were given.
+pub(crate) fn create_with_fallbacks<T, F>(pf_reqs: &PixelFormatRequirements, mut create: F)
+    -> Result<(T, Vec<RelaxedRequirement>), CreationError>
+    where F: FnMut(&PixelFormatRequirements) -> Result<T, CreationError>
+{
+    let mut first_error = None;
+    for (pf_reqs, relaxed) in pf_reqs.fallback_chain() {
+        if !relaxed.is_empty() {
+            pf_reqs.log_config(&format!("retrying with the relaxed requirements {:?}", relaxed));
+        }
+        match create(&pf_reqs) {
+            Ok(value) => return Ok((value, relaxed)),
+            Err(err) => {
+                if !err.is_no_pixel_format() {
+                    return Err(err);
+                }
+                pf_reqs.log_config(&format!("no pixel format was chosen: {}", err));
+                if first_error.is_none() {
+                    first_error = Some(err);
+                }
+            },
+        }
+    }
+
+    Err(first_error.unwrap_or(CreationError::NoAvailablePixelFormat))
+}
+
+/// Calls `create` with `gl_attr`, or, if it requests `GlRequest::PreferredList`, with each
+/// version of the list in order until one of them succeeds, so that the backends only see
+/// `GlRequest::Specific` requests. If they all fail, the first error that isn't
+/// `OpenGlVersionNotSupported` is returned, since it is the most informative.
+pub(crate) fn create_with_versions<S: Clone, T, F>(gl_attr: &GlAttributes<S>, mut create: F)
+    -> Result<T, CreationError>
+    where F: FnMut(&GlAttributes<S>) -> Result<T, CreationError>
+{
+    let versions = match gl_attr.version {
+        GlRequest::PreferredList(versions) => versions,
+        _ => return create(gl_attr),
+    };
+
+    let mut error = None;
+    for &(api, version) in versions {
+        let mut attempt = gl_attr.clone();
+        attempt.version = GlRequest::Specific(api, version);
+        match create(&attempt) {
+            Ok(value) => return Ok(value),
+            Err(CreationError::OpenGlVersionNotSupported) => (),
+            Err(err) => {
+                if error.is_none() {
+                    error = Some(err);
+                }
+            },
+        }
+    }
+
+    Err(error.unwrap_or(CreationError::OpenGlVersionNotSupported))
+}
+
+/// An available pixel format that doesn't match the requirements, reported by
+/// `CreationError::NoMatchingPixelFormat`.
+#[derive(Debug, Clone)]
+pub struct NearestPixelFormat {
+    /// The pixel format.
+    pub pixel_format: PixelFormat,
+    /// The requirements that the pixel format fails.
+    pub unmet_requirements: Vec<UnmetRequirement>,
+}
+
+impl std::fmt::Display for NearestPixelFormat {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        let format = &self.pixel_format;
+        write!(formatter, "{} color bits, {} alpha bits, {} depth bits, {} stencil bits, {}x \
+                           multisampling{}{}{}{}",
+               format.color_bits, format.alpha_bits, format.depth_bits, format.stencil_bits,
+               format.multisampling.unwrap_or(0),
+               if format.srgb { ", sRGB" } else { "" },
+               if format.double_buffer { ", double buffered" } else { "" },
+               if format.stereoscopy { ", stereoscopic" } else { "" },
+               if format.hardware_accelerated { "" } else { ", software" })?;
+        write!(formatter, " (fails {:?})", self.unmet_requirements)
+    }
+}
+
+/// A requirement of `PixelFormatRequirements` that a `NearestPixelFormat` fails.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UnmetRequirement {
+    /// `hardware_accelerated`.
+    HardwareAcceleration,
+    /// `color_bits`, which is also failed when one of the channels has too few bits, or
+    /// `rgb_bits`.
+    ColorBits,
+    /// `alpha_bits`.
+    AlphaBits,
+    /// `depth_bits`.
+    DepthBits,
+    /// `stencil_bits`.
+    StencilBits,
+    /// `double_buffer`.
+    DoubleBuffer,
+    /// `multisampling`.
+    Multisampling,
+    /// `stereoscopy`.
+    Stereoscopy,
+    /// `srgb`.
+    Srgb,
+}
+
+/// Splits `color_bits`, the size of a color buffer without alpha, between the red, green and
+/// blue channels like the formats of the drivers, for example 5-6-5 for 16 bits and 10-10-10 for
+/// 30 bits.
+#[cfg(any(target_os = "windows", target_os = "linux", target_os = "android",
+          target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd",
+          target_os = "macos"))]
+pub(crate) fn channel_bits(color_bits: u8) -> (u8, u8, u8) {
+    let bits = color_bits / 3;
+    (bits,
+     bits + if color_bits % 3 != 0 { 1 } else { 0 },
+     bits + if color_bits % 3 == 2 { 1 } else { 0 })
+}
+
+/// Finds the highest versions of `api` for which `supports` succeeds in creating a context, by
+/// trying each version in descending order. `supports` receives the version and the profile to
+/// request, which is `None` with OpenGL ES.
+#[cfg(any(target_os = "windows", target_os = "linux", target_os = "android",
+          target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+pub(crate) fn find_max_versions<F>(api: Api, mut supports: F)
+                                   -> Result<SupportedVersions, CreationError>
+    where F: FnMut((u8, u8), Option<GlProfile>) -> bool
+{
+    const OPENGL_VERSIONS: &[(u8, u8)] = &[(4, 6), (4, 5), (4, 4), (4, 3), (4, 2), (4, 1),
+                                           (4, 0), (3, 3), (3, 2), (3, 1), (3, 0), (2, 1),
+                                           (2, 0)];
+    const OPENGL_ES_VERSIONS: &[(u8, u8)] = &[(3, 2), (3, 1), (3, 0), (2, 0), (1, 1), (1, 0)];
+
+    match api {
+        Api::OpenGl => {
+            // profiles only exist since OpenGL 3.2
+            let core = OPENGL_VERSIONS.iter().cloned()
+                .filter(|&version| version >= (3, 2))
+                .find(|&version| supports(version, Some(GlProfile::Core)));
+            let compatibility = OPENGL_VERSIONS.iter().cloned()
+                .find(|&version| supports(version, Some(GlProfile::Compatibility)));
+            Ok(SupportedVersions { core: core, compatibility: compatibility })
+        },
+        Api::OpenGlEs => {
+            let core = OPENGL_ES_VERSIONS.iter().cloned()
+                .find(|&version| supports(version, None));
+            Ok(SupportedVersions { core: core, compatibility: None })
+        },
+        Api::WebGl => Err(CreationError::NotSupported("WebGL contexts can't be probed")),
+    }
+}
+
+/// Returns requirements that any config able to create a context satisfies, used to find a
+/// config to probe the versions of a display with.
+#[cfg(any(target_os = "windows", target_os = "linux", target_os = "android",
+          target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+pub(crate) fn probe_requirements() -> PixelFormatRequirements {
+    PixelFormatRequirements {
+        hardware_accelerated: HardwareAcceleration::DontCare,
+        color_bits: None,
+        alpha_bits: None,
+        depth_bits: None,
+        stencil_bits: None,
+        conformance: Conformance::DontCare,
+        .. PixelFormatRequirements::default()
+    }
+}
+
+/// Returns the `GL_RENDERER` string of the current context, whose functions are loaded with
+/// `get_proc_address`, or `None` if `glGetString` isn't available.
+pub(crate) unsafe fn current_renderer<F>(get_proc_address: F) -> Option<String>
+    where F: Fn(&str) -> *const ()
+{
+    // From `gl.h`.
+    const GL_RENDERER: c_uint = 0x1F01;
+
+    let get_string = get_proc_address("glGetString");
+    if get_string.is_null() {
+        return None;
+    }
+    let get_string: extern "system" fn(c_uint) -> *const c_char = mem::transmute(get_string);
+    let renderer = get_string(GL_RENDERER);
+    if renderer.is_null() {
+        return None;
+    }
+    Some(CStr::from_ptr(renderer).to_string_lossy().into_owned())
+}
 
-    /// Same as `RobustLoseContextOnReset` but the context creation doesn't fail if it's not
-    /// supported.
-    TryRobustLoseContextOnReset,
+/// Returns true if `renderer`, the `GL_RENDERER` string of a context, names a software renderer.
+pub(crate) fn is_software_renderer(renderer: &str) -> bool {
+    const SOFTWARE_RENDERERS: &[&str] = &[
+        "llvmpipe", "softpipe", "Software Rasterizer", "swrast", "SWR", "Mesa OffScreen",
+        "GDI Generic", "Apple Software Renderer", "Microsoft Basic Render Driver", "SwiftShader",
+    ];
+    SOFTWARE_RENDERERS.iter().any(|name| renderer.contains(name))
 }
 
-/// The behavior of the driver when you change the current context.
+/// The caveat of a pixel format, which tells whether it may be slow or not fully conformant.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum ReleaseBehavior {
-    /// Doesn't do anything. Most notably doesn't flush.
+pub enum ConfigCaveat {
+    /// The format has no caveat.
     None,
-
-    /// Flushes the context that was previously current as if `glFlush` was called.
-    Flush,
+    /// The format is slow, usually because it is rendered in software.
+    Slow,
+    /// The format doesn't pass the conformance tests of the API.
+    NonConformant,
 }
 
 /// Describes a possible format. Unused.
@@ -600,6 +2392,9 @@ pub enum ReleaseBehavior {
 pub struct PixelFormat {
     pub hardware_accelerated: bool,
     pub color_bits: u8,
+    pub red_bits: u8,
+    pub green_bits: u8,
+    pub blue_bits: u8,
     pub alpha_bits: u8,
     pub depth_bits: u8,
     pub stencil_bits: u8,
@@ -607,20 +2402,143 @@ pub struct PixelFormat {
     pub double_buffer: bool,
     pub multisampling: Option<u16>,
     pub srgb: bool,
+    /// The identifier of the format in the native API, to recreate windows with the same visual
+    /// or to find the format in vendor tools. `None` on macOS, iOS and emscripten, and with
+    /// OSMesa.
+    pub native_id: Option<NativeConfigId>,
+    /// The caveat of the format, from `EGL_CONFIG_CAVEAT` or `GLX_CONFIG_CAVEAT`. The other
+    /// backends report `Slow` for the formats that aren't hardware accelerated.
+    pub caveat: ConfigCaveat,
+    /// The colorspace of the surfaces of the format, `Srgb` for sRGB-capable formats and
+    /// `Linear` otherwise. EGL chooses the colorspace per surface, see `Context::colorspace`.
+    pub colorspace: Colorspace,
+    /// True if the color buffer stores floating-point values.
+    pub float_color_buffer: bool,
+    /// True if windows of this format can be transparent where the alpha of the rendered pixels
+    /// is below 1, see `ContextBuilder::with_transparency`. GLX requires a 32 bits visual, and
+    /// the other backends alpha bits.
+    pub transparent: bool,
+}
+
+/// The identifier of a pixel format in the native API.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum NativeConfigId {
+    /// An EGL config.
+    Egl {
+        /// The `EGL_CONFIG_ID` of the config.
+        config_id: i32,
+        /// The `EGL_NATIVE_VISUAL_ID` of the config, such as an X11 VisualID, or `None` if the
+        /// config has no native visual.
+        native_visual_id: Option<i32>,
+    },
+    /// A GLX FBConfig.
+    Glx {
+        /// The `GLX_FBCONFIG_ID` of the FBConfig.
+        fbconfig_id: i32,
+        /// The X11 VisualID of the visual of the FBConfig, or `None` if it has no visual.
+        visual_id: Option<i32>,
+    },
+    /// The index of a Windows pixel format, starting at 1, as passed to `SetPixelFormat`.
+    Wgl(i32),
+}
+
+/// The highest versions of an API for which a context can be created on a display.
+///
+/// See `Display::max_supported_version`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SupportedVersions {
+    /// The highest version of the core profile with OpenGL, or the highest version with OpenGL
+    /// ES. `None` if no context can be created.
+    pub core: Option<(u8, u8)>,
+    /// The highest version of the compatibility profile with OpenGL. Always `None` with OpenGL
+    /// ES.
+    pub compatibility: Option<(u8, u8)>,
+}
+
+/// Information about the device and driver of a display, queried without creating a context.
+///
+/// See `Display::device_info`. The fields that the driver doesn't report are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// The name of the vendor of the device, such as `"Intel"` or `"AMD"`.
+    pub vendor: Option<String>,
+    /// The PCI vendor ID of the device.
+    pub vendor_id: Option<u32>,
+    /// The name of the device, as reported by the driver.
+    pub device_name: Option<String>,
+    /// The PCI device ID of the device.
+    pub device_id: Option<u32>,
+    /// The amount of video memory, in megabytes.
+    pub video_memory: Option<u32>,
+    /// The name of the driver, such as `"iris"` or `"radeonsi"`.
+    pub driver_name: Option<String>,
+    /// The major, minor and patch version of the driver.
+    pub driver_version: Option<(u32, u32, u32)>,
+    /// True if the device renders on the GPU, false for software renderers such as llvmpipe.
+    pub hardware_accelerated: Option<bool>,
+}
+
+impl PixelFormat {
+    /// Returns true if the format is rendered by the GPU.
+    ///
+    /// This comes from the caveat of the config with EGL and GLX, the acceleration of the pixel
+    /// format with WGL and CGL, and is always false with OSMesa. Since drivers don't always flag
+    /// software renderers such as llvmpipe, the pixel formats returned by the `get_pixel_format`
+    /// of a context that is current are also checked against its `GL_RENDERER` string.
+    #[inline]
+    pub fn is_hardware_accelerated(&self) -> bool {
+        self.hardware_accelerated
+    }
+
+    /// The buffer rendered to by backends that can't change it at runtime.
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "dragonfly",
+              target_os = "freebsd", target_os = "openbsd", target_os = "macos",
+              target_os = "emscripten"))]
+    pub(crate) fn render_buffer(&self) -> RenderBuffer {
+        if self.double_buffer { RenderBuffer::Back } else { RenderBuffer::Single }
+    }
+}
+
+/// A rectangle of a surface, in pixels. The origin is the bottom-left corner of the surface.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rect {
+    /// Horizontal position of the bottom-left corner of the rectangle.
+    pub x: u32,
+    /// Vertical position of the bottom-left corner of the rectangle.
+    pub y: u32,
+    /// Width of the rectangle.
+    pub width: u32,
+    /// Height of the rectangle.
+    pub height: u32,
 }
 
 /// Describes how the backend should choose a pixel format.
 // TODO: swap method? (swap, copy)
 #[derive(Clone, Debug)]
 pub struct PixelFormatRequirements {
-    /// If true, only hardware-accelerated formats will be considered. If false, only software
-    /// renderers. `None` means "don't care". Default is `Some(true)`.
-    pub hardware_accelerated: Option<bool>,
+    /// Whether the format must, should or must not be hardware accelerated. Default is
+    /// `Require`.
+    pub hardware_accelerated: HardwareAcceleration,
+
+    /// If true, only software renderers such as llvmpipe, softpipe, OSMesa, the generic
+    /// implementation of Windows or the Apple software renderer are accepted, which is checked
+    /// with `GL_RENDERER` once the context is built. Default is `false`.
+    pub software_rendering: bool,
 
     /// Minimum number of bits for the color buffer, excluding alpha. `None` means "don't care".
     /// The default is `Some(24)`.
+    ///
+    /// The bits are split between the channels, and each channel must have at least its share,
+    /// so for example `Some(30)` only matches formats with 10 bits or more per channel.
     pub color_bits: Option<u8>,
 
+    /// The exact number of bits of the red, green and blue channels, such as `(5, 6, 5)` or
+    /// `(8, 8, 8)`, which replaces `color_bits` when set. The default is `None`.
+    ///
+    /// Only formats with exactly these sizes are chosen, except on macOS, iOS, emscripten and
+    /// with OSMesa, where only their sum is requested.
+    pub rgb_bits: Option<(u8, u8, u8)>,
+
     /// If true, the color buffer must be in a floating point format. Default is `false`.
     ///
     /// Using floating points allows you to write values outside of the `[0.0, 1.0]` range.
@@ -638,6 +2556,19 @@ pub struct PixelFormatRequirements {
     /// The default value is `Some(8)`.
     pub stencil_bits: Option<u8>,
 
+    /// Number of bits of alpha to pick among the formats that have at least `alpha_bits`. Formats
+    /// whose sizes are the closest to the preferred ones are picked first, before applying the
+    /// `ranking_policy`. `None` means no preference. The default is `None`.
+    pub preferred_alpha_bits: Option<u8>,
+
+    /// Number of bits of the depth buffer to pick among the formats that have at least
+    /// `depth_bits`. See `preferred_alpha_bits`. The default is `None`.
+    pub preferred_depth_bits: Option<u8>,
+
+    /// Number of bits of the stencil buffer to pick among the formats that have at least
+    /// `stencil_bits`. See `preferred_alpha_bits`. The default is `None`.
+    pub preferred_stencil_bits: Option<u8>,
+
     /// If true, only double-buffered formats will be considered. If false, only single-buffer
     /// formats. `None` means "don't care". The default is `Some(true)`.
     pub double_buffer: Option<bool>,
@@ -647,33 +2578,473 @@ pub struct PixelFormatRequirements {
     /// A value of `Some(0)` indicates that multisampling must not be enabled.
     pub multisampling: Option<u16>,
 
+    /// Contains the minimum number of coverage samples per pixel, for the coverage-sample
+    /// antialiasing of `EGL_NV_coverage_sample`. Only supported by EGL, mostly on Tegra GPUs.
+    /// `None` means "don't care". Default is `None`.
+    pub coverage_samples: Option<u16>,
+
     /// If true, only stereoscopic formats will be considered. If false, only non-stereoscopic
     /// formats. The default is `false`.
     pub stereoscopy: bool,
 
-    /// If true, only sRGB-capable formats will be considered. If false, don't care.
+    /// Whether the format must, should or must not be sRGB-capable.
+    /// The default is `DontCare`.
+    pub srgb: SrgbRequirement,
+
+    /// Whether EGL configs must be conformant to the requested API. Ignored by the other
+    /// backends. The default is `Require`.
+    pub conformance: Conformance,
+
+    /// If true, EGL and GLX configs with a caveat, that is slow or non-conformant ones, are
+    /// excluded. Ignored by the other backends. The default is `false`.
+    pub reject_caveats: bool,
+
+    /// The requested behavior of the back buffer when swapping. `Preserved` only considers
+    /// formats that can preserve it, and is only supported by EGL. The default is `Destroyed`.
+    pub swap_behavior: SwapBehavior,
+
+    /// If true, only formats whose window surfaces can switch between rendering to the back
+    /// buffer and to the front buffer at runtime will be considered. Only supported by EGL. The
+    /// default is `false`.
+    pub mutable_render_buffer: bool,
+
+    /// If true, only formats whose window surfaces can resolve their samples with a box filter,
+    /// as set with `Context::set_multisample_resolve`, will be considered. Only supported by EGL.
     /// The default is `false`.
-    pub srgb: bool,
+    pub multisample_resolve_box: bool,
 
-    /// The behavior when changing the current context. Default is `Flush`.
-    pub release_behavior: ReleaseBehavior,
+    /// If true, only EGL configs whose surfaces can be recorded, for example fed to a
+    /// `MediaCodec` input surface or a virtual display, will be considered. Requires
+    /// `EGL_ANDROID_recordable`. The default is `false`.
+    pub recordable: bool,
+
+    /// If set, only EGL configs with this `EGL_NATIVE_VISUAL_ID` will be considered, for example
+    /// a `WINDOW_FORMAT_*` of Android or a GBM format. The default is `None`.
+    pub native_visual_id: Option<i32>,
+
+    /// If true, the window is made transparent and only formats with at least 8 bits of alpha
+    /// will be considered, so that the window system blends the window with what is behind it.
+    /// The default is `false`.
+    pub transparent: bool,
+
+    /// How to choose between several formats that match the requirements. Default is `Strict`.
+    pub ranking_policy: RankingPolicy,
+
+    /// If true, the multisampling and sRGB requirements are relaxed when no format satisfies
+    /// them. The default is `false`.
+    pub fallback: bool,
+
+    /// Native `(attribute, value)` pairs appended to the attribute list used to choose the
+    /// format (`eglChooseConfig`, `glXChooseFBConfig`, `wglChoosePixelFormatARB` or
+    /// `NSOpenGLPixelFormat`). The default is empty.
+    pub raw_attributes: Vec<(i32, i32)>,
+
+    /// Picks the pixel format to use instead of the built-in heuristic. Default is `None`.
+    pub(crate) config_selector: Option<ConfigSelector>,
+
+    /// Receives the diagnostics of the choice of the format. Default is `None`.
+    pub(crate) config_diagnostics: Option<ConfigDiagnostics>,
+}
+
+/// A user-supplied function that picks the pixel format to use among all the ones that match the
+/// requirements. See `ContextBuilder::with_config_selector`.
+#[derive(Clone)]
+pub(crate) struct ConfigSelector(Arc<Fn(&[PixelFormat]) -> usize + Send + Sync>);
+
+/// A user-supplied function receiving the diagnostics of the choice of the pixel format. See
+/// `ContextBuilder::with_config_diagnostics`.
+#[derive(Clone)]
+pub(crate) struct ConfigDiagnostics(Arc<Fn(&str) + Send + Sync>);
+
+// Whether `GLUTIN_DEBUG_CONFIGS` is set to `1`: 0 if the variable wasn't read yet, 1 if it
+// isn't set, 2 if it is.
+static DEBUG_CONFIGS: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns true if `GLUTIN_DEBUG_CONFIGS` is set to `1`, reading it the first time.
+fn debug_configs() -> bool {
+    match DEBUG_CONFIGS.load(Ordering::Relaxed) {
+        0 => {
+            let enabled = std::env::var("GLUTIN_DEBUG_CONFIGS").map_or(false, |value| value == "1");
+            DEBUG_CONFIGS.store(if enabled { 2 } else { 1 }, Ordering::Relaxed);
+            enabled
+        },
+        state => state == 2,
+    }
+}
+
+impl PixelFormatRequirements {
+    /// Returns the requirements to try in turn, starting with `self`, along with what was
+    /// relaxed in each of them. Only contains `self` unless `fallback` is enabled.
+    pub(crate) fn fallback_chain(&self) -> Vec<(PixelFormatRequirements, Vec<RelaxedRequirement>)> {
+        let mut samples = vec![self.multisampling];
+        if self.fallback {
+            if let Some(requested) = self.multisampling {
+                samples.extend([8, 4, 2, 0].iter().filter(|&&s| s < requested).map(|&s| Some(s)));
+            }
+        }
+
+        let mut srgb = vec![self.srgb];
+        match self.srgb {
+            SrgbRequirement::Require if self.fallback => srgb.push(SrgbRequirement::DontCare),
+            SrgbRequirement::Prefer => srgb.push(SrgbRequirement::DontCare),
+            _ => (),
+        }
+
+        let mut acceleration = vec![self.hardware_accelerated];
+        if self.hardware_accelerated == HardwareAcceleration::Prefer {
+            acceleration.push(HardwareAcceleration::DontCare);
+        }
+
+        let mut conformance = vec![self.conformance];
+        if self.conformance == Conformance::Prefer {
+            conformance.push(Conformance::DontCare);
+        }
+
+        let mut chain = Vec::with_capacity(conformance.len() * acceleration.len() *
+                                           samples.len() * srgb.len());
+        for &conformance in &conformance {
+            for &hardware_accelerated in &acceleration {
+                for &srgb in &srgb {
+                    for &multisampling in &samples {
+                        let mut relaxed = Vec::new();
+                        if multisampling != self.multisampling {
+                            relaxed.push(RelaxedRequirement::Multisampling(
+                                multisampling.unwrap_or(0)
+                            ));
+                        }
+                        if srgb != self.srgb {
+                            relaxed.push(RelaxedRequirement::Srgb);
+                        }
+                        if hardware_accelerated != self.hardware_accelerated {
+                            relaxed.push(RelaxedRequirement::HardwareAcceleration);
+                        }
+                        if conformance != self.conformance {
+                            relaxed.push(RelaxedRequirement::Conformance);
+                        }
+
+                        chain.push((PixelFormatRequirements {
+                            hardware_accelerated: hardware_accelerated,
+                            multisampling: multisampling,
+                            srgb: srgb,
+                            conformance: conformance,
+                            .. self.clone()
+                        }, relaxed));
+                    }
+                }
+            }
+        }
+        chain
+    }
+
+    /// Returns true if the backend must enumerate every matching format and call `select_format`,
+    /// instead of taking the one preferred by the driver.
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+    pub(crate) fn needs_all_formats(&self) -> bool {
+        self.config_selector.is_some() || self.ranking_policy != RankingPolicy::Strict ||
+            self.preferred_alpha_bits.is_some() || self.preferred_depth_bits.is_some() ||
+            self.preferred_stencil_bits.is_some() || self.native_visual_id.is_some() ||
+            self.rgb_bits.is_some() || self.logs_configs()
+    }
+
+    /// Returns the minimum number of bits of the red, green and blue channels, from `rgb_bits`
+    /// or else `color_bits`.
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd",
+              target_os = "macos"))]
+    pub(crate) fn min_rgb_bits(&self) -> Option<(u8, u8, u8)> {
+        self.rgb_bits.or_else(|| self.color_bits.map(channel_bits))
+    }
+
+    /// Returns the minimum number of bits of the color buffer, without alpha.
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    pub(crate) fn min_color_bits(&self) -> Option<u8> {
+        self.min_rgb_bits().map(|(red, green, blue)| red + green + blue)
+    }
+
+    /// Returns false if `rgb_bits` is set and the channels of `format` don't have exactly these
+    /// sizes. The drivers treat the sizes as minimums, so this is checked after choosing.
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+    pub(crate) fn matches_rgb_bits(&self, format: &PixelFormat) -> bool {
+        match self.rgb_bits {
+            Some(rgb) => rgb == (format.red_bits, format.green_bits, format.blue_bits),
+            None => true,
+        }
+    }
+
+    /// Returns true if the diagnostics of the choice of the format are wanted, either with
+    /// `ContextBuilder::with_config_diagnostics` or with `GLUTIN_DEBUG_CONFIGS=1`.
+    pub(crate) fn logs_configs(&self) -> bool {
+        self.config_diagnostics.is_some() || debug_configs()
+    }
+
+    /// Reports a diagnostic of the choice of the format, if they are wanted.
+    pub(crate) fn log_config(&self, message: &str) {
+        if let Some(ConfigDiagnostics(ref diagnostics)) = self.config_diagnostics {
+            diagnostics(message);
+        }
+        if debug_configs() {
+            eprintln!("glutin: {}", message);
+        }
+    }
+
+    /// Picks a format among the ones matching the requirements, which must be given in the order
+    /// of preference of the driver. Returns `None` if none is suitable.
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+    pub(crate) fn select_format(&self, formats: &[PixelFormat]) -> Option<usize> {
+        let eligible: Vec<usize> = (0 .. formats.len())
+            .filter(|&index| self.matches_rgb_bits(&formats[index]))
+            .collect();
+        let eligible_formats: Vec<PixelFormat> = eligible.iter()
+            .map(|&index| formats[index].clone())
+            .collect();
+        let selected = self.rank_formats(&eligible_formats).map(|index| eligible[index]);
+        if self.logs_configs() {
+            self.log_selection(formats, selected);
+        }
+        selected
+    }
+
+    /// Reports every format considered by `select_format`, and why it was rejected or chosen.
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+    fn log_selection(&self, formats: &[PixelFormat], selected: Option<usize>) {
+        self.log_config(&format!("{} pixel formats match the requirements {:?}", formats.len(),
+                                 self));
+        let best = formats.iter()
+            .filter(|format| self.matches_rgb_bits(format))
+            .map(|format| self.preference_distance(format))
+            .min();
+        for (index, format) in formats.iter().enumerate() {
+            let decision = if Some(index) == selected {
+                "chosen"
+            } else if !self.matches_rgb_bits(format) {
+                "rejected: the sizes of the channels differ from rgb_bits"
+            } else if self.config_selector.is_some() {
+                if selected.is_some() {
+                    "rejected: not picked by the config selector"
+                } else {
+                    "rejected: the config selector returned an out of bounds index"
+                }
+            } else if Some(self.preference_distance(format)) != best {
+                "rejected: further from the preferred alpha, depth and stencil sizes"
+            } else if self.ranking_policy == RankingPolicy::Strict {
+                "rejected: ranked lower by the driver"
+            } else {
+                "rejected: ranked lower by the ranking policy"
+            };
+            self.log_config(&format!("  #{} {}: {:?}", index, decision, format));
+        }
+    }
+
+    /// Picks a format the same way as `select_format`, without reporting anything.
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+    fn rank_formats(&self, formats: &[PixelFormat]) -> Option<usize> {
+        use std::cmp;
+
+        if let Some(ref selector) = self.config_selector {
+            return selector.select(formats);
+        }
+
+        // only the formats the closest to the preferred sizes are ranked
+        let best = match formats.iter().map(|format| self.preference_distance(format)).min() {
+            Some(best) => best,
+            None => return None,
+        };
+
+        // `min_by_key` returns the first of several equal elements, so ties keep the order of
+        // the driver.
+        let formats = formats.iter().enumerate()
+            .filter(|&(_, format)| self.preference_distance(format) == best);
+        match self.ranking_policy {
+            RankingPolicy::Strict => formats.map(|(index, _)| index).next(),
+            RankingPolicy::PreferPerformance => formats.min_by_key(|&(_, format)| {
+                (!format.hardware_accelerated, format.multisampling.unwrap_or(0),
+                 format.depth_bits as u16 + format.stencil_bits as u16,
+                 format.color_bits as u16 + format.alpha_bits as u16)
+            }).map(|(index, _)| index),
+            RankingPolicy::MinimizeMemory => formats.min_by_key(|&(_, format)| {
+                let bits = format.color_bits as u32 + format.alpha_bits as u32 +
+                           format.depth_bits as u32 + format.stencil_bits as u32;
+                let samples = cmp::max(format.multisampling.unwrap_or(0), 1) as u32;
+                let buffers = if format.double_buffer { 2 } else { 1 };
+                (bits * samples * buffers, !format.hardware_accelerated)
+            }).map(|(index, _)| index),
+            RankingPolicy::MaximizeQuality => formats.min_by_key(|&(_, format)| {
+                (!format.hardware_accelerated, cmp::Reverse(format.multisampling.unwrap_or(0)),
+                 cmp::Reverse(format.color_bits), cmp::Reverse(format.depth_bits),
+                 !format.srgb, cmp::Reverse(format.alpha_bits),
+                 cmp::Reverse(format.stencil_bits))
+            }).map(|(index, _)| index),
+        }
+    }
+
+    /// Returns the requirements that `format` fails.
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+    pub(crate) fn unmet_requirements(&self, format: &PixelFormat) -> Vec<UnmetRequirement> {
+        let mut unmet = Vec::new();
+        if let Some(hardware_accelerated) = self.hardware_accelerated.required() {
+            if format.hardware_accelerated != hardware_accelerated {
+                unmet.push(UnmetRequirement::HardwareAcceleration);
+            }
+        }
+        if self.rgb_bits.is_some() {
+            if !self.matches_rgb_bits(format) {
+                unmet.push(UnmetRequirement::ColorBits);
+            }
+        } else if let Some(color) = self.color_bits {
+            let (red, green, blue) = channel_bits(color);
+            if format.color_bits < color || format.red_bits < red || format.green_bits < green ||
+               format.blue_bits < blue
+            {
+                unmet.push(UnmetRequirement::ColorBits);
+            }
+        }
+        if format.alpha_bits < self.alpha_bits.unwrap_or(0) {
+            unmet.push(UnmetRequirement::AlphaBits);
+        }
+        if format.depth_bits < self.depth_bits.unwrap_or(0) {
+            unmet.push(UnmetRequirement::DepthBits);
+        }
+        if format.stencil_bits < self.stencil_bits.unwrap_or(0) {
+            unmet.push(UnmetRequirement::StencilBits);
+        }
+        if let Some(double_buffer) = self.double_buffer {
+            if format.double_buffer != double_buffer {
+                unmet.push(UnmetRequirement::DoubleBuffer);
+            }
+        }
+        match self.multisampling {
+            Some(0) if format.multisampling.unwrap_or(0) != 0 => {
+                unmet.push(UnmetRequirement::Multisampling);
+            },
+            Some(samples) if format.multisampling.unwrap_or(0) < samples => {
+                unmet.push(UnmetRequirement::Multisampling);
+            },
+            _ => (),
+        }
+        if format.stereoscopy != self.stereoscopy {
+            unmet.push(UnmetRequirement::Stereoscopy);
+        }
+        // a preference for sRGB isn't a requirement, since it is dropped by `fallback_chain`
+        match self.srgb {
+            SrgbRequirement::Require if !format.srgb => unmet.push(UnmetRequirement::Srgb),
+            SrgbRequirement::Disallow if format.srgb => unmet.push(UnmetRequirement::Srgb),
+            _ => (),
+        }
+        unmet
+    }
+
+    /// Returns the error to report when none of `formats`, all the formats of a display, matches
+    /// the requirements: `CreationError::NoMatchingPixelFormat` with the 3 formats that fail the
+    /// fewest requirements.
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+    pub(crate) fn no_matching_format(&self, formats: Vec<PixelFormat>) -> CreationError {
+        if self.logs_configs() {
+            self.log_config(&format!("no pixel format matches the requirements {:?}", self));
+            for (index, format) in formats.iter().enumerate() {
+                let unmet = self.unmet_requirements(format);
+                if unmet.is_empty() {
+                    self.log_config(&format!("  #{} rejected: wrong API or surface type: {:?}",
+                                             index, format));
+                } else {
+                    self.log_config(&format!("  #{} rejected: fails {:?}: {:?}", index, unmet,
+                                             format));
+                }
+            }
+        }
+
+        let mut nearest: Vec<_> = formats.into_iter()
+            .map(|format| NearestPixelFormat {
+                unmet_requirements: self.unmet_requirements(&format),
+                pixel_format: format,
+            })
+            // formats that fail none of these requirements were rejected for other reasons, such
+            // as the API or the type of surface, and aren't helpful
+            .filter(|nearest| !nearest.unmet_requirements.is_empty())
+            .collect();
+        // the sort is stable, so ties keep the order of the driver
+        nearest.sort_by_key(|nearest| nearest.unmet_requirements.len());
+        nearest.truncate(3);
+        CreationError::NoMatchingPixelFormat(nearest)
+    }
+
+    /// Returns how far the sizes of the buffers of `format` are from the preferred ones.
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+    fn preference_distance(&self, format: &PixelFormat) -> u32 {
+        let distance = |preferred: Option<u8>, bits: u8| {
+            preferred.map(|preferred| (preferred as i32 - bits as i32).abs() as u32).unwrap_or(0)
+        };
+        distance(self.preferred_alpha_bits, format.alpha_bits) +
+            distance(self.preferred_depth_bits, format.depth_bits) +
+            distance(self.preferred_stencil_bits, format.stencil_bits)
+    }
+}
+
+impl ConfigSelector {
+    /// Calls the function, returning `None` if the index it picked is out of bounds.
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+    pub(crate) fn select(&self, formats: &[PixelFormat]) -> Option<usize> {
+        if formats.is_empty() {
+            return None;
+        }
+        let index = (self.0)(formats);
+        if index < formats.len() { Some(index) } else { None }
+    }
+}
+
+impl std::fmt::Debug for ConfigSelector {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        formatter.write_str("ConfigSelector")
+    }
+}
+
+impl std::fmt::Debug for ConfigDiagnostics {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        formatter.write_str("ConfigDiagnostics")
+    }
 }
 
 impl Default for PixelFormatRequirements {
     #[inline]
     fn default() -> PixelFormatRequirements {
         PixelFormatRequirements {
-            hardware_accelerated: Some(true),
+            hardware_accelerated: HardwareAcceleration::Require,
+            software_rendering: false,
             color_bits: Some(24),
+            rgb_bits: None,
             float_color_buffer: false,
             alpha_bits: Some(8),
             depth_bits: Some(24),
             stencil_bits: Some(8),
+            preferred_alpha_bits: None,
+            preferred_depth_bits: None,
+            preferred_stencil_bits: None,
             double_buffer: None,
             multisampling: None,
+            coverage_samples: None,
             stereoscopy: false,
-            srgb: false,
-            release_behavior: ReleaseBehavior::Flush,
+            srgb: SrgbRequirement::DontCare,
+            conformance: Conformance::Require,
+            reject_caveats: false,
+            swap_behavior: SwapBehavior::Destroyed,
+            mutable_render_buffer: false,
+            multisample_resolve_box: false,
+            recordable: false,
+            native_visual_id: None,
+            transparent: false,
+            ranking_policy: RankingPolicy::Strict,
+            fallback: false,
+            raw_attributes: Vec::new(),
+            config_selector: None,
+            config_diagnostics: None,
         }
     }
 }
@@ -686,6 +3057,12 @@ pub struct GlAttributes<S> {
     /// The default is `None`.
     pub sharing: Option<S>,
 
+    /// A context created by another library to share the new context with. Takes precedence
+    /// over `sharing`.
+    ///
+    /// The default is `None`.
+    pub raw_sharing: Option<RawContextHandle>,
+
     /// Version to try create. See `GlRequest` for more infos.
     ///
     /// The default is `Latest`.
@@ -709,24 +3086,139 @@ pub struct GlAttributes<S> {
     /// OpenGL context. However for safety you should consider `TryRobustLoseContextOnReset`.
     pub robustness: Robustness,
 
+    /// Whether the context is flushed when it stops being current, with
+    /// `EGL_KHR_context_flush_control`, `GLX_ARB_context_flush_control` or
+    /// `WGL_ARB_context_flush_control`. `ReleaseBehavior::None` falls back to `Flush` when the
+    /// extension isn't supported.
+    ///
+    /// The default is `Flush`.
+    pub release_behavior: ReleaseBehavior,
+
+    /// The priority of the context, requested with `EGL_IMG_context_priority`. It is only a
+    /// hint, ignored by the other backends and by the implementations without the extension.
+    ///
+    /// The default is `None`, which leaves the default priority.
+    pub priority: Option<ContextPriority>,
+
     /// Whether to use vsync. If vsync is enabled, calling `swap_buffers` will block until the
     /// screen refreshes. This is typically used to prevent screen tearing.
     ///
     /// The default is `false`.
     pub vsync: bool,
+
+    /// The GLX extension to use to set the swap interval.
+    ///
+    /// The default is `None`, which picks the first available one.
+    pub glx_swap_control: Option<GlxSwapControl>,
+
+    /// Native `(attribute, value)` pairs appended to the attribute list used to create the
+    /// context (`eglCreateContext`, `glXCreateContextAttribsARB` or
+    /// `wglCreateContextAttribsARB`).
+    ///
+    /// The default is empty.
+    pub raw_context_attributes: Vec<(i32, i32)>,
+
+    /// Native `(attribute, value)` pairs appended to the attribute list used to create the
+    /// surface of the context with EGL (`eglCreateWindowSurface` or `eglCreatePbufferSurface`).
+    ///
+    /// The default is empty.
+    pub raw_surface_attributes: Vec<(i32, i32)>,
+
+    /// The colorspace of the surface of the context, only used by EGL. `None` leaves the choice
+    /// to the implementation, which is usually `Colorspace::Linear`.
+    ///
+    /// The default is `None`.
+    pub colorspace: Option<Colorspace>,
+
+    /// Whether the context and its surface are protected, so that they can render content
+    /// decoded into secure memory, such as DRM-protected video. Only supported by EGL with
+    /// `EGL_EXT_protected_content`.
+    ///
+    /// The default is `false`.
+    pub protected_content: bool,
+
+    /// Whether the context is created without error checking, with `GL_KHR_no_error`. Unlike
+    /// `Robustness::NoError`, the creation fails if it isn't supported.
+    ///
+    /// The default is `false`.
+    pub no_error: bool,
+
+    /// The function receiving the debug messages of the context, installed with
+    /// `glDebugMessageCallback` the first time the context is made current.
+    pub(crate) debug_callback: Option<DebugCallback>,
+}
+
+impl<'a, T: ContextCurrentState> GlAttributes<&'a Context<T>> {
+    /// Returns the share group of a context built with these attributes: the one of the context
+    /// it shares lists with, or a new one.
+    pub(crate) fn share_group(&self) -> Arc<()> {
+        match self.sharing {
+            Some(other) if self.raw_sharing.is_none() => other.share_group.clone(),
+            _ => Arc::new(()),
+        }
+    }
 }
 
 impl<S> GlAttributes<S> {
+    /// Replaces the `sharing` parameter, possibly changing its type.
+    #[inline]
+    pub fn set_sharing<T>(self, sharing: Option<T>) -> GlAttributes<T> {
+        GlAttributes {
+            sharing: sharing,
+            raw_sharing: self.raw_sharing,
+            version: self.version,
+            profile: self.profile,
+            debug: self.debug,
+            robustness: self.robustness,
+            release_behavior: self.release_behavior,
+            priority: self.priority,
+            vsync: self.vsync,
+            glx_swap_control: self.glx_swap_control,
+            raw_context_attributes: self.raw_context_attributes,
+            raw_surface_attributes: self.raw_surface_attributes,
+            colorspace: self.colorspace,
+            protected_content: self.protected_content,
+            no_error: self.no_error,
+            debug_callback: self.debug_callback,
+        }
+    }
+
+    /// Returns an error if a context without error checking was requested along with the debug
+    /// flag or a robustness, which `GL_KHR_no_error` forbids.
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+    pub(crate) fn check_no_error(&self) -> Result<(), CreationError> {
+        let robust = match self.robustness {
+            Robustness::NotRobust | Robustness::NoError => false,
+            _ => true,
+        };
+        if self.no_error && (self.debug || robust) {
+            return Err(CreationError::NotSupported("a context without error checking can't be \
+                                                    a debug or robust context"));
+        }
+        Ok(())
+    }
+
     /// Turns the `sharing` parameter into another type by calling a closure.
     #[inline]
     pub fn map_sharing<F, T>(self, f: F) -> GlAttributes<T> where F: FnOnce(S) -> T {
         GlAttributes {
             sharing: self.sharing.map(f),
+            raw_sharing: self.raw_sharing,
             version: self.version,
             profile: self.profile,
             debug: self.debug,
             robustness: self.robustness,
+            release_behavior: self.release_behavior,
+            priority: self.priority,
             vsync: self.vsync,
+            glx_swap_control: self.glx_swap_control,
+            raw_context_attributes: self.raw_context_attributes,
+            raw_surface_attributes: self.raw_surface_attributes,
+            colorspace: self.colorspace,
+            protected_content: self.protected_content,
+            no_error: self.no_error,
+            debug_callback: self.debug_callback,
         }
     }
 }
@@ -736,12 +3228,427 @@ impl<S> Default for GlAttributes<S> {
     fn default() -> GlAttributes<S> {
         GlAttributes {
             sharing: None,
+            raw_sharing: None,
             version: GlRequest::Latest,
             profile: None,
             debug: cfg!(debug_assertions),
             robustness: Robustness::NotRobust,
+            release_behavior: ReleaseBehavior::Flush,
+            priority: None,
             vsync: false,
+            glx_swap_control: None,
+            raw_context_attributes: Vec::new(),
+            raw_surface_attributes: Vec::new(),
+            colorspace: None,
+            protected_content: false,
+            no_error: false,
+            debug_callback: None,
         }
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::create_with_versions;
+    use Api;
+    use Conformance;
+    use CreationError;
+    use GlAttributes;
+    use GlRequest;
+    use HardwareAcceleration;
+    use PixelFormatRequirements;
+    use RelaxedRequirement;
+    use SrgbRequirement;
+
+    const VERSIONS: &'static [(Api, (u8, u8))] =
+        &[(Api::OpenGl, (4, 6)), (Api::OpenGl, (3, 3)), (Api::OpenGlEs, (3, 0))];
+
+    fn attributes() -> GlAttributes<()> {
+        GlAttributes { version: GlRequest::PreferredList(VERSIONS), .. Default::default() }
+    }
+
+    // returns the version requested from `create`
+    fn specific(gl_attr: &GlAttributes<()>) -> (Api, (u8, u8)) {
+        match gl_attr.version {
+            GlRequest::Specific(api, version) => (api, version),
+            ref request => panic!("create was called with {:?}", request),
+        }
+    }
+
+    #[test]
+    fn versions_are_tried_in_order() {
+        let mut attempts = Vec::new();
+        let result: Result<(), _> = create_with_versions(&attributes(), |gl_attr| {
+            attempts.push(specific(gl_attr));
+            Err(CreationError::OpenGlVersionNotSupported)
+        });
+        assert_eq!(attempts, VERSIONS);
+        match result {
+            Err(CreationError::OpenGlVersionNotSupported) => (),
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn first_success_is_returned() {
+        let mut attempts = Vec::new();
+        let result = create_with_versions(&attributes(), |gl_attr| {
+            attempts.push(specific(gl_attr));
+            match specific(gl_attr) {
+                (Api::OpenGl, (4, 6)) => Err(CreationError::OpenGlVersionNotSupported),
+                version => Ok(version),
+            }
+        });
+        assert_eq!(attempts, &VERSIONS[..2]);
+        assert_eq!(result.unwrap(), (Api::OpenGl, (3, 3)));
+    }
+
+    #[test]
+    fn first_informative_error_is_returned() {
+        let result: Result<(), _> = create_with_versions(&attributes(), |gl_attr| {
+            match specific(gl_attr) {
+                (Api::OpenGl, (4, 6)) => Err(CreationError::OpenGlVersionNotSupported),
+                (Api::OpenGl, (3, 3)) => Err(CreationError::NotSupported("first")),
+                _ => Err(CreationError::NotSupported("second")),
+            }
+        });
+        match result {
+            Err(CreationError::NotSupported("first")) => (),
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn other_requests_are_passed_through() {
+        let gl_attr = GlAttributes::<()> {
+            version: GlRequest::Specific(Api::OpenGlEs, (2, 0)),
+            .. Default::default()
+        };
+        let mut calls = 0;
+        let result = create_with_versions(&gl_attr, |gl_attr| {
+            calls += 1;
+            Ok(specific(gl_attr))
+        });
+        assert_eq!(calls, 1);
+        assert_eq!(result.unwrap(), (Api::OpenGlEs, (2, 0)));
+    }
+
+    #[test]
+    fn fallback_chain_without_fallback() {
+        let chain = PixelFormatRequirements::default().fallback_chain();
+        assert_eq!(chain.len(), 1);
+        assert!(chain[0].1.is_empty());
+
+        // the multisampling and a required sRGB are only relaxed with `fallback`
+        let reqs = PixelFormatRequirements {
+            multisampling: Some(8),
+            srgb: SrgbRequirement::Require,
+            .. Default::default()
+        };
+        assert_eq!(reqs.fallback_chain().len(), 1);
+
+        // the preferences are always relaxed
+        let reqs = PixelFormatRequirements {
+            srgb: SrgbRequirement::Prefer,
+            hardware_accelerated: HardwareAcceleration::Prefer,
+            .. Default::default()
+        };
+        let relaxed: Vec<_> = reqs.fallback_chain().into_iter().map(|(_, r)| r).collect();
+        assert_eq!(relaxed, [vec![], vec![RelaxedRequirement::Srgb],
+                             vec![RelaxedRequirement::HardwareAcceleration],
+                             vec![RelaxedRequirement::Srgb,
+                                  RelaxedRequirement::HardwareAcceleration]]);
+    }
+
+    #[test]
+    fn fallback_chain_lowers_the_samples_first() {
+        let reqs = PixelFormatRequirements {
+            multisampling: Some(6),
+            srgb: SrgbRequirement::Require,
+            fallback: true,
+            .. Default::default()
+        };
+        let chain = reqs.fallback_chain();
+        let attempts: Vec<_> = chain.iter()
+            .map(|&(ref reqs, _)| (reqs.multisampling, reqs.srgb))
+            .collect();
+        assert_eq!(attempts, [(Some(6), SrgbRequirement::Require),
+                              (Some(4), SrgbRequirement::Require),
+                              (Some(2), SrgbRequirement::Require),
+                              (Some(0), SrgbRequirement::Require),
+                              (Some(6), SrgbRequirement::DontCare),
+                              (Some(4), SrgbRequirement::DontCare),
+                              (Some(2), SrgbRequirement::DontCare),
+                              (Some(0), SrgbRequirement::DontCare)]);
+        assert!(chain[0].1.is_empty());
+        assert_eq!(chain[3].1, [RelaxedRequirement::Multisampling(0)]);
+        assert_eq!(chain[4].1, [RelaxedRequirement::Srgb]);
+        assert_eq!(chain[5].1, [RelaxedRequirement::Multisampling(4), RelaxedRequirement::Srgb]);
+    }
+
+    #[test]
+    fn fallback_chain_size() {
+        let reqs = PixelFormatRequirements {
+            multisampling: Some(16),
+            srgb: SrgbRequirement::Prefer,
+            hardware_accelerated: HardwareAcceleration::Prefer,
+            conformance: Conformance::Prefer,
+            fallback: true,
+            .. Default::default()
+        };
+        let chain = reqs.fallback_chain();
+        // 5 sample counts, with and without sRGB, acceleration and conformance
+        assert_eq!(chain.len(), 40);
+        assert_eq!(chain[39].0.multisampling, Some(0));
+        assert_eq!(chain[39].0.conformance, Conformance::DontCare);
+        assert_eq!(chain[39].1, [RelaxedRequirement::Multisampling(0), RelaxedRequirement::Srgb,
+                                 RelaxedRequirement::HardwareAcceleration,
+                                 RelaxedRequirement::Conformance]);
+        // the conformance is relaxed last
+        assert!(chain[..20].iter().all(|&(ref reqs, _)| reqs.conformance == Conformance::Prefer));
+    }
+
+    // the formats are only selected by glutin with the backends that enumerate them
+    #[cfg(any(target_os = "windows", target_os = "linux", target_os = "android",
+              target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+    mod selection {
+        use super::super::ConfigSelector;
+        use ConfigCaveat;
+        use Colorspace;
+        use CreationError;
+        use PixelFormat;
+        use PixelFormatRequirements;
+        use RankingPolicy;
+        use SrgbRequirement;
+        use UnmetRequirement;
+
+        use std::sync::Arc;
+
+        // an RGBA8 format with a 24 bits depth buffer and an 8 bits stencil buffer, which meets the
+        // default requirements
+        fn format() -> PixelFormat {
+            PixelFormat {
+                hardware_accelerated: true,
+                color_bits: 24,
+                red_bits: 8,
+                green_bits: 8,
+                blue_bits: 8,
+                alpha_bits: 8,
+                depth_bits: 24,
+                stencil_bits: 8,
+                stereoscopy: false,
+                double_buffer: true,
+                multisampling: None,
+                srgb: false,
+                native_id: None,
+                caveat: ConfigCaveat::None,
+                colorspace: Colorspace::Linear,
+                float_color_buffer: false,
+                transparent: false,
+            }
+        }
+
+        #[test]
+        fn unmet_requirements() {
+            let reqs = PixelFormatRequirements::default();
+            assert!(reqs.unmet_requirements(&format()).is_empty());
+
+            let format = PixelFormat { depth_bits: 16, stencil_bits: 0, .. format() };
+            assert_eq!(reqs.unmet_requirements(&format),
+                       [UnmetRequirement::DepthBits, UnmetRequirement::StencilBits]);
+
+            let format = PixelFormat { red_bits: 5, green_bits: 6, blue_bits: 5, color_bits: 16,
+                                       hardware_accelerated: false, .. format };
+            assert_eq!(reqs.unmet_requirements(&format),
+                       [UnmetRequirement::HardwareAcceleration, UnmetRequirement::ColorBits,
+                        UnmetRequirement::DepthBits, UnmetRequirement::StencilBits]);
+        }
+
+        #[test]
+        fn srgb_preference_is_not_a_requirement() {
+            let linear = format();
+            let srgb = PixelFormat { srgb: true, .. format() };
+
+            let reqs = PixelFormatRequirements {
+                srgb: SrgbRequirement::Prefer,
+                .. Default::default()
+            };
+            assert!(reqs.unmet_requirements(&linear).is_empty());
+            let reqs = PixelFormatRequirements { srgb: SrgbRequirement::Require, .. reqs };
+            assert_eq!(reqs.unmet_requirements(&linear), [UnmetRequirement::Srgb]);
+            assert!(reqs.unmet_requirements(&srgb).is_empty());
+            let reqs = PixelFormatRequirements { srgb: SrgbRequirement::Disallow, .. reqs };
+            assert_eq!(reqs.unmet_requirements(&srgb), [UnmetRequirement::Srgb]);
+        }
+
+        #[test]
+        fn nearest_formats_fail_the_fewest_requirements() {
+            let reqs = PixelFormatRequirements::default();
+            let formats = vec![
+                PixelFormat { alpha_bits: 0, depth_bits: 0, stencil_bits: 0, .. format() },
+                // rejected for another reason, such as the API
+                format(),
+                PixelFormat { depth_bits: 16, .. format() },
+                PixelFormat { alpha_bits: 0, stereoscopy: true, .. format() },
+                PixelFormat { stencil_bits: 0, .. format() },
+                PixelFormat { hardware_accelerated: false, .. format() },
+            ];
+
+            let nearest = match reqs.no_matching_format(formats) {
+                CreationError::NoMatchingPixelFormat(nearest) => nearest,
+                err => panic!("unexpected error {:?}", err),
+            };
+            // sorted by the number of unmet requirements, ties in the order of the driver, and
+            // truncated to 3
+            let unmet: Vec<_> = nearest.iter().map(|n| n.unmet_requirements.clone()).collect();
+            assert_eq!(unmet, [vec![UnmetRequirement::DepthBits],
+                               vec![UnmetRequirement::StencilBits],
+                               vec![UnmetRequirement::HardwareAcceleration]]);
+        }
+
+        #[test]
+        fn no_nearest_formats_without_unmet_requirements() {
+            let reqs = PixelFormatRequirements::default();
+            match reqs.no_matching_format(vec![format(), format()]) {
+                CreationError::NoMatchingPixelFormat(nearest) => assert!(nearest.is_empty()),
+                err => panic!("unexpected error {:?}", err),
+            }
+        }
+
+        fn ranked(policy: RankingPolicy, formats: &[PixelFormat]) -> Option<usize> {
+            let reqs = PixelFormatRequirements { ranking_policy: policy, .. Default::default() };
+            reqs.select_format(formats)
+        }
+
+        #[test]
+        fn strict_ranking_keeps_the_order_of_the_driver() {
+            let formats = [PixelFormat { hardware_accelerated: false, .. format() },
+                           PixelFormat { multisampling: Some(4), .. format() }];
+            assert_eq!(ranked(RankingPolicy::Strict, &formats), Some(0));
+            assert_eq!(ranked(RankingPolicy::Strict, &[]), None);
+        }
+
+        #[test]
+        fn performance_ranking() {
+            let formats = [PixelFormat { hardware_accelerated: false, .. format() },
+                           PixelFormat { multisampling: Some(4), .. format() },
+                           PixelFormat { depth_bits: 32, .. format() },
+                           format(),
+                           format()];
+            // accelerated, without multisampling and with the smallest buffers, first of the ties
+            assert_eq!(ranked(RankingPolicy::PreferPerformance, &formats), Some(3));
+            assert_eq!(ranked(RankingPolicy::PreferPerformance, &formats[..3]), Some(2));
+        }
+
+        #[test]
+        fn memory_ranking() {
+            let formats = [PixelFormat { multisampling: Some(4), .. format() },
+                           format(),
+                           PixelFormat { hardware_accelerated: false, .. format() },
+                           PixelFormat { double_buffer: false, .. format() },
+                           PixelFormat { double_buffer: false, .. format() }];
+            assert_eq!(ranked(RankingPolicy::MinimizeMemory, &formats), Some(3));
+            // equal sizes are ranked by acceleration
+            let formats = [PixelFormat { hardware_accelerated: false, .. format() }, format()];
+            assert_eq!(ranked(RankingPolicy::MinimizeMemory, &formats), Some(1));
+        }
+
+        #[test]
+        fn quality_ranking() {
+            let formats = [format(),
+                           PixelFormat { hardware_accelerated: false, multisampling: Some(8),
+                                         .. format() },
+                           PixelFormat { multisampling: Some(4), .. format() },
+                           PixelFormat { multisampling: Some(4), srgb: true, .. format() },
+                           PixelFormat { multisampling: Some(4), depth_bits: 32, .. format() }];
+            assert_eq!(ranked(RankingPolicy::MaximizeQuality, &formats), Some(4));
+            assert_eq!(ranked(RankingPolicy::MaximizeQuality, &formats[..4]), Some(3));
+            assert_eq!(ranked(RankingPolicy::MaximizeQuality, &formats[..3]), Some(2));
+        }
+
+        #[test]
+        fn rgb_bits_filter_maps_back_to_the_driver_indices() {
+            let ten_bits = PixelFormat { red_bits: 10, green_bits: 10, blue_bits: 10,
+                                         color_bits: 30, alpha_bits: 2, .. format() };
+            let formats = [format(),
+                           ten_bits.clone(),
+                           format(),
+                           PixelFormat { multisampling: Some(4), .. ten_bits.clone() }];
+            let reqs = PixelFormatRequirements {
+                rgb_bits: Some((10, 10, 10)),
+                .. Default::default()
+            };
+            assert_eq!(reqs.select_format(&formats), Some(1));
+            let reqs = PixelFormatRequirements { ranking_policy: RankingPolicy::MaximizeQuality,
+                                                 .. reqs };
+            assert_eq!(reqs.select_format(&formats), Some(3));
+            assert_eq!(reqs.select_format(&formats[..1]), None);
+        }
+
+        #[test]
+        fn config_selector_picks_among_the_eligible_formats() {
+            let ten_bits = PixelFormat { red_bits: 10, green_bits: 10, blue_bits: 10,
+                                         color_bits: 30, .. format() };
+            let formats = [format(), ten_bits.clone(), format(), ten_bits];
+            let reqs = PixelFormatRequirements {
+                rgb_bits: Some((10, 10, 10)),
+                config_selector: Some(ConfigSelector(Arc::new(|formats: &[PixelFormat]| {
+                    assert_eq!(formats.len(), 2);
+                    1
+                }))),
+                .. Default::default()
+            };
+            assert_eq!(reqs.select_format(&formats), Some(3));
+
+            // an out of bounds index selects nothing
+            let reqs = PixelFormatRequirements {
+                config_selector: Some(ConfigSelector(Arc::new(|formats: &[PixelFormat]| {
+                    formats.len()
+                }))),
+                .. Default::default()
+            };
+            assert_eq!(reqs.select_format(&formats), None);
+        }
+
+        #[test]
+        fn preferred_sizes_pick_the_closest_format() {
+            let formats = [format(),
+                           PixelFormat { depth_bits: 16, stencil_bits: 0, .. format() },
+                           PixelFormat { depth_bits: 32, stencil_bits: 0, .. format() }];
+            let reqs = PixelFormatRequirements {
+                depth_bits: None,
+                stencil_bits: None,
+                preferred_depth_bits: Some(16),
+                preferred_stencil_bits: Some(0),
+                .. Default::default()
+            };
+            assert_eq!(reqs.preference_distance(&formats[0]), 16);
+            assert_eq!(reqs.preference_distance(&formats[1]), 0);
+            assert_eq!(reqs.select_format(&formats), Some(1));
+
+            // the distance is absolute, so larger buffers are as far as smaller ones
+            let reqs = PixelFormatRequirements { preferred_depth_bits: Some(28), .. reqs };
+            assert_eq!(reqs.preference_distance(&formats[0]), 12);
+            assert_eq!(reqs.preference_distance(&formats[2]), 4);
+            assert_eq!(reqs.select_format(&formats), Some(2));
+        }
+
+        #[test]
+        fn preferred_sizes_are_ranked_before_the_policy() {
+            let formats = [PixelFormat { alpha_bits: 8, multisampling: Some(4), .. format() },
+                           PixelFormat { alpha_bits: 0, .. format() },
+                           PixelFormat { alpha_bits: 0, multisampling: Some(4), .. format() }];
+            let reqs = PixelFormatRequirements {
+                alpha_bits: None,
+                preferred_alpha_bits: Some(0),
+                ranking_policy: RankingPolicy::MaximizeQuality,
+                .. Default::default()
+            };
+            // the first format has the most samples but isn't the closest to the preferred size
+            assert_eq!(reqs.select_format(&formats), Some(2));
+            let reqs = PixelFormatRequirements { ranking_policy: RankingPolicy::Strict, .. reqs };
+            assert_eq!(reqs.select_format(&formats), Some(1));
+        }
+    }
+}
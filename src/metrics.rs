@@ -0,0 +1,181 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Statistics about the swaps and `make_current` calls of a context, as returned by
+/// `Context::metrics`.
+///
+/// Only the calls made while the metrics were enabled with `Context::set_metrics_enabled` are
+/// counted.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ContextMetrics {
+    /// The number of times the buffers were swapped.
+    pub swap_count: u64,
+    /// The time spent in the last swap, including the time the driver blocked for vsync.
+    pub last_swap_duration: Option<Duration>,
+    /// The longest time spent in a swap.
+    pub max_swap_duration: Duration,
+    /// The total time spent in swaps. Divide by `swap_count` to get the average.
+    pub total_swap_duration: Duration,
+    /// The time between the starts of the last two swaps, which is the duration of the last
+    /// frame.
+    pub last_frame_time: Option<Duration>,
+    /// The longest time between the starts of two consecutive swaps.
+    pub max_frame_time: Duration,
+    /// The number of times the context was made current, including by `make_current_scoped`
+    /// and `make_current_surfaces`.
+    pub make_current_count: u64,
+}
+
+/// Records the metrics of a context when they are enabled. Used by `Context::metrics`.
+pub(crate) struct MetricsRecorder {
+    // `None` while the metrics are disabled.
+    state: Mutex<Option<RecorderState>>,
+}
+
+struct RecorderState {
+    metrics: ContextMetrics,
+    last_swap_start: Option<Instant>,
+}
+
+impl MetricsRecorder {
+    pub(crate) fn new() -> MetricsRecorder {
+        MetricsRecorder { state: Mutex::new(None) }
+    }
+
+    /// Starts or stops recording. Enabling the metrics when they already are doesn't reset them.
+    pub(crate) fn set_enabled(&self, enabled: bool) {
+        let mut state = self.state.lock().unwrap();
+        match (state.is_some(), enabled) {
+            (false, true) => {
+                *state = Some(RecorderState {
+                    metrics: Default::default(),
+                    last_swap_start: None,
+                });
+            },
+            (true, false) => *state = None,
+            _ => (),
+        }
+    }
+
+    /// Returns the metrics recorded so far, or `None` if they are disabled.
+    pub(crate) fn metrics(&self) -> Option<ContextMetrics> {
+        self.state.lock().unwrap().as_ref().map(|state| state.metrics.clone())
+    }
+
+    /// Clears the metrics recorded so far, if they are enabled.
+    pub(crate) fn reset(&self) {
+        if let Some(ref mut state) = *self.state.lock().unwrap() {
+            state.metrics = Default::default();
+            state.last_swap_start = None;
+        }
+    }
+
+    /// Calls `swap`, and records how long it took if it succeeded.
+    pub(crate) fn time_swap<F, R, E>(&self, swap: F) -> Result<R, E>
+        where F: FnOnce() -> Result<R, E>
+    {
+        // the clock isn't read at all when the metrics are disabled
+        if self.state.lock().unwrap().is_none() {
+            return swap();
+        }
+
+        let start = Instant::now();
+        let result = swap();
+        let end = Instant::now();
+
+        if result.is_ok() {
+            if let Some(ref mut state) = *self.state.lock().unwrap() {
+                let duration = end - start;
+                let metrics = &mut state.metrics;
+                metrics.swap_count += 1;
+                metrics.last_swap_duration = Some(duration);
+                metrics.max_swap_duration = metrics.max_swap_duration.max(duration);
+                metrics.total_swap_duration += duration;
+
+                if let Some(last_swap_start) = state.last_swap_start {
+                    let frame_time = start - last_swap_start;
+                    metrics.last_frame_time = Some(frame_time);
+                    metrics.max_frame_time = metrics.max_frame_time.max(frame_time);
+                }
+                state.last_swap_start = Some(start);
+            }
+        }
+        result
+    }
+
+    /// Records that the context was made current.
+    pub(crate) fn on_make_current(&self) {
+        if let Some(ref mut state) = *self.state.lock().unwrap() {
+            state.metrics.make_current_count += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MetricsRecorder;
+
+    use std::thread;
+    use std::time::Duration;
+
+    fn swap(recorder: &MetricsRecorder, succeeds: bool) {
+        let _ = recorder.time_swap(|| if succeeds { Ok(()) } else { Err(()) });
+    }
+
+    #[test]
+    fn disabled_metrics_record_nothing() {
+        let recorder = MetricsRecorder::new();
+        swap(&recorder, true);
+        recorder.on_make_current();
+        assert_eq!(recorder.metrics(), None);
+    }
+
+    #[test]
+    fn failed_swaps_are_not_counted() {
+        let recorder = MetricsRecorder::new();
+        recorder.set_enabled(true);
+        swap(&recorder, false);
+        assert_eq!(recorder.metrics().unwrap().swap_count, 0);
+        assert_eq!(recorder.metrics().unwrap().last_swap_duration, None);
+        swap(&recorder, true);
+        swap(&recorder, false);
+        assert_eq!(recorder.metrics().unwrap().swap_count, 1);
+        assert_eq!(recorder.time_swap(|| Err::<(), _>(7)), Err(7));
+    }
+
+    #[test]
+    fn frame_time_needs_two_swaps() {
+        let recorder = MetricsRecorder::new();
+        recorder.set_enabled(true);
+        swap(&recorder, true);
+        let metrics = recorder.metrics().unwrap();
+        assert!(metrics.last_swap_duration.is_some());
+        assert_eq!(metrics.last_frame_time, None);
+
+        thread::sleep(Duration::from_millis(2));
+        swap(&recorder, true);
+        let metrics = recorder.metrics().unwrap();
+        assert!(metrics.last_frame_time.unwrap() >= Duration::from_millis(2));
+        assert_eq!(metrics.max_frame_time, metrics.last_frame_time.unwrap());
+        assert_eq!(metrics.swap_count, 2);
+    }
+
+    #[test]
+    fn enabling_twice_keeps_the_metrics() {
+        let recorder = MetricsRecorder::new();
+        recorder.set_enabled(true);
+        swap(&recorder, true);
+        recorder.on_make_current();
+        recorder.set_enabled(true);
+        let metrics = recorder.metrics().unwrap();
+        assert_eq!((metrics.swap_count, metrics.make_current_count), (1, 1));
+
+        // disabling forgets them, and reset clears them
+        recorder.set_enabled(false);
+        recorder.set_enabled(true);
+        assert_eq!(recorder.metrics().unwrap().swap_count, 0);
+        swap(&recorder, true);
+        recorder.reset();
+        assert_eq!(recorder.metrics(), Some(Default::default()));
+    }
+}
@@ -0,0 +1,353 @@
+//! A context that doesn't use any GPU, for the unit tests of code built on `GlContext` and
+//! `ContextTrait`. Requires the `mock` feature.
+//!
+//! A `MockContext` records the calls made on it, so that tests can check for example that a
+//! frame was swapped or that the context was resized when the window was, on machines without
+//! any OpenGL driver.
+//!
+//! ```
+//! # extern crate glutin;
+//! use glutin::{ContextTrait, GlContext};
+//! use glutin::mock::MockContext;
+//!
+//! # fn main() {
+//! let context = unsafe { MockContext::new().make_current().unwrap() };
+//! assert!(context.is_current());
+//! context.swap_buffers().unwrap();
+//! assert_eq!(context.swap_count(), 1);
+//! # }
+//! ```
+
+use Api;
+use Colorspace;
+use ConfigCaveat;
+use ContextCurrentState;
+use ContextError;
+use ContextTrait;
+use GlContext;
+use NotCurrent;
+use PixelFormat;
+use PossiblyCurrent;
+
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The identifier of the next mock context, so that each one can be told apart in `CURRENT`.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    // The identifier of the mock context that is current on this thread, or 0.
+    static CURRENT: Cell<usize> = Cell::new(0);
+}
+
+/// The function that every address returned by `get_proc_address` points to. It does nothing
+/// and returns 0, which is read as a null pointer, `GL_NO_ERROR` or `GL_FALSE` by the callers
+/// expecting a value.
+extern "system" fn stub_function() -> usize {
+    0
+}
+
+/// The functions returning a pointer to data, which are unavailable from a mock context since
+/// their callers read the data, including the helpers of glutin such as `AsyncReadback` and
+/// `Context::flags`.
+const POINTER_GETTERS: &'static [&'static str] = &[
+    "glGetString", "glGetStringi", "glMapBuffer", "glMapBufferOES", "glMapBufferRange",
+    "glMapNamedBuffer", "glMapNamedBufferRange",
+];
+
+/// The calls recorded by a mock context, shared by all its states.
+#[derive(Default)]
+struct Recorder {
+    swap_count: u64,
+    make_current_count: u64,
+    dimensions: Option<(u32, u32)>,
+    requested_functions: Vec<String>,
+}
+
+/// The configuration of a mock context, set with the `with_*` methods.
+#[derive(Clone)]
+struct Settings {
+    api: Api,
+    pixel_format: PixelFormat,
+    unavailable_functions: HashSet<String>,
+    fail_swaps: bool,
+}
+
+/// A context that doesn't render anything, and records the calls made on it instead.
+///
+/// The addresses returned by `get_proc_address` all point to a function that does nothing and
+/// returns 0, so `glGetError` reports no error and `glFenceSync` fails for example. The
+/// functions returning a pointer to data, such as `glGetString` and `glMapBufferRange`, aren't
+/// available, and neither are the functions given to `with_unavailable_functions`, to test
+/// fallbacks.
+pub struct MockContext<T: ContextCurrentState> {
+    id: usize,
+    settings: Arc<Settings>,
+    recorder: Arc<Mutex<Recorder>>,
+    phantom: PhantomData<T>,
+}
+
+impl MockContext<NotCurrent> {
+    /// Builds a mock context of desktop OpenGL, with a double buffered RGBA8 pixel format that
+    /// has a 24 bits depth buffer and an 8 bits stencil buffer.
+    pub fn new() -> MockContext<NotCurrent> {
+        MockContext {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            settings: Arc::new(Settings {
+                api: Api::OpenGl,
+                pixel_format: PixelFormat {
+                    hardware_accelerated: true,
+                    color_bits: 24,
+                    red_bits: 8,
+                    green_bits: 8,
+                    blue_bits: 8,
+                    alpha_bits: 8,
+                    depth_bits: 24,
+                    stencil_bits: 8,
+                    stereoscopy: false,
+                    double_buffer: true,
+                    multisampling: None,
+                    srgb: false,
+                    native_id: None,
+                    caveat: ConfigCaveat::None,
+                    colorspace: Colorspace::Linear,
+                    float_color_buffer: false,
+                    transparent: false,
+                },
+                unavailable_functions: HashSet::new(),
+                fail_swaps: false,
+            }),
+            recorder: Arc::new(Mutex::new(Default::default())),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets the API returned by `get_api`.
+    pub fn with_api(mut self, api: Api) -> MockContext<NotCurrent> {
+        Arc::make_mut(&mut self.settings).api = api;
+        self
+    }
+
+    /// Sets the pixel format returned by `get_pixel_format`.
+    pub fn with_pixel_format(mut self, pixel_format: PixelFormat) -> MockContext<NotCurrent> {
+        Arc::make_mut(&mut self.settings).pixel_format = pixel_format;
+        self
+    }
+
+    /// Sets the functions for which `get_proc_address` returns a null pointer.
+    pub fn with_unavailable_functions(mut self, functions: &[&str]) -> MockContext<NotCurrent> {
+        Arc::make_mut(&mut self.settings).unavailable_functions =
+            functions.iter().map(|&name| name.to_owned()).collect();
+        self
+    }
+
+    /// Sets whether `swap_buffers` fails with `ContextError::ContextLost`, to test how errors
+    /// are handled. The failed swaps aren't counted.
+    pub fn with_failing_swaps(mut self, fail: bool) -> MockContext<NotCurrent> {
+        Arc::make_mut(&mut self.settings).fail_swaps = fail;
+        self
+    }
+}
+
+impl Default for MockContext<NotCurrent> {
+    fn default() -> MockContext<NotCurrent> {
+        MockContext::new()
+    }
+}
+
+impl<T: ContextCurrentState> MockContext<T> {
+    /// Sets the context as the current context on the calling thread. Never fails.
+    pub unsafe fn make_current(self)
+        -> Result<MockContext<PossiblyCurrent>, (Self, ContextError)>
+    {
+        CURRENT.with(|current| current.set(self.id));
+        self.recorder.lock().unwrap().make_current_count += 1;
+        Ok(self.with_state())
+    }
+
+    /// If this context is current on the calling thread, releases it. Never fails.
+    pub unsafe fn make_not_current(self)
+        -> Result<MockContext<NotCurrent>, (Self, ContextError)>
+    {
+        CURRENT.with(|current| {
+            if current.get() == self.id {
+                current.set(0);
+            }
+        });
+        Ok(self.with_state())
+    }
+
+    /// Returns true if this context is the current one in this thread.
+    pub fn is_current(&self) -> bool {
+        CURRENT.with(|current| current.get() == self.id)
+    }
+
+    /// Returns the API set with `with_api`.
+    pub fn get_api(&self) -> Api {
+        self.settings.api
+    }
+
+    /// Returns the pixel format set with `with_pixel_format`.
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        self.settings.pixel_format.clone()
+    }
+
+    /// Returns the number of successful calls to `swap_buffers`.
+    pub fn swap_count(&self) -> u64 {
+        self.recorder.lock().unwrap().swap_count
+    }
+
+    /// Returns the number of calls to `make_current`.
+    pub fn make_current_count(&self) -> u64 {
+        self.recorder.lock().unwrap().make_current_count
+    }
+
+    /// Returns the dimensions passed to the last call to `resize`, if any.
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        self.recorder.lock().unwrap().dimensions
+    }
+
+    /// Returns the names passed to `get_proc_address`, in order, including the unavailable ones.
+    pub fn requested_functions(&self) -> Vec<String> {
+        self.recorder.lock().unwrap().requested_functions.clone()
+    }
+
+    fn with_state<T2: ContextCurrentState>(self) -> MockContext<T2> {
+        MockContext {
+            id: self.id,
+            settings: self.settings,
+            recorder: self.recorder,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl GlContext for MockContext<PossiblyCurrent> {
+    fn is_current(&self) -> bool {
+        MockContext::is_current(self)
+    }
+
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        self.recorder.lock().unwrap().requested_functions.push(addr.to_owned());
+        if self.settings.unavailable_functions.contains(addr) || POINTER_GETTERS.contains(&addr) {
+            return ::std::ptr::null();
+        }
+        stub_function as *const ()
+    }
+
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        if self.settings.fail_swaps {
+            return Err(ContextError::ContextLost);
+        }
+        self.recorder.lock().unwrap().swap_count += 1;
+        Ok(())
+    }
+
+    fn get_api(&self) -> Api {
+        self.settings.api
+    }
+
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.settings.pixel_format.clone()
+    }
+
+    fn resize(&self, width: u32, height: u32) {
+        self.recorder.lock().unwrap().dimensions = Some((width, height));
+    }
+}
+
+impl<T: ContextCurrentState> ContextTrait for MockContext<T> {
+    type PossiblyCurrent = MockContext<PossiblyCurrent>;
+    type NotCurrent = MockContext<NotCurrent>;
+
+    unsafe fn make_current(self) -> Result<Self::PossiblyCurrent, (Self, ContextError)> {
+        MockContext::make_current(self)
+    }
+
+    unsafe fn make_not_current(self) -> Result<Self::NotCurrent, (Self, ContextError)> {
+        MockContext::make_not_current(self)
+    }
+
+    fn is_current(&self) -> bool {
+        MockContext::is_current(self)
+    }
+
+    fn get_api(&self) -> Api {
+        MockContext::get_api(self)
+    }
+
+    fn get_pixel_format(&self) -> PixelFormat {
+        MockContext::get_pixel_format(self)
+    }
+}
+
+impl<T: ContextCurrentState> ::std::fmt::Debug for MockContext<T> {
+    fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        formatter.debug_struct("MockContext")
+            .field("api", &self.get_api())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MockContext;
+    use {Api, ContextError, ContextTrait, GlContext};
+
+    #[test]
+    fn calls_are_recorded() {
+        let context = unsafe { MockContext::new().make_current().unwrap() };
+        assert!(context.is_current());
+        assert_eq!(context.make_current_count(), 1);
+        context.swap_buffers().unwrap();
+        context.swap_buffers().unwrap();
+        assert_eq!(context.swap_count(), 2);
+        assert_eq!(context.dimensions(), None);
+        context.resize(640, 480);
+        assert_eq!(context.dimensions(), Some((640, 480)));
+
+        let context = unsafe { context.make_not_current().unwrap() };
+        assert!(!ContextTrait::is_current(&context));
+        assert_eq!(context.swap_count(), 2);
+    }
+
+    #[test]
+    fn only_one_context_is_current() {
+        let first = unsafe { MockContext::new().make_current().unwrap() };
+        let second = unsafe { MockContext::new().make_current().unwrap() };
+        assert!(!first.is_current());
+        assert!(second.is_current());
+        // releasing a context that isn't current leaves the current one alone
+        let _first = unsafe { first.make_not_current().unwrap() };
+        assert!(second.is_current());
+    }
+
+    #[test]
+    fn functions_are_recorded() {
+        let context = unsafe {
+            MockContext::new().with_api(Api::OpenGlEs)
+                .with_unavailable_functions(&["glFenceSync"])
+                .make_current()
+                .unwrap()
+        };
+        assert_eq!(GlContext::get_api(&context), Api::OpenGlEs);
+        assert!(!context.get_proc_address("glClear").is_null());
+        assert!(context.get_proc_address("glFenceSync").is_null());
+        assert!(context.get_proc_address("glGetString").is_null());
+        assert_eq!(context.requested_functions(), ["glClear", "glFenceSync", "glGetString"]);
+    }
+
+    #[test]
+    fn failing_swaps_are_not_counted() {
+        let context = MockContext::new().with_failing_swaps(true);
+        let context = unsafe { context.make_current().unwrap() };
+        match context.swap_buffers() {
+            Err(ContextError::ContextLost) => (),
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert_eq!(context.swap_count(), 0);
+    }
+}
@@ -1,13 +1,17 @@
 #![cfg(any(target_os = "android"))]
 
+#[cfg(feature = "winit")]
 pub use winit::os::android::{WindowBuilderExt, WindowExt};
 
 pub use api::egl::ffi::EGLContext;
+pub use platform::NativeWindowFormat;
 
-use {Context, HeadlessContext};
+use {Context, ContextBuilder, ContextCurrentState, ContextError, HeadlessContext, PossiblyCurrent};
 use os::GlContextExt;
 
-impl GlContextExt for Context {
+use std::time::Duration;
+
+impl<T: ContextCurrentState> GlContextExt for Context<T> {
     type Handle = EGLContext;
 
     #[inline]
@@ -16,7 +20,7 @@ impl GlContextExt for Context {
     }
 }
 
-impl GlContextExt for HeadlessContext {
+impl<T: ContextCurrentState> GlContextExt for HeadlessContext<T> {
     type Handle = EGLContext;
 
     #[inline]
@@ -24,3 +28,40 @@ impl GlContextExt for HeadlessContext {
         self.context.raw_handle()
     }
 }
+
+/// Additional methods for contexts on Android.
+pub trait ContextExt {
+    /// Sets the time at which the frame submitted by the next call to `swap_buffers` should be
+    /// presented, so that frames can be scheduled relative to vsync.
+    ///
+    /// The time is measured on the same monotonic clock as `System.nanoTime()` and the values
+    /// returned by `Context::presentation_time`. Requires `EGL_ANDROID_presentation_time`,
+    /// otherwise `ContextError::FunctionUnavailable` is returned.
+    fn set_presentation_time(&self, time: Duration) -> Result<(), ContextError>;
+}
+
+impl ContextExt for Context<PossiblyCurrent> {
+    #[inline]
+    fn set_presentation_time(&self, time: Duration) -> Result<(), ContextError> {
+        self.context.set_presentation_time(time)
+    }
+}
+
+/// Additional methods to build contexts on Android.
+pub trait ContextBuilderExt {
+    /// Forces the format of the buffers of the window, and only considers the EGL configs whose
+    /// native visual is that format.
+    ///
+    /// By default, a config matching the current format of the window is preferred, and the
+    /// window is given the format of the chosen config, since a mismatch can cause a slow
+    /// conversion or a black screen on some devices.
+    fn with_native_window_format(self, format: NativeWindowFormat) -> Self;
+}
+
+impl<'a, T: ContextCurrentState> ContextBuilderExt for ContextBuilder<'a, T> {
+    #[inline]
+    fn with_native_window_format(mut self, format: NativeWindowFormat) -> Self {
+        self.pf_reqs.native_visual_id = Some(format.native_visual_id());
+        self
+    }
+}
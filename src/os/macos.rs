@@ -1,16 +1,20 @@
 #![cfg(target_os = "macos")]
 
+#[cfg(feature = "winit")]
 pub use winit::os::macos::ActivationPolicy;
+#[cfg(feature = "winit")]
 pub use winit::os::macos::MonitorIdExt;
+#[cfg(feature = "winit")]
 pub use winit::os::macos::WindowBuilderExt;
+#[cfg(feature = "winit")]
 pub use winit::os::macos::WindowExt;
 
-use {Context, HeadlessContext};
+use {Context, ContextCurrentState, ContextError, HeadlessContext};
 use os::GlContextExt;
 
 use std::os::raw::c_void;
 
-impl GlContextExt for Context {
+impl<T: ContextCurrentState> GlContextExt for Context<T> {
     type Handle = *mut c_void;
 
     #[inline]
@@ -19,7 +23,7 @@ impl GlContextExt for Context {
     }
 }
 
-impl GlContextExt for HeadlessContext {
+impl<T: ContextCurrentState> GlContextExt for HeadlessContext<T> {
     type Handle = *mut c_void;
 
     #[inline]
@@ -27,3 +31,54 @@ impl GlContextExt for HeadlessContext {
         self.context.raw_handle()
     }
 }
+
+/// Additional methods for contexts on macOS.
+///
+/// The swap interval of `NSOpenGLContext` paces frames poorly. These methods use a
+/// `CVDisplayLink`, which is created the first time one of them is called and follows the display
+/// the window of the context is on.
+pub trait ContextExt {
+    /// Blocks until the next vertical blank of the display the context is on.
+    fn wait_for_vsync(&self) -> Result<(), ContextError>;
+
+    /// Sets a function called on every vertical blank of the display the context is on, or
+    /// removes it if `callback` is `None`.
+    ///
+    /// The function is called on a thread owned by CoreVideo and must return quickly. The display
+    /// is only updated when this method or `wait_for_vsync` is called, so it should be called
+    /// again after the window moves to another screen.
+    fn set_vsync_callback(&self, callback: Option<Box<FnMut() + Send>>)
+                          -> Result<(), ContextError>;
+}
+
+impl<T: ContextCurrentState> ContextExt for Context<T> {
+    #[inline]
+    fn wait_for_vsync(&self) -> Result<(), ContextError> {
+        self.context.wait_for_vsync()
+    }
+
+    #[inline]
+    fn set_vsync_callback(&self, callback: Option<Box<FnMut() + Send>>)
+                          -> Result<(), ContextError>
+    {
+        self.context.set_vsync_callback(callback)
+    }
+}
+
+/// Additional methods for headless contexts on macOS.
+pub trait HeadlessContextExt {
+    /// Returns the name of the framebuffer object that the context renders to.
+    ///
+    /// CGL contexts without a view have no default framebuffer, so headless contexts render to a
+    /// framebuffer object of the requested dimensions, which is bound when the context is made
+    /// current. Binding framebuffer 0 renders nowhere, so this name should be bound again
+    /// instead. Returns `None` if the context was built with `build_surfaceless`.
+    fn framebuffer(&self) -> Option<u32>;
+}
+
+impl<T: ContextCurrentState> HeadlessContextExt for HeadlessContext<T> {
+    #[inline]
+    fn framebuffer(&self) -> Option<u32> {
+        self.context.framebuffer()
+    }
+}
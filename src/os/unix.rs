@@ -2,20 +2,36 @@
 
 pub use api::egl::ffi::EGLContext;
 pub use api::glx::ffi::GLXContext;
-pub use platform::RawHandle;
+pub use platform::{DrmDevice, DrmDeviceInfo, EglDevice, HeadlessBackend, OsMesaDepthBuffer};
+pub use platform::{KmsConnector, KmsContext, KmsMode, OsMesaFormat, RawHandle};
 
+#[cfg(feature = "winit")]
 pub use winit::os::unix::XNotSupported;
+#[cfg(feature = "winit")]
+pub use winit::os::unix::x11::XConnection;
+#[cfg(feature = "winit")]
 pub use winit::os::unix::EventsLoopExt;
+#[cfg(feature = "winit")]
 pub use winit::os::unix::MonitorIdExt;
+#[cfg(feature = "winit")]
 pub use winit::os::unix::WindowBuilderExt;
+#[cfg(feature = "winit")]
 pub use winit::os::unix::WindowExt;
 
-use {Context, HeadlessContext};
+use {Context, ContextBuilder, ContextCurrentState, ContextError, CreationError, FrameId};
+use {GlxSwapControl, HeadlessContext, HeadlessRendererBuilder, NotCurrent, PresentationFeedback};
+use {Display, PossiblyCurrent, RawContext, RawWindowHandle};
+use create_with_fallbacks;
+use create_with_versions;
 use os::GlContextExt;
+use platform;
 
 use std::os::raw::c_void;
+#[cfg(feature = "winit")]
+use std::os::raw;
+use std::sync::Arc;
 
-impl GlContextExt for Context {
+impl<T: ContextCurrentState> GlContextExt for Context<T> {
     type Handle = RawHandle;
 
     #[inline]
@@ -24,7 +40,7 @@ impl GlContextExt for Context {
     }
 }
 
-impl GlContextExt for HeadlessContext {
+impl<T: ContextCurrentState> GlContextExt for HeadlessContext<T> {
     type Handle = *mut c_void;
 
     #[inline]
@@ -32,3 +48,314 @@ impl GlContextExt for HeadlessContext {
         self.context.raw_handle()
     }
 }
+
+/// Additional methods for contexts on Unix-like platforms.
+pub trait ContextExt {
+    /// Returns the GLX extension used to set the swap interval, chosen when the context was built
+    /// or forced with `ContextBuilder::with_glx_swap_control`. Returns `None` if the context
+    /// doesn't use GLX or if no extension is available.
+    fn glx_swap_control(&self) -> Option<GlxSwapControl>;
+
+    /// Returns when and how the frame identified by `frame` was presented, as reported by the X
+    /// Present extension or by the `presentation-time` protocol of Wayland. Returns `None` if the
+    /// frame was not presented yet, if it was discarded by the Wayland compositor, or if it is
+    /// too old to be remembered.
+    ///
+    /// When they are available, the Present extension and the `presentation-time` protocol are
+    /// also used by `swap_buffers_with_frame_id`, `try_swap_buffers` and `presentation_time`
+    /// instead of `GLX_OML_sync_control` or `EGL_ANDROID_get_frame_timestamps`. On Wayland, the
+    /// times are given by the clock chosen by the compositor, which is the monotonic clock in
+    /// practice.
+    ///
+    /// On X11, the completion events are read from the event queue of the X11 connection, so
+    /// frames stay pending if the event loop of the window consumes them first. Returns
+    /// `ContextError::FunctionUnavailable` if `libXpresent` or the extension are not available,
+    /// or if the Wayland compositor doesn't support `wp_presentation`.
+    fn presentation_feedback(&self, frame: FrameId)
+                             -> Result<Option<PresentationFeedback>, ContextError>;
+}
+
+impl<T: ContextCurrentState> ContextExt for Context<T> {
+    #[inline]
+    fn glx_swap_control(&self) -> Option<GlxSwapControl> {
+        self.context.glx_swap_control()
+    }
+
+    #[inline]
+    fn presentation_feedback(&self, frame: FrameId)
+                             -> Result<Option<PresentationFeedback>, ContextError>
+    {
+        self.context.presentation_feedback(frame.0)
+    }
+}
+
+/// Additional methods to build headless contexts on Unix-like platforms.
+pub trait HeadlessRendererBuilderExt {
+    /// Builds the context with EGL on a `gbm_device` created on the chosen DRM device, instead
+    /// of the default EGL display. This picks the GPU that renders on systems with several of
+    /// them, for example with `DrmDevice::Path("/dev/dri/renderD129".into())` or with a file
+    /// descriptor that is already open. Also applies to surfaceless contexts.
+    ///
+    /// OsMesa isn't used as a fallback when a device is chosen, so `build` fails if `libgbm` or
+    /// `libEGL` are not present, if the device can't be opened or if none matches. Replaces the
+    /// device chosen with `with_egl_device`.
+    fn with_drm_device(self, device: DrmDevice) -> Self;
+
+    /// Builds the context on the EGL display of a device returned by `EglDevice::enumerate`,
+    /// through `EGL_EXT_platform_device`. Unlike `with_drm_device`, this doesn't need GBM and so
+    /// also works with the proprietary NVIDIA driver. Also applies to surfaceless contexts.
+    ///
+    /// OsMesa isn't used as a fallback. Replaces the device chosen with `with_drm_device`.
+    fn with_egl_device(self, device: EglDevice) -> Self;
+
+    /// Sets the ways to try building the context, in order. Only the backends in `priority`
+    /// are tried, so for example `&[HeadlessBackend::OsMesa]` forces software rendering on CI
+    /// machines. `build` returns `CreationError::NotSupported` if `priority` is empty.
+    ///
+    /// Ignored if a device was chosen with `with_drm_device` or `with_egl_device`. By default,
+    /// the order described in `HeadlessContextExt::headless_backend` is used.
+    fn with_headless_backend_priority(self, priority: &[HeadlessBackend]) -> Self;
+
+    /// Sets the layout of the pixels of the buffer that an OSMesa context renders to, which takes
+    /// precedence over the color and alpha bits of the builder. Has no effect on the other
+    /// backends.
+    ///
+    /// By default, `OsMesaFormat::RgbaF32` is used if a float color buffer is requested and
+    /// `OsMesaFormat::Rgba` otherwise.
+    fn with_osmesa_format(self, format: OsMesaFormat) -> Self;
+}
+
+impl<'a> HeadlessRendererBuilderExt for HeadlessRendererBuilder<'a> {
+    #[inline]
+    fn with_drm_device(mut self, device: DrmDevice) -> Self {
+        self.platform_specific.drm_device = Some(device);
+        self.platform_specific.egl_device = None;
+        self
+    }
+
+    #[inline]
+    fn with_egl_device(mut self, device: EglDevice) -> Self {
+        self.platform_specific.egl_device = Some(device);
+        self.platform_specific.drm_device = None;
+        self
+    }
+
+    #[inline]
+    fn with_headless_backend_priority(mut self, priority: &[HeadlessBackend]) -> Self {
+        self.platform_specific.backend_priority = Some(priority.to_vec());
+        self
+    }
+
+    #[inline]
+    fn with_osmesa_format(mut self, format: OsMesaFormat) -> Self {
+        self.platform_specific.osmesa_format = Some(format);
+        self
+    }
+}
+
+/// Additional methods for headless contexts on Unix-like platforms.
+pub trait HeadlessContextExt {
+    /// Returns how the context was built.
+    ///
+    /// Unless a device or another order was chosen, glutin tries in order EGL without any
+    /// surface if `with_surfaceless` was called, EGL with a pbuffer on the default display, EGL
+    /// on the first render node through GBM, and finally OSMesa. If all of them fail, the error
+    /// of each of them is returned in `CreationError::BackendsFailed`.
+    fn headless_backend(&self) -> HeadlessBackend;
+
+    /// Returns the DRM device that the context renders on, including its PCI ids and kernel
+    /// driver when they are known. Returns `None` if the context wasn't built with
+    /// `HeadlessRendererBuilderExt::with_drm_device`.
+    fn drm_device(&self) -> Option<DrmDeviceInfo>;
+
+    /// Returns the layout of the pixels of the buffer the context renders to, or `None` if it
+    /// isn't an OSMesa context. `get_pixel_format` also reports the bits of this format.
+    fn osmesa_format(&self) -> Option<OsMesaFormat>;
+
+    /// Reallocates the buffer of an OSMesa context for the given dimensions, so that the context
+    /// and its objects can be reused for renders of another size. If the context is current, it
+    /// is made current again with the new buffer.
+    ///
+    /// Returns `ContextError::FunctionUnavailable` if the context isn't an OSMesa context.
+    fn resize_osmesa_buffer(&mut self, width: u32, height: u32) -> Result<(), ContextError>;
+}
+
+impl<T: ContextCurrentState> HeadlessContextExt for HeadlessContext<T> {
+    #[inline]
+    fn headless_backend(&self) -> HeadlessBackend {
+        self.context.headless_backend()
+    }
+
+    #[inline]
+    fn drm_device(&self) -> Option<DrmDeviceInfo> {
+        self.context.drm_device().cloned()
+    }
+
+    #[inline]
+    fn osmesa_format(&self) -> Option<OsMesaFormat> {
+        self.context.osmesa_format()
+    }
+
+    #[inline]
+    fn resize_osmesa_buffer(&mut self, width: u32, height: u32) -> Result<(), ContextError> {
+        match self.context.osmesa_context_mut() {
+            Some(mesa) => mesa.resize_buffer((width, height))?,
+            None => return Err(ContextError::FunctionUnavailable),
+        }
+        self.dimensions = Some((width, height));
+        Ok(())
+    }
+}
+
+/// Additional methods to access the buffers of OSMesa contexts directly.
+pub trait OsMesaContextExt {
+    /// Returns the buffer the context renders to, in the format returned by
+    /// `HeadlessContextExt::osmesa_format` and row by row from the bottom of the buffer. Returns
+    /// `None` if the context isn't an OSMesa context.
+    ///
+    /// If the context is current, this waits for the rendering commands to finish first.
+    fn osmesa_color_buffer(&self) -> Option<&[u8]>;
+
+    /// Returns the depth buffer of the context with `OSMesaGetDepthBuffer`. Returns `None` if
+    /// the context isn't an OSMesa context, has no depth buffer or was never made current.
+    ///
+    /// If the context is current, this waits for the rendering commands to finish first.
+    fn osmesa_depth_buffer(&self) -> Option<OsMesaDepthBuffer>;
+
+    /// Makes the OSMesa context current again, rendering to `buffer` of `width` by `height`
+    /// pixels instead of its own buffer, until it is made current again with `make_current`.
+    /// `buffer` must hold the pixels in the format returned by
+    /// `HeadlessContextExt::osmesa_format` and be aligned for its components, otherwise an error
+    /// is returned. `osmesa_color_buffer` keeps returning the buffer of the context.
+    ///
+    /// Returns `ContextError::FunctionUnavailable` if the context isn't an OSMesa context.
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must stay alive and must not be accessed while the context renders to it.
+    unsafe fn bind_osmesa_buffer(&self, buffer: &mut [u8], width: u32, height: u32)
+        -> Result<(), ContextError>;
+}
+
+impl OsMesaContextExt for HeadlessContext<PossiblyCurrent> {
+    #[inline]
+    fn osmesa_color_buffer(&self) -> Option<&[u8]> {
+        self.context.osmesa_context().map(|mesa| mesa.color_buffer())
+    }
+
+    #[inline]
+    fn osmesa_depth_buffer(&self) -> Option<OsMesaDepthBuffer> {
+        self.context.osmesa_context().and_then(|mesa| mesa.depth_buffer())
+    }
+
+    #[inline]
+    unsafe fn bind_osmesa_buffer(&self, buffer: &mut [u8], width: u32, height: u32)
+        -> Result<(), ContextError>
+    {
+        match self.context.osmesa_context() {
+            Some(mesa) => mesa.bind_to_buffer(buffer, width, height),
+            None => Err(ContextError::FunctionUnavailable),
+        }
+    }
+}
+
+/// Additional methods to build a `RawContext` on Unix-like platforms.
+pub trait RawContextExt {
+    /// Builds a context on a X11 window that was created by the user.
+    ///
+    /// # Unsafety
+    ///
+    /// The window must remain alive for as long as the returned context.
+    #[cfg(feature = "winit")]
+    unsafe fn new_raw_x11_context<T: ContextCurrentState>(
+        xconn: Arc<XConnection>,
+        xlib_window: raw::c_ulong,
+        cb: ContextBuilder<T>,
+    ) -> Result<Self, CreationError>
+        where Self: Sized;
+
+    /// Builds a context on a `wl_surface` that was created by the user.
+    ///
+    /// # Unsafety
+    ///
+    /// The `wl_display` and the `wl_surface` must remain alive for as long as the returned
+    /// context.
+    unsafe fn new_raw_wayland_context<T: ContextCurrentState>(
+        display: *const c_void,
+        surface: *mut c_void,
+        width: u32,
+        height: u32,
+        cb: ContextBuilder<T>,
+    ) -> Result<Self, CreationError>
+        where Self: Sized;
+
+    /// Wraps a GLX or EGL context that was created by another library, such as the host of a
+    /// plugin, so that it can be used through glutin's API.
+    ///
+    /// The context draws to `window_handle`, which must belong to `display`. EGL contexts must
+    /// have been created on the EGL display of `display`, and GLX contexts can only be used on
+    /// X11. The context is not destroyed when the returned `RawContext` is dropped, and can't be
+    /// recreated with `RawContext::recreate`.
+    ///
+    /// # Unsafety
+    ///
+    /// The handles must be valid, and the context, the display and the window must remain alive
+    /// for as long as the returned context.
+    unsafe fn adopt_raw_context(
+        display: &Display,
+        context: RawHandle,
+        window_handle: RawWindowHandle,
+    ) -> Result<Self, CreationError>
+        where Self: Sized;
+}
+
+impl RawContextExt for RawContext<NotCurrent> {
+    #[cfg(feature = "winit")]
+    #[inline]
+    unsafe fn new_raw_x11_context<T: ContextCurrentState>(
+        xconn: Arc<XConnection>,
+        xlib_window: raw::c_ulong,
+        cb: ContextBuilder<T>,
+    ) -> Result<Self, CreationError>
+    {
+        let ContextBuilder { pf_reqs, gl_attr } = cb;
+        let share_group = gl_attr.share_group();
+        let gl_attr = gl_attr.map_sharing(|ctxt| &ctxt.context);
+        create_with_versions(&gl_attr, |gl_attr| {
+            create_with_fallbacks(&pf_reqs, |pf_reqs| {
+                platform::Context::new_raw_x11_context(&xconn, xlib_window, pf_reqs, gl_attr)
+            }).map(|context| RawContext::from_platform(context, share_group.clone(), gl_attr))
+        })
+    }
+
+    #[inline]
+    unsafe fn new_raw_wayland_context<T: ContextCurrentState>(
+        display: *const c_void,
+        surface: *mut c_void,
+        width: u32,
+        height: u32,
+        cb: ContextBuilder<T>,
+    ) -> Result<Self, CreationError>
+    {
+        let ContextBuilder { pf_reqs, gl_attr } = cb;
+        let share_group = gl_attr.share_group();
+        let gl_attr = gl_attr.map_sharing(|ctxt| &ctxt.context);
+        create_with_versions(&gl_attr, |gl_attr| {
+            create_with_fallbacks(&pf_reqs, |pf_reqs| {
+                platform::Context::new_raw_wayland_context(display, surface, width, height,
+                                                            pf_reqs, gl_attr)
+            }).map(|context| RawContext::from_platform(context, share_group.clone(), gl_attr))
+        })
+    }
+
+    #[inline]
+    unsafe fn adopt_raw_context(
+        display: &Display,
+        context: RawHandle,
+        window_handle: RawWindowHandle,
+    ) -> Result<Self, CreationError>
+    {
+        platform::Context::adopt_raw_context(&display.display, context, window_handle)
+            .map(RawContext::from_adopted)
+    }
+}
@@ -1,15 +1,24 @@
 #![cfg(target_os = "windows")]
 
 pub use winapi::shared::windef::HGLRC;
+#[cfg(feature = "winit")]
 pub use winit::os::windows::{WindowBuilderExt, WindowExt, MonitorIdExt};
 
 pub use api::egl::ffi::EGLContext;
-pub use platform::RawHandle;
+pub use platform::{DibSection, RawHandle};
 
-use {Context, HeadlessContext};
+use {Context, ContextBuilder, ContextCurrentState, ContextError, CreationError, HeadlessContext};
+use HeadlessRendererBuilder;
+use {NotCurrent, PossiblyCurrent};
+use RawContext;
+use create_with_fallbacks;
+use create_with_versions;
 use os::GlContextExt;
+use platform;
 
-impl GlContextExt for Context {
+use std::os::raw::c_void;
+
+impl<T: ContextCurrentState> GlContextExt for Context<T> {
     type Handle = RawHandle;
 
     #[inline]
@@ -18,7 +27,7 @@ impl GlContextExt for Context {
     }
 }
 
-impl GlContextExt for HeadlessContext {
+impl<T: ContextCurrentState> GlContextExt for HeadlessContext<T> {
     type Handle = RawHandle;
 
     #[inline]
@@ -26,3 +35,101 @@ impl GlContextExt for HeadlessContext {
         self.context.raw_handle()
     }
 }
+
+/// Additional methods for contexts on Windows.
+pub trait ContextExt {
+    /// Synchronizes the swaps with the vertical blanks of the monitor the window is currently
+    /// on, instead of the primary monitor. This avoids stuttering when the monitors have
+    /// different refresh rates. Vsync follows the window when it is moved to another monitor.
+    ///
+    /// Enabling it replaces the swap interval of the context. Only supported by WGL contexts,
+    /// otherwise `ContextError::FunctionUnavailable` is returned.
+    fn set_monitor_vsync(&self, enabled: bool) -> Result<(), ContextError>;
+
+    /// Waits for the desktop window manager to compose each frame after swapping the buffers,
+    /// with `DwmFlush`, instead of letting the driver queue several frames. This reduces the
+    /// latency added by the composition, but lowers the throughput since the next frame can't
+    /// be rendered while the current one waits. Has no effect when the desktop isn't composited.
+    ///
+    /// Enabling it replaces the swap interval of the context. Only supported by WGL contexts,
+    /// otherwise `ContextError::FunctionUnavailable` is returned.
+    fn set_dwm_low_latency(&self, enabled: bool) -> Result<(), ContextError>;
+}
+
+impl ContextExt for Context<PossiblyCurrent> {
+    #[inline]
+    fn set_monitor_vsync(&self, enabled: bool) -> Result<(), ContextError> {
+        self.context.set_monitor_vsync(enabled)
+    }
+
+    #[inline]
+    fn set_dwm_low_latency(&self, enabled: bool) -> Result<(), ContextError> {
+        self.context.set_dwm_low_latency(enabled)
+    }
+}
+
+/// Additional methods to build headless contexts on Windows.
+pub trait HeadlessRendererBuilderExt {
+    /// Sets whether to build the context with WGL on a device-independent bitmap created with
+    /// `CreateDIBSection`, whose pixels can be read directly or copied to a window with `BitBlt`
+    /// for interop with GDI. `swap_buffers` finishes the rendering so that the pixels are up to
+    /// date.
+    ///
+    /// Only the generic implementation of Microsoft can draw to bitmaps, so the context is
+    /// software rendered, single buffered and supports OpenGL 1.1 at most. `build` returns
+    /// `CreationError::NotSupported` if `with_surfaceless` is also set.
+    ///
+    /// The default value is `false`.
+    fn with_dib_section(self, dib_section: bool) -> Self;
+}
+
+impl<'a> HeadlessRendererBuilderExt for HeadlessRendererBuilder<'a> {
+    #[inline]
+    fn with_dib_section(mut self, dib_section: bool) -> Self {
+        self.platform_specific.dib_section = dib_section;
+        self
+    }
+}
+
+/// Additional methods for headless contexts on Windows.
+pub trait HeadlessContextExt {
+    /// Returns the bitmap the context draws to, or `None` if it wasn't built with
+    /// `HeadlessRendererBuilderExt::with_dib_section`. The bitmap is destroyed with the context.
+    fn dib_section(&self) -> Option<DibSection>;
+}
+
+impl<T: ContextCurrentState> HeadlessContextExt for HeadlessContext<T> {
+    #[inline]
+    fn dib_section(&self) -> Option<DibSection> {
+        self.context.dib_section()
+    }
+}
+
+/// Additional methods to build a `RawContext` on Windows.
+pub trait RawContextExt {
+    /// Builds a context on a window that was created by the user.
+    ///
+    /// # Unsafety
+    ///
+    /// The window must **not** have had `SetPixelFormat` called on it and must remain alive for
+    /// as long as the returned context.
+    unsafe fn new_raw_context<T: ContextCurrentState>(hwnd: *mut c_void, cb: ContextBuilder<T>)
+        -> Result<Self, CreationError>
+        where Self: Sized;
+}
+
+impl RawContextExt for RawContext<NotCurrent> {
+    #[inline]
+    unsafe fn new_raw_context<T: ContextCurrentState>(hwnd: *mut c_void, cb: ContextBuilder<T>)
+        -> Result<Self, CreationError>
+    {
+        let ContextBuilder { pf_reqs, gl_attr } = cb;
+        let share_group = gl_attr.share_group();
+        let gl_attr = gl_attr.map_sharing(|ctxt| &ctxt.context);
+        create_with_versions(&gl_attr, |gl_attr| {
+            create_with_fallbacks(&pf_reqs, |pf_reqs| {
+                platform::Context::new_raw_context(hwnd as *mut _, pf_reqs, gl_attr)
+            }).map(|context| RawContext::from_platform(context, share_group.clone(), gl_attr))
+        })
+    }
+}
@@ -1,5 +1,6 @@
 #![cfg(target_os = "android")]
 
+#[cfg(feature = "winit")]
 pub use winit::EventsLoop;
 
 pub use api::android::*;
@@ -58,9 +58,6 @@ extern {
     pub fn emscripten_webgl_enable_extension(context: EMSCRIPTEN_WEBGL_CONTEXT_HANDLE,
         extension: *const libc::c_char) -> EM_BOOL;
 
-    pub fn emscripten_set_webglcontextlost_callback(target: *const libc::c_char,
-        userData: *mut libc::c_void, useCapture: EM_BOOL, callback: em_webgl_context_callback)
-        -> EMSCRIPTEN_RESULT;
     pub fn emscripten_set_webglcontextrestored_callback(target: *const libc::c_char,
         userData: *mut libc::c_void, useCapture: EM_BOOL, callback: em_webgl_context_callback)
         -> EMSCRIPTEN_RESULT;
@@ -1,19 +1,142 @@
 #![cfg(target_os = "emscripten")]
 
 use std::ffi::CString;
+use std::ptr;
 
-use {Api, ContextError, CreationError, GlAttributes, GlRequest};
-use {PixelFormat, PixelFormatRequirements};
+use {Api, Colorspace, ConfigCaveat, ContextError, CreationError, GlAttributes, GlRequest};
+use {DeviceInfo, MultisampleResolve, SupportedVersions};
+use {PbufferAttributes, PbufferLimits, PixelFormat, PixelFormatRequirements, RawDisplayHandle, RawPixmapHandle,
+     RawWindowHandle, PresentMode, PresentationTime, Rect, RenderBuffer, SwapBehavior,
+     SwapInterval};
 
+#[cfg(feature = "winit")]
 use winit;
 
 mod ffi;
 
+/// Raw displays are not supported on emscripten, so this type can't be constructed.
+pub enum Display {}
+
+impl Display {
+    pub unsafe fn from_raw(_: RawDisplayHandle) -> Result<Display, CreationError> {
+        Err(CreationError::NotSupported("raw handles are not supported on emscripten"))
+    }
+
+    pub fn find_configs(&self, _: &PixelFormatRequirements)
+                        -> Result<Vec<Config>, CreationError>
+    {
+        match *self {}
+    }
+
+    pub fn device_info(&self) -> Result<DeviceInfo, CreationError> {
+        match *self {}
+    }
+
+    pub fn probe_versions(&self, _: Api) -> Result<SupportedVersions, CreationError> {
+        match *self {}
+    }
+}
+
+/// Config enumeration is not supported on this platform, so this type can't be constructed.
+#[derive(Clone)]
+pub enum Config {}
+
+impl Config {
+    #[inline]
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        match *self {}
+    }
+
+    pub unsafe fn create_window_surface(&self, _: RawWindowHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        match *self {}
+    }
+
+    pub fn create_pbuffer_surface(&self, _: &PbufferAttributes) -> Result<Surface, CreationError> {
+        match *self {}
+    }
+
+    pub unsafe fn create_pixmap_surface(&self, _: RawPixmapHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        match *self {}
+    }
+}
+
+/// Surfaces are not supported on this platform, so this type can't be constructed.
+pub enum Surface {}
+
+impl Surface {
+    #[inline]
+    pub fn dimensions(&self) -> Result<(u32, u32), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn buffer_age(&self) -> Result<u32, ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn set_damage_region(&self, _rects: &[Rect]) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn set_swap_behavior(&self, _behavior: SwapBehavior) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn set_render_buffer(&self, _render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn set_multisample_resolve(&self, _resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn resize(&self, _: u32, _: u32) {
+        match *self {}
+    }
+}
+
 pub struct Context {
     context: ffi::EMSCRIPTEN_WEBGL_CONTEXT_HANDLE,
 }
 
 impl Context {
+    #[cfg(feature = "winit")]
     #[inline]
     pub fn new(
         window_builder: winit::WindowBuilder,
@@ -22,6 +145,13 @@ impl Context {
         gl_attr: &GlAttributes<&Context>,
     ) -> Result<(winit::Window, Self), CreationError>
     {
+        if gl_attr.protected_content {
+            return Err(CreationError::ProtectedContentNotSupported);
+        }
+        if gl_attr.no_error {
+            return Err(CreationError::NoErrorNotSupported);
+        }
+
         let window = window_builder.build(events_loop)?;
 
         // getting the default values of attributes
@@ -59,6 +189,68 @@ impl Context {
         Ok((window, ctxt))
     }
 
+    #[inline]
+    pub unsafe fn new_from_raw_handles(
+        _: RawDisplayHandle,
+        _: RawWindowHandle,
+        _: &PixelFormatRequirements,
+        _: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        Err(CreationError::NotSupported("raw handles are not supported on emscripten"))
+    }
+
+    #[inline]
+    pub unsafe fn new_with_display(
+        display: &Display,
+        _: RawWindowHandle,
+        _: &PixelFormatRequirements,
+        _: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        match *display {}
+    }
+
+    #[inline]
+    pub unsafe fn new_with_config(
+        config: &Config,
+        _: RawWindowHandle,
+        _: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        match *config {}
+    }
+
+    /// Destroys this context and builds a new one drawing to the same window, which isn't
+    /// supported on this platform.
+    #[inline]
+    pub unsafe fn recreate(self, _: &GlAttributes<&Self>) -> Result<Self, CreationError> {
+        Err(CreationError::NotSupported("contexts can't be recreated on this platform"))
+    }
+
+    pub unsafe fn create_window_surface(&self, _: RawWindowHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        Err(CreationError::NotSupported("surfaces are not supported on this platform"))
+    }
+
+    pub fn create_pbuffer_surface(&self, _: &PbufferAttributes) -> Result<Surface, CreationError> {
+        Err(CreationError::NotSupported("surfaces are not supported on this platform"))
+    }
+
+    pub unsafe fn create_pixmap_surface(&self, _: RawPixmapHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        Err(CreationError::NotSupported("surfaces are not supported on this platform"))
+    }
+
+    #[inline]
+    pub unsafe fn make_current_surfaces(&self, draw: &Surface, _: &Surface)
+                                        -> Result<(), ContextError>
+    {
+        match *draw {}
+    }
+
     #[inline]
     pub fn resize(&self, width: u32, height: u32) {
         // TODO: ?
@@ -68,6 +260,19 @@ impl Context {
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         // TOOD: check if == EMSCRIPTEN_RESULT
         ffi::emscripten_webgl_make_context_current(self.context);
+        // the loss of the canvas is polled rather than listened to, so that it is reported by
+        // the operation that runs into it
+        if ffi::emscripten_is_webgl_context_lost(ptr::null()) != 0 {
+            return Err(ContextError::ContextLost);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        if self.is_current() {
+            ffi::emscripten_webgl_make_context_current(0);
+        }
         Ok(())
     }
 
@@ -78,6 +283,110 @@ impl Context {
         }
     }
 
+    #[inline]
+    pub fn validate(&self) -> Result<(), ContextError> {
+        if unsafe { ffi::emscripten_is_webgl_context_lost(ptr::null()) } != 0 {
+            return Err(ContextError::ContextLost);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn set_swap_interval(&self, _: SwapInterval) -> Result<(), ContextError> {
+        // The browser decides when frames are presented.
+        Err(ContextError::FunctionUnavailable)
+    }
+
+    #[inline]
+    pub fn get_swap_interval(&self) -> Result<SwapInterval, ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+
+    #[inline]
+    pub fn get_swap_interval_range(&self) -> Result<(u32, u32), ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+
+    #[inline]
+    pub fn supported_present_modes(&self) -> Result<Vec<PresentMode>, ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        Ok(SwapBehavior::Destroyed)
+    }
+
+    #[inline]
+    pub fn colorspace(&self) -> Result<Colorspace, ContextError> {
+        Ok(self.get_pixel_format().colorspace)
+    }
+
+    #[inline]
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        Ok(self.get_pixel_format().render_buffer())
+    }
+
+    #[inline]
+    pub fn set_render_buffer(&self, render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        if self.render_buffer()? == render_buffer {
+            Ok(())
+        } else {
+            Err(ContextError::FunctionUnavailable)
+        }
+    }
+
+    #[inline]
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        Ok(MultisampleResolve::Default)
+    }
+
+    #[inline]
+    pub fn set_multisample_resolve(&self, resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        match resolve {
+            MultisampleResolve::Default => Ok(()),
+            MultisampleResolve::Box => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn join_swap_group(&self, _: u32) -> Result<(), ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+
+    #[inline]
+    pub fn bind_swap_barrier(&self, _: u32, _: u32) -> Result<(), ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+
+    #[inline]
+    pub fn swap_buffers_with_frame_id(&self) -> Result<u64, ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+
+    #[inline]
+    pub fn try_swap_buffers(&self) -> Result<u64, ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+
+    #[inline]
+    pub fn presentation_time(&self, _: u64) -> Result<PresentationTime, ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+
+    #[inline]
+    pub fn current_state(&self) -> CurrentState {
+        CurrentState(unsafe { ffi::emscripten_webgl_get_current_context() })
+    }
+
+    #[inline]
+    pub unsafe fn restore_current_state(&self, state: &CurrentState) -> Result<(), ContextError> {
+        ffi::emscripten_webgl_make_context_current(state.0);
+        Ok(())
+    }
+
     #[inline]
     pub fn get_proc_address(&self, addr: &str) -> *const () {
         let addr = CString::new(addr).unwrap();
@@ -90,6 +399,10 @@ impl Context {
 
     #[inline]
     pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        // the browser presents the frame itself, so only the loss of the canvas is reported
+        if unsafe { ffi::emscripten_is_webgl_context_lost(ptr::null()) } != 0 {
+            return Err(ContextError::ContextLost);
+        }
         Ok(())
     }
 
@@ -104,6 +417,9 @@ impl Context {
         PixelFormat {
             hardware_accelerated: true,
             color_bits: 24,
+            red_bits: 8,
+            green_bits: 8,
+            blue_bits: 8,
             alpha_bits: 8,
             depth_bits: 24,
             stencil_bits: 8,
@@ -111,15 +427,28 @@ impl Context {
             double_buffer: true,
             multisampling: None,
             srgb: true,
+            native_id: None,
+            caveat: ConfigCaveat::None,
+            colorspace: Colorspace::Srgb,
+            float_color_buffer: false,
+            transparent: true,
         }
     }
 
+    #[inline]
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+
     #[inline]
     pub unsafe fn raw_handle(&self) -> ffi::EMSCRIPTEN_WEBGL_CONTEXT_HANDLE {
         self.context
     }
 }
 
+/// The WebGL context that was current when `Context::current_state` was called, which may be 0.
+pub struct CurrentState(ffi::EMSCRIPTEN_WEBGL_CONTEXT_HANDLE);
+
 impl Drop for Context {
     fn drop(&mut self) {
         unsafe {
@@ -144,6 +473,19 @@ impl HeadlessContext {
         unimplemented!()
     }
 
+    pub fn new_surfaceless(_: &PixelFormatRequirements, _: &GlAttributes<&HeadlessContext>,
+                           _: &PlatformSpecificHeadlessBuilderAttributes)
+                           -> Result<HeadlessContext, CreationError>
+    {
+        Err(CreationError::NotSupported("surfaceless contexts are not supported by emscripten"))
+    }
+
+    pub fn new_shared(_: (u32, u32), _: bool, _: &GlAttributes<&Context>)
+                      -> Result<HeadlessContext, CreationError>
+    {
+        Err(CreationError::NotSupported("sharing lists between a headless and a windowed context"))
+    }
+
     #[inline]
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         // TOOD: check if == EMSCRIPTEN_RESULT
@@ -151,6 +493,14 @@ impl HeadlessContext {
         Ok(())
     }
 
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        if self.is_current() {
+            ffi::emscripten_webgl_make_context_current(0);
+        }
+        Ok(())
+    }
+
     #[inline]
     pub fn is_current(&self) -> bool {
         unsafe {
@@ -26,11 +26,31 @@ impl HeadlessContext {
         unimplemented!()
     }
 
+    /// See the docs in the crate root file.
+    pub fn new_surfaceless(_: &PixelFormatRequirements, _: &GlAttributes<&HeadlessContext>,
+                           _: &PlatformSpecificHeadlessBuilderAttributes)
+                           -> Result<HeadlessContext, CreationError>
+    {
+        unimplemented!()
+    }
+
+    /// See the docs in the crate root file.
+    pub fn new_shared(_: (u32, u32), _: bool, _: &GlAttributes<&Context>)
+                      -> Result<HeadlessContext, CreationError>
+    {
+        unimplemented!()
+    }
+
     /// See the docs in the crate root file.
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         unimplemented!()
     }
 
+    /// See the docs in the crate root file.
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        unimplemented!()
+    }
+
     pub fn swap_buffers(&self) -> Result<(), ContextError> {
         unimplemented!()
     }
@@ -0,0 +1,366 @@
+#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+
+use CreationError;
+
+use api::dlopen;
+
+use std::ffi::CString;
+use std::fs::{self, File, OpenOptions};
+use std::os::raw::{c_int, c_void};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::mem;
+
+/// The directory of the DRM device nodes.
+const DRI_DIRECTORY: &'static str = "/dev/dri";
+
+/// Selects the DRM device on which a headless context is built with GBM.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DrmDevice {
+    /// The first render node that can be opened, in the order of their numbers.
+    FirstRenderNode,
+    /// The device node at this path, for example `/dev/dri/renderD129` or
+    /// `/dev/dri/by-path/pci-0000:01:00.0-render`.
+    Path(PathBuf),
+    /// A device node that was already opened, for example by a process that has the permission
+    /// to and passed it over a socket. The file descriptor isn't closed by glutin and must stay
+    /// open for as long as the context.
+    Fd(RawFd),
+    /// The first render node of a PCI GPU with this vendor id and, if it isn't `None`, this
+    /// device id.
+    PciId {
+        vendor: u16,
+        device: Option<u16>,
+    },
+}
+
+/// The DRM device that a headless context was built on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DrmDeviceInfo {
+    /// The path of the device node that was opened.
+    pub path: PathBuf,
+    /// The PCI vendor id of the GPU, or `None` if it isn't a PCI device.
+    pub vendor: Option<u16>,
+    /// The PCI device id of the GPU, or `None` if it isn't a PCI device.
+    pub device: Option<u16>,
+    /// The name of the kernel driver of the GPU, for example `i915` or `amdgpu`.
+    pub driver: Option<String>,
+}
+
+impl DrmDeviceInfo {
+    /// Reads the ids and the driver of the device node at `path` from sysfs.
+    fn from_path(path: &Path) -> DrmDeviceInfo {
+        // the node may be a symlink such as the ones in `/dev/dri/by-path`
+        let node = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+        let sysfs = node.file_name()
+            .map(|name| Path::new("/sys/class/drm").join(name).join("device"));
+
+        let read_id = |file: &str| {
+            let sysfs = sysfs.as_ref()?;
+            let id = fs::read_to_string(sysfs.join(file)).ok()?;
+            u16::from_str_radix(id.trim().trim_left_matches("0x"), 16).ok()
+        };
+        let driver = sysfs.as_ref()
+            .and_then(|sysfs| fs::read_link(sysfs.join("driver")).ok())
+            .and_then(|driver| {
+                driver.file_name().map(|name| name.to_string_lossy().into_owned())
+            });
+
+        DrmDeviceInfo {
+            path: path.to_owned(),
+            vendor: read_id("vendor"),
+            device: read_id("device"),
+            driver: driver,
+        }
+    }
+}
+
+// From `gbm.h`.
+const GBM_BO_USE_SCANOUT: u32 = 1 << 0;
+const GBM_BO_USE_RENDERING: u32 = 1 << 2;
+
+/// The functions of libgbm, loaded at runtime since it is not installed everywhere.
+struct Gbm {
+    create_device: unsafe extern "C" fn(c_int) -> *mut c_void,
+    device_destroy: unsafe extern "C" fn(*mut c_void),
+    surface_create: unsafe extern "C" fn(*mut c_void, u32, u32, u32, u32) -> *mut c_void,
+    surface_destroy: unsafe extern "C" fn(*mut c_void),
+    surface_lock_front_buffer: unsafe extern "C" fn(*mut c_void) -> *mut c_void,
+    surface_release_buffer: unsafe extern "C" fn(*mut c_void, *mut c_void),
+    // `gbm_bo_get_handle` returns a `union gbm_bo_handle`, which is 64 bits wide
+    bo_get_handle: unsafe extern "C" fn(*mut c_void) -> u64,
+    bo_get_stride: unsafe extern "C" fn(*mut c_void) -> u32,
+}
+
+impl Gbm {
+    unsafe fn load() -> Option<Gbm> {
+        let lib = dlopen::dlopen(b"libgbm.so.1\0".as_ptr() as *const _, dlopen::RTLD_NOW);
+        if lib.is_null() {
+            return None;
+        }
+
+        let sym = |name: &str| {
+            let name = CString::new(name).unwrap();
+            let sym = dlopen::dlsym(lib, name.as_ptr());
+            if sym.is_null() { None } else { Some(sym) }
+        };
+
+        Some(Gbm {
+            create_device: mem::transmute(sym("gbm_create_device")?),
+            device_destroy: mem::transmute(sym("gbm_device_destroy")?),
+            surface_create: mem::transmute(sym("gbm_surface_create")?),
+            surface_destroy: mem::transmute(sym("gbm_surface_destroy")?),
+            surface_lock_front_buffer: mem::transmute(sym("gbm_surface_lock_front_buffer")?),
+            surface_release_buffer: mem::transmute(sym("gbm_surface_release_buffer")?),
+            bo_get_handle: mem::transmute(sym("gbm_bo_get_handle")?),
+            bo_get_stride: mem::transmute(sym("gbm_bo_get_stride")?),
+        })
+    }
+}
+
+/// A `gbm_device` created on a DRM device node, to be used as the native display of EGL.
+pub struct GbmDevice {
+    gbm: Gbm,
+    device: *mut c_void,
+    fd: RawFd,
+    info: DrmDeviceInfo,
+    // The device node must stay open for as long as the `gbm_device`. `None` if the file
+    // descriptor was given by the user.
+    _file: Option<File>,
+}
+
+unsafe impl Send for GbmDevice {}
+unsafe impl Sync for GbmDevice {}
+
+impl GbmDevice {
+    /// Opens the device selected by `selection` and creates a `gbm_device` on it.
+    pub fn open(selection: &DrmDevice) -> Result<GbmDevice, CreationError> {
+        GbmDevice::open_node_kind(selection, NodeKind::Render)
+    }
+
+    /// Opens the device selected by `selection` and creates a `gbm_device` on it, like `open`,
+    /// except that `DrmDevice::FirstRenderNode` and `DrmDevice::PciId` select primary nodes such
+    /// as `/dev/dri/card0`, since render nodes can't set modes.
+    pub fn open_primary(selection: &DrmDevice) -> Result<GbmDevice, CreationError> {
+        GbmDevice::open_node_kind(selection, NodeKind::Primary)
+    }
+
+    fn open_node_kind(selection: &DrmDevice, kind: NodeKind)
+                      -> Result<GbmDevice, CreationError>
+    {
+        let gbm = unsafe { Gbm::load() }
+            .ok_or(CreationError::NotSupported("libgbm not present"))?;
+
+        match *selection {
+            DrmDevice::Path(ref path) => GbmDevice::open_path(gbm, path),
+            DrmDevice::Fd(fd) => {
+                let path = fs::read_link(format!("/proc/self/fd/{}", fd))
+                    .unwrap_or_else(|_| PathBuf::from(format!("fd {}", fd)));
+                GbmDevice::create(gbm, fd, None, DrmDeviceInfo::from_path(&path))
+            },
+            DrmDevice::FirstRenderNode => {
+                let (file, path) = kind.nodes()?.into_iter()
+                    .filter_map(|path| open_node(&path).ok().map(|file| (file, path)))
+                    .next()
+                    .ok_or(CreationError::NotSupported(match kind {
+                        NodeKind::Render => "no DRM render node could be opened",
+                        NodeKind::Primary => "no DRM primary node could be opened",
+                    }))?;
+                GbmDevice::create(gbm, file.as_raw_fd(), Some(file),
+                                  DrmDeviceInfo::from_path(&path))
+            },
+            DrmDevice::PciId { vendor, device } => {
+                let path = kind.nodes()?.into_iter()
+                    .find(|path| {
+                        let info = DrmDeviceInfo::from_path(path);
+                        info.vendor == Some(vendor) &&
+                            device.map_or(true, |device| info.device == Some(device))
+                    })
+                    .ok_or_else(|| CreationError::OsError(match device {
+                        Some(device) => format!("no DRM {} node for the PCI device \
+                                                 {:04x}:{:04x}", kind.name(), vendor, device),
+                        None => format!("no DRM {} node for the PCI vendor {:04x}", kind.name(),
+                                        vendor),
+                    }))?;
+                GbmDevice::open_path(gbm, &path)
+            },
+        }
+    }
+
+    fn open_path(gbm: Gbm, path: &Path) -> Result<GbmDevice, CreationError> {
+        let file = open_node(path).map_err(|err| {
+            CreationError::OsError(format!("Couldn't open {}: {}", path.display(), err))
+        })?;
+        GbmDevice::create(gbm, file.as_raw_fd(), Some(file), DrmDeviceInfo::from_path(path))
+    }
+
+    /// Creates the `gbm_device` on `fd`, which is owned by `file` if it was opened by glutin.
+    fn create(gbm: Gbm, fd: RawFd, file: Option<File>, info: DrmDeviceInfo)
+              -> Result<GbmDevice, CreationError>
+    {
+        let device = unsafe { (gbm.create_device)(fd) };
+        if device.is_null() {
+            return Err(CreationError::OsError(format!("gbm_create_device failed on {}",
+                                                      info.path.display())));
+        }
+
+        Ok(GbmDevice {
+            gbm: gbm,
+            device: device,
+            fd: fd,
+            info: info,
+            _file: file,
+        })
+    }
+
+    /// Returns the `gbm_device`.
+    #[inline]
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.device
+    }
+
+    /// Returns the file descriptor of the device node.
+    #[inline]
+    pub fn fd(&self) -> RawFd {
+        self.fd
+    }
+
+    /// Returns the device node that was opened.
+    #[inline]
+    pub fn info(&self) -> &DrmDeviceInfo {
+        &self.info
+    }
+}
+
+impl Drop for GbmDevice {
+    fn drop(&mut self) {
+        unsafe { (self.gbm.device_destroy)(self.device); }
+    }
+}
+
+/// A `gbm_surface` whose buffers can be scanned out, to be used as the native window of EGL.
+pub struct GbmSurface {
+    surface: *mut c_void,
+    // The `gbm_device` must outlive the surface.
+    device: Arc<GbmDevice>,
+}
+
+/// A buffer of a `GbmSurface` that was locked after a swap, until it is released.
+pub struct GbmBuffer {
+    bo: *mut c_void,
+}
+
+impl GbmSurface {
+    /// Creates a surface of `width` by `height` pixels in `format`, one of the fourcc codes of
+    /// `drm_fourcc.h`.
+    pub fn new(device: Arc<GbmDevice>, width: u32, height: u32, format: u32)
+               -> Result<GbmSurface, CreationError>
+    {
+        let surface = unsafe {
+            (device.gbm.surface_create)(device.device, width, height, format,
+                                        GBM_BO_USE_SCANOUT | GBM_BO_USE_RENDERING)
+        };
+        if surface.is_null() {
+            return Err(CreationError::OsError(format!("gbm_surface_create failed for {}x{} \
+                                                       with the format {:#x}", width, height,
+                                                      format)));
+        }
+        Ok(GbmSurface { surface: surface, device: device })
+    }
+
+    /// Returns the `gbm_surface`.
+    #[inline]
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.surface
+    }
+
+    /// Locks the buffer that was presented by the last call to `eglSwapBuffers`, so that it can
+    /// be scanned out. Returns `None` if there is no such buffer.
+    pub fn lock_front_buffer(&self) -> Option<GbmBuffer> {
+        let bo = unsafe { (self.device.gbm.surface_lock_front_buffer)(self.surface) };
+        if bo.is_null() { None } else { Some(GbmBuffer { bo: bo }) }
+    }
+
+    /// Gives a buffer back to the surface, so that EGL can render to it again.
+    pub fn release_buffer(&self, buffer: GbmBuffer) {
+        unsafe { (self.device.gbm.surface_release_buffer)(self.surface, buffer.bo); }
+    }
+
+    /// Returns the GEM handle of `buffer`, with which a DRM framebuffer can be created.
+    #[inline]
+    pub fn buffer_handle(&self, buffer: &GbmBuffer) -> u32 {
+        unsafe { (self.device.gbm.bo_get_handle)(buffer.bo) as u32 }
+    }
+
+    /// Returns the length of a row of `buffer`, in bytes.
+    #[inline]
+    pub fn buffer_stride(&self, buffer: &GbmBuffer) -> u32 {
+        unsafe { (self.device.gbm.bo_get_stride)(buffer.bo) }
+    }
+}
+
+impl Drop for GbmSurface {
+    fn drop(&mut self) {
+        unsafe { (self.device.gbm.surface_destroy)(self.surface); }
+    }
+}
+
+impl GbmBuffer {
+    /// Returns the `gbm_bo`, which identifies the buffer among the ones of its surface.
+    #[inline]
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.bo
+    }
+}
+
+/// The kind of DRM device nodes that `DrmDevice::FirstRenderNode` and `DrmDevice::PciId` select.
+#[derive(Clone, Copy)]
+enum NodeKind {
+    /// The render nodes, such as `/dev/dri/renderD128`, which can only render.
+    Render,
+    /// The primary nodes, such as `/dev/dri/card0`, which can also set modes.
+    Primary,
+}
+
+impl NodeKind {
+    fn name(&self) -> &'static str {
+        match *self {
+            NodeKind::Render => "render",
+            NodeKind::Primary => "primary",
+        }
+    }
+
+    fn nodes(&self) -> Result<Vec<PathBuf>, CreationError> {
+        match *self {
+            NodeKind::Render => nodes("renderD"),
+            NodeKind::Primary => nodes("card"),
+        }
+    }
+}
+
+fn open_node(path: &Path) -> ::std::io::Result<File> {
+    OpenOptions::new().read(true).write(true).open(path)
+}
+
+/// Returns the device nodes of the system whose names are `prefix` followed by a number, in the
+/// order of their numbers.
+fn nodes(prefix: &str) -> Result<Vec<PathBuf>, CreationError> {
+    let entries = fs::read_dir(DRI_DIRECTORY).map_err(|err| {
+        CreationError::OsError(format!("Couldn't list {}: {}", DRI_DIRECTORY, err))
+    })?;
+
+    let mut nodes: Vec<(u32, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) {
+                return None;
+            }
+            let number = name[prefix.len()..].parse().ok()?;
+            Some((number, entry.path()))
+        })
+        .collect();
+    nodes.sort();
+    Ok(nodes.into_iter().map(|(_, path)| path).collect())
+}
@@ -0,0 +1,668 @@
+#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+
+use {Api, ContextBuilder, ContextCurrentState, ContextError, CreationError, GlAttributes};
+use {PixelFormat, PixelFormatRequirements, SwapInterval};
+use create_with_fallbacks;
+use create_with_versions;
+use api::dlopen;
+use api::egl;
+
+use super::gbm::{DrmDevice, GbmBuffer, GbmDevice, GbmSurface};
+use super::x11;
+
+use libc;
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_uint, c_void};
+use std::os::unix::io::RawFd;
+use std::sync::Arc;
+use std::{io, mem, ptr, slice};
+
+// From `xf86drmMode.h`.
+const DRM_MODE_CONNECTED: c_int = 1;
+const DRM_MODE_TYPE_PREFERRED: u32 = 1 << 3;
+const DRM_MODE_PAGE_FLIP_EVENT: u32 = 0x01;
+
+// From `drm_fourcc.h`.
+const DRM_FORMAT_RGB565: u32 = 0x3631_5752;
+const DRM_FORMAT_XRGB8888: u32 = 0x3432_5258;
+const DRM_FORMAT_ARGB8888: u32 = 0x3432_5241;
+
+#[repr(C)]
+#[allow(dead_code)]
+struct DrmModeRes {
+    count_fbs: c_int,
+    fbs: *mut u32,
+    count_crtcs: c_int,
+    crtcs: *mut u32,
+    count_connectors: c_int,
+    connectors: *mut u32,
+    count_encoders: c_int,
+    encoders: *mut u32,
+    min_width: u32,
+    max_width: u32,
+    min_height: u32,
+    max_height: u32,
+}
+
+#[repr(C)]
+#[allow(dead_code)]
+#[derive(Clone, Copy)]
+struct DrmModeModeInfo {
+    clock: u32,
+    hdisplay: u16,
+    hsync_start: u16,
+    hsync_end: u16,
+    htotal: u16,
+    hskew: u16,
+    vdisplay: u16,
+    vsync_start: u16,
+    vsync_end: u16,
+    vtotal: u16,
+    vscan: u16,
+    vrefresh: u32,
+    flags: u32,
+    type_: u32,
+    name: [c_char; 32],
+}
+
+#[repr(C)]
+#[allow(dead_code)]
+struct DrmModeConnector {
+    connector_id: u32,
+    encoder_id: u32,
+    connector_type: u32,
+    connector_type_id: u32,
+    connection: c_int,
+    mm_width: u32,
+    mm_height: u32,
+    subpixel: c_int,
+    count_modes: c_int,
+    modes: *mut DrmModeModeInfo,
+    count_props: c_int,
+    props: *mut u32,
+    prop_values: *mut u64,
+    count_encoders: c_int,
+    encoders: *mut u32,
+}
+
+#[repr(C)]
+#[allow(dead_code)]
+struct DrmModeEncoder {
+    encoder_id: u32,
+    encoder_type: u32,
+    crtc_id: u32,
+    possible_crtcs: u32,
+    possible_clones: u32,
+}
+
+#[repr(C)]
+#[allow(dead_code)]
+struct DrmModeCrtc {
+    crtc_id: u32,
+    buffer_id: u32,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    mode_valid: c_int,
+    mode: DrmModeModeInfo,
+    gamma_size: c_int,
+}
+
+type PageFlipHandler = extern "C" fn(c_int, c_uint, c_uint, c_uint, *mut c_void);
+
+/// The version 2 of `drmEventContext`.
+#[repr(C)]
+struct DrmEventContext {
+    version: c_int,
+    vblank_handler: Option<PageFlipHandler>,
+    page_flip_handler: Option<PageFlipHandler>,
+}
+
+/// The functions of libdrm, loaded at runtime since it is not installed everywhere.
+struct Drm {
+    get_resources: unsafe extern "C" fn(c_int) -> *mut DrmModeRes,
+    free_resources: unsafe extern "C" fn(*mut DrmModeRes),
+    get_connector: unsafe extern "C" fn(c_int, u32) -> *mut DrmModeConnector,
+    free_connector: unsafe extern "C" fn(*mut DrmModeConnector),
+    get_encoder: unsafe extern "C" fn(c_int, u32) -> *mut DrmModeEncoder,
+    free_encoder: unsafe extern "C" fn(*mut DrmModeEncoder),
+    get_crtc: unsafe extern "C" fn(c_int, u32) -> *mut DrmModeCrtc,
+    free_crtc: unsafe extern "C" fn(*mut DrmModeCrtc),
+    add_fb: unsafe extern "C" fn(c_int, u32, u32, u8, u8, u32, u32, *mut u32) -> c_int,
+    rm_fb: unsafe extern "C" fn(c_int, u32) -> c_int,
+    set_crtc: unsafe extern "C" fn(c_int, u32, u32, u32, u32, *mut u32, c_int,
+                                   *mut DrmModeModeInfo) -> c_int,
+    page_flip: unsafe extern "C" fn(c_int, u32, u32, u32, *mut c_void) -> c_int,
+    handle_event: unsafe extern "C" fn(c_int, *mut DrmEventContext) -> c_int,
+}
+
+impl Drm {
+    unsafe fn load() -> Option<Drm> {
+        let lib = dlopen::dlopen(b"libdrm.so.2\0".as_ptr() as *const _, dlopen::RTLD_NOW);
+        if lib.is_null() {
+            return None;
+        }
+
+        let sym = |name: &str| {
+            let name = CString::new(name).unwrap();
+            let sym = dlopen::dlsym(lib, name.as_ptr());
+            if sym.is_null() { None } else { Some(sym) }
+        };
+
+        Some(Drm {
+            get_resources: mem::transmute(sym("drmModeGetResources")?),
+            free_resources: mem::transmute(sym("drmModeFreeResources")?),
+            get_connector: mem::transmute(sym("drmModeGetConnector")?),
+            free_connector: mem::transmute(sym("drmModeFreeConnector")?),
+            get_encoder: mem::transmute(sym("drmModeGetEncoder")?),
+            free_encoder: mem::transmute(sym("drmModeFreeEncoder")?),
+            get_crtc: mem::transmute(sym("drmModeGetCrtc")?),
+            free_crtc: mem::transmute(sym("drmModeFreeCrtc")?),
+            add_fb: mem::transmute(sym("drmModeAddFB")?),
+            rm_fb: mem::transmute(sym("drmModeRmFB")?),
+            set_crtc: mem::transmute(sym("drmModeSetCrtc")?),
+            page_flip: mem::transmute(sym("drmModePageFlip")?),
+            handle_event: mem::transmute(sym("drmHandleEvent")?),
+        })
+    }
+}
+
+/// Selects the connector that a `KmsContext` presents to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KmsConnector {
+    /// The first connector of the device with a display plugged in.
+    FirstConnected,
+    /// The connector with this id, as reported by `modetest` or by
+    /// `/sys/class/drm/card0-*/connector_id`.
+    Id(u32),
+}
+
+/// A display mode of a connector.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KmsMode {
+    /// The width of the mode, in pixels.
+    pub width: u32,
+    /// The height of the mode, in pixels.
+    pub height: u32,
+    /// The vertical refresh rate, in hertz.
+    pub refresh_rate: u32,
+    /// The name of the mode, for example `1920x1080`.
+    pub name: String,
+}
+
+impl KmsMode {
+    fn from_raw(mode: &DrmModeModeInfo) -> KmsMode {
+        let name = unsafe { CStr::from_ptr(mode.name.as_ptr()) };
+        KmsMode {
+            width: mode.hdisplay as u32,
+            height: mode.vdisplay as u32,
+            refresh_rate: mode.vrefresh,
+            name: name.to_string_lossy().into_owned(),
+        }
+    }
+}
+
+/// The CRTC that was driving the connector before the context took it, restored on drop.
+struct SavedCrtc {
+    buffer_id: u32,
+    x: u32,
+    y: u32,
+    mode: Option<DrmModeModeInfo>,
+}
+
+/// The buffers of the surface that are known to the kernel, and the one that is scanned out.
+struct ScanoutState {
+    // The framebuffer created for each `gbm_bo` of the surface.
+    framebuffers: Vec<(*mut c_void, u32)>,
+    // The buffer locked by the last swap, released after the next page flip.
+    front: Option<GbmBuffer>,
+}
+
+/// An EGL context that presents straight to a connector through KMS, without any display server.
+///
+/// The context renders with EGL to a `gbm_surface` of the size of the preferred mode of the
+/// connector. The first call to `swap_buffers` sets the mode, and the following ones schedule a
+/// page flip and wait for it, so that presentation is always synchronized with the vertical
+/// blank. The mode that was set before is restored when the context is dropped.
+///
+/// Setting modes requires being the DRM master of the device, which is the case for the first
+/// process that opens it when no display server runs, or for a process started by a session
+/// manager such as `seatd`.
+pub struct KmsContext {
+    drm: Drm,
+    connector: u32,
+    crtc: u32,
+    mode: DrmModeModeInfo,
+    // The fourcc code of the format of the surface.
+    format: u32,
+    saved_crtc: Option<SavedCrtc>,
+    state: RefCell<ScanoutState>,
+    // The context is dropped before the surface, which is dropped before the device.
+    context: egl::Context,
+    surface: GbmSurface,
+    device: Arc<GbmDevice>,
+}
+
+unsafe impl Send for KmsContext {}
+
+impl KmsContext {
+    /// Opens `device`, finds a CRTC for `connector` and builds a context on it.
+    ///
+    /// Since render nodes can't set modes, `DrmDevice::FirstRenderNode` selects the first
+    /// primary node that can be opened, such as `/dev/dri/card0`, and `DrmDevice::PciId` the
+    /// primary node of the GPU. The builder can't share lists with another context.
+    pub fn new<T: ContextCurrentState>(device: &DrmDevice, connector: KmsConnector,
+                                       cb: ContextBuilder<T>)
+                                       -> Result<KmsContext, CreationError>
+    {
+        let ContextBuilder { pf_reqs, gl_attr } = cb;
+        if gl_attr.sharing.is_some() {
+            return Err(CreationError::NotSupported("KMS contexts can't share lists"));
+        }
+        let gl_attr = gl_attr.map_sharing(|_| unreachable!());
+
+        let drm = unsafe { Drm::load() }
+            .ok_or(CreationError::NotSupported("libdrm not present"))?;
+        let egl = x11::GlxOrEgl::new().egl
+            .ok_or(CreationError::NotSupported("libEGL not present"))?;
+        let device = Arc::new(GbmDevice::open_primary(device)?);
+
+        let (connector, crtc, mode) = unsafe { find_output(&drm, device.fd(), &connector)? };
+        let saved_crtc = unsafe { save_crtc(&drm, device.fd(), crtc) };
+
+        let (context, surface, format) = create_with_versions(&gl_attr, |gl_attr| {
+            create_with_fallbacks(&pf_reqs, |pf_reqs| {
+                KmsContext::new_egl(&egl, &device, (mode.hdisplay as u32, mode.vdisplay as u32),
+                                    pf_reqs, gl_attr.clone())
+            })
+        })?.0;
+
+        Ok(KmsContext {
+            drm: drm,
+            connector: connector,
+            crtc: crtc,
+            mode: mode,
+            format: format,
+            saved_crtc: saved_crtc,
+            state: RefCell::new(ScanoutState { framebuffers: Vec::new(), front: None }),
+            context: context,
+            surface: surface,
+            device: device,
+        })
+    }
+
+    /// Builds the EGL context and its `gbm_surface`, in the format of the native visual of the
+    /// config chosen by EGL.
+    fn new_egl(egl: &egl::ffi::egl::Egl, device: &Arc<GbmDevice>, dimensions: (u32, u32),
+               pf_reqs: &PixelFormatRequirements, gl_attr: GlAttributes<&egl::Context>)
+               -> Result<(egl::Context, GbmSurface, u32), CreationError>
+    {
+        let native_display = egl::NativeDisplay::Gbm(Some(device.as_ptr() as *const c_void));
+        let prototype = egl::Context::new(egl.clone(), pf_reqs, gl_attr, native_display)?;
+        let format = match prototype.get_native_visual_id() as u32 {
+            0 => DRM_FORMAT_XRGB8888,
+            format => format,
+        };
+        if framebuffer_depth(format).is_none() {
+            return Err(CreationError::NoAvailablePixelFormat);
+        }
+        let surface = GbmSurface::new(device.clone(), dimensions.0, dimensions.1, format)?;
+        let context = prototype.finish(surface.as_ptr() as egl::ffi::EGLNativeWindowType)?;
+        Ok((context, surface, format))
+    }
+
+    /// Returns the id of the connector the context presents to.
+    #[inline]
+    pub fn connector_id(&self) -> u32 {
+        self.connector
+    }
+
+    /// Returns the id of the CRTC that drives the connector.
+    #[inline]
+    pub fn crtc_id(&self) -> u32 {
+        self.crtc
+    }
+
+    /// Returns the mode that is set on the connector, whose dimensions are the ones of the
+    /// surface.
+    #[inline]
+    pub fn mode(&self) -> KmsMode {
+        KmsMode::from_raw(&self.mode)
+    }
+
+    /// Returns the file descriptor of the primary node, for example to read the properties of
+    /// the connector with libdrm.
+    #[inline]
+    pub fn drm_fd(&self) -> RawFd {
+        self.device.fd()
+    }
+
+    #[inline]
+    pub unsafe fn make_current(&self) -> Result<(), ContextError> {
+        self.context.make_current()
+    }
+
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        self.context.make_not_current()
+    }
+
+    #[inline]
+    pub fn is_current(&self) -> bool {
+        self.context.is_current()
+    }
+
+    #[inline]
+    pub fn get_proc_address(&self, addr: &str) -> *const () {
+        self.context.get_proc_address(addr)
+    }
+
+    /// Swaps the buffers of the surface and scans the new front buffer out, waiting for the
+    /// page flip to complete.
+    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.context.swap_buffers()?;
+        let buffer = self.surface.lock_front_buffer().ok_or_else(|| {
+            ContextError::OsError(format!("gbm_surface_lock_front_buffer failed"))
+        })?;
+
+        let mut state = self.state.borrow_mut();
+        let presented = self.framebuffer(&mut state, &buffer).and_then(|framebuffer| unsafe {
+            if state.front.is_none() {
+                self.set_crtc(framebuffer)
+            } else {
+                self.page_flip(framebuffer)
+            }
+        });
+        if let Err(err) = presented {
+            self.surface.release_buffer(buffer);
+            return Err(err);
+        }
+
+        // the previous buffer isn't scanned out anymore once the flip completed
+        if let Some(previous) = mem::replace(&mut state.front, Some(buffer)) {
+            self.surface.release_buffer(previous);
+        }
+        Ok(())
+    }
+
+    /// Sets the swap interval of the EGL surface. Presentation is synchronized with the vertical
+    /// blank by the page flips regardless.
+    #[inline]
+    pub fn set_swap_interval(&self, interval: SwapInterval) -> Result<(), ContextError> {
+        self.context.set_swap_interval(interval)
+    }
+
+    #[inline]
+    pub fn get_api(&self) -> Api {
+        self.context.get_api()
+    }
+
+    #[inline]
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        self.context.get_pixel_format()
+    }
+
+    #[inline]
+    pub unsafe fn raw_handle(&self) -> egl::ffi::egl::types::EGLContext {
+        self.context.raw_handle()
+    }
+
+    /// Returns the DRM framebuffer of `buffer`, creating it the first time the buffer is seen.
+    fn framebuffer(&self, state: &mut ScanoutState, buffer: &GbmBuffer)
+                   -> Result<u32, ContextError>
+    {
+        if let Some(&(_, framebuffer)) = state.framebuffers.iter()
+            .find(|&&(bo, _)| bo == buffer.as_ptr())
+        {
+            return Ok(framebuffer);
+        }
+
+        let (depth, bpp) = framebuffer_depth(self.format).unwrap();
+        let mut framebuffer = 0;
+        let ret = unsafe {
+            (self.drm.add_fb)(self.device.fd(), self.mode.hdisplay as u32,
+                              self.mode.vdisplay as u32, depth, bpp,
+                              self.surface.buffer_stride(buffer),
+                              self.surface.buffer_handle(buffer), &mut framebuffer)
+        };
+        if ret != 0 {
+            return Err(ContextError::IoError(io::Error::from_raw_os_error(-ret)));
+        }
+        state.framebuffers.push((buffer.as_ptr(), framebuffer));
+        Ok(framebuffer)
+    }
+
+    /// Sets the mode on the connector, scanning out `framebuffer`.
+    unsafe fn set_crtc(&self, framebuffer: u32) -> Result<(), ContextError> {
+        let mut connector = self.connector;
+        let mut mode = self.mode;
+        let ret = (self.drm.set_crtc)(self.device.fd(), self.crtc, framebuffer, 0, 0,
+                                      &mut connector, 1, &mut mode);
+        if ret != 0 {
+            return Err(ContextError::IoError(io::Error::from_raw_os_error(-ret)));
+        }
+        Ok(())
+    }
+
+    /// Schedules a page flip to `framebuffer` on the next vertical blank, and waits for it.
+    unsafe fn page_flip(&self, framebuffer: u32) -> Result<(), ContextError> {
+        let fd = self.device.fd();
+        let mut flipped = false;
+        let ret = (self.drm.page_flip)(fd, self.crtc, framebuffer, DRM_MODE_PAGE_FLIP_EVENT,
+                                       &mut flipped as *mut bool as *mut c_void);
+        if ret != 0 {
+            return Err(ContextError::IoError(io::Error::from_raw_os_error(-ret)));
+        }
+
+        let mut event_context = DrmEventContext {
+            version: 2,
+            vblank_handler: None,
+            page_flip_handler: Some(page_flip_handler),
+        };
+        while !flipped {
+            let mut pollfd = libc::pollfd { fd: fd, events: libc::POLLIN, revents: 0 };
+            if libc::poll(&mut pollfd, 1, -1) < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(ContextError::IoError(err));
+            }
+            if (self.drm.handle_event)(fd, &mut event_context) != 0 {
+                return Err(ContextError::OsError(format!("drmHandleEvent failed")));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for KmsContext {
+    fn drop(&mut self) {
+        let fd = self.device.fd();
+        unsafe {
+            if let Some(ref saved) = self.saved_crtc {
+                let mut connector = self.connector;
+                let mut mode = saved.mode;
+                let mode_ptr = mode.as_mut().map_or(ptr::null_mut(), |mode| mode as *mut _);
+                (self.drm.set_crtc)(fd, self.crtc, saved.buffer_id, saved.x, saved.y,
+                                    &mut connector, 1, mode_ptr);
+            }
+            let state = self.state.get_mut();
+            for &(_, framebuffer) in &state.framebuffers {
+                (self.drm.rm_fb)(fd, framebuffer);
+            }
+            if let Some(front) = state.front.take() {
+                self.surface.release_buffer(front);
+            }
+        }
+    }
+}
+
+impl ::GlContext for KmsContext {
+    #[inline]
+    fn is_current(&self) -> bool {
+        KmsContext::is_current(self)
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        KmsContext::get_proc_address(self, addr)
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        KmsContext::swap_buffers(self)
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        KmsContext::get_api(self)
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        KmsContext::get_pixel_format(self)
+    }
+
+    #[inline]
+    fn resize(&self, _: u32, _: u32) {
+    }
+}
+
+extern "C" fn page_flip_handler(_: c_int, _: c_uint, _: c_uint, _: c_uint, data: *mut c_void) {
+    unsafe { *(data as *mut bool) = true; }
+}
+
+/// Returns the depth and the bits per pixel passed to `drmModeAddFB` for `format`, or `None` if
+/// it can't be scanned out that way.
+fn framebuffer_depth(format: u32) -> Option<(u8, u8)> {
+    match format {
+        DRM_FORMAT_XRGB8888 | DRM_FORMAT_ARGB8888 => Some((24, 32)),
+        DRM_FORMAT_RGB565 => Some((16, 16)),
+        _ => None,
+    }
+}
+
+/// Finds the connector selected by `selection`, a CRTC that can drive it and its preferred
+/// mode. The CRTC that already drives the connector is kept if there is one.
+unsafe fn find_output(drm: &Drm, fd: c_int, selection: &KmsConnector)
+                      -> Result<(u32, u32, DrmModeModeInfo), CreationError>
+{
+    let resources = (drm.get_resources)(fd);
+    if resources.is_null() {
+        return Err(CreationError::OsError(format!("drmModeGetResources failed: {}",
+                                                  io::Error::last_os_error())));
+    }
+    let output = find_output_in(drm, fd, &*resources, selection);
+    (drm.free_resources)(resources);
+    output
+}
+
+unsafe fn find_output_in(drm: &Drm, fd: c_int, resources: &DrmModeRes, selection: &KmsConnector)
+                         -> Result<(u32, u32, DrmModeModeInfo), CreationError>
+{
+    let connectors = raw_slice(resources.connectors, resources.count_connectors);
+    let crtcs = raw_slice(resources.crtcs, resources.count_crtcs);
+
+    for &id in connectors {
+        if let KmsConnector::Id(wanted) = *selection {
+            if id != wanted {
+                continue;
+            }
+        }
+        let connector = (drm.get_connector)(fd, id);
+        if connector.is_null() {
+            continue;
+        }
+
+        let output = {
+            let info = &*connector;
+            let modes = raw_slice(info.modes, info.count_modes);
+            if info.connection != DRM_MODE_CONNECTED || modes.is_empty() {
+                None
+            } else {
+                let mode = *modes.iter()
+                    .find(|mode| mode.type_ & DRM_MODE_TYPE_PREFERRED != 0)
+                    .unwrap_or(&modes[0]);
+                find_crtc(drm, fd, info, crtcs).map(|crtc| (id, crtc, mode))
+            }
+        };
+        (drm.free_connector)(connector);
+
+        match (output, selection) {
+            (Some(output), _) => return Ok(output),
+            (None, &KmsConnector::Id(_)) => {
+                return Err(CreationError::OsError(format!("the connector {} has no display \
+                                                           plugged in or no free CRTC", id)));
+            },
+            (None, &KmsConnector::FirstConnected) => (),
+        }
+    }
+
+    Err(match *selection {
+        KmsConnector::Id(id) => CreationError::OsError(format!("no connector with the id {}", id)),
+        KmsConnector::FirstConnected => {
+            CreationError::NotSupported("no connector has a display plugged in")
+        },
+    })
+}
+
+/// Returns the CRTC of the current encoder of `connector`, or the first CRTC that one of its
+/// encoders can use.
+unsafe fn find_crtc(drm: &Drm, fd: c_int, connector: &DrmModeConnector, crtcs: &[u32])
+                    -> Option<u32>
+{
+    if connector.encoder_id != 0 {
+        let encoder = (drm.get_encoder)(fd, connector.encoder_id);
+        if !encoder.is_null() {
+            let crtc = (*encoder).crtc_id;
+            (drm.free_encoder)(encoder);
+            if crtc != 0 {
+                return Some(crtc);
+            }
+        }
+    }
+
+    for &id in raw_slice(connector.encoders, connector.count_encoders) {
+        let encoder = (drm.get_encoder)(fd, id);
+        if encoder.is_null() {
+            continue;
+        }
+        let possible_crtcs = (*encoder).possible_crtcs;
+        (drm.free_encoder)(encoder);
+        let crtc = crtcs.iter().enumerate()
+            .find(|&(index, _)| index < 32 && possible_crtcs & (1 << index) != 0)
+            .map(|(_, &crtc)| crtc);
+        if crtc.is_some() {
+            return crtc;
+        }
+    }
+    None
+}
+
+/// Reads the current configuration of `crtc`, to restore it when the context is dropped.
+unsafe fn save_crtc(drm: &Drm, fd: c_int, crtc: u32) -> Option<SavedCrtc> {
+    let info = (drm.get_crtc)(fd, crtc);
+    if info.is_null() {
+        return None;
+    }
+    let saved = SavedCrtc {
+        buffer_id: (*info).buffer_id,
+        x: (*info).x,
+        y: (*info).y,
+        mode: if (*info).mode_valid != 0 { Some((*info).mode) } else { None },
+    };
+    (drm.free_crtc)(info);
+    Some(saved)
+}
+
+unsafe fn raw_slice<'a, T>(data: *const T, count: c_int) -> &'a [T] {
+    if data.is_null() || count <= 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, count as usize)
+    }
+}
@@ -1,18 +1,38 @@
 #![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
 
-use {Api, ContextError, CreationError, GlAttributes, PixelFormat, PixelFormatRequirements};
+use {Api, Backend, ContextError, CreationError, GlAttributes, GlxSwapControl, PixelFormat};
+use {PixelFormatRequirements, PresentMode, PresentationFeedback, PresentationTime, Rect};
+use {Colorspace, MultisampleResolve, PbufferAttributes, PbufferLimits, RawContextHandle};
+use {DeviceInfo, RenderBuffer, SupportedVersions, SwapBehavior};
+use SwapInterval;
+use {RawDisplayHandle, RawPixmapHandle, RawWindowHandle};
 use api::egl;
 use api::glx;
 use api::osmesa::OsMesaContext;
+pub use api::osmesa::{OsMesaDepthBuffer, OsMesaFormat};
 use self::x11::GlContext;
+#[cfg(feature = "winit")]
+use self::x11::XConnection;
 
+#[cfg(feature = "winit")]
 use winit;
+#[cfg(feature = "winit")]
 use winit::os::unix::EventsLoopExt;
 
 use std::os::raw::c_void;
+#[cfg(feature = "winit")]
+use std::os::raw;
+use std::path::PathBuf;
+use std::sync::Arc;
 
+pub use self::gbm::{DrmDevice, DrmDeviceInfo};
+pub use self::kms::{KmsConnector, KmsContext, KmsMode};
+
+mod gbm;
+mod kms;
 mod wayland;
 mod x11;
+mod xpresent;
 
 /// Context handles available on Unix-like platforms.
 #[derive(Clone, Debug)]
@@ -26,7 +46,225 @@ pub enum Context {
     Wayland(wayland::Context)
 }
 
+/// The context that was current on the calling thread when `Context::current_state` was called.
+pub enum CurrentState {
+    X(x11::CurrentState),
+    Wayland(egl::CurrentState),
+}
+
+/// A display connection provided by the user.
+pub enum Display {
+    X(x11::Display),
+    Wayland(*const c_void),
+}
+
+impl Display {
+    pub unsafe fn from_raw(display_handle: RawDisplayHandle) -> Result<Display, CreationError> {
+        match display_handle {
+            RawDisplayHandle::Xlib { display } => {
+                x11::Display::from_raw(display as *mut _).map(Display::X)
+            },
+            RawDisplayHandle::Wayland { display } => Ok(Display::Wayland(display)),
+            _ => Err(CreationError::NotSupported("the display handle is not an Xlib or Wayland \
+                                                  handle")),
+        }
+    }
+
+    pub fn find_configs(&self, reqs: &PixelFormatRequirements)
+                        -> Result<Vec<Config>, CreationError>
+    {
+        match *self {
+            Display::X(ref display) => {
+                display.find_configs(reqs).map(|configs| {
+                    configs.into_iter().map(Config::X).collect()
+                })
+            },
+            Display::Wayland(display) => {
+                let configs = unsafe { wayland::find_configs(display, reqs)? };
+                Ok(configs.into_iter().map(Config::Wayland).collect())
+            },
+        }
+    }
+
+    pub fn device_info(&self) -> Result<DeviceInfo, CreationError> {
+        match *self {
+            Display::X(ref display) => display.device_info(),
+            Display::Wayland(display) => unsafe { wayland::device_info(display) },
+        }
+    }
+
+    pub fn probe_versions(&self, api: Api) -> Result<SupportedVersions, CreationError> {
+        match *self {
+            Display::X(ref display) => display.probe_versions(api),
+            Display::Wayland(display) => unsafe { wayland::probe_versions(display, api) },
+        }
+    }
+}
+
+/// A config of a display provided by the user.
+#[derive(Clone)]
+pub enum Config {
+    X(x11::Config),
+    Wayland(wayland::Config),
+}
+
+impl Config {
+    #[inline]
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        match *self {
+            Config::X(ref config) => config.get_pixel_format(),
+            Config::Wayland(ref config) => config.get_pixel_format(),
+        }
+    }
+
+    #[inline]
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        match *self {
+            Config::X(ref config) => config.max_pbuffer_size(),
+            Config::Wayland(ref config) => config.max_pbuffer_size(),
+        }
+    }
+
+    pub unsafe fn create_window_surface(&self, window_handle: RawWindowHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        match (self, window_handle) {
+            (&Config::X(ref config), RawWindowHandle::Xlib { window }) => {
+                config.create_window_surface(window).map(Surface::X)
+            },
+            (&Config::Wayland(ref config),
+             RawWindowHandle::Wayland { surface, width, height }) => {
+                config.create_window_surface(surface, width, height).map(Surface::Wayland)
+            },
+            _ => Err(CreationError::NotSupported("the window handle doesn't match the config")),
+        }
+    }
+
+    pub fn create_pbuffer_surface(&self, attributes: &PbufferAttributes)
+                                  -> Result<Surface, CreationError>
+    {
+        match *self {
+            Config::X(ref config) => config.create_pbuffer_surface(attributes).map(Surface::X),
+            Config::Wayland(ref config) => {
+                config.create_pbuffer_surface(attributes).map(Surface::Wayland)
+            },
+        }
+    }
+
+    pub unsafe fn create_pixmap_surface(&self, pixmap_handle: RawPixmapHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        match (self, pixmap_handle) {
+            (&Config::X(ref config), RawPixmapHandle::Xlib { pixmap }) => {
+                config.create_pixmap_surface(pixmap).map(Surface::X)
+            },
+            (&Config::Wayland(_), _) => {
+                Err(CreationError::NotSupported("pixmap surfaces are not supported on wayland"))
+            },
+        }
+    }
+}
+
+/// A surface created from the config of a context.
+pub enum Surface {
+    X(x11::Surface),
+    Wayland(wayland::Surface),
+}
+
+impl Surface {
+    #[inline]
+    pub fn dimensions(&self) -> Result<(u32, u32), ContextError> {
+        match *self {
+            Surface::X(ref surface) => surface.dimensions(),
+            Surface::Wayland(ref surface) => surface.dimensions(),
+        }
+    }
+
+    #[inline]
+    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        match *self {
+            Surface::X(ref surface) => surface.swap_buffers(),
+            Surface::Wayland(ref surface) => surface.swap_buffers(),
+        }
+    }
+
+    #[inline]
+    pub fn buffer_age(&self) -> Result<u32, ContextError> {
+        match *self {
+            Surface::X(ref surface) => surface.buffer_age(),
+            Surface::Wayland(ref surface) => surface.buffer_age(),
+        }
+    }
+
+    #[inline]
+    pub fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        match *self {
+            Surface::X(ref surface) => surface.set_damage_region(rects),
+            Surface::Wayland(ref surface) => surface.set_damage_region(rects),
+        }
+    }
+
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        match *self {
+            Surface::X(ref surface) => surface.swap_behavior(),
+            Surface::Wayland(ref surface) => surface.swap_behavior(),
+        }
+    }
+
+    #[inline]
+    pub fn set_swap_behavior(&self, behavior: SwapBehavior) -> Result<(), ContextError> {
+        match *self {
+            Surface::X(ref surface) => surface.set_swap_behavior(behavior),
+            Surface::Wayland(ref surface) => surface.set_swap_behavior(behavior),
+        }
+    }
+
+    #[inline]
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        match *self {
+            Surface::X(ref surface) => surface.render_buffer(),
+            Surface::Wayland(ref surface) => surface.render_buffer(),
+        }
+    }
+
+    #[inline]
+    pub fn set_render_buffer(&self, render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        match *self {
+            Surface::X(ref surface) => surface.set_render_buffer(render_buffer),
+            Surface::Wayland(ref surface) => surface.set_render_buffer(render_buffer),
+        }
+    }
+
+    #[inline]
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        match *self {
+            Surface::X(ref surface) => surface.multisample_resolve(),
+            Surface::Wayland(ref surface) => surface.multisample_resolve(),
+        }
+    }
+
+    #[inline]
+    pub fn set_multisample_resolve(&self, resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        match *self {
+            Surface::X(ref surface) => surface.set_multisample_resolve(resolve),
+            Surface::Wayland(ref surface) => surface.set_multisample_resolve(resolve),
+        }
+    }
+
+    #[inline]
+    pub fn resize(&self, width: u32, height: u32) {
+        match *self {
+            Surface::X(_) => (),
+            Surface::Wayland(ref surface) => surface.resize(width, height),
+        }
+    }
+}
+
 impl Context {
+    #[cfg(feature = "winit")]
     #[inline]
     pub fn new(
         window_builder: winit::WindowBuilder,
@@ -60,6 +298,225 @@ impl Context {
         }
     }
 
+    /// Builds a context on a X11 window that was created by the user.
+    #[cfg(feature = "winit")]
+    #[inline]
+    pub unsafe fn new_raw_x11_context(
+        xconn: &Arc<XConnection>,
+        xlib_window: raw::c_ulong,
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        if let Some(&Context::Wayland(_)) = gl_attr.sharing {
+            let msg = "Cannot share a X11 context with an wayland context";
+            return Err(CreationError::PlatformSpecific(msg.into()));
+        }
+        let gl_attr = gl_attr.clone().map_sharing(|ctxt| match ctxt {
+            &Context::Wayland(_) => unreachable!(),
+            &Context::X(ref ctxt) => ctxt,
+        });
+        x11::Context::new_raw_context(xconn, xlib_window, pf_reqs, &gl_attr)
+            .map(Context::X)
+    }
+
+    /// Builds a context on a wayland surface that was created by the user.
+    #[inline]
+    pub unsafe fn new_raw_wayland_context(
+        display: *const c_void,
+        surface: *mut c_void,
+        width: u32,
+        height: u32,
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        if let Some(&Context::X(_)) = gl_attr.sharing {
+            let msg = "Cannot share a wayland context with an X11 context";
+            return Err(CreationError::PlatformSpecific(msg.into()));
+        }
+        let gl_attr = gl_attr.clone().map_sharing(|ctxt| match ctxt {
+            &Context::X(_) => unreachable!(),
+            &Context::Wayland(ref ctxt) => ctxt,
+        });
+        wayland::Context::new_raw_context(display, surface, width, height, pf_reqs, &gl_attr)
+            .map(Context::Wayland)
+    }
+
+    /// Builds a context on a window that was created by another library.
+    pub unsafe fn new_from_raw_handles(
+        display_handle: RawDisplayHandle,
+        window_handle: RawWindowHandle,
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        let display = Display::from_raw(display_handle)?;
+        Context::new_with_display(&display, window_handle, pf_reqs, gl_attr)
+    }
+
+    /// Builds a context on a window that was created by another library, using a display
+    /// provided by the user.
+    pub unsafe fn new_with_display(
+        display: &Display,
+        window_handle: RawWindowHandle,
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        match (display, window_handle) {
+            (&Display::X(ref display), RawWindowHandle::Xlib { window }) => {
+                if let Some(&Context::Wayland(_)) = gl_attr.sharing {
+                    let msg = "Cannot share a X11 context with an wayland context";
+                    return Err(CreationError::PlatformSpecific(msg.into()));
+                }
+                let gl_attr = gl_attr.clone().map_sharing(|ctxt| match ctxt {
+                    &Context::Wayland(_) => unreachable!(),
+                    &Context::X(ref ctxt) => ctxt,
+                });
+                x11::Context::new_raw_context_with_display(display, window, pf_reqs, &gl_attr)
+                    .map(Context::X)
+            },
+            (&Display::Wayland(display), RawWindowHandle::Wayland { surface, width, height }) => {
+                Context::new_raw_wayland_context(display, surface, width, height, pf_reqs,
+                                                 gl_attr)
+            },
+            _ => Err(CreationError::NotSupported("the window handle doesn't match the display")),
+        }
+    }
+
+    /// Wraps a context created by another library, drawing to a window that was also created
+    /// by another library.
+    pub unsafe fn adopt_raw_context(
+        display: &Display,
+        raw_handle: RawHandle,
+        window_handle: RawWindowHandle,
+    ) -> Result<Self, CreationError>
+    {
+        match (display, raw_handle, window_handle) {
+            (&Display::X(ref display), raw_handle, RawWindowHandle::Xlib { window }) => {
+                x11::Context::adopt_raw_context(display, raw_handle, window).map(Context::X)
+            },
+            (&Display::Wayland(display), RawHandle::Egl(context),
+             RawWindowHandle::Wayland { surface, width, height }) =>
+            {
+                wayland::Context::adopt_raw_context(display, context, surface, width, height)
+                    .map(Context::Wayland)
+            },
+            (&Display::Wayland(_), RawHandle::Glx(_), _) => {
+                Err(CreationError::NotSupported("GLX contexts can't be used on wayland"))
+            },
+            _ => Err(CreationError::NotSupported("the window handle doesn't match the display")),
+        }
+    }
+
+    /// Builds a context on a window that was created by another library, using a config
+    /// returned by `Display::find_configs`.
+    pub unsafe fn new_with_config(
+        config: &Config,
+        window_handle: RawWindowHandle,
+        gl_attr: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        match (config, window_handle) {
+            (&Config::X(ref config), RawWindowHandle::Xlib { window }) => {
+                if let Some(&Context::Wayland(_)) = gl_attr.sharing {
+                    let msg = "Cannot share a X11 context with an wayland context";
+                    return Err(CreationError::PlatformSpecific(msg.into()));
+                }
+                let gl_attr = gl_attr.clone().map_sharing(|ctxt| match ctxt {
+                    &Context::Wayland(_) => unreachable!(),
+                    &Context::X(ref ctxt) => ctxt,
+                });
+                x11::Context::new_with_config(config, window, &gl_attr).map(Context::X)
+            },
+            (&Config::Wayland(ref config), RawWindowHandle::Wayland { surface, width, height }) => {
+                if let Some(&Context::X(_)) = gl_attr.sharing {
+                    let msg = "Cannot share a wayland context with an X11 context";
+                    return Err(CreationError::PlatformSpecific(msg.into()));
+                }
+                let gl_attr = gl_attr.clone().map_sharing(|ctxt| match ctxt {
+                    &Context::X(_) => unreachable!(),
+                    &Context::Wayland(ref ctxt) => ctxt,
+                });
+                wayland::Context::new_with_config(config, surface, width, height, &gl_attr)
+                    .map(Context::Wayland)
+            },
+            _ => Err(CreationError::NotSupported("the window handle doesn't match the config")),
+        }
+    }
+
+    /// Destroys this context and builds a new one with the same config, drawing to the same
+    /// window. Lists are shared with the context of `gl_attr` through its raw handle.
+    pub unsafe fn recreate(self, gl_attr: &GlAttributes<&Context>) -> Result<Self, CreationError> {
+        // contexts built from a config share lists through the raw handle of the other context
+        let raw_sharing = match gl_attr.sharing {
+            Some(share) if gl_attr.raw_sharing.is_none() => Some(match share.raw_handle() {
+                RawHandle::Glx(context) => RawContextHandle::Glx { context: context as *const _ },
+                RawHandle::Egl(context) => RawContextHandle::Egl { context: context as *const _ },
+            }),
+            _ => gl_attr.raw_sharing,
+        };
+        let mut gl_attr = gl_attr.clone().set_sharing(None::<()>);
+        gl_attr.raw_sharing = raw_sharing;
+        match self {
+            Context::X(ctxt) => {
+                ctxt.recreate(&gl_attr.clone().set_sharing(None)).map(Context::X)
+            },
+            Context::Wayland(ctxt) => {
+                ctxt.recreate(&gl_attr.clone().set_sharing(None)).map(Context::Wayland)
+            },
+        }
+    }
+
+    /// Returns the config this context was created with.
+    #[inline]
+    pub fn config(&self) -> Config {
+        match *self {
+            Context::X(ref ctxt) => Config::X(ctxt.config()),
+            Context::Wayland(ref ctxt) => Config::Wayland(ctxt.config()),
+        }
+    }
+
+    #[inline]
+    pub unsafe fn create_window_surface(&self, window_handle: RawWindowHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        self.config().create_window_surface(window_handle)
+    }
+
+    #[inline]
+    pub fn create_pbuffer_surface(&self, attributes: &PbufferAttributes)
+                                  -> Result<Surface, CreationError>
+    {
+        self.config().create_pbuffer_surface(attributes)
+    }
+
+    #[inline]
+    pub unsafe fn create_pixmap_surface(&self, pixmap_handle: RawPixmapHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        self.config().create_pixmap_surface(pixmap_handle)
+    }
+
+    #[inline]
+    pub unsafe fn make_current_surfaces(&self, draw: &Surface, read: &Surface)
+                                        -> Result<(), ContextError>
+    {
+        match (self, draw, read) {
+            (&Context::X(ref ctxt), &Surface::X(ref draw), &Surface::X(ref read)) => {
+                ctxt.make_current_surfaces(draw, read)
+            },
+            (&Context::Wayland(ref ctxt), &Surface::Wayland(ref draw),
+             &Surface::Wayland(ref read)) => {
+                ctxt.make_current_surfaces(draw, read)
+            },
+            _ => Err(ContextError::OsError(
+                "the surfaces weren't created with the same backend as the context".to_string()
+            )),
+        }
+    }
+
     pub fn resize(&self, width: u32, height: u32) {
         match *self {
             Context::X(ref _ctxt) => (),
@@ -75,6 +532,14 @@ impl Context {
         }
     }
 
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        match *self {
+            Context::X(ref ctxt) => ctxt.make_not_current(),
+            Context::Wayland(ref ctxt) => ctxt.make_not_current()
+        }
+    }
+
     #[inline]
     pub fn is_current(&self) -> bool {
         match *self {
@@ -83,6 +548,176 @@ impl Context {
         }
     }
 
+    /// Checks that the native context and its window are still valid.
+    #[inline]
+    pub fn validate(&self) -> Result<(), ContextError> {
+        match *self {
+            Context::X(ref ctxt) => ctxt.validate(),
+            Context::Wayland(ref ctxt) => ctxt.validate(),
+        }
+    }
+
+    #[inline]
+    pub fn set_swap_interval(&self, interval: SwapInterval) -> Result<(), ContextError> {
+        match *self {
+            Context::X(ref ctxt) => ctxt.set_swap_interval(interval),
+            Context::Wayland(ref ctxt) => ctxt.set_swap_interval(interval)
+        }
+    }
+
+    #[inline]
+    pub fn get_swap_interval(&self) -> Result<SwapInterval, ContextError> {
+        match *self {
+            Context::X(ref ctxt) => ctxt.get_swap_interval(),
+            Context::Wayland(ref ctxt) => ctxt.get_swap_interval()
+        }
+    }
+
+    #[inline]
+    pub fn get_swap_interval_range(&self) -> Result<(u32, u32), ContextError> {
+        match *self {
+            Context::X(ref ctxt) => ctxt.get_swap_interval_range(),
+            Context::Wayland(ref ctxt) => ctxt.get_swap_interval_range()
+        }
+    }
+
+    #[inline]
+    pub fn supported_present_modes(&self) -> Result<Vec<PresentMode>, ContextError> {
+        match *self {
+            Context::X(ref ctxt) => ctxt.supported_present_modes(),
+            Context::Wayland(ref ctxt) => ctxt.supported_present_modes()
+        }
+    }
+
+    #[inline]
+    pub fn glx_swap_control(&self) -> Option<GlxSwapControl> {
+        match *self {
+            Context::X(ref ctxt) => ctxt.glx_swap_control(),
+            Context::Wayland(_) => None
+        }
+    }
+
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        match *self {
+            Context::X(ref ctxt) => ctxt.swap_behavior(),
+            Context::Wayland(ref ctxt) => ctxt.swap_behavior()
+        }
+    }
+
+    #[inline]
+    pub fn colorspace(&self) -> Result<Colorspace, ContextError> {
+        match *self {
+            Context::X(ref ctxt) => ctxt.colorspace(),
+            Context::Wayland(ref ctxt) => ctxt.colorspace()
+        }
+    }
+
+    #[inline]
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        match *self {
+            Context::X(ref ctxt) => ctxt.render_buffer(),
+            Context::Wayland(ref ctxt) => ctxt.render_buffer()
+        }
+    }
+
+    #[inline]
+    pub fn set_render_buffer(&self, render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        match *self {
+            Context::X(ref ctxt) => ctxt.set_render_buffer(render_buffer),
+            Context::Wayland(ref ctxt) => ctxt.set_render_buffer(render_buffer)
+        }
+    }
+
+    #[inline]
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        match *self {
+            Context::X(ref ctxt) => ctxt.multisample_resolve(),
+            Context::Wayland(ref ctxt) => ctxt.multisample_resolve()
+        }
+    }
+
+    #[inline]
+    pub fn set_multisample_resolve(&self, resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        match *self {
+            Context::X(ref ctxt) => ctxt.set_multisample_resolve(resolve),
+            Context::Wayland(ref ctxt) => ctxt.set_multisample_resolve(resolve)
+        }
+    }
+
+    #[inline]
+    pub fn join_swap_group(&self, group: u32) -> Result<(), ContextError> {
+        match *self {
+            Context::X(ref ctxt) => ctxt.join_swap_group(group),
+            Context::Wayland(_) => Err(ContextError::FunctionUnavailable)
+        }
+    }
+
+    #[inline]
+    pub fn bind_swap_barrier(&self, group: u32, barrier: u32) -> Result<(), ContextError> {
+        match *self {
+            Context::X(ref ctxt) => ctxt.bind_swap_barrier(group, barrier),
+            Context::Wayland(_) => Err(ContextError::FunctionUnavailable)
+        }
+    }
+
+    #[inline]
+    pub fn swap_buffers_with_frame_id(&self) -> Result<u64, ContextError> {
+        match *self {
+            Context::X(ref ctxt) => ctxt.swap_buffers_with_frame_id(),
+            Context::Wayland(ref ctxt) => ctxt.swap_buffers_with_frame_id()
+        }
+    }
+
+    #[inline]
+    pub fn try_swap_buffers(&self) -> Result<u64, ContextError> {
+        match *self {
+            Context::X(ref ctxt) => ctxt.try_swap_buffers(),
+            Context::Wayland(ref ctxt) => ctxt.try_swap_buffers()
+        }
+    }
+
+    #[inline]
+    pub fn presentation_time(&self, frame_id: u64) -> Result<PresentationTime, ContextError> {
+        match *self {
+            Context::X(ref ctxt) => ctxt.presentation_time(frame_id),
+            Context::Wayland(ref ctxt) => ctxt.presentation_time(frame_id)
+        }
+    }
+
+    #[inline]
+    pub fn presentation_feedback(&self, frame_id: u64)
+                                 -> Result<Option<PresentationFeedback>, ContextError>
+    {
+        match *self {
+            Context::X(ref ctxt) => ctxt.presentation_feedback(frame_id),
+            Context::Wayland(ref ctxt) => ctxt.presentation_feedback(frame_id)
+        }
+    }
+
+    #[inline]
+    pub fn current_state(&self) -> CurrentState {
+        match *self {
+            Context::X(ref ctxt) => CurrentState::X(ctxt.current_state()),
+            Context::Wayland(ref ctxt) => CurrentState::Wayland(ctxt.current_state())
+        }
+    }
+
+    #[inline]
+    pub unsafe fn restore_current_state(&self, state: &CurrentState) -> Result<(), ContextError> {
+        match (self, state) {
+            (&Context::X(ref ctxt), &CurrentState::X(ref state)) => {
+                ctxt.restore_current_state(state)
+            },
+            (&Context::Wayland(ref ctxt), &CurrentState::Wayland(ref state)) => {
+                ctxt.restore_current_state(state)
+            },
+            _ => unreachable!(),
+        }
+    }
+
     #[inline]
     pub fn get_proc_address(&self, addr: &str) -> *const () {
         match *self {
@@ -115,6 +750,11 @@ impl Context {
         }
     }
 
+    #[inline]
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        self.config().max_pbuffer_size()
+    }
+
     #[inline]
     pub unsafe fn raw_handle(&self) -> RawHandle {
         match *self {
@@ -128,38 +768,554 @@ impl Context {
     }
 }
 
+impl ::GlContext for Context {
+    #[inline]
+    fn is_current(&self) -> bool {
+        Context::is_current(self)
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        Context::get_proc_address(self, addr)
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        Context::swap_buffers(self)
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        Context::get_api(self)
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        Context::get_pixel_format(self)
+    }
+
+    #[inline]
+    fn resize(&self, width: u32, height: u32) {
+        Context::resize(self, width, height)
+    }
+}
+
+/// A GPU or a software renderer exposed by EGL through `EGL_EXT_device_enumeration`.
+///
+/// Headless contexts can be built on a device with
+/// `HeadlessRendererBuilderExt::with_egl_device`, which works with drivers that don't support
+/// GBM such as the proprietary NVIDIA driver.
+#[derive(Clone)]
+pub struct EglDevice {
+    device: egl::Device,
+}
+
+impl EglDevice {
+    /// Returns the devices of the system. Returns `CreationError::NotSupported` if `libEGL` is
+    /// not present or if it doesn't support `EGL_EXT_device_enumeration`.
+    pub fn enumerate() -> Result<Vec<EglDevice>, CreationError> {
+        let egl = x11::GlxOrEgl::new().egl
+            .ok_or(CreationError::NotSupported("libEGL not present"))?;
+        let devices = egl::Device::enumerate(&egl)?;
+        Ok(devices.into_iter().map(|device| EglDevice { device: device }).collect())
+    }
+
+    /// Returns the extensions supported by the device, such as `EGL_EXT_device_drm`.
+    #[inline]
+    pub fn extensions(&self) -> Vec<String> {
+        self.device.extensions()
+    }
+
+    /// Returns the name of the renderer, for example `NVIDIA GeForce RTX 3060/PCIe/SSE2`.
+    /// Returns `None` if the driver doesn't support `EGL_EXT_device_query_name`.
+    #[inline]
+    pub fn name(&self) -> Option<String> {
+        self.device.renderer()
+    }
+
+    /// Returns the vendor of the driver. Returns `None` if the driver doesn't support
+    /// `EGL_EXT_device_query_name`.
+    #[inline]
+    pub fn vendor(&self) -> Option<String> {
+        self.device.vendor()
+    }
+
+    /// Returns the path of the DRM device node, such as `/dev/dri/card0`. Returns `None` if the
+    /// device isn't a DRM device or if the driver doesn't support `EGL_EXT_device_drm`.
+    #[inline]
+    pub fn drm_device_file(&self) -> Option<PathBuf> {
+        self.device.drm_device_file()
+    }
+
+    /// Returns the path of the DRM render node, such as `/dev/dri/renderD128`. Returns `None` if
+    /// the driver doesn't support `EGL_EXT_device_drm_render_node`.
+    #[inline]
+    pub fn drm_render_node_file(&self) -> Option<PathBuf> {
+        self.device.drm_render_node_file()
+    }
+
+    /// Returns true if the device is a software renderer such as llvmpipe.
+    #[inline]
+    pub fn is_software(&self) -> bool {
+        self.device.is_software()
+    }
+}
+
 #[derive(Clone, Default)]
-pub struct PlatformSpecificHeadlessBuilderAttributes;
+pub struct PlatformSpecificHeadlessBuilderAttributes {
+    pub drm_device: Option<DrmDevice>,
+    pub egl_device: Option<EglDevice>,
+    pub backend_priority: Option<Vec<HeadlessBackend>>,
+    pub osmesa_format: Option<OsMesaFormat>,
+}
 
 pub enum HeadlessContext {
     OsMesa(OsMesaContext),
     Egl(egl::Context),
+    /// An EGL context on a `gbm_device` that was opened by glutin. The context is dropped first.
+    Gbm(egl::Context, Arc<gbm::GbmDevice>),
+    /// A GLX context drawing to a pbuffer, on the display of a windowed context whose lists it
+    /// shares or of an events loop. The context is dropped before the pbuffer, and both before
+    /// the display kept alive by the config.
+    SharedGlx(glx::Context, glx::Surface, Config),
+    /// An EGL context on the display of a windowed context whose lists it shares or of an events
+    /// loop. The config keeps the display alive.
+    SharedEgl(egl::Context, Config),
+}
+
+/// The way a headless context was built on Unix, as returned by
+/// `HeadlessContextExt::headless_backend`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeadlessBackend {
+    /// EGL on the default display or on the device chosen with `with_egl_device`, without any
+    /// surface.
+    EglSurfaceless,
+    /// EGL on the default display or on the device chosen with `with_egl_device`, with a
+    /// pbuffer.
+    EglPbuffer,
+    /// EGL on a `gbm_device` created on a DRM render node, which is returned by
+    /// `HeadlessContextExt::drm_device`.
+    Gbm,
+    /// OSMesa, which renders in software.
+    OsMesa,
+    /// GLX with a pbuffer, on the display of the windowed context whose lists are shared, or of
+    /// the events loop passed to `HeadlessContext::new_shared_display`.
+    SharedGlx,
+    /// EGL on the display of the windowed context whose lists are shared, or of the events loop
+    /// passed to `HeadlessContext::new_shared_display`.
+    SharedEgl,
 }
 
 impl HeadlessContext {
+    /// Builds a context with a pbuffer of `dimensions`, trying EGL on the default display, then
+    /// EGL on the first render node through GBM, and then OSMesa.
     pub fn new(dimensions: (u32, u32), pf_reqs: &PixelFormatRequirements,
                opengl: &GlAttributes<&HeadlessContext>,
-               _: &PlatformSpecificHeadlessBuilderAttributes)
+               attributes: &PlatformSpecificHeadlessBuilderAttributes)
                -> Result<HeadlessContext, CreationError>
     {
-        let mut opengl = opengl.clone();
-        opengl.sharing = None;
-        let opengl = opengl.map_sharing(|_| unreachable!());
+        HeadlessContext::new_with_fallbacks(dimensions, false, pf_reqs, opengl, attributes)
+    }
 
-        let backend = x11::GlxOrEgl::new();
-        let egl = backend.egl.unwrap();
+    /// Builds a context without any surface, trying EGL on the default display and then EGL on
+    /// the first render node through GBM.
+    ///
+    /// If none of them support surfaceless contexts, falls back to a context with a 1x1 pbuffer,
+    /// which can render to framebuffer objects too.
+    pub fn new_surfaceless(pf_reqs: &PixelFormatRequirements,
+                           opengl: &GlAttributes<&HeadlessContext>,
+                           attributes: &PlatformSpecificHeadlessBuilderAttributes)
+                           -> Result<HeadlessContext, CreationError>
+    {
+        HeadlessContext::new_with_fallbacks((1, 1), true, pf_reqs, opengl, attributes)
+    }
 
-        Ok(HeadlessContext::Egl(
-            egl::Context::new(egl, pf_reqs, &opengl, egl::NativeDisplay::Gbm(None)).unwrap()
-            .finish_pbuffer(dimensions).unwrap()
-        ))
+    fn new_with_fallbacks(dimensions: (u32, u32), surfaceless: bool,
+                          pf_reqs: &PixelFormatRequirements,
+                          opengl: &GlAttributes<&HeadlessContext>,
+                          attributes: &PlatformSpecificHeadlessBuilderAttributes)
+                          -> Result<HeadlessContext, CreationError>
+    {
+        if let Some(share) = opengl.sharing {
+            return HeadlessContext::new_shared_headless(dimensions, surfaceless, pf_reqs, opengl,
+                                                        share);
+        }
+        let opengl = opengl.clone().map_sharing(|_| unreachable!());
+
+        // don't fall back to anything else when the user asked for a specific GPU
+        if let Some(ref egl_device) = attributes.egl_device {
+            return if surfaceless {
+                HeadlessContext::new_egl_device(egl_device, pf_reqs, opengl.clone(),
+                                                |p| p.finish_surfaceless())
+                    .or_else(|_| {
+                        HeadlessContext::new_egl_device(egl_device, pf_reqs, opengl,
+                                                        |p| p.finish_pbuffer(dimensions))
+                    })
+            } else {
+                HeadlessContext::new_egl_device(egl_device, pf_reqs, opengl,
+                                                |p| p.finish_pbuffer(dimensions))
+            };
+        }
+        if let Some(ref drm_device) = attributes.drm_device {
+            return HeadlessContext::new_gbm(drm_device, surfaceless, dimensions, pf_reqs, opengl);
+        }
+
+        let default_priority;
+        let priority = match attributes.backend_priority {
+            Some(ref priority) => priority,
+            None => {
+                let mut priority = if surfaceless {
+                    vec![HeadlessBackend::EglSurfaceless, HeadlessBackend::EglPbuffer,
+                         HeadlessBackend::Gbm, HeadlessBackend::OsMesa]
+                } else {
+                    vec![HeadlessBackend::EglPbuffer, HeadlessBackend::Gbm,
+                         HeadlessBackend::OsMesa]
+                };
+                // OSMesa always renders in software, while EGL usually picks the GPU
+                if pf_reqs.software_rendering {
+                    priority.pop();
+                    priority.insert(0, HeadlessBackend::OsMesa);
+                }
+                default_priority = priority;
+                &default_priority
+            },
+        };
+        if priority.is_empty() {
+            return Err(CreationError::NotSupported("the headless backend priority is empty"));
+        }
+
+        let egl = x11::GlxOrEgl::new().egl;
+        let mut errors = Vec::new();
+
+        for &backend in priority {
+            let context = match backend {
+                HeadlessBackend::EglSurfaceless => {
+                    egl.clone().ok_or(CreationError::NotSupported("libEGL not present"))
+                        .and_then(|egl| {
+                            egl::Context::new(egl, pf_reqs, opengl.clone(),
+                                              egl::NativeDisplay::Gbm(None))
+                        })
+                        .and_then(|p| p.finish_surfaceless())
+                        .map(HeadlessContext::Egl)
+                },
+                HeadlessBackend::EglPbuffer => {
+                    egl.clone().ok_or(CreationError::NotSupported("libEGL not present"))
+                        .and_then(|egl| {
+                            egl::Context::new(egl, pf_reqs, opengl.clone(),
+                                              egl::NativeDisplay::Gbm(None))
+                        })
+                        .and_then(|p| p.finish_pbuffer(dimensions))
+                        .map(HeadlessContext::Egl)
+                },
+                // the default display may be a X11 or Wayland one that can't be used without a
+                // window, in which case a render node is opened explicitly
+                HeadlessBackend::Gbm => {
+                    HeadlessContext::new_gbm(&DrmDevice::FirstRenderNode, surfaceless,
+                                             dimensions, pf_reqs, opengl.clone())
+                },
+                HeadlessBackend::OsMesa => {
+                    let opengl = opengl.clone().map_sharing(|_| unreachable!());
+                    OsMesaContext::new(dimensions, pf_reqs, &opengl, attributes.osmesa_format)
+                        .map(HeadlessContext::OsMesa)
+                },
+            };
+
+            match context {
+                Ok(context) => return Ok(context),
+                Err(err) => {
+                    let backend = match backend {
+                        HeadlessBackend::OsMesa => Backend::OsMesa,
+                        _ => Backend::Egl,
+                    };
+                    errors.push((backend, err));
+                },
+            }
+        }
+
+        Err(CreationError::from_backend_errors(errors))
+    }
+
+    /// Builds a context on the display and with the config of the windowed context in
+    /// `gl_attr.sharing`, sharing its lists. With EGL, no surface is created if `surfaceless` is
+    /// true and supported. Otherwise, and always with GLX, the context draws to a pbuffer of
+    /// `dimensions`.
+    pub fn new_shared(dimensions: (u32, u32), surfaceless: bool,
+                      gl_attr: &GlAttributes<&Context>)
+                      -> Result<HeadlessContext, CreationError>
+    {
+        let share = gl_attr.sharing.expect("new_shared called without a context to share with");
+        let config = share.config();
+
+        let mut gl_attr = gl_attr.clone();
+        gl_attr.vsync = false;
+        if gl_attr.raw_sharing.is_none() {
+            gl_attr.raw_sharing = Some(match unsafe { share.raw_handle() } {
+                RawHandle::Glx(context) => RawContextHandle::Glx { context: context as *const _ },
+                RawHandle::Egl(context) => RawContextHandle::Egl { context: context as *const _ },
+            });
+        }
+
+        let egl_config = match config {
+            Config::X(ref x11_config) => match *x11_config.gl_config() {
+                x11::GlConfig::Glx(ref glx_config) => {
+                    let (context, surface) = HeadlessContext::new_glx_with_config(
+                        x11_config, glx_config, dimensions, gl_attr.set_sharing(None)
+                    )?;
+                    return Ok(HeadlessContext::SharedGlx(context, surface, config.clone()));
+                },
+                x11::GlConfig::Egl(ref egl_config) => egl_config.clone(),
+            },
+            Config::Wayland(ref wayland_config) => wayland_config.egl_config().clone(),
+        };
+
+        let context = HeadlessContext::new_egl_with_config(&egl_config, surfaceless, dimensions,
+                                                           gl_attr.set_sharing(None))?;
+        Ok(HeadlessContext::SharedEgl(context, config))
+    }
+
+    /// Builds a context on the X11 connection of `events_loop` instead of opening a display,
+    /// with a config of its default screen. With EGL, no surface is created if `surfaceless` is
+    /// true and supported. Otherwise, and always with GLX, the context draws to a pbuffer of
+    /// `dimensions`.
+    ///
+    /// The wayland display of an events loop can't be reached without a window, so wayland
+    /// events loops return `CreationError::NotSupported`.
+    #[cfg(feature = "winit")]
+    pub fn new_on_events_loop(events_loop: &winit::EventsLoop, dimensions: (u32, u32),
+                              surfaceless: bool, pf_reqs: &PixelFormatRequirements,
+                              opengl: &GlAttributes<&HeadlessContext>)
+                              -> Result<HeadlessContext, CreationError>
+    {
+        if events_loop.is_wayland() {
+            return Err(CreationError::NotSupported("the wayland display of an events loop can't \
+                                                    be used without a window"));
+        }
+        if opengl.sharing.is_some() {
+            return Err(CreationError::NotSupported("a headless context sharing lists uses the \
+                                                    display of the other context"));
+        }
+
+        let display = x11::Display::from_events_loop(events_loop)?;
+        let x11_config = display.find_configs(pf_reqs)?.into_iter().next()
+            .ok_or(CreationError::NoAvailablePixelFormat)?;
+
+        match *x11_config.gl_config() {
+            x11::GlConfig::Glx(ref glx_config) => {
+                let gl_attr = opengl.clone().map_sharing(|_| unreachable!());
+                let (context, surface) = HeadlessContext::new_glx_with_config(
+                    &x11_config, glx_config, dimensions, gl_attr
+                )?;
+                Ok(HeadlessContext::SharedGlx(context, surface, Config::X(x11_config.clone())))
+            },
+            x11::GlConfig::Egl(ref egl_config) => {
+                let gl_attr = opengl.clone().map_sharing(|_| unreachable!());
+                let context = HeadlessContext::new_egl_with_config(egl_config, surfaceless,
+                                                                   dimensions, gl_attr)?;
+                Ok(HeadlessContext::SharedEgl(context, Config::X(x11_config.clone())))
+            },
+        }
+    }
+
+    /// Builds a context with the same backend, display and config as `share`, another headless
+    /// context, sharing its lists.
+    fn new_shared_headless(dimensions: (u32, u32), surfaceless: bool,
+                           pf_reqs: &PixelFormatRequirements,
+                           opengl: &GlAttributes<&HeadlessContext>, share: &HeadlessContext)
+                           -> Result<HeadlessContext, CreationError>
+    {
+        if let HeadlessContext::OsMesa(ref mesa) = *share {
+            let opengl = opengl.clone().map_sharing(|_| mesa);
+            return OsMesaContext::new(dimensions, pf_reqs, &opengl, Some(mesa.get_format()))
+                .map(HeadlessContext::OsMesa);
+        }
+
+        let mut gl_attr = opengl.clone();
+        gl_attr.vsync = false;
+        if gl_attr.raw_sharing.is_none() {
+            let context = unsafe { share.raw_handle() } as *const c_void;
+            gl_attr.raw_sharing = Some(match *share {
+                HeadlessContext::SharedGlx(..) => RawContextHandle::Glx { context: context },
+                _ => RawContextHandle::Egl { context: context },
+            });
+        }
+
+        match *share {
+            HeadlessContext::OsMesa(_) => unreachable!(),
+            HeadlessContext::Egl(ref egl) => {
+                HeadlessContext::new_egl_with_config(&egl.config(), surfaceless, dimensions,
+                                                     gl_attr.set_sharing(None))
+                    .map(HeadlessContext::Egl)
+            },
+            HeadlessContext::Gbm(ref egl, ref device) => {
+                HeadlessContext::new_egl_with_config(&egl.config(), surfaceless, dimensions,
+                                                     gl_attr.set_sharing(None))
+                    .map(|context| HeadlessContext::Gbm(context, device.clone()))
+            },
+            HeadlessContext::SharedEgl(ref egl, ref config) => {
+                HeadlessContext::new_egl_with_config(&egl.config(), surfaceless, dimensions,
+                                                     gl_attr.set_sharing(None))
+                    .map(|context| HeadlessContext::SharedEgl(context, config.clone()))
+            },
+            HeadlessContext::SharedGlx(_, _, ref config) => {
+                let x11_config = match *config {
+                    Config::X(ref x11_config) => x11_config,
+                    Config::Wayland(_) => unreachable!(),
+                };
+                let glx_config = match *x11_config.gl_config() {
+                    x11::GlConfig::Glx(ref glx_config) => glx_config,
+                    x11::GlConfig::Egl(_) => unreachable!(),
+                };
+                let (context, surface) = HeadlessContext::new_glx_with_config(
+                    x11_config, glx_config, dimensions, gl_attr.set_sharing(None)
+                )?;
+                Ok(HeadlessContext::SharedGlx(context, surface, config.clone()))
+            },
+        }
+    }
+
+    /// Builds an EGL context with `egl_config`, without any surface if `surfaceless` is true and
+    /// supported, or with a pbuffer of `dimensions` otherwise.
+    fn new_egl_with_config(egl_config: &egl::Config, surfaceless: bool, dimensions: (u32, u32),
+                           gl_attr: GlAttributes<&egl::Context>)
+                           -> Result<egl::Context, CreationError>
+    {
+        let surfaceless_context = if surfaceless {
+            egl::Context::new_with_config(egl_config, gl_attr.clone())
+                .and_then(|p| p.finish_surfaceless())
+                .ok()
+        } else {
+            None
+        };
+        match surfaceless_context {
+            Some(context) => Ok(context),
+            None => {
+                egl::Context::new_with_config(egl_config, gl_attr)
+                    .and_then(|p| p.finish_pbuffer(dimensions))
+            },
+        }
+    }
+
+    /// Builds a GLX context with `glx_config`, drawing to a pbuffer of `dimensions` since GLX
+    /// doesn't support surfaceless contexts.
+    fn new_glx_with_config(x11_config: &x11::Config, glx_config: &glx::Config,
+                           dimensions: (u32, u32), gl_attr: GlAttributes<&glx::Context>)
+                           -> Result<(glx::Context, glx::Surface), CreationError>
+    {
+        let attributes = PbufferAttributes::new(dimensions.0, dimensions.1);
+        let surface = glx_config.create_pbuffer_surface(&attributes)?;
+        let context = glx::Context::new_with_config(x11_config.xlib(), glx_config, gl_attr,
+                                                    x11_config.screen_id())?
+            .finish(surface.raw_handle())?;
+        Ok((context, surface))
+    }
+
+    /// Builds an EGL context on a `gbm_device` created on `drm_device`. If `surfaceless` is true
+    /// but EGL doesn't support it, falls back to a pbuffer of `dimensions`.
+    fn new_gbm(drm_device: &DrmDevice, surfaceless: bool, dimensions: (u32, u32),
+               pf_reqs: &PixelFormatRequirements, opengl: GlAttributes<&egl::Context>)
+               -> Result<HeadlessContext, CreationError>
+    {
+        let egl = x11::GlxOrEgl::new().egl
+            .ok_or(CreationError::NotSupported("libEGL not present"))?;
+        let device = gbm::GbmDevice::open(drm_device)?;
+        let gbm_device = device.as_ptr() as *const c_void;
+        let native_display = move || egl::NativeDisplay::Gbm(Some(gbm_device));
+
+        let surfaceless_context = if surfaceless {
+            egl::Context::new(egl.clone(), pf_reqs, opengl.clone(), native_display())
+                .and_then(|p| p.finish_surfaceless())
+                .ok()
+        } else {
+            None
+        };
+        let context = match surfaceless_context {
+            Some(context) => context,
+            None => {
+                egl::Context::new(egl, pf_reqs, opengl, native_display())
+                    .and_then(|p| p.finish_pbuffer(dimensions))?
+            },
+        };
+        Ok(HeadlessContext::Gbm(context, Arc::new(device)))
+    }
+
+    /// Builds an EGL context on the display of `egl_device`.
+    fn new_egl_device<F>(egl_device: &EglDevice, pf_reqs: &PixelFormatRequirements,
+                         opengl: GlAttributes<&egl::Context>, finish: F)
+                         -> Result<HeadlessContext, CreationError>
+        where F: FnOnce(egl::ContextPrototype) -> Result<egl::Context, CreationError>
+    {
+        let native_display = egl::NativeDisplay::Device(egl_device.device.as_ptr());
+        egl::Context::new(egl_device.device.egl().clone(), pf_reqs, opengl, native_display)
+            .and_then(finish)
+            .map(HeadlessContext::Egl)
+    }
+
+    /// Returns the way the context was built.
+    pub fn headless_backend(&self) -> HeadlessBackend {
+        match *self {
+            HeadlessContext::OsMesa(_) => HeadlessBackend::OsMesa,
+            HeadlessContext::Egl(ref egl) if egl.is_surfaceless() => {
+                HeadlessBackend::EglSurfaceless
+            },
+            HeadlessContext::Egl(_) => HeadlessBackend::EglPbuffer,
+            HeadlessContext::Gbm(..) => HeadlessBackend::Gbm,
+            HeadlessContext::SharedGlx(..) => HeadlessBackend::SharedGlx,
+            HeadlessContext::SharedEgl(..) => HeadlessBackend::SharedEgl,
+        }
+    }
+
+    /// Returns the DRM device that the context was built on, if it was built with GBM.
+    #[inline]
+    pub fn drm_device(&self) -> Option<&DrmDeviceInfo> {
+        match *self {
+            HeadlessContext::Gbm(_, ref device) => Some(device.info()),
+            _ => None,
+        }
+    }
+
+    /// Returns the OSMesa context, if it is one.
+    #[inline]
+    pub fn osmesa_context(&self) -> Option<&OsMesaContext> {
+        match *self {
+            HeadlessContext::OsMesa(ref mesa) => Some(mesa),
+            _ => None,
+        }
+    }
+
+    /// Returns the OSMesa context, if it is one.
+    #[inline]
+    pub fn osmesa_context_mut(&mut self) -> Option<&mut OsMesaContext> {
+        match *self {
+            HeadlessContext::OsMesa(ref mut mesa) => Some(mesa),
+            _ => None,
+        }
+    }
+
+    /// Returns the format of the buffer of an OSMesa context.
+    #[inline]
+    pub fn osmesa_format(&self) -> Option<OsMesaFormat> {
+        self.osmesa_context().map(|mesa| mesa.get_format())
     }
 
     #[inline]
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         match *self {
             HeadlessContext::OsMesa(ref mesa) => mesa.make_current(),
-            HeadlessContext::Egl(ref egl) => egl.make_current(),
+            HeadlessContext::Egl(ref egl) |
+            HeadlessContext::Gbm(ref egl, _) |
+            HeadlessContext::SharedEgl(ref egl, _) => egl.make_current(),
+            HeadlessContext::SharedGlx(ref glx, _, _) => glx.make_current(),
+        }
+    }
+
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        match *self {
+            HeadlessContext::OsMesa(ref mesa) => mesa.make_not_current(),
+            HeadlessContext::Egl(ref egl) |
+            HeadlessContext::Gbm(ref egl, _) |
+            HeadlessContext::SharedEgl(ref egl, _) => egl.make_not_current(),
+            HeadlessContext::SharedGlx(ref glx, _, _) => glx.make_not_current(),
         }
     }
 
@@ -167,7 +1323,10 @@ impl HeadlessContext {
     pub fn is_current(&self) -> bool {
         match *self {
             HeadlessContext::OsMesa(ref mesa) => mesa.is_current(),
-            HeadlessContext::Egl(ref egl) => egl.is_current(),
+            HeadlessContext::Egl(ref egl) |
+            HeadlessContext::Gbm(ref egl, _) |
+            HeadlessContext::SharedEgl(ref egl, _) => egl.is_current(),
+            HeadlessContext::SharedGlx(ref glx, _, _) => glx.is_current(),
         }
     }
 
@@ -175,7 +1334,10 @@ impl HeadlessContext {
     pub fn get_proc_address(&self, addr: &str) -> *const () {
         match *self {
             HeadlessContext::OsMesa(ref mesa) => mesa.get_proc_address(addr),
-            HeadlessContext::Egl(ref egl) => egl.get_proc_address(addr),
+            HeadlessContext::Egl(ref egl) |
+            HeadlessContext::Gbm(ref egl, _) |
+            HeadlessContext::SharedEgl(ref egl, _) => egl.get_proc_address(addr),
+            HeadlessContext::SharedGlx(ref glx, _, _) => glx.get_proc_address(addr),
         }
     }
 
@@ -183,7 +1345,10 @@ impl HeadlessContext {
     pub fn swap_buffers(&self) -> Result<(), ContextError> {
         match *self {
             HeadlessContext::OsMesa(ref mesa) => mesa.swap_buffers(),
-            HeadlessContext::Egl(ref egl) => egl.swap_buffers(),
+            HeadlessContext::Egl(ref egl) |
+            HeadlessContext::Gbm(ref egl, _) |
+            HeadlessContext::SharedEgl(ref egl, _) => egl.swap_buffers(),
+            HeadlessContext::SharedGlx(ref glx, _, _) => glx.swap_buffers(),
         }
     }
 
@@ -191,7 +1356,10 @@ impl HeadlessContext {
     pub fn get_api(&self) -> Api {
         match *self {
             HeadlessContext::OsMesa(ref mesa) => mesa.get_api(),
-            HeadlessContext::Egl(ref egl) => egl.get_api(),
+            HeadlessContext::Egl(ref egl) |
+            HeadlessContext::Gbm(ref egl, _) |
+            HeadlessContext::SharedEgl(ref egl, _) => egl.get_api(),
+            HeadlessContext::SharedGlx(ref glx, _, _) => glx.get_api(),
         }
     }
 
@@ -199,7 +1367,10 @@ impl HeadlessContext {
     pub fn get_pixel_format(&self) -> PixelFormat {
         match *self {
             HeadlessContext::OsMesa(ref mesa) => mesa.get_pixel_format(),
-            HeadlessContext::Egl(ref egl) => egl.get_pixel_format(),
+            HeadlessContext::Egl(ref egl) |
+            HeadlessContext::Gbm(ref egl, _) |
+            HeadlessContext::SharedEgl(ref egl, _) => egl.get_pixel_format(),
+            HeadlessContext::SharedGlx(ref glx, _, _) => glx.get_pixel_format(),
         }
     }
 
@@ -207,9 +1378,43 @@ impl HeadlessContext {
     pub unsafe fn raw_handle(&self) -> *mut c_void {
         let handle = match *self {
              HeadlessContext::OsMesa(ref mesa) => mesa.raw_handle(),
-             HeadlessContext::Egl(ref egl) => egl.raw_handle(),
+             HeadlessContext::Egl(ref egl) |
+             HeadlessContext::Gbm(ref egl, _) |
+             HeadlessContext::SharedEgl(ref egl, _) => egl.raw_handle(),
+             HeadlessContext::SharedGlx(ref glx, _, _) => glx.raw_handle() as *const _,
         };
 
         handle as *mut c_void
     }
 }
+
+impl ::GlContext for HeadlessContext {
+    #[inline]
+    fn is_current(&self) -> bool {
+        HeadlessContext::is_current(self)
+    }
+
+    #[inline]
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        HeadlessContext::get_proc_address(self, addr)
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        HeadlessContext::swap_buffers(self)
+    }
+
+    #[inline]
+    fn get_api(&self) -> Api {
+        HeadlessContext::get_api(self)
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> PixelFormat {
+        HeadlessContext::get_pixel_format(self)
+    }
+
+    #[inline]
+    fn resize(&self, _: u32, _: u32) {
+    }
+}
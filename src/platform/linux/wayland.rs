@@ -1,18 +1,855 @@
-use std::sync::Arc;
-use std::ffi::CString;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::VecDeque;
+use std::ffi::{CStr, CString};
+use std::time::Duration;
+use std::{mem, ptr};
+#[cfg(feature = "winit")]
 use winit;
+#[cfg(feature = "winit")]
 use winit::os::unix::WindowExt;
-use {ContextError, CreationError, GlAttributes, PixelFormat, PixelFormatRequirements};
+use {Backend, ContextError, CreationError, GlAttributes, PixelFormat, PixelFormatRequirements};
+use {PresentMode, PresentationFeedback, PresentationFlags, PresentationMethod, PresentationTime};
+use {Colorspace, MultisampleResolve, PbufferAttributes, PbufferLimits, Rect, RenderBuffer};
+use {Api, DeviceInfo, SupportedVersions, SwapBehavior, SwapInterval};
 use api::dlopen;
 use api::egl::{self, ffi, Context as EglContext};
+use super::xpresent::FrameFeedback;
 use wayland_client::egl as wegl;
+use std::os::raw::{c_char, c_int, c_void};
+use libc;
 
 pub struct Context {
     egl_surface: Arc<wegl::WlEglSurface>,
     context: EglContext,
+    display: *const c_void,
+    // `None` if libwayland-client couldn't be loaded, in which case swaps aren't throttled.
+    frame_callbacks: Option<FrameCallbacks>,
+    // False when the swap interval is 0, in which case EGL doesn't wait for the compositor.
+    throttle: AtomicBool,
+}
+
+unsafe impl Send for Context {}
+unsafe impl Sync for Context {}
+
+/// How long `swap_buffers` waits for the compositor to ask for a new frame before dropping the
+/// frame, in milliseconds.
+const FRAME_CALLBACK_TIMEOUT: c_int = 100;
+
+/// The functions of libwayland-client used to wait for frame callbacks on a queue of our own,
+/// so that they are not dispatched by the event loop of the window.
+struct WaylandClient {
+    display_create_queue: unsafe extern "C" fn(*mut c_void) -> *mut c_void,
+    display_prepare_read_queue: unsafe extern "C" fn(*mut c_void, *mut c_void) -> c_int,
+    display_dispatch_queue_pending: unsafe extern "C" fn(*mut c_void, *mut c_void) -> c_int,
+    display_read_events: unsafe extern "C" fn(*mut c_void) -> c_int,
+    display_cancel_read: unsafe extern "C" fn(*mut c_void),
+    display_flush: unsafe extern "C" fn(*mut c_void) -> c_int,
+    display_get_fd: unsafe extern "C" fn(*mut c_void) -> c_int,
+    display_roundtrip_queue: unsafe extern "C" fn(*mut c_void, *mut c_void) -> c_int,
+    event_queue_destroy: unsafe extern "C" fn(*mut c_void),
+    proxy_create_wrapper: unsafe extern "C" fn(*mut c_void) -> *mut c_void,
+    proxy_wrapper_destroy: unsafe extern "C" fn(*mut c_void),
+    proxy_set_queue: unsafe extern "C" fn(*mut c_void, *mut c_void),
+    proxy_marshal: unsafe extern "C" fn(*mut c_void, u32, ...),
+    proxy_marshal_constructor: unsafe extern "C" fn(*mut c_void, u32, *const c_void, ...)
+                                                    -> *mut c_void,
+    proxy_marshal_constructor_versioned: unsafe extern "C" fn(*mut c_void, u32, *const c_void,
+                                                              u32, ...) -> *mut c_void,
+    proxy_add_listener: unsafe extern "C" fn(*mut c_void, *const c_void, *mut c_void) -> c_int,
+    proxy_destroy: unsafe extern "C" fn(*mut c_void),
+    callback_interface: *const c_void,
+    registry_interface: *const c_void,
+}
+
+impl WaylandClient {
+    unsafe fn load() -> Option<WaylandClient> {
+        let lib = dlopen::dlopen(b"libwayland-client.so.0\0".as_ptr() as *const _,
+                                 dlopen::RTLD_NOW);
+        if lib.is_null() {
+            return None;
+        }
+
+        let sym = |name: &str| {
+            let name = CString::new(name).unwrap();
+            let sym = dlopen::dlsym(lib, name.as_ptr());
+            if sym.is_null() { None } else { Some(sym) }
+        };
+
+        Some(WaylandClient {
+            display_create_queue: mem::transmute(sym("wl_display_create_queue")?),
+            display_prepare_read_queue: mem::transmute(sym("wl_display_prepare_read_queue")?),
+            display_dispatch_queue_pending:
+                mem::transmute(sym("wl_display_dispatch_queue_pending")?),
+            display_read_events: mem::transmute(sym("wl_display_read_events")?),
+            display_cancel_read: mem::transmute(sym("wl_display_cancel_read")?),
+            display_flush: mem::transmute(sym("wl_display_flush")?),
+            display_get_fd: mem::transmute(sym("wl_display_get_fd")?),
+            display_roundtrip_queue: mem::transmute(sym("wl_display_roundtrip_queue")?),
+            event_queue_destroy: mem::transmute(sym("wl_event_queue_destroy")?),
+            proxy_create_wrapper: mem::transmute(sym("wl_proxy_create_wrapper")?),
+            proxy_wrapper_destroy: mem::transmute(sym("wl_proxy_wrapper_destroy")?),
+            proxy_set_queue: mem::transmute(sym("wl_proxy_set_queue")?),
+            proxy_marshal: mem::transmute(sym("wl_proxy_marshal")?),
+            proxy_marshal_constructor: mem::transmute(sym("wl_proxy_marshal_constructor")?),
+            proxy_marshal_constructor_versioned:
+                mem::transmute(sym("wl_proxy_marshal_constructor_versioned")?),
+            proxy_add_listener: mem::transmute(sym("wl_proxy_add_listener")?),
+            proxy_destroy: mem::transmute(sym("wl_proxy_destroy")?),
+            callback_interface: sym("wl_callback_interface")? as *const _,
+            registry_interface: sym("wl_registry_interface")? as *const _,
+        })
+    }
+}
+
+// The opcode of the `frame` request of `wl_surface`.
+const WL_SURFACE_FRAME: u32 = 3;
+
+#[repr(C)]
+struct CallbackListener {
+    done: extern "C" fn(*mut c_void, *mut c_void, u32),
+}
+
+static CALLBACK_LISTENER: CallbackListener = CallbackListener { done: frame_done };
+
+extern "C" fn frame_done(data: *mut c_void, _: *mut c_void, _: u32) {
+    unsafe { *(data as *mut bool) = true; }
+}
+
+// The opcodes of the requests used to bind `wp_presentation`.
+const WL_DISPLAY_GET_REGISTRY: u32 = 1;
+const WL_REGISTRY_BIND: u32 = 0;
+
+// From `presentation-time.xml`.
+const WP_PRESENTATION_DESTROY: u32 = 0;
+const WP_PRESENTATION_FEEDBACK: u32 = 1;
+const WP_PRESENTATION_FEEDBACK_KIND_VSYNC: u32 = 0x1;
+const WP_PRESENTATION_FEEDBACK_KIND_HW_CLOCK: u32 = 0x2;
+const WP_PRESENTATION_FEEDBACK_KIND_HW_COMPLETION: u32 = 0x4;
+const WP_PRESENTATION_FEEDBACK_KIND_ZERO_COPY: u32 = 0x8;
+
+/// Number of presented frames whose feedback is remembered.
+const KEPT_FRAMES: usize = 64;
+
+/// `struct wl_message` from `wayland-util.h`.
+#[repr(C)]
+struct WlMessage {
+    name: *const c_char,
+    signature: *const c_char,
+    types: *const *const WlInterface,
+}
+
+/// `struct wl_interface` from `wayland-util.h`.
+#[repr(C)]
+struct WlInterface {
+    name: *const c_char,
+    version: c_int,
+    method_count: c_int,
+    methods: *const WlMessage,
+    event_count: c_int,
+    events: *const WlMessage,
+}
+
+// The interfaces of the protocols that libwayland-client doesn't provide.
+struct Static<T>(T);
+unsafe impl<T> Sync for Static<T> {}
+
+// The interfaces of objects are only checked when they are not null.
+static NULL_TYPES: Static<[*const WlInterface; 7]> = Static([ptr::null(); 7]);
+static FEEDBACK_REQUEST_TYPES: Static<[*const WlInterface; 2]> =
+    Static([ptr::null(), &WP_PRESENTATION_FEEDBACK_INTERFACE.0]);
+
+static WP_PRESENTATION_REQUESTS: Static<[WlMessage; 2]> = Static([
+    WlMessage {
+        name: b"destroy\0" as *const u8 as *const c_char,
+        signature: b"\0" as *const u8 as *const c_char,
+        types: &NULL_TYPES.0 as *const _ as *const _,
+    },
+    WlMessage {
+        name: b"feedback\0" as *const u8 as *const c_char,
+        signature: b"on\0" as *const u8 as *const c_char,
+        types: &FEEDBACK_REQUEST_TYPES.0 as *const _ as *const _,
+    },
+]);
+static WP_PRESENTATION_EVENTS: Static<[WlMessage; 1]> = Static([
+    WlMessage {
+        name: b"clock_id\0" as *const u8 as *const c_char,
+        signature: b"u\0" as *const u8 as *const c_char,
+        types: &NULL_TYPES.0 as *const _ as *const _,
+    },
+]);
+static WP_PRESENTATION_INTERFACE: Static<WlInterface> = Static(WlInterface {
+    name: b"wp_presentation\0" as *const u8 as *const c_char,
+    version: 1,
+    method_count: 2,
+    methods: &WP_PRESENTATION_REQUESTS.0 as *const _ as *const _,
+    event_count: 1,
+    events: &WP_PRESENTATION_EVENTS.0 as *const _ as *const _,
+});
+
+static WP_PRESENTATION_FEEDBACK_EVENTS: Static<[WlMessage; 3]> = Static([
+    WlMessage {
+        name: b"sync_output\0" as *const u8 as *const c_char,
+        signature: b"o\0" as *const u8 as *const c_char,
+        types: &NULL_TYPES.0 as *const _ as *const _,
+    },
+    WlMessage {
+        name: b"presented\0" as *const u8 as *const c_char,
+        signature: b"uuuuuuu\0" as *const u8 as *const c_char,
+        types: &NULL_TYPES.0 as *const _ as *const _,
+    },
+    WlMessage {
+        name: b"discarded\0" as *const u8 as *const c_char,
+        signature: b"\0" as *const u8 as *const c_char,
+        types: &NULL_TYPES.0 as *const _ as *const _,
+    },
+]);
+static WP_PRESENTATION_FEEDBACK_INTERFACE: Static<WlInterface> = Static(WlInterface {
+    name: b"wp_presentation_feedback\0" as *const u8 as *const c_char,
+    version: 1,
+    method_count: 0,
+    methods: ptr::null(),
+    event_count: 3,
+    events: &WP_PRESENTATION_FEEDBACK_EVENTS.0 as *const _ as *const _,
+});
+
+// From `linux-explicit-synchronization-unstable-v1.xml`.
+const ZWP_LINUX_EXPLICIT_SYNCHRONIZATION_DESTROY: u32 = 0;
+const ZWP_LINUX_EXPLICIT_SYNCHRONIZATION_GET_SYNCHRONIZATION: u32 = 1;
+const ZWP_LINUX_SURFACE_SYNCHRONIZATION_DESTROY: u32 = 0;
+const ZWP_LINUX_SURFACE_SYNCHRONIZATION_SET_ACQUIRE_FENCE: u32 = 1;
+
+static GET_SYNCHRONIZATION_TYPES: Static<[*const WlInterface; 2]> =
+    Static([&ZWP_LINUX_SURFACE_SYNCHRONIZATION_INTERFACE.0, ptr::null()]);
+
+static ZWP_LINUX_EXPLICIT_SYNCHRONIZATION_REQUESTS: Static<[WlMessage; 2]> = Static([
+    WlMessage {
+        name: b"destroy\0" as *const u8 as *const c_char,
+        signature: b"\0" as *const u8 as *const c_char,
+        types: &NULL_TYPES.0 as *const _ as *const _,
+    },
+    WlMessage {
+        name: b"get_synchronization\0" as *const u8 as *const c_char,
+        signature: b"no\0" as *const u8 as *const c_char,
+        types: &GET_SYNCHRONIZATION_TYPES.0 as *const _ as *const _,
+    },
+]);
+static ZWP_LINUX_EXPLICIT_SYNCHRONIZATION_INTERFACE: Static<WlInterface> = Static(WlInterface {
+    name: b"zwp_linux_explicit_synchronization_v1\0" as *const u8 as *const c_char,
+    version: 1,
+    method_count: 2,
+    methods: &ZWP_LINUX_EXPLICIT_SYNCHRONIZATION_REQUESTS.0 as *const _ as *const _,
+    event_count: 0,
+    events: ptr::null(),
+});
+
+// `get_release` is never sent, so the interface of its object is left out.
+static ZWP_LINUX_SURFACE_SYNCHRONIZATION_REQUESTS: Static<[WlMessage; 3]> = Static([
+    WlMessage {
+        name: b"destroy\0" as *const u8 as *const c_char,
+        signature: b"\0" as *const u8 as *const c_char,
+        types: &NULL_TYPES.0 as *const _ as *const _,
+    },
+    WlMessage {
+        name: b"set_acquire_fence\0" as *const u8 as *const c_char,
+        signature: b"h\0" as *const u8 as *const c_char,
+        types: &NULL_TYPES.0 as *const _ as *const _,
+    },
+    WlMessage {
+        name: b"get_release\0" as *const u8 as *const c_char,
+        signature: b"n\0" as *const u8 as *const c_char,
+        types: &NULL_TYPES.0 as *const _ as *const _,
+    },
+]);
+static ZWP_LINUX_SURFACE_SYNCHRONIZATION_INTERFACE: Static<WlInterface> = Static(WlInterface {
+    name: b"zwp_linux_surface_synchronization_v1\0" as *const u8 as *const c_char,
+    version: 1,
+    method_count: 3,
+    methods: &ZWP_LINUX_SURFACE_SYNCHRONIZATION_REQUESTS.0 as *const _ as *const _,
+    event_count: 0,
+    events: ptr::null(),
+});
+
+#[repr(C)]
+struct RegistryListener {
+    global: extern "C" fn(*mut c_void, *mut c_void, u32, *const c_char, u32),
+    global_remove: extern "C" fn(*mut c_void, *mut c_void, u32),
+}
+
+static REGISTRY_LISTENER: RegistryListener = RegistryListener {
+    global: registry_global,
+    global_remove: registry_global_remove,
+};
+
+/// The global looked up by `FrameCallbacks::bind_global`.
+struct GlobalLookup {
+    interface: &'static WlInterface,
+    // Set by `registry_global` if the global is advertised.
+    name: Option<u32>,
+}
+
+extern "C" fn registry_global(data: *mut c_void, _: *mut c_void, name: u32,
+                              interface: *const c_char, _: u32)
+{
+    let lookup = unsafe { &mut *(data as *mut GlobalLookup) };
+    let interface = unsafe { CStr::from_ptr(interface) };
+    let wanted = unsafe { CStr::from_ptr(lookup.interface.name) };
+    if interface == wanted {
+        lookup.name = Some(name);
+    }
+}
+
+extern "C" fn registry_global_remove(_: *mut c_void, _: *mut c_void, _: u32) {
+}
+
+#[repr(C)]
+struct FeedbackListener {
+    sync_output: extern "C" fn(*mut c_void, *mut c_void, *mut c_void),
+    presented: extern "C" fn(*mut c_void, *mut c_void, u32, u32, u32, u32, u32, u32, u32),
+    discarded: extern "C" fn(*mut c_void, *mut c_void),
+}
+
+static FEEDBACK_LISTENER: FeedbackListener = FeedbackListener {
+    sync_output: feedback_sync_output,
+    presented: feedback_presented,
+    discarded: feedback_discarded,
+};
+
+extern "C" fn feedback_sync_output(_: *mut c_void, _: *mut c_void, _: *mut c_void) {
+}
+
+extern "C" fn feedback_presented(data: *mut c_void, _: *mut c_void, tv_sec_hi: u32,
+                                 tv_sec_lo: u32, tv_nsec: u32, refresh: u32, seq_hi: u32,
+                                 seq_lo: u32, flags: u32)
+{
+    let frame = unsafe { &mut *(data as *mut PendingFrame) };
+    let secs = ((tv_sec_hi as u64) << 32) | tv_sec_lo as u64;
+    frame.result = Some(Some(PresentationFeedback {
+        time: Duration::new(secs, tv_nsec),
+        msc: ((seq_hi as u64) << 32) | seq_lo as u64,
+        method: if flags & WP_PRESENTATION_FEEDBACK_KIND_ZERO_COPY != 0 {
+            PresentationMethod::Flip
+        } else {
+            PresentationMethod::Copy
+        },
+        refresh: if refresh != 0 { Some(Duration::new(0, refresh)) } else { None },
+        flags: Some(PresentationFlags {
+            vsync: flags & WP_PRESENTATION_FEEDBACK_KIND_VSYNC != 0,
+            hw_clock: flags & WP_PRESENTATION_FEEDBACK_KIND_HW_CLOCK != 0,
+            hw_completion: flags & WP_PRESENTATION_FEEDBACK_KIND_HW_COMPLETION != 0,
+            zero_copy: flags & WP_PRESENTATION_FEEDBACK_KIND_ZERO_COPY != 0,
+        }),
+    }));
+}
+
+extern "C" fn feedback_discarded(data: *mut c_void, _: *mut c_void) {
+    let frame = unsafe { &mut *(data as *mut PendingFrame) };
+    frame.result = Some(None);
+}
+
+/// A frame whose `wp_presentation_feedback` didn't complete yet.
+struct PendingFrame {
+    id: u64,
+    feedback: *mut c_void,
+    // Set by the listener. `Some(None)` if the frame was discarded.
+    result: Option<Option<PresentationFeedback>>,
+}
+
+enum PresentationState {
+    // `wp_presentation` wasn't looked up yet.
+    Unknown,
+    Unavailable,
+    Active(Presentation),
+}
+
+/// Tracks when the committed frames are presented through the `presentation-time` protocol.
+struct Presentation {
+    presentation: *mut c_void,
+    // The number of swaps since the protocol was bound, which is the id of the last frame.
+    swaps: u64,
+    // Boxed so that their address, given to the listener, doesn't change.
+    pending: VecDeque<Box<PendingFrame>>,
+    // The last completed frames, `None` for discarded ones.
+    completed: VecDeque<(u64, Option<PresentationFeedback>)>,
+}
+
+impl Presentation {
+    fn complete(&mut self, id: u64, result: Option<PresentationFeedback>) {
+        if self.completed.len() == KEPT_FRAMES {
+            self.completed.pop_front();
+        }
+        self.completed.push_back((id, result));
+    }
+}
+
+/// Requests a frame callback from the compositor before every swap, so that the next swap can
+/// wait for it with a timeout. EGL waits for its own frame callbacks without any timeout, which
+/// blocks forever when the surface is hidden.
+struct FrameCallbacks {
+    client: WaylandClient,
+    display: *mut c_void,
+    // A wrapper of the `wl_surface` whose requests create objects on `queue`.
+    surface_wrapper: *mut c_void,
+    queue: *mut c_void,
+    state: Mutex<FrameState>,
+}
+
+struct FrameState {
+    // The callback requested with the last swap, or null.
+    callback: *mut c_void,
+    // Set by `frame_done`. Boxed so that its address doesn't change.
+    done: Box<bool>,
+    presentation: PresentationState,
+    explicit_sync: ExplicitSyncState,
+}
+
+enum ExplicitSyncState {
+    // The global wasn't looked up yet.
+    Unknown,
+    Unavailable,
+    // The `zwp_linux_surface_synchronization_v1` of the surface.
+    Active(*mut c_void),
+}
+
+impl FrameCallbacks {
+    unsafe fn new(display: *const c_void, surface: *mut c_void) -> Option<FrameCallbacks> {
+        let client = WaylandClient::load()?;
+        let display = display as *mut c_void;
+        let queue = (client.display_create_queue)(display);
+        if queue.is_null() {
+            return None;
+        }
+        let surface_wrapper = (client.proxy_create_wrapper)(surface);
+        if surface_wrapper.is_null() {
+            (client.event_queue_destroy)(queue);
+            return None;
+        }
+        (client.proxy_set_queue)(surface_wrapper, queue);
+
+        Some(FrameCallbacks {
+            client: client,
+            display: display,
+            surface_wrapper: surface_wrapper,
+            queue: queue,
+            state: Mutex::new(FrameState {
+                callback: ptr::null_mut(),
+                done: Box::new(true),
+                presentation: PresentationState::Unknown,
+                explicit_sync: ExplicitSyncState::Unknown,
+            }),
+        })
+    }
+
+    /// Waits for at most `timeout` milliseconds for the callback of the last swap, and returns
+    /// true if it was received.
+    fn wait(&self, state: &mut FrameState, timeout: c_int) -> bool {
+        unsafe { (self.client.display_dispatch_queue_pending)(self.display, self.queue); }
+        if *state.done {
+            return true;
+        }
+        self.dispatch(timeout);
+        *state.done
+    }
+
+    /// Waits for at most `timeout` milliseconds for events, and dispatches the ones of our
+    /// queue.
+    fn dispatch(&self, timeout: c_int) {
+        let client = &self.client;
+        unsafe {
+            while (client.display_prepare_read_queue)(self.display, self.queue) != 0 {
+                (client.display_dispatch_queue_pending)(self.display, self.queue);
+            }
+            (client.display_flush)(self.display);
+
+            let mut fd = libc::pollfd {
+                fd: (client.display_get_fd)(self.display),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            if libc::poll(&mut fd, 1, timeout) > 0 {
+                (client.display_read_events)(self.display);
+            } else {
+                (client.display_cancel_read)(self.display);
+            }
+            (client.display_dispatch_queue_pending)(self.display, self.queue);
+        }
+    }
+
+    /// Requests a callback for the next commit of the surface.
+    fn request(&self, state: &mut FrameState) {
+        let client = &self.client;
+        unsafe {
+            if !state.callback.is_null() {
+                (client.proxy_destroy)(state.callback);
+            }
+            let callback = (client.proxy_marshal_constructor)(self.surface_wrapper,
+                                                              WL_SURFACE_FRAME,
+                                                              client.callback_interface,
+                                                              ptr::null_mut::<c_void>());
+            if callback.is_null() {
+                state.callback = ptr::null_mut();
+                *state.done = true;
+                return;
+            }
+            *state.done = false;
+            (client.proxy_add_listener)(callback,
+                                        &CALLBACK_LISTENER as *const _ as *const c_void,
+                                        &mut *state.done as *mut bool as *mut c_void);
+            state.callback = callback;
+        }
+    }
+
+    /// Binds `wp_presentation` if it wasn't done yet. Returns whether the compositor supports it.
+    fn activate_presentation(&self, state: &mut FrameState) -> bool {
+        if let PresentationState::Unknown = state.presentation {
+            state.presentation = match unsafe { self.bind_presentation() } {
+                Some(presentation) => PresentationState::Active(Presentation {
+                    presentation: presentation,
+                    swaps: 0,
+                    pending: VecDeque::new(),
+                    completed: VecDeque::with_capacity(KEPT_FRAMES),
+                }),
+                None => PresentationState::Unavailable,
+            };
+        }
+        match state.presentation {
+            PresentationState::Active(_) => true,
+            _ => false,
+        }
+    }
+
+    unsafe fn bind_presentation(&self) -> Option<*mut c_void> {
+        self.bind_global(&WP_PRESENTATION_INTERFACE.0)
+    }
+
+    /// Binds the version 1 of the global implementing `interface`, creating the object on our
+    /// queue. Returns `None` if the compositor doesn't advertise it.
+    unsafe fn bind_global(&self, interface: &'static WlInterface) -> Option<*mut c_void> {
+        let client = &self.client;
+        let display_wrapper = (client.proxy_create_wrapper)(self.display);
+        if display_wrapper.is_null() {
+            return None;
+        }
+        (client.proxy_set_queue)(display_wrapper, self.queue);
+        let registry = (client.proxy_marshal_constructor)(display_wrapper,
+                                                          WL_DISPLAY_GET_REGISTRY,
+                                                          client.registry_interface,
+                                                          ptr::null_mut::<c_void>());
+        (client.proxy_wrapper_destroy)(display_wrapper);
+        if registry.is_null() {
+            return None;
+        }
+
+        let mut lookup = GlobalLookup { interface: interface, name: None };
+        (client.proxy_add_listener)(registry, &REGISTRY_LISTENER as *const _ as *const c_void,
+                                    &mut lookup as *mut GlobalLookup as *mut c_void);
+        (client.display_roundtrip_queue)(self.display, self.queue);
+
+        // the new object is created on our queue like the registry
+        let object = lookup.name.map(|name| {
+            (client.proxy_marshal_constructor_versioned)(
+                registry, WL_REGISTRY_BIND, interface as *const _ as *const c_void, 1, name,
+                interface.name, 1u32, ptr::null_mut::<c_void>()
+            )
+        });
+        (client.proxy_destroy)(registry);
+        object.and_then(|o| if o.is_null() { None } else { Some(o) })
+    }
+
+    /// Creates the `zwp_linux_surface_synchronization_v1` of the surface if it wasn't done yet.
+    /// Returns the object, or `None` if the compositor doesn't support explicit synchronization.
+    fn surface_synchronization(&self, state: &mut FrameState) -> Option<*mut c_void> {
+        if let ExplicitSyncState::Unknown = state.explicit_sync {
+            let synchronization = unsafe {
+                self.bind_global(&ZWP_LINUX_EXPLICIT_SYNCHRONIZATION_INTERFACE.0)
+            };
+            state.explicit_sync = match synchronization {
+                Some(synchronization) => unsafe {
+                    let surface_sync = (self.client.proxy_marshal_constructor)(
+                        synchronization, ZWP_LINUX_EXPLICIT_SYNCHRONIZATION_GET_SYNCHRONIZATION,
+                        &ZWP_LINUX_SURFACE_SYNCHRONIZATION_INTERFACE.0 as *const _ as *const _,
+                        ptr::null_mut::<c_void>(), self.surface_wrapper
+                    );
+                    // the surface synchronization stays valid without the global
+                    (self.client.proxy_marshal)(synchronization,
+                                                ZWP_LINUX_EXPLICIT_SYNCHRONIZATION_DESTROY);
+                    (self.client.proxy_destroy)(synchronization);
+                    if surface_sync.is_null() {
+                        ExplicitSyncState::Unavailable
+                    } else {
+                        ExplicitSyncState::Active(surface_sync)
+                    }
+                },
+                None => ExplicitSyncState::Unavailable,
+            };
+        }
+        match state.explicit_sync {
+            ExplicitSyncState::Active(surface_sync) => Some(surface_sync),
+            _ => None,
+        }
+    }
+
+    /// Makes the compositor wait for `fence` before using the buffer of the next commit. The
+    /// file descriptor is duplicated, and stays owned by the caller.
+    fn set_acquire_fence(&self, surface_sync: *mut c_void, fence: c_int) {
+        unsafe {
+            (self.client.proxy_marshal)(surface_sync,
+                                        ZWP_LINUX_SURFACE_SYNCHRONIZATION_SET_ACQUIRE_FENCE,
+                                        fence);
+        }
+    }
+
+    /// Assigns an id to the frame about to be swapped, and requests its presentation feedback
+    /// if it is going to be committed. Returns `None` if `wp_presentation` isn't bound.
+    fn next_frame(&self, state: &mut FrameState, committed: bool) -> Option<u64> {
+        let presentation = match state.presentation {
+            PresentationState::Active(ref mut presentation) => presentation,
+            _ => return None,
+        };
+        presentation.swaps += 1;
+        let id = presentation.swaps;
+
+        let feedback = if committed {
+            unsafe {
+                (self.client.proxy_marshal_constructor)(
+                    presentation.presentation, WP_PRESENTATION_FEEDBACK,
+                    &WP_PRESENTATION_FEEDBACK_INTERFACE.0 as *const _ as *const _,
+                    self.surface_wrapper, ptr::null_mut::<c_void>()
+                )
+            }
+        } else {
+            ptr::null_mut()
+        };
+        if feedback.is_null() {
+            presentation.complete(id, None);
+            return Some(id);
+        }
+
+        let mut frame = Box::new(PendingFrame { id: id, feedback: feedback, result: None });
+        unsafe {
+            (self.client.proxy_add_listener)(feedback,
+                                             &FEEDBACK_LISTENER as *const _ as *const c_void,
+                                             &mut *frame as *mut PendingFrame as *mut c_void);
+        }
+        presentation.pending.push_back(frame);
+        Some(id)
+    }
+
+    /// Returns whether the last swapped frame wasn't presented or discarded yet.
+    fn is_last_frame_pending(&self, state: &mut FrameState) -> bool {
+        let last_frame = match state.presentation {
+            PresentationState::Active(ref presentation) => presentation.swaps,
+            _ => return false,
+        };
+        match self.feedback(state, last_frame) {
+            Some(FrameFeedback::Pending) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns what is known about the presentation of `frame_id`, or `None` if
+    /// `wp_presentation` isn't bound.
+    fn feedback(&self, state: &mut FrameState, frame_id: u64) -> Option<FrameFeedback> {
+        match state.presentation {
+            PresentationState::Active(_) => (),
+            _ => return None,
+        }
+        self.dispatch(0);
+
+        let presentation = match state.presentation {
+            PresentationState::Active(ref mut presentation) => presentation,
+            _ => unreachable!(),
+        };
+        while let Some(index) = presentation.pending.iter().position(|f| f.result.is_some()) {
+            let frame = presentation.pending.remove(index).unwrap();
+            unsafe { (self.client.proxy_destroy)(frame.feedback); }
+            presentation.complete(frame.id, frame.result.unwrap());
+        }
+
+        if presentation.pending.iter().any(|frame| frame.id == frame_id) {
+            return Some(FrameFeedback::Pending);
+        }
+        Some(match presentation.completed.iter().find(|&&(id, _)| id == frame_id) {
+            Some(&(_, Some(feedback))) => FrameFeedback::Presented(feedback),
+            _ => FrameFeedback::Unavailable,
+        })
+    }
+}
+
+impl Drop for FrameCallbacks {
+    fn drop(&mut self) {
+        let state = self.state.get_mut().unwrap();
+        unsafe {
+            if !state.callback.is_null() {
+                (self.client.proxy_destroy)(state.callback);
+            }
+            if let PresentationState::Active(ref presentation) = state.presentation {
+                for frame in &presentation.pending {
+                    (self.client.proxy_destroy)(frame.feedback);
+                }
+                (self.client.proxy_marshal)(presentation.presentation, WP_PRESENTATION_DESTROY);
+                (self.client.proxy_destroy)(presentation.presentation);
+            }
+            if let ExplicitSyncState::Active(surface_sync) = state.explicit_sync {
+                (self.client.proxy_marshal)(surface_sync,
+                                            ZWP_LINUX_SURFACE_SYNCHRONIZATION_DESTROY);
+                (self.client.proxy_destroy)(surface_sync);
+            }
+            (self.client.proxy_wrapper_destroy)(self.surface_wrapper);
+            (self.client.event_queue_destroy)(self.queue);
+        }
+    }
+}
+
+/// A surface created from the config of a wayland context.
+pub struct Surface {
+    // The EGL surface must be destroyed before the `wl_egl_window` it was created on.
+    surface: egl::Surface,
+    wl_egl_surface: Option<wegl::WlEglSurface>,
+}
+
+impl Surface {
+    #[inline]
+    pub fn dimensions(&self) -> Result<(u32, u32), ContextError> {
+        self.surface.dimensions()
+    }
+
+    #[inline]
+    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.surface.swap_buffers()
+    }
+
+    #[inline]
+    pub fn buffer_age(&self) -> Result<u32, ContextError> {
+        self.surface.buffer_age()
+    }
+
+    #[inline]
+    pub fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        self.surface.set_damage_region(rects)
+    }
+
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        self.surface.swap_behavior()
+    }
+
+    #[inline]
+    pub fn set_swap_behavior(&self, behavior: SwapBehavior) -> Result<(), ContextError> {
+        self.surface.set_swap_behavior(behavior)
+    }
+
+    #[inline]
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        self.surface.render_buffer()
+    }
+
+    #[inline]
+    pub fn set_render_buffer(&self, render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        self.surface.set_render_buffer(render_buffer)
+    }
+
+    #[inline]
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        self.surface.multisample_resolve()
+    }
+
+    #[inline]
+    pub fn set_multisample_resolve(&self, resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        self.surface.set_multisample_resolve(resolve)
+    }
+
+    pub fn resize(&self, width: u32, height: u32) {
+        if let Some(ref wl_egl_surface) = self.wl_egl_surface {
+            wl_egl_surface.resize(width as i32, height as i32, 0, 0);
+        }
+    }
+}
+
+/// A config of a wayland display, from which contexts and surfaces are created.
+#[derive(Clone)]
+pub struct Config(egl::Config, *const c_void);
+
+unsafe impl Send for Config {}
+unsafe impl Sync for Config {}
+
+impl Config {
+    #[inline]
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        self.0.get_pixel_format()
+    }
+
+    #[inline]
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        self.0.max_pbuffer_size()
+    }
+
+    /// Returns the EGL config.
+    #[inline]
+    pub fn egl_config(&self) -> &egl::Config {
+        &self.0
+    }
+
+    /// Creates a surface on a `wl_surface` using this config.
+    pub unsafe fn create_window_surface(&self, surface: *mut c_void, width: u32, height: u32)
+                                        -> Result<Surface, CreationError>
+    {
+        let wl_egl_surface = wegl::WlEglSurface::new_from_raw(surface as *mut _, width as i32,
+                                                              height as i32);
+        let surface = self.0.create_window_surface(wl_egl_surface.ptr() as *const _)?;
+        Ok(Surface { surface: surface, wl_egl_surface: Some(wl_egl_surface) })
+    }
+
+    /// Creates an offscreen surface using this config.
+    pub fn create_pbuffer_surface(&self, attributes: &PbufferAttributes)
+                                  -> Result<Surface, CreationError>
+    {
+        let surface = self.0.create_pbuffer_surface(attributes)?;
+        Ok(Surface { surface: surface, wl_egl_surface: None })
+    }
+}
+
+/// Returns the configs of a `wl_display` that match `reqs`.
+pub unsafe fn find_configs(display_ptr: *const c_void, reqs: &PixelFormatRequirements)
+                           -> Result<Vec<Config>, CreationError>
+{
+    let egl = load_egl()?;
+    let native_display = egl::NativeDisplay::Wayland(Some(display_ptr as *const _));
+    let configs = egl::find_configs(egl, native_display, reqs)?;
+    Ok(configs.into_iter().map(|config| Config(config, display_ptr)).collect())
+}
+
+/// Returns the device of a `wl_display`.
+pub unsafe fn device_info(display_ptr: *const c_void) -> Result<DeviceInfo, CreationError> {
+    let egl = load_egl()?;
+    let native_display = egl::NativeDisplay::Wayland(Some(display_ptr as *const _));
+    egl::device_info(egl, native_display)
+}
+
+/// Probes the versions of `api` on a `wl_display`.
+pub unsafe fn probe_versions(display_ptr: *const c_void, api: Api)
+                             -> Result<SupportedVersions, CreationError>
+{
+    let egl = load_egl()?;
+    let native_display = egl::NativeDisplay::Wayland(Some(display_ptr as *const _));
+    egl::probe_versions(egl, native_display, api)
+}
+
+unsafe fn load_egl() -> Result<ffi::egl::Egl, CreationError> {
+    let libegl = dlopen::dlopen(b"libEGL.so\0".as_ptr() as *const _, dlopen::RTLD_NOW);
+    if libegl.is_null() {
+        return Err(CreationError::NotSupported("could not find libEGL"));
+    }
+    Ok(ffi::egl::Egl::load_with(|sym| {
+        let sym = CString::new(sym).unwrap();
+        dlopen::dlsym(libegl, sym.as_ptr())
+    }))
 }
 
 impl Context {
+    #[cfg(feature = "winit")]
     pub fn new(
         window_builder: winit::WindowBuilder,
         events_loop: &winit::EventsLoop,
@@ -25,29 +862,129 @@ impl Context {
         let hidpi_factor = window.hidpi_factor();
         let w = (w_px as f32 / hidpi_factor) as u32;
         let h = (h_px as f32 / hidpi_factor) as u32;
+        let display_ptr = window.get_wayland_display().unwrap() as *const _;
         let surface = window.get_wayland_surface().unwrap();
-        let egl_surface = unsafe { wegl::WlEglSurface::new_from_raw(surface as *mut _, w as i32, h as i32) };
-        let context = {
-            let libegl = unsafe { dlopen::dlopen(b"libEGL.so\0".as_ptr() as *const _, dlopen::RTLD_NOW) };
-            if libegl.is_null() {
-                return Err(CreationError::NotSupported("could not find libEGL"));
-            }
-            let egl = ::api::egl::ffi::egl::Egl::load_with(|sym| {
-                let sym = CString::new(sym).unwrap();
-                unsafe { dlopen::dlsym(libegl, sym.as_ptr()) }
-            });
-            let gl_attr = gl_attr.clone().map_sharing(|_| unimplemented!()); // TODO
-            let native_display = egl::NativeDisplay::Wayland(Some(
-                window.get_wayland_display().unwrap() as *const _
-            ));
-            EglContext::new(egl, pf_reqs, &gl_attr, native_display)
-                .and_then(|p| p.finish(egl_surface.ptr() as *const _))?
+        let context = unsafe {
+            Self::new_raw_context(display_ptr, surface, w, h, pf_reqs, gl_attr)?
         };
-        let context = Context {
+        Ok((window, context))
+    }
+
+    /// Builds a context on a `wl_surface` that was created by the user.
+    ///
+    /// The surface and the display must remain alive for as long as the context does.
+    pub unsafe fn new_raw_context(
+        display_ptr: *const c_void,
+        surface: *mut c_void,
+        width: u32,
+        height: u32,
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        let egl_surface = wegl::WlEglSurface::new_from_raw(surface as *mut _, width as i32, height as i32);
+        let context = load_egl().and_then(|egl| {
+            let gl_attr = gl_attr.clone().map_sharing(|_| unimplemented!()); // TODO
+            let native_display = egl::NativeDisplay::Wayland(Some(display_ptr as *const _));
+            EglContext::new(egl, pf_reqs, gl_attr, native_display)
+                .and_then(|p| p.finish(egl_surface.ptr() as *const _))
+        }).map_err(|err| CreationError::from_backend_errors(vec![(Backend::Egl, err)]))?;
+        Ok(Context {
             egl_surface: Arc::new(egl_surface),
             context: context,
-        };
-        Ok((window, context))
+            display: display_ptr,
+            frame_callbacks: FrameCallbacks::new(display_ptr, surface),
+            throttle: AtomicBool::new(true),
+        })
+    }
+
+    /// Builds a context on a `wl_surface` that was created by the user, using a config returned
+    /// by `find_configs`.
+    ///
+    /// The surface and the display must remain alive for as long as the context does.
+    pub unsafe fn new_with_config(
+        config: &Config,
+        surface: *mut c_void,
+        width: u32,
+        height: u32,
+        gl_attr: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        if gl_attr.sharing.is_some() {
+            return Err(CreationError::NotSupported(
+                "sharing lists with a context built from a Config"));
+        }
+        let egl_surface = wegl::WlEglSurface::new_from_raw(surface as *mut _, width as i32, height as i32);
+        let gl_attr = gl_attr.clone().map_sharing(|_| unreachable!());
+        let context = EglContext::new_with_config(&config.0, gl_attr)
+            .and_then(|p| p.finish(egl_surface.ptr() as *const _))?;
+        Ok(Context {
+            egl_surface: Arc::new(egl_surface),
+            context: context,
+            display: config.1,
+            frame_callbacks: FrameCallbacks::new(config.1, surface),
+            throttle: AtomicBool::new(true),
+        })
+    }
+
+    /// Destroys this context and builds a new one with the same config, drawing to the same
+    /// `wl_egl_window`.
+    pub unsafe fn recreate(self, gl_attr: &GlAttributes<&Context>) -> Result<Self, CreationError> {
+        let config = self.config();
+        let Context { egl_surface, context, display, frame_callbacks, throttle } = self;
+        // a window can't have two EGL surfaces at once
+        drop(context);
+        let gl_attr = gl_attr.clone().map_sharing(|ctxt| &ctxt.context);
+        let context = EglContext::new_with_config(&config.0, gl_attr)
+            .and_then(|p| p.finish(egl_surface.ptr() as *const _))?;
+        Ok(Context {
+            egl_surface: egl_surface,
+            context: context,
+            display: display,
+            frame_callbacks: frame_callbacks,
+            throttle: throttle,
+        })
+    }
+
+    /// Wraps an EGL context created by another library, drawing to a `wl_surface` that was
+    /// created by the user.
+    ///
+    /// The context is not destroyed when dropped, and the surface and the display must remain
+    /// alive for as long as the context does.
+    pub unsafe fn adopt_raw_context(
+        display_ptr: *const c_void,
+        context: ffi::EGLContext,
+        surface: *mut c_void,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, CreationError>
+    {
+        let egl_surface = wegl::WlEglSurface::new_from_raw(surface as *mut _, width as i32, height as i32);
+        let egl = load_egl()?;
+        let native_display = egl::NativeDisplay::Wayland(Some(display_ptr as *const _));
+        let context = EglContext::adopt(egl, native_display, context,
+                                        egl_surface.ptr() as *const _)?;
+        Ok(Context {
+            egl_surface: Arc::new(egl_surface),
+            context: context,
+            display: display_ptr,
+            frame_callbacks: FrameCallbacks::new(display_ptr, surface),
+            throttle: AtomicBool::new(true),
+        })
+    }
+
+    /// Returns the config this context was created with.
+    #[inline]
+    pub fn config(&self) -> Config {
+        Config(self.context.config(), self.display)
+    }
+
+    /// Makes the context current, drawing to `draw` and reading from `read`.
+    #[inline]
+    pub unsafe fn make_current_surfaces(&self, draw: &Surface, read: &Surface)
+                                        -> Result<(), ContextError>
+    {
+        self.context.make_current_surfaces(&draw.surface, &read.surface)
     }
 
     pub fn resize(&self, width: u32, height: u32) {
@@ -59,11 +996,169 @@ impl Context {
         self.context.make_current()
     }
 
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        self.context.make_not_current()
+    }
+
     #[inline]
     pub fn is_current(&self) -> bool {
         self.context.is_current()
     }
 
+    #[inline]
+    pub fn validate(&self) -> Result<(), ContextError> {
+        self.context.validate()
+    }
+
+    #[inline]
+    pub fn set_swap_interval(&self, interval: SwapInterval) -> Result<(), ContextError> {
+        self.context.set_swap_interval(interval)?;
+        self.throttle.store(interval != SwapInterval::Wait(0), Ordering::Relaxed);
+        Ok(())
+    }
+
+    #[inline]
+    pub fn get_swap_interval(&self) -> Result<SwapInterval, ContextError> {
+        self.context.get_swap_interval()
+    }
+
+    #[inline]
+    pub fn get_swap_interval_range(&self) -> Result<(u32, u32), ContextError> {
+        self.context.get_swap_interval_range()
+    }
+
+    /// The compositor only shows the latest frame, so an interval of 0 never tears.
+    pub fn supported_present_modes(&self) -> Result<Vec<PresentMode>, ContextError> {
+        let modes = self.context.supported_present_modes()?;
+        Ok(modes.into_iter().map(|mode| match mode {
+            PresentMode::Immediate => PresentMode::Mailbox,
+            mode => mode,
+        }).collect())
+    }
+
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        self.context.swap_behavior()
+    }
+
+    #[inline]
+    pub fn colorspace(&self) -> Result<Colorspace, ContextError> {
+        self.context.colorspace()
+    }
+
+    #[inline]
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        self.context.render_buffer()
+    }
+
+    #[inline]
+    pub fn set_render_buffer(&self, render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        self.context.set_render_buffer(render_buffer)
+    }
+
+    #[inline]
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        self.context.multisample_resolve()
+    }
+
+    #[inline]
+    pub fn set_multisample_resolve(&self, resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        self.context.set_multisample_resolve(resolve)
+    }
+
+    /// Binds `wp_presentation` if it wasn't done yet, after which the frames are timed with the
+    /// `presentation-time` protocol instead of EGL.
+    fn activate_presentation(&self) -> bool {
+        match self.frame_callbacks {
+            Some(ref frame_callbacks) => {
+                let mut state = frame_callbacks.state.lock().unwrap();
+                frame_callbacks.activate_presentation(&mut state)
+            },
+            None => false,
+        }
+    }
+
+    /// Returns the surface synchronization through which acquire fences are attached to the
+    /// swaps, if both EGL and the compositor support explicit synchronization.
+    fn surface_synchronization(&self, frame_callbacks: &FrameCallbacks, state: &mut FrameState)
+                               -> Option<*mut c_void>
+    {
+        if let ExplicitSyncState::Unknown = state.explicit_sync {
+            if !self.context.has_native_fence_sync() {
+                state.explicit_sync = ExplicitSyncState::Unavailable;
+            }
+        }
+        frame_callbacks.surface_synchronization(state)
+    }
+
+    fn frame_feedback(&self, frame_id: u64) -> Option<FrameFeedback> {
+        self.frame_callbacks.as_ref().and_then(|frame_callbacks| {
+            let mut state = frame_callbacks.state.lock().unwrap();
+            frame_callbacks.feedback(&mut state, frame_id)
+        })
+    }
+
+    #[inline]
+    pub fn swap_buffers_with_frame_id(&self) -> Result<u64, ContextError> {
+        if self.activate_presentation() {
+            return Ok(self.swap()?.unwrap());
+        }
+        self.context.swap_buffers_with_frame_id()
+    }
+
+    #[inline]
+    pub fn try_swap_buffers(&self) -> Result<u64, ContextError> {
+        if self.activate_presentation() {
+            let frame_callbacks = self.frame_callbacks.as_ref().unwrap();
+            let pending = {
+                let mut state = frame_callbacks.state.lock().unwrap();
+                frame_callbacks.is_last_frame_pending(&mut state)
+            };
+            if pending {
+                return Err(ContextError::WouldBlock);
+            }
+            return self.swap_buffers_with_frame_id();
+        }
+        self.context.try_swap_buffers()
+    }
+
+    #[inline]
+    pub fn presentation_time(&self, frame_id: u64) -> Result<PresentationTime, ContextError> {
+        if let Some(feedback) = self.frame_feedback(frame_id) {
+            return Ok(feedback.presentation_time());
+        }
+        self.context.presentation_time(frame_id)
+    }
+
+    /// Returns how the frame identified by `frame_id` was presented, using the
+    /// `presentation-time` protocol.
+    pub fn presentation_feedback(&self, frame_id: u64)
+                                 -> Result<Option<PresentationFeedback>, ContextError>
+    {
+        if !self.activate_presentation() {
+            return Err(ContextError::FunctionUnavailable);
+        }
+        match self.frame_feedback(frame_id) {
+            Some(FrameFeedback::Presented(feedback)) => Ok(Some(feedback)),
+            _ => Ok(None),
+        }
+    }
+
+    #[inline]
+    pub fn current_state(&self) -> egl::CurrentState {
+        self.context.current_state()
+    }
+
+    #[inline]
+    pub unsafe fn restore_current_state(&self, state: &egl::CurrentState)
+                                        -> Result<(), ContextError>
+    {
+        self.context.restore_current_state(state)
+    }
+
     #[inline]
     pub fn get_proc_address(&self, addr: &str) -> *const () {
         self.context.get_proc_address(addr)
@@ -71,7 +1166,38 @@ impl Context {
 
     #[inline]
     pub fn swap_buffers(&self) -> Result<(), ContextError> {
-        self.context.swap_buffers()
+        self.swap().map(|_| ())
+    }
+
+    /// Swaps the buffers, and returns the id of the frame if `wp_presentation` is bound.
+    fn swap(&self) -> Result<Option<u64>, ContextError> {
+        let frame_callbacks = match self.frame_callbacks {
+            Some(ref frame_callbacks) => frame_callbacks,
+            None => return self.context.swap_buffers().map(|_| None),
+        };
+
+        let frame = {
+            let mut state = frame_callbacks.state.lock().unwrap();
+            if self.throttle.load(Ordering::Relaxed) {
+                if !frame_callbacks.wait(&mut state, FRAME_CALLBACK_TIMEOUT) {
+                    // the surface is most likely hidden, and EGL would block until it is shown
+                    // again, so the frame is dropped instead
+                    return Ok(frame_callbacks.next_frame(&mut state, false));
+                }
+                frame_callbacks.request(&mut state);
+            }
+            // the feedback and the fence must be set before the swap commits the surface
+            if let Some(surface_sync) = self.surface_synchronization(frame_callbacks, &mut state) {
+                // without a fence, the compositor falls back to implicit synchronization
+                if let Ok(fence) = self.context.create_native_fence_fd() {
+                    frame_callbacks.set_acquire_fence(surface_sync, fence);
+                    unsafe { libc::close(fence); }
+                }
+            }
+            frame_callbacks.next_frame(&mut state, true)
+        };
+        self.context.swap_buffers()?;
+        Ok(frame)
     }
 
     #[inline]
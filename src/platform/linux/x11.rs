@@ -1,12 +1,21 @@
+#[cfg(feature = "winit")]
 pub use winit::os::unix::x11::{XError, XNotSupported, XConnection};
 
-use std::{mem, ptr, fmt, error};
+use std::{mem, ptr};
+#[cfg(feature = "winit")]
+use std::{fmt, error};
 use std::sync::Arc;
 
+#[cfg(feature = "winit")]
 use winit;
+#[cfg(feature = "winit")]
 use winit::os::unix::{EventsLoopExt, WindowExt, WindowBuilderExt};
 
-use {Api, ContextError, CreationError, GlAttributes, GlRequest, PixelFormat, PixelFormatRequirements};
+use {Api, Backend, ContextError, CreationError, GlAttributes, GlRequest, GlxSwapControl};
+use {DeviceInfo, PixelFormat, SupportedVersions};
+use {PixelFormatRequirements, PresentMode, PresentationFeedback, PresentationTime, Rect};
+use {Colorspace, MultisampleResolve, PbufferAttributes, PbufferLimits, RenderBuffer, SwapBehavior};
+use SwapInterval;
 
 use std::ffi::CString;
 
@@ -14,17 +23,22 @@ use api::glx::{ffi, Context as GlxContext};
 use api::{dlopen, egl};
 use api::egl::Context as EglContext;
 use api::glx::ffi::glx::Glx;
+use platform::RawHandle;
 use api::egl::ffi::egl::Egl;
+use super::xpresent::PresentTracker;
 
+#[cfg(feature = "winit")]
 #[derive(Debug)]
 struct NoX11Connection;
 
+#[cfg(feature = "winit")]
 impl error::Error for NoX11Connection {
     fn description(&self) -> &str {
         "failed to get x11 connection"
     }
 }
 
+#[cfg(feature = "winit")]
 impl fmt::Display for NoX11Connection {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.write_str(error::Error::description(self))
@@ -83,16 +97,368 @@ impl GlxOrEgl {
     }
 }
 
+enum Prototype<'a> {
+    Glx(::api::glx::ContextPrototype<'a>),
+    Egl(::api::egl::ContextPrototype<'a>),
+}
+
 pub enum GlContext {
     Glx(GlxContext),
     Egl(EglContext),
     None,
 }
 
+/// The state saved by `Context::current_state`, for the backend the context was created with.
+pub enum CurrentState {
+    Glx(::api::glx::CurrentState),
+    Egl(::api::egl::CurrentState),
+    None,
+}
+
+/// The X11 display a context is built on.
+#[derive(Clone)]
+enum XDisplay {
+    /// The connection of a winit events loop, or one provided by the user.
+    #[cfg(feature = "winit")]
+    Connection(Arc<XConnection>),
+    /// A display opened by another library. It is not closed when the context is dropped.
+    Foreign(Arc<ffi::Xlib>, *mut ffi::Display),
+}
+
+impl XDisplay {
+    #[inline]
+    fn xlib(&self) -> &ffi::Xlib {
+        match *self {
+            #[cfg(feature = "winit")]
+            XDisplay::Connection(ref xconn) => &xconn.xlib,
+            XDisplay::Foreign(ref xlib, _) => xlib,
+        }
+    }
+
+    #[inline]
+    fn display(&self) -> *mut ffi::Display {
+        match *self {
+            #[cfg(feature = "winit")]
+            XDisplay::Connection(ref xconn) => xconn.display,
+            XDisplay::Foreign(_, display) => display,
+        }
+    }
+
+    /// Panics with `msg` if an X error was reported through the connection.
+    ///
+    /// Errors on foreign displays are reported to whatever error handler the owner installed.
+    #[inline]
+    fn check_errors(&self, msg: &str) {
+        match *self {
+            #[cfg(feature = "winit")]
+            XDisplay::Connection(ref xconn) => { xconn.check_errors().expect(msg); },
+            XDisplay::Foreign(..) => { let _ = msg; },
+        }
+    }
+
+    /// Waits for the X server to process the pending requests, and returns the first error they
+    /// caused if it was reported through the connection.
+    fn sync_errors(&self) -> Result<(), CreationError> {
+        unsafe { (self.xlib().XSync)(self.display(), ffi::False); }
+        match *self {
+            #[cfg(feature = "winit")]
+            XDisplay::Connection(ref xconn) => {
+                xconn.check_errors().map_err(|err| CreationError::OsError(err.to_string()))
+            },
+            XDisplay::Foreign(..) => Ok(()),
+        }
+    }
+}
+
+/// An Xlib display opened by the user, from which contexts are created.
+pub struct Display(XDisplay);
+
+impl Display {
+    /// Wraps a display opened by another library. It is not closed when dropped.
+    pub unsafe fn from_raw(display: *mut ffi::Display) -> Result<Display, CreationError> {
+        let xlib = ffi::Xlib::open()
+            .map_err(|err| CreationError::NoBackendAvailable(Box::new(err)))?;
+        Ok(Display(XDisplay::Foreign(Arc::new(xlib), display)))
+    }
+
+    /// Uses the X11 connection of a winit events loop.
+    #[cfg(feature = "winit")]
+    pub fn from_events_loop(events_loop: &winit::EventsLoop) -> Result<Display, CreationError> {
+        match events_loop.get_xlib_xconnection() {
+            Some(display) => Ok(Display(XDisplay::Connection(display))),
+            None => Err(CreationError::NoBackendAvailable(Box::new(NoX11Connection))),
+        }
+    }
+
+    /// Returns the configs of the default screen that match `reqs`.
+    ///
+    /// GLX configs are returned if libGL is available, and EGL configs otherwise.
+    pub fn find_configs(&self, reqs: &PixelFormatRequirements)
+                        -> Result<Vec<Config>, CreationError>
+    {
+        let screen_id = unsafe { (self.0.xlib().XDefaultScreen)(self.0.display()) };
+        let backend = GlxOrEgl::new();
+        let configs: Vec<_> = if let Some(ref glx) = backend.glx {
+            let configs = unsafe {
+                ::api::glx::find_configs(glx, self.0.xlib(), self.0.display(), screen_id, reqs)?
+            };
+            configs.into_iter().map(GlConfig::Glx).collect()
+        } else if let Some(egl) = backend.egl {
+            let native_display = egl::NativeDisplay::X11(Some(self.0.display() as *const _));
+            let configs = egl::find_configs(egl, native_display, reqs)?;
+            configs.into_iter().map(GlConfig::Egl).collect()
+        } else {
+            return Err(CreationError::NotSupported("both libglx and libEGL not present"));
+        };
+
+        Ok(configs.into_iter().map(|config| {
+            Config { config: config, display: self.0.clone(), screen_id: screen_id }
+        }).collect())
+    }
+
+    /// Returns the device of the default screen, with GLX if libGL is available and with EGL
+    /// otherwise.
+    pub fn device_info(&self) -> Result<DeviceInfo, CreationError> {
+        let screen_id = unsafe { (self.0.xlib().XDefaultScreen)(self.0.display()) };
+        let backend = GlxOrEgl::new();
+        if let Some(ref glx) = backend.glx {
+            unsafe { ::api::glx::device_info(glx, self.0.display(), screen_id) }
+        } else if let Some(egl) = backend.egl {
+            let native_display = egl::NativeDisplay::X11(Some(self.0.display() as *const _));
+            egl::device_info(egl, native_display)
+        } else {
+            Err(CreationError::NotSupported("both libglx and libEGL not present"))
+        }
+    }
+
+    /// Probes the versions of `api` on the default screen, with GLX if libGL is available and
+    /// with EGL otherwise.
+    pub fn probe_versions(&self, api: Api) -> Result<SupportedVersions, CreationError> {
+        let screen_id = unsafe { (self.0.xlib().XDefaultScreen)(self.0.display()) };
+        let backend = GlxOrEgl::new();
+        if let Some(ref glx) = backend.glx {
+            if api != Api::OpenGl {
+                return Err(CreationError::NotSupported("GLX only creates OpenGL contexts"));
+            }
+            unsafe { ::api::glx::probe_versions(glx, self.0.xlib(), self.0.display(), screen_id) }
+        } else if let Some(egl) = backend.egl {
+            let native_display = egl::NativeDisplay::X11(Some(self.0.display() as *const _));
+            egl::probe_versions(egl, native_display, api)
+        } else {
+            Err(CreationError::NotSupported("both libglx and libEGL not present"))
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum GlConfig {
+    Glx(::api::glx::Config),
+    Egl(egl::Config),
+}
+
+/// A config of an X11 display, from which contexts and surfaces are created.
+#[derive(Clone)]
+pub struct Config {
+    config: GlConfig,
+    display: XDisplay,
+    screen_id: libc::c_int,
+}
+
+unsafe impl Send for Config {}
+unsafe impl Sync for Config {}
+
+impl Config {
+    #[inline]
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        match self.config {
+            GlConfig::Glx(ref config) => config.get_pixel_format(),
+            GlConfig::Egl(ref config) => config.get_pixel_format(),
+        }
+    }
+
+    #[inline]
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        match self.config {
+            GlConfig::Glx(ref config) => config.max_pbuffer_size(),
+            GlConfig::Egl(ref config) => config.max_pbuffer_size(),
+        }
+    }
+
+    /// Returns the GLX or EGL config.
+    #[inline]
+    pub fn gl_config(&self) -> &GlConfig {
+        &self.config
+    }
+
+    #[inline]
+    pub fn xlib(&self) -> &ffi::Xlib {
+        self.display.xlib()
+    }
+
+    #[inline]
+    pub fn screen_id(&self) -> libc::c_int {
+        self.screen_id
+    }
+
+    /// Creates a surface on `xlib_window` using this config.
+    pub unsafe fn create_window_surface(&self, xlib_window: ffi::Window)
+                                        -> Result<Surface, CreationError>
+    {
+        let surface = match self.config {
+            GlConfig::Glx(ref config) => {
+                GlSurface::Glx(config.create_window_surface(xlib_window)?)
+            },
+            GlConfig::Egl(ref config) => {
+                GlSurface::Egl(config.create_window_surface(xlib_window as _)?)
+            },
+        };
+        self.wrap_surface(surface)
+    }
+
+    /// Creates an offscreen surface using this config.
+    pub fn create_pbuffer_surface(&self, attributes: &PbufferAttributes)
+                                  -> Result<Surface, CreationError>
+    {
+        let surface = match self.config {
+            GlConfig::Glx(ref config) => GlSurface::Glx(config.create_pbuffer_surface(attributes)?),
+            GlConfig::Egl(ref config) => GlSurface::Egl(config.create_pbuffer_surface(attributes)?),
+        };
+        self.wrap_surface(surface)
+    }
+
+    /// Creates a surface on `pixmap` using this config.
+    pub unsafe fn create_pixmap_surface(&self, pixmap: ffi::Pixmap)
+                                        -> Result<Surface, CreationError>
+    {
+        let surface = match self.config {
+            GlConfig::Glx(ref config) => GlSurface::Glx(config.create_pixmap_surface(pixmap)?),
+            GlConfig::Egl(ref config) => {
+                GlSurface::Egl(config.create_pixmap_surface(pixmap as _)?)
+            },
+        };
+        self.wrap_surface(surface)
+    }
+
+    fn wrap_surface(&self, surface: GlSurface) -> Result<Surface, CreationError> {
+        if let Err(err) = self.display.sync_errors() {
+            // dropping the surface releases its EGL display. Destroying a drawable that was never
+            // created fails, and the error is discarded so that a later request doesn't report it
+            drop(surface);
+            let _ = self.display.sync_errors();
+            return Err(err);
+        }
+        Ok(Surface { surface: surface, display: self.display.clone() })
+    }
+}
+
 pub struct Context {
-    display: Arc<XConnection>,
-    colormap: ffi::Colormap,
+    display: XDisplay,
+    // `None` for contexts created by another library.
+    colormap: Option<ffi::Colormap>,
     context: GlContext,
+    // Frame timing through the Present extension, used instead of the one of GLX or EGL when
+    // available.
+    present: PresentTracker,
+}
+
+enum GlSurface {
+    Glx(::api::glx::Surface),
+    Egl(::api::egl::Surface),
+}
+
+/// A surface created from the config of an X11 context.
+pub struct Surface {
+    // The surface must be destroyed before the display is closed.
+    surface: GlSurface,
+    #[allow(dead_code)]
+    display: XDisplay,
+}
+
+unsafe impl Send for Surface {}
+unsafe impl Sync for Surface {}
+
+impl Surface {
+    #[inline]
+    pub fn dimensions(&self) -> Result<(u32, u32), ContextError> {
+        match self.surface {
+            GlSurface::Glx(ref surface) => surface.dimensions(),
+            GlSurface::Egl(ref surface) => surface.dimensions(),
+        }
+    }
+
+    #[inline]
+    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        match self.surface {
+            GlSurface::Glx(ref surface) => surface.swap_buffers(),
+            GlSurface::Egl(ref surface) => surface.swap_buffers(),
+        }
+    }
+
+    #[inline]
+    pub fn buffer_age(&self) -> Result<u32, ContextError> {
+        match self.surface {
+            GlSurface::Glx(ref surface) => surface.buffer_age(),
+            GlSurface::Egl(ref surface) => surface.buffer_age(),
+        }
+    }
+
+    #[inline]
+    pub fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        match self.surface {
+            GlSurface::Glx(ref surface) => surface.set_damage_region(rects),
+            GlSurface::Egl(ref surface) => surface.set_damage_region(rects),
+        }
+    }
+
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        match self.surface {
+            GlSurface::Glx(ref surface) => surface.swap_behavior(),
+            GlSurface::Egl(ref surface) => surface.swap_behavior(),
+        }
+    }
+
+    #[inline]
+    pub fn set_swap_behavior(&self, behavior: SwapBehavior) -> Result<(), ContextError> {
+        match self.surface {
+            GlSurface::Glx(ref surface) => surface.set_swap_behavior(behavior),
+            GlSurface::Egl(ref surface) => surface.set_swap_behavior(behavior),
+        }
+    }
+
+    #[inline]
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        match self.surface {
+            GlSurface::Glx(ref surface) => surface.render_buffer(),
+            GlSurface::Egl(ref surface) => surface.render_buffer(),
+        }
+    }
+
+    #[inline]
+    pub fn set_render_buffer(&self, render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        match self.surface {
+            GlSurface::Glx(ref surface) => surface.set_render_buffer(render_buffer),
+            GlSurface::Egl(ref surface) => surface.set_render_buffer(render_buffer),
+        }
+    }
+
+    #[inline]
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        match self.surface {
+            GlSurface::Glx(ref surface) => surface.multisample_resolve(),
+            GlSurface::Egl(ref surface) => surface.multisample_resolve(),
+        }
+    }
+
+    #[inline]
+    pub fn set_multisample_resolve(&self, resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        match self.surface {
+            GlSurface::Glx(ref surface) => surface.set_multisample_resolve(resolve),
+            GlSurface::Egl(ref surface) => surface.set_multisample_resolve(resolve),
+        }
+    }
 }
 
 unsafe impl Send for Context {}
@@ -104,14 +470,17 @@ impl Drop for Context {
             // we don't call MakeCurrent(0, 0) because we are not sure that the context
             // is still the current one
             self.context = GlContext::None;
+            self.present.release(self.display.display());
 
-            (self.display.xlib.XFreeColormap)(self.display.display, self.colormap);
+            if let Some(colormap) = self.colormap {
+                (self.display.xlib().XFreeColormap)(self.display.display(), colormap);
+            }
         }
     }
 }
 
 impl Context {
-
+    #[cfg(feature = "winit")]
     pub fn new(
         window_builder: winit::WindowBuilder,
         events_loop: &winit::EventsLoop,
@@ -124,65 +493,156 @@ impl Context {
             None => return Err(CreationError::NoBackendAvailable(Box::new(NoX11Connection))),
         };
 
+        let display = XDisplay::Connection(display);
+
         // Get the screen_id for the window being built.
-        let screen_id = unsafe { (display.xlib.XDefaultScreen)(display.display) };
+        let screen_id = unsafe { (display.xlib().XDefaultScreen)(display.display()) };
 
-        // start the context building process
-        enum Prototype<'a> {
-            Glx(::api::glx::ContextPrototype<'a>),
-            Egl(::api::egl::ContextPrototype<'a>),
-        }
+        let (context, visual_infos) = Self::new_first_stage(
+            &display,
+            pf_reqs,
+            gl_attr,
+            screen_id,
+            window_builder.window.transparent,
+        )?;
+
+        let window = window_builder
+                .with_x11_visual(&visual_infos as *const _)
+                .with_x11_screen(screen_id)
+                .build(events_loop)?;
+
+        let xlib_window = window.get_xlib_window().unwrap();
+        let context = Self::new_last_stage(&display, context, xlib_window, &visual_infos, true)?;
+
+        Ok((window, context))
+    }
+
+    /// Builds a context on a window that was created by the user.
+    ///
+    /// The window must remain alive for as long as the context does.
+    #[cfg(feature = "winit")]
+    pub unsafe fn new_raw_context(
+        display: &Arc<XConnection>,
+        xlib_window: ffi::Window,
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        Self::new_raw_context_on(XDisplay::Connection(display.clone()), xlib_window, pf_reqs,
+                                 gl_attr)
+    }
+
+    /// Builds a context on a window that was created by another library, using a display
+    /// provided by the user.
+    ///
+    /// The display and the window must remain alive for as long as the context does.
+    pub unsafe fn new_raw_context_with_display(
+        display: &Display,
+        xlib_window: ffi::Window,
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        Self::new_raw_context_on(display.0.clone(), xlib_window, pf_reqs, gl_attr)
+    }
+
+    unsafe fn new_raw_context_on(
+        display: XDisplay,
+        xlib_window: ffi::Window,
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        let screen_id = (display.xlib().XDefaultScreen)(display.display());
+
+        let (context, visual_infos) = Self::new_first_stage(
+            &display,
+            pf_reqs,
+            gl_attr,
+            screen_id,
+            false,
+        )?;
 
+        Self::new_last_stage(&display, context, xlib_window, &visual_infos, false)
+    }
+
+    fn new_first_stage<'a>(
+        display: &'a XDisplay,
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &'a GlAttributes<&'a Context>,
+        screen_id: libc::c_int,
+        transparent: bool,
+    ) -> Result<(Prototype<'a>, ffi::XVisualInfo), CreationError>
+    {
         let builder_clone_opengl_glx = gl_attr.clone().map_sharing(|_| unimplemented!());      // FIXME:
         let builder_clone_opengl_egl = gl_attr.clone().map_sharing(|_| unimplemented!());      // FIXME:
         let backend = GlxOrEgl::new();
-        let context = match gl_attr.version {
+        let mut errors = Vec::new();
+
+        let try_glx = match gl_attr.version {
             GlRequest::Latest |
             GlRequest::Specific(Api::OpenGl, _) |
-            GlRequest::GlThenGles { .. } => {
-                // GLX should be preferred over EGL, otherwise crashes may occur
-                // on X11 – issue #314
-                if let Some(ref glx) = backend.glx {
-                    Prototype::Glx(GlxContext::new(
+            GlRequest::GlThenGles { .. } => true,
+            GlRequest::Specific(Api::OpenGlEs, _) => false,
+            GlRequest::Specific(_, _) => {
+                return Err(CreationError::NotSupported("requested specific without gl or gles"));
+            },
+            // the list is expanded into `GlRequest::Specific` requests by `create_with_versions`
+            GlRequest::PreferredList(_) => return Err(CreationError::OpenGlVersionNotSupported),
+        };
+
+        // GLX should be preferred over EGL, otherwise crashes may occur
+        // on X11 – issue #314
+        if try_glx {
+            match backend.glx {
+                Some(ref glx) => {
+                    match GlxContext::new(
                         glx.clone(),
-                        &display.xlib,
+                        display.xlib(),
                         pf_reqs,
-                        &builder_clone_opengl_glx,
-                        display.display,
+                        builder_clone_opengl_glx,
+                        display.display(),
                         screen_id,
-                        window_builder.window.transparent,
-                    )?)
-                } else if let Some(ref egl) = backend.egl {
-                    let native_display = egl::NativeDisplay::X11(Some(display.display as *const _));
-                    Prototype::Egl(EglContext::new(
-                        egl.clone(),
-                        pf_reqs,
-                        &builder_clone_opengl_egl,
-                        native_display,
-                    )?)
-                } else {
-                    return Err(CreationError::NotSupported("both libglx and libEGL not present"));
+                        transparent,
+                    ) {
+                        Ok(context) => {
+                            let context = Prototype::Glx(context);
+                            let visual_infos = Self::get_visual_infos(display, &context);
+                            return Ok((context, visual_infos));
+                        },
+                        Err(err) => errors.push((Backend::Glx, err)),
+                    }
+                },
+                None => errors.push((Backend::Glx, CreationError::NotSupported("libGL not present"))),
+            }
+        }
+
+        let context = match backend.egl {
+            Some(ref egl) => {
+                let native_display = egl::NativeDisplay::X11(Some(display.display() as *const _));
+                match EglContext::new(egl.clone(), pf_reqs, builder_clone_opengl_egl,
+                                      native_display)
+                {
+                    Ok(context) => Prototype::Egl(context),
+                    Err(err) => {
+                        errors.push((Backend::Egl, err));
+                        return Err(CreationError::from_backend_errors(errors));
+                    },
                 }
             },
-            GlRequest::Specific(Api::OpenGlEs, _) => {
-                if let Some(ref egl) = backend.egl {
-                    Prototype::Egl(EglContext::new(
-                        egl.clone(),
-                        pf_reqs,
-                        &builder_clone_opengl_egl,
-                        egl::NativeDisplay::X11(Some(display.display as *const _)),
-                    )?)
-                } else {
-                    return Err(CreationError::NotSupported("libEGL not present"));
-                }
-            },
-            GlRequest::Specific(_, _) => {
-                return Err(CreationError::NotSupported("requested specific without gl or gles"));
+            None => {
+                errors.push((Backend::Egl, CreationError::NotSupported("libEGL not present")));
+                return Err(CreationError::from_backend_errors(errors));
             },
         };
 
-        // getting the `visual_infos` (a struct that contains information about the visual to use)
-        let visual_infos = match context {
+        let visual_infos = Self::get_visual_infos(display, &context);
+        Ok((context, visual_infos))
+    }
+
+    /// Returns the visual that windows must be created with to be used with `context`.
+    fn get_visual_infos(display: &XDisplay, context: &Prototype) -> ffi::XVisualInfo {
+        match *context {
             Prototype::Glx(ref p) => p.get_visual_infos().clone(),
             Prototype::Egl(ref p) => {
                 unsafe {
@@ -190,25 +650,34 @@ impl Context {
                     template.visualid = p.get_native_visual_id() as ffi::VisualID;
 
                     let mut num_visuals = 0;
-                    let vi = (display.xlib.XGetVisualInfo)(display.display, ffi::VisualIDMask,
-                                                           &mut template, &mut num_visuals);
-                    display.check_errors().expect("Failed to call XGetVisualInfo");
+                    let vi = (display.xlib().XGetVisualInfo)(display.display(), ffi::VisualIDMask,
+                                                             &mut template, &mut num_visuals);
+                    display.check_errors("Failed to call XGetVisualInfo");
                     assert!(!vi.is_null());
                     assert!(num_visuals == 1);
 
                     let vi_copy = ptr::read(vi as *const _);
-                    (display.xlib.XFree)(vi as *mut _);
+                    (display.xlib().XFree)(vi as *mut _);
                     vi_copy
                 }
             },
-        };
+        }
+    }
 
-        let window = window_builder
-                .with_x11_visual(&visual_infos as *const _)
-                .with_x11_screen(screen_id)
-                .build(events_loop)?;
+    /// Finishes creating the context on `xlib_window`. The windows that glutin didn't create
+    /// must already have the visual of the context, and get no colormap.
+    fn new_last_stage(
+        display: &XDisplay,
+        context: Prototype,
+        xlib_window: ffi::Window,
+        visual_infos: &ffi::XVisualInfo,
+        owns_window: bool,
+    ) -> Result<Self, CreationError>
+    {
+        if !owns_window {
+            Self::check_window_visual(display, xlib_window, visual_infos)?;
+        }
 
-        let xlib_window = window.get_xlib_window().unwrap();
         // finish creating the OpenGL context
         let context = match context {
             Prototype::Glx(ctxt) => {
@@ -219,26 +688,160 @@ impl Context {
             },
         };
 
-        // getting the root window
-        let root = unsafe { (display.xlib.XDefaultRootWindow)(display.display) };
-        display.check_errors().expect("Failed to get root window");
-
-        // creating the color map
-        let cmap = unsafe {
-            let cmap = (display.xlib.XCreateColormap)(display.display, root,
-                                                      visual_infos.visual as *mut _,
-                                                      ffi::AllocNone);
-            display.check_errors().expect("Failed to call XCreateColormap");
-            cmap
+        let colormap = if owns_window {
+            // getting the root window
+            let root = unsafe { (display.xlib().XDefaultRootWindow)(display.display()) };
+            display.check_errors("Failed to get root window");
+
+            // creating the color map
+            let cmap = unsafe {
+                let cmap = (display.xlib().XCreateColormap)(display.display(), root,
+                                                            visual_infos.visual as *mut _,
+                                                            ffi::AllocNone);
+                display.check_errors("Failed to call XCreateColormap");
+                cmap
+            };
+            Some(cmap)
+        } else {
+            None
         };
 
-        let context = Context {
+        Ok(Context {
             display: display.clone(),
             context: context,
-            colormap: cmap,
+            colormap: colormap,
+            present: PresentTracker::new(xlib_window),
+        })
+    }
+
+    /// Returns `CreationError::NotSupported` if `xlib_window` wasn't created with the visual of
+    /// `visual_infos`, in which case making the context current would fail with `BadMatch`.
+    fn check_window_visual(display: &XDisplay, xlib_window: ffi::Window,
+                           visual_infos: &ffi::XVisualInfo)
+                           -> Result<(), CreationError>
+    {
+        let visual_id = unsafe {
+            let mut attributes: ffi::XWindowAttributes = mem::zeroed();
+            let status = (display.xlib().XGetWindowAttributes)(display.display(), xlib_window,
+                                                               &mut attributes);
+            // a window that doesn't exist is reported as an error instead of panicking
+            display.sync_errors()?;
+            if status == 0 || attributes.visual.is_null() {
+                return Err(CreationError::OsError(format!("XGetWindowAttributes failed")));
+            }
+            (display.xlib().XVisualIDFromVisual)(attributes.visual)
         };
+        if visual_id != visual_infos.visualid {
+            return Err(CreationError::NotSupported(
+                "the window wasn't created with the visual of the chosen config"));
+        }
+        Ok(())
+    }
 
-        Ok((window, context))
+    /// Builds a context on a window that was created by another library, using a config
+    /// returned by `Display::find_configs`.
+    ///
+    /// The window must remain alive for as long as the context does.
+    pub unsafe fn new_with_config(
+        config: &Config,
+        xlib_window: ffi::Window,
+        gl_attr: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        if gl_attr.sharing.is_some() {
+            return Err(CreationError::NotSupported(
+                "sharing lists with a context built from a Config"));
+        }
+        let prototype = match config.config {
+            GlConfig::Glx(ref glx_config) => {
+                let gl_attr = gl_attr.clone().map_sharing(|_| unreachable!());
+                Prototype::Glx(GlxContext::new_with_config(config.display.xlib(), glx_config,
+                                                           gl_attr, config.screen_id)?)
+            },
+            GlConfig::Egl(ref egl_config) => {
+                let gl_attr = gl_attr.clone().map_sharing(|_| unreachable!());
+                Prototype::Egl(EglContext::new_with_config(egl_config, gl_attr)?)
+            },
+        };
+
+        let visual_infos = Self::get_visual_infos(&config.display, &prototype);
+        Self::new_last_stage(&config.display, prototype, xlib_window, &visual_infos, false)
+    }
+
+    /// Destroys this context and builds a new one with the same config, drawing to the same
+    /// window.
+    pub unsafe fn recreate(self, gl_attr: &GlAttributes<&Context>) -> Result<Self, CreationError> {
+        let config = self.config();
+        let window = self.present.window();
+        // with EGL, a window can't have two surfaces at once
+        drop(self);
+        Self::new_with_config(&config, window, gl_attr)
+    }
+
+    /// Wraps a context created by another library, drawing to a window that was also created
+    /// by another library.
+    ///
+    /// The context is not destroyed when dropped, and the window must remain alive for as long
+    /// as the context does.
+    pub unsafe fn adopt_raw_context(
+        display: &Display,
+        raw_handle: RawHandle,
+        xlib_window: ffi::Window,
+    ) -> Result<Self, CreationError>
+    {
+        let display = &display.0;
+        let screen_id = (display.xlib().XDefaultScreen)(display.display());
+        let backend = GlxOrEgl::new();
+
+        let context = match raw_handle {
+            RawHandle::Glx(context) => {
+                let glx = backend.glx.ok_or(CreationError::NotSupported("libglx not present"))?;
+                GlContext::Glx(GlxContext::adopt(glx, display.xlib(), display.display(),
+                                                 screen_id, context, xlib_window)?)
+            },
+            RawHandle::Egl(context) => {
+                let egl = backend.egl.ok_or(CreationError::NotSupported("libEGL not present"))?;
+                let native_display = egl::NativeDisplay::X11(Some(display.display() as *const _));
+                GlContext::Egl(EglContext::adopt(egl, native_display, context,
+                                                 xlib_window as _)?)
+            },
+        };
+
+        Ok(Context {
+            display: display.clone(),
+            colormap: None,
+            context: context,
+            present: PresentTracker::new(xlib_window),
+        })
+    }
+
+    /// Returns the config this context was created with.
+    pub fn config(&self) -> Config {
+        let config = match self.context {
+            GlContext::Glx(ref ctxt) => GlConfig::Glx(ctxt.config()),
+            GlContext::Egl(ref ctxt) => GlConfig::Egl(ctxt.config()),
+            GlContext::None => unreachable!(),
+        };
+        let screen_id = unsafe {
+            (self.display.xlib().XDefaultScreen)(self.display.display())
+        };
+        Config { config: config, display: self.display.clone(), screen_id: screen_id }
+    }
+
+    /// Makes the context current, drawing to `draw` and reading from `read`.
+    pub unsafe fn make_current_surfaces(&self, draw: &Surface, read: &Surface)
+                                        -> Result<(), ContextError>
+    {
+        match (&self.context, &draw.surface, &read.surface) {
+            (&GlContext::Glx(ref ctxt), &GlSurface::Glx(ref draw), &GlSurface::Glx(ref read)) => {
+                ctxt.make_current_surfaces(draw, read)
+            },
+            (&GlContext::Egl(ref ctxt), &GlSurface::Egl(ref draw), &GlSurface::Egl(ref read)) => {
+                ctxt.make_current_surfaces(draw, read)
+            },
+            _ => Err(ContextError::OsError(format!("the surfaces weren't created with the same \
+                                                    backend as the context"))),
+        }
     }
 
     #[inline]
@@ -250,6 +853,15 @@ impl Context {
         }
     }
 
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        match self.context {
+            GlContext::Glx(ref ctxt) => ctxt.make_not_current(),
+            GlContext::Egl(ref ctxt) => ctxt.make_not_current(),
+            GlContext::None => Ok(())
+        }
+    }
+
     #[inline]
     pub fn is_current(&self) -> bool {
         match self.context {
@@ -259,6 +871,201 @@ impl Context {
         }
     }
 
+    #[inline]
+    pub fn validate(&self) -> Result<(), ContextError> {
+        match self.context {
+            GlContext::Glx(ref ctxt) => ctxt.validate(),
+            GlContext::Egl(ref ctxt) => ctxt.validate(),
+            GlContext::None => unreachable!(),
+        }
+    }
+
+    #[inline]
+    pub fn set_swap_interval(&self, interval: SwapInterval) -> Result<(), ContextError> {
+        match self.context {
+            GlContext::Glx(ref ctxt) => ctxt.set_swap_interval(interval),
+            GlContext::Egl(ref ctxt) => ctxt.set_swap_interval(interval),
+            GlContext::None => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn get_swap_interval(&self) -> Result<SwapInterval, ContextError> {
+        match self.context {
+            GlContext::Glx(ref ctxt) => ctxt.get_swap_interval(),
+            GlContext::Egl(ref ctxt) => ctxt.get_swap_interval(),
+            GlContext::None => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn get_swap_interval_range(&self) -> Result<(u32, u32), ContextError> {
+        match self.context {
+            GlContext::Glx(ref ctxt) => ctxt.get_swap_interval_range(),
+            GlContext::Egl(ref ctxt) => ctxt.get_swap_interval_range(),
+            GlContext::None => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn supported_present_modes(&self) -> Result<Vec<PresentMode>, ContextError> {
+        match self.context {
+            GlContext::Glx(ref ctxt) => ctxt.supported_present_modes(),
+            GlContext::Egl(ref ctxt) => ctxt.supported_present_modes(),
+            GlContext::None => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn glx_swap_control(&self) -> Option<GlxSwapControl> {
+        match self.context {
+            GlContext::Glx(ref ctxt) => ctxt.swap_control(),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        match self.context {
+            GlContext::Glx(_) => Ok(SwapBehavior::Destroyed),
+            GlContext::Egl(ref ctxt) => ctxt.swap_behavior(),
+            GlContext::None => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn colorspace(&self) -> Result<Colorspace, ContextError> {
+        match self.context {
+            GlContext::Glx(ref ctxt) => Ok(ctxt.get_pixel_format().colorspace),
+            GlContext::Egl(ref ctxt) => ctxt.colorspace(),
+            GlContext::None => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        match self.context {
+            GlContext::Glx(ref ctxt) => Ok(ctxt.get_pixel_format().render_buffer()),
+            GlContext::Egl(ref ctxt) => ctxt.render_buffer(),
+            GlContext::None => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn set_render_buffer(&self, render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        match self.context {
+            GlContext::Glx(_) if self.render_buffer()? == render_buffer => Ok(()),
+            GlContext::Glx(_) => Err(ContextError::FunctionUnavailable),
+            GlContext::Egl(ref ctxt) => ctxt.set_render_buffer(render_buffer),
+            GlContext::None => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        match self.context {
+            GlContext::Glx(_) => Ok(MultisampleResolve::Default),
+            GlContext::Egl(ref ctxt) => ctxt.multisample_resolve(),
+            GlContext::None => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn set_multisample_resolve(&self, resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        match self.context {
+            GlContext::Glx(_) if resolve == MultisampleResolve::Default => Ok(()),
+            GlContext::Glx(_) => Err(ContextError::FunctionUnavailable),
+            GlContext::Egl(ref ctxt) => ctxt.set_multisample_resolve(resolve),
+            GlContext::None => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn join_swap_group(&self, group: u32) -> Result<(), ContextError> {
+        match self.context {
+            GlContext::Glx(ref ctxt) => ctxt.join_swap_group(group),
+            _ => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn bind_swap_barrier(&self, group: u32, barrier: u32) -> Result<(), ContextError> {
+        match self.context {
+            GlContext::Glx(ref ctxt) => ctxt.bind_swap_barrier(group, barrier),
+            _ => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn swap_buffers_with_frame_id(&self) -> Result<u64, ContextError> {
+        if self.present.activate(self.display.xlib(), self.display.display()) {
+            self.swap_buffers()?;
+            return Ok(self.present.on_swap().unwrap());
+        }
+
+        match self.context {
+            GlContext::Glx(ref ctxt) => ctxt.swap_buffers_with_frame_id(),
+            GlContext::Egl(ref ctxt) => ctxt.swap_buffers_with_frame_id(),
+            GlContext::None => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn try_swap_buffers(&self) -> Result<u64, ContextError> {
+        if self.present.activate(self.display.xlib(), self.display.display()) {
+            if self.present.is_pending(self.display.xlib(), self.display.display()) == Some(true) {
+                return Err(ContextError::WouldBlock);
+            }
+            return self.swap_buffers_with_frame_id();
+        }
+
+        match self.context {
+            GlContext::Glx(ref ctxt) => ctxt.try_swap_buffers(),
+            GlContext::Egl(ref ctxt) => ctxt.try_swap_buffers(),
+            GlContext::None => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn presentation_time(&self, frame_id: u64) -> Result<PresentationTime, ContextError> {
+        if let Some(feedback) = self.present.feedback(self.display.xlib(), self.display.display(),
+                                                      frame_id)
+        {
+            return Ok(feedback.presentation_time());
+        }
+
+        match self.context {
+            GlContext::Glx(ref ctxt) => ctxt.presentation_time(frame_id),
+            GlContext::Egl(ref ctxt) => ctxt.presentation_time(frame_id),
+            GlContext::None => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn current_state(&self) -> CurrentState {
+        match self.context {
+            GlContext::Glx(ref ctxt) => CurrentState::Glx(ctxt.current_state()),
+            GlContext::Egl(ref ctxt) => CurrentState::Egl(ctxt.current_state()),
+            GlContext::None => CurrentState::None,
+        }
+    }
+
+    #[inline]
+    pub unsafe fn restore_current_state(&self, state: &CurrentState) -> Result<(), ContextError> {
+        match (&self.context, state) {
+            (&GlContext::Glx(ref ctxt), &CurrentState::Glx(ref state)) => {
+                ctxt.restore_current_state(state)
+            },
+            (&GlContext::Egl(ref ctxt), &CurrentState::Egl(ref state)) => {
+                ctxt.restore_current_state(state)
+            },
+            (&GlContext::None, _) => Ok(()),
+            _ => unreachable!(),
+        }
+    }
+
     #[inline]
     pub fn get_proc_address(&self, addr: &str) -> *const () {
         match self.context {
@@ -271,9 +1078,28 @@ impl Context {
     #[inline]
     pub fn swap_buffers(&self) -> Result<(), ContextError> {
         match self.context {
-            GlContext::Glx(ref ctxt) => ctxt.swap_buffers(),
-            GlContext::Egl(ref ctxt) => ctxt.swap_buffers(),
-            GlContext::None => Ok(())
+            GlContext::Glx(ref ctxt) => ctxt.swap_buffers()?,
+            GlContext::Egl(ref ctxt) => ctxt.swap_buffers()?,
+            GlContext::None => return Ok(())
+        }
+        // the completion of every swap is notified once the events are selected
+        self.present.on_swap();
+        Ok(())
+    }
+
+    /// Returns how the frame identified by `frame_id` was presented, using the Present
+    /// extension.
+    pub fn presentation_feedback(&self, frame_id: u64)
+                                 -> Result<Option<PresentationFeedback>, ContextError>
+    {
+        use super::xpresent::FrameFeedback;
+
+        if !self.present.activate(self.display.xlib(), self.display.display()) {
+            return Err(ContextError::FunctionUnavailable);
+        }
+        match self.present.feedback(self.display.xlib(), self.display.display(), frame_id) {
+            Some(FrameFeedback::Presented(feedback)) => Ok(Some(feedback)),
+            _ => Ok(None),
         }
     }
 
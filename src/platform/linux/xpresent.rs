@@ -0,0 +1,280 @@
+#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+
+use {PresentationFeedback, PresentationMethod, PresentationTime};
+
+use api::dlopen;
+use api::glx::ffi;
+
+use std::collections::VecDeque;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int, c_uint, c_ulong};
+use std::sync::Mutex;
+use std::time::Duration;
+use std::mem;
+
+/// Number of presented frames whose feedback is remembered.
+const KEPT_FRAMES: usize = 64;
+
+// From `presentproto.h`.
+const PRESENT_COMPLETE_NOTIFY: c_int = 1;
+const PRESENT_COMPLETE_NOTIFY_MASK: c_uint = 2;
+const PRESENT_COMPLETE_KIND_PIXMAP: u8 = 0;
+const PRESENT_COMPLETE_MODE_COPY: u8 = 0;
+const PRESENT_COMPLETE_MODE_FLIP: u8 = 1;
+const PRESENT_COMPLETE_MODE_SKIP: u8 = 2;
+
+/// `XPresentCompleteNotifyEvent` from `Xpresent.h`.
+#[repr(C)]
+struct CompleteNotifyEvent {
+    type_: c_int,
+    serial: c_ulong,
+    send_event: ffi::Bool,
+    display: *mut ffi::Display,
+    extension: c_int,
+    evtype: c_int,
+    eid: u32,
+    window: ffi::Window,
+    serial_number: u32,
+    ust: u64,
+    msc: u64,
+    kind: u8,
+    mode: u8,
+}
+
+/// The functions of libXpresent, loaded at runtime since it is not installed everywhere.
+struct XPresent {
+    query_extension: unsafe extern "C" fn(*mut ffi::Display, *mut c_int, *mut c_int,
+                                          *mut c_int) -> ffi::Bool,
+    select_input: unsafe extern "C" fn(*mut ffi::Display, ffi::Window, c_uint) -> ffi::XID,
+    free_input: unsafe extern "C" fn(*mut ffi::Display, ffi::Window, ffi::XID),
+}
+
+impl XPresent {
+    unsafe fn load() -> Option<XPresent> {
+        let lib = dlopen::dlopen(b"libXpresent.so.1\0".as_ptr() as *const _, dlopen::RTLD_NOW);
+        if lib.is_null() {
+            return None;
+        }
+
+        let sym = |name: &str| {
+            let name = CString::new(name).unwrap();
+            let sym = dlopen::dlsym(lib, name.as_ptr());
+            if sym.is_null() { None } else { Some(sym) }
+        };
+
+        Some(XPresent {
+            query_extension: mem::transmute(sym("XPresentQueryExtension")?),
+            select_input: mem::transmute(sym("XPresentSelectInput")?),
+            free_input: mem::transmute(sym("XPresentFreeInput")?),
+        })
+    }
+}
+
+enum State {
+    // The extension wasn't looked up yet.
+    Unknown,
+    Unavailable,
+    Active(Feedback),
+}
+
+struct Feedback {
+    xpresent: XPresent,
+    opcode: c_int,
+    event_id: ffi::XID,
+    // The number of swaps since the events were selected, which is the id of the last frame.
+    swaps: u64,
+    // The number of frames whose completion was received.
+    completed: u64,
+    // The feedback of the last completed frames. The last element is frame `completed`.
+    frames: VecDeque<PresentationFeedback>,
+}
+
+/// Tracks when the frames swapped on a window are presented through the `PresentCompleteNotify`
+/// events of the X Present extension.
+///
+/// The events are taken from the event queue of the display as they are needed. They go through
+/// the queue of the event loop of the window, which may discard them if it reads them first, in
+/// which case the frames are reported as pending.
+pub struct PresentTracker {
+    window: ffi::Window,
+    state: Mutex<State>,
+}
+
+impl PresentTracker {
+    pub fn new(window: ffi::Window) -> PresentTracker {
+        PresentTracker {
+            window: window,
+            state: Mutex::new(State::Unknown),
+        }
+    }
+
+    /// Returns the window whose presentations are tracked.
+    #[inline]
+    pub fn window(&self) -> ffi::Window {
+        self.window
+    }
+
+    /// Starts receiving the events of the window if it wasn't done yet. Returns whether the
+    /// Present extension is available.
+    pub fn activate(&self, xlib: &ffi::Xlib, display: *mut ffi::Display) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if let State::Unknown = *state {
+            *state = match unsafe { self.select_input(xlib, display) } {
+                Some(feedback) => State::Active(feedback),
+                None => State::Unavailable,
+            };
+        }
+        match *state {
+            State::Active(_) => true,
+            _ => false,
+        }
+    }
+
+    unsafe fn select_input(&self, xlib: &ffi::Xlib, display: *mut ffi::Display)
+                           -> Option<Feedback>
+    {
+        let xpresent = XPresent::load()?;
+        let (mut opcode, mut event_base, mut error_base) = (0, 0, 0);
+        if (xpresent.query_extension)(display, &mut opcode, &mut event_base,
+                                      &mut error_base) == 0
+        {
+            return None;
+        }
+
+        let event_id = (xpresent.select_input)(display, self.window,
+                                               PRESENT_COMPLETE_NOTIFY_MASK);
+        (xlib.XFlush)(display);
+        Some(Feedback {
+            xpresent: xpresent,
+            opcode: opcode,
+            event_id: event_id,
+            swaps: 0,
+            completed: 0,
+            frames: VecDeque::with_capacity(KEPT_FRAMES),
+        })
+    }
+
+    /// Records a swap of the window, returning the id of the frame. Returns `None` if the tracker
+    /// isn't active.
+    pub fn on_swap(&self) -> Option<u64> {
+        match *self.state.lock().unwrap() {
+            State::Active(ref mut feedback) => {
+                feedback.swaps += 1;
+                Some(feedback.swaps)
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns whether the last frame swapped on the window wasn't presented yet, or `None` if
+    /// the tracker isn't active.
+    pub fn is_pending(&self, xlib: &ffi::Xlib, display: *mut ffi::Display) -> Option<bool> {
+        match *self.state.lock().unwrap() {
+            State::Active(ref mut feedback) => {
+                unsafe { self.read_events(feedback, xlib, display); }
+                Some(feedback.completed < feedback.swaps)
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the feedback of `frame_id`, or `None` if the tracker isn't active.
+    pub fn feedback(&self, xlib: &ffi::Xlib, display: *mut ffi::Display, frame_id: u64)
+                    -> Option<FrameFeedback>
+    {
+        match *self.state.lock().unwrap() {
+            State::Active(ref mut feedback) => {
+                unsafe { self.read_events(feedback, xlib, display); }
+                if frame_id > feedback.completed {
+                    return Some(FrameFeedback::Pending);
+                }
+                let age = (feedback.completed - frame_id) as usize;
+                if age >= feedback.frames.len() {
+                    return Some(FrameFeedback::Unavailable);
+                }
+                let index = feedback.frames.len() - 1 - age;
+                Some(FrameFeedback::Presented(feedback.frames[index]))
+            },
+            _ => None,
+        }
+    }
+
+    /// Removes the completion events of the window from the event queue of the display.
+    unsafe fn read_events(&self, feedback: &mut Feedback, xlib: &ffi::Xlib,
+                          display: *mut ffi::Display)
+    {
+        let mut event: ffi::XEvent = mem::zeroed();
+        let arg = &feedback.opcode as *const c_int as *mut c_char;
+        while (xlib.XCheckIfEvent)(display, &mut event, Some(is_complete_notify), arg) != 0 {
+            let cookie = &mut *(&mut event as *mut ffi::XEvent as *mut ffi::XGenericEventCookie);
+            if (xlib.XGetEventData)(display, cookie) == 0 {
+                continue;
+            }
+
+            let notify = &*(cookie.data as *const CompleteNotifyEvent);
+            if notify.window == self.window && notify.eid as ffi::XID == feedback.event_id &&
+               notify.kind == PRESENT_COMPLETE_KIND_PIXMAP
+            {
+                let method = match notify.mode {
+                    PRESENT_COMPLETE_MODE_FLIP => PresentationMethod::Flip,
+                    PRESENT_COMPLETE_MODE_COPY => PresentationMethod::Copy,
+                    PRESENT_COMPLETE_MODE_SKIP => PresentationMethod::Skipped,
+                    // `PresentCompleteModeSuboptimalCopy`
+                    _ => PresentationMethod::Copy,
+                };
+                if feedback.frames.len() == KEPT_FRAMES {
+                    feedback.frames.pop_front();
+                }
+                feedback.frames.push_back(PresentationFeedback {
+                    time: Duration::from_micros(notify.ust),
+                    msc: notify.msc,
+                    method: method,
+                    refresh: None,
+                    flags: None,
+                });
+                feedback.completed += 1;
+            }
+
+            (xlib.XFreeEventData)(display, cookie);
+        }
+    }
+
+    /// Stops receiving the events of the window. Must be called before the display is closed.
+    pub fn release(&self, display: *mut ffi::Display) {
+        let mut state = self.state.lock().unwrap();
+        if let State::Active(ref feedback) = *state {
+            unsafe { (feedback.xpresent.free_input)(display, self.window, feedback.event_id); }
+        }
+        *state = State::Unavailable;
+    }
+}
+
+/// What is known about the presentation of a frame.
+pub enum FrameFeedback {
+    Pending,
+    Presented(PresentationFeedback),
+    Unavailable,
+}
+
+impl FrameFeedback {
+    pub fn presentation_time(&self) -> PresentationTime {
+        match *self {
+            FrameFeedback::Pending => PresentationTime::Pending,
+            FrameFeedback::Presented(ref feedback)
+                if feedback.method != PresentationMethod::Skipped =>
+            {
+                PresentationTime::Presented(feedback.time)
+            },
+            _ => PresentationTime::Unavailable,
+        }
+    }
+}
+
+unsafe extern "C" fn is_complete_notify(_: *mut ffi::Display, event: *mut ffi::XEvent,
+                                        arg: *mut c_char) -> ffi::Bool
+{
+    let cookie = &*(event as *const ffi::XGenericEventCookie);
+    let opcode = *(arg as *const c_int);
+    (cookie.type_ == ffi::GenericEvent && cookie.extension == opcode &&
+     cookie.evtype == PRESENT_COMPLETE_NOTIFY) as ffi::Bool
+}
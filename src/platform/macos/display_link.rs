@@ -0,0 +1,165 @@
+#![cfg(target_os = "macos")]
+
+use ContextError;
+
+use cgl::CGLContextObj;
+
+use std::os::raw::c_void;
+use std::sync::{Arc, Condvar, Mutex};
+
+type CVDisplayLinkRef = *mut c_void;
+type CVReturn = i32;
+type CGLPixelFormatObj = *mut c_void;
+
+#[repr(C)]
+struct CVSMPTETime {
+    subframes: i16,
+    subframe_divisor: i16,
+    counter: u32,
+    kind: u32,
+    flags: u32,
+    hours: i16,
+    minutes: i16,
+    seconds: i16,
+    frames: i16,
+}
+
+#[repr(C)]
+struct CVTimeStamp {
+    version: u32,
+    video_time_scale: i32,
+    video_time: i64,
+    host_time: u64,
+    rate_scalar: f64,
+    video_refresh_period: i64,
+    smpte_time: CVSMPTETime,
+    flags: u64,
+    reserved: u64,
+}
+
+type CVDisplayLinkOutputCallback = extern "C" fn(CVDisplayLinkRef, *const CVTimeStamp,
+                                                 *const CVTimeStamp, u64, *mut u64,
+                                                 *mut c_void) -> CVReturn;
+
+#[link(name = "CoreVideo", kind = "framework")]
+extern "C" {
+    fn CVDisplayLinkCreateWithActiveCGDisplays(link: *mut CVDisplayLinkRef) -> CVReturn;
+    fn CVDisplayLinkSetOutputCallback(link: CVDisplayLinkRef,
+                                      callback: CVDisplayLinkOutputCallback,
+                                      user_info: *mut c_void) -> CVReturn;
+    fn CVDisplayLinkSetCurrentCGDisplayFromOpenGLContext(link: CVDisplayLinkRef,
+                                                         context: CGLContextObj,
+                                                         pixel_format: CGLPixelFormatObj)
+                                                         -> CVReturn;
+    fn CVDisplayLinkStart(link: CVDisplayLinkRef) -> CVReturn;
+    fn CVDisplayLinkStop(link: CVDisplayLinkRef) -> CVReturn;
+    fn CVDisplayLinkRelease(link: CVDisplayLinkRef);
+}
+
+// The OpenGL framework is already linked by the `cgl` crate.
+extern "C" {
+    fn CGLGetPixelFormat(context: CGLContextObj) -> CGLPixelFormatObj;
+}
+
+/// State shared with the display link thread.
+struct Shared {
+    state: Mutex<State>,
+    condvar: Condvar,
+}
+
+struct State {
+    // Incremented on every vertical blank.
+    frame: u64,
+    callback: Option<Box<FnMut() + Send>>,
+}
+
+/// A `CVDisplayLink`, which runs a thread that wakes up on every vertical blank of a display.
+pub struct DisplayLink {
+    link: CVDisplayLinkRef,
+    shared: Arc<Shared>,
+}
+
+impl DisplayLink {
+    /// Creates and starts a display link following the display that `context` is on.
+    pub fn new(context: CGLContextObj) -> Result<DisplayLink, ContextError> {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State { frame: 0, callback: None }),
+            condvar: Condvar::new(),
+        });
+
+        let mut link = ::std::ptr::null_mut();
+        unsafe {
+            check(CVDisplayLinkCreateWithActiveCGDisplays(&mut link),
+                  "CVDisplayLinkCreateWithActiveCGDisplays")?;
+        }
+        // the link is released when dropped from now on
+        let display_link = DisplayLink { link: link, shared: shared };
+
+        unsafe {
+            let user_info = &*display_link.shared as *const Shared as *mut c_void;
+            check(CVDisplayLinkSetOutputCallback(link, output_callback, user_info),
+                  "CVDisplayLinkSetOutputCallback")?;
+            display_link.set_display(context)?;
+            check(CVDisplayLinkStart(link), "CVDisplayLinkStart")?;
+        }
+
+        Ok(display_link)
+    }
+
+    /// Makes the display link follow the display that `context` is currently on, which changes
+    /// when its window is moved to another screen.
+    pub fn set_display(&self, context: CGLContextObj) -> Result<(), ContextError> {
+        unsafe {
+            let pixel_format = CGLGetPixelFormat(context);
+            check(CVDisplayLinkSetCurrentCGDisplayFromOpenGLContext(self.link, context,
+                                                                    pixel_format),
+                  "CVDisplayLinkSetCurrentCGDisplayFromOpenGLContext")
+        }
+    }
+
+    /// Blocks until the next vertical blank.
+    pub fn wait(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+        let frame = state.frame;
+        while state.frame == frame {
+            state = self.shared.condvar.wait(state).unwrap();
+        }
+    }
+
+    /// Sets the function called on the display link thread on every vertical blank.
+    pub fn set_callback(&self, callback: Option<Box<FnMut() + Send>>) {
+        self.shared.state.lock().unwrap().callback = callback;
+    }
+}
+
+impl Drop for DisplayLink {
+    fn drop(&mut self) {
+        // `CVDisplayLinkStop` waits for the callback to return, after which `shared` isn't used
+        // by the display link thread anymore
+        unsafe {
+            CVDisplayLinkStop(self.link);
+            CVDisplayLinkRelease(self.link);
+        }
+    }
+}
+
+extern "C" fn output_callback(_: CVDisplayLinkRef, _: *const CVTimeStamp, _: *const CVTimeStamp,
+                              _: u64, _: *mut u64, user_info: *mut c_void) -> CVReturn
+{
+    let shared = unsafe { &*(user_info as *const Shared) };
+    let mut state = shared.state.lock().unwrap();
+    state.frame = state.frame.wrapping_add(1);
+    if let Some(ref mut callback) = state.callback {
+        callback();
+    }
+    shared.condvar.notify_all();
+    0
+}
+
+fn check(ret: CVReturn, function: &str) -> Result<(), ContextError> {
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(ContextError::OsError(format!("{} failed (returned {})", function, ret)))
+    }
+}
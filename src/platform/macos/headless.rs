@@ -1,60 +1,247 @@
+use Colorspace;
+use ConfigCaveat;
 use ContextError;
 use CreationError;
 use CreationError::OsError;
 use GlAttributes;
 use PixelFormat;
+use channel_bits;
 use PixelFormatRequirements;
 use super::helpers;
 
+use cgl::{CGLChoosePixelFormat, CGLContextObj, CGLCreateContext, CGLDescribePixelFormat};
+use cgl::{CGLDestroyContext, CGLDestroyPixelFormat, CGLFlushDrawable, CGLGetCurrentContext};
+use cgl::{CGLPixelFormatObj, CGLSetCurrentContext};
 use core_foundation::base::TCFType;
 use core_foundation::string::CFString;
 use core_foundation::bundle::{CFBundleGetBundleWithIdentifier, CFBundleGetFunctionPointerForName};
-use cocoa::base::{id, nil};
-use cocoa::appkit::*;
-use std::os::raw::c_void;
+use cocoa::appkit;
+use std::os::raw::{c_int, c_uint, c_void};
+use std::{mem, ptr};
+
+// From `gl.h`, for the framebuffer object that headless contexts render to.
+const GL_FRAMEBUFFER: c_uint = 0x8D40;
+const GL_RENDERBUFFER: c_uint = 0x8D41;
+const GL_COLOR_ATTACHMENT0: c_uint = 0x8CE0;
+const GL_DEPTH_STENCIL_ATTACHMENT: c_uint = 0x821A;
+const GL_FRAMEBUFFER_COMPLETE: c_uint = 0x8CD5;
+const GL_RGBA8: c_uint = 0x8058;
+const GL_RGBA32F: c_uint = 0x8814;
+const GL_DEPTH24_STENCIL8: c_uint = 0x88F0;
 
 #[derive(Clone, Default)]
 pub struct PlatformSpecificHeadlessBuilderAttributes;
 
+/// A CGL context without any drawable.
+///
+/// CGL contexts only have a default framebuffer when they are attached to a view, so contexts
+/// built with `new` render to a framebuffer object of the requested dimensions instead, which is
+/// bound every time the context is made current.
 pub struct HeadlessContext {
-    context: id,
+    context: CGLContextObj,
+    pixel_format: PixelFormat,
+    framebuffer: Option<Framebuffer>,
+}
+
+/// The framebuffer object and the renderbuffers attached to it.
+struct Framebuffer {
+    framebuffer: c_uint,
+    renderbuffers: [c_uint; 2],
 }
 
 impl HeadlessContext {
-    pub fn new((_width, _height): (u32, u32), pf_reqs: &PixelFormatRequirements,
+    pub fn new(dimensions: (u32, u32), pf_reqs: &PixelFormatRequirements,
                opengl: &GlAttributes<&HeadlessContext>,
-               _: &PlatformSpecificHeadlessBuilderAttributes)
+               attributes: &PlatformSpecificHeadlessBuilderAttributes)
                -> Result<HeadlessContext, CreationError>
     {
+        let mut headless = HeadlessContext::new_surfaceless(pf_reqs, opengl, attributes)?;
+        let framebuffer = unsafe { headless.create_framebuffer(dimensions, pf_reqs)? };
+        headless.framebuffer = Some(framebuffer);
+        Ok(headless)
+    }
+
+    /// Builds a context that doesn't render anywhere until the user binds a framebuffer object.
+    pub fn new_surfaceless(pf_reqs: &PixelFormatRequirements,
+                           opengl: &GlAttributes<&HeadlessContext>,
+                           _: &PlatformSpecificHeadlessBuilderAttributes)
+                           -> Result<HeadlessContext, CreationError>
+    {
+        if opengl.protected_content {
+            return Err(CreationError::ProtectedContentNotSupported);
+        }
+        if opengl.no_error {
+            return Err(CreationError::NoErrorNotSupported);
+        }
+
         let gl_profile = helpers::get_gl_profile(opengl)?;
         let attributes = helpers::build_nsattributes(pf_reqs, gl_profile)?;
-        let context = unsafe {
-            let pixelformat = NSOpenGLPixelFormat::alloc(nil).initWithAttributes_(&attributes);
-            if pixelformat == nil {
-                return Err(OsError(format!("Could not create the pixel format")));
+        let attributes: Vec<_> = attributes.into_iter().map(|attrib| attrib as _).collect();
+
+        unsafe {
+            let mut pixel_format_obj: CGLPixelFormatObj = ptr::null_mut();
+            let mut num_formats = 0;
+            if CGLChoosePixelFormat(attributes.as_ptr(), &mut pixel_format_obj,
+                                    &mut num_formats) != 0 || pixel_format_obj.is_null()
+            {
+                return Err(CreationError::NoAvailablePixelFormat);
             }
-            let context = NSOpenGLContext::alloc(nil).initWithFormat_shareContext_(pixelformat, nil);
-            if context == nil {
-                return Err(OsError(format!("Could not create the rendering context")));
+
+            let get_attr = |attrib: appkit::NSOpenGLPixelFormatAttribute| -> i32 {
+                let mut value = 0;
+                CGLDescribePixelFormat(pixel_format_obj, 0, attrib as _, &mut value);
+                value as i32
+            };
+            // CGL only describes the size of the whole color buffer
+            let color_bits = (get_attr(appkit::NSOpenGLPFAColorSize) -
+                              get_attr(appkit::NSOpenGLPFAAlphaSize)) as u8;
+            let (red_bits, green_bits, blue_bits) = channel_bits(color_bits);
+            let pixel_format = PixelFormat {
+                hardware_accelerated: get_attr(appkit::NSOpenGLPFAAccelerated) != 0,
+                color_bits: color_bits,
+                red_bits: red_bits,
+                green_bits: green_bits,
+                blue_bits: blue_bits,
+                alpha_bits: get_attr(appkit::NSOpenGLPFAAlphaSize) as u8,
+                depth_bits: get_attr(appkit::NSOpenGLPFADepthSize) as u8,
+                stencil_bits: get_attr(appkit::NSOpenGLPFAStencilSize) as u8,
+                stereoscopy: get_attr(appkit::NSOpenGLPFAStereo) != 0,
+                double_buffer: get_attr(appkit::NSOpenGLPFADoubleBuffer) != 0,
+                multisampling: if get_attr(appkit::NSOpenGLPFAMultisample) > 0 {
+                    Some(get_attr(appkit::NSOpenGLPFASamples) as u16)
+                } else {
+                    None
+                },
+                srgb: true,
+                native_id: None,
+                caveat: if get_attr(appkit::NSOpenGLPFAAccelerated) != 0 {
+                    ConfigCaveat::None
+                } else {
+                    ConfigCaveat::Slow
+                },
+                colorspace: Colorspace::Srgb,
+                float_color_buffer: get_attr(appkit::NSOpenGLPFAColorFloat) != 0,
+                transparent: get_attr(appkit::NSOpenGLPFAAlphaSize) > 0,
+            };
+
+            let share = opengl.sharing.map_or(ptr::null_mut(), |ctxt| ctxt.context);
+            let mut context: CGLContextObj = ptr::null_mut();
+            let err = CGLCreateContext(pixel_format_obj, share, &mut context);
+            CGLDestroyPixelFormat(pixel_format_obj);
+            if err != 0 || context.is_null() {
+                return Err(OsError(format!("CGLCreateContext failed with error {}", err)));
             }
-            context
-        };
 
-        let headless = HeadlessContext {
-            context,
-        };
+            Ok(HeadlessContext {
+                context: context,
+                pixel_format: pixel_format,
+                framebuffer: None,
+            })
+        }
+    }
 
-        Ok(headless)
+    pub fn new_shared(_: (u32, u32), _: bool, _: &GlAttributes<&super::Context>)
+                      -> Result<HeadlessContext, CreationError>
+    {
+        Err(CreationError::NotSupported("sharing lists between a headless and a windowed context"))
+    }
+
+    /// Creates the framebuffer object that the context renders to, with a color renderbuffer and
+    /// a depth and stencil renderbuffer of `dimensions`.
+    unsafe fn create_framebuffer(&self, dimensions: (u32, u32), pf_reqs: &PixelFormatRequirements)
+                                 -> Result<Framebuffer, CreationError>
+    {
+        let previous = CGLGetCurrentContext();
+        if CGLSetCurrentContext(self.context) != 0 {
+            return Err(OsError(format!("CGLSetCurrentContext failed")));
+        }
+
+        let gen_framebuffers: extern "C" fn(c_int, *mut c_uint) =
+            mem::transmute(self.get_proc_address("glGenFramebuffers"));
+        let bind_framebuffer: extern "C" fn(c_uint, c_uint) =
+            mem::transmute(self.get_proc_address("glBindFramebuffer"));
+        let gen_renderbuffers: extern "C" fn(c_int, *mut c_uint) =
+            mem::transmute(self.get_proc_address("glGenRenderbuffers"));
+        let bind_renderbuffer: extern "C" fn(c_uint, c_uint) =
+            mem::transmute(self.get_proc_address("glBindRenderbuffer"));
+        let renderbuffer_storage_multisample: extern "C" fn(c_uint, c_int, c_uint, c_int, c_int) =
+            mem::transmute(self.get_proc_address("glRenderbufferStorageMultisample"));
+        let framebuffer_renderbuffer: extern "C" fn(c_uint, c_uint, c_uint, c_uint) =
+            mem::transmute(self.get_proc_address("glFramebufferRenderbuffer"));
+        let check_framebuffer_status: extern "C" fn(c_uint) -> c_uint =
+            mem::transmute(self.get_proc_address("glCheckFramebufferStatus"));
+
+        let color_format = if pf_reqs.float_color_buffer { GL_RGBA32F } else { GL_RGBA8 };
+        let samples = pf_reqs.multisampling.unwrap_or(0) as c_int;
+
+        let mut framebuffer = Framebuffer { framebuffer: 0, renderbuffers: [0; 2] };
+        gen_framebuffers(1, &mut framebuffer.framebuffer);
+        gen_renderbuffers(2, framebuffer.renderbuffers.as_mut_ptr());
+        bind_framebuffer(GL_FRAMEBUFFER, framebuffer.framebuffer);
+
+        let attachments = [
+            (GL_COLOR_ATTACHMENT0, color_format),
+            (GL_DEPTH_STENCIL_ATTACHMENT, GL_DEPTH24_STENCIL8),
+        ];
+        for (&renderbuffer, &(attachment, format)) in framebuffer.renderbuffers.iter()
+                                                                 .zip(attachments.iter())
+        {
+            bind_renderbuffer(GL_RENDERBUFFER, renderbuffer);
+            renderbuffer_storage_multisample(GL_RENDERBUFFER, samples, format,
+                                             dimensions.0 as c_int, dimensions.1 as c_int);
+            framebuffer_renderbuffer(GL_FRAMEBUFFER, attachment, GL_RENDERBUFFER, renderbuffer);
+        }
+        let status = check_framebuffer_status(GL_FRAMEBUFFER);
+
+        CGLSetCurrentContext(previous);
+
+        if status != GL_FRAMEBUFFER_COMPLETE {
+            self.delete_framebuffer(&framebuffer);
+            return Err(OsError(format!("the framebuffer object is incomplete: 0x{:x}", status)));
+        }
+        Ok(framebuffer)
+    }
+
+    /// Deletes `framebuffer` and its renderbuffers, making the context current in the meantime.
+    unsafe fn delete_framebuffer(&self, framebuffer: &Framebuffer) {
+        let previous = CGLGetCurrentContext();
+        if CGLSetCurrentContext(self.context) != 0 {
+            return;
+        }
+
+        let delete_framebuffers: extern "C" fn(c_int, *const c_uint) =
+            mem::transmute(self.get_proc_address("glDeleteFramebuffers"));
+        let delete_renderbuffers: extern "C" fn(c_int, *const c_uint) =
+            mem::transmute(self.get_proc_address("glDeleteRenderbuffers"));
+        delete_framebuffers(1, &framebuffer.framebuffer);
+        delete_renderbuffers(2, framebuffer.renderbuffers.as_ptr());
+
+        CGLSetCurrentContext(previous);
     }
 
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
-        self.context.makeCurrentContext();
+        if CGLSetCurrentContext(self.context) != 0 {
+            return Err(ContextError::OsError(format!("CGLSetCurrentContext failed")));
+        }
+
+        if let Some(ref framebuffer) = self.framebuffer {
+            let bind_framebuffer: extern "C" fn(c_uint, c_uint) =
+                mem::transmute(self.get_proc_address("glBindFramebuffer"));
+            bind_framebuffer(GL_FRAMEBUFFER, framebuffer.framebuffer);
+        }
+        Ok(())
+    }
+
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        if self.is_current() && CGLSetCurrentContext(ptr::null_mut()) != 0 {
+            return Err(ContextError::OsError(format!("CGLSetCurrentContext failed")));
+        }
         Ok(())
     }
 
     #[inline]
     pub fn is_current(&self) -> bool {
-        unsafe { id::currentContext(self.context) == self.context }
+        unsafe { CGLGetCurrentContext() == self.context }
     }
 
     #[inline]
@@ -70,9 +257,10 @@ impl HeadlessContext {
         symbol as *const ()
     }
 
+    /// Without a drawable, flushing the context only submits the pending commands.
     #[inline]
     pub fn swap_buffers(&self) -> Result<(), ContextError> {
-        unsafe { self.context.flushBuffer(); }
+        unsafe { CGLFlushDrawable(self.context); }
         Ok(())
     }
 
@@ -83,14 +271,33 @@ impl HeadlessContext {
 
     #[inline]
     pub fn get_pixel_format(&self) -> PixelFormat {
-        unimplemented!();
+        self.pixel_format.clone()
+    }
+
+    /// Returns the name of the framebuffer object the context renders to, or `None` if it was
+    /// built with `new_surfaceless`.
+    #[inline]
+    pub fn framebuffer(&self) -> Option<u32> {
+        self.framebuffer.as_ref().map(|framebuffer| framebuffer.framebuffer as u32)
     }
 
+    /// Returns the `CGLContextObj` of the context.
     #[inline]
     pub unsafe fn raw_handle(&self) -> *mut c_void {
         self.context as *mut _
     }
 }
 
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(framebuffer) = self.framebuffer.take() {
+                self.delete_framebuffer(&framebuffer);
+            }
+            CGLDestroyContext(self.context);
+        }
+    }
+}
+
 unsafe impl Send for HeadlessContext {}
 unsafe impl Sync for HeadlessContext {}
@@ -4,10 +4,12 @@ use GlAttributes;
 use GlProfile;
 use GlRequest;
 use PixelFormatRequirements;
-use ReleaseBehavior;
 use cocoa::appkit::*;
 use cocoa::base::nil;
 
+// From `CGLRenderers.h`, the Apple software renderer.
+const K_CGL_RENDERER_GENERIC_FLOAT_ID: u32 = 0x00020400;
+
 pub fn get_gl_profile<T>(
     opengl: &GlAttributes<&T>
 ) -> Result<NSOpenGLPFAOpenGLProfiles, CreationError> {
@@ -69,7 +71,7 @@ pub fn build_nsattributes(
     // `NSOpenGLPFAColorSize` also includes `NSOpenGLPFAAlphaSize`,
     // so we have to account for that as well.
     let alpha_depth = pf_reqs.alpha_bits.unwrap_or(8);
-    let color_depth = pf_reqs.color_bits.unwrap_or(24) + alpha_depth;
+    let color_depth = pf_reqs.min_color_bits().unwrap_or(24) + alpha_depth;
 
     let mut attributes = vec![
         NSOpenGLPFAOpenGLProfile as u32, profile as u32,
@@ -81,7 +83,10 @@ pub fn build_nsattributes(
         NSOpenGLPFAAllowOfflineRenderers as u32,
     ];
 
-    if let Some(true) = pf_reqs.hardware_accelerated {
+    if pf_reqs.software_rendering || pf_reqs.hardware_accelerated.required() == Some(false) {
+        attributes.push(NSOpenGLPFARendererID as u32);
+        attributes.push(K_CGL_RENDERER_GENERIC_FLOAT_ID);
+    } else if let Some(true) = pf_reqs.hardware_accelerated.required() {
         attributes.push(NSOpenGLPFAAccelerated as u32);
     }
 
@@ -92,10 +97,6 @@ pub fn build_nsattributes(
         attributes.push(NSOpenGLPFADoubleBuffer as u32);
     }
 
-    if pf_reqs.release_behavior != ReleaseBehavior::Flush {
-        return Err(CreationError::NoAvailablePixelFormat);
-    }
-
     if pf_reqs.stereoscopy {
         unimplemented!();   // TODO:
     }
@@ -110,6 +111,11 @@ pub fn build_nsattributes(
         attributes.push(NSOpenGLPFASamples as u32); attributes.push(samples as u32);
     }
 
+    for &(attrib, value) in &pf_reqs.raw_attributes {
+        attributes.push(attrib as u32);
+        attributes.push(value as u32);
+    }
+
     // attribute list must be null terminated.
     attributes.push(0);
 
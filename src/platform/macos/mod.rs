@@ -3,16 +3,36 @@
 pub use self::headless::HeadlessContext;
 pub use self::headless::PlatformSpecificHeadlessBuilderAttributes;
 
+#[cfg(feature = "winit")]
 pub use winit::MonitorId;
 
 use CreationError;
 use ContextError;
+use Api;
+use DeviceInfo;
+use SupportedVersions;
 use GlAttributes;
+use PbufferAttributes;
+use PbufferLimits;
 use PixelFormat;
+use channel_bits;
 use PixelFormatRequirements;
+use RawDisplayHandle;
+use RawPixmapHandle;
+use RawWindowHandle;
+use Rect;
 use Robustness;
+use PresentMode;
+use MultisampleResolve;
+use PresentationTime;
+use RenderBuffer;
+use SwapBehavior;
+use Colorspace;
+use ConfigCaveat;
+use SwapInterval;
 
 use cgl::{CGLEnable, kCGLCECrashOnRemovedFunctions, CGLSetParameter, kCGLCPSurfaceOpacity};
+use cgl::{CGLContextObj, CGLGetCurrentContext, CGLSetCurrentContext};
 use cocoa::base::{id, nil};
 use cocoa::foundation::NSAutoreleasePool;
 use cocoa::appkit::{self, NSOpenGLContext, NSOpenGLPixelFormat};
@@ -20,23 +40,152 @@ use core_foundation::base::TCFType;
 use core_foundation::string::CFString;
 use core_foundation::bundle::{CFBundleGetBundleWithIdentifier, CFBundleGetFunctionPointerForName};
 use objc::runtime::{BOOL, NO};
+#[cfg(feature = "winit")]
 use winit;
+#[cfg(feature = "winit")]
 use winit::os::macos::WindowExt;
 
 use std::str::FromStr;
+use std::sync::Mutex;
 use std::ops::Deref;
 use std::os::raw::c_void;
 
+mod display_link;
 mod headless;
 mod helpers;
 
+use self::display_link::DisplayLink;
+
+/// A display connection provided by the user. AppKit doesn't need one, so it holds no state.
+pub struct Display;
+
+impl Display {
+    pub unsafe fn from_raw(display_handle: RawDisplayHandle) -> Result<Display, CreationError> {
+        match display_handle {
+            RawDisplayHandle::AppKit => Ok(Display),
+            _ => Err(CreationError::NotSupported("the display handle is not an AppKit handle")),
+        }
+    }
+
+    pub fn find_configs(&self, _: &PixelFormatRequirements)
+                        -> Result<Vec<Config>, CreationError>
+    {
+        Err(CreationError::NotSupported("config enumeration is not supported on this platform"))
+    }
+
+    pub fn device_info(&self) -> Result<DeviceInfo, CreationError> {
+        Err(CreationError::NotSupported("device information is not available on this platform"))
+    }
+
+    pub fn probe_versions(&self, _: Api) -> Result<SupportedVersions, CreationError> {
+        Err(CreationError::NotSupported("version probing is not supported on this platform"))
+    }
+}
+
+/// Config enumeration is not supported on this platform, so this type can't be constructed.
+#[derive(Clone)]
+pub enum Config {}
+
+impl Config {
+    #[inline]
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        match *self {}
+    }
+
+    pub unsafe fn create_window_surface(&self, _: RawWindowHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        match *self {}
+    }
+
+    pub fn create_pbuffer_surface(&self, _: &PbufferAttributes) -> Result<Surface, CreationError> {
+        match *self {}
+    }
+
+    pub unsafe fn create_pixmap_surface(&self, _: RawPixmapHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        match *self {}
+    }
+}
+
+/// Surfaces are not supported on this platform, so this type can't be constructed.
+pub enum Surface {}
+
+impl Surface {
+    #[inline]
+    pub fn dimensions(&self) -> Result<(u32, u32), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn buffer_age(&self) -> Result<u32, ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn set_damage_region(&self, _rects: &[Rect]) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn set_swap_behavior(&self, _behavior: SwapBehavior) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn set_render_buffer(&self, _render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn set_multisample_resolve(&self, _resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn resize(&self, _: u32, _: u32) {
+        match *self {}
+    }
+}
+
 pub struct Context {
     // NSOpenGLContext
     gl: IdRef,
     pixel_format: PixelFormat,
+    // Created the first time vsync callbacks are used.
+    display_link: Mutex<Option<DisplayLink>>,
 }
 
 impl Context {
+    #[cfg(feature = "winit")]
     pub fn new(
         window_builder: winit::WindowBuilder,
         events_loop: &winit::EventsLoop,
@@ -46,9 +195,94 @@ impl Context {
     {
         let transparent = window_builder.window.transparent;
         let window = window_builder.build(events_loop)?;
+        let view = window.get_nsview() as id;
+        let context = unsafe { Context::new_on_view(view, transparent, pf_reqs, gl_attr)? };
+        Ok((window, context))
+    }
+
+    /// Builds a context on a window that was created by another library.
+    #[inline]
+    pub unsafe fn new_from_raw_handles(
+        display_handle: RawDisplayHandle,
+        window_handle: RawWindowHandle,
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        match (display_handle, window_handle) {
+            (RawDisplayHandle::AppKit, RawWindowHandle::AppKit { ns_view }) => {
+                Context::new_on_view(ns_view as id, false, pf_reqs, gl_attr)
+            },
+            _ => Err(CreationError::NotSupported("raw handles are not AppKit handles")),
+        }
+    }
+
+    /// Builds a context on a window that was created by another library, using a display
+    /// provided by the user.
+    #[inline]
+    pub unsafe fn new_with_display(
+        _: &Display,
+        window_handle: RawWindowHandle,
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        Context::new_from_raw_handles(RawDisplayHandle::AppKit, window_handle, pf_reqs, gl_attr)
+    }
+
+    #[inline]
+    pub unsafe fn new_with_config(
+        config: &Config,
+        _: RawWindowHandle,
+        _: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        match *config {}
+    }
+
+    /// Destroys this context and builds a new one drawing to the same window, which isn't
+    /// supported on this platform.
+    #[inline]
+    pub unsafe fn recreate(self, _: &GlAttributes<&Self>) -> Result<Self, CreationError> {
+        Err(CreationError::NotSupported("contexts can't be recreated on this platform"))
+    }
+
+    pub unsafe fn create_window_surface(&self, _: RawWindowHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        Err(CreationError::NotSupported("surfaces are not supported on this platform"))
+    }
+
+    pub fn create_pbuffer_surface(&self, _: &PbufferAttributes) -> Result<Surface, CreationError> {
+        Err(CreationError::NotSupported("surfaces are not supported on this platform"))
+    }
+
+    pub unsafe fn create_pixmap_surface(&self, _: RawPixmapHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        Err(CreationError::NotSupported("surfaces are not supported on this platform"))
+    }
+
+    #[inline]
+    pub unsafe fn make_current_surfaces(&self, draw: &Surface, _: &Surface)
+                                        -> Result<(), ContextError>
+    {
+        match *draw {}
+    }
 
+    unsafe fn new_on_view(
+        view: id,
+        transparent: bool,
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
         if gl_attr.sharing.is_some() {
-            unimplemented!()
+            return Err(CreationError::NotSupported("sharing lists is not supported on macOS"));
+        }
+        if gl_attr.raw_sharing.is_some() {
+            return Err(CreationError::NotSupported("sharing with a raw context is not supported \
+                                                    on macOS"));
         }
 
         match gl_attr.robustness {
@@ -59,74 +293,122 @@ impl Context {
             _ => (),
         }
 
-        let view = window.get_nsview() as id;
+        if gl_attr.protected_content {
+            return Err(CreationError::ProtectedContentNotSupported);
+        }
+        if gl_attr.no_error {
+            return Err(CreationError::NoErrorNotSupported);
+        }
 
         let gl_profile = helpers::get_gl_profile(gl_attr)?;
         let attributes = helpers::build_nsattributes(pf_reqs, gl_profile)?;
-        unsafe {
-            let pixel_format = IdRef::new(NSOpenGLPixelFormat::alloc(nil)
-                .initWithAttributes_(&attributes));
-            let pixel_format = match pixel_format.non_nil() {
-                None => return Err(CreationError::NoAvailablePixelFormat),
-                Some(pf) => pf,
-            };
+        let pixel_format = IdRef::new(NSOpenGLPixelFormat::alloc(nil)
+            .initWithAttributes_(&attributes));
+        let pixel_format = match pixel_format.non_nil() {
+            None => return Err(CreationError::NoAvailablePixelFormat),
+            Some(pf) => pf,
+        };
 
-            // TODO: Add context sharing
-            let gl_context = IdRef::new(NSOpenGLContext::alloc(nil)
-                .initWithFormat_shareContext_(*pixel_format, nil));
-            let gl_context = match gl_context.non_nil() {
-                Some(gl_context) => gl_context,
-                None => return Err(CreationError::NotSupported("could not open gl context")),
-            };
+        // TODO: Add context sharing
+        let gl_context = IdRef::new(NSOpenGLContext::alloc(nil)
+            .initWithFormat_shareContext_(*pixel_format, nil));
+        let gl_context = match gl_context.non_nil() {
+            Some(gl_context) => gl_context,
+            None => return Err(CreationError::NotSupported("could not open gl context")),
+        };
 
-            let pixel_format = {
-                let get_attr = |attrib: appkit::NSOpenGLPixelFormatAttribute| -> i32 {
-                    let mut value = 0;
-                    NSOpenGLPixelFormat::getValues_forAttribute_forVirtualScreen_(
-                        *pixel_format,
-                        &mut value,
-                        attrib,
-                        NSOpenGLContext::currentVirtualScreen(*gl_context));
-                    value
-                };
-
-                PixelFormat {
-                    hardware_accelerated: get_attr(appkit::NSOpenGLPFAAccelerated) != 0,
-                    color_bits: (get_attr(appkit::NSOpenGLPFAColorSize) - get_attr(appkit::NSOpenGLPFAAlphaSize)) as u8,
-                    alpha_bits: get_attr(appkit::NSOpenGLPFAAlphaSize) as u8,
-                    depth_bits: get_attr(appkit::NSOpenGLPFADepthSize) as u8,
-                    stencil_bits: get_attr(appkit::NSOpenGLPFAStencilSize) as u8,
-                    stereoscopy: get_attr(appkit::NSOpenGLPFAStereo) != 0,
-                    double_buffer: get_attr(appkit::NSOpenGLPFADoubleBuffer) != 0,
-                    multisampling: if get_attr(appkit::NSOpenGLPFAMultisample) > 0 {
-                        Some(get_attr(appkit::NSOpenGLPFASamples) as u16)
-                    } else {
-                        None
-                    },
-                    srgb: true,
-                }
+        let pixel_format = {
+            let get_attr = |attrib: appkit::NSOpenGLPixelFormatAttribute| -> i32 {
+                let mut value = 0;
+                NSOpenGLPixelFormat::getValues_forAttribute_forVirtualScreen_(
+                    *pixel_format,
+                    &mut value,
+                    attrib,
+                    NSOpenGLContext::currentVirtualScreen(*gl_context));
+                value
             };
-
-            gl_context.setView_(view);
-            let value = if gl_attr.vsync { 1 } else { 0 };
-            gl_context.setValues_forParameter_(&value, appkit::NSOpenGLContextParameter::NSOpenGLCPSwapInterval);
-
-            if transparent {
-                let mut opacity = 0;
-                CGLSetParameter(gl_context.CGLContextObj() as *mut _, kCGLCPSurfaceOpacity, &mut opacity);
+            // AppKit only describes the size of the whole color buffer
+            let color_bits = (get_attr(appkit::NSOpenGLPFAColorSize) -
+                              get_attr(appkit::NSOpenGLPFAAlphaSize)) as u8;
+            let (red_bits, green_bits, blue_bits) = channel_bits(color_bits);
+
+            PixelFormat {
+                hardware_accelerated: get_attr(appkit::NSOpenGLPFAAccelerated) != 0,
+                color_bits: color_bits,
+                red_bits: red_bits,
+                green_bits: green_bits,
+                blue_bits: blue_bits,
+                alpha_bits: get_attr(appkit::NSOpenGLPFAAlphaSize) as u8,
+                depth_bits: get_attr(appkit::NSOpenGLPFADepthSize) as u8,
+                stencil_bits: get_attr(appkit::NSOpenGLPFAStencilSize) as u8,
+                stereoscopy: get_attr(appkit::NSOpenGLPFAStereo) != 0,
+                double_buffer: get_attr(appkit::NSOpenGLPFADoubleBuffer) != 0,
+                multisampling: if get_attr(appkit::NSOpenGLPFAMultisample) > 0 {
+                    Some(get_attr(appkit::NSOpenGLPFASamples) as u16)
+                } else {
+                    None
+                },
+                srgb: true,
+                native_id: None,
+                caveat: if get_attr(appkit::NSOpenGLPFAAccelerated) != 0 {
+                    ConfigCaveat::None
+                } else {
+                    ConfigCaveat::Slow
+                },
+                colorspace: Colorspace::Srgb,
+                float_color_buffer: get_attr(appkit::NSOpenGLPFAColorFloat) != 0,
+                transparent: get_attr(appkit::NSOpenGLPFAAlphaSize) > 0,
             }
+        };
 
-            CGLEnable(gl_context.CGLContextObj() as *mut _, kCGLCECrashOnRemovedFunctions);
+        gl_context.setView_(view);
+        let value = if gl_attr.vsync { 1 } else { 0 };
+        gl_context.setValues_forParameter_(&value, appkit::NSOpenGLContextParameter::NSOpenGLCPSwapInterval);
 
-            let context = Context { gl: gl_context, pixel_format: pixel_format };
-            Ok((window, context))
+        if transparent {
+            let mut opacity = 0;
+            CGLSetParameter(gl_context.CGLContextObj() as *mut _, kCGLCPSurfaceOpacity, &mut opacity);
         }
+
+        CGLEnable(gl_context.CGLContextObj() as *mut _, kCGLCECrashOnRemovedFunctions);
+
+        Ok(Context {
+            gl: gl_context,
+            pixel_format: pixel_format,
+            display_link: Mutex::new(None),
+        })
     }
 
     pub fn resize(&self, _width: u32, _height: u32) {
         unsafe { self.gl.update(); }
     }
 
+    /// Blocks until the next vertical blank of the display the context is on.
+    pub fn wait_for_vsync(&self) -> Result<(), ContextError> {
+        self.with_display_link(|display_link| display_link.wait())
+    }
+
+    /// Sets the function called on every vertical blank of the display the context is on.
+    pub fn set_vsync_callback(&self, callback: Option<Box<FnMut() + Send>>)
+                              -> Result<(), ContextError>
+    {
+        self.with_display_link(|display_link| display_link.set_callback(callback))
+    }
+
+    // Creates the display link if needed and makes it follow the display the context is on.
+    fn with_display_link<F, R>(&self, f: F) -> Result<R, ContextError>
+        where F: FnOnce(&DisplayLink) -> R
+    {
+        let cgl_context = unsafe { self.gl.CGLContextObj() as *mut _ };
+        let mut display_link = self.display_link.lock().unwrap();
+        if display_link.is_none() {
+            *display_link = Some(DisplayLink::new(cgl_context)?);
+        }
+        let display_link = display_link.as_ref().unwrap();
+        display_link.set_display(cgl_context)?;
+        Ok(f(display_link))
+    }
+
     #[inline]
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         let _: () = msg_send![*self.gl, update];
@@ -134,6 +416,14 @@ impl Context {
         Ok(())
     }
 
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        if self.is_current() {
+            NSOpenGLContext::clearCurrentContext(nil);
+        }
+        Ok(())
+    }
+
     #[inline]
     pub fn is_current(&self) -> bool {
         unsafe {
@@ -150,6 +440,124 @@ impl Context {
         }
     }
 
+    /// NSOpenGL doesn't report invalid contexts, whose loss is only reported by
+    /// `glGetGraphicsResetStatus`.
+    #[inline]
+    pub fn validate(&self) -> Result<(), ContextError> {
+        Ok(())
+    }
+
+    #[inline]
+    pub fn set_swap_interval(&self, interval: SwapInterval) -> Result<(), ContextError> {
+        let value = match interval {
+            SwapInterval::Wait(interval) => interval as i32,
+            SwapInterval::Adaptive(_) => return Err(ContextError::FunctionUnavailable),
+        };
+        unsafe {
+            self.gl.setValues_forParameter_(&value,
+                                            appkit::NSOpenGLContextParameter::NSOpenGLCPSwapInterval);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn get_swap_interval(&self) -> Result<SwapInterval, ContextError> {
+        let mut value: i32 = 0;
+        unsafe {
+            let param = appkit::NSOpenGLContextParameter::NSOpenGLCPSwapInterval;
+            let _: () = msg_send![*self.gl, getValues:&mut value forParameter:param];
+        }
+        Ok(SwapInterval::Wait(value.max(0) as u32))
+    }
+
+    // NSOpenGLCPSwapInterval only enables or disables vsync.
+    #[inline]
+    pub fn get_swap_interval_range(&self) -> Result<(u32, u32), ContextError> {
+        Ok((0, 1))
+    }
+
+    #[inline]
+    pub fn supported_present_modes(&self) -> Result<Vec<PresentMode>, ContextError> {
+        Ok(vec![PresentMode::Immediate, PresentMode::Fifo])
+    }
+
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        Ok(SwapBehavior::Destroyed)
+    }
+
+    #[inline]
+    pub fn colorspace(&self) -> Result<Colorspace, ContextError> {
+        Ok(self.get_pixel_format().colorspace)
+    }
+
+    #[inline]
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        Ok(self.get_pixel_format().render_buffer())
+    }
+
+    #[inline]
+    pub fn set_render_buffer(&self, render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        if self.render_buffer()? == render_buffer {
+            Ok(())
+        } else {
+            Err(ContextError::FunctionUnavailable)
+        }
+    }
+
+    #[inline]
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        Ok(MultisampleResolve::Default)
+    }
+
+    #[inline]
+    pub fn set_multisample_resolve(&self, resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        match resolve {
+            MultisampleResolve::Default => Ok(()),
+            MultisampleResolve::Box => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn join_swap_group(&self, _: u32) -> Result<(), ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+
+    #[inline]
+    pub fn bind_swap_barrier(&self, _: u32, _: u32) -> Result<(), ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+
+    #[inline]
+    pub fn swap_buffers_with_frame_id(&self) -> Result<u64, ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+
+    #[inline]
+    pub fn try_swap_buffers(&self) -> Result<u64, ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+
+    #[inline]
+    pub fn presentation_time(&self, _: u64) -> Result<PresentationTime, ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+
+    #[inline]
+    pub fn current_state(&self) -> CurrentState {
+        CurrentState(unsafe { CGLGetCurrentContext() })
+    }
+
+    #[inline]
+    pub unsafe fn restore_current_state(&self, state: &CurrentState) -> Result<(), ContextError> {
+        if CGLSetCurrentContext(state.0) != 0 {
+            return Err(ContextError::OsError(format!("CGLSetCurrentContext failed")));
+        }
+        Ok(())
+    }
+
     pub fn get_proc_address(&self, addr: &str) -> *const () {
         let symbol_name: CFString = FromStr::from_str(addr).unwrap();
         let framework_name: CFString = FromStr::from_str("com.apple.opengl").unwrap();
@@ -181,12 +589,22 @@ impl Context {
         self.pixel_format.clone()
     }
 
+    /// CGL pbuffers are deprecated and not supported.
+    #[inline]
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        Err(ContextError::FunctionUnavailable)
+    }
+
     #[inline]
     pub unsafe fn raw_handle(&self) -> *mut c_void {
         *self.gl.deref() as *mut _
     }
 }
 
+/// The CGL context that was current on the calling thread when `Context::current_state` was
+/// called, which may be null.
+pub struct CurrentState(CGLContextObj);
+
 struct IdRef(id);
 
 impl IdRef {
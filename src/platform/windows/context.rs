@@ -3,6 +3,7 @@
 use std::ptr;
 
 use winapi::shared::windef::HWND;
+#[cfg(feature = "winit")]
 use winit;
 
 use ContextError;
@@ -10,13 +11,23 @@ use CreationError;
 use GlAttributes;
 use GlRequest;
 use Api;
+use Backend;
+use PbufferLimits;
 use PixelFormat;
 use PixelFormatRequirements;
+use PresentMode;
+use MultisampleResolve;
+use PresentationTime;
+use RenderBuffer;
+use SwapBehavior;
+use Colorspace;
+use SwapInterval;
 
 use api::wgl::Context as WglContext;
 use api::egl::Context as EglContext;
 use api::egl::ffi::egl::Egl;
 use api::egl;
+use api::wgl;
 use platform::RawHandle;
 
 unsafe impl Send for Context {}
@@ -27,8 +38,15 @@ pub enum Context {
     Wgl(WglContext),
 }
 
+/// The context that was current on the calling thread when `Context::current_state` was called.
+pub enum CurrentState {
+    Egl(egl::CurrentState),
+    Wgl(wgl::CurrentState),
+}
+
 impl Context {
     /// See the docs in the crate root file.
+    #[cfg(feature = "winit")]
     pub fn new(
         window_builder: winit::WindowBuilder,
         events_loop: &winit::EventsLoop,
@@ -38,6 +56,24 @@ impl Context {
     ) -> Result<(winit::Window, Self), CreationError>
     {
         let window = window_builder.build(events_loop)?;
+        let context_result = unsafe {
+            let w = window.platform_window() as HWND;
+            Self::new_raw_context(w, pf_reqs, gl_attr, egl)
+        };
+        context_result.map(|context| (window, context))
+    }
+
+    /// Builds a context on a window that was created by the user.
+    ///
+    /// The window must **not** have had `SetPixelFormat` called on it and must remain alive for
+    /// as long as the context does.
+    pub unsafe fn new_raw_context(
+        w: HWND,
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Self>,
+        egl: Option<&Egl>,
+    ) -> Result<Self, CreationError>
+    {
         let gl_attr = gl_attr.clone().map_sharing(|ctxt| {
             match *ctxt {
                 Context::Wgl(ref c) => c.get_hglrc(),
@@ -45,31 +81,47 @@ impl Context {
                 Context::Egl(_) => unimplemented!(),
             }
         });
-        let context_result = unsafe {
-            let w = window.platform_window() as HWND;
-            match gl_attr.version {
-                GlRequest::Specific(Api::OpenGlEs, (_major, _minor)) => {
-                    if let Some(egl) = egl {
-                        if let Ok(c) =
-                               EglContext::new(egl.clone(),
-                                               &pf_reqs,
-                                               &gl_attr.clone().map_sharing(|_| unimplemented!()),
-                                               egl::NativeDisplay::Other(Some(ptr::null())))
-                            .and_then(|p| p.finish(w)) {
-                            Ok(Context::Egl(c))
-                        } else {
-                            WglContext::new(&pf_reqs, &gl_attr, w).map(Context::Wgl)
-                        }
-
-                    } else {
-                        // falling back to WGL, which is always available
-                        WglContext::new(&pf_reqs, &gl_attr, w).map(Context::Wgl)
+        match gl_attr.version {
+            GlRequest::Specific(Api::OpenGlEs, (_major, _minor)) => {
+                let mut errors = Vec::new();
+                if let Some(egl) = egl {
+                    let context = EglContext::new(egl.clone(),
+                                                  &pf_reqs,
+                                                  gl_attr.clone().map_sharing(|_| unimplemented!()),
+                                                  egl::NativeDisplay::Other(Some(ptr::null())))
+                        .and_then(|p| p.finish(w));
+                    match context {
+                        Ok(c) => return Ok(Context::Egl(c)),
+                        Err(err) => errors.push((Backend::Egl, err)),
                     }
                 }
-                _ => WglContext::new(&pf_reqs, &gl_attr, w).map(Context::Wgl),
+
+                // falling back to WGL, which is always available
+                WglContext::new(&pf_reqs, &gl_attr, w).map(Context::Wgl).map_err(|err| {
+                    errors.push((Backend::Wgl, err));
+                    CreationError::from_backend_errors(errors)
+                })
             }
+            _ => WglContext::new(&pf_reqs, &gl_attr, w).map(Context::Wgl),
+        }
+    }
+
+    /// Destroys this context and builds a new one drawing to the same window. Only WGL contexts
+    /// can be recreated.
+    pub unsafe fn recreate(self, gl_attr: &GlAttributes<&Self>) -> Result<Self, CreationError> {
+        let share = match gl_attr.sharing {
+            Some(&Context::Wgl(ref c)) => Some(c.get_hglrc()),
+            Some(&Context::Egl(_)) => {
+                return Err(CreationError::NotSupported("a WGL context can't share lists with EGL"));
+            },
+            None => None,
         };
-        context_result.map(|context| (window, context))
+        match self {
+            Context::Wgl(c) => c.recreate(&gl_attr.clone().set_sharing(share)).map(Context::Wgl),
+            Context::Egl(_) => {
+                Err(CreationError::NotSupported("EGL contexts can't be recreated on this platform"))
+            },
+        }
     }
 
     #[inline]
@@ -85,6 +137,14 @@ impl Context {
         }
     }
 
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        match *self {
+            Context::Wgl(ref c) => c.make_not_current(),
+            Context::Egl(ref c) => c.make_not_current(),
+        }
+    }
+
     #[inline]
     pub fn is_current(&self) -> bool {
         match *self {
@@ -93,6 +153,171 @@ impl Context {
         }
     }
 
+    #[inline]
+    pub fn validate(&self) -> Result<(), ContextError> {
+        match *self {
+            Context::Wgl(ref c) => c.validate(),
+            Context::Egl(ref c) => c.validate(),
+        }
+    }
+
+    #[inline]
+    pub fn set_swap_interval(&self, interval: SwapInterval) -> Result<(), ContextError> {
+        match *self {
+            Context::Wgl(ref c) => c.set_swap_interval(interval),
+            Context::Egl(ref c) => c.set_swap_interval(interval),
+        }
+    }
+
+    #[inline]
+    pub fn get_swap_interval(&self) -> Result<SwapInterval, ContextError> {
+        match *self {
+            Context::Wgl(ref c) => c.get_swap_interval(),
+            Context::Egl(ref c) => c.get_swap_interval(),
+        }
+    }
+
+    #[inline]
+    pub fn get_swap_interval_range(&self) -> Result<(u32, u32), ContextError> {
+        match *self {
+            Context::Wgl(ref c) => c.get_swap_interval_range(),
+            Context::Egl(ref c) => c.get_swap_interval_range(),
+        }
+    }
+
+    #[inline]
+    pub fn supported_present_modes(&self) -> Result<Vec<PresentMode>, ContextError> {
+        match *self {
+            Context::Wgl(ref c) => c.supported_present_modes(),
+            Context::Egl(ref c) => c.supported_present_modes(),
+        }
+    }
+
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        match *self {
+            Context::Wgl(_) => Ok(SwapBehavior::Destroyed),
+            Context::Egl(ref c) => c.swap_behavior(),
+        }
+    }
+
+    #[inline]
+    pub fn colorspace(&self) -> Result<Colorspace, ContextError> {
+        match *self {
+            Context::Wgl(ref c) => Ok(c.get_pixel_format().colorspace),
+            Context::Egl(ref c) => c.colorspace(),
+        }
+    }
+
+    #[inline]
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        match *self {
+            Context::Wgl(ref c) => Ok(c.get_pixel_format().render_buffer()),
+            Context::Egl(ref c) => c.render_buffer(),
+        }
+    }
+
+    #[inline]
+    pub fn set_render_buffer(&self, render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        match *self {
+            Context::Wgl(_) if self.render_buffer()? == render_buffer => Ok(()),
+            Context::Wgl(_) => Err(ContextError::FunctionUnavailable),
+            Context::Egl(ref c) => c.set_render_buffer(render_buffer),
+        }
+    }
+
+    #[inline]
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        match *self {
+            Context::Wgl(_) => Ok(MultisampleResolve::Default),
+            Context::Egl(ref c) => c.multisample_resolve(),
+        }
+    }
+
+    #[inline]
+    pub fn set_multisample_resolve(&self, resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        match *self {
+            Context::Wgl(_) if resolve == MultisampleResolve::Default => Ok(()),
+            Context::Wgl(_) => Err(ContextError::FunctionUnavailable),
+            Context::Egl(ref c) => c.set_multisample_resolve(resolve),
+        }
+    }
+
+    #[inline]
+    pub fn set_monitor_vsync(&self, enabled: bool) -> Result<(), ContextError> {
+        match *self {
+            Context::Wgl(ref c) => c.set_monitor_vsync(enabled),
+            Context::Egl(_) => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn set_dwm_low_latency(&self, enabled: bool) -> Result<(), ContextError> {
+        match *self {
+            Context::Wgl(ref c) => c.set_dwm_low_latency(enabled),
+            Context::Egl(_) => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn join_swap_group(&self, group: u32) -> Result<(), ContextError> {
+        match *self {
+            Context::Wgl(ref c) => c.join_swap_group(group),
+            Context::Egl(_) => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn bind_swap_barrier(&self, group: u32, barrier: u32) -> Result<(), ContextError> {
+        match *self {
+            Context::Wgl(ref c) => c.bind_swap_barrier(group, barrier),
+            Context::Egl(_) => Err(ContextError::FunctionUnavailable),
+        }
+    }
+
+    #[inline]
+    pub fn swap_buffers_with_frame_id(&self) -> Result<u64, ContextError> {
+        match *self {
+            Context::Wgl(_) => Err(ContextError::FunctionUnavailable),
+            Context::Egl(ref c) => c.swap_buffers_with_frame_id(),
+        }
+    }
+
+    #[inline]
+    pub fn try_swap_buffers(&self) -> Result<u64, ContextError> {
+        match *self {
+            Context::Wgl(_) => Err(ContextError::FunctionUnavailable),
+            Context::Egl(ref c) => c.try_swap_buffers(),
+        }
+    }
+
+    #[inline]
+    pub fn presentation_time(&self, frame_id: u64) -> Result<PresentationTime, ContextError> {
+        match *self {
+            Context::Wgl(_) => Err(ContextError::FunctionUnavailable),
+            Context::Egl(ref c) => c.presentation_time(frame_id),
+        }
+    }
+
+    #[inline]
+    pub fn current_state(&self) -> CurrentState {
+        match *self {
+            Context::Wgl(ref c) => CurrentState::Wgl(c.current_state()),
+            Context::Egl(ref c) => CurrentState::Egl(c.current_state()),
+        }
+    }
+
+    #[inline]
+    pub unsafe fn restore_current_state(&self, state: &CurrentState) -> Result<(), ContextError> {
+        match (self, state) {
+            (&Context::Wgl(ref c), &CurrentState::Wgl(ref state)) => c.restore_current_state(state),
+            (&Context::Egl(ref c), &CurrentState::Egl(ref state)) => c.restore_current_state(state),
+            _ => unreachable!(),
+        }
+    }
+
     #[inline]
     pub fn get_proc_address(&self, addr: &str) -> *const () {
         match *self {
@@ -125,6 +350,14 @@ impl Context {
         }
     }
 
+    #[inline]
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        match *self {
+            Context::Wgl(ref c) => c.max_pbuffer_size(),
+            Context::Egl(ref c) => c.max_pbuffer_size(),
+        }
+    }
+
     #[inline]
     pub unsafe fn raw_handle(&self) -> RawHandle {
         match *self {
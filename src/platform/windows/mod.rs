@@ -3,23 +3,40 @@
 use std::ffi::CString;
 use std::ops::{Deref, DerefMut};
 
-use winapi::shared::windef::HGLRC;
+use winapi::shared::windef::{HGLRC, HWND};
 use winapi::um::libloaderapi::*;
+#[cfg(feature = "winit")]
 use winit;
 
 use Api;
+use Backend;
 use ContextError;
 use CreationError;
+use DeviceInfo;
+use SupportedVersions;
+use PbufferAttributes;
+use PbufferLimits;
 use PixelFormat;
 use PixelFormatRequirements;
 use GlAttributes;
+use RawDisplayHandle;
+use RawPixmapHandle;
+use RawWindowHandle;
+use MultisampleResolve;
+use Rect;
+use RenderBuffer;
+use SwapBehavior;
 
 use api::egl::ffi::egl::Egl;
 use api::egl;
 use api::egl::Context as EglContext;
+use api::wgl::Context as WglContext;
 
 mod context;
 
+pub use self::context::CurrentState;
+pub use api::wgl::DibSection;
+
 /// Context handles available on Windows.
 #[derive(Clone, Debug)]
 pub enum RawHandle {
@@ -59,11 +76,132 @@ lazy_static! {
     };
 }
 
+/// A display connection provided by the user. Windows doesn't need one, so it holds no state.
+pub struct Display;
+
+impl Display {
+    pub unsafe fn from_raw(display_handle: RawDisplayHandle) -> Result<Display, CreationError> {
+        match display_handle {
+            RawDisplayHandle::Windows => Ok(Display),
+            _ => Err(CreationError::NotSupported("the display handle is not a Windows handle")),
+        }
+    }
+
+    pub fn find_configs(&self, _: &PixelFormatRequirements)
+                        -> Result<Vec<Config>, CreationError>
+    {
+        Err(CreationError::NotSupported("config enumeration is not supported on this platform"))
+    }
+
+    pub fn device_info(&self) -> Result<DeviceInfo, CreationError> {
+        Err(CreationError::NotSupported("device information is not available on this platform"))
+    }
+
+    pub fn probe_versions(&self, _: Api) -> Result<SupportedVersions, CreationError> {
+        Err(CreationError::NotSupported("version probing is not supported on this platform"))
+    }
+}
+
+/// Config enumeration is not supported on this platform, so this type can't be constructed.
+#[derive(Clone)]
+pub enum Config {}
+
+impl Config {
+    #[inline]
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn max_pbuffer_size(&self) -> Result<PbufferLimits, ContextError> {
+        match *self {}
+    }
+
+    pub unsafe fn create_window_surface(&self, _: RawWindowHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        match *self {}
+    }
+
+    pub fn create_pbuffer_surface(&self, _: &PbufferAttributes) -> Result<Surface, CreationError> {
+        match *self {}
+    }
+
+    pub unsafe fn create_pixmap_surface(&self, _: RawPixmapHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        match *self {}
+    }
+}
+
+/// Surfaces are not supported on this platform, so this type can't be constructed.
+pub enum Surface {}
+
+impl Surface {
+    #[inline]
+    pub fn dimensions(&self) -> Result<(u32, u32), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn buffer_age(&self) -> Result<u32, ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn set_damage_region(&self, _rects: &[Rect]) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn set_swap_behavior(&self, _behavior: SwapBehavior) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn set_render_buffer(&self, _render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn set_multisample_resolve(&self, _resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        match *self {}
+    }
+
+    #[inline]
+    pub fn resize(&self, _: u32, _: u32) {
+        match *self {}
+    }
+}
+
 /// The Win32 implementation of the main `Context` object.
 pub struct Context(context::Context);
 
 impl Context {
     /// See the docs in the crate root file.
+    #[cfg(feature = "winit")]
     #[inline]
     pub fn new(
         window_builder: winit::WindowBuilder,
@@ -79,6 +217,90 @@ impl Context {
             EGL.as_ref().map(|w| &w.0),
         ).map(|(w, c)| (w, Context(c)))
     }
+
+    /// Builds a context on a window that was created by the user.
+    #[inline]
+    pub unsafe fn new_raw_context(
+        window: HWND,
+        pf_reqs: &PixelFormatRequirements,
+        opengl: &GlAttributes<&Self>,
+    ) -> Result<Self, CreationError> {
+        context::Context::new_raw_context(
+            window,
+            pf_reqs,
+            &opengl.clone().map_sharing(|w| &w.0),
+            EGL.as_ref().map(|w| &w.0),
+        ).map(Context)
+    }
+
+    /// Builds a context on a window that was created by another library.
+    #[inline]
+    pub unsafe fn new_from_raw_handles(
+        display_handle: RawDisplayHandle,
+        window_handle: RawWindowHandle,
+        pf_reqs: &PixelFormatRequirements,
+        opengl: &GlAttributes<&Self>,
+    ) -> Result<Self, CreationError> {
+        match (display_handle, window_handle) {
+            (RawDisplayHandle::Windows, RawWindowHandle::Win32 { hwnd }) => {
+                Context::new_raw_context(hwnd as HWND, pf_reqs, opengl)
+            },
+            _ => Err(CreationError::NotSupported("raw handles are not Win32 handles")),
+        }
+    }
+
+    /// Builds a context on a window that was created by another library, using a display
+    /// provided by the user.
+    #[inline]
+    pub unsafe fn new_with_display(
+        _: &Display,
+        window_handle: RawWindowHandle,
+        pf_reqs: &PixelFormatRequirements,
+        opengl: &GlAttributes<&Self>,
+    ) -> Result<Self, CreationError>
+    {
+        Context::new_from_raw_handles(RawDisplayHandle::Windows, window_handle, pf_reqs, opengl)
+    }
+
+    #[inline]
+    pub unsafe fn new_with_config(
+        config: &Config,
+        _: RawWindowHandle,
+        _: &GlAttributes<&Self>,
+    ) -> Result<Self, CreationError>
+    {
+        match *config {}
+    }
+
+    /// Destroys this context and builds a new one drawing to the same window, which is only
+    /// supported with WGL.
+    #[inline]
+    pub unsafe fn recreate(self, gl_attr: &GlAttributes<&Self>) -> Result<Self, CreationError> {
+        self.0.recreate(&gl_attr.clone().map_sharing(|c| &c.0)).map(Context)
+    }
+
+    pub unsafe fn create_window_surface(&self, _: RawWindowHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        Err(CreationError::NotSupported("surfaces are not supported on this platform"))
+    }
+
+    pub fn create_pbuffer_surface(&self, _: &PbufferAttributes) -> Result<Surface, CreationError> {
+        Err(CreationError::NotSupported("surfaces are not supported on this platform"))
+    }
+
+    pub unsafe fn create_pixmap_surface(&self, _: RawPixmapHandle)
+                                        -> Result<Surface, CreationError>
+    {
+        Err(CreationError::NotSupported("surfaces are not supported on this platform"))
+    }
+
+    #[inline]
+    pub unsafe fn make_current_surfaces(&self, draw: &Surface, _: &Surface)
+                                        -> Result<(), ContextError>
+    {
+        match *draw {}
+    }
 }
 
 impl Deref for Context {
@@ -98,13 +320,20 @@ impl DerefMut for Context {
 }
 
 #[derive(Clone, Default)]
-pub struct PlatformSpecificHeadlessBuilderAttributes;
+pub struct PlatformSpecificHeadlessBuilderAttributes {
+    pub dib_section: bool,
+}
 
 pub enum HeadlessContext {
     /// A regular window, but invisible.
+    #[cfg(feature = "winit")]
     HiddenWindow(winit::EventsLoop, winit::Window, context::Context),
-    /// An EGL pbuffer.
+    /// An EGL pbuffer, or an EGL context without surface if built with `new_surfaceless`.
     EglPbuffer(EglContext),
+    /// A WGL pbuffer, created from an invisible window that glutin manages itself.
+    WglPbuffer(WglContext),
+    /// A WGL context drawing to a device-independent bitmap.
+    DibSection(WglContext),
 }
 
 impl HeadlessContext {
@@ -112,21 +341,77 @@ impl HeadlessContext {
         dimensions: (u32, u32),
         pf_reqs: &PixelFormatRequirements,
         gl_attr: &GlAttributes<&Self>,
-        _: &PlatformSpecificHeadlessBuilderAttributes,
+        platform_specific: &PlatformSpecificHeadlessBuilderAttributes,
     ) -> Result<Self, CreationError>
     {
+        if platform_specific.dib_section {
+            let gl_attr = gl_attr.clone().map_sharing(|_| unimplemented!()); // TODO
+            return unsafe { WglContext::new_dib_section(pf_reqs, &gl_attr, dimensions) }
+                .map(HeadlessContext::DibSection);
+        }
+
         // if EGL is available, we try using EGL first
-        // if EGL returns an error, we try the hidden window method
-        if let &Some(ref egl) = &*EGL {
-            let gl_attr = &gl_attr.clone().map_sharing(|_| unimplemented!()); // TODO
+        // if EGL returns an error, we try a WGL pbuffer and then the hidden window method
+        // ANGLE usually renders with the GPU, while WGL can pick the generic implementation
+        let mut errors = Vec::new();
+        if let (&Some(ref egl), false) = (&*EGL, pf_reqs.software_rendering) {
+            let gl_attr = gl_attr.clone().map_sharing(|_| unimplemented!()); // TODO
             let native_display = egl::NativeDisplay::Other(None);
-            let context = EglContext::new(egl.0.clone(), pf_reqs, &gl_attr, native_display)
-                .and_then(|prototype| prototype.finish_pbuffer(dimensions))
-                .map(|ctxt| HeadlessContext::EglPbuffer(ctxt));
-            if let Ok(context) = context {
-                return Ok(context);
+            let context = EglContext::new(egl.0.clone(), pf_reqs, gl_attr, native_display)
+                .and_then(|prototype| prototype.finish_pbuffer(dimensions));
+            match context {
+                Ok(context) => return Ok(HeadlessContext::EglPbuffer(context)),
+                Err(err) => errors.push((Backend::Egl, err)),
             }
         }
+
+        let wgl_attr = gl_attr.clone().map_sharing(|_| unimplemented!()); // TODO
+        match unsafe { WglContext::new_pbuffer(pf_reqs, &wgl_attr, dimensions) } {
+            Ok(context) => return Ok(HeadlessContext::WglPbuffer(context)),
+            Err(err) => errors.push((Backend::Wgl, err)),
+        }
+
+        HeadlessContext::new_hidden_window(pf_reqs, gl_attr).map_err(|err| {
+            errors.push((Backend::Wgl, err));
+            CreationError::from_backend_errors(errors)
+        })
+    }
+
+    /// Builds a context without any surface, which only EGL supports.
+    pub fn new_surfaceless(
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Self>,
+        platform_specific: &PlatformSpecificHeadlessBuilderAttributes,
+    ) -> Result<Self, CreationError>
+    {
+        if platform_specific.dib_section {
+            return Err(CreationError::NotSupported("DIB section contexts always have a surface"));
+        }
+        let egl = EGL.as_ref().ok_or(CreationError::NotSupported("libEGL not present"))?;
+        let gl_attr = gl_attr.clone().map_sharing(|_| unimplemented!()); // TODO
+        let native_display = egl::NativeDisplay::Other(None);
+        EglContext::new(egl.0.clone(), pf_reqs, gl_attr, native_display)
+            .and_then(|prototype| prototype.finish_surfaceless())
+            .map(|ctxt| HeadlessContext::EglPbuffer(ctxt))
+    }
+
+    pub fn new_shared(
+        _: (u32, u32),
+        _: bool,
+        _: &GlAttributes<&Context>,
+    ) -> Result<Self, CreationError>
+    {
+        Err(CreationError::NotSupported("sharing lists between a headless and a windowed context"))
+    }
+
+    /// Builds the context on an invisible window, for when neither EGL nor WGL pbuffers are
+    /// available.
+    #[cfg(feature = "winit")]
+    fn new_hidden_window(
+        pf_reqs: &PixelFormatRequirements,
+        gl_attr: &GlAttributes<&Self>,
+    ) -> Result<Self, CreationError>
+    {
         let events_loop = winit::EventsLoop::new();
         let window_builder = winit::WindowBuilder::new().with_visibility(false);
         let gl_attr = &gl_attr.clone().map_sharing(|_| unimplemented!());
@@ -135,59 +420,109 @@ impl HeadlessContext {
             .map(|(window, context)| HeadlessContext::HiddenWindow(events_loop, window, context))
     }
 
+    #[cfg(not(feature = "winit"))]
+    fn new_hidden_window(
+        _: &PixelFormatRequirements,
+        _: &GlAttributes<&Self>,
+    ) -> Result<Self, CreationError>
+    {
+        Err(CreationError::NotSupported("hidden windows require the `winit` feature"))
+    }
+
     #[inline]
     pub unsafe fn make_current(&self) -> Result<(), ContextError> {
         match self {
+            #[cfg(feature = "winit")]
             &HeadlessContext::HiddenWindow(_, _, ref ctxt) => ctxt.make_current(),
             &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.make_current(),
+            &HeadlessContext::WglPbuffer(ref ctxt) => ctxt.make_current(),
+            &HeadlessContext::DibSection(ref ctxt) => ctxt.make_current(),
+        }
+    }
+
+    #[inline]
+    pub unsafe fn make_not_current(&self) -> Result<(), ContextError> {
+        match self {
+            #[cfg(feature = "winit")]
+            &HeadlessContext::HiddenWindow(_, _, ref ctxt) => ctxt.make_not_current(),
+            &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.make_not_current(),
+            &HeadlessContext::WglPbuffer(ref ctxt) => ctxt.make_not_current(),
+            &HeadlessContext::DibSection(ref ctxt) => ctxt.make_not_current(),
         }
     }
 
     #[inline]
     pub fn is_current(&self) -> bool {
         match self {
+            #[cfg(feature = "winit")]
             &HeadlessContext::HiddenWindow(_, _, ref ctxt) => ctxt.is_current(),
             &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.is_current(),
+            &HeadlessContext::WglPbuffer(ref ctxt) => ctxt.is_current(),
+            &HeadlessContext::DibSection(ref ctxt) => ctxt.is_current(),
         }
     }
 
     #[inline]
     pub fn get_proc_address(&self, addr: &str) -> *const () {
         match self {
+            #[cfg(feature = "winit")]
             &HeadlessContext::HiddenWindow(_, _, ref ctxt) => ctxt.get_proc_address(addr),
             &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.get_proc_address(addr),
+            &HeadlessContext::WglPbuffer(ref ctxt) => ctxt.get_proc_address(addr),
+            &HeadlessContext::DibSection(ref ctxt) => ctxt.get_proc_address(addr),
         }
     }
 
     #[inline]
     pub fn swap_buffers(&self) -> Result<(), ContextError> {
         match self {
+            #[cfg(feature = "winit")]
             &HeadlessContext::HiddenWindow(_, _, ref ctxt) => ctxt.swap_buffers(),
             &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.swap_buffers(),
+            &HeadlessContext::WglPbuffer(ref ctxt) => ctxt.swap_buffers(),
+            &HeadlessContext::DibSection(ref ctxt) => ctxt.swap_buffers(),
         }
     }
 
     #[inline]
     pub fn get_api(&self) -> Api {
         match self {
+            #[cfg(feature = "winit")]
             &HeadlessContext::HiddenWindow(_, _, ref ctxt) => ctxt.get_api(),
             &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.get_api(),
+            &HeadlessContext::WglPbuffer(ref ctxt) => ctxt.get_api(),
+            &HeadlessContext::DibSection(ref ctxt) => ctxt.get_api(),
         }
     }
 
     #[inline]
     pub fn get_pixel_format(&self) -> PixelFormat {
         match self {
+            #[cfg(feature = "winit")]
             &HeadlessContext::HiddenWindow(_, _, ref ctxt) => ctxt.get_pixel_format(),
             &HeadlessContext::EglPbuffer(ref ctxt) => ctxt.get_pixel_format(),
+            &HeadlessContext::WglPbuffer(ref ctxt) => ctxt.get_pixel_format(),
+            &HeadlessContext::DibSection(ref ctxt) => ctxt.get_pixel_format(),
         }
     }
 
     #[inline]
     pub unsafe fn raw_handle(&self) -> RawHandle {
         match *self {
+            #[cfg(feature = "winit")]
             HeadlessContext::HiddenWindow(_, _, ref ctxt) => ctxt.raw_handle(),
             HeadlessContext::EglPbuffer(ref ctxt) => RawHandle::Egl(ctxt.raw_handle()),
+            HeadlessContext::WglPbuffer(ref ctxt) => RawHandle::Wgl(ctxt.get_hglrc()),
+            HeadlessContext::DibSection(ref ctxt) => RawHandle::Wgl(ctxt.get_hglrc()),
+        }
+    }
+
+    /// Returns the bitmap the context draws to, if it was built with `dib_section` set.
+    #[inline]
+    pub fn dib_section(&self) -> Option<DibSection> {
+        match *self {
+            HeadlessContext::DibSection(ref ctxt) => ctxt.dib_section(),
+            _ => None,
         }
     }
 }
@@ -0,0 +1,286 @@
+use Api;
+use Config;
+use ContextBuilder;
+use ContextCurrentState;
+use ContextError;
+use ContextTrait;
+use CreationError;
+use Context;
+use Display;
+use GlAttributes;
+use GlContext;
+use NotCurrent;
+use PixelFormat;
+use PossiblyCurrent;
+use RawDisplayHandle;
+use RawWindowHandle;
+use RelaxedRequirement;
+
+use create_with_fallbacks;
+use create_with_versions;
+
+use platform;
+
+use std::sync::Arc;
+
+/// Represents an OpenGL context built on a window that glutin does not own.
+///
+/// A `RawContext` is obtained either by building it on a native window handle (see
+/// `RawContext::new_from_raw_handles` and the `RawContextExt` traits in the `os` module) or by
+/// splitting a `WindowedContext`.
+///
+/// The window must outlive the `RawContext`.
+pub struct RawContext<T: ContextCurrentState> {
+    pub(crate) context: Context<T>,
+}
+
+impl RawContext<NotCurrent> {
+    /// Builds a GL context on a window that was created by another library, without involving
+    /// winit.
+    ///
+    /// The handles must belong to the current platform, otherwise
+    /// `CreationError::NotSupported` is returned.
+    ///
+    /// # Unsafety
+    ///
+    /// The handles must be valid, and the display and window must remain alive for as long as
+    /// the returned context.
+    pub unsafe fn new_from_raw_handles<T: ContextCurrentState>(
+        display_handle: RawDisplayHandle,
+        window_handle: RawWindowHandle,
+        context_builder: ContextBuilder<T>,
+    ) -> Result<Self, CreationError>
+    {
+        let ContextBuilder { pf_reqs, gl_attr } = context_builder;
+        let share_group = gl_attr.share_group();
+        let gl_attr = gl_attr.map_sharing(|ctxt| &ctxt.context);
+        create_with_versions(&gl_attr, |gl_attr| {
+            create_with_fallbacks(&pf_reqs, |pf_reqs| {
+                platform::Context::new_from_raw_handles(display_handle, window_handle, pf_reqs,
+                                                        gl_attr)
+            }).map(|context| RawContext::from_platform(context, share_group.clone(), gl_attr))
+        })
+    }
+
+    /// Builds a GL context on a window that was created by another library, reusing a display
+    /// connection provided by the user.
+    ///
+    /// The window handle must belong to the same platform as the display, otherwise
+    /// `CreationError::NotSupported` is returned.
+    ///
+    /// # Unsafety
+    ///
+    /// The handle must be valid, and the window must remain alive for as long as the returned
+    /// context.
+    pub unsafe fn new_with_display<T: ContextCurrentState>(
+        display: &Display,
+        window_handle: RawWindowHandle,
+        context_builder: ContextBuilder<T>,
+    ) -> Result<Self, CreationError>
+    {
+        let ContextBuilder { pf_reqs, gl_attr } = context_builder;
+        let share_group = gl_attr.share_group();
+        let gl_attr = gl_attr.map_sharing(|ctxt| &ctxt.context);
+        create_with_versions(&gl_attr, |gl_attr| {
+            create_with_fallbacks(&pf_reqs, |pf_reqs| {
+                platform::Context::new_with_display(&display.display, window_handle, pf_reqs,
+                                                    gl_attr)
+            }).map(|context| RawContext::from_platform(context, share_group.clone(), gl_attr))
+        })
+    }
+
+    /// Builds a GL context on a window that was created by another library, using a config
+    /// returned by `Display::find_configs`.
+    ///
+    /// The pixel format requirements of `context_builder` are ignored, since the config was
+    /// already chosen. The window handle must belong to the same display as the config,
+    /// otherwise `CreationError::NotSupported` is returned. It is also returned if
+    /// `context_builder` shares lists with another context, which isn't supported yet.
+    ///
+    /// # Unsafety
+    ///
+    /// The handle must be valid, and the window must remain alive for as long as the returned
+    /// context.
+    pub unsafe fn new_with_config<T: ContextCurrentState>(
+        config: &Config,
+        window_handle: RawWindowHandle,
+        context_builder: ContextBuilder<T>,
+    ) -> Result<Self, CreationError>
+    {
+        if context_builder.gl_attr.sharing.is_some() {
+            return Err(CreationError::NotSupported(
+                "sharing lists with a context built from a Config"));
+        }
+        let share_group = context_builder.gl_attr.share_group();
+        let gl_attr = context_builder.gl_attr.map_sharing(|ctxt| &ctxt.context);
+        create_with_versions(&gl_attr, |gl_attr| {
+            platform::Context::new_with_config(&config.config, window_handle, gl_attr)
+                .map(|context| {
+                    RawContext::from_platform((context, Vec::new()), share_group.clone(), gl_attr)
+                })
+        })
+    }
+
+    #[inline]
+    pub(crate) fn from_platform<S: Clone>(
+        (context, relaxed): (platform::Context, Vec<RelaxedRequirement>),
+        share_group: Arc<()>,
+        gl_attr: &GlAttributes<S>,
+    ) -> Self
+    {
+        RawContext {
+            context: Context::from_platform(context, relaxed, share_group, gl_attr),
+        }
+    }
+
+    /// Wraps a context created by another library, which can't be recreated since the attributes
+    /// it was built with are unknown.
+    pub(crate) fn from_adopted(context: platform::Context) -> Self {
+        let mut context = Context::from_platform(context, Vec::new(), Arc::new(()),
+                                                 &GlAttributes::<()>::default());
+        context.attributes = None;
+        RawContext { context: context }
+    }
+}
+
+impl<T: ContextCurrentState> RawContext<T> {
+    /// Borrow the inner GL `Context`.
+    pub fn context(&self) -> &Context<T> {
+        &self.context
+    }
+
+    /// Destroys the context and builds a new one on the same window, with the same attributes
+    /// and config.
+    ///
+    /// See `WindowedContext::recreate` for more infos. The contexts adopted from another library
+    /// can't be recreated, in which case `CreationError::NotSupported` is returned.
+    pub unsafe fn recreate(self) -> Result<RawContext<NotCurrent>, CreationError> {
+        self.context.recreate(None).map(|context| RawContext { context: context })
+    }
+
+    /// Same as `recreate`, but the new context shares lists with `shared`, which must have
+    /// survived the loss, and joins its share group.
+    ///
+    /// See `WindowedContext::recreate_with_shared_lists` for more infos.
+    pub unsafe fn recreate_with_shared_lists<U: ContextCurrentState>(self, shared: &Context<U>)
+        -> Result<RawContext<NotCurrent>, CreationError>
+    {
+        self.context.recreate(Some((&shared.context, &shared.share_group)))
+            .map(|context| RawContext { context: context })
+    }
+
+    /// Sets the context as the current context on the calling thread.
+    ///
+    /// See `Context::make_current` for more infos.
+    pub unsafe fn make_current(self) -> Result<RawContext<PossiblyCurrent>, (Self, ContextError)> {
+        match self.context.make_current() {
+            Ok(context) => Ok(RawContext { context: context }),
+            Err((context, err)) => Err((RawContext { context: context }, err)),
+        }
+    }
+
+    /// If this context is current on the calling thread, releases it.
+    ///
+    /// See `Context::make_not_current` for more infos.
+    pub unsafe fn make_not_current(self) -> Result<RawContext<NotCurrent>, (Self, ContextError)> {
+        match self.context.make_not_current() {
+            Ok(context) => Ok(RawContext { context: context }),
+            Err((context, err)) => Err((RawContext { context: context }, err)),
+        }
+    }
+
+    /// Changes the state of the context to `PossiblyCurrent` without making it current.
+    ///
+    /// See `Context::treat_as_current` for more infos.
+    pub unsafe fn treat_as_current(self) -> RawContext<PossiblyCurrent> {
+        RawContext { context: self.context.treat_as_current() }
+    }
+
+    /// Changes the state of the context to `NotCurrent` without releasing it.
+    ///
+    /// See `Context::treat_as_not_current` for more infos.
+    pub unsafe fn treat_as_not_current(self) -> RawContext<NotCurrent> {
+        RawContext { context: self.context.treat_as_not_current() }
+    }
+
+    /// Returns true if this context is the current one in this thread.
+    pub fn is_current(&self) -> bool {
+        self.context.is_current()
+    }
+
+    /// Returns the OpenGL API being used.
+    pub fn get_api(&self) -> Api {
+        self.context.get_api()
+    }
+
+    /// Returns the pixel format of the main framebuffer of the context.
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        self.context.get_pixel_format()
+    }
+
+    /// Returns true if this context and `other` share objects.
+    ///
+    /// See `Context::is_shared_with` for more infos.
+    pub fn is_shared_with<T2: ContextCurrentState>(&self, other: &Context<T2>) -> bool {
+        self.context.is_shared_with(other)
+    }
+}
+
+impl GlContext for RawContext<PossiblyCurrent> {
+    fn is_current(&self) -> bool {
+        self.context.is_current()
+    }
+
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        self.context.get_proc_address(addr)
+    }
+
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.context.swap_buffers()
+    }
+
+    fn get_api(&self) -> Api {
+        self.context.get_api()
+    }
+
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.context.get_pixel_format()
+    }
+
+    fn resize(&self, width: u32, height: u32) {
+        self.context.resize(width, height);
+    }
+}
+
+impl<T: ContextCurrentState> ContextTrait for RawContext<T> {
+    type PossiblyCurrent = RawContext<PossiblyCurrent>;
+    type NotCurrent = RawContext<NotCurrent>;
+
+    unsafe fn make_current(self) -> Result<Self::PossiblyCurrent, (Self, ContextError)> {
+        RawContext::make_current(self)
+    }
+
+    unsafe fn make_not_current(self) -> Result<Self::NotCurrent, (Self, ContextError)> {
+        RawContext::make_not_current(self)
+    }
+
+    fn is_current(&self) -> bool {
+        RawContext::is_current(self)
+    }
+
+    fn get_api(&self) -> Api {
+        RawContext::get_api(self)
+    }
+
+    fn get_pixel_format(&self) -> PixelFormat {
+        RawContext::get_pixel_format(self)
+    }
+}
+
+impl<T: ContextCurrentState> ::std::fmt::Debug for RawContext<T> {
+    fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        formatter.debug_struct("RawContext")
+            .field("context", &self.context)
+            .finish()
+    }
+}
@@ -0,0 +1,84 @@
+use std::os::raw::{c_ulong, c_void};
+
+/// A native window handle, used to build a `RawContext` on a window that was created by another
+/// library.
+///
+/// Only the variants matching the current platform are accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RawWindowHandle {
+    /// An Xlib `Window`.
+    Xlib {
+        window: c_ulong,
+    },
+    /// A `wl_surface`, along with the size its buffer should be created with.
+    ///
+    /// Wayland surfaces must be resized manually through `GlContext::resize`.
+    Wayland {
+        surface: *mut c_void,
+        width: u32,
+        height: u32,
+    },
+    /// A Win32 `HWND`.
+    Win32 {
+        hwnd: *mut c_void,
+    },
+    /// A Cocoa `NSView`.
+    AppKit {
+        ns_view: *mut c_void,
+    },
+    /// An Android `ANativeWindow`.
+    AndroidNdk {
+        a_native_window: *mut c_void,
+    },
+}
+
+/// A native pixmap handle, used to create a `Surface<PixmapSurface>`.
+///
+/// Only the variants matching the current platform are accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RawPixmapHandle {
+    /// An Xlib `Pixmap`.
+    Xlib {
+        pixmap: c_ulong,
+    },
+}
+
+/// A native context created by another library, used to share objects with it through
+/// `ContextBuilder::with_shared_raw_context`.
+///
+/// Only the variant matching the backend of the new context is accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RawContextHandle {
+    /// A `GLXContext`.
+    Glx {
+        context: *const c_void,
+    },
+    /// An `EGLContext`.
+    Egl {
+        context: *const c_void,
+    },
+    /// A WGL `HGLRC`.
+    Wgl {
+        hglrc: *mut c_void,
+    },
+    /// A `CGLContextObj`.
+    Cgl {
+        context: *mut c_void,
+    },
+}
+
+/// The native display connection a `RawWindowHandle` belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RawDisplayHandle {
+    /// An Xlib `Display`.
+    Xlib {
+        display: *mut c_void,
+    },
+    /// A `wl_display`.
+    Wayland {
+        display: *mut c_void,
+    },
+    Windows,
+    AppKit,
+    Android,
+}
@@ -0,0 +1,313 @@
+use ContextError;
+use GlContext;
+
+use std::collections::VecDeque;
+use std::os::raw::{c_int, c_uint, c_void};
+use std::{mem, ptr, slice, thread};
+
+// From `gl.h`, since glutin doesn't generate OpenGL bindings.
+const GL_RGBA: c_uint = 0x1908;
+const GL_UNSIGNED_BYTE: c_uint = 0x1401;
+const GL_PACK_ALIGNMENT: c_uint = 0x0D05;
+const GL_PIXEL_PACK_BUFFER: c_uint = 0x88EB;
+const GL_PIXEL_PACK_BUFFER_BINDING: c_uint = 0x88ED;
+const GL_STREAM_READ: c_uint = 0x88E1;
+const GL_MAP_READ_BIT: c_uint = 0x0001;
+const GL_SYNC_GPU_COMMANDS_COMPLETE: c_uint = 0x9117;
+const GL_SYNC_FLUSH_COMMANDS_BIT: c_uint = 0x0001;
+const GL_ALREADY_SIGNALED: c_uint = 0x911A;
+const GL_CONDITION_SATISFIED: c_uint = 0x911C;
+const GL_WAIT_FAILED: c_uint = 0x911D;
+
+type GlSync = *const c_void;
+
+/// The OpenGL functions used by `AsyncReadback`, loaded from the context it was built with.
+struct Functions {
+    gen_buffers: extern "system" fn(c_int, *mut c_uint),
+    delete_buffers: extern "system" fn(c_int, *const c_uint),
+    bind_buffer: extern "system" fn(c_uint, c_uint),
+    buffer_data: extern "system" fn(c_uint, isize, *const c_void, c_uint),
+    map_buffer_range: extern "system" fn(c_uint, isize, isize, c_uint) -> *mut c_void,
+    unmap_buffer: extern "system" fn(c_uint) -> u8,
+    get_integerv: extern "system" fn(c_uint, *mut c_int),
+    pixel_storei: extern "system" fn(c_uint, c_int),
+    read_pixels: extern "system" fn(c_int, c_int, c_int, c_int, c_uint, c_uint, *mut c_void),
+    fence_sync: extern "system" fn(c_uint, c_uint) -> GlSync,
+    client_wait_sync: extern "system" fn(GlSync, c_uint, u64) -> c_uint,
+    delete_sync: extern "system" fn(GlSync),
+}
+
+impl Functions {
+    fn load<C: GlContext>(context: &C) -> Result<Functions, ContextError> {
+        let load = |name: &str| -> Result<*const (), ContextError> {
+            let ptr = context.get_proc_address(name);
+            if ptr.is_null() { Err(ContextError::FunctionUnavailable) } else { Ok(ptr) }
+        };
+        unsafe {
+            Ok(Functions {
+                gen_buffers: mem::transmute(load("glGenBuffers")?),
+                delete_buffers: mem::transmute(load("glDeleteBuffers")?),
+                bind_buffer: mem::transmute(load("glBindBuffer")?),
+                buffer_data: mem::transmute(load("glBufferData")?),
+                map_buffer_range: mem::transmute(load("glMapBufferRange")?),
+                unmap_buffer: mem::transmute(load("glUnmapBuffer")?),
+                get_integerv: mem::transmute(load("glGetIntegerv")?),
+                pixel_storei: mem::transmute(load("glPixelStorei")?),
+                read_pixels: mem::transmute(load("glReadPixels")?),
+                fence_sync: mem::transmute(load("glFenceSync")?),
+                client_wait_sync: mem::transmute(load("glClientWaitSync")?),
+                delete_sync: mem::transmute(load("glDeleteSync")?),
+            })
+        }
+    }
+}
+
+/// A readback that was issued and whose fence wasn't signaled yet.
+struct Pending {
+    buffer: usize,
+    fence: GlSync,
+    frame: u64,
+}
+
+/// The pixels of a frame read by `AsyncReadback`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadbackFrame {
+    /// The number of the readback, as returned by `AsyncReadback::read`.
+    pub frame: u64,
+    /// The width and height of the frame.
+    pub dimensions: (u32, u32),
+    /// Tightly packed 8 bits RGBA values, from the bottom row to the top row like OpenGL.
+    pub pixels: Vec<u8>,
+}
+
+/// Reads the framebuffer of a context without stalling the pipeline, by copying it to one of
+/// several pixel buffer objects and polling a fence until the copy is finished.
+///
+/// The helper requires OpenGL 3.2 or OpenGL ES 3.0. All its methods must be called with the
+/// context it was built with, current on the calling thread. They return
+/// `ContextError::OsError` if `context` isn't current.
+///
+/// `destroy` must be called to delete the buffers, since they can only be deleted while the
+/// context is current. Dropping the helper without destroying it panics.
+///
+/// ```no_run
+/// # fn example<C: glutin::GlContext>(context: &C) -> Result<(), glutin::ContextError> {
+/// # unsafe {
+/// let mut readback = glutin::AsyncReadback::new(context, 800, 600, 3)?;
+/// loop {
+///     // draw the frame
+///     readback.read(context)?;
+///     context.swap_buffers()?;
+///     while let Some(frame) = readback.poll(context)? {
+///         // encode `frame.pixels`
+///     }
+/// }
+/// # }
+/// # }
+/// ```
+pub struct AsyncReadback {
+    functions: Functions,
+    dimensions: (u32, u32),
+    buffers: Vec<c_uint>,
+    // The readbacks in the order they were issued.
+    pending: VecDeque<Pending>,
+    next_frame: u64,
+    // Set by `destroy`.
+    destroyed: bool,
+}
+
+/// Returns an error if `context` isn't current on the calling thread, in which case the
+/// functions of the readback would be called without it.
+fn check_current<C: GlContext>(context: &C) -> Result<(), ContextError> {
+    if context.is_current() {
+        Ok(())
+    } else {
+        Err(ContextError::OsError(format!("the context of the readback isn't current")))
+    }
+}
+
+impl AsyncReadback {
+    /// Creates `buffers` pixel buffer objects large enough for frames of `width` by `height`.
+    /// As many readbacks can be in flight at the same time.
+    ///
+    /// Returns `ContextError::FunctionUnavailable` if the context doesn't provide pixel buffer
+    /// objects and fences.
+    ///
+    /// # Unsafety
+    ///
+    /// `context` must be current on the calling thread, and the same context must be given to
+    /// all the other methods.
+    pub unsafe fn new<C: GlContext>(context: &C, width: u32, height: u32, buffers: usize)
+                                    -> Result<AsyncReadback, ContextError>
+    {
+        check_current(context)?;
+        let functions = Functions::load(context)?;
+        let size = width as isize * height as isize * 4;
+
+        let mut names = vec![0; buffers];
+        let mut previous = 0;
+        (functions.get_integerv)(GL_PIXEL_PACK_BUFFER_BINDING, &mut previous);
+        (functions.gen_buffers)(buffers as c_int, names.as_mut_ptr());
+        for &buffer in &names {
+            (functions.bind_buffer)(GL_PIXEL_PACK_BUFFER, buffer);
+            (functions.buffer_data)(GL_PIXEL_PACK_BUFFER, size, ptr::null(), GL_STREAM_READ);
+        }
+        (functions.bind_buffer)(GL_PIXEL_PACK_BUFFER, previous as c_uint);
+
+        Ok(AsyncReadback {
+            functions: functions,
+            dimensions: (width, height),
+            buffers: names,
+            pending: VecDeque::new(),
+            next_frame: 0,
+            destroyed: false,
+        })
+    }
+
+    /// Returns the width and height of the frames.
+    #[inline]
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    /// Returns the number of readbacks that were issued and not returned by `poll` yet.
+    #[inline]
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Starts copying the current read framebuffer to a free buffer without waiting for the
+    /// copy, and returns the number of the readback, which increases by one for every call.
+    ///
+    /// The back buffer of a double buffered window is undefined after a swap, so the readback
+    /// must be issued before `swap_buffers`.
+    ///
+    /// Returns `ContextError::WouldBlock` if all the buffers hold readbacks that weren't
+    /// returned by `poll` yet.
+    ///
+    /// # Unsafety
+    ///
+    /// `context` must be the context the readback was built with.
+    pub unsafe fn read<C: GlContext>(&mut self, context: &C) -> Result<u64, ContextError> {
+        check_current(context)?;
+        if self.pending.len() == self.buffers.len() {
+            return Err(ContextError::WouldBlock);
+        }
+        let buffer = match self.pending.back() {
+            Some(last) => (last.buffer + 1) % self.buffers.len(),
+            None => 0,
+        };
+
+        let f = &self.functions;
+        let mut previous_buffer = 0;
+        let mut previous_alignment = 0;
+        (f.get_integerv)(GL_PIXEL_PACK_BUFFER_BINDING, &mut previous_buffer);
+        (f.get_integerv)(GL_PACK_ALIGNMENT, &mut previous_alignment);
+
+        (f.bind_buffer)(GL_PIXEL_PACK_BUFFER, self.buffers[buffer]);
+        (f.pixel_storei)(GL_PACK_ALIGNMENT, 1);
+        (f.read_pixels)(0, 0, self.dimensions.0 as c_int, self.dimensions.1 as c_int, GL_RGBA,
+                        GL_UNSIGNED_BYTE, ptr::null_mut());
+        let fence = (f.fence_sync)(GL_SYNC_GPU_COMMANDS_COMPLETE, 0);
+
+        (f.pixel_storei)(GL_PACK_ALIGNMENT, previous_alignment);
+        (f.bind_buffer)(GL_PIXEL_PACK_BUFFER, previous_buffer as c_uint);
+
+        if fence.is_null() {
+            return Err(ContextError::OsError(format!("glFenceSync failed")));
+        }
+
+        let frame = self.next_frame;
+        self.next_frame += 1;
+        self.pending.push_back(Pending { buffer: buffer, fence: fence, frame: frame });
+        Ok(frame)
+    }
+
+    /// Returns the pixels of the oldest readback if its copy is finished, or `None` if it isn't
+    /// or if no readback is pending. Never blocks.
+    ///
+    /// # Unsafety
+    ///
+    /// `context` must be the context the readback was built with.
+    pub unsafe fn poll<C: GlContext>(&mut self, context: &C)
+                                     -> Result<Option<ReadbackFrame>, ContextError>
+    {
+        check_current(context)?;
+        let fence = match self.pending.front() {
+            Some(pending) => pending.fence,
+            None => return Ok(None),
+        };
+
+        // the flush makes sure that the fence is eventually signaled
+        match (self.functions.client_wait_sync)(fence, GL_SYNC_FLUSH_COMMANDS_BIT, 0) {
+            GL_ALREADY_SIGNALED | GL_CONDITION_SATISFIED => (),
+            GL_WAIT_FAILED => {
+                return Err(ContextError::OsError(format!("glClientWaitSync failed")));
+            },
+            _ => return Ok(None),
+        }
+
+        let pending = self.pending.pop_front().unwrap();
+        (self.functions.delete_sync)(pending.fence);
+        let pixels = self.map(pending.buffer)?;
+        Ok(Some(ReadbackFrame {
+            frame: pending.frame,
+            dimensions: self.dimensions,
+            pixels: pixels,
+        }))
+    }
+
+    /// Copies the content of a buffer whose readback is finished. The context must be current.
+    unsafe fn map(&self, buffer: usize) -> Result<Vec<u8>, ContextError> {
+        let f = &self.functions;
+        let size = self.dimensions.0 as usize * self.dimensions.1 as usize * 4;
+
+        let mut previous = 0;
+        (f.get_integerv)(GL_PIXEL_PACK_BUFFER_BINDING, &mut previous);
+        (f.bind_buffer)(GL_PIXEL_PACK_BUFFER, self.buffers[buffer]);
+
+        let data = (f.map_buffer_range)(GL_PIXEL_PACK_BUFFER, 0, size as isize, GL_MAP_READ_BIT);
+        let pixels = if data.is_null() {
+            None
+        } else {
+            let pixels = slice::from_raw_parts(data as *const u8, size).to_vec();
+            (f.unmap_buffer)(GL_PIXEL_PACK_BUFFER);
+            Some(pixels)
+        };
+
+        (f.bind_buffer)(GL_PIXEL_PACK_BUFFER, previous as c_uint);
+        pixels.ok_or_else(|| ContextError::OsError(format!("glMapBufferRange failed")))
+    }
+
+    /// Deletes the buffers and the fences of the pending readbacks.
+    ///
+    /// If `context` isn't current, the readback is handed back along with the error so that it
+    /// can be destroyed later.
+    ///
+    /// # Unsafety
+    ///
+    /// `context` must be the context the readback was built with.
+    pub unsafe fn destroy<C: GlContext>(mut self, context: &C)
+                                        -> Result<(), (AsyncReadback, ContextError)>
+    {
+        if let Err(err) = check_current(context) {
+            return Err((self, err));
+        }
+        for pending in self.pending.drain(..) {
+            (self.functions.delete_sync)(pending.fence);
+        }
+        (self.functions.delete_buffers)(self.buffers.len() as c_int, self.buffers.as_ptr());
+        self.destroyed = true;
+        Ok(())
+    }
+}
+
+impl Drop for AsyncReadback {
+    fn drop(&mut self) {
+        // the buffers can't be deleted here since the context may not be current, and leaking
+        // them silently would hide the mistake
+        if !self.destroyed && !thread::panicking() {
+            panic!("an AsyncReadback was dropped without calling destroy, leaking its buffers");
+        }
+    }
+}
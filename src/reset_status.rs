@@ -0,0 +1,97 @@
+use std::os::raw::c_uint;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ContextError;
+use Robustness;
+
+/// Whether the GPU was reset since the last check, as reported by `glGetGraphicsResetStatus`.
+/// See `Context::reset_status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetStatus {
+    /// No reset happened.
+    NoError,
+    /// The context caused a reset.
+    GuiltyContextReset,
+    /// A reset happened, caused by another context.
+    InnocentContextReset,
+    /// A reset happened, but its cause is unknown.
+    UnknownContextReset,
+}
+
+impl ResetStatus {
+    /// Returns true if a reset happened, after which the context is lost and must be recreated.
+    #[inline]
+    pub fn is_reset(&self) -> bool {
+        *self != ResetStatus::NoError
+    }
+}
+
+// Values of `reset_status_fn` which aren't function pointers.
+const NOT_LOADED: usize = 0;
+const UNAVAILABLE: usize = 1;
+
+type GetGraphicsResetStatusFn = extern "system" fn() -> c_uint;
+
+/// Queries the reset status of a context. Used by `Context`.
+pub(crate) struct ResetStatusQuery {
+    // Whether the context was created with a robustness that reports resets, in which case
+    // `make_current` and `swap_buffers` check the status.
+    check_automatically: bool,
+    reset_status_fn: AtomicUsize,
+}
+
+impl ResetStatusQuery {
+    pub(crate) fn new(robustness: Robustness) -> ResetStatusQuery {
+        let check_automatically = match robustness {
+            Robustness::RobustLoseContextOnReset |
+            Robustness::TryRobustLoseContextOnReset => true,
+            _ => false,
+        };
+        ResetStatusQuery {
+            check_automatically: check_automatically,
+            reset_status_fn: AtomicUsize::new(NOT_LOADED),
+        }
+    }
+
+    /// Returns the reset status of the context, which must be current. `get_proc_address` must
+    /// load its functions.
+    pub(crate) unsafe fn query<F>(&self, get_proc_address: F) -> Result<ResetStatus, ContextError>
+        where F: Fn(&str) -> *const ()
+    {
+        let mut address = self.reset_status_fn.load(Ordering::SeqCst);
+        if address == NOT_LOADED {
+            address = ["glGetGraphicsResetStatus", "glGetGraphicsResetStatusKHR",
+                       "glGetGraphicsResetStatusARB", "glGetGraphicsResetStatusEXT"]
+                .iter()
+                .map(|name| get_proc_address(name) as usize)
+                .find(|&address| address != 0)
+                .unwrap_or(UNAVAILABLE);
+            self.reset_status_fn.store(address, Ordering::SeqCst);
+        }
+        if address == UNAVAILABLE {
+            return Err(ContextError::FunctionUnavailable);
+        }
+
+        let get_status: GetGraphicsResetStatusFn = ::std::mem::transmute(address);
+        Ok(match get_status() {
+            0x8253 => ResetStatus::GuiltyContextReset,
+            0x8254 => ResetStatus::InnocentContextReset,
+            0x8255 => ResetStatus::UnknownContextReset,
+            _ => ResetStatus::NoError,
+        })
+    }
+
+    /// Returns `ContextError::ContextLost` if the context reports resets and was reset. The
+    /// context must be current.
+    pub(crate) unsafe fn check<F>(&self, get_proc_address: F) -> Result<(), ContextError>
+        where F: Fn(&str) -> *const ()
+    {
+        if !self.check_automatically {
+            return Ok(());
+        }
+        match self.query(get_proc_address) {
+            Ok(status) if status.is_reset() => Err(ContextError::ContextLost),
+            _ => Ok(()),
+        }
+    }
+}
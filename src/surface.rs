@@ -0,0 +1,326 @@
+use Config;
+use Context;
+use ContextCurrentState;
+use ContextError;
+use CreationError;
+use RawPixmapHandle;
+use RawWindowHandle;
+use Rect;
+use MultisampleResolve;
+use RenderBuffer;
+use SwapBehavior;
+
+use platform;
+
+use std::marker::PhantomData;
+
+/// A type that surfaces use to keep track, at compile time, of what they draw to.
+pub trait SurfaceTypeTrait {}
+
+/// A surface that draws to a native window.
+#[derive(Debug, Clone, Copy)]
+pub enum WindowSurface {}
+
+impl SurfaceTypeTrait for WindowSurface {}
+
+/// An offscreen surface whose buffers are allocated by the driver.
+#[derive(Debug, Clone, Copy)]
+pub enum PbufferSurface {}
+
+impl SurfaceTypeTrait for PbufferSurface {}
+
+/// A surface that draws to a native pixmap.
+#[derive(Debug, Clone, Copy)]
+pub enum PixmapSurface {}
+
+impl SurfaceTypeTrait for PixmapSurface {}
+
+/// The format of the texture that a pbuffer can be bound to with `eglBindTexImage`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PbufferTextureFormat {
+    /// The texture has red, green and blue components.
+    Rgb,
+    /// The texture has red, green, blue and alpha components.
+    Rgba,
+}
+
+/// Describes the offscreen surface built by `Surface::<PbufferSurface>::new_with_attributes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PbufferAttributes {
+    /// The width and height of the surface.
+    pub dimensions: (u32, u32),
+
+    /// If true and the driver can't allocate a surface of `dimensions`, the largest surface
+    /// that can be allocated is created instead of failing. Its size can then be queried with
+    /// `Surface::dimensions`.
+    ///
+    /// The default value is `false`.
+    pub largest: bool,
+
+    /// The format of the texture the surface can be bound to, or `None` if it can't be bound to
+    /// a texture. Binding requires a config that supports `EGL_BIND_TO_TEXTURE_RGB` or
+    /// `EGL_BIND_TO_TEXTURE_RGBA`, and the target of the texture is always `GL_TEXTURE_2D`.
+    ///
+    /// Only supported by EGL. The default value is `None`.
+    pub texture_format: Option<PbufferTextureFormat>,
+
+    /// If true, memory is allocated for the mipmap levels of the texture the surface is bound
+    /// to. Requires a `texture_format`.
+    ///
+    /// Only supported by EGL. The default value is `false`.
+    pub mipmap: bool,
+}
+
+/// The largest offscreen surface that a config supports, as returned by `max_pbuffer_size`.
+///
+/// Both dimensions of a pbuffer must not exceed `max_width` and `max_height`, and its area must
+/// not exceed `max_pixels`, which is often smaller than `max_width * max_height`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PbufferLimits {
+    /// The largest width of a pbuffer, in pixels.
+    pub max_width: u32,
+    /// The largest height of a pbuffer, in pixels.
+    pub max_height: u32,
+    /// The largest number of pixels of a pbuffer.
+    pub max_pixels: u32,
+}
+
+impl PbufferAttributes {
+    /// Initializes the attributes of a surface of the given dimensions, with default values for
+    /// the others.
+    #[inline]
+    pub fn new(width: u32, height: u32) -> PbufferAttributes {
+        PbufferAttributes {
+            dimensions: (width, height),
+            largest: false,
+            texture_format: None,
+            mipmap: false,
+        }
+    }
+}
+
+/// A drawable that a `Context` can be made current on, independently of the window or buffer
+/// the context was originally created for.
+///
+/// Surfaces are created either from the config of a context or from a `Config` returned by
+/// `Display::find_configs`, and can be made current with any context that was created with a
+/// compatible config through `Context::make_current_surfaces`.
+///
+/// Surfaces are only supported on X11 and Wayland for now. On other platforms, creating one
+/// returns `CreationError::NotSupported`.
+pub struct Surface<T: SurfaceTypeTrait> {
+    pub(crate) surface: platform::Surface,
+    phantom: PhantomData<T>,
+}
+
+impl Surface<WindowSurface> {
+    /// Creates a surface that draws to the given native window.
+    ///
+    /// # Unsafety
+    ///
+    /// The window handle must be valid, and the window must outlive the surface.
+    pub unsafe fn new<C: ContextCurrentState>(
+        context: &Context<C>,
+        window_handle: RawWindowHandle,
+    ) -> Result<Self, CreationError>
+    {
+        context.context.create_window_surface(window_handle).map(Surface::from_platform)
+    }
+
+    /// Creates a surface that draws to the given native window, using a config returned by
+    /// `Display::find_configs`.
+    ///
+    /// # Unsafety
+    ///
+    /// The window handle must be valid, and the window must outlive the surface.
+    pub unsafe fn new_with_config(
+        config: &Config,
+        window_handle: RawWindowHandle,
+    ) -> Result<Self, CreationError>
+    {
+        config.config.create_window_surface(window_handle).map(Surface::from_platform)
+    }
+
+    /// Resizes the surface.
+    ///
+    /// Some platforms (macOS, Wayland) require being manually updated when their window or
+    /// container is resized. On other platforms this does nothing.
+    pub fn resize(&self, width: u32, height: u32) {
+        self.surface.resize(width, height);
+    }
+}
+
+impl Surface<PbufferSurface> {
+    /// Creates an offscreen surface with the given dimensions.
+    pub fn new<C: ContextCurrentState>(
+        context: &Context<C>,
+        dimensions: (u32, u32),
+    ) -> Result<Self, CreationError>
+    {
+        let attributes = PbufferAttributes::new(dimensions.0, dimensions.1);
+        Surface::new_with_attributes(context, &attributes)
+    }
+
+    /// Creates an offscreen surface with the given dimensions, using a config returned by
+    /// `Display::find_configs`.
+    pub fn new_with_config(
+        config: &Config,
+        dimensions: (u32, u32),
+    ) -> Result<Self, CreationError>
+    {
+        let attributes = PbufferAttributes::new(dimensions.0, dimensions.1);
+        Surface::new_with_config_and_attributes(config, &attributes)
+    }
+
+    /// Creates an offscreen surface described by `attributes`.
+    ///
+    /// The surface can be made current with `context` through `Context::make_current_surfaces`,
+    /// including when `context` draws to a window. Returns `CreationError::NotSupported` if an
+    /// attribute that is only supported by EGL is requested with GLX.
+    pub fn new_with_attributes<C: ContextCurrentState>(
+        context: &Context<C>,
+        attributes: &PbufferAttributes,
+    ) -> Result<Self, CreationError>
+    {
+        context.context.create_pbuffer_surface(attributes).map(Surface::from_platform)
+    }
+
+    /// Creates an offscreen surface described by `attributes`, using a config returned by
+    /// `Display::find_configs`.
+    pub fn new_with_config_and_attributes(
+        config: &Config,
+        attributes: &PbufferAttributes,
+    ) -> Result<Self, CreationError>
+    {
+        config.config.create_pbuffer_surface(attributes).map(Surface::from_platform)
+    }
+}
+
+impl Surface<PixmapSurface> {
+    /// Creates a surface that draws to the given native pixmap.
+    ///
+    /// # Unsafety
+    ///
+    /// The pixmap handle must be valid, and the pixmap must outlive the surface.
+    pub unsafe fn new<C: ContextCurrentState>(
+        context: &Context<C>,
+        pixmap_handle: RawPixmapHandle,
+    ) -> Result<Self, CreationError>
+    {
+        context.context.create_pixmap_surface(pixmap_handle).map(Surface::from_platform)
+    }
+
+    /// Creates a surface that draws to the given native pixmap, using a config returned by
+    /// `Display::find_configs`.
+    ///
+    /// # Unsafety
+    ///
+    /// The pixmap handle must be valid, and the pixmap must outlive the surface.
+    pub unsafe fn new_with_config(
+        config: &Config,
+        pixmap_handle: RawPixmapHandle,
+    ) -> Result<Self, CreationError>
+    {
+        config.config.create_pixmap_surface(pixmap_handle).map(Surface::from_platform)
+    }
+}
+
+impl<T: SurfaceTypeTrait> Surface<T> {
+    #[inline]
+    fn from_platform(surface: platform::Surface) -> Self {
+        Surface { surface: surface, phantom: PhantomData }
+    }
+
+    /// Returns the width and height of the surface, which may be smaller than the requested
+    /// dimensions for a pbuffer built with `largest`.
+    pub fn dimensions(&self) -> Result<(u32, u32), ContextError> {
+        self.surface.dimensions()
+    }
+
+    /// Swaps the buffers of the surface, if it is double buffered.
+    ///
+    /// The context drawing to this surface must be current on the calling thread.
+    pub fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.surface.swap_buffers()
+    }
+
+    /// Returns the number of frames ago the content of the current back buffer was drawn, so
+    /// that only the regions that changed since then need to be redrawn. A value of 0 means
+    /// that the content of the back buffer is undefined and it must be redrawn entirely.
+    ///
+    /// The context drawing to this surface must be current on the calling thread. Returns
+    /// `ContextError::FunctionUnavailable` if `EGL_EXT_buffer_age` or `GLX_EXT_buffer_age` is
+    /// not supported.
+    pub fn buffer_age(&self) -> Result<u32, ContextError> {
+        self.surface.buffer_age()
+    }
+
+    /// Tells the implementation that only the regions in `rects` of the back buffer will be
+    /// modified before the next call to `swap_buffers`, so that tiled GPUs can skip the other
+    /// tiles. The content of the back buffer outside of these regions becomes undefined.
+    ///
+    /// Must be called after `buffer_age` and before rendering to the surface, and the context
+    /// drawing to this surface must be current on the calling thread. Returns
+    /// `ContextError::FunctionUnavailable` if `EGL_KHR_partial_update` is not supported, which
+    /// is always the case with GLX.
+    pub fn set_damage_region(&self, rects: &[Rect]) -> Result<(), ContextError> {
+        self.surface.set_damage_region(rects)
+    }
+
+    /// Returns what happens to the content of the back buffer when the buffers are swapped.
+    ///
+    /// GLX surfaces always return `SwapBehavior::Destroyed`.
+    pub fn swap_behavior(&self) -> Result<SwapBehavior, ContextError> {
+        self.surface.swap_behavior()
+    }
+
+    /// Sets whether the content of the back buffer is preserved when the buffers are swapped.
+    ///
+    /// `SwapBehavior::Preserved` requires a config with `EGL_SWAP_BEHAVIOR_PRESERVED_BIT`, which
+    /// can be found by passing a template with `swap_behavior` set to `Preserved` to
+    /// `Display::find_configs`. Returns `ContextError::FunctionUnavailable` with GLX.
+    pub fn set_swap_behavior(&self, behavior: SwapBehavior) -> Result<(), ContextError> {
+        self.surface.set_swap_behavior(behavior)
+    }
+
+    /// Returns the buffer rendering to this surface should go to, as last set with
+    /// `set_render_buffer`.
+    pub fn render_buffer(&self) -> Result<RenderBuffer, ContextError> {
+        self.surface.render_buffer()
+    }
+
+    /// Switches between rendering to the back buffer and directly to the front buffer. The change
+    /// takes effect after the next swap.
+    ///
+    /// Requires a config with `EGL_MUTABLE_RENDER_BUFFER_BIT_KHR`, which can be found by passing
+    /// a template with `mutable_render_buffer` set to `Display::find_configs`. Returns
+    /// `ContextError::FunctionUnavailable` with GLX unless the buffer doesn't change.
+    pub fn set_render_buffer(&self, render_buffer: RenderBuffer) -> Result<(), ContextError> {
+        self.surface.set_render_buffer(render_buffer)
+    }
+
+    /// Returns how the samples of this surface are resolved when the buffers are swapped.
+    ///
+    /// GLX surfaces always return `MultisampleResolve::Default`.
+    pub fn multisample_resolve(&self) -> Result<MultisampleResolve, ContextError> {
+        self.surface.multisample_resolve()
+    }
+
+    /// Sets how the samples of this surface are resolved when the buffers are swapped.
+    ///
+    /// `MultisampleResolve::Box` requires a config with `EGL_MULTISAMPLE_RESOLVE_BOX_BIT`, which
+    /// can be found by passing a template with `multisample_resolve_box` set to
+    /// `Display::find_configs`. Returns `ContextError::FunctionUnavailable` with GLX unless the
+    /// resolve is `MultisampleResolve::Default`.
+    pub fn set_multisample_resolve(&self, resolve: MultisampleResolve)
+                                   -> Result<(), ContextError>
+    {
+        self.surface.set_multisample_resolve(resolve)
+    }
+}
+
+impl<T: SurfaceTypeTrait> std::fmt::Debug for Surface<T> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        formatter.debug_struct("Surface").finish()
+    }
+}
@@ -0,0 +1,300 @@
+use Api;
+use ContextBuilder;
+use ContextCurrentState;
+use ContextError;
+use ContextTrait;
+use CreationError;
+use Context;
+use EventsLoop;
+use GlContext;
+use NotCurrent;
+use PixelFormat;
+use PossiblyCurrent;
+use RawContext;
+use Window;
+use WindowBuilder;
+
+use capture::{self, CapturedFrame};
+use create_with_fallbacks;
+use create_with_versions;
+
+use platform;
+
+use std::cmp;
+
+/// Represents an OpenGL context and a Window with which it is associated.
+///
+/// # Example
+///
+/// ```no_run
+/// # extern crate glutin;
+/// # use glutin::GlContext;
+/// # fn main() {
+/// let mut events_loop = glutin::EventsLoop::new();
+/// let window = glutin::WindowBuilder::new();
+/// let context = glutin::ContextBuilder::new();
+/// let gl_window = glutin::WindowedContext::new(window, context, &events_loop).unwrap();
+///
+/// let gl_window = unsafe { gl_window.make_current().unwrap() };
+///
+/// loop {
+///     events_loop.poll_events(|event| {
+///         match event {
+///             // process events here
+///             _ => ()
+///         }
+///     });
+///
+///     // draw everything here
+///
+///     gl_window.swap_buffers();
+///     std::thread::sleep(std::time::Duration::from_millis(17));
+/// }
+/// # }
+/// ```
+pub struct WindowedContext<T: ContextCurrentState> {
+    // The context must be destroyed before the window.
+    context: Context<T>,
+    window: Window,
+}
+
+/// Former name of `WindowedContext`.
+pub type GlWindow<T> = WindowedContext<T>;
+
+impl WindowedContext<NotCurrent> {
+    /// Builds the given window along with the associated GL context, returning the pair as a
+    /// `WindowedContext`.
+    ///
+    /// Error should be very rare and only occur in case of permission denied, incompatible system,
+    /// out of memory, etc.
+    pub fn new<T: ContextCurrentState>(
+        window_builder: WindowBuilder,
+        context_builder: ContextBuilder<T>,
+        events_loop: &EventsLoop,
+    ) -> Result<Self, CreationError>
+    {
+        let ContextBuilder { mut pf_reqs, gl_attr } = context_builder;
+        let window_builder = if pf_reqs.transparent {
+            pf_reqs.alpha_bits = Some(cmp::max(pf_reqs.alpha_bits.unwrap_or(0), 8));
+            window_builder.with_transparency(true)
+        } else {
+            window_builder
+        };
+        let share_group = gl_attr.share_group();
+        let gl_attr = gl_attr.map_sharing(|ctxt| &ctxt.context);
+        create_with_versions(&gl_attr, |gl_attr| {
+            create_with_fallbacks(&pf_reqs, |pf_reqs| {
+                platform::Context::new(window_builder.clone(), events_loop, pf_reqs, gl_attr)
+            }).map(|((window, context), relaxed)| WindowedContext {
+                window: window,
+                context: Context::from_platform(context, relaxed, share_group.clone(), gl_attr),
+            })
+        })
+    }
+}
+
+impl<T: ContextCurrentState> WindowedContext<T> {
+    /// Borrow the inner `Window`.
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    /// Borrow the inner GL `Context`.
+    pub fn context(&self) -> &Context<T> {
+        &self.context
+    }
+
+    /// Sets the context as the current context on the calling thread.
+    ///
+    /// See `Context::make_current` for more infos.
+    pub unsafe fn make_current(self)
+        -> Result<WindowedContext<PossiblyCurrent>, (Self, ContextError)>
+    {
+        let window = self.window;
+        match self.context.make_current() {
+            Ok(context) => Ok(WindowedContext { context: context, window: window }),
+            Err((context, err)) => Err((WindowedContext { context: context, window: window }, err)),
+        }
+    }
+
+    /// If this context is current on the calling thread, releases it.
+    ///
+    /// See `Context::make_not_current` for more infos.
+    pub unsafe fn make_not_current(self)
+        -> Result<WindowedContext<NotCurrent>, (Self, ContextError)>
+    {
+        let window = self.window;
+        match self.context.make_not_current() {
+            Ok(context) => Ok(WindowedContext { context: context, window: window }),
+            Err((context, err)) => Err((WindowedContext { context: context, window: window }, err)),
+        }
+    }
+
+    /// Changes the state of the context to `PossiblyCurrent` without making it current.
+    ///
+    /// See `Context::treat_as_current` for more infos.
+    pub unsafe fn treat_as_current(self) -> WindowedContext<PossiblyCurrent> {
+        WindowedContext { context: self.context.treat_as_current(), window: self.window }
+    }
+
+    /// Changes the state of the context to `NotCurrent` without releasing it.
+    ///
+    /// See `Context::treat_as_not_current` for more infos.
+    pub unsafe fn treat_as_not_current(self) -> WindowedContext<NotCurrent> {
+        WindowedContext { context: self.context.treat_as_not_current(), window: self.window }
+    }
+
+    /// Returns true if this context is the current one in this thread.
+    pub fn is_current(&self) -> bool {
+        self.context.is_current()
+    }
+
+    /// Returns the OpenGL API being used.
+    pub fn get_api(&self) -> Api {
+        self.context.get_api()
+    }
+
+    /// Returns the pixel format of the main framebuffer of the context.
+    pub fn get_pixel_format(&self) -> PixelFormat {
+        self.context.get_pixel_format()
+    }
+
+    /// Returns true if this context and `other` share objects.
+    ///
+    /// See `Context::is_shared_with` for more infos.
+    pub fn is_shared_with<T2: ContextCurrentState>(&self, other: &Context<T2>) -> bool {
+        self.context.is_shared_with(other)
+    }
+
+    /// Splits the `WindowedContext` into its context and its window, so that their lifetimes can
+    /// be managed separately.
+    ///
+    /// # Unsafety
+    ///
+    /// The `Window` must be dropped after the `RawContext`.
+    pub unsafe fn split(self) -> (RawContext<T>, Window) {
+        (RawContext { context: self.context }, self.window)
+    }
+
+    /// Destroys the context and builds a new one on the same window, with the attributes and
+    /// the pixel format it was built with, for example to recover after
+    /// `ContextError::ContextLost`. The frame rate limit, the metrics and the context-lost
+    /// callback are kept.
+    ///
+    /// The new context doesn't share lists with any other context, since the contexts sharing
+    /// them were usually lost too; see `recreate_with_shared_lists` otherwise. This is supported
+    /// on X11, Wayland and with WGL on Windows, elsewhere `CreationError::NotSupported` is
+    /// returned. On failure, the window is handed back along with the error.
+    ///
+    /// # Unsafety
+    ///
+    /// The context must not be current on another thread.
+    pub unsafe fn recreate(self) -> Result<WindowedContext<NotCurrent>, (Window, CreationError)> {
+        let window = self.window;
+        match self.context.recreate(None) {
+            Ok(context) => Ok(WindowedContext { context: context, window: window }),
+            Err(err) => Err((window, err)),
+        }
+    }
+
+    /// Same as `recreate`, but the new context shares lists with `shared` and joins its share
+    /// group, for when some contexts of the group survived the loss, such as a context of
+    /// another GPU or one that was recreated first.
+    ///
+    /// `shared` must use the same backend as this context, otherwise
+    /// `CreationError::NotSupported` is returned.
+    ///
+    /// # Unsafety
+    ///
+    /// The context must not be current on another thread.
+    pub unsafe fn recreate_with_shared_lists<U: ContextCurrentState>(self, shared: &Context<U>)
+        -> Result<WindowedContext<NotCurrent>, (Window, CreationError)>
+    {
+        let window = self.window;
+        match self.context.recreate(Some((&shared.context, &shared.share_group))) {
+            Ok(context) => Ok(WindowedContext { context: context, window: window }),
+            Err(err) => Err((window, err)),
+        }
+    }
+}
+
+impl WindowedContext<PossiblyCurrent> {
+    /// Reads the last frame presented in the window, for bug reports or automated visual tests.
+    ///
+    /// With desktop OpenGL, the front buffer is read, so this should be called after
+    /// `swap_buffers`. OpenGL ES can only read the back buffer, so this should be called before
+    /// `swap_buffers` instead. Multisampled framebuffers are resolved into a temporary
+    /// framebuffer object first, and sRGB values are returned as they are stored. The rows are
+    /// returned from the top of the window to the bottom.
+    pub fn capture_frame(&self) -> Result<CapturedFrame, ContextError> {
+        let dimensions = self.window.get_inner_size()
+            .ok_or_else(|| ContextError::OsError(format!("the window was closed")))?;
+        capture::capture_frame(self, dimensions)
+    }
+}
+
+impl GlContext for WindowedContext<PossiblyCurrent> {
+    fn is_current(&self) -> bool {
+        self.context.is_current()
+    }
+
+    fn get_proc_address(&self, addr: &str) -> *const () {
+        self.context.get_proc_address(addr)
+    }
+
+    fn swap_buffers(&self) -> Result<(), ContextError> {
+        self.context.swap_buffers()
+    }
+
+    fn get_api(&self) -> Api {
+        self.context.get_api()
+    }
+
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.context.get_pixel_format()
+    }
+
+    fn resize(&self, width: u32, height: u32) {
+        self.context.resize(width, height);
+    }
+}
+
+impl<T: ContextCurrentState> ContextTrait for WindowedContext<T> {
+    type PossiblyCurrent = WindowedContext<PossiblyCurrent>;
+    type NotCurrent = WindowedContext<NotCurrent>;
+
+    unsafe fn make_current(self) -> Result<Self::PossiblyCurrent, (Self, ContextError)> {
+        WindowedContext::make_current(self)
+    }
+
+    unsafe fn make_not_current(self) -> Result<Self::NotCurrent, (Self, ContextError)> {
+        WindowedContext::make_not_current(self)
+    }
+
+    fn is_current(&self) -> bool {
+        WindowedContext::is_current(self)
+    }
+
+    fn get_api(&self) -> Api {
+        WindowedContext::get_api(self)
+    }
+
+    fn get_pixel_format(&self) -> PixelFormat {
+        WindowedContext::get_pixel_format(self)
+    }
+}
+
+impl<T: ContextCurrentState> ::std::fmt::Debug for WindowedContext<T> {
+    fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        formatter.debug_struct("WindowedContext")
+            .field("context", &self.context)
+            .finish()
+    }
+}
+
+impl<T: ContextCurrentState> ::std::ops::Deref for WindowedContext<T> {
+    type Target = Window;
+    fn deref(&self) -> &Self::Target {
+        &self.window
+    }
+}